@@ -0,0 +1,180 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::time::Duration;
+
+use configs::CFG;
+use futures::stream::{self, StreamExt};
+use jsonwebtoken::{DecodingKey, Header, Validation, decode, decode_header};
+use log::Level;
+use tracing::{info, warn};
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::QueryParam};
+use salvo::prelude::*;
+use salvo::sse::{self, SseEvent};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::time::Instant;
+
+use crate::controller::Claims;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::feature_flags::{FEATURE_OVERRIDES_KEY, FeatureFlags};
+use crate::utils::log_buffer::LOG_BUFFER;
+use crate::utils::res::{Res, ResObj, res_json_ok};
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "token": "eyJ..." })))]
+pub struct DecodeTokenRequest {
+    pub token: String,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct DecodeTokenResponse {
+    /// 始终为 false：仅做解码，未校验签名与过期时间
+    pub verified: bool,
+    pub header: serde_json::Value,
+    pub claims: serde_json::Value,
+}
+
+/// 从 depot 中取出 claims 并校验是否为平台管理员
+pub(crate) fn require_admin(depot: &Depot) -> Result<&Claims, Json<ResObj<()>>> {
+    match depot.get::<Claims>("claims") {
+        Ok(claims) if claims.is_admin() => Ok(claims),
+        Ok(claims) => {
+            warn!("User {} with role {} tried to access admin endpoint", claims.sub, claims.role);
+            Err(ApiError::new(ErrorCode::AdminRoleRequired).to_json(depot))
+        }
+        Err(_) => Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot)),
+    }
+}
+
+/// 查看当前请求生效的功能开关 (含 `X-Feature-Flags` 覆盖)，用于验证灰度配置 (仅管理员)
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403),
+    responses(
+        (status_code = 200, description = "开关名称 -> 是否启用", body = BTreeMap<String, bool>),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+    )
+)]
+pub async fn list_features(depot: &mut Depot) -> Res<BTreeMap<String, bool>> {
+    require_admin(depot)?;
+    let mut names: Vec<&String> = CFG.features.flags.keys().collect();
+    let overrides = depot.get::<HashMap<String, bool>>(FEATURE_OVERRIDES_KEY).ok().cloned().unwrap_or_default();
+    names.extend(overrides.keys());
+    let flags = names.into_iter().map(|name| (name.clone(), depot.feature(name))).collect();
+    Ok(res_json_ok(Some(flags)))
+}
+
+/// 解码 JWT (不校验签名)，用于排查用户的角色/过期问题 (仅管理员)
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403),
+    request_body = DecodeTokenRequest,
+    responses(
+        (status_code = 200, description = "解码成功，结果未经校验", body = DecodeTokenResponse),
+        (status_code = 400, description = "Token 格式无效"),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+    )
+)]
+pub async fn decode_token(req: JsonBody<DecodeTokenRequest>, depot: &mut Depot) -> Res<DecodeTokenResponse> {
+    let admin = require_admin(depot)?;
+    let token = req.token.trim().trim_start_matches("Bearer ").trim();
+
+    let header: Header = match decode_header(token) {
+        Ok(h) => h,
+        Err(e) => {
+            warn!("Failed to decode JWT header: {}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("InvalidTokenFormat").to_json(depot));
+        }
+    };
+
+    // 关闭签名、过期等所有校验，只做 base64 + JSON 解码
+    let mut validation = Validation::new(header.alg);
+    validation.insecure_disable_signature_validation();
+    validation.validate_exp = false;
+    validation.validate_nbf = false;
+    validation.validate_aud = false;
+    validation.required_spec_claims.clear();
+
+    let claims = match decode::<serde_json::Value>(token, &DecodingKey::from_secret(&[]), &validation) {
+        Ok(data) => data.claims,
+        Err(e) => {
+            warn!("Failed to decode JWT claims: {}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("InvalidTokenFormat").to_json(depot));
+        }
+    };
+
+    info!("Admin {} decoded a token (unverified)", admin.sub);
+
+    Ok(res_json_ok(Some(DecodeTokenResponse {
+        verified: false,
+        header: serde_json::to_value(&header).unwrap_or(serde_json::Value::Null),
+        claims,
+    })))
+}
+
+/// 以 SSE 方式实时推送服务日志 (仅管理员)
+///
+/// 先回放环形缓冲区中已有的日志，再推送新产生的日志；
+/// 连接最长持续 `admin.log_stream_max_secs` 秒后由服务端关闭。
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403),
+    parameters(
+        ("level" = Option<String>, Query, description = "最低日志级别 (error/warn/info/debug/trace)，默认 info")
+    ),
+    responses(
+        (status_code = 200, description = "text/event-stream 日志流"),
+        (status_code = 400, description = "无效的日志级别"),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+    )
+)]
+pub async fn stream_logs(level: QueryParam<String, false>, depot: &mut Depot, res: &mut Response) {
+    let admin = match require_admin(depot) {
+        Ok(claims) => claims.sub.clone(),
+        Err(err) => return res.render(err),
+    };
+
+    let min_level = match level.into_inner() {
+        Some(l) => match l.parse::<Level>() {
+            Ok(l) => l,
+            Err(_) => return res.render(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid log level").to_json::<()>(depot)),
+        },
+        None => Level::Info,
+    };
+
+    let max_duration = Duration::from_secs(CFG.admin.log_stream_max_secs);
+    let deadline = Instant::now() + max_duration;
+    info!("Admin {} opened log stream at level {} for up to {:?}", admin, min_level, max_duration);
+
+    // 先订阅再取快照，避免两者之间产生的日志丢失
+    let receiver = LOG_BUFFER.subscribe();
+    let backlog = LOG_BUFFER.snapshot(min_level);
+
+    let to_event = |entry: &crate::utils::log_buffer::LogEntry| {
+        SseEvent::default()
+            .name("log")
+            .text(serde_json::to_string(entry).unwrap_or_default())
+    };
+
+    let replay = stream::iter(backlog.iter().map(to_event).map(Ok::<_, Infallible>).collect::<Vec<_>>());
+    let live = stream::unfold(receiver, move |mut rx| async move {
+        loop {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Ok(entry)) if entry.level() <= min_level => return Some((Ok::<_, Infallible>(to_event(&entry)), rx)),
+                Ok(Ok(_)) => continue,
+                // 订阅者消费太慢，丢弃积压的日志继续推送
+                Ok(Err(RecvError::Lagged(_))) => continue,
+                Ok(Err(RecvError::Closed)) | Err(_) => return None,
+            }
+        }
+    });
+
+    sse::stream(res, replay.chain(live));
+}
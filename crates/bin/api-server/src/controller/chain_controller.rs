@@ -0,0 +1,197 @@
+use std::sync::Arc;
+
+use common::pagination::{OffsetPagination, Page};
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::LocalWallet;
+use tracing::error;
+use mongodb::Database;
+use pharos_interact::{ContractQuerier, InvoiceContract, TransferSyncHandle};
+use salvo::oapi::ToSchema;
+use salvo::oapi::extract::QueryParam;
+use salvo::prelude::*;
+use serde::Serialize;
+use service::repository::{ContractOperationRepository, TokenTransferRepository};
+use service::service::transfer_store::TRANSFER_INDEXER_ID;
+
+use crate::controller::admin_controller;
+use crate::utils::pagination;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::res::{Res, res_json_ok};
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct ContractStatusResponse {
+    /// 合约地址
+    pub contract_address: String,
+    /// 合约是否处于暂停状态，暂停时创建/兑付/铸造等写操作会直接失败
+    pub paused: bool,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct TransferIndexerStatusResponse {
+    /// 本实例是否运行转账索引任务
+    pub running: bool,
+    /// 最后处理完成的区块 (持久化进度)
+    pub last_processed_block: Option<u64>,
+    /// 最近一轮看到的链头区块，未运行时为空
+    pub chain_head: Option<u64>,
+    /// 落后链头的区块数
+    pub lag: Option<u64>,
+    /// 跟踪的代币合约数量
+    pub tracked_tokens: usize,
+    /// 最近一次成功同步时间 (毫秒时间戳)
+    pub last_synced_at: Option<i64>,
+    /// 最近一轮失败的原因
+    pub last_error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+pub struct OnchainFailureResponse {
+    pub id: String,
+    /// 操作类型，如 batch_create_invoices / create_token_batch / confirm_token_batch_issue / purchase_shares
+    pub operation: String,
+    /// 业务标识 (批次 ID / 票据号)
+    pub reference: String,
+    /// 交易哈希 (发送前失败时为空)
+    pub tx_hash: Option<String>,
+    /// 解析出的 revert 原因
+    pub revert_reason: Option<String>,
+    /// 原始错误信息
+    pub error: Option<String>,
+    /// 重试次数 (累计提交次数 - 1)
+    pub retry_count: u32,
+    /// 首次提交时间 (毫秒时间戳)
+    pub first_attempt_at: i64,
+    /// 最近一次提交时间 (毫秒时间戳)
+    pub last_attempt_at: i64,
+}
+
+/// 查询票据合约状态 (是否暂停)
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    status_codes(200, 502, 503),
+    responses(
+        (status_code = 200, description = "合约状态", body = ContractStatusResponse),
+        (status_code = 502, description = "查询合约失败"),
+        (status_code = 503, description = "区块链连接不可用"),
+    )
+)]
+pub async fn get_contract_status(depot: &mut Depot) -> Res<ContractStatusResponse> {
+    let contract = match depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+
+    match contract.is_paused().await {
+        Ok(paused) => Ok(res_json_ok(Some(ContractStatusResponse {
+            contract_address: format!("{:?}", contract.address()),
+            paused,
+        }))),
+        Err(e) => {
+            error!("Failed to query contract paused state: {}", e);
+            Err(ApiError::new(ErrorCode::ContractQueryFailed).to_json(depot))
+        }
+    }
+}
+
+/// 查询最近失败的合约写操作 (管理员)
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 500),
+    parameters(
+        ("operation" = Option<String>, Query, description = "按操作类型过滤"),
+        ("page" = Option<u64>, Query, description = "页码，从 1 开始"),
+        ("page_size" = Option<i64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status_code = 200, description = "失败的合约操作，按最近提交时间倒序", body = Page<OnchainFailureResponse>),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn list_onchain_failures(
+    operation: QueryParam<String, false>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    depot: &mut Depot,
+) -> Res<Page<OnchainFailureResponse>> {
+    admin_controller::require_admin(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("admin.onchain_failures", page_size.into_inner()) as u64);
+    let operation = operation.into_inner().filter(|o| !o.is_empty());
+
+    let repo = ContractOperationRepository::new(&mongodb);
+    match repo.find_failures(operation.as_deref(), pagination.skip(), pagination.page_size as i64).await {
+        Ok((records, total)) => {
+            let rows = records
+                .into_iter()
+                .map(|op| OnchainFailureResponse {
+                    id: op.id.map(|id| id.to_hex()).unwrap_or_default(),
+                    operation: op.operation,
+                    reference: op.reference,
+                    tx_hash: op.tx_hash,
+                    revert_reason: op.revert_reason,
+                    error: op.error,
+                    retry_count: op.attempts.saturating_sub(1),
+                    first_attempt_at: op.created_at.timestamp_millis(),
+                    last_attempt_at: op.updated_at.timestamp_millis(),
+                })
+                .collect();
+            Ok(res_json_ok(Some(Page::offset(rows, total, pagination.skip()))))
+        }
+        Err(e) => {
+            error!("Failed to list on-chain failures: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 查询 ERC20 转账索引的同步状态 (管理员)
+#[salvo::oapi::endpoint(
+    tags("链上"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 500),
+    responses(
+        (status_code = 200, description = "转账索引同步状态", body = TransferIndexerStatusResponse),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_transfer_indexer_status(depot: &mut Depot) -> Res<TransferIndexerStatusResponse> {
+    admin_controller::require_admin(depot)?;
+
+    if let Ok(handle) = depot.obtain::<TransferSyncHandle>() {
+        let status = handle.snapshot();
+        return Ok(res_json_ok(Some(TransferIndexerStatusResponse {
+            running: true,
+            last_processed_block: status.last_processed_block,
+            chain_head: status.chain_head,
+            lag: status.lag(),
+            tracked_tokens: status.tracked_tokens,
+            last_synced_at: status.last_synced_at.map(|t| t.timestamp_millis()),
+            last_error: status.last_error,
+        })));
+    }
+
+    // 本实例未运行索引任务时只返回持久化的进度
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    match TokenTransferRepository::new(&mongodb).last_processed_block(TRANSFER_INDEXER_ID).await {
+        Ok(last_processed_block) => Ok(res_json_ok(Some(TransferIndexerStatusResponse {
+            running: false,
+            last_processed_block,
+            chain_head: None,
+            lag: None,
+            tracked_tokens: 0,
+            last_synced_at: None,
+            last_error: None,
+        }))),
+        Err(e) => {
+            error!("Failed to read transfer indexer progress: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
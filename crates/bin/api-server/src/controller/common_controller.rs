@@ -1,8 +1,13 @@
 use salvo::{Depot, FlowCtrl, Request, Response, handler, prelude::StatusCode};
-use crate::utils::res::res_json_custom;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation, Algorithm};
-use configs::CFG; // Assuming your JWT secret is in CFG
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::i18n::Locale;
+use crate::utils::request_id::REQUEST_ID_KEY;
+use crate::utils::jwt_keys::{JWT_KEYS, JwtKeySet};
 use crate::controller::Claims; // Import the Claims struct
+use crate::utils::token_denylist::{AuthTokenDenylist, TokenDenylist, issued_before_user_cutoff};
+use crate::utils::health::{HealthChecks, ReadinessReport};
+use salvo::prelude::Json;
+use std::sync::Arc;
 
 #[handler]
 pub async fn auth_token(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl, depot: &mut Depot) {
@@ -12,64 +17,153 @@ pub async fn auth_token(req: &mut Request, res: &mut Response, ctrl: &mut FlowCt
         if auth_str.starts_with("Bearer ") {
             let token = &auth_str[7..]; // Remove "Bearer " prefix
 
-            // Retrieve the secret key from configuration
-            // Ensure CFG.jwt.secret is properly configured and accessible
-            let secret = &CFG.jwt.secret;
-            let decoding_key = DecodingKey::from_secret(secret.as_ref());
-            // Use HS256 Algorithm
-            let mut validation = Validation::new(Algorithm::HS256);
-            // Optionally add leeway for clock skew
-            // validation.leeway = 60; 
-            // validation.validate_exp = true; // Default is true
-
-            match decode::<Claims>(&token, &decoding_key, &validation) {
-                Ok(token_data) => {
-                    // Token is valid, extract the user_address (subject)
-                    let user_address = token_data.claims.sub;
-                    // Inject the user_address into the depot
-                    depot.insert("user_address", user_address);
+            let denylist = depot.obtain::<Arc<AuthTokenDenylist>>().expect("AuthTokenDenylist not found in depot").clone();
+            match verify_token(token, &JWT_KEYS, denylist.as_ref()).await {
+                Ok(claims) => {
+                    // Inject the user_address, user_id and full claims into the depot
+                    depot.insert("user_address", claims.sub.clone());
+                    depot.insert("user_id", claims.user_id.clone());
+                    depot.insert("claims", claims);
                     // Continue to the next handler
                     // ctrl.call_next(req, depot, res).await; // call_next is implicitly called if not skipped
                 }
-                Err(e) => {
-                    log::error!("JWT validation failed: {}", e);
+                Err(code) => {
                     ctrl.skip_rest();
-                    res.render(res_json_custom::<()>(401, "Invalid or expired token"));
+                    res.render(ApiError::new(code).to_json::<()>(depot));
                 }
             }
         } else {
             // Header format is incorrect (not Bearer)
             ctrl.skip_rest();
-            res.render(res_json_custom::<()>(401, "Invalid Authorization header format"));
+            res.render(ApiError::new(ErrorCode::InvalidAuthHeader).to_json::<()>(depot));
         }
     } else {
         // Authorization header is missing
         ctrl.skip_rest();
-        res.render(res_json_custom::<()>(401, "Authorization header missing"));
+        res.render(ApiError::new(ErrorCode::AuthHeaderMissing).to_json::<()>(depot));
     }
 }
 
+/// 公共路由使用的可选认证：携带有效令牌时与 `auth_token` 一样注入用户信息，
+/// 未携带或令牌无效时按匿名请求继续处理，不返回错误
 #[handler]
-pub async fn catcher_err(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl) {
+pub async fn optional_auth(req: &mut Request, depot: &mut Depot) {
+    let Some(token) = req.headers().get("Authorization").and_then(|v| v.to_str().ok()).and_then(|v| v.strip_prefix("Bearer ")) else {
+        return;
+    };
+    let denylist = depot.obtain::<Arc<AuthTokenDenylist>>().expect("AuthTokenDenylist not found in depot").clone();
+    if let Ok(claims) = verify_token(token, &JWT_KEYS, denylist.as_ref()).await {
+        depot.insert("user_address", claims.sub.clone());
+        depot.insert("user_id", claims.user_id.clone());
+        depot.insert("claims", claims);
+    }
+}
+
+/// 按 kid 选择密钥校验签名与过期时间，并拒绝已注销 (logout) 的令牌
+pub async fn verify_token<D: TokenDenylist + ?Sized>(token: &str, keys: &JwtKeySet, denylist: &D) -> Result<Claims, ErrorCode> {
+    let claims = match keys.decode::<Claims>(token, |_| {}) {
+        Ok(token_data) => token_data.claims,
+        Err(e) => {
+            tracing::error!("JWT validation failed: {}", e);
+            return Err(ErrorCode::InvalidToken);
+        }
+    };
+    if !claims.jti.is_empty() {
+        match denylist.is_revoked(&claims.jti).await {
+            Ok(false) => {}
+            Ok(true) => {
+                tracing::warn!("Rejected revoked token {} of {}", claims.jti, claims.sub);
+                return Err(ErrorCode::TokenRevoked);
+            }
+            Err(e) => {
+                tracing::error!("Failed to check token denylist for {}: {}", claims.jti, e);
+                return Err(ErrorCode::TokenDenylistUnavailable);
+            }
+        }
+    }
+    // 角色变更后强制重新登录
+    match issued_before_user_cutoff(denylist, &claims.user_id, claims.issued_at()).await {
+        Ok(false) => {}
+        Ok(true) => {
+            tracing::warn!("Rejected token {} of {} issued before forced re-login", claims.jti, claims.sub);
+            return Err(ErrorCode::TokenRevoked);
+        }
+        Err(e) => {
+            tracing::error!("Failed to check token denylist for user {}: {}", claims.user_id, e);
+            return Err(ErrorCode::TokenDenylistUnavailable);
+        }
+    }
+    Ok(claims)
+}
+
+/// 存活探针：进程能处理请求即返回 200
+#[salvo::oapi::endpoint(tags("健康检查"), status_codes(200))]
+pub async fn healthz() -> &'static str {
+    "ok"
+}
+
+/// 就绪探针：MongoDB、Redis、区块链 RPC 均可用时返回 200，否则返回 503
+#[salvo::oapi::endpoint(
+    tags("健康检查"),
+    status_codes(200, 503),
+    responses(
+        (status_code = 200, description = "所有依赖可用", body = ReadinessReport),
+        (status_code = 503, description = "存在不可用的依赖", body = ReadinessReport),
+    )
+)]
+pub async fn readyz(depot: &mut Depot, res: &mut Response) {
+    let report = match depot.obtain::<Arc<HealthChecks>>() {
+        Ok(checks) => checks.clone().run().await,
+        Err(_) => {
+            tracing::error!("HealthChecks not injected, reporting not ready");
+            ReadinessReport::not_ready()
+        }
+    };
+    if !report.is_ready() {
+        res.status_code(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    res.render(Json(report));
+}
+
+/// Prometheus 指标 (text exposition format)
+#[salvo::oapi::endpoint(tags("健康检查"), status_codes(200, 500))]
+pub async fn metrics(res: &mut Response) {
+    match crate::utils::metrics::render() {
+        Ok(body) => {
+            res.add_header("Content-Type", "text/plain; version=0.0.4; charset=utf-8", true).ok();
+            res.render(body);
+        }
+        Err(e) => {
+            tracing::error!("Failed to encode metrics: {}", e);
+            res.status_code(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    }
+}
+
+#[handler]
+pub async fn catcher_err(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
     // 记录请求基本信息
     let method = req.method().to_string();
     let path = req.uri().path().to_string();
     let client_ip = req.remote_addr().to_string();
 
+    // 错误响应与接口返回的错误格式一致，带上请求 ID (未匹配到路由时中间件未执行，没有请求 ID)
+    let request_id = depot.get::<String>(REQUEST_ID_KEY).ok().cloned();
+
     // 仅处理错误状态码
     if let Some(status_code) = res.status_code {
         match status_code {
-            StatusCode::NOT_FOUND => handle_not_found(req, res, ctrl).await,
-            StatusCode::INTERNAL_SERVER_ERROR => handle_server_error(res, ctrl).await,
-            _ => handle_other_errors(req, res, status_code, ctrl).await,
+            StatusCode::NOT_FOUND => handle_not_found(req, res, ctrl, request_id).await,
+            StatusCode::INTERNAL_SERVER_ERROR => handle_server_error(req, res, ctrl, request_id).await,
+            _ => handle_other_errors(req, res, status_code, ctrl, request_id).await,
         }
     } else {
         // 记录未处理的成功请求（可选）
-        log::warn!("请求成功但未记录 | 方法: {} | 路径: {} | 客户端IP: {}", method, path, client_ip);
+        tracing::warn!("请求成功但未记录 | 方法: {} | 路径: {} | 客户端IP: {}", method, path, client_ip);
     }
 }
 
-async fn handle_not_found(req: &Request, res: &mut Response, ctrl: &mut FlowCtrl) {
+async fn handle_not_found(req: &Request, res: &mut Response, ctrl: &mut FlowCtrl, request_id: Option<String>) {
     ctrl.skip_rest();
 
     // 收集请求参数
@@ -77,7 +171,7 @@ async fn handle_not_found(req: &Request, res: &mut Response, ctrl: &mut FlowCtrl
 
     if !req.uri().path().contains("actuator") {
         // 记录详细错误信息
-        log::error!(
+        tracing::error!(
             "未找到接口 | 路径: {} | 方法: {} | 参数: {} | 客户端IP: {}",
             req.uri().path(),
             req.method(),
@@ -85,17 +179,17 @@ async fn handle_not_found(req: &Request, res: &mut Response, ctrl: &mut FlowCtrl
             req.remote_addr()
         );
     }
-    res.render(res_json_custom::<()>(404, "请求的资源不存在"));
+    res.render(ApiError::new(ErrorCode::NotFound).render_with_request_id::<()>(Locale::from_request(req), request_id));
 }
 
-async fn handle_server_error(res: &mut Response, ctrl: &mut FlowCtrl) {
+async fn handle_server_error(req: &Request, res: &mut Response, ctrl: &mut FlowCtrl, request_id: Option<String>) {
     ctrl.skip_rest();
-    log::error!("服务器内部错误: {:?}", res.to_string());
+    tracing::error!("服务器内部错误: {:?}", res.to_string());
 
-    res.render(res_json_custom::<()>(500, "服务器内部错误，请稍后重试"));
+    res.render(ApiError::new(ErrorCode::InternalError).render_with_request_id::<()>(Locale::from_request(req), request_id));
 }
 
-async fn handle_other_errors(req: &Request, res: &mut Response, code: StatusCode, ctrl: &mut FlowCtrl) {
+async fn handle_other_errors(req: &Request, res: &mut Response, code: StatusCode, ctrl: &mut FlowCtrl, request_id: Option<String>) {
     ctrl.skip_rest();
     let status_code = code.as_u16();
 
@@ -103,7 +197,7 @@ async fn handle_other_errors(req: &Request, res: &mut Response, code: StatusCode
     let params = req.params().iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join(", ");
 
     // 记录详细错误信息
-    log::error!(
+    tracing::error!(
         "其他错误, 路径: {} | 方法: {} | 参数: {} | 客户端IP: {},错误码: {}",
         req.uri().path(),
         req.method(),
@@ -111,5 +205,188 @@ async fn handle_other_errors(req: &Request, res: &mut Response, code: StatusCode
         req.remote_addr(),
         code
     );
-    res.render(res_json_custom::<()>(status_code as i32, format!("请求处理失败 (状态码: {})", status_code).as_str()));
+    res.render(ApiError::new(ErrorCode::RequestFailed).with_status(status_code as i32).render_with_request_id::<()>(Locale::from_request(req), request_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::health::HealthProbe;
+    use crate::utils::token_denylist::{MemoryTokenDenylist, revoke_claims};
+    use salvo::async_trait;
+    use salvo::prelude::{Handler, Router, Service};
+    use salvo::test::{ResponseExt, TestClient};
+    use std::time::Duration;
+
+    const SECRET: &str = "test-secret-test-secret-test-secret";
+
+    fn keys() -> JwtKeySet {
+        JwtKeySet::hs256(SECRET)
+    }
+
+    fn issue(jti: &str, exp: i64) -> String {
+        let claims = Claims {
+            sub: "0xabc".to_string(),
+            exp: exp as usize,
+            user_id: "64b000000000000000000001".to_string(),
+            role: "creditor".to_string(),
+            jti: jti.to_string(),
+            iat: 0,
+        };
+        keys().sign(&claims).unwrap()
+    }
+
+    // 注销后，同一令牌再访问受保护接口 (如 /user/enterprise-info 的 get_enterprise_info) 时被 auth_token 拒绝
+    #[tokio::test]
+    async fn test_logged_out_token_is_rejected() {
+        let denylist = MemoryTokenDenylist::new(Duration::from_secs(60));
+        let now = chrono::Utc::now().timestamp();
+        let token = issue("jti-logout", now + 600);
+
+        let claims = verify_token(&token, &keys(), &denylist).await.unwrap();
+        assert!(revoke_claims(&denylist, &claims.jti, claims.exp, 0, now).await.unwrap());
+        assert_eq!(verify_token(&token, &keys(), &denylist).await.unwrap_err(), ErrorCode::TokenRevoked);
+
+        // 其他令牌不受影响
+        let other = issue("jti-other", now + 600);
+        assert!(verify_token(&other, &keys(), &denylist).await.is_ok());
+    }
+
+    // 角色变更强制重新登录：此前签发的令牌失效，之后登录签发的令牌不受影响
+    #[tokio::test]
+    async fn test_tokens_issued_before_forced_relogin_are_rejected() {
+        let denylist = MemoryTokenDenylist::new(Duration::from_secs(60));
+        let now = chrono::Utc::now().timestamp();
+        let lifetime = crate::controller::user_controller::TOKEN_LIFETIME_SECS;
+        let old = issue("jti-old", now - 10 + lifetime);
+        assert!(verify_token(&old, &keys(), &denylist).await.is_ok());
+
+        denylist.revoke_user("64b000000000000000000001", now, 60).await.unwrap();
+        assert_eq!(verify_token(&old, &keys(), &denylist).await.unwrap_err(), ErrorCode::TokenRevoked);
+        let relogin = issue("jti-new", now + lifetime);
+        assert!(verify_token(&relogin, &keys(), &denylist).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_token_without_jti_is_not_revocable() {
+        let denylist = MemoryTokenDenylist::new(Duration::from_secs(60));
+        let now = chrono::Utc::now().timestamp();
+        let token = issue("", now + 600);
+
+        let claims = verify_token(&token, &keys(), &denylist).await.unwrap();
+        assert!(!revoke_claims(&denylist, &claims.jti, claims.exp, 0, now).await.unwrap());
+        assert!(verify_token(&token, &keys(), &denylist).await.is_ok());
+    }
+
+    struct StubProbe(&'static str, Result<(), String>);
+
+    #[async_trait]
+    impl HealthProbe for StubProbe {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            self.1.clone()
+        }
+    }
+
+    struct InjectChecks(Arc<HealthChecks>);
+
+    #[async_trait]
+    impl Handler for InjectChecks {
+        async fn handle(&self, _req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+            depot.inject(self.0.clone());
+        }
+    }
+
+    fn health_service(redis: Result<(), String>) -> Service {
+        let checks = Arc::new(HealthChecks::new(vec![
+            Arc::new(StubProbe("mongodb", Ok(()))),
+            Arc::new(StubProbe("redis", redis)),
+            Arc::new(StubProbe("rpc", Ok(()))),
+        ]));
+        Service::new(
+            Router::new()
+                .hoop(InjectChecks(checks))
+                .push(Router::with_path("healthz").get(healthz))
+                .push(Router::with_path("readyz").get(readyz)),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_readyz_reports_unhealthy_redis() {
+        let service = health_service(Err("Connection refused (os error 111)".to_string()));
+        let mut res = TestClient::get("http://127.0.0.1:5800/readyz").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        let body: serde_json::Value = res.take_json().await.unwrap();
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["dependencies"]["redis"]["status"], "down");
+        assert_eq!(body["dependencies"]["mongodb"]["status"], "up");
+
+        // 存活探针不检查依赖
+        let res = TestClient::get("http://127.0.0.1:5800/healthz").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_readyz_all_up() {
+        let service = health_service(Ok(()));
+        let mut res = TestClient::get("http://127.0.0.1:5800/readyz").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let body: serde_json::Value = res.take_json().await.unwrap();
+        assert_eq!(body["status"], "ready");
+    }
+
+    struct PanicProbe;
+
+    #[async_trait]
+    impl HealthProbe for PanicProbe {
+        fn name(&self) -> &'static str {
+            "rpc"
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            panic!("probe bug");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_readyz_not_ready_when_check_cannot_run() {
+        // 探测 panic 记为 down
+        let checks = HealthChecks::new(vec![Arc::new(StubProbe("mongodb", Ok(()))), Arc::new(PanicProbe)]);
+        let report = checks.run().await;
+        assert!(!report.is_ready());
+        assert_eq!(report.dependencies["rpc"].status, "down");
+
+        // 没有注册探测或未注入 HealthChecks 都不能报告就绪
+        assert!(!HealthChecks::default().run().await.is_ready());
+        let service = Service::new(Router::with_path("readyz").get(readyz));
+        let mut res = TestClient::get("http://127.0.0.1:5800/readyz").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::SERVICE_UNAVAILABLE));
+        let body: serde_json::Value = res.take_json().await.unwrap();
+        assert_eq!(body["status"], "not_ready");
+    }
+
+    #[handler]
+    async fn panics() -> &'static str {
+        panic!("boom");
+    }
+
+    // CatchPanic 捕获的 panic 经 catcher_err 渲染为统一的错误格式
+    #[tokio::test]
+    async fn test_catcher_wraps_panic_in_error_body() {
+        let router = Router::new()
+            .hoop(crate::router::middware::request_id)
+            .hoop(salvo::prelude::CatchPanic::new())
+            .push(Router::with_path("boom").get(panics));
+        let service = Service::new(router).catcher(salvo::catcher::Catcher::default().hoop(catcher_err));
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/boom").add_header("X-Request-Id", "req-panic", true).send(&service).await;
+        let body: serde_json::Value = res.take_json().await.unwrap();
+        assert_eq!(body["code"], 500);
+        assert_eq!(body["error"]["code"], "INTERNAL_ERROR");
+        assert_eq!(body["error"]["requestId"], "req-panic");
+        assert!(body["error"]["message"].is_string());
+    }
 }
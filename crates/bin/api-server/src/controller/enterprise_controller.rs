@@ -1,15 +1,28 @@
-use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_not_found};
+use crate::utils::pagination;
+use crate::utils::res::{Res, ResObj, res_json_ok};
 use mongodb::{Database, bson::oid::ObjectId};
 use salvo::oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam};
+use salvo::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use salvo::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use service::error::ServiceError;
 use service::repository::EnterpriseRepository;
-use service::repository::enterprise_repository::UpdateEnterpriseData;
+use service::repository::enterprise_repository::{EnterpriseFilter, UpdateEnterpriseData};
+use std::io::Cursor;
 use std::sync::Arc;
 
-use common::domain::entity::EnterpriseStatus;
+use common::domain::dto::enterprise_performance_dto::{EnterprisePerformanceDto, EnterprisePerformanceSummaryDto};
+use common::domain::entity::{EnterpriseStatus, UserRole, WebhookSubscription};
 use common::domain::entity::enterprise::EnterpriseDto;
+use common::pagination::{OffsetPagination, Page};
+use common::utils::wallet_utils::normalize_address;
+use configs::CFG;
+use service::repository::UserRepository;
+use service::service::{EnterpriseExportService, StatsService, WebhookService};
+
+use crate::controller::{AuthedUser, admin_controller};
+use crate::utils::api_error::{ApiError, ErrorCode};
 
 // --- Request DTOs ---
 #[derive(Deserialize, ToSchema, Debug)]
@@ -33,7 +46,45 @@ pub struct UpdateEnterpriseRequest {
     pub kyc_details_ipfs_hash: Option<String>,
 }
 
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "reason": "营业执照已过期"})))]
+pub struct RejectEnterpriseRequest {
+    /// 驳回原因
+    pub reason: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "url": "https://erp.example.com/pharos/webhook"})))]
+pub struct RegisterWebhookRequest {
+    /// 回调地址 (http/https)，不能指向本机、内网或链路本地地址
+    pub url: String,
+}
+
 // --- Response DTO ---
+#[derive(Serialize, ToSchema, Debug)]
+pub struct WebhookSubscriptionResponse {
+    pub id: String,
+    pub enterprise_id: String,
+    pub url: String,
+    /// 签名密钥，用于校验 X-Pharos-Signature；只在登记时返回，平台不保存明文
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// 登记时间 (毫秒时间戳)
+    pub created_at: i64,
+}
+
+impl WebhookSubscriptionResponse {
+    fn new(subscription: WebhookSubscription, secret: Option<String>) -> Self {
+        Self {
+            id: subscription.id.map(|id| id.to_hex()).unwrap_or_default(),
+            enterprise_id: subscription.enterprise_id.to_hex(),
+            url: subscription.url,
+            secret,
+            created_at: subscription.created_at.timestamp_millis(),
+        }
+    }
+}
+
 // We can reuse the entity directly or create a specific response DTO
 // For simplicity, reusing Enterprise entity here.
 // Note: ObjectId and DateTime might not serialize to simple strings by default depending on features.
@@ -42,29 +93,44 @@ pub struct UpdateEnterpriseRequest {
 // --- Handlers ---
 
 /// 创建企业实体
+///
+/// 按钱包地址幂等：该地址已登记企业时返回已有企业 (200)，
+/// 配置 `enterprise.reject_duplicate = true` 时改为返回 409 `ENTERPRISE_EXISTS`。
 #[salvo::oapi::endpoint(
     tags("企业"),
-    status_codes(200, 400, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 409, 500),
     request_body = CreateEnterpriseRequest,
     responses(
-        (status_code = 200, description = "Enterprise created successfully.", body = EnterpriseDto),
-        (status_code = 400, description = "Invalid request data."),
+        (status_code = 200, description = "Enterprise created, or the existing enterprise for this wallet address.", body = EnterpriseDto),
+        (status_code = 400, description = "INVALID_ADDRESS: malformed wallet address or bad checksum."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Creditor or admin role required."),
+        (status_code = 409, description = "ENTERPRISE_EXISTS: wallet address already registered (when duplicates are rejected)."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
 pub async fn create_enterprise(req: JsonBody<CreateEnterpriseRequest>, depot: &mut Depot) -> Res<EnterpriseDto> {
+    let wallet_address = match normalize_address(req.wallet_address.trim()) {
+        Ok(address) => address,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot)),
+    };
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let repo = EnterpriseRepository::new(&mongodb);
-    log::warn!("enterprise database connection established");
-    match repo.create(&req.name, &req.wallet_address).await {
-        Ok(enterprise) => {
-            let data = EnterpriseDto::from(enterprise);
-            Ok(res_json_ok(Some(data)))
+    match repo.create_or_get(&req.name, &wallet_address).await {
+        Ok((enterprise, true)) => Ok(res_json_ok(Some(EnterpriseDto::from(enterprise)))),
+        Ok((enterprise, false)) => {
+            if CFG.enterprise.reject_duplicate {
+                tracing::warn!("Enterprise with wallet {} already exists: {:?}", wallet_address, enterprise.id);
+                return Err(ApiError::new(ErrorCode::EnterpriseExists).to_json(depot));
+            }
+            tracing::info!("Enterprise with wallet {} already exists, returning {:?}", wallet_address, enterprise.id);
+            Ok(res_json_ok(Some(EnterpriseDto::from(enterprise))))
         }
 
         Err(e) => {
-            log::error!("Failed to create enterprise: {}", e);
-            Err(res_json_err("Failed to create enterprise"))
+            tracing::error!("Failed to create enterprise: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -89,21 +155,179 @@ pub async fn get_enterprise_by_id(id: QueryParam<String>, depot: &mut Depot) ->
 
     let id_str = id.into_inner();
 
-    log::warn!("get_enterprise_by_id, id: {}", &id_str);
+    tracing::warn!("get_enterprise_by_id, id: {}", &id_str);
     let oid = match ObjectId::parse_str(&id_str) {
         Ok(oid) => oid,
-        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
     };
 
     match repo.find_by_id(oid).await {
         Ok(Some(enterprise)) => {
-            let data = EnterpriseDto::from(enterprise);
+            // 履约摘要计算失败不影响详情返回
+            let stats_service = depot.obtain::<Arc<StatsService>>().expect("StatsService not found in depot");
+            let now_ms = chrono::Utc::now().timestamp_millis();
+            let performance = match stats_service.enterprise_performance(&enterprise, CFG.invoice.settlement_grace_days, now_ms).await {
+                Ok(p) => Some(EnterprisePerformanceSummaryDto::from(&p)),
+                Err(e) => {
+                    tracing::warn!("Failed to compute performance for enterprise {}: {}", id_str, e);
+                    None
+                }
+            };
+            let mut data = EnterpriseDto::from(enterprise);
+            data.performance = performance;
             Ok(res_json_ok(Some(data)))
         }
-        Ok(None) => Err(res_not_found("Enterprise not found")),
+        Ok(None) => Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to get enterprise by ID: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 查询企业历史履约表现 (管理员)
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Enterprise MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Enterprise settlement performance.", body = EnterprisePerformanceDto),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "Enterprise not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_enterprise_performance(id: PathParam<String>, depot: &mut Depot) -> Res<EnterprisePerformanceDto> {
+    admin_controller::require_admin(depot)?;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let stats_service = depot.obtain::<Arc<StatsService>>().expect("StatsService not found in depot").clone();
+
+    let oid = ObjectId::parse_str(id.into_inner()).map_err(|_| ApiError::new(ErrorCode::InvalidId).to_json(depot))?;
+    let enterprise = match EnterpriseRepository::new(&mongodb).find_by_id(oid).await {
+        Ok(Some(enterprise)) => enterprise,
+        Ok(None) => return Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to get enterprise by ID: {}", e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    let now_ms = chrono::Utc::now().timestamp_millis();
+    match stats_service.enterprise_performance(&enterprise, CFG.invoice.settlement_grace_days, now_ms).await {
+        Ok(performance) => Ok(res_json_ok(Some(performance))),
+        Err(e) => {
+            tracing::error!("Failed to compute enterprise performance: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 审核通过企业 (管理员)，通过后其发行的票据才可被认购
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Enterprise MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Enterprise verified.", body = EnterpriseDto),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "Enterprise not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn verify_enterprise(id: PathParam<String>, depot: &mut Depot) -> Res<EnterpriseDto> {
+    let admin = admin_controller::require_admin(depot)?.sub.clone();
+    set_verification_status(depot, id.into_inner(), EnterpriseStatus::Verified, None, &admin).await
+}
+
+/// 驳回企业审核 (管理员)，需填写原因
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Enterprise MongoDB ObjectId")
+    ),
+    request_body = RejectEnterpriseRequest,
+    responses(
+        (status_code = 200, description = "Enterprise rejected.", body = EnterpriseDto),
+        (status_code = 400, description = "Invalid ID format or empty reason."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "Enterprise not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn reject_enterprise(id: PathParam<String>, req: JsonBody<RejectEnterpriseRequest>, depot: &mut Depot) -> Res<EnterpriseDto> {
+    let admin = admin_controller::require_admin(depot)?.sub.clone();
+    let reason = req.into_inner().reason;
+    if reason.trim().is_empty() {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Rejection reason is required").to_json(depot));
+    }
+    set_verification_status(depot, id.into_inner(), EnterpriseStatus::Rejected, Some(reason.trim()), &admin).await
+}
+
+async fn set_verification_status(depot: &mut Depot, id: String, status: EnterpriseStatus, reason: Option<&str>, admin: &str) -> Res<EnterpriseDto> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let oid = ObjectId::parse_str(&id).map_err(|_| ApiError::new(ErrorCode::InvalidId).to_json(depot))?;
+
+    match EnterpriseRepository::new(&mongodb).set_verification_status(oid, status, reason).await {
+        Ok(Some(enterprise)) => {
+            tracing::info!("Enterprise {} set to {:?} by {}", oid, status, admin);
+            Ok(res_json_ok(Some(EnterpriseDto::from(enterprise))))
+        }
+        Ok(None) => Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot)),
         Err(e) => {
-            log::error!("Failed to get enterprise by ID: {}", e);
-            Err(res_json_err("Failed to get enterprise"))
+            tracing::error!("Failed to set verification status of enterprise {}: {}", oid, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 分页查询企业 (管理员)，可按审核状态和名称 (不区分大小写的子串) 筛选
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 500),
+    parameters(
+        ("status" = Option<EnterpriseStatus>, Query, description = "Verification status"),
+        ("name" = Option<String>, Query, description = "Case-insensitive name substring"),
+        ("page" = Option<u64>, Query, description = "Page number, starting from 1"),
+        ("page_size" = Option<i64>, Query, description = "Page size (capped per `pagination.overrides`)")
+    ),
+    responses(
+        (status_code = 200, description = "Matching enterprises, newest first, with total count.", body = Page<EnterpriseDto>),
+        (status_code = 400, description = "Invalid filter."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_enterprises_admin(
+    status: QueryParam<EnterpriseStatus, false>,
+    name: QueryParam<String, false>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    depot: &mut Depot,
+) -> Res<Page<EnterpriseDto>> {
+    admin_controller::require_admin(depot)?;
+    let filter = EnterpriseFilter { status: status.into_inner(), name: name.into_inner() };
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("admin.enterprises", page_size.into_inner()) as u64);
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    match EnterpriseRepository::new(&mongodb).list(&filter, pagination).await {
+        Ok((enterprises, total)) => {
+            let rows = enterprises.into_iter().map(EnterpriseDto::from).collect();
+            Ok(res_json_ok(Some(Page::offset(rows, total, pagination.skip() as u64))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to list enterprises with {:?}: {}", filter, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -132,8 +356,8 @@ pub async fn list_enterprises(depot: &mut Depot) -> Res<Vec<EnterpriseDto>> {
             Ok(res_json_ok(Some(data)))
         }
         Err(e) => {
-            log::error!("Failed to list enterprises: {}", e);
-            Err(res_json_err("Failed to list enterprises"))
+            tracing::error!("Failed to list enterprises: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -159,7 +383,7 @@ pub async fn update_enterprise(id: PathParam<String>, req: JsonBody<UpdateEnterp
 
     let oid = match ObjectId::parse_str(&id.into_inner()) {
         Ok(oid) => oid,
-        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
     };
 
     // Map request DTO to repository update struct
@@ -173,14 +397,14 @@ pub async fn update_enterprise(id: PathParam<String>, req: JsonBody<UpdateEnterp
     match repo.update(oid, update_data).await {
         Ok(update_result) => {
             if update_result.matched_count == 0 {
-                Err(res_not_found("Enterprise not found"))
+                Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot))
             } else {
                 Ok(res_json_ok(None))
             }
         }
         Err(e) => {
-            log::error!("Failed to update enterprise: {}", e);
-            Err(res_json_err("Failed to update enterprise"))
+            tracing::error!("Failed to update enterprise: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -188,13 +412,16 @@ pub async fn update_enterprise(id: PathParam<String>, req: JsonBody<UpdateEnterp
 /// 删除企业
 #[salvo::oapi::endpoint(
     tags("企业"),
-    status_codes(200, 400, 404, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
     parameters(
         ("id" = String, Query, description = "Enterprise MongoDB ObjectId")
     ),
     responses(
         (status_code = 200, description = "Enterprise deleted successfully."),
         (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Creditor or admin role required."),
         (status_code = 404, description = "Enterprise not found."),
         (status_code = 500, description = "Internal server error."),
     )
@@ -205,20 +432,202 @@ pub async fn delete_enterprise(id: QueryParam<String>, depot: &mut Depot) -> Res
 
     let oid = match ObjectId::parse_str(&id.into_inner()) {
         Ok(oid) => oid,
-        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
     };
 
     match repo.delete(oid).await {
         Ok(delete_result) => {
             if delete_result.deleted_count == 0 {
-                Err(res_not_found("Enterprise not found"))
+                Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot))
             } else {
                 Ok(res_json_ok(None))
             }
         }
         Err(e) => {
-            log::error!("Failed to delete enterprise: {}", e);
-            Err(res_json_err("Failed to delete enterprise"))
+            tracing::error!("Failed to delete enterprise: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 导出企业全部数据 (ZIP，企业管理员或平台管理员)
+///
+/// 企业管理员导出时投资人地址会被替换为编号；平台管理员导出完整数据。
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Enterprise MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "application/zip 附件，包含企业、票据、认购、兑付及审计日志 JSON"),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Not an admin of this enterprise."),
+        (status_code = 404, description = "Enterprise not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn export_enterprise(id: PathParam<String>, user: AuthedUser, depot: &mut Depot, res: &mut Response) {
+    let Ok(oid) = ObjectId::parse_str(id.into_inner()) else {
+        return res.render(ApiError::new(ErrorCode::InvalidId).to_json::<()>(depot));
+    };
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    // 平台管理员可导出任意企业；企业管理员只能导出自己绑定的企业，且投资人信息需脱敏
+    let redact_investors = if user.is_admin() {
+        false
+    } else {
+        match UserRepository::new(&mongodb).find_by_wallet_address(&user.address).await {
+            Ok(Some(u)) if u.role == UserRole::EnterpriseAdmin && u.enterprise_id == Some(oid) => true,
+            Ok(_) => return res.render(ApiError::new(ErrorCode::Forbidden).to_json::<()>(depot)),
+            Err(e) => {
+                tracing::error!("Failed to load user {}: {}", user.address, e);
+                return res.render(ApiError::new(ErrorCode::DatabaseError).to_json::<()>(depot));
+            }
+        }
+    };
+
+    let export_service = EnterpriseExportService::new(&mongodb);
+    let enterprise = match export_service.find_enterprise(oid).await {
+        Ok(Some(enterprise)) => enterprise,
+        Ok(None) => return res.render(ApiError::new(ErrorCode::EnterpriseNotFound).to_json::<()>(depot)),
+        Err(e) => {
+            tracing::error!("Failed to get enterprise by ID: {}", e);
+            return res.render(ApiError::from(&e).to_json::<()>(depot));
+        }
+    };
+
+    // 归档在内存中生成：查询之间只有压缩与内存写入，不会在 handler 中做阻塞的文件 I/O
+    let archive = match export_service.export_zip(&enterprise, redact_investors, Cursor::new(Vec::new())).await {
+        Ok(cursor) => cursor.into_inner(),
+        Err(e) => {
+            tracing::error!("Failed to export enterprise {}: {}", oid, e);
+            return res.render(ApiError::from(&e).to_json::<()>(depot));
+        }
+    };
+    tracing::info!("User {} exported enterprise {} ({} bytes, redacted: {})", user.address, oid, archive.len(), redact_investors);
+
+    let filename = format!("enterprise-{}-{}.zip", oid.to_hex(), chrono::Utc::now().format("%Y%m%d%H%M%S"));
+    let disposition = format!("attachment; filename=\"{}\"", filename);
+    let headers = res
+        .add_header(CONTENT_TYPE, "application/zip", true)
+        .and_then(|res| res.add_header(CONTENT_DISPOSITION, disposition, true))
+        .map(|_| ());
+    if let Err(e) = headers {
+        tracing::error!("Failed to set export headers: {}", e);
+        return res.render(ApiError::new(ErrorCode::InternalError).to_json::<()>(depot));
+    }
+    res.body(archive);
+}
+
+/// 登记企业 webhook 回调地址，票据状态变更时推送带签名的 JSON。
+/// 响应中返回签名密钥，仅此一次；同一地址重复登记会更换密钥
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    request_body = RegisterWebhookRequest,
+    responses(
+        (status_code = 200, description = "Webhook registered, the response carries the signing secret.", body = WebhookSubscriptionResponse),
+        (status_code = 400, description = "WEBHOOK_URL_NOT_ALLOWED: invalid URL or it resolves to a loopback/private/link-local address."),
+        (status_code = 401, description = "Unauthorized."),
+        (status_code = 403, description = "Creditor or admin role required."),
+        (status_code = 404, description = "EnterpriseNotBound: user is not bound to any enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn register_webhook(req: JsonBody<RegisterWebhookRequest>, user: AuthedUser, depot: &mut Depot) -> Res<WebhookSubscriptionResponse> {
+    let enterprise_id = webhook_enterprise_id(&user, depot).await?;
+    let url = req.url.trim();
+    let webhook_service = depot.obtain::<Arc<WebhookService>>().expect("WebhookService not found in depot").clone();
+    match webhook_service.register(enterprise_id, url).await {
+        Ok((subscription, secret)) => {
+            tracing::info!("Enterprise {} registered webhook {}", enterprise_id, url);
+            Ok(res_json_ok(Some(WebhookSubscriptionResponse::new(subscription, Some(secret)))))
+        }
+        Err(ServiceError::InvalidWebhookUrl(reason)) => {
+            tracing::warn!("Enterprise {} webhook {} rejected: {}", enterprise_id, url, reason);
+            Err(ApiError::new(ErrorCode::WebhookUrlNotAllowed).to_json(depot))
+        }
+        Err(e) => {
+            tracing::error!("Failed to register webhook for enterprise {}: {}", enterprise_id, e);
+            Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
+        }
+    }
+}
+
+/// 当前企业登记的 webhook 回调地址 (不含密钥)
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 404, 500),
+    responses(
+        (status_code = 200, description = "Registered webhooks.", body = Vec<WebhookSubscriptionResponse>),
+        (status_code = 401, description = "Unauthorized."),
+        (status_code = 403, description = "Creditor or admin role required."),
+        (status_code = 404, description = "EnterpriseNotBound: user is not bound to any enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_webhooks(user: AuthedUser, depot: &mut Depot) -> Res<Vec<WebhookSubscriptionResponse>> {
+    let enterprise_id = webhook_enterprise_id(&user, depot).await?;
+    let webhook_service = depot.obtain::<Arc<WebhookService>>().expect("WebhookService not found in depot").clone();
+    match webhook_service.list_subscriptions(enterprise_id).await {
+        Ok(subscriptions) => Ok(res_json_ok(Some(
+            subscriptions.into_iter().map(|s| WebhookSubscriptionResponse::new(s, None)).collect(),
+        ))),
+        Err(e) => {
+            tracing::error!("Failed to list webhooks for enterprise {}: {}", enterprise_id, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 删除当前企业登记的 webhook 回调地址，已排队的投递仍会完成
+#[salvo::oapi::endpoint(
+    tags("企业"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(("id" = String, Path, description = "Webhook ID")),
+    responses(
+        (status_code = 200, description = "Webhook deleted."),
+        (status_code = 400, description = "INVALID_ID: malformed webhook ID."),
+        (status_code = 401, description = "Unauthorized."),
+        (status_code = 403, description = "Creditor or admin role required."),
+        (status_code = 404, description = "NOT_FOUND: no such webhook for this enterprise; EnterpriseNotBound: user is not bound to any enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn delete_webhook(id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let Ok(webhook_id) = ObjectId::parse_str(id.into_inner()) else {
+        return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot));
+    };
+    let enterprise_id = webhook_enterprise_id(&user, depot).await?;
+    let webhook_service = depot.obtain::<Arc<WebhookService>>().expect("WebhookService not found in depot").clone();
+    match webhook_service.unregister(enterprise_id, webhook_id).await {
+        Ok(true) => {
+            tracing::info!("Enterprise {} deleted webhook {}", enterprise_id, webhook_id);
+            Ok(res_json_ok(None))
+        }
+        Ok(false) => Err(ApiError::new(ErrorCode::NotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to delete webhook {} for enterprise {}: {}", webhook_id, enterprise_id, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 当前用户绑定的企业，webhook 只能由企业自己管理
+async fn webhook_enterprise_id(user: &AuthedUser, depot: &mut Depot) -> Result<ObjectId, Json<ResObj<()>>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    match UserRepository::new(&mongodb).find_by_wallet_address(&user.address).await {
+        Ok(Some(u)) => u.enterprise_id.ok_or_else(|| ApiError::new(ErrorCode::EnterpriseNotBound).to_json(depot)),
+        Ok(None) => Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to find user {}: {}", user.address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
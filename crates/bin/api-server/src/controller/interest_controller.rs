@@ -1,14 +1,23 @@
-use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_not_found};
-use mongodb::{Database, bson::oid::ObjectId};
+use crate::controller::AuthedUser;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::res::{Res, res_json_ok};
+use chrono::NaiveDate;
+use mongodb::{Database, bson::{DateTime, oid::ObjectId}};
 use salvo::{
-    oapi::{ToSchema, extract::QueryParam},
+    oapi::{ToSchema, extract::{PathParam, QueryParam}},
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
+use service::error::ServiceError;
 use service::repository::DailyInterestAccrualRepository;
+use service::service::InterestService;
+use common::domain::dto::accrued_interest_dto::AccruedInterestDto;
+use common::domain::dto::payment_schedule_dto::PaymentScheduleDto;
+use common::domain::dto::portfolio_interest_dto::PortfolioInterestDto;
 use common::domain::entity::DailyInterestAccrual;
+use configs::CFG;
 use std::sync::Arc;
-use log::{error, info};
+use tracing::{error, info};
 
 /// 每日利息记录DTO
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -39,6 +48,7 @@ impl From<&DailyInterestAccrual> for DailyInterestDto {
 /// 查询用户的所有日利息记录
 #[salvo::oapi::endpoint(
     tags("利息"),
+    security(("bearerAuth" = [])),
     status_codes(200, 401, 500),
     responses(
         (status_code = 200, description = "用户日利息记录列表", body = Vec<DailyInterestDto>),
@@ -46,15 +56,9 @@ impl From<&DailyInterestAccrual> for DailyInterestDto {
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_user_interest_accruals(depot: &mut Depot) -> Res<Vec<DailyInterestDto>> {
+pub async fn list_user_interest_accruals(user: AuthedUser, depot: &mut Depot) -> Res<Vec<DailyInterestDto>> {
     // 获取认证用户地址
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("认证用户地址未找到: {:?}", e);
-            return Err(res_json_err("用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
     let repo = DailyInterestAccrualRepository::new(&mongodb);
@@ -68,7 +72,7 @@ pub async fn list_user_interest_accruals(depot: &mut Depot) -> Res<Vec<DailyInte
         }
         Err(e) => {
             error!("查询用户日利息记录失败: {}", e);
-            Err(res_json_err("查询日利息记录失败"))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -76,6 +80,7 @@ pub async fn list_user_interest_accruals(depot: &mut Depot) -> Res<Vec<DailyInte
 /// 查询特定持仓的日利息记录
 #[salvo::oapi::endpoint(
     tags("利息"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 401, 500),
     parameters(
         ("holding_id" = String, Query, description = "持仓ID")
@@ -87,15 +92,9 @@ pub async fn list_user_interest_accruals(depot: &mut Depot) -> Res<Vec<DailyInte
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_holding_interest_accruals(holding_id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<DailyInterestDto>> {
+pub async fn list_holding_interest_accruals(holding_id: QueryParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<DailyInterestDto>> {
     // 获取认证用户地址
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("认证用户地址未找到: {:?}", e);
-            return Err(res_json_err("用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
     let repo = DailyInterestAccrualRepository::new(&mongodb);
@@ -111,7 +110,155 @@ pub async fn list_holding_interest_accruals(holding_id: QueryParam<String>, depo
         }
         Err(e) => {
             error!("查询持仓日利息记录失败: {}", e);
-            Err(res_json_err("查询日利息记录失败"))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 查询当前投资人全部活跃持仓截至某日的应计利息
+///
+/// 每个持仓按认购金额占票据金额的比例分得票据的应计利息，返回各持仓明细及合计；尚未融资的票据不计息。
+#[salvo::oapi::endpoint(
+    tags("利息"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 500),
+    parameters(
+        ("as_of" = Option<String>, Query, description = "计息截止日 (YYYY-MM-DD)，默认今天")
+    ),
+    responses(
+        (status_code = 200, description = "持仓应计利息明细及合计", body = PortfolioInterestDto),
+        (status_code = 400, description = "无效的请求参数"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_portfolio_interest(as_of: QueryParam<Option<String>>, user: AuthedUser, depot: &mut Depot) -> Res<PortfolioInterestDto> {
+    let as_of = match parse_as_of(as_of.into_inner()) {
+        Ok(as_of) => as_of,
+        Err(msg) => return Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+    };
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
+    let interest_service = InterestService::new(&mongodb, &CFG.interest, CFG.settlement.payout_decimals);
+    match interest_service.portfolio_interest(&user.address, as_of).await {
+        Ok(portfolio) => {
+            info!("用户 {} 截至 {} 持仓应计利息合计 {}", user.address, portfolio.as_of, portfolio.total_accrued_interest);
+            Ok(res_json_ok(Some(portfolio)))
+        }
+        Err(e) => {
+            error!("计算用户 {} 持仓应计利息失败: {}", user.address, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 解析计息截止日 (YYYY-MM-DD)，未指定时为当前时间
+fn parse_as_of(as_of: Option<String>) -> Result<DateTime, &'static str> {
+    match as_of {
+        Some(date_str) => match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
+            Ok(date) => Ok(DateTime::from_millis(date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc().timestamp_millis())),
+            Err(_) => Err("日期格式无效，请使用 YYYY-MM-DD"),
+        },
+        None => Ok(DateTime::now()),
+    }
+}
+
+/// 查询票据截至某日的应计利息
+///
+/// 自融资日起按配置的日计数规则 (ACT/365 或 30/360) 与计息方式计息，到期日后不再计息。
+#[salvo::oapi::endpoint(
+    tags("利息"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 409, 500),
+    parameters(
+        ("invoice_id" = String, Path, description = "票据ID"),
+        ("as_of" = Option<String>, Query, description = "计息截止日 (YYYY-MM-DD)，默认今天")
+    ),
+    responses(
+        (status_code = 200, description = "应计利息", body = AccruedInterestDto),
+        (status_code = 400, description = "无效的请求参数"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "票据不存在"),
+        (status_code = 409, description = "票据尚未融资或未接受融资条款"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_accrued_interest(invoice_id: PathParam<String>, as_of: QueryParam<Option<String>>, _user: AuthedUser, depot: &mut Depot) -> Res<AccruedInterestDto> {
+    let invoice_id = match ObjectId::parse_str(&invoice_id.into_inner()) {
+        Ok(oid) => oid,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
+    };
+    let as_of = match parse_as_of(as_of.into_inner()) {
+        Ok(as_of) => as_of,
+        Err(msg) => return Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+    };
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
+    let interest_service = InterestService::new(&mongodb, &CFG.interest, CFG.settlement.payout_decimals);
+    let invoice = match interest_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            error!("查询票据 {} 失败: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+
+    match interest_service.accrued_interest_detail(&invoice, as_of).await {
+        Ok(detail) => {
+            info!("票据 {} 截至 {} 应计利息 {}", invoice_id, detail.accrued_to, detail.accrued_interest);
+            Ok(res_json_ok(Some(detail)))
+        }
+        Err(e @ (ServiceError::InvoiceNotFinanced(_) | ServiceError::TermsNotAccepted(_))) => Err(ApiError::from(&e).to_json(depot)),
+        Err(e) => {
+            error!("计算票据 {} 应计利息失败: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 查询票据预计还款计划
+///
+/// 按配置的付息频率 (到期一次/按月/按季) 列出各期还款日、本金、利息及剩余本金，末期承担舍入差额。
+#[salvo::oapi::endpoint(
+    tags("利息"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 409, 500),
+    parameters(
+        ("invoice_id" = String, Path, description = "票据ID")
+    ),
+    responses(
+        (status_code = 200, description = "还款计划", body = PaymentScheduleDto),
+        (status_code = 400, description = "无效的请求参数"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "票据不存在"),
+        (status_code = 409, description = "票据尚未融资或未接受融资条款"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_payment_schedule(invoice_id: PathParam<String>, _user: AuthedUser, depot: &mut Depot) -> Res<PaymentScheduleDto> {
+    let invoice_id = match ObjectId::parse_str(&invoice_id.into_inner()) {
+        Ok(oid) => oid,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
+    };
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
+    let interest_service = InterestService::new(&mongodb, &CFG.interest, CFG.settlement.payout_decimals);
+    let invoice = match interest_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            error!("查询票据 {} 失败: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+
+    match interest_service.payment_schedule_detail(&invoice).await {
+        Ok(schedule) => Ok(res_json_ok(Some(schedule))),
+        Err(e @ (ServiceError::InvoiceNotFinanced(_) | ServiceError::TermsNotAccepted(_))) => Err(ApiError::from(&e).to_json(depot)),
+        Err(e) => {
+            error!("生成票据 {} 还款计划失败: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
-} 
\ No newline at end of file
+}
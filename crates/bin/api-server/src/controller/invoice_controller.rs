@@ -1,15 +1,21 @@
-use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_not_found, res_json_custom};
-use chrono::NaiveDate;
+use crate::utils::pagination::{self, PageLinks};
+use crate::utils::res::{Res, res_json_ok};
+use chrono::{NaiveDate, Utc};
+use common::domain::dto::batch_settlement_dto::BatchSettlementDto;
+use common::domain::dto::batch_invoice_create_dto::BatchCreateInvoicesDto;
+use common::domain::dto::batch_invoice_transition_dto::BatchTransitionDto;
 use common::domain::dto::interest_detail_dto::InterestDetailDto;
-use common::domain::dto::invoice_dto::{CreateInvoiceDto, InvoiceDataDto};
+use common::domain::dto::invoice_dto::CreateInvoiceDto;
+use common::domain::dto::invoice_reconciliation_dto::InvoiceReconciliationDto;
 use common::domain::dto::query_invoice_dto::QueryParamsDto;
 use common::domain::entity::Invoice;
 use common::domain::entity::enterprise::EnterpriseDto;
 use common::domain::entity::invoice::InvoiceDto;
+use common::pagination::{OffsetPagination, Page, Pagination};
 use ethers::middleware::SignerMiddleware;
 use ethers::providers::{Http, Provider};
 use ethers::signers::LocalWallet;
-use log::{error, warn};
+use tracing::{error, warn};
 use mongodb::{
     Database,
     bson::{DateTime, Decimal128, oid::ObjectId},
@@ -21,68 +27,160 @@ use salvo::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use service::invoice::InvoiceService;
+use service::invoice::{BatchItemError, InvoiceLimits, InvoiceListFilter, InvoiceService, InvoiceValidationError, SettlementOptions, is_bulk_transition_target};
+use service::invoice::invoice_validation::{InvoiceEdit, parse_create_invoice, validate_invoice_edit};
+use service::cache::InvoiceEventBus;
 use service::repository::InvoiceRepository;
-use service::repository::invoice_repository::UpdateInvoiceData;
-use std::convert::From;
+use service::repository::invoice_repository::{InvoiceFilter, UpdateInvoiceData};
+use futures::StreamExt;
+use salvo::sse::{SseEvent, SseKeepAlive};
+use std::convert::{From, Infallible};
 use std::str::FromStr;
 use std::sync::Arc;
 use std::fs::read;
 use salvo::http::StatusCode;
 use salvo::oapi::oapi;
 use service::{EnterpriseRepository, UserRepository};
-use crate::controller::EnterpriseInfoResponse;
+use crate::controller::{AuthedUser, EnterpriseInfoResponse, admin_controller, parse_object_id};
+use common::domain::dto::timeline_dto::TimelineEntryDto;
+use common::domain::dto::funding_ledger_dto::FundingLedgerDto;
+use common::domain::dto::repayment_settlement_dto::RepaymentSettlementDto;
+use common::domain::dto::invoice_cancellation_dto::InvoiceCancellationDto;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::contract_revert::chain_error_code;
+use service::service::{InvoiceLedgerService, InvoiceTimelineService, PurchaseService, SharedContractWriter};
+use service::service::timeline_service::TimelineViewer;
 use service::error::ServiceError;
 use common::domain::entity::invoice_status::InvoiceStatus;
 use service::repository::invoice_batch_repository::InvoiceBatchRepository;
+use service::repository::InvoiceDocumentRepository;
+use common::domain::entity::{InvoiceAuditDto, InvoiceDocument, InvoiceDocumentDto, User};
+use common::domain::entity::audit_log::SYSTEM_ACTOR;
+use configs::CFG;
 
 // --- Handlers ---
 /// 创建一个票据 (Standard endpoint for creating invoice directly in DB)
 #[salvo::oapi::endpoint(
     tags("票据"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 500),
-    request_body = InvoiceDataDto,
+    request_body = CreateInvoiceDto,
     responses(
         (status_code = 200, description = "Invoice created successfully.", body = InvoiceDto),
-        (status_code = 400, description = "Invalid request data."),
+        (status_code = 400, description = "Invalid request data, or INVOICE_AMOUNT_NOT_POSITIVE / INVOICE_AMOUNT_NOT_INTEGER / INVOICE_AMOUNT_EXCEEDS_CAP / UNSUPPORTED_CURRENCY / INVOICE_DUE_DATE_NOT_IN_FUTURE."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn create_invoice(req: &mut Request, depot: &mut Depot) -> Res<InvoiceDto> {
+pub async fn create_invoice(req: &mut Request, user: AuthedUser, depot: &mut Depot) -> Res<InvoiceDto> {
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let repo = InvoiceRepository::new(&mongodb);
 
-    // 1. Get authenticated user address from depot (inserted by auth_token middleware)
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
+    let user_address = user.address.as_str();
+
+    // 2. 解析请求体并校验金额 (整数最小单位)、币种与到期日
+    let body = match req.parse_body::<serde_json::Value>().await {
+        Ok(body) => body,
         Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
+            tracing::error!("Failed to deserialize CreateInvoiceDto: {}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail(format!("Invalid request body: {}", e)).to_json(depot));
         }
     };
-
-    // 2. Manually parse the body and handle potential deserialization errors
-    let data = match req.parse_body::<CreateInvoiceDto>().await {
+    let limits = InvoiceLimits { max_amount: CFG.invoice.max_amount, supported_currencies: CFG.invoice.supported_currencies.clone() };
+    let data = match parse_create_invoice(body, &limits, Utc::now().timestamp_millis()) {
         Ok(dto) => dto,
         Err(e) => {
-            log::error!("Failed to deserialize InvoiceDataDto: {}", e);
-            return Err(res_bad_request(&format!("Invalid request body: {}", e)));
+            tracing::warn!("Rejected invoice creation by {}: {}", user_address, e);
+            return Err(match invoice_validation_code(&e) {
+                Some(code) => ApiError::new(code).to_json(depot),
+                None => ApiError::new(ErrorCode::BadRequest).with_detail(e.to_string()).to_json(depot),
+            });
         }
     };
 
-    match repo.create_from_blockchain(&data).await {
+    match repo.create_from_blockchain(&data, user_address).await {
         Ok(invoice) => {
-            log::info!("Successfully created invoice {} for user {}", invoice.invoice_number, user_address);
+            tracing::info!("Successfully created invoice {} for user {}", invoice.invoice_number, user_address);
             let response_dto = InvoiceDto::from(&invoice);
             Ok(res_json_ok(Some(response_dto)))
         }
         Err(e) => {
-            log::error!("Failed to create invoice in repository for user {}: {}", user_address, e);
-            Err(res_json_err("Failed to save invoice data"))
+            tracing::error!("Failed to create invoice in repository for user {}: {}", user_address, e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
 
+/// 校验失败对应的错误码，请求体格式错误时为 None
+fn invoice_validation_code(e: &InvoiceValidationError) -> Option<ErrorCode> {
+    match e {
+        InvoiceValidationError::InvalidBody(_) => None,
+        InvoiceValidationError::AmountNotPositive => Some(ErrorCode::InvoiceAmountNotPositive),
+        InvoiceValidationError::AmountNotInteger => Some(ErrorCode::InvoiceAmountNotInteger),
+        InvoiceValidationError::AmountExceedsCap { .. } => Some(ErrorCode::InvoiceAmountExceedsCap),
+        InvoiceValidationError::UnsupportedCurrency(_) => Some(ErrorCode::UnsupportedCurrency),
+        InvoiceValidationError::DueDateNotInFuture => Some(ErrorCode::InvoiceDueDateNotInFuture),
+    }
+}
+
+// 批量创建票据请求参数
+#[derive(Deserialize, Debug, ToSchema)]
+pub struct BatchCreateInvoicesRequest {
+    /// 票据列表，每条与单条创建接口的请求体相同
+    pub invoices: Vec<serde_json::Value>,
+    /// 是否在创建后提交链上登记
+    #[serde(default)]
+    pub register_on_chain: bool,
+}
+
+/// 批量创建票据，逐条校验 (规则与单条创建相同) 并返回每条的结果，单条失败不影响其余条目
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 503),
+    request_body = BatchCreateInvoicesRequest,
+    responses(
+        (status_code = 200, description = "Per-item results in request order.", body = BatchCreateInvoicesDto),
+        (status_code = 400, description = "Empty batch, invalid body, or INVOICE_BATCH_TOO_LARGE."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 503, description = "On-chain registration requested but blockchain connection unavailable."),
+    )
+)]
+pub async fn create_invoices_batch(req: JsonBody<BatchCreateInvoicesRequest>, user: AuthedUser, depot: &mut Depot) -> Res<BatchCreateInvoicesDto> {
+    let req = req.into_inner();
+    if req.invoices.is_empty() {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Batch must contain at least one invoice").to_json(depot));
+    }
+    if req.invoices.len() > CFG.invoice.max_batch_size {
+        return Err(ApiError::new(ErrorCode::InvoiceBatchTooLarge).to_json(depot));
+    }
+    let contract = if req.register_on_chain {
+        match depot.obtain::<SharedContractWriter>() {
+            Ok(contract) => Some(contract.clone()),
+            Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+        }
+    } else {
+        None
+    };
+
+    let limits = InvoiceLimits { max_amount: CFG.invoice.max_amount, supported_currencies: CFG.invoice.supported_currencies.clone() };
+    let now_ms = Utc::now().timestamp_millis();
+    let items = req.invoices.into_iter().map(|body| validate_batch_item(body, &limits, now_ms)).collect();
+
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    let summary = invoice_service
+        .create_invoices_batch(items, contract.as_deref(), CFG.invoice.batch_concurrency, &user.address)
+        .await;
+    Ok(res_json_ok(Some(summary)))
+}
+
+/// 按单条创建的规则校验批量中的一条，失败时带上对应的错误码
+fn validate_batch_item(body: serde_json::Value, limits: &InvoiceLimits, now_ms: i64) -> Result<CreateInvoiceDto, BatchItemError> {
+    parse_create_invoice(body, limits, now_ms).map_err(|e| BatchItemError {
+        code: invoice_validation_code(&e).map(|code| code.as_str().to_string()),
+        message: e.to_string(),
+    })
+}
+
 /// 查询所有票据
 #[salvo::oapi::endpoint(
     tags("票据"),
@@ -106,46 +204,383 @@ pub async fn list_invoices(depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
             Ok(res_json_ok(Some(data)))
         }
         Err(e) => {
-            log::error!("Failed to list invoices: {}", e);
-            Err(res_json_err("Failed to list invoices"))
+            tracing::error!("Failed to list invoices: {}", e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
 
-/// 删除票据
+/// 游标分页查询票据，按创建时间倒序；把返回的 `next_cursor` 作为 `cursor` 传回获取下一页
+///
+/// 响应头 `X-Total-Count` 为总数，`Link` 给出下一页 (`rel="next"`) 与第一页 (`rel="first"`) 的地址。
 #[salvo::oapi::endpoint(
     tags("票据"),
-    status_codes(200, 400, 404, 500),
+    status_codes(200, 400, 500),
     parameters(
-        ("id" = String, Query, description = "Invoice MongoDB ObjectId")
+        ("cursor" = Option<String>, Query, description = "Opaque cursor from the previous page's `next_cursor`"),
+        ("limit" = Option<i64>, Query, description = "Page size (capped per `pagination.overrides`, default max 100)"),
+        ("status" = Option<InvoiceStatus>, Query, description = "Filter by invoice status"),
+        ("payee" = Option<String>, Query, description = "Filter by payee wallet address"),
+        ("include_deleted" = Option<bool>, Query, description = "Include soft-deleted invoices (admin only, ignored otherwise)")
     ),
     responses(
-        (status_code = 200, description = "Invoice deleted successfully."),
-        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 200, description = "A page of invoices.", body = Page<InvoiceDto>),
+        (status_code = 400, description = "Invalid cursor."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_invoices_page(
+    cursor: QueryParam<String, false>,
+    limit: QueryParam<i64, false>,
+    status: QueryParam<InvoiceStatus, false>,
+    payee: QueryParam<String, false>,
+    include_deleted: QueryParam<bool, false>,
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Res<Page<InvoiceDto>> {
+    let filter = InvoiceListFilter {
+        status: status.into_inner(),
+        payee: payee.into_inner().filter(|p| !p.is_empty()),
+        include_deleted: admin_include_deleted(include_deleted.into_inner(), depot),
+    };
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    let pagination = Pagination::new(cursor.into_inner(), pagination::page_size("invoice.list", limit.into_inner()) as u64);
+
+    match invoice_service.list_invoices(filter, pagination).await {
+        Ok(page) => {
+            pagination::set_page_headers(req, res, &page, PageLinks::Cursor);
+            Ok(res_json_ok(Some(page)))
+        }
+        Err(ServiceError::InvalidCursor(_)) => Err(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid cursor").to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to list invoices page: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 按出票企业、状态、金额与创建时间搜索票据，条件均为空时分页返回全部票据
+///
+/// 响应头 `X-Total-Count` 为总数，`Link` 给出上一页 / 下一页的地址。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    status_codes(200, 400, 500),
+    parameters(
+        ("enterprise_id" = Option<String>, Query, description = "Issuing enterprise ObjectId"),
+        ("status" = Option<InvoiceStatus>, Query, description = "Invoice status"),
+        ("min_amount" = Option<u64>, Query, description = "Minimum amount (inclusive)"),
+        ("max_amount" = Option<u64>, Query, description = "Maximum amount (inclusive)"),
+        ("created_from" = Option<i64>, Query, description = "Created at or after (ms timestamp, inclusive)"),
+        ("created_to" = Option<i64>, Query, description = "Created before (ms timestamp, exclusive)"),
+        ("page" = Option<u64>, Query, description = "Page number, starting from 1"),
+        ("page_size" = Option<i64>, Query, description = "Page size (capped per `pagination.overrides`)"),
+        ("include_deleted" = Option<bool>, Query, description = "Include soft-deleted invoices (admin only, ignored otherwise)")
+    ),
+    responses(
+        (status_code = 200, description = "Matching invoices, newest first.", body = Page<InvoiceDto>),
+        (status_code = 400, description = "Invalid filter."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
+pub async fn search_invoices(
+    enterprise_id: QueryParam<String, false>,
+    status: QueryParam<InvoiceStatus, false>,
+    min_amount: QueryParam<u64, false>,
+    max_amount: QueryParam<u64, false>,
+    created_from: QueryParam<i64, false>,
+    created_to: QueryParam<i64, false>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    include_deleted: QueryParam<bool, false>,
+    req: &mut Request,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Res<Page<InvoiceDto>> {
+    let enterprise_id = match enterprise_id.into_inner().filter(|id| !id.is_empty()) {
+        Some(id) => match ObjectId::parse_str(&id) {
+            Ok(oid) => Some(oid),
+            Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
+        },
+        None => None,
+    };
+    let (min_amount, max_amount) = (min_amount.into_inner(), max_amount.into_inner());
+    if let (Some(min), Some(max)) = (min_amount, max_amount) {
+        if min > max {
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("min_amount must not exceed max_amount").to_json(depot));
+        }
+    }
+    let created_between = match created_range(created_from.into_inner(), created_to.into_inner()) {
+        Ok(range) => range,
+        Err(msg) => return Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+    };
+    let filter = InvoiceFilter {
+        enterprise_id,
+        status: status.into_inner(),
+        min_amount,
+        max_amount,
+        created_between,
+        include_deleted: admin_include_deleted(include_deleted.into_inner(), depot),
+    };
+
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("invoice.search", page_size.into_inner()) as u64);
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let repo = InvoiceRepository::new(&mongodb);
+    match repo.query(&filter, pagination.skip(), pagination.page_size as i64).await {
+        Ok((invoices, total)) => {
+            let rows = invoices.iter().map(InvoiceDto::from).collect();
+            let result = Page::offset(rows, total, pagination.skip());
+            pagination::set_page_headers(req, res, &result, PageLinks::Offset { page: pagination.page, page_size: pagination.page_size });
+            Ok(res_json_ok(Some(result)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to search invoices with {:?}: {}", filter, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 查询当前用户绑定企业的票据 (Requires authentication)
+///
+/// 企业范围由服务端按用户绑定关系确定，不接受客户端传入的 enterprise_id；未绑定企业时返回 403。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 500),
+    parameters(
+        ("status" = Option<InvoiceStatus>, Query, description = "Invoice status"),
+        ("page" = Option<u64>, Query, description = "Page number, starting from 1"),
+        ("page_size" = Option<i64>, Query, description = "Page size (capped per `pagination.overrides`)")
+    ),
+    responses(
+        (status_code = 200, description = "The enterprise's invoices, newest first.", body = Page<InvoiceDto>),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "User is not bound to any enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_enterprise_invoices(
+    status: QueryParam<InvoiceStatus, false>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    user: AuthedUser,
+    depot: &mut Depot,
+) -> Res<Page<InvoiceDto>> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let filter = match UserRepository::new(&mongodb).find_by_wallet_address(&user.address).await {
+        Ok(Some(u)) => match enterprise_scoped_filter(&u, status.into_inner()) {
+            Ok(filter) => filter,
+            Err(code) => return Err(ApiError::new(code).to_json(depot)),
+        },
+        Ok(None) => return Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to find user {}: {}", user.address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("enterprise.invoices", page_size.into_inner()) as u64);
+    match InvoiceRepository::new(&mongodb).query(&filter, pagination.skip(), pagination.page_size as i64).await {
+        Ok((invoices, total)) => {
+            let rows = invoices.iter().map(InvoiceDto::from).collect();
+            Ok(res_json_ok(Some(Page::offset(rows, total, pagination.skip()))))
+        }
+        Err(e) => {
+            tracing::error!("Failed to list invoices for enterprise {:?}: {}", filter.enterprise_id, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 按用户绑定的企业限定票据查询范围，未绑定企业时返回 `Forbidden`
+fn enterprise_scoped_filter(user: &User, status: Option<InvoiceStatus>) -> Result<InvoiceFilter, ErrorCode> {
+    let enterprise_id = user.enterprise_id.ok_or(ErrorCode::Forbidden)?;
+    Ok(InvoiceFilter { enterprise_id: Some(enterprise_id), status, ..Default::default() })
+}
+
+/// 创建时间区间 [from, to)，只给出一端时另一端不限
+pub(crate) fn created_range(from: Option<i64>, to: Option<i64>) -> Result<Option<(DateTime, DateTime)>, &'static str> {
+    if from.is_none() && to.is_none() {
+        return Ok(None);
+    }
+    let start = from.map(DateTime::from_millis).unwrap_or(DateTime::MIN);
+    let end = to.map(DateTime::from_millis).unwrap_or(DateTime::MAX);
+    if start >= end {
+        return Err("created_from must be earlier than created_to");
+    }
+    Ok(Some((start, end)))
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "version": 3, "amount": 250000, "currency": "USDC"})))]
+pub struct UpdateInvoiceRequest {
+    /// 读取票据时得到的版本号
+    pub version: i64,
+    #[serde(default)]
+    pub amount: Option<u64>,
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// 到期日时间戳 (毫秒)
+    #[serde(default)]
+    pub due_date: Option<i64>,
+    #[serde(default)]
+    pub invoice_ipfs_hash: Option<String>,
+}
+
+/// 按 ID 查询单张票据
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId"),
+        ("include_deleted" = Option<bool>, Query, description = "Return the invoice even if soft-deleted (admin only, ignored otherwise)")
+    ),
+    responses(
+        (status_code = 200, description = "Invoice found.", body = InvoiceDto),
+        (status_code = 400, description = "INVALID_ID: the ID is not a valid ObjectId."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 404, description = "INVOICE_NOT_FOUND: no invoice with this ID."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_invoice(id: PathParam<String>, include_deleted: QueryParam<bool, false>, depot: &mut Depot) -> Res<InvoiceDto> {
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+    let include_deleted = admin_include_deleted(include_deleted.into_inner(), depot);
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    match invoice_service.get_by_id(invoice_id, include_deleted).await {
+        Ok(invoice) => Ok(res_json_ok(Some(InvoiceDto::from(&invoice)))),
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to get invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 修改未上链票据 (出票企业或平台管理员)
+///
+/// 按请求中的 `version` 做乐观锁更新，票据在读取后被修改过 (包括状态变更) 时返回 409 `STALE_WRITE`。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    request_body = UpdateInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Invoice updated, with the new version.", body = InvoiceDto),
+        (status_code = 400, description = "Invalid ID or field values."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Only the payee or an admin can edit the invoice."),
         (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "STALE_WRITE: version mismatch, or the invoice is no longer pending."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn delete_invoice(invoice_number: QueryParam<String>, depot: &mut Depot) -> Res<()> {
+pub async fn update_invoice(id: PathParam<String>, req: JsonBody<UpdateInvoiceRequest>, user: AuthedUser, depot: &mut Depot) -> Res<InvoiceDto> {
+    let oid = parse_object_id(&id.into_inner(), depot)?;
+    let req = req.into_inner();
+    // 与创建票据相同的金额上限、币种与到期日校验
+    let limits = InvoiceLimits { max_amount: CFG.invoice.max_amount, supported_currencies: CFG.invoice.supported_currencies.clone() };
+    let edit = InvoiceEdit { amount: req.amount, currency: req.currency, due_date: req.due_date };
+    let edit = match validate_invoice_edit(edit, &limits, Utc::now().timestamp_millis()) {
+        Ok(edit) => edit,
+        Err(e) => {
+            tracing::warn!("Rejected update of invoice {} by {}: {}", oid, user.address, e);
+            return Err(match invoice_validation_code(&e) {
+                Some(code) => ApiError::new(code).to_json(depot),
+                None => ApiError::new(ErrorCode::BadRequest).with_detail(e.to_string()).to_json(depot),
+            });
+        }
+    };
+
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let repo = InvoiceRepository::new(&mongodb);
+    let invoice = match repo.find_by_id(oid).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", oid, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+    if !user.is_admin() && !invoice.payee.eq_ignore_ascii_case(&user.address) {
+        return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot));
+    }
+    // 已上链的票据要与链上数据保持一致，不允许修改
+    if invoice.status != InvoiceStatus::Pending {
+        return Err(ApiError::new(ErrorCode::InvoiceNotEditable).to_json(depot));
+    }
 
+    let data = UpdateInvoiceData {
+        amount: edit.amount,
+        currency: edit.currency,
+        due_date: edit.due_date,
+        invoice_ipfs_hash: req.invoice_ipfs_hash,
+        ..Default::default()
+    };
+    match repo.update(oid, req.version, data, &user.address).await {
+        Ok(updated) => {
+            tracing::info!("User {} updated invoice {} to version {}", user.address, oid, updated.version);
+            Ok(res_json_ok(Some(InvoiceDto::from(&updated))))
+        }
+        Err(ServiceError::StaleWrite { expected, actual }) => {
+            tracing::warn!("Rejected stale update of invoice {} by {}: expected version {}, current {}", oid, user.address, expected, actual);
+            Err(ApiError::new(ErrorCode::StaleWrite).to_json(depot))
+        }
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to update invoice {}: {}", oid, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// `include_deleted` 仅对管理员生效，其他用户 (含未登录) 传入时忽略
+fn admin_include_deleted(requested: Option<bool>, depot: &Depot) -> bool {
+    requested.unwrap_or(false) && AuthedUser::from_depot(depot).is_ok_and(|user| user.is_admin())
+}
+
+/// 删除票据 (软删除)
+///
+/// 写入删除时间后票据不再出现在默认查询中，也不能再认购或兑付；管理员可通过 `include_deleted=true` 查看。
+/// 只有出票企业或管理员可以删除，且只能删除尚未认购的待审核票据。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 500),
+    parameters(
+        ("id" = String, Query, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Invoice deleted successfully."),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Neither the payee of the invoice nor an admin."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Invoice is no longer pending or already has funding."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn delete_invoice(invoice_number: QueryParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<()> {
     let oid = match ObjectId::parse_str(&invoice_number.into_inner()) {
         Ok(oid) => oid,
-        Err(_) => return Err(res_bad_request("Invalid ObjectId format")),
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
     };
 
-    match repo.delete(oid).await {
-        Ok(delete_result) => {
-            if delete_result.deleted_count == 0 {
-                Err(res_not_found("Invoice not found"))
-            } else {
-                Ok(res_json_ok(None))
-            }
-        }
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    match invoice_service.soft_delete(oid, &user.address, user.is_admin()).await {
+        Ok(()) => Ok(res_json_ok(None)),
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(ServiceError::Forbidden(_)) => Err(ApiError::new(ErrorCode::Forbidden).to_json(depot)),
+        Err(ServiceError::InvoiceNotDeletable(_)) => Err(ApiError::new(ErrorCode::InvoiceNotDeletable).to_json(depot)),
         Err(e) => {
-            log::error!("Failed to delete invoice: {}", e);
-            Err(res_json_err("Failed to delete invoice"))
+            tracing::error!("Failed to delete invoice: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -161,18 +596,12 @@ pub async fn delete_invoice(invoice_number: QueryParam<String>, depot: &mut Depo
         (status_code = 500, description = "Internal server error or blockchain query failed."),
     )
 )]
-pub async fn query_my_invoice(depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
+pub async fn query_my_invoice(user: AuthedUser, depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let repo = InvoiceRepository::new(&mongodb);
 
     // 2. Retrieve the authenticated user's address from the Depot using obtain
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+    let user_address = user.address.as_str();
 
     // Use the retrieved user_address to query invoices
     let res = repo.find_by_user(user_address).await;
@@ -184,8 +613,8 @@ pub async fn query_my_invoice(depot: &mut Depot) -> Res<Vec<InvoiceDto>> {
         }
         Err(e) => {
             // Log the specific database error but return a generic message to the user
-            log::error!("Failed to query my invoice for user {}: {}", user_address, e);
-            Err(res_json_err("Failed to query my invoice"))
+            tracing::error!("Failed to query my invoice for user {}: {}", user_address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -213,19 +642,19 @@ pub async fn query_invoice_data(invoice_number: QueryParam<String>, depot: &mut
     // 1. Query the database first
     match repo.find_by_invoice_number(&invoice_number_query).await {
         Ok(Some(db_invoice)) => {
-            log::info!("Found invoice {} in database.", invoice_number_query);
+            tracing::info!("Found invoice {} in database.", invoice_number_query);
             // Convert to DTO and return as Vec
             let data = vec![InvoiceDto::from(&db_invoice)];
             Ok(res_json_ok(Some(data)))
         }
         Ok(None) => {
-            log::info!("Invoice {} not found in database. Querying blockchain...", invoice_number_query);
+            tracing::info!("Invoice {} not found in database. Querying blockchain...", invoice_number_query);
             // 2. If not in DB, query the blockchain
             query_and_save_from_blockchain(&invoice_number_query, depot, &repo).await
         }
         Err(e) => {
-            log::error!("Database query failed for invoice {}: {}", invoice_number_query, e);
-            Err(res_json_err("Database query failed"))
+            tracing::error!("Database query failed for invoice {}: {}", invoice_number_query, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -233,6 +662,7 @@ pub async fn query_invoice_data(invoice_number: QueryParam<String>, depot: &mut
 /// 查询持仓利息明细
 #[salvo::oapi::endpoint(
     tags("票据"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 401, 404, 500),
     parameters(
         ("holding_id" = String, Query, description = "Holding ID to get interest details for")
@@ -245,28 +675,22 @@ pub async fn query_invoice_data(invoice_number: QueryParam<String>, depot: &mut
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn get_holding_interest_details(holding_id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<InterestDetailDto>> {
+pub async fn get_holding_interest_details(holding_id: QueryParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<InterestDetailDto>> {
     // Get the authenticated user address
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            log::error!("Authenticated user address not found in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+    let user_address = user.address.as_str();
 
     // Get the Invoice Service
     let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
 
     let holding_id_str = holding_id.into_inner();
 
-    log::info!("Fetching interest details for holding {} owned by user {}", holding_id_str, user_address);
+    tracing::info!("Fetching interest details for holding {} owned by user {}", holding_id_str, user_address);
 
     match invoice_service.get_holding_interest_details(user_address, &holding_id_str).await {
         Ok(details) => Ok(res_json_ok(Some(details))),
         Err(e) => {
-            log::error!("Failed to get interest details for holding {}: {}", holding_id_str, e);
-            Err(res_json_err(&format!("Failed to retrieve interest details: {}", e)))
+            tracing::error!("Failed to get interest details for holding {}: {}", holding_id_str, e);
+            Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
         }
     }
 }
@@ -274,7 +698,8 @@ pub async fn get_holding_interest_details(holding_id: QueryParam<String>, depot:
 /// 管理员触发每日利息计算
 #[salvo::oapi::endpoint(
     tags("管理员"),
-    status_codes(200, 400, 401, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 500),
     parameters(
         ("date" = String, Query, description = "Date to calculate interest for (YYYY-MM-DD)")
     ),
@@ -282,11 +707,12 @@ pub async fn get_holding_interest_details(holding_id: QueryParam<String>, depot:
         (status_code = 200, description = "Interest calculation triggered successfully.", body = u32),
         (status_code = 400, description = "Invalid date format."),
         (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
 pub async fn trigger_daily_interest_calculation(date: QueryParam<String>, depot: &mut Depot) -> Res<u32> {
-    // TODO: Add admin authentication check
+    // 管理员权限由 admin 路由上的 RequireRole 保证
 
     let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
 
@@ -295,21 +721,21 @@ pub async fn trigger_daily_interest_calculation(date: QueryParam<String>, depot:
     let calculation_date = match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
         Ok(date) => date,
         Err(e) => {
-            log::error!("Invalid date format: {}", e);
-            return Err(res_bad_request("Invalid date format. Please use YYYY-MM-DD."));
+            tracing::error!("Invalid date format: {}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid date format. Please use YYYY-MM-DD.").to_json(depot));
         }
     };
 
-    log::info!("Triggering daily interest calculation for date: {}", calculation_date);
+    tracing::info!("Triggering daily interest calculation for date: {}", calculation_date);
 
     match invoice_service.calculate_daily_interest_for_date(calculation_date).await {
         Ok(count) => {
-            log::info!("Successfully calculated interest for {} holdings", count);
+            tracing::info!("Successfully calculated interest for {} holdings", count);
             Ok(res_json_ok(Some(count)))
         }
         Err(e) => {
-            log::error!("Failed to calculate daily interest: {}", e);
-            Err(res_json_err(&format!("Failed to calculate daily interest: {}", e)))
+            tracing::error!("Failed to calculate daily interest: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -317,19 +743,33 @@ pub async fn trigger_daily_interest_calculation(date: QueryParam<String>, depot:
 /// 管理员触发到期票据还款处理
 #[salvo::oapi::endpoint(
     tags("管理员"),
-    status_codes(200, 400, 401, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 500),
     parameters(
-        ("date" = String, Query, description = "Date to process maturity payments for (YYYY-MM-DD)")
+        ("date" = String, Query, description = "Date to process maturity payments for (YYYY-MM-DD)"),
+        ("early_override" = Option<bool>, Query, description = "Admin override to settle before maturity (recorded in audit log)")
     ),
     responses(
-        (status_code = 200, description = "Maturity payment processing triggered successfully.", body = u32),
+        (status_code = 200, description = "Per-invoice maturity payment results; invoices not yet matured are reported as not_matured.", body = BatchSettlementDto),
         (status_code = 400, description = "Invalid date format."),
         (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn trigger_maturity_payments(date: QueryParam<String>, depot: &mut Depot) -> Res<u32> {
-    // TODO: Add admin authentication check
+pub async fn trigger_maturity_payments(date: QueryParam<String>, early_override: QueryParam<bool, false>, depot: &mut Depot) -> Res<BatchSettlementDto> {
+    // 管理员权限由 admin 路由上的 RequireRole 保证
+
+    // 提前兑付必须由管理员显式发起，操作人会写入审计日志
+    let early_override_by = if early_override.into_inner().unwrap_or(false) {
+        Some(admin_controller::require_admin(depot)?.sub.clone())
+    } else {
+        None
+    };
+    let options = SettlementOptions {
+        early_window_secs: CFG.settlement.early_window_secs,
+        early_override_by,
+    };
 
     let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
 
@@ -338,21 +778,202 @@ pub async fn trigger_maturity_payments(date: QueryParam<String>, depot: &mut Dep
     let payment_date = match NaiveDate::parse_from_str(&date_str, "%Y-%m-%d") {
         Ok(date) => date,
         Err(e) => {
-            log::error!("Invalid date format: {}", e);
-            return Err(res_bad_request("Invalid date format. Please use YYYY-MM-DD."));
+            tracing::error!("Invalid date format: {}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid date format. Please use YYYY-MM-DD.").to_json(depot));
         }
     };
 
-    log::info!("Triggering maturity payments for date: {}", payment_date);
+    tracing::info!("Triggering maturity payments for date: {}", payment_date);
 
-    match invoice_service.process_maturity_payments_for_date(payment_date).await {
-        Ok(count) => {
-            log::info!("Successfully processed maturity payments for {} holdings", count);
-            Ok(res_json_ok(Some(count)))
+    match invoice_service.process_maturity_payments_for_date(payment_date, &options).await {
+        Ok(summary) => {
+            tracing::info!("Processed maturity payments: {} settled, {} not matured, {} failed", summary.settled, summary.not_matured, summary.failed);
+            Ok(res_json_ok(Some(summary)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to process maturity payments: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 管理员批量兑付所有已到期、已募满且未结算的票据
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 500),
+    responses(
+        (status_code = 200, description = "Per-invoice settlement results.", body = BatchSettlementDto),
+        (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn settle_matured_batch(depot: &mut Depot) -> Res<BatchSettlementDto> {
+    let admin = admin_controller::require_admin(depot)?.sub.clone();
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+
+    match invoice_service.settle_matured_invoices(&admin, CFG.settlement.early_window_secs, CFG.settlement.batch_concurrency).await {
+        Ok(summary) => {
+            tracing::info!(
+                "Batch settlement by {}: {} eligible, {} settled, {} failed",
+                admin, summary.eligible, summary.settled, summary.failed
+            );
+            Ok(res_json_ok(Some(summary)))
+        }
+        Err(e) => {
+            tracing::error!("Batch settlement failed: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+// 管理员批量变更票据状态请求参数
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[salvo(schema(example = json!({ "invoice_ids": ["64b000000000000000000001", "64b000000000000000000002"], "to": "VERIFIED" })))]
+pub struct BulkTransitionRequest {
+    pub invoice_ids: Vec<String>,
+    /// 目标状态，仅支持 VERIFIED、OVERDUE、DEFAULTED
+    pub to: InvoiceStatus,
+}
+
+/// 管理员批量变更票据状态：逐张按状态机校验，每张单独提交并写入审计记录，单张失败不影响其余票据
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403),
+    request_body = BulkTransitionRequest,
+    responses(
+        (status_code = 200, description = "Per-invoice results in request order; illegal transitions are reported as INVALID_STATUS_TRANSITION.", body = BatchTransitionDto),
+        (status_code = 400, description = "Empty batch, unsupported target status, or INVOICE_BATCH_TOO_LARGE."),
+        (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
+    )
+)]
+pub async fn bulk_transition_invoices(req: JsonBody<BulkTransitionRequest>, depot: &mut Depot) -> Res<BatchTransitionDto> {
+    let admin = admin_controller::require_admin(depot)?.sub.clone();
+    let req = req.into_inner();
+    if req.invoice_ids.is_empty() {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Batch must contain at least one invoice").to_json(depot));
+    }
+    if req.invoice_ids.len() > CFG.invoice.max_batch_size {
+        return Err(ApiError::new(ErrorCode::InvoiceBatchTooLarge).to_json(depot));
+    }
+    if !is_bulk_transition_target(req.to) {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail(format!("Invoices cannot be bulk transitioned to {:?}", req.to)).to_json(depot));
+    }
+
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    let summary = invoice_service.transition_invoices(req.invoice_ids, req.to, &admin).await;
+    if summary.failed > 0 {
+        warn!("Bulk transition to {:?} by {}: {} of {} failed", summary.to, admin, summary.failed, summary.total);
+    }
+    Ok(res_json_ok(Some(summary)))
+}
+
+/// 管理员核对票据数据库记录与链上数据 (金额、所有人、状态)，返回不一致的字段
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 502, 503, 500),
+    parameters(("id" = String, Path, description = "Invoice ID")),
+    responses(
+        (status_code = 200, description = "Reconciliation result with per-field diffs.", body = InvoiceReconciliationDto),
+        (status_code = 400, description = "Invalid invoice ID."),
+        (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 502, description = "Contract query failed."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn reconcile_invoice(id: PathParam<String>, depot: &mut Depot) -> Res<InvoiceReconciliationDto> {
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+    let contract = match depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    match invoice_service.reconcile(invoice_id, contract.as_ref()).await {
+        Ok(result) => Ok(res_json_ok(Some(result))),
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(ServiceError::ChainRpcError(msg)) => {
+            tracing::error!("Failed to reconcile invoice {}: {}", invoice_id, msg);
+            Err(ApiError::new(ErrorCode::ContractQueryFailed).to_json(depot))
         }
         Err(e) => {
-            log::error!("Failed to process maturity payments: {}", e);
-            Err(res_json_err(&format!("Failed to process maturity payments: {}", e)))
+            tracing::error!("Failed to reconcile invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+// 管理员作废票据请求参数
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[salvo(schema(example = json!({ "reason": "duplicate invoice entered in error" })))]
+pub struct CancelInvoiceRequest {
+    /// 作废原因，写入票据审计记录
+    pub reason: String,
+}
+
+/// 管理员强制作废票据：按代币持仓比例退回投资人认购资金，链上作废并标记为已作废
+///
+/// 已兑付的票据返回 409；作废原因与退款交易写入审计记录。
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 500, 502, 503),
+    parameters(("id" = String, Path, description = "Invoice MongoDB ObjectId")),
+    request_body = CancelInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Invoice cancelled; per-holder refunds.", body = InvoiceCancellationDto),
+        (status_code = 400, description = "Invalid invoice ID or missing reason."),
+        (status_code = 401, description = "Not authorized."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Invoice already repaid, already cancelled, a purchase is in progress, or the refund was rejected by the contract."),
+        (status_code = 500, description = "Internal server error."),
+        (status_code = 502, description = "On-chain refund failed."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn cancel_invoice(id: PathParam<String>, req: JsonBody<CancelInvoiceRequest>, admin: AuthedUser, depot: &mut Depot) -> Res<InvoiceCancellationDto> {
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+    let reason = req.reason.trim();
+    if reason.is_empty() {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Cancellation reason is required").to_json(depot));
+    }
+
+    let contract = match depot.obtain::<SharedContractWriter>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+
+    let purchase_service = depot.obtain::<Arc<PurchaseService>>().expect("PurchaseService not found in depot");
+    match purchase_service.cancel_invoice(invoice_id, reason, &admin.address, contract.as_ref(), CFG.settlement.payout_decimals).await {
+        Ok(cancellation) => {
+            tracing::info!("Invoice {} cancelled by admin {}: {}", cancellation.invoice_number, admin.address, reason);
+            Ok(res_json_ok(Some(cancellation)))
+        }
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(
+            e @ (ServiceError::InvoiceAlreadySettled(_)
+            | ServiceError::RefundNotAllowed(_)
+            | ServiceError::InvalidStatusTransition { .. }
+            | ServiceError::PurchaseInProgress(_)),
+        ) => Err(ApiError::from(&e).to_json(depot)),
+        Err(ServiceError::ChainRpcError(msg)) => {
+            error!("On-chain refund for invoice {} failed: {}", invoice_id, msg);
+            match chain_error_code(&msg) {
+                Some(code) => Err(ApiError::new(code).to_json(depot)),
+                None => Err(ApiError::new(ErrorCode::ChainTransactionFailed).to_json(depot)),
+            }
+        }
+        Err(e) => {
+            error!("Failed to cancel invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -363,9 +984,9 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
     let contract_opt = depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>();
 
     if contract_opt.is_err() {
-        log::warn!("Blockchain contract connection not available.");
+        tracing::warn!("Blockchain contract connection not available.");
         // Return Not Found as we couldn't check the canonical source
-        return Err(res_not_found("Invoice not found and blockchain connection unavailable"));
+        return Err(ApiError::new(ErrorCode::NotFound).with_detail("Invoice not found and blockchain connection unavailable").to_json(depot));
     }
     let contract = contract_opt.unwrap();
 
@@ -382,16 +1003,16 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
         params.invoice_number = None;
     }
 
-    log::info!("Querying blockchain for invoice number: {} with params: {:?}", invoice_number, params);
+    tracing::info!("Querying blockchain for invoice number: {} with params: {:?}", invoice_number, params);
 
     // Call the blockchain contract
     match contract.query_invoices(params).await {
         Ok(blockchain_invoices_data) => {
             if blockchain_invoices_data.is_empty() {
-                log::warn!("Invoice {} not found on blockchain.", invoice_number);
-                Err(res_not_found("Invoice not found on blockchain"))
+                tracing::warn!("Invoice {} not found on blockchain.", invoice_number);
+                Err(ApiError::new(ErrorCode::NotFound).with_detail("Invoice not found on blockchain").to_json(depot))
             } else {
-                log::info!(
+                tracing::info!(
                     "Found {} invoice(s) on blockchain for number {}. Saving to DB...",
                     blockchain_invoices_data.len(),
                     invoice_number
@@ -404,7 +1025,7 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
                     match repo.find_by_invoice_number(&invoice_data_dto.invoice_number.clone()).await {
                         // 查找成功，并且找到了票据
                         Ok(Some(existing_invoice)) => {
-                            log::info!(
+                            tracing::info!(
                                 "Invoice {} already exists in DB with id {:?}, skipping creation.",
                                 existing_invoice.invoice_number,
                                 existing_invoice.id
@@ -415,7 +1036,7 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
                         // 查找成功，但没有找到票据
                         Ok(None) => {
                             // 票据不存在，可以安全地尝试创建
-                            log::debug!("Invoice {:?} not found in DB, attempting to create from blockchain data.", invoice_data_dto);
+                            tracing::debug!("Invoice {:?} not found in DB, attempting to create from blockchain data.", invoice_data_dto);
 
                             let create_dto = CreateInvoiceDto {
                                 payee: invoice_data_dto.payee.clone(),
@@ -426,20 +1047,20 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
                                 due_date: invoice_data_dto.due_date.clone(),
                                 currency: invoice_data_dto.currency.clone(),
                             };
-                            match repo.create_from_blockchain(&create_dto).await {
+                            match repo.create_from_blockchain(&create_dto, SYSTEM_ACTOR).await {
                                 Ok(saved_invoice) => {
-                                    log::info!("Successfully saved new invoice {} from blockchain to DB.", saved_invoice.invoice_number);
+                                    tracing::info!("Successfully saved new invoice {} from blockchain to DB.", saved_invoice.invoice_number);
                                     saved_invoice_dtos.push(InvoiceDto::from(&saved_invoice));
                                 }
                                 Err(e) => {
                                     // 创建过程中发生错误
-                                    log::error!("Failed to save new invoice {:?} from blockchain to DB: {}", invoice_data_dto, e);
+                                    tracing::error!("Failed to save new invoice {:?} from blockchain to DB: {}", invoice_data_dto, e);
                                 }
                             }
                         }
                         // 查找过程中发生错误
                         Err(e) => {
-                            log::error!("Failed to check existence for invoice {:?}: {}. Skipping creation.", invoice_data_dto, e);
+                            tracing::error!("Failed to check existence for invoice {:?}: {}. Skipping creation.", invoice_data_dto, e);
                             // 如果查找失败，也跳过创建，避免潜在的重复
                         }
                     }
@@ -447,7 +1068,7 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
 
                 if saved_invoice_dtos.is_empty() {
                     // This case might happen if all saves failed
-                    Err(res_json_err("Found invoice on chain but failed to save to database"))
+                    Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
                 } else {
                     // Return the successfully saved invoices as DTOs
                     Ok(res_json_ok(Some(saved_invoice_dtos)))
@@ -455,8 +1076,8 @@ async fn query_and_save_from_blockchain(invoice_number: &str, depot: &mut Depot,
             }
         }
         Err(e) => {
-            log::error!("Failed to query blockchain for invoice {}: {}", invoice_number, e);
-            Err(res_json_err(&format!("Blockchain query failed: {}", e)))
+            tracing::error!("Failed to query blockchain for invoice {}: {}", invoice_number, e);
+            Err(ApiError::new(ErrorCode::ContractQueryFailed).to_json(depot))
         }
     }
 }
@@ -476,31 +1097,27 @@ struct IssueInvoicesParams {
 /// 票据上链(将票据状态更新为已上链)
 #[salvo::oapi::endpoint(
     tags("票据"),
-    status_codes(200, 400, 404, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 404, 409, 500),
     request_body = VerifyInvoiceParams,
     responses(
         (status_code = 200, description = "Invoice verified successfully.", body = InvoiceDto),
         (status_code = 400, description = "Invalid invoice ID."),
         (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Invoice status does not allow verification."),
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn verify_invoice(req: &mut Request, depot: &mut Depot) -> Res<InvoiceDto> {
+pub async fn verify_invoice(req: &mut Request, user: AuthedUser, depot: &mut Depot) -> Res<InvoiceDto> {
     // 1. 获取已认证用户的地址（由auth_token中间件插入）
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+    let user_address = user.address.as_str();
 
     // 2. 解析请求参数
     let params = match req.parse_json::<VerifyInvoiceParams>().await {
         Ok(p) => p,
         Err(e) => {
-            log::error!("Failed to parse verify invoice parameters: {:?}", e);
-            return Err(res_bad_request("Invalid request parameters"));
+            tracing::error!("Failed to parse verify invoice parameters: {:?}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid request parameters").to_json(depot));
         }
     };
 
@@ -510,20 +1127,20 @@ pub async fn verify_invoice(req: &mut Request, depot: &mut Depot) -> Res<Invoice
     let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
 
     // 4. 调用服务将票据状态更新为已上链
-    match invoice_service.verify_invoice(&params.id).await {
+    match invoice_service.verify_invoice(&params.id, user_address).await {
         Ok(invoice) => {
-            log::info!("Successfully verified invoice {} by user {}", params.id, user_address);
+            tracing::info!("Successfully verified invoice {} by user {}", params.id, user_address);
             // 返回成功响应
             let dto = InvoiceDto::from(&invoice);
             Ok(res_json_ok(Some(dto)))
         }
         Err(e) => {
-            log::error!("Failed to verify invoice {}: {}", params.id, e);
+            tracing::error!("Failed to verify invoice {}: {}", params.id, e);
             match e {
-                ServiceError::NotFound(_) => Err(res_not_found(&format!("Invoice not found: {}", params.id))),
-                ServiceError::InternalError(msg) => Err(res_bad_request(&msg)),
-                ServiceError::MongoDbError(msg) => Err(res_json_err(&format!("Database error: {}", msg))),
-                _ => Err(res_json_err(&format!("Failed to verify invoice: {}", e)))
+                ServiceError::NotFound(_) => Err(ApiError::new(ErrorCode::NotFound).with_detail(format!("Invoice not found: {}", params.id)).to_json(depot)),
+                ServiceError::InvalidStatusTransition { .. } => Err(ApiError::new(ErrorCode::InvalidStatusTransition).to_json(depot)),
+                ServiceError::InternalError(msg) => Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+                _ => Err(ApiError::from(&e).to_json(depot))
             }
         }
     }
@@ -532,6 +1149,7 @@ pub async fn verify_invoice(req: &mut Request, depot: &mut Depot) -> Res<Invoice
 /// 票据批量发行到市场(将票据状态从已上链更新为在售)
 #[salvo::oapi::endpoint(
     tags("票据"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 500),
     request_body = IssueInvoicesParams,
     responses(
@@ -540,28 +1158,22 @@ pub async fn verify_invoice(req: &mut Request, depot: &mut Depot) -> Res<Invoice
         (status_code = 500, description = "Internal server error."),
     )
 )]
-pub async fn issue_invoices(req: &mut Request, depot: &mut Depot) -> Res<String> {
+pub async fn issue_invoices(req: &mut Request, user: AuthedUser, depot: &mut Depot) -> Res<String> {
     // 1. 获取已认证用户的地址（由auth_token中间件插入）
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+    let user_address = user.address.as_str();
 
     // 2. 解析请求参数
     let params = match req.parse_json::<IssueInvoicesParams>().await {
         Ok(p) => p,
         Err(e) => {
-            log::error!("Failed to parse issue invoices parameters: {:?}", e);
-            return Err(res_bad_request("Invalid request parameters"));
+            tracing::error!("Failed to parse issue invoices parameters: {:?}", e);
+            return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Invalid request parameters").to_json(depot));
         }
     };
 
     // 验证参数有效性
     if params.invoice_ids.is_empty() {
-        return Err(res_bad_request("No invoices selected for issuance"));
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("No invoices selected for issuance").to_json(depot));
     }
 
     // 3. 获取依赖
@@ -570,25 +1182,25 @@ pub async fn issue_invoices(req: &mut Request, depot: &mut Depot) -> Res<String>
     let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
 
     // 4. 调用服务批量更新票据状态为在售(OnSale)
-    match invoice_service.issue_invoices(&params.invoice_ids).await {
+    match invoice_service.issue_invoices(&params.invoice_ids, user_address).await {
         Ok(count) => {
-            log::info!("Successfully issued {} invoices by user {}", count, user_address);
+            tracing::info!("Successfully issued {} invoices by user {}", count, user_address);
             let message = format!("Successfully issued {} invoices", count);
             Ok(res_json_ok(Some(message)))
         }
         Err(e) => {
-            log::error!("Failed to issue invoices: {}", e);
+            tracing::error!("Failed to issue invoices: {}", e);
             // 区分验证错误和其他错误
             match e {
                 ServiceError::InvoiceNotIssue(msg) if msg.contains("same payee, payer and currency") => {
                     // 这是一个一致性验证错误
-                    Err(res_json_err(&format!("Consistency check failed: {}", msg)))
+                    Err(ApiError::new(ErrorCode::BadRequest).with_detail(format!("Consistency check failed: {}", msg)).to_json(depot))
                 }
                 ServiceError::InternalError(msg) if msg.contains("No valid verified invoices") => {
                     // 没有找到有效的已上链票据
-                    Err(res_bad_request("No valid verified invoices found"))
+                    Err(ApiError::new(ErrorCode::BadRequest).with_detail("No valid verified invoices found").to_json(depot))
                 }
-                _ => Err(res_json_err(&format!("Failed to issue invoices: {}", e)))
+                _ => Err(ApiError::from(&e).to_json(depot))
             }
         }
     }
@@ -597,6 +1209,7 @@ pub async fn issue_invoices(req: &mut Request, depot: &mut Depot) -> Res<String>
 /// 获取当前用户的发票批次列表
 #[salvo::oapi::endpoint(
     tags("发票批次"),
+    security(("bearerAuth" = [])),
     status_codes(200, 401, 500),
     responses(
         (status_code = 200, description = "用户发票批次列表", body = Vec<InvoiceBatchDto>),
@@ -604,19 +1217,16 @@ pub async fn issue_invoices(req: &mut Request, depot: &mut Depot) -> Res<String>
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_user_invoice_batches(depot: &mut Depot) -> Res<Vec<InvoiceBatchDto>> {
+pub async fn list_user_invoice_batches(user: AuthedUser, depot: &mut Depot) -> Res<Vec<InvoiceBatchDto>> {
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let invoice_batch_repo = InvoiceBatchRepository::new(&mongodb);
     let enterprise_repo = EnterpriseRepository::new(&mongodb);
     
     // 获取认证用户信息
-    let user_id = match depot.get::<String>("user_id") {
-        Ok(id) => id,
-        Err(_) => {
-            error!("User ID not found in depot");
-            return Err(res_json_custom(401, "用户未认证"));
-        }
-    };
+    if user.user_id.is_none() {
+        error!("User ID not found in depot");
+        return Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot));
+    }
     
     // 获取用户绑定的企业ID
     let enterprise_id = match depot.get::<String>("enterprise_id") {
@@ -625,13 +1235,13 @@ pub async fn list_user_invoice_batches(depot: &mut Depot) -> Res<Vec<InvoiceBatc
                 Ok(oid) => oid,
                 Err(_) => {
                     error!("Invalid enterprise ID format");
-                    return Err(res_json_err("企业ID格式无效"));
+                    return Err(ApiError::new(ErrorCode::EnterpriseMissingId).to_json(depot));
                 }
             }
         },
         Err(_) => {
             error!("Enterprise ID not found in depot");
-            return Err(res_json_custom(403, "用户未绑定企业"));
+            return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot));
         }
     };
     
@@ -692,7 +1302,7 @@ pub async fn list_user_invoice_batches(depot: &mut Depot) -> Res<Vec<InvoiceBatc
         },
         Err(e) => {
             error!("Failed to get user invoice batches: {}", e);
-            Err(res_json_err(&format!("获取发票批次列表失败: {}", e)))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -700,6 +1310,7 @@ pub async fn list_user_invoice_batches(depot: &mut Depot) -> Res<Vec<InvoiceBatc
 /// 获取发票批次详情
 #[salvo::oapi::endpoint(
     tags("发票批次"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 404, 500),
     parameters(
         ("id" = String, Path, description = "批次ID")
@@ -721,7 +1332,7 @@ pub async fn get_invoice_batch_by_id(id: PathParam<String>, depot: &mut Depot) -
         Ok(oid) => oid,
         Err(_) => {
             error!("Invalid batch ID format");
-            return Err(res_bad_request("批次ID格式无效"));
+            return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot));
         }
     };
     
@@ -733,7 +1344,7 @@ pub async fn get_invoice_batch_by_id(id: PathParam<String>, depot: &mut Depot) -
                 Some(id) => id,
                 None => {
                     error!("Batch ID is missing");
-                    return Err(res_json_err("批次ID缺失"));
+                    return Err(ApiError::new(ErrorCode::InternalError).to_json(depot));
                 }
             };
             
@@ -742,7 +1353,7 @@ pub async fn get_invoice_batch_by_id(id: PathParam<String>, depot: &mut Depot) -
                 Ok(Some(e)) => e,
                 _ => {
                     error!("Creditor not found for batch {}", batch_id);
-                    return Err(res_json_err("找不到批次的债权人信息"));
+                    return Err(ApiError::new(ErrorCode::InternalError).to_json(depot));
                 }
             };
             
@@ -750,7 +1361,7 @@ pub async fn get_invoice_batch_by_id(id: PathParam<String>, depot: &mut Depot) -
                 Ok(Some(e)) => e,
                 _ => {
                     error!("Debtor not found for batch {}", batch_id);
-                    return Err(res_json_err("找不到批次的债务人信息"));
+                    return Err(ApiError::new(ErrorCode::InternalError).to_json(depot));
                 }
             };
             
@@ -785,11 +1396,11 @@ pub async fn get_invoice_batch_by_id(id: PathParam<String>, depot: &mut Depot) -
         },
         Ok(None) => {
             error!("Invoice batch not found: {}", batch_id);
-            Err(res_not_found("找不到指定批次"))
+            Err(ApiError::new(ErrorCode::NotFound).with_detail("找不到指定批次").to_json(depot))
         },
         Err(e) => {
             error!("Failed to get invoice batch: {}", e);
-            Err(res_json_err(&format!("获取批次详情失败: {}", e)))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -817,7 +1428,7 @@ pub async fn get_invoices_by_batch_id(id: PathParam<String>, depot: &mut Depot)
         Ok(oid) => oid,
         Err(_) => {
             error!("Invalid batch ID format");
-            return Err(res_bad_request("批次ID格式无效"));
+            return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot));
         }
     };
     
@@ -829,7 +1440,7 @@ pub async fn get_invoices_by_batch_id(id: PathParam<String>, depot: &mut Depot)
         },
         Err(e) => {
             error!("Failed to get invoices by batch: {}", e);
-            Err(res_json_err(&format!("获取批次发票列表失败: {}", e)))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -848,3 +1459,701 @@ pub struct InvoiceBatchDto {
     pub token_batch_id: Option<String>,
 }
 
+
+/// 上传票据附件 (multipart, 字段名 file)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 409, 413, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Document uploaded.", body = InvoiceDocumentDto),
+        (status_code = 400, description = "Invalid ID or missing file."),
+        (status_code = 403, description = "Not the payee of the invoice."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Maximum document count reached."),
+        (status_code = 413, description = "Cumulative document size limit exceeded."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn upload_invoice_document(id: PathParam<String>, req: &mut Request, user: AuthedUser, depot: &mut Depot) -> Res<InvoiceDocumentDto> {
+    let user_address = user.address;
+
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let document_repo = InvoiceDocumentRepository::new(&mongodb);
+
+    let invoice = match invoice_repo.find_by_id(invoice_id).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+    if !invoice.payee.eq_ignore_ascii_case(&user_address) {
+        return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot));
+    }
+
+    let file = match req.file("file").await {
+        Some(file) => file,
+        None => return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Missing file field").to_json(depot)),
+    };
+    let size = file.size();
+    let file_name = file.name().unwrap_or("document").to_string();
+    let content_type = file.content_type().map(|m| m.to_string());
+    let temp_path = file.path().clone();
+
+    // 1. 先原子地占用配额，再写文件，避免并发上传绕过限制
+    let cfg = &CFG.invoice;
+    match invoice_repo.reserve_document_quota(invoice_id, size, cfg.max_documents, cfg.max_documents_total_bytes).await {
+        Ok(true) => {}
+        Ok(false) => {
+            // 区分是数量超限还是大小超限
+            return if invoice.document_count >= cfg.max_documents {
+                Err(ApiError::new(ErrorCode::DocumentLimitReached).to_json(depot))
+            } else {
+                Err(ApiError::new(ErrorCode::PayloadTooLarge).with_detail(format!("Invoice documents would exceed the {} byte limit", cfg.max_documents_total_bytes)).to_json(depot))
+            };
+        }
+        Err(e) => {
+            tracing::error!("Failed to reserve document quota for invoice {}: {}", invoice_id, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    }
+
+    // 2. 保存文件
+    let document_oid = ObjectId::new();
+    let dir = std::path::Path::new(&cfg.document_dir).join(invoice_id.to_hex());
+    let storage_path = dir.join(document_oid.to_hex());
+    let saved = match tokio::fs::create_dir_all(&dir).await {
+        Ok(_) => tokio::fs::copy(&temp_path, &storage_path).await.map(|_| ()),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        tracing::error!("Failed to store document for invoice {}: {}", invoice_id, e);
+        let _ = invoice_repo.release_document_quota(invoice_id, size).await;
+        return Err(ApiError::new(ErrorCode::InternalError).to_json(depot));
+    }
+
+    // 3. 记录附件元数据
+    let document = InvoiceDocument {
+        id: Some(document_oid),
+        invoice_id,
+        file_name,
+        content_type,
+        size,
+        storage_path: storage_path.to_string_lossy().to_string(),
+        uploaded_by: user_address,
+        created_at: DateTime::now(),
+    };
+    match document_repo.create(&document).await {
+        Ok(created) => Ok(res_json_ok(Some(InvoiceDocumentDto::from(&created)))),
+        Err(e) => {
+            tracing::error!("Failed to save document record for invoice {}: {}", invoice_id, e);
+            let _ = invoice_repo.release_document_quota(invoice_id, size).await;
+            let _ = tokio::fs::remove_file(&storage_path).await;
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 删除票据附件 (释放配额)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId"),
+        ("doc_id" = String, Path, description = "Document MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Document deleted."),
+        (status_code = 400, description = "Invalid ID format."),
+        (status_code = 403, description = "Not the payee of the invoice."),
+        (status_code = 404, description = "Invoice or document not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn delete_invoice_document(id: PathParam<String>, doc_id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let user_address = user.address;
+
+    let (invoice_id, document_id) = match (ObjectId::parse_str(&id.into_inner()), ObjectId::parse_str(&doc_id.into_inner())) {
+        (Ok(invoice_id), Ok(document_id)) => (invoice_id, document_id),
+        _ => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
+    };
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let invoice_repo = InvoiceRepository::new(&mongodb);
+    let document_repo = InvoiceDocumentRepository::new(&mongodb);
+
+    match invoice_repo.find_by_id(invoice_id).await {
+        Ok(Some(invoice)) if invoice.payee.eq_ignore_ascii_case(&user_address) => {}
+        Ok(Some(_)) => return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot)),
+        Ok(None) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    }
+
+    let document = match document_repo.find_by_id_and_invoice(document_id, invoice_id).await {
+        Ok(Some(document)) => document,
+        Ok(None) => return Err(ApiError::new(ErrorCode::NotFound).with_detail("Document not found").to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load document {}: {}", document_id, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    match document_repo.delete(document_id).await {
+        // 只有真正删除了记录才释放配额，避免并发删除重复扣减
+        Ok(result) if result.deleted_count == 1 => {
+            if let Err(e) = invoice_repo.release_document_quota(invoice_id, document.size).await {
+                tracing::error!("Failed to release document quota for invoice {}: {}", invoice_id, e);
+            }
+            if let Err(e) = tokio::fs::remove_file(&document.storage_path).await {
+                tracing::warn!("Failed to remove document file {}: {}", document.storage_path, e);
+            }
+            Ok(res_json_ok(None))
+        }
+        Ok(_) => Err(ApiError::new(ErrorCode::NotFound).with_detail("Document not found").to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to delete document {}: {}", document_id, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+// 接受融资条款请求参数 (需与平台当前报价一致)
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[salvo(schema(example = json!({ "apr": "0.08", "platform_fee_rate": "0.01" })))]
+pub struct AcceptTermsRequest {
+    pub apr: String,
+    pub platform_fee_rate: String,
+}
+
+/// 企业接受融资条款 (接受后票据才能上架和被购买)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 403, 404, 409, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    request_body = AcceptTermsRequest,
+    responses(
+        (status_code = 200, description = "Terms accepted.", body = InvoiceDto),
+        (status_code = 400, description = "Invalid invoice ID."),
+        (status_code = 403, description = "Not the payee of the invoice."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Terms already accepted, invoice not in Verified state, or quote changed."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn accept_invoice_terms(id: PathParam<String>, req: JsonBody<AcceptTermsRequest>, user: AuthedUser, depot: &mut Depot) -> Res<InvoiceDto> {
+    let user_address = user.address;
+    let invoice_id = id.into_inner();
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+
+    match invoice_service
+        .accept_terms(&invoice_id, &user_address, &req.apr, &req.platform_fee_rate, &CFG.invoice.quoted_apr, &CFG.invoice.platform_fee_rate)
+        .await
+    {
+        Ok(invoice) => Ok(res_json_ok(Some(InvoiceDto::from(&invoice)))),
+        Err(e) => {
+            tracing::warn!("Failed to accept terms for invoice {}: {}", invoice_id, e);
+            match e {
+                ServiceError::InternalError(msg) => Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+                ServiceError::NotFound(msg) => Err(ApiError::new(ErrorCode::NotFound).with_detail(msg).to_json(depot)),
+                _ => Err(ApiError::from(&e).to_json(depot)),
+            }
+        }
+    }
+}
+
+/// 时间线未指定 limit 时返回的条数
+const TIMELINE_DEFAULT_LIMIT: u64 = 50;
+
+/// 获取票据活动时间线 (按时间升序，仅返回最近的记录)
+///
+/// 管理员与票据收付款方可看到全部记录；其他用户看不到审计记录，且其他投资人的地址被隐藏。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId"),
+        ("limit" = Option<i64>, Query, description = "Maximum number of most recent entries (default 50, capped per `pagination.overrides`)")
+    ),
+    responses(
+        (status_code = 200, description = "Invoice timeline.", body = Vec<TimelineEntryDto>),
+        (status_code = 400, description = "Invalid invoice ID."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_invoice_timeline(id: PathParam<String>, limit: QueryParam<i64, false>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<TimelineEntryDto>> {
+    let (user_address, is_admin) = (user.address.clone(), user.is_admin());
+
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+    let limit = pagination::page_size_or("invoice.timeline", limit.into_inner(), TIMELINE_DEFAULT_LIMIT) as usize;
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let timeline_service = InvoiceTimelineService::new(&mongodb);
+
+    let invoice = match timeline_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+    let viewer = TimelineViewer::resolve(is_admin, &user_address, &invoice);
+
+    match timeline_service.timeline(&invoice, &viewer, limit).await {
+        Ok(entries) => Ok(res_json_ok(Some(entries))),
+        Err(e) => {
+            tracing::error!("Failed to build timeline for invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 获取票据融资台账：按时间顺序列出每笔认购及累计金额 (仅出票企业和管理员)
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Funding ledger with running totals.", body = FundingLedgerDto),
+        (status_code = 400, description = "Invalid invoice ID."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Only the issuing enterprise or an admin can view the ledger."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_invoice_ledger(id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<FundingLedgerDto> {
+    let (user_address, is_admin) = (user.address.clone(), user.is_admin());
+
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let ledger_service = InvoiceLedgerService::new(&mongodb);
+
+    let invoice = match ledger_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+    match ledger_service.can_view(is_admin, &user_address, &invoice).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to check ledger access for {}: {}", user_address, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    }
+
+    match ledger_service.ledger(&invoice).await {
+        Ok(ledger) => Ok(res_json_ok(Some(ledger))),
+        Err(e) => {
+            tracing::error!("Failed to build funding ledger for invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 票据变更记录：谁在何时把票据从什么状态改为什么状态 (Requires authentication)
+///
+/// 与资金台账权限一致，仅出票企业和管理员可查看。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "Audit trail, oldest first.", body = Vec<InvoiceAuditDto>),
+        (status_code = 400, description = "Invalid invoice ID."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Only the issuing enterprise or an admin can view the history."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_invoice_history(id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<InvoiceAuditDto>> {
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let ledger_service = InvoiceLedgerService::new(&mongodb);
+    let invoice = match ledger_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+    match ledger_service.can_view(user.is_admin(), &user.address, &invoice).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to check history access for {}: {}", user.address, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    }
+
+    let invoice_service = depot.obtain::<Arc<InvoiceService>>().expect("InvoiceService not found in depot");
+    match invoice_service.invoice_history(invoice_id).await {
+        Ok(entries) => Ok(res_json_ok(Some(entries.iter().map(InvoiceAuditDto::from).collect()))),
+        Err(e) => {
+            tracing::error!("Failed to load history of invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// SSE 心跳间隔，代理不会因空闲断开连接，客户端断开后也能及时发现并释放订阅
+const INVOICE_EVENTS_KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// 以 SSE 方式推送票据状态变更 (出票企业或平台管理员)
+///
+/// 每次状态变更推送一条 `status` 事件，数据为 `InvoiceStatusEventDto`；连接建立前的变更不会补发，
+/// 客户端应先调用 `GET /invoice/{id}` 获取当前状态。客户端断开后服务端关闭对应的 Redis 订阅。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    responses(
+        (status_code = 200, description = "text/event-stream of `status` events (InvoiceStatusEventDto)."),
+        (status_code = 400, description = "INVALID_ID: the ID is not a valid ObjectId."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "FORBIDDEN: only the issuing enterprise or an admin can subscribe."),
+        (status_code = 404, description = "INVOICE_NOT_FOUND: no invoice with this ID."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn stream_invoice_events(id: PathParam<String>, user: AuthedUser, depot: &mut Depot, res: &mut Response) {
+    let invoice_id = match parse_object_id(&id.into_inner(), depot) {
+        Ok(invoice_id) => invoice_id,
+        Err(err) => return res.render(err),
+    };
+
+    // 与状态历史相同：只有平台管理员和出票企业可以订阅
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let ledger_service = InvoiceLedgerService::new(&mongodb);
+    let invoice = match ledger_service.find_invoice(invoice_id).await {
+        Ok(invoice) if !invoice.is_deleted() => invoice,
+        Ok(_) | Err(ServiceError::InvoiceNotFound(_)) => return res.render(ApiError::new(ErrorCode::InvoiceNotFound).to_json::<()>(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return res.render(ApiError::from(&e).to_json::<()>(depot));
+        }
+    };
+    match ledger_service.can_view(user.is_admin(), &user.address, &invoice).await {
+        Ok(true) => {}
+        Ok(false) => return res.render(ApiError::new(ErrorCode::Forbidden).to_json::<()>(depot)),
+        Err(e) => {
+            tracing::error!("Failed to check event access for {}: {}", user.address, e);
+            return res.render(ApiError::from(&e).to_json::<()>(depot));
+        }
+    }
+
+    let event_bus = depot.obtain::<Arc<InvoiceEventBus>>().expect("InvoiceEventBus not found in depot").clone();
+    let events = match event_bus.subscribe(&invoice_id.to_hex()).await {
+        Ok(events) => events,
+        Err(e) => {
+            tracing::error!("Failed to subscribe to events of invoice {}: {}", invoice_id, e);
+            return res.render(ApiError::from(&e).to_json::<()>(depot));
+        }
+    };
+    tracing::info!("{} subscribed to status events of invoice {}", user.address, invoice_id);
+
+    let events = events.map(|event| {
+        Ok::<_, Infallible>(SseEvent::default().name("status").text(serde_json::to_string(&event).unwrap_or_default()))
+    });
+    SseKeepAlive::new(events).max_interval(INVOICE_EVENTS_KEEP_ALIVE).stream(res);
+}
+
+// 企业还款请求参数
+#[derive(Deserialize, Serialize, Debug, ToSchema)]
+#[salvo(schema(example = json!({ "amount": "10250.50" })))]
+pub struct SettleInvoiceRequest {
+    /// 还款金额 (稳定币)，按代币持仓比例分配给持有人
+    pub amount: String,
+}
+
+/// 企业还款兑付：按代币持仓比例分配给持有人，并将票据标记为已兑付 (Requires creditor role)
+///
+/// 只有出票企业成员可以发起；已兑付的票据返回 409，不会重复分配。
+#[salvo::oapi::endpoint(
+    tags("票据"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 500, 502, 503),
+    parameters(
+        ("id" = String, Path, description = "Invoice MongoDB ObjectId")
+    ),
+    request_body = SettleInvoiceRequest,
+    responses(
+        (status_code = 200, description = "Repayment distributed; per-holder payouts.", body = RepaymentSettlementDto),
+        (status_code = 400, description = "Invalid invoice ID or repayment amount."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 403, description = "Creditor role required, or not a member of the issuing enterprise."),
+        (status_code = 404, description = "Invoice not found."),
+        (status_code = 409, description = "Invoice already repaid, not yet financed, settlement in progress, or the distribution was rejected by the contract."),
+        (status_code = 500, description = "Internal server error."),
+        (status_code = 502, description = "On-chain distribution failed."),
+        (status_code = 503, description = "Blockchain connection unavailable."),
+    )
+)]
+pub async fn settle_invoice(id: PathParam<String>, req: JsonBody<SettleInvoiceRequest>, user: AuthedUser, depot: &mut Depot) -> Res<RepaymentSettlementDto> {
+    let invoice_id = parse_object_id(&id.into_inner(), depot)?;
+
+    let contract = match depot.obtain::<SharedContractWriter>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+
+    // 与资金台账一致：只有出票企业成员可以为票据还款
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let ledger_service = InvoiceLedgerService::new(&mongodb);
+    let invoice = match ledger_service.find_invoice(invoice_id).await {
+        Ok(invoice) => invoice,
+        Err(ServiceError::InvoiceNotFound(_)) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to load invoice {}: {}", invoice_id, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+    match ledger_service.can_view(false, &user.address, &invoice).await {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot)),
+        Err(e) => {
+            tracing::error!("Failed to check settlement access for {}: {}", user.address, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    }
+
+    let purchase_service = depot.obtain::<Arc<PurchaseService>>().expect("PurchaseService not found in depot");
+    match purchase_service.settle_invoice(invoice_id, &req.amount, &user.address, contract.as_ref(), CFG.settlement.payout_decimals).await {
+        Ok(settlement) => Ok(res_json_ok(Some(settlement))),
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(ServiceError::InvalidRepaymentAmount(msg)) => Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+        Err(
+            e @ (ServiceError::InvoiceAlreadySettled(_)
+            | ServiceError::RepaymentNotAllowed(_)
+            | ServiceError::InvalidStatusTransition { .. }
+            | ServiceError::SettlementInProgress(_)),
+        ) => Err(ApiError::from(&e).to_json(depot)),
+        Err(ServiceError::ChainRpcError(msg)) => {
+            error!("On-chain repayment distribution for invoice {} failed: {}", invoice_id, msg);
+            match chain_error_code(&msg) {
+                Some(code) => Err(ApiError::new(code).to_json(depot)),
+                None => Err(ApiError::new(ErrorCode::ChainTransactionFailed).to_json(depot)),
+            }
+        }
+        Err(e) => {
+            error!("Failed to settle invoice {}: {}", invoice_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::Claims;
+    use common::domain::entity::UserRole;
+    use mongodb::bson::doc;
+    use salvo::test::{ResponseExt, TestClient};
+    use service::test_support::{TestDb, unused_redis_client};
+
+    #[test]
+    fn test_created_range() {
+        assert_eq!(created_range(None, None), Ok(None));
+        assert_eq!(
+            created_range(Some(1_000), Some(2_000)),
+            Ok(Some((DateTime::from_millis(1_000), DateTime::from_millis(2_000))))
+        );
+        assert_eq!(created_range(Some(1_000), None), Ok(Some((DateTime::from_millis(1_000), DateTime::MAX))));
+        assert!(created_range(Some(2_000), Some(2_000)).is_err());
+        assert!(created_range(Some(3_000), Some(2_000)).is_err());
+    }
+
+    fn enterprise_user(enterprise_id: Option<ObjectId>) -> User {
+        let mut user = User::new("0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa".to_string(), "".to_string(), UserRole::EnterpriseAdmin);
+        user.enterprise_id = enterprise_id;
+        user
+    }
+
+    #[test]
+    fn test_enterprise_invoices_scoped_to_bound_enterprise() {
+        let (ours, theirs) = (ObjectId::new(), ObjectId::new());
+        let filter = enterprise_scoped_filter(&enterprise_user(Some(ours)), Some(InvoiceStatus::Verified)).unwrap();
+        assert_eq!(filter.enterprise_id, Some(ours));
+        assert_eq!(filter.status, Some(InvoiceStatus::Verified));
+
+        // 另一企业的用户只能得到自己企业的范围
+        let other = enterprise_scoped_filter(&enterprise_user(Some(theirs)), None).unwrap();
+        assert_eq!(other.enterprise_id, Some(theirs));
+
+        // 查询按企业钱包匹配 payee，不同企业的票据互不可见
+        let query = filter.to_document(Some(&["0xours".to_string()])).unwrap();
+        assert_eq!(query.get_document("payee").unwrap(), &doc! { "$in": ["0xours"] });
+    }
+
+    #[test]
+    fn test_enterprise_invoices_require_binding() {
+        let err = enterprise_scoped_filter(&enterprise_user(None), None).unwrap_err();
+        assert_eq!(err, ErrorCode::Forbidden);
+        assert_eq!(err.status(), 403);
+    }
+
+    #[test]
+    fn test_batch_items_carry_single_create_error_codes() {
+        let limits = InvoiceLimits { max_amount: 1_000, supported_currencies: vec!["USDC".to_string()] };
+        let now_ms = 1_700_000_000_000;
+        let valid = json!({
+            "payee": "0xpayee", "payer": "0xpayer", "amount": 100, "currency": "usdc",
+            "invoice_ipfs_hash": "", "contract_ipfs_hash": "", "due_date": now_ms + 86_400_000,
+        });
+        let mut zero = valid.clone();
+        zero["amount"] = json!(0);
+        let mut currency = valid.clone();
+        currency["currency"] = json!("EUR");
+
+        assert!(validate_batch_item(valid, &limits, now_ms).is_ok());
+        assert_eq!(validate_batch_item(zero, &limits, now_ms).unwrap_err().code.as_deref(), Some("INVOICE_AMOUNT_NOT_POSITIVE"));
+        assert_eq!(validate_batch_item(currency, &limits, now_ms).unwrap_err().code.as_deref(), Some("UNSUPPORTED_CURRENCY"));
+        // 格式错误没有专门的错误码，只返回描述
+        let malformed = validate_batch_item(json!("not an object"), &limits, now_ms).unwrap_err();
+        assert!(malformed.code.is_none() && !malformed.message.is_empty());
+    }
+
+    /// 模拟 auth_token 写入的登录信息，以及路由注入的数据库与票据服务
+    struct SignedIn {
+        address: &'static str,
+        role: &'static str,
+        db: Database,
+    }
+
+    #[async_trait]
+    impl Handler for SignedIn {
+        async fn handle(&self, _req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+            depot.insert("user_address", self.address.to_string());
+            depot.insert(
+                "claims",
+                Claims {
+                    sub: self.address.to_string(),
+                    exp: usize::MAX,
+                    user_id: ObjectId::new().to_hex(),
+                    role: self.role.to_string(),
+                    jti: String::new(),
+                    iat: 0,
+                },
+            );
+            depot.inject(Arc::new(self.db.clone()));
+            depot.inject(Arc::new(InvoiceService::new(self.db.clone(), unused_redis_client())));
+        }
+    }
+
+    fn invoice_routes(address: &'static str, role: &'static str, db: &Database) -> Service {
+        Service::new(
+            Router::new()
+                .hoop(SignedIn { address, role, db: db.clone() })
+                .push(Router::with_path("invoice/del").delete(delete_invoice))
+                .push(Router::with_path("invoice/{id}").get(get_invoice))
+                .push(Router::with_path("invoice/{id}/history").get(get_invoice_history)),
+        )
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_delete_endpoint_is_recorded_in_history() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let invoice = InvoiceRepository::new(&db).create_from_blockchain(&CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }, "0xPayee").await.unwrap();
+        let id = invoice.id.unwrap().to_hex();
+        let admin = invoice_routes("0xadmin", "admin", &db);
+        let outsider = invoice_routes("0xother", "investor", &db);
+
+        let mut deleted = TestClient::delete(format!("http://127.0.0.1:5800/invoice/del?invoice_number={}", id)).send(&admin).await;
+        let deleted: serde_json::Value = deleted.take_json().await.unwrap();
+        let mut history = TestClient::get(format!("http://127.0.0.1:5800/invoice/{}/history", id)).send(&admin).await;
+        let history: serde_json::Value = history.take_json().await.unwrap();
+        let mut hidden = TestClient::get(format!("http://127.0.0.1:5800/invoice/{}/history", id)).send(&outsider).await;
+        let hidden: serde_json::Value = hidden.take_json().await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(deleted["code"], 200);
+        let entries: Vec<_> = history["data"].as_array().unwrap().iter()
+            .map(|entry| (entry["action"].as_str().unwrap(), entry["actor"].as_str().unwrap(), entry["next_status"].as_str().unwrap()))
+            .collect();
+        assert_eq!(entries, vec![("create", "0xpayee", "PENDING"), ("delete", "0xadmin", "PENDING")]);
+        assert!(history["data"][0]["previous_status"].is_null());
+        // 与出票企业无关的用户看不到变更记录
+        assert_eq!(hidden["code"], 403);
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_get_invoice_by_id() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let invoice = InvoiceRepository::new(&db).create_from_blockchain(&CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }, "0xpayee").await.unwrap();
+        let url = format!("http://127.0.0.1:5800/invoice/{}", invoice.id.unwrap().to_hex());
+        let status = |response: serde_json::Value| response["code"].clone();
+
+        let mut payee = TestClient::get(&url).send(&invoice_routes("0xPAYEE", "enterprise", &db)).await;
+        let payee: serde_json::Value = payee.take_json().await.unwrap();
+        let mut admin = TestClient::get(&url).send(&invoice_routes("0xadmin", "admin", &db)).await;
+        let admin: serde_json::Value = admin.take_json().await.unwrap();
+        let mut outsider = TestClient::get(&url).send(&invoice_routes("0xother", "investor", &db)).await;
+        let outsider: serde_json::Value = outsider.take_json().await.unwrap();
+        let mut missing = TestClient::get(format!("http://127.0.0.1:5800/invoice/{}", ObjectId::new().to_hex()))
+            .send(&invoice_routes("0xadmin", "admin", &db)).await;
+        let missing: serde_json::Value = missing.take_json().await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(status(payee.clone()), 200);
+        assert_eq!(payee["data"]["invoice_number"], invoice.invoice_number);
+        assert_eq!(status(admin), 200);
+        // 与公开的票据列表接口一致，任何登录用户都能按 ID 查询
+        assert_eq!(status(outsider), 200);
+        assert_eq!(missing["error"]["code"], "INVOICE_NOT_FOUND");
+    }
+}
@@ -1,28 +1,45 @@
+pub mod admin_controller;
+pub mod chain_controller;
 pub mod common_controller;
 pub mod enterprise_controller;
 pub mod interest_controller;
 pub mod invoice_controller;
 pub mod purchase_controller;
+pub mod reservation_controller;
+pub mod stats_controller;
 pub mod transaction_controller;
 pub mod user_controller;
 pub mod swagger_controller;
 pub mod token_controller;
 
 
+pub use admin_controller::*;
+pub use chain_controller::*;
 pub use common_controller::*;
 pub use enterprise_controller::*;
 pub use interest_controller::*;
 pub use invoice_controller::*;
 pub use purchase_controller::*;
+pub use reservation_controller::*;
+pub use stats_controller::*;
 pub use transaction_controller::*;
 pub use user_controller::*;
 pub use swagger_controller::*;
 pub use token_controller::*;
 
+use std::fmt;
+
+use mongodb::bson::oid::ObjectId;
+use salvo::extract::{Extractible, Metadata};
+use salvo::oapi::{Components, EndpointArgRegister, Operation};
+use salvo::{Depot, Request, Writer, prelude::Json};
 use serde::{Deserialize, Serialize};
 
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::res::ResObj;
+
 /// Defines the structure of the JWT claims (payload).
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     /// Subject (typically the user identifier, e.g., wallet address)
     pub sub: String, 
@@ -32,6 +49,12 @@ pub struct Claims {
     pub user_id: String,
     /// User role
     pub role: String,
+    /// Token ID，用于注销 (旧令牌没有该字段)
+    #[serde(default)]
+    pub jti: String,
+    /// 签发时间 (Unix timestamp)，旧令牌没有该字段
+    #[serde(default)]
+    pub iat: usize,
 }
 
 impl Claims {
@@ -46,5 +69,117 @@ impl Claims {
     pub fn is_investor(&self) -> bool {
         self.role == "investor"
     }
+
+    /// 签发时间。没有 `iat` 的旧令牌按有效期推算 (登录和刷新签发的令牌有效期均为 `TOKEN_LIFETIME_SECS`)
+    pub fn issued_at(&self) -> i64 {
+        if self.iat > 0 {
+            return self.iat as i64;
+        }
+        self.exp as i64 - user_controller::TOKEN_LIFETIME_SECS
+    }
+}
+
+
+/// `auth_token` 中间件写入 depot 的当前用户。
+/// 需要认证的接口直接声明 `user: AuthedUser` 参数，未认证时由提取器返回 401。
+#[derive(Debug, Clone)]
+pub struct AuthedUser {
+    /// 小写的钱包地址 (JWT `sub`)
+    pub address: String,
+    /// 用户 ID (JWT `user_id`)
+    pub user_id: Option<String>,
+    pub claims: Option<Claims>,
+}
+
+impl AuthedUser {
+    pub fn from_depot(depot: &Depot) -> Result<Self, Json<ResObj<()>>> {
+        match depot.get::<String>("user_address") {
+            Ok(address) => Ok(Self {
+                address: address.to_lowercase(),
+                user_id: depot.get::<String>("user_id").ok().cloned(),
+                claims: depot.get::<Claims>("claims").ok().cloned(),
+            }),
+            Err(_) => {
+                tracing::warn!("Authenticated user address not found in depot");
+                Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot))
+            }
+        }
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.claims.as_ref().is_some_and(Claims::is_admin)
+    }
+
+    pub fn is_creditor(&self) -> bool {
+        self.claims.as_ref().is_some_and(Claims::is_creditor)
+    }
 }
 
+impl<'ex> Extractible<'ex> for AuthedUser {
+    fn metadata() -> &'static Metadata {
+        static METADATA: Metadata = Metadata::new("AuthedUser");
+        &METADATA
+    }
+
+    async fn extract(_req: &'ex mut Request, depot: &'ex mut Depot) -> Result<Self, impl Writer + Send + fmt::Debug + 'static> {
+        Self::from_depot(depot)
+    }
+}
+
+/// 认证要求已由各接口的 `security(("bearerAuth" = []))` 声明，不再生成额外参数
+impl EndpointArgRegister for AuthedUser {
+    fn register(_components: &mut Components, _operation: &mut Operation, _arg: &str) {}
+}
+
+/// 解析路径中的 ObjectId，格式错误时返回 400 `INVALID_ID`。
+/// `{id}` 路由统一使用 `parse_object_id(&id.into_inner(), depot)?`
+pub fn parse_object_id(raw: &str, depot: &Depot) -> Result<ObjectId, Json<ResObj<()>>> {
+    ObjectId::parse_str(raw.trim()).map_err(|_| {
+        tracing::warn!("Invalid ObjectId in request path: {}", raw);
+        ApiError::new(ErrorCode::InvalidId).to_json(depot)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authed_user_from_depot() {
+        let depot = Depot::new();
+        let err = AuthedUser::from_depot(&depot).unwrap_err();
+        assert_eq!(err.0.code, 401);
+
+        let mut depot = Depot::new();
+        depot.insert("user_address", "0xAbC".to_string());
+        let user = AuthedUser::from_depot(&depot).unwrap();
+        assert_eq!(user.address, "0xabc");
+        assert!(user.claims.is_none() && !user.is_admin());
+    }
+
+    #[tokio::test]
+    async fn test_authed_user_extractor() {
+        let mut req = Request::default();
+        let mut depot = Depot::new();
+        assert!(AuthedUser::extract(&mut req, &mut depot).await.is_err());
+
+        depot.insert("user_address", "0xAbC".to_string());
+        depot.insert("user_id", "u-1".to_string());
+        let user = AuthedUser::extract(&mut req, &mut depot).await.unwrap();
+        assert_eq!(user.address, "0xabc");
+        assert_eq!(user.user_id.as_deref(), Some("u-1"));
+    }
+
+    #[test]
+    fn test_parse_object_id() {
+        let depot = Depot::new();
+        let id = ObjectId::new();
+        assert_eq!(parse_object_id(&id.to_hex(), &depot).unwrap(), id);
+
+        let too_long = format!("{}0", id.to_hex());
+        for raw in ["", "not-an-id", "123", too_long.as_str()] {
+            let err = parse_object_id(raw, &depot).unwrap_err();
+            assert_eq!(err.0.code, 400);
+        }
+    }
+}
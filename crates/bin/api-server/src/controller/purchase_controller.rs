@@ -1,16 +1,44 @@
-use salvo::oapi::{ToSchema, extract::JsonBody};
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam};
 use salvo::prelude::*;
 use std::sync::Arc;
 use mongodb::Database;
-use crate::utils::res::{Res, res_json_err, res_json_ok};
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::contract_revert::revert_error_code;
+use crate::controller::AuthedUser;
+use crate::utils::res::{Res, res_json_ok};
+use configs::CFG;
 
 use common::domain::entity::UserInvoiceHolding;
 use service::service::PurchaseService;
-use log::{error, info};
+use common::pagination::OffsetPagination;
+use crate::utils::pagination::{self, PageLinks};
+use service::cache::idempotency::is_valid_idempotency_key;
+use service::error::ServiceError;
+use tracing::{error, info};
 use serde::{Deserialize, Serialize};
 use common::domain::dto::holding_dto::HoldingDto;
 use common::domain::dto::invoice_redis_dto::InvoiceRedisDto;
 use common::domain::dto::purchase_invoice_dto::PurchaseInvoiceDto;
+use common::domain::dto::purchase_history_dto::PurchaseHistoryDto;
+use common::domain::dto::settlement_projection_dto::SettlementProjectionDto;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::LocalWallet;
+use ethers::types::Address;
+use mongodb::bson::oid::ObjectId;
+use pharos_interact::{ContractQuerier, InvoiceContract, revert_error};
+use service::repository::InvoiceRepository;
+
+/// 认购交易费用预估 (数值均为十进制字符串)
+#[derive(Serialize, ToSchema, Debug)]
+pub struct PurchaseGasEstimateDto {
+    /// 预估 gas 用量
+    pub gas_units: String,
+    /// 当前 base fee 加优先费，单位 wei
+    pub fee_per_gas_wei: String,
+    /// 预估手续费，单位 wei
+    pub estimated_fee_wei: String,
+}
 // --- API Handlers ---
 
 /// 获取可购买的票据列表
@@ -32,7 +60,7 @@ pub async fn list_available_invoices(depot: &mut Depot) -> Res<Vec<InvoiceRedisD
         }
         Err(e) => {
             error!("Failed to get available invoices: {}", e);
-            Err(res_json_err("获取可购买票据失败"))
+            Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
         }
     }
 }
@@ -40,47 +68,56 @@ pub async fn list_available_invoices(depot: &mut Depot) -> Res<Vec<InvoiceRedisD
 /// 购买票据
 #[salvo::oapi::endpoint(
     tags("购买"),
-    status_codes(200, 400, 401, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 422, 500),
     request_body = PurchaseInvoiceDto,
+    parameters(
+        ("Idempotency-Key" = Option<String>, Header, description = "重试时携带相同的值，24 小时内返回首次认购的结果而不会重复认购")
+    ),
     responses(
         (status_code = 200, description = "购买成功", body = HoldingDto),
         (status_code = 400, description = "无效的请求数据"),
         (status_code = 401, description = "未认证"),
+        (status_code = 403, description = "不能认购本企业发行的票据 (SELF_FUNDING_NOT_ALLOWED)"),
+        (status_code = 404, description = "指定的预约不存在、已过期或不属于该票据"),
+        (status_code = 409, description = "票据尚未接受融资条款、当前状态不可认购、其他认购正在进行 (PURCHASE_IN_PROGRESS)、出票企业未通过审核 (ENTERPRISE_NOT_VERIFIED)，或相同幂等键的请求仍在处理"),
+        (status_code = 422, description = "相同的幂等键已用于内容不同的认购请求"),
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn purchase_invoice(req: JsonBody<PurchaseInvoiceDto>, depot: &mut Depot) -> Res<HoldingDto> {
-    // 1. Get authenticated user address from depot (inserted by auth_token middleware)
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("Authenticated user address not found in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+pub async fn purchase_invoice(req: JsonBody<PurchaseInvoiceDto>, request: &mut Request, user: AuthedUser, depot: &mut Depot) -> Res<HoldingDto> {
+    let user_address = user.address.as_str();
     
     let purchase_service = depot.obtain::<Arc<PurchaseService>>()
         .expect("PurchaseService not found in depot");
     
     let purchase_data = req.into_inner();
+
+    let idempotency_key = match request.headers().get("Idempotency-Key") {
+        Some(value) => match value.to_str() {
+            Ok(key) if is_valid_idempotency_key(key) => Some(key.to_string()),
+            _ => return Err(ApiError::new(ErrorCode::BadRequest).with_detail("Idempotency-Key 格式无效").to_json(depot)),
+        },
+        None => None,
+    };
     
     info!("User {} is purchasing invoice: {}, amount: {}", 
           user_address, purchase_data.invoice_id, purchase_data.purchase_amount);
     
-    match purchase_service.purchase_invoice(user_address, &purchase_data).await {
+    match purchase_service.purchase_invoice_idempotent(user_address, &purchase_data, idempotency_key.as_deref()).await {
         Ok(holding) => {
             // 转换为DTO
             match convert_to_holding_dto(holding).await {
                 Ok(holding_dto) => Ok(res_json_ok(Some(holding_dto))),
                 Err(e) => {
                     error!("Failed to convert holding to DTO after purchase: {}", e);
-                    Err(res_json_err("购买成功但转换数据失败"))
+                    Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
                 }
             }
         }
         Err(e) => {
             error!("Purchase failed for user {}: {}", user_address, e);
-            Err(res_json_err(&format!("购买失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -88,6 +125,7 @@ pub async fn purchase_invoice(req: JsonBody<PurchaseInvoiceDto>, depot: &mut Dep
 /// 查询我的持仓列表
 #[salvo::oapi::endpoint(
     tags("购买"),
+    security(("bearerAuth" = [])),
     status_codes(200, 401, 500),
     responses(
         (status_code = 200, description = "成功获取持仓列表", body = Vec<HoldingDto>),
@@ -95,15 +133,8 @@ pub async fn purchase_invoice(req: JsonBody<PurchaseInvoiceDto>, depot: &mut Dep
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_my_holdings(depot: &mut Depot) -> Res<Vec<HoldingDto>> {
-    // 1. Get authenticated user address from depot
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("Authenticated user address not found in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+pub async fn list_my_holdings(user: AuthedUser, depot: &mut Depot) -> Res<Vec<HoldingDto>> {
+    let user_address = user.address.as_str();
     
     let purchase_service = depot.obtain::<Arc<PurchaseService>>()
         .expect("PurchaseService not found in depot");
@@ -129,11 +160,158 @@ pub async fn list_my_holdings(depot: &mut Depot) -> Res<Vec<HoldingDto>> {
         }
         Err(e) => {
             error!("Failed to get holdings for user {}: {}", user_address, e);
-            Err(res_json_err("获取持仓列表失败"))
+            Err(ApiError::new(ErrorCode::InternalError).to_json(depot))
         }
     }
 }
 
+/// 查询我的认购记录，附带累计认购金额与持有中仓位数
+///
+/// 响应头 `X-Total-Count` 为总数，`Link` 给出上一页 / 下一页的地址。
+#[salvo::oapi::endpoint(
+    tags("购买"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 500),
+    parameters(
+        ("page" = Option<u64>, Query, description = "页码，从 1 开始"),
+        ("page_size" = Option<i64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status_code = 200, description = "认购记录 (按认购时间倒序) 及汇总", body = PurchaseHistoryDto),
+        (status_code = 401, description = "未认证"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_purchase_history(
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    req: &mut Request,
+    user: AuthedUser,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Res<PurchaseHistoryDto> {
+    let user_address = user.address.as_str();
+    let purchase_service = depot.obtain::<Arc<PurchaseService>>()
+        .expect("PurchaseService not found in depot");
+
+    let page_size = pagination::page_size("purchase.history", page_size.into_inner()) as u64;
+    let page = page.into_inner().unwrap_or(1).max(1);
+    let pagination = OffsetPagination::new(page, page_size);
+
+    match purchase_service.list_by_investor(user_address, pagination).await {
+        Ok(history) => {
+            pagination::set_page_headers(req, res, &history.page, PageLinks::Offset { page, page_size });
+            Ok(res_json_ok(Some(history)))
+        }
+        Err(e) => {
+            error!("Failed to load purchase history for user {}: {}", user_address, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 查询持仓的预计兑付信息 (预估值，每次请求重新计算)
+#[salvo::oapi::endpoint(
+    tags("购买"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "持仓ID (holding_id)")
+    ),
+    responses(
+        (status_code = 200, description = "预计兑付信息", body = SettlementProjectionDto),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "持仓或票据不存在"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_settlement_projection(id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<SettlementProjectionDto> {
+    let user_address = user.address;
+
+    let purchase_service = depot.obtain::<Arc<PurchaseService>>()
+        .expect("PurchaseService not found in depot");
+
+    let holding_id = id.into_inner();
+    match purchase_service
+        .settlement_projection(&user_address, &holding_id, CFG.invoice.settlement_grace_days, &CFG.invoice.platform_fee_rate, CFG.interest.day_count)
+        .await
+    {
+        Ok(projection) => Ok(res_json_ok(Some(projection))),
+        Err(ServiceError::HoldingNotFound(_)) => Err(ApiError::new(ErrorCode::NotFound).with_detail("持仓不存在").to_json(depot)),
+        Err(ServiceError::InvoiceNotFound(_)) => Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            error!("Failed to compute settlement projection for holding {}: {}", holding_id, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 预估认购交易的 gas 与手续费，提交前供前端展示
+#[salvo::oapi::endpoint(
+    tags("购买"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 409, 502, 503),
+    parameters(
+        ("invoice_id" = String, Query, description = "票据ID"),
+        ("amount" = u64, Query, description = "认购数量 (与链上 purchaseShares 的 amount 一致)")
+    ),
+    responses(
+        (status_code = 200, description = "费用预估", body = PurchaseGasEstimateDto),
+        (status_code = 400, description = "无效的请求参数"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "票据不存在"),
+        (status_code = 409, description = "票据尚未上链发行，或认购交易会被合约回滚 (已知原因返回对应错误码，如 TOKEN_BATCH_NOT_ACTIVE，其余为 CONTRACT_REVERTED)"),
+        (status_code = 502, description = "查询合约失败"),
+        (status_code = 503, description = "区块链连接不可用"),
+    )
+)]
+pub async fn estimate_purchase_gas(invoice_id: QueryParam<String>, amount: QueryParam<u64>, user: AuthedUser, depot: &mut Depot) -> Res<PurchaseGasEstimateDto> {
+    let Ok(buyer) = user.address.parse::<Address>() else {
+        return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot));
+    };
+    let invoice_id = match ObjectId::parse_str(invoice_id.into_inner()) {
+        Ok(id) => id,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidId).to_json(depot)),
+    };
+    let amount = amount.into_inner();
+    if amount == 0 {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("认购数量必须大于 0").to_json(depot));
+    }
+
+    let contract = match depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    let invoice = match InvoiceRepository::new(&mongodb).find_by_id(invoice_id).await {
+        Ok(Some(invoice)) => invoice,
+        Ok(None) => return Err(ApiError::new(ErrorCode::InvoiceNotFound).to_json(depot)),
+        Err(e) => {
+            error!("Failed to load invoice {} for gas estimation: {}", invoice_id, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+    let Some(batch_id) = invoice.token_batch.filter(|b| !b.is_empty()) else {
+        return Err(ApiError::new(ErrorCode::InvoiceNotOnChain).to_json(depot));
+    };
+
+    match contract.estimate_gas_for_purchase(buyer, batch_id, amount.to_string()).await {
+        Ok(estimate) => Ok(res_json_ok(Some(PurchaseGasEstimateDto {
+            gas_units: estimate.gas_units.to_string(),
+            fee_per_gas_wei: estimate.fee_per_gas.to_string(),
+            estimated_fee_wei: estimate.estimated_fee.to_string(),
+        }))),
+        Err(e) => match revert_error(&e) {
+            Some(revert) => Err(ApiError::new(revert_error_code(revert)).to_json(depot)),
+            None => {
+                error!("Failed to estimate purchase gas for invoice {}: {}", invoice_id, e);
+                Err(ApiError::new(ErrorCode::ContractQueryFailed).to_json(depot))
+            }
+        },
+    }
+}
+
 // --- Helper Functions ---
 
 // 将实体转换为DTO
@@ -150,7 +328,7 @@ async fn convert_to_holding_dto(holding: UserInvoiceHolding) -> Result<HoldingDt
         purchase_date: holding.purchase_date,
         current_balance: holding.current_balance.to_string(),
         total_accrued_interest: holding.total_accrued_interest.to_string(),
-        annual_rate: 0.0,                          // 实际应查询获取
+        annual_rate: Default::default(),           // 实际应查询获取
         maturity_date: chrono::NaiveDate::from_ymd_opt(2023, 1, 1).unwrap(), // 实际应查询获取
         status: holding.holding_status,
     };
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use tracing::{error, info};
+use salvo::oapi::extract::{JsonBody, PathParam};
+use salvo::prelude::*;
+
+use common::domain::dto::reservation_dto::{CreateReservationDto, ReservationDto};
+use service::cache::ReservationService;
+use service::error::ServiceError;
+use crate::controller::AuthedUser;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::res::{Res, res_json_ok};
+
+/// 预约票据份额 (在有效期内保留份数)
+#[salvo::oapi::endpoint(
+    tags("预约"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 409, 500),
+    request_body = CreateReservationDto,
+    responses(
+        (status_code = 200, description = "预约成功", body = ReservationDto),
+        (status_code = 400, description = "无效的预约金额"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "票据不存在"),
+        (status_code = 409, description = "票据不可预约、份数不足，或该票据正在认购 (PURCHASE_IN_PROGRESS)"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn create_reservation(req: JsonBody<CreateReservationDto>, user: AuthedUser, depot: &mut Depot) -> Res<ReservationDto> {
+    let user_address = user.address;
+    let reservation_service = depot.obtain::<Arc<ReservationService>>()
+        .expect("ReservationService not found in depot");
+
+    let data = req.into_inner();
+    match reservation_service.reserve(&user_address, &data.invoice_id, data.amount).await {
+        Ok(reservation) => Ok(res_json_ok(Some(reservation))),
+        Err(e @ (ServiceError::InvalidPurchaseAmount(_) | ServiceError::DecimalConversionError(_))) => Err(ApiError::new(ErrorCode::BadRequest).with_detail(e.to_string()).to_json(depot)),
+        Err(e) => {
+            error!("Failed to reserve invoice {} for user {}: {}", data.invoice_id, user_address, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 查询我的有效预约 (不包含已过期的预约)
+#[salvo::oapi::endpoint(
+    tags("预约"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "有效预约列表", body = Vec<ReservationDto>),
+        (status_code = 401, description = "未认证"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn list_reservations(user: AuthedUser, depot: &mut Depot) -> Res<Vec<ReservationDto>> {
+    let user_address = user.address;
+    let reservation_service = depot.obtain::<Arc<ReservationService>>()
+        .expect("ReservationService not found in depot");
+
+    match reservation_service.list_active(&user_address).await {
+        Ok(reservations) => Ok(res_json_ok(Some(reservations))),
+        Err(e) => {
+            error!("Failed to list reservations for user {}: {}", user_address, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 提前取消预约并释放份数
+#[salvo::oapi::endpoint(
+    tags("预约"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 404, 409, 500),
+    parameters(
+        ("id" = String, Path, description = "预约ID")
+    ),
+    responses(
+        (status_code = 200, description = "取消成功"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "预约不存在或已过期"),
+        (status_code = 409, description = "该票据正在认购，请稍后重试 (PURCHASE_IN_PROGRESS)"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn cancel_reservation(id: PathParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let user_address = user.address;
+    let reservation_service = depot.obtain::<Arc<ReservationService>>()
+        .expect("ReservationService not found in depot");
+
+    let reservation_id = id.into_inner();
+    match reservation_service.cancel(&user_address, &reservation_id).await {
+        Ok(true) => {
+            info!("User {} cancelled reservation {}", user_address, reservation_id);
+            Ok(res_json_ok(None))
+        }
+        Ok(false) => Err(ApiError::new(ErrorCode::NotFound).with_detail("预约不存在或已过期").to_json(depot)),
+        Err(e) => {
+            error!("Failed to cancel reservation {} for user {}: {}", reservation_id, user_address, e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use common::domain::dto::admin_stats_dto::AdminStatsDto;
+use common::domain::dto::platform_stats_dto::PlatformStatsDto;
+use tracing::error;
+use salvo::prelude::*;
+use service::service::StatsService;
+
+use crate::utils::api_error::ApiError;
+use crate::utils::res::{Res, res_json_ok};
+
+/// 平台公开统计数据 (无需认证)
+#[salvo::oapi::endpoint(
+    tags("统计"),
+    status_codes(200, 500),
+    responses(
+        (status_code = 200, description = "平台统计数据", body = PlatformStatsDto),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn platform_stats(depot: &mut Depot) -> Res<PlatformStatsDto> {
+    let stats_service = depot.obtain::<Arc<StatsService>>().expect("StatsService not found in depot");
+
+    match stats_service.platform_stats().await {
+        Ok(stats) => Ok(res_json_ok(Some(stats))),
+        Err(e) => {
+            error!("Failed to compute platform stats: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 管理后台平台统计：用户/企业数量、各状态票据数量、融资金额、未兑付本金和代币发行量
+#[salvo::oapi::endpoint(
+    tags("统计"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 403, 500),
+    responses(
+        (status_code = 200, description = "平台统计数据", body = AdminStatsDto),
+        (status_code = 401, description = "未认证"),
+        (status_code = 403, description = "需要管理员权限"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn admin_stats(depot: &mut Depot) -> Res<AdminStatsDto> {
+    // 管理员权限由 admin 路由上的 RequireRole 保证
+    let stats_service = depot.obtain::<Arc<StatsService>>().expect("StatsService not found in depot");
+
+    match stats_service.admin_stats().await {
+        Ok(stats) => Ok(res_json_ok(Some(stats))),
+        Err(e) => {
+            error!("Failed to compute admin stats: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
@@ -1,18 +1,29 @@
 use salvo::{oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam}, prelude::*};
 use serde::{Deserialize, Serialize};
-use mongodb::bson::oid::ObjectId;
 use std::sync::Arc;
-use log::{debug, info, error};
+use tracing::{debug, info, error};
 
 use common::domain::entity::{
     CreateTokenBatchRequest, PurchaseTokenRequest, QueryTokenMarketRequest, QueryUserTokenHoldingsRequest,
     TokenBatchStatus, TokenBatchResponse, TokenMarketResponse, TokenHoldingResponse
 };
 use common::domain::entity::token::CreateTokenBatchFromInvoiceBatchRequest;
+use common::domain::dto::token_holder_dto::TokenHolderDto;
+use common::pagination::{OffsetPagination, Page};
+use common::domain::dto::token_balance_dto::TokenBalanceDto;
+use service::error::ServiceError;
 use service::service::TokenService;
+use service::service::token_service::{balance_drift, scale_onchain_balance};
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::LocalWallet;
+use ethers::types::Address;
+use pharos_interact::{ContractQuerier, InvoiceContract};
 
-use crate::utils::res::{Res, res_json_err, res_json_ok, res_json_custom, res_bad_request};
-use crate::controller::Claims;
+use crate::utils::pagination;
+use crate::utils::res::{Res, res_json_ok};
+use crate::controller::{AuthedUser, parse_object_id};
+use crate::utils::api_error::{ApiError, ErrorCode};
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TokenBatchIdResponse {
@@ -48,13 +59,13 @@ pub async fn create_token_batch(req: JsonBody<CreateTokenBatchRequest>, depot: &
     //     Ok(claims) => claims,
     //     Err(_) => {
     //         error!("Failed to get claims from depot");
-    //         return Err(res_json_custom(403, "认证失败"));
+    //         return Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot));
     //     }
     // };
     // 
     // // 验证用户是债权人或管理员
     // if !claims.is_admin() && !claims.is_creditor() {
-    //     return Err(res_json_custom(403, "只有债权人或管理员可以创建代币批次"));
+    //     return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot));
     // }
 
     match token_service.create_token_batch(req.into_inner()).await {
@@ -64,7 +75,7 @@ pub async fn create_token_batch(req: JsonBody<CreateTokenBatchRequest>, depot: &
         },
         Err(e) => {
             error!("Failed to create token batch: {}", e);
-            Err(res_json_err(&format!("创建代币批次失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -121,14 +132,14 @@ pub async fn list_token_batches(
         creditor_id.into_inner(),
         stablecoin_symbol.into_inner(),
         page.into_inner(),
-        page_size.into_inner()
+        Some(pagination::page_size("token.batches", page_size.into_inner()))
     ).await {
         Ok(token_batches) => {
             Ok(res_json_ok(Some(token_batches)))
         },
         Err(e) => {
             error!("Failed to list token batches: {}", e);
-            Err(res_json_err(&format!("查询代币批次列表失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -161,7 +172,7 @@ pub async fn list_token_markets(
     let request = QueryTokenMarketRequest {
         stablecoin_symbol: stablecoin_symbol.into_inner(),
         page: page.into_inner(),
-        page_size: page_size.into_inner(),
+        page_size: Some(pagination::page_size("token.markets", page_size.into_inner())),
     };
     
     info!("Listing token markets with query: {:?}", request);
@@ -172,7 +183,7 @@ pub async fn list_token_markets(
         },
         Err(e) => {
             error!("Failed to list token markets: {}", e);
-            Err(res_json_err(&format!("查询代币市场列表失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -180,6 +191,7 @@ pub async fn list_token_markets(
 /// 购买代币
 #[salvo::oapi::endpoint(
     tags("代币管理"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 401, 500),
     request_body = PurchaseTokenRequest,
     responses(
@@ -189,19 +201,13 @@ pub async fn list_token_markets(
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn purchase_tokens(req: JsonBody<PurchaseTokenRequest>, depot: &mut Depot) -> Res<TokenHoldingIdResponse> {
+pub async fn purchase_tokens(req: JsonBody<PurchaseTokenRequest>, user: AuthedUser, depot: &mut Depot) -> Res<TokenHoldingIdResponse> {
     // 获取Token服务
     let token_service = depot.obtain::<Arc<TokenService>>()
         .expect("TokenService not found in depot");
     
     // 获取认证用户信息
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address) => address,
-        Err(_) => {
-            error!("User address not found in depot");
-            return Err(res_json_custom(401, "用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     info!("Purchasing tokens: user={}, request={:?}", user_address, req);
     
@@ -212,7 +218,7 @@ pub async fn purchase_tokens(req: JsonBody<PurchaseTokenRequest>, depot: &mut De
         },
         Err(e) => {
             error!("Failed to purchase tokens: {}", e);
-            Err(res_json_err(&format!("购买代币失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -220,6 +226,7 @@ pub async fn purchase_tokens(req: JsonBody<PurchaseTokenRequest>, depot: &mut De
 /// 查询用户代币持有情况
 #[salvo::oapi::endpoint(
     tags("代币管理"),
+    security(("bearerAuth" = [])),
     status_codes(200, 401, 500),
     responses(
         (status_code = 200, description = "用户代币持有列表", body = Vec<TokenHoldingResponse>),
@@ -227,18 +234,15 @@ pub async fn purchase_tokens(req: JsonBody<PurchaseTokenRequest>, depot: &mut De
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn get_user_token_holdings(depot: &mut Depot) -> Res<Vec<TokenHoldingResponse>> {
+pub async fn get_user_token_holdings(user: AuthedUser, depot: &mut Depot) -> Res<Vec<TokenHoldingResponse>> {
     // 获取Token服务
     let token_service = depot.obtain::<Arc<TokenService>>()
         .expect("TokenService not found in depot");
     
     // 获取认证用户信息
-    let user_id = match depot.get::<String>("user_id") {
-        Ok(id) => id,
-        Err(_) => {
-            error!("User ID not found in depot");
-            return Err(res_json_custom(401, "用户未认证"));
-        }
+    let Some(user_id) = user.user_id else {
+        error!("User ID not found in depot");
+        return Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot));
     };
     
     let request = QueryUserTokenHoldingsRequest { 
@@ -253,7 +257,7 @@ pub async fn get_user_token_holdings(depot: &mut Depot) -> Res<Vec<TokenHoldingR
         },
         Err(e) => {
             error!("Failed to get token holdings: {}", e);
-            Err(res_json_err(&format!("查询代币持有列表失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
 }
@@ -261,6 +265,7 @@ pub async fn get_user_token_holdings(depot: &mut Depot) -> Res<Vec<TokenHoldingR
 /// 从发票批次创建代币批次
 #[salvo::oapi::endpoint(
     tags("代币管理"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 403, 500),
     parameters(
         ("invoice_batch_id" = String, Query, description = "发票批次ID")
@@ -276,6 +281,7 @@ pub async fn get_user_token_holdings(depot: &mut Depot) -> Res<Vec<TokenHoldingR
 pub async fn create_token_batch_from_invoice_batch(
     invoice_batch_id: QueryParam<String>,
     req: JsonBody<CreateTokenBatchFromInvoiceBatchRequest>,
+    user: AuthedUser,
     depot: &mut Depot
 ) -> Res<TokenBatchIdResponse> {
     info!("Creating token batch from invoice batch: {:?}", invoice_batch_id);
@@ -284,18 +290,9 @@ pub async fn create_token_batch_from_invoice_batch(
     let token_service = depot.obtain::<Arc<TokenService>>()
         .expect("TokenService not found in depot");
     
-    // 获取认证用户信息
-    let claims = match depot.get::<Claims>("claims") {
-        Ok(claims) => claims,
-        Err(_) => {
-            error!("Failed to get claims from depot");
-            return Err(res_json_custom(403, "认证失败"));
-        }
-    };
-    
     // 验证用户是债权人或管理员
-    if !claims.is_admin() && !claims.is_creditor() {
-        return Err(res_json_custom(403, "只有债权人或管理员可以创建代币批次"));
+    if !user.is_admin() && !user.is_creditor() {
+        return Err(ApiError::new(ErrorCode::Forbidden).to_json(depot));
     }
 
     match token_service.create_token_batch_from_invoice_batch(
@@ -308,7 +305,127 @@ pub async fn create_token_batch_from_invoice_batch(
         },
         Err(e) => {
             error!("Failed to create token batch from invoice batch: {}", e);
-            Err(res_json_err(&format!("从发票批次创建代币批次失败: {}", e)))
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+/// 查询代币持有人分布（按持仓降序，含占发行量百分比）
+#[salvo::oapi::endpoint(
+    tags("代币管理"),
+    status_codes(200, 400, 404, 500),
+    parameters(
+        ("id" = String, Path, description = "代币批次ID (tokenId)"),
+        ("page" = Option<u64>, Query, description = "页码，从 1 开始"),
+        ("page_size" = Option<i64>, Query, description = "每页数量")
+    ),
+    responses(
+        (status_code = 200, description = "持有人列表", body = Page<TokenHolderDto>),
+        (status_code = 400, description = "无效的代币批次ID"),
+        (status_code = 404, description = "代币批次不存在"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_token_holders(
+    id: PathParam<String>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    depot: &mut Depot,
+) -> Res<Page<TokenHolderDto>> {
+    let token_id = parse_object_id(&id.into_inner(), depot)?.to_hex();
+    let token_service = depot.obtain::<Arc<TokenService>>()
+        .expect("TokenService not found in depot");
+
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("token.holders", page_size.into_inner()) as u64);
+    match token_service.get_token_holders(&token_id, pagination).await {
+        Ok((rows, total)) => Ok(res_json_ok(Some(Page::offset(rows, total, pagination.skip())))),
+        Err(ServiceError::NotFound(_)) => Err(ApiError::new(ErrorCode::NotFound).with_detail("代币批次不存在").to_json(depot)),
+        Err(e) => {
+            error!("Failed to get holders of token {}: {}", token_id, e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
-} 
\ No newline at end of file
+}
+
+/// 查询当前用户在某代币合约上的余额，`onchain=true` 时同时读取链上 `balanceOf`，按代币 `decimals()` 换算后标记是否不一致
+#[salvo::oapi::endpoint(
+    tags("代币管理"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 500, 502, 503),
+    parameters(
+        ("token_address" = String, Path, description = "代币合约地址"),
+        ("onchain" = Option<bool>, Query, description = "是否读取链上余额进行对账，默认 false")
+    ),
+    responses(
+        (status_code = 200, description = "余额对账结果", body = TokenBalanceDto),
+        (status_code = 400, description = "无效的代币合约地址"),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 404, description = "合约地址没有对应的代币批次"),
+        (status_code = 500, description = "服务器内部错误"),
+        (status_code = 502, description = "查询合约失败"),
+        (status_code = 503, description = "区块链连接不可用"),
+    )
+)]
+pub async fn get_token_balance(
+    token_address: PathParam<String>,
+    onchain: QueryParam<bool, false>,
+    user: AuthedUser,
+    depot: &mut Depot,
+) -> Res<TokenBalanceDto> {
+    let token_address = token_address.into_inner().to_lowercase();
+    let Ok(token) = token_address.parse::<Address>() else {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("无效的代币合约地址").to_json(depot));
+    };
+
+    let token_service = depot.obtain::<Arc<TokenService>>().expect("TokenService not found in depot").clone();
+    let recorded = match token_service.recorded_balance(&token_address, &user.address).await {
+        Ok(balance) => balance,
+        Err(ServiceError::NotFound(_)) => return Err(ApiError::new(ErrorCode::NotFound).with_detail("代币合约没有对应的代币批次").to_json(depot)),
+        Err(e) => {
+            error!("Failed to get recorded balance of token {} for {}: {}", token_address, user.address, e);
+            return Err(ApiError::from(&e).to_json(depot));
+        }
+    };
+
+    let mut balance = TokenBalanceDto {
+        token_address,
+        wallet_address: user.address.clone(),
+        recorded_balance: recorded.normalize().to_string(),
+        onchain_balance: None,
+        drift: None,
+    };
+    if !onchain.into_inner().unwrap_or(false) {
+        return Ok(res_json_ok(Some(balance)));
+    }
+
+    let Ok(owner) = user.address.parse::<Address>() else {
+        return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot));
+    };
+    let contract = match depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>() {
+        Ok(contract) => contract.clone(),
+        Err(_) => return Err(ApiError::new(ErrorCode::BlockchainUnavailable).to_json(depot)),
+    };
+    let onchain = match contract.token_decimals(token).await {
+        Ok(decimals) => contract.token_balance_of(token, owner).await.map(|raw| (raw, decimals)),
+        Err(e) => Err(e),
+    };
+    match onchain {
+        Ok((raw, decimals)) => {
+            let onchain_balance = scale_onchain_balance(raw, decimals).map_or_else(|| raw.to_string(), |b| b.to_string());
+            let drift = balance_drift(recorded, raw, decimals);
+            if drift {
+                tracing::warn!(
+                    "Token balance drift for {} on {}: recorded {}, on-chain {} (decimals {})",
+                    balance.wallet_address, balance.token_address, balance.recorded_balance, onchain_balance, decimals
+                );
+            }
+            balance.onchain_balance = Some(onchain_balance);
+            balance.drift = Some(drift);
+            Ok(res_json_ok(Some(balance)))
+        }
+        Err(e) => {
+            error!("Failed to query balanceOf / decimals on token {}: {}", balance.token_address, e);
+            Err(ApiError::new(ErrorCode::ContractQueryFailed).to_json(depot))
+        }
+    }
+}
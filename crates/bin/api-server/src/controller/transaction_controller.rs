@@ -1,14 +1,23 @@
-use crate::utils::res::{Res, res_bad_request, res_json_err, res_json_ok, res_not_found};
-use mongodb::{Database, bson::oid::ObjectId};
+use crate::controller::AuthedUser;
+use crate::utils::pagination::{self, PageLinks};
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::res::{Res, res_json_ok};
+use mongodb::{Database, bson::{DateTime, oid::ObjectId}};
 use salvo::{
-    oapi::{ToSchema, extract::QueryParam},
+    oapi::{ToSchema, extract::{PathParam, QueryParam}},
     prelude::*,
 };
 use serde::{Deserialize, Serialize};
 use service::repository::TransactionRepository;
-use common::domain::entity::Transaction;
+use service::error::ServiceError;
+use service::service::{TransactionFilter, TransactionService};
+use service::service::transaction_listing::resolve_address_scope;
+use service::service::transaction_service::normalize_tx_hash;
+use common::pagination::{Page, Pagination};
+use common::domain::entity::{OnchainTransaction, OnchainTxStatus, Transaction};
+use ethers::types::H256;
 use std::sync::Arc;
-use log::{error, info};
+use tracing::{error, info};
 
 /// 交易明细DTO
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -38,9 +47,54 @@ impl From<&Transaction> for TransactionDto {
     }
 }
 
+/// 链上交易状态DTO
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TransactionStatusDto {
+    pub tx_hash: String,
+    /// 操作类型，如 purchase_shares / create_token_batch
+    pub operation: String,
+    /// 业务标识 (批次 ID / 票据号)
+    pub reference: String,
+    /// 发送方地址，未知时为空
+    pub from_address: Option<String>,
+    /// 接收方地址，未知时为空
+    pub to_address: Option<String>,
+    /// 交易代其操作的用户地址 (如兑付持有人)
+    pub accounts: Vec<String>,
+    /// Pending / Confirmed / Reverted
+    pub status: String,
+    /// 交易所在区块，未打包时为空
+    pub block_number: Option<i64>,
+    /// 最近一次检查时的确认数
+    pub confirmations: i64,
+    /// 登记时间 (毫秒时间戳)
+    pub created_at: i64,
+    /// 最近一次检查时间 (毫秒时间戳)
+    pub updated_at: i64,
+}
+
+impl From<OnchainTransaction> for TransactionStatusDto {
+    fn from(tx: OnchainTransaction) -> Self {
+        Self {
+            tx_hash: tx.tx_hash,
+            operation: tx.operation,
+            reference: tx.reference,
+            from_address: tx.from_address,
+            to_address: tx.to_address,
+            accounts: tx.accounts,
+            status: format!("{:?}", tx.status),
+            block_number: tx.block_number,
+            confirmations: tx.confirmations,
+            created_at: tx.created_at.timestamp_millis(),
+            updated_at: tx.updated_at.timestamp_millis(),
+        }
+    }
+}
+
 /// 查询用户的所有交易记录
 #[salvo::oapi::endpoint(
     tags("交易"),
+    security(("bearerAuth" = [])),
     status_codes(200, 401, 500),
     responses(
         (status_code = 200, description = "用户交易记录列表", body = Vec<TransactionDto>),
@@ -48,15 +102,9 @@ impl From<&Transaction> for TransactionDto {
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_user_transactions(depot: &mut Depot) -> Res<Vec<TransactionDto>> {
+pub async fn list_user_transactions(user: AuthedUser, depot: &mut Depot) -> Res<Vec<TransactionDto>> {
     // 获取认证用户地址
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("认证用户地址未找到: {:?}", e);
-            return Err(res_json_err("用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
     let repo = TransactionRepository::new(&mongodb);
@@ -70,7 +118,7 @@ pub async fn list_user_transactions(depot: &mut Depot) -> Res<Vec<TransactionDto
         }
         Err(e) => {
             error!("查询用户交易记录失败: {}", e);
-            Err(res_json_err("查询交易记录失败"))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -78,6 +126,7 @@ pub async fn list_user_transactions(depot: &mut Depot) -> Res<Vec<TransactionDto
 /// 查询特定持仓的交易记录
 #[salvo::oapi::endpoint(
     tags("交易"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 401, 500),
     parameters(
         ("holding_id" = String, Query, description = "持仓ID")
@@ -89,15 +138,9 @@ pub async fn list_user_transactions(depot: &mut Depot) -> Res<Vec<TransactionDto
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_holding_transactions(holding_id: QueryParam<String>, depot: &mut Depot) -> Res<Vec<TransactionDto>> {
+pub async fn list_holding_transactions(holding_id: QueryParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<TransactionDto>> {
     // 获取认证用户地址
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("认证用户地址未找到: {:?}", e);
-            return Err(res_json_err("用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
     let repo = TransactionRepository::new(&mongodb);
@@ -119,7 +162,7 @@ pub async fn list_holding_transactions(holding_id: QueryParam<String>, depot: &m
         }
         Err(e) => {
             error!("查询持仓交易记录失败: {}", e);
-            Err(res_json_err("查询交易记录失败"))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
@@ -127,6 +170,7 @@ pub async fn list_holding_transactions(holding_id: QueryParam<String>, depot: &m
 /// 按交易类型查询用户交易记录
 #[salvo::oapi::endpoint(
     tags("交易"),
+    security(("bearerAuth" = [])),
     status_codes(200, 400, 401, 500),
     parameters(
         ("transaction_type" = String, Query, description = "交易类型(Purchase/InterestAccrual/MaturityPayment/Withdrawal)")
@@ -138,15 +182,9 @@ pub async fn list_holding_transactions(holding_id: QueryParam<String>, depot: &m
         (status_code = 500, description = "服务器内部错误"),
     )
 )]
-pub async fn list_transactions_by_type(transaction_type: QueryParam<String>, depot: &mut Depot) -> Res<Vec<TransactionDto>> {
+pub async fn list_transactions_by_type(transaction_type: QueryParam<String>, user: AuthedUser, depot: &mut Depot) -> Res<Vec<TransactionDto>> {
     // 获取认证用户地址
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => address_ref.as_str(),
-        Err(e) => {
-            error!("认证用户地址未找到: {:?}", e);
-            return Err(res_json_err("用户未认证"));
-        }
-    };
+    let user_address = user.address.as_str();
     
     let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
     let repo = TransactionRepository::new(&mongodb);
@@ -155,7 +193,7 @@ pub async fn list_transactions_by_type(transaction_type: QueryParam<String>, dep
     
     // 验证交易类型
     if !["Purchase", "InterestAccrual", "MaturityPayment", "Withdrawal"].contains(&tx_type.as_str()) {
-        return Err(res_bad_request("无效的交易类型"));
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("无效的交易类型").to_json(depot));
     }
     
     info!("查询用户 {} 的 {} 类型交易记录", user_address, tx_type);
@@ -167,7 +205,119 @@ pub async fn list_transactions_by_type(transaction_type: QueryParam<String>, dep
         }
         Err(e) => {
             error!("查询交易记录失败: {}", e);
-            Err(res_json_err("查询交易记录失败"))
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 分页查询链上交易 (最新在前)，可按地址、状态和登记时间筛选
+///
+/// 非管理员只能查询与自己地址相关的交易 (发送方、接收方或代其操作的用户，如兑付持有人)；管理员不传地址时返回全部交易。
+/// 响应头 `X-Total-Count` 为总数，`Link` 给出下一页 (`rel="next"`) 与第一页 (`rel="first"`) 的地址。
+#[salvo::oapi::endpoint(
+    tags("交易"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 500),
+    parameters(
+        ("address" = Option<String>, Query, description = "发送方、接收方或代其操作的用户地址，非管理员默认为当前用户"),
+        ("status" = Option<String>, Query, description = "pending / confirmed / reverted"),
+        ("since" = Option<i64>, Query, description = "登记时间下限 (毫秒时间戳，含)"),
+        ("until" = Option<i64>, Query, description = "登记时间上限 (毫秒时间戳，不含)"),
+        ("cursor" = Option<String>, Query, description = "上一页返回的 `next_cursor`"),
+        ("limit" = Option<i64>, Query, description = "每页条数")
+    ),
+    responses(
+        (status_code = 200, description = "链上交易分页", body = Page<TransactionStatusDto>),
+        (status_code = 400, description = "无效的状态或游标"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 403, description = "无权查询其他地址的交易"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn list_onchain_transactions(
+    address: QueryParam<String, false>,
+    status: QueryParam<String, false>,
+    since: QueryParam<i64, false>,
+    until: QueryParam<i64, false>,
+    cursor: QueryParam<String, false>,
+    limit: QueryParam<i64, false>,
+    req: &mut Request,
+    user: AuthedUser,
+    depot: &mut Depot,
+    res: &mut Response,
+) -> Res<Page<TransactionStatusDto>> {
+    let address = match resolve_address_scope(user.is_admin(), &user.address, address.into_inner().as_deref()) {
+        Ok(address) => address,
+        Err(e) => return Err(ApiError::from(&e).to_json(depot)),
+    };
+    let status = match status.into_inner().filter(|s| !s.is_empty()) {
+        Some(s) => match parse_tx_status(&s) {
+            Some(status) => Some(status),
+            None => return Err(ApiError::new(ErrorCode::BadRequest).with_detail("无效的交易状态，可选 pending / confirmed / reverted").to_json(depot)),
+        },
+        None => None,
+    };
+    let filter = TransactionFilter {
+        address,
+        status,
+        since: since.into_inner().map(DateTime::from_millis),
+        until: until.into_inner().map(DateTime::from_millis),
+    };
+    let pagination = Pagination::new(cursor.into_inner(), pagination::page_size("transaction.list", limit.into_inner()) as u64);
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
+    match TransactionService::new(&mongodb).list(&filter, &pagination, TransactionStatusDto::from).await {
+        Ok(page) => {
+            pagination::set_page_headers(req, res, &page, PageLinks::Cursor);
+            Ok(res_json_ok(Some(page)))
+        }
+        Err(ServiceError::InvalidCursor(_)) => Err(ApiError::new(ErrorCode::BadRequest).with_detail("无效的游标").to_json(depot)),
+        Err(e) => {
+            error!("查询链上交易列表失败: {}", e);
+            Err(ApiError::from(&e).to_json(depot))
+        }
+    }
+}
+
+fn parse_tx_status(value: &str) -> Option<OnchainTxStatus> {
+    match value.to_ascii_lowercase().as_str() {
+        "pending" => Some(OnchainTxStatus::Pending),
+        "confirmed" => Some(OnchainTxStatus::Confirmed),
+        "reverted" => Some(OnchainTxStatus::Reverted),
+        _ => None,
+    }
+}
+
+/// 查询已提交链上交易的确认状态
+#[salvo::oapi::endpoint(
+    tags("交易"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 404, 500),
+    parameters(
+        ("tx_hash" = String, Path, description = "交易哈希 (0x 开头)")
+    ),
+    responses(
+        (status_code = 200, description = "交易状态", body = TransactionStatusDto),
+        (status_code = 400, description = "无效的交易哈希"),
+        (status_code = 401, description = "未认证"),
+        (status_code = 404, description = "交易未登记"),
+        (status_code = 500, description = "服务器内部错误"),
+    )
+)]
+pub async fn get_transaction_status(tx_hash: PathParam<String>, depot: &mut Depot) -> Res<TransactionStatusDto> {
+    let tx_hash = normalize_tx_hash(&tx_hash.into_inner());
+    if tx_hash.parse::<H256>().is_err() {
+        return Err(ApiError::new(ErrorCode::BadRequest).with_detail("无效的交易哈希").to_json(depot));
+    }
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("数据库连接未找到").clone();
+
+    match TransactionService::new(&mongodb).find_by_hash(&tx_hash).await {
+        Ok(Some(tx)) => Ok(res_json_ok(Some(TransactionStatusDto::from(tx)))),
+        Ok(None) => Err(ApiError::new(ErrorCode::NotFound).with_detail("交易未找到").to_json(depot)),
+        Err(e) => {
+            error!("查询交易 {} 状态失败: {}", tx_hash, e);
+            Err(ApiError::from(&e).to_json(depot))
         }
     }
-} 
\ No newline at end of file
+}
@@ -1,42 +1,46 @@
-use ethers::types::{Address, Signature};
-use moka::future::Cache;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Provider};
+use ethers::signers::LocalWallet;
+use ethers::types::{Address, Bytes, Signature, H256};
+use ethers::utils::hash_message;
+use pharos_interact::{ContractQuerier, Eip1271Verifier, InvoiceContract, SignatureValidator};
 use rand::RngCore;
-use salvo::oapi::{ToSchema, extract::JsonBody};
+use salvo::oapi::{ToSchema, extract::JsonBody, extract::PathParam, extract::QueryParam};
 use salvo::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use std::time::Duration;
+use redis::Client as RedisClient;
 use uuid::Uuid;
 
-use crate::utils::res::{Res, ResObj, res_json_custom, res_json_err, res_json_ok};
+use crate::utils::enterprise_info_cache::{LoadedEnterpriseInfo, RedisEnterpriseInfoCache, cached_enterprise_info, invalidate_enterprise_info};
+use crate::utils::jwt_keys::{JWT_KEYS, JwtKeySet};
+use crate::utils::nonce_store::{self, AuthNonceStore, NonceStore, render_challenge, with_validity};
+use crate::utils::typed_challenge::{self, build_login_challenge, challenge_address, challenge_digest, decode_stored, encode_stored};
+use crate::utils::token_denylist::{AuthTokenDenylist, TokenDenylist, issued_before_user_cutoff, revoke_claims};
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::pagination;
+use crate::utils::res::{Res, res_json_ok};
 use chrono::Utc;
-use log::{error, info, warn};
+use tracing::{error, info, warn};
 use salvo::http::header;
 use serde_json::json;
 
 use service::repository::UserRepository;
+use service::repository::user_repository::UserFilter;
+use common::pagination::OffsetPagination;
+use service::error::ServiceError;
+use service::service::UserAccountService;
+use common::domain::dto::admin_user_dto::{AdminUserDto, AdminUserPageDto};
+use common::domain::dto::user_export_dto::UserDataExportDto;
 use mongodb::Database;
 use thiserror::Error;
-use crate::controller::Claims;
-use jsonwebtoken::{encode, Header, EncodingKey, Algorithm};
+use crate::controller::{AuthedUser, Claims, admin_controller, invoice_controller, parse_object_id};
 use configs::CFG;
-use service::repository::EnterpriseRepository;
-use common::domain::entity::Enterprise;
+use service::repository::{AuditLogRepository, EnterpriseRepository};
+use common::domain::entity::{AuditLog, Enterprise, User, UserRole};
+use common::utils::wallet_utils::normalize_address;
 use mongodb::bson::oid::ObjectId;
 
-// --- Nonce Cache ---
-lazy_static::lazy_static! {
-    // Cache stores nonce (String) keyed by a unique request ID (String)
-    static ref NONCE_CACHE: Cache<String, String> = Cache::builder()
-        // Time to live: Nonces expire after 5 minutes
-        .time_to_live(Duration::from_secs(5 * 60))
-        // Time to idle: Nonces expire if not accessed for 5 minutes
-        .time_to_idle(Duration::from_secs(5 * 60))
-        // Maximum capacity of the cache
-        .max_capacity(10_000)
-        .build();
-}
-
 // --- Error Handling ---
 #[derive(Debug, Error, Serialize, ToSchema)]
 pub enum AuthError {
@@ -52,26 +56,42 @@ pub enum AuthError {
 
 // --- API Structures ---
 #[derive(Deserialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "address": "0x..."})))]
+#[salvo(schema(example = json!({ "address": "0x...", "typedData": false})))]
 pub struct ChallengeRequest {
     #[serde(rename = "address")]
     pub address: String, // Wallet address requesting the challenge
+    /// 为 true 时生成 EIP-712 类型化挑战 (钱包用 eth_signTypedData_v4 签名)，默认为字符串挑战
+    #[serde(rename = "typedData", default)]
+    pub typed_data: bool,
 }
 
 #[derive(Serialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "nonce": "...", "requestId": "..."})))]
+#[salvo(schema(example = json!({ "nonce": "...", "requestId": "...", "expiresAt": 1700000300})))]
 pub struct ChallengeResponse {
+    /// 待签名的挑战消息 (按 `auth.message_template` 生成，末尾附签发时间和过期时间)；类型化挑战时为其中的随机数
     pub nonce: String,
     #[serde(rename = "requestId")]
     pub request_id: String, // Unique ID to link challenge and login
+    /// 挑战过期时间 (Unix 秒)，已签入挑战消息，过期后登录返回 `CHALLENGE_EXPIRED`
+    #[serde(rename = "expiresAt")]
+    pub expires_at: i64,
+    /// EIP-712 类型化数据 (domain / types / primaryType / message)，原样交给钱包签名
+    #[serde(rename = "typedData", skip_serializing_if = "Option::is_none")]
+    pub typed_data: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "requestId": "...", "signature": "0x..."})))]
+#[salvo(schema(example = json!({ "requestId": "...", "signature": "0x...", "address": "0x...", "walletType": "eoa"})))]
 pub struct LoginRequest {
     #[serde(rename = "requestId")]
     pub request_id: String, // ID received from /challenge
     pub signature: String, // Signature generated by the wallet
+    /// 客户端声明的钱包地址，合约钱包 (EIP-1271) 必填
+    #[serde(default)]
+    pub address: Option<String>,
+    /// 钱包类型提示: "eoa" (默认) 或 "contract"
+    #[serde(rename = "walletType", default)]
+    pub wallet_type: Option<String>,
 }
 
 #[derive(Serialize, ToSchema, Debug)]
@@ -83,14 +103,77 @@ pub struct LoginResponse {
 }
 
 #[derive(Deserialize, ToSchema, Debug)]
-#[salvo(schema(example = json!({ "enterpriseAddress": "0x..."})))]
+#[salvo(schema(example = json!({ "token": "eyJ..."})))]
+pub struct RefreshTokenRequest {
+    pub token: String, // 当前持有的 JWT (可以是宽限期内刚过期的)
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "enterpriseAddress": "0x...", "requestId": "...", "signature": "0x..."})))]
 pub struct BindEnterpriseRequest {
     #[serde(rename = "enterpriseAddress")]
     pub enterprise_address: String,
+    /// 绑定挑战 ID (开启 `enterprise_binding.require_signature` 时必填)
+    #[serde(rename = "requestId", default)]
+    pub request_id: Option<String>,
+    /// 对绑定挑战消息的钱包签名 (开启 `enterprise_binding.require_signature` 时必填)
+    #[serde(default)]
+    pub signature: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "role": "PlatformAdmin", "forceRelogin": true})))]
+pub struct ChangeRoleRequest {
+    pub role: UserRole,
+    /// 为 true 时该用户此前签发的令牌全部失效，须重新登录才能取得新角色；否则新角色在下次登录后生效
+    #[serde(rename = "forceRelogin", default)]
+    pub force_relogin: bool,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "enterpriseAddress": "0x..."})))]
+pub struct BindChallengeRequest {
+    #[serde(rename = "enterpriseAddress")]
+    pub enterprise_address: String,
 }
 
-// 用户绑定的企业信息响应
 #[derive(Serialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "message": "...", "requestId": "..."})))]
+pub struct BindChallengeResponse {
+    /// 需要钱包签名的完整消息
+    pub message: String,
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "walletAddress": "0x..."})))]
+pub struct LinkWalletChallengeRequest {
+    #[serde(rename = "walletAddress")]
+    pub wallet_address: String, // 要关联的新钱包
+}
+
+#[derive(Deserialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "walletAddress": "0x...", "requestId": "...", "signature": "0x..."})))]
+pub struct LinkWalletRequest {
+    #[serde(rename = "walletAddress")]
+    pub wallet_address: String,
+    #[serde(rename = "requestId")]
+    pub request_id: String, // ID received from /wallets/link/challenge
+    pub signature: String, // 新钱包对挑战消息的签名
+}
+
+#[derive(Serialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "walletAddress": "0x...", "linkedWallets": ["0x..."]})))]
+pub struct LinkedWalletsResponse {
+    #[serde(rename = "walletAddress")]
+    pub wallet_address: String, // 账户主钱包
+    #[serde(rename = "linkedWallets")]
+    pub linked_wallets: Vec<String>,
+}
+
+// 用户绑定的企业信息响应
+#[derive(Serialize, Deserialize, ToSchema, Debug)]
 #[salvo(schema(example = json!({ "isEnterpriseBound": true, "enterpriseName": "Acme Corp", "enterpriseAddress": "0x..." })))]
 pub struct EnterpriseInfoResponse {
     #[serde(rename = "isEnterpriseBound")]
@@ -103,46 +186,81 @@ pub struct EnterpriseInfoResponse {
     pub enterprise_id: Option<String>,
 }
 
+// 当前登录用户信息
+#[derive(Serialize, ToSchema, Debug)]
+#[salvo(schema(example = json!({ "address": "0x...", "role": "creditor", "userId": "64b0...", "enterprise": { "isEnterpriseBound": true, "enterpriseId": "64b1..." }, "createdAt": 1700000000000_i64 })))]
+pub struct CurrentUserResponse {
+    pub address: String,
+    /// 与 JWT `role` 相同的取值: investor / creditor / admin
+    pub role: String,
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    /// 企业绑定概况，不含企业名称等详情 (见 /user/enterprise-info)
+    pub enterprise: EnterpriseInfoResponse,
+    /// 账户创建时间 (毫秒时间戳)
+    #[serde(rename = "createdAt")]
+    pub created_at: i64,
+}
+
 // --- Handlers ---
 
 /// 登录步骤1 生成一个挑战
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400),
+    status_codes(200, 400, 429),
     request_body = ChallengeRequest,
     responses(
         (status_code = 200, description = "Challenge generated successfully."),
         (status_code = 400, description = "Invalid request."),
+        (status_code = 429, description = "Too many challenge requests from this IP or for this address; see Retry-After."),
     )
 )]
 pub async fn challenge(req: JsonBody<ChallengeRequest>, depot: &mut Depot) -> Res<ChallengeResponse> {
-    let address_str = &req.address;
-
-    // Basic validation (more thorough validation might be needed)
-    if !address_str.starts_with("0x") || address_str.len() != 42 {
-        warn!("Invalid address format received: {}", address_str);
-        return Err(res_json_custom(400, "InvalidAddress"));
+    // 校验格式及 EIP-55 校验和
+    if let Err(e) = normalize_address(&req.address) {
+        warn!("Invalid address received: {} ({})", req.address, e);
+        return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot));
     }
 
-    let nonce = generate_nonce();
     let request_id = Uuid::new_v4().to_string();
+    let issued_at = Utc::now().timestamp();
+    let expires_at = issued_at + CFG.auth.nonce_ttl_secs as i64;
+    let (nonce, stored, typed_data) = if req.typed_data {
+        let nonce = generate_nonce();
+        let typed = build_login_challenge(&CFG.auth.eip712_domain_name, CFG.eip712_chain_id(), &req.address, &nonce, issued_at as u64, expires_at as u64);
+        match typed.and_then(|t| Ok((encode_stored(&t)?, serde_json::to_value(&t).map_err(|e| e.to_string())?))) {
+            Ok((stored, typed_json)) => (nonce, stored, Some(typed_json)),
+            Err(e) => {
+                error!("Failed to build typed login challenge for {}: {}", req.address, e);
+                return Err(ApiError::new(ErrorCode::ChallengeGenerationFailed).to_json(depot));
+            }
+        }
+    } else {
+        let nonce = with_validity(&render_challenge(&CFG.auth.message_template, &generate_nonce()), issued_at, expires_at);
+        (nonce.clone(), nonce, None)
+    };
 
-    // Store nonce associated with the request ID
-    NONCE_CACHE.insert(request_id.clone(), nonce.clone()).await;
-    info!("Generated nonce for request ID: {}", request_id);
+    // Store nonce associated with the request ID (shared across instances via Redis)
+    let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot");
+    if let Err(e) = nonce_store.put(&request_id, &stored).await {
+        error!("Failed to store nonce for request ID {}: {}", request_id, e);
+        return Err(ApiError::new(ErrorCode::ChallengeGenerationFailed).to_json(depot));
+    }
+    info!("Generated {} nonce for request ID: {}", if typed_data.is_some() { "typed" } else { "string" }, request_id);
 
-    Ok(res_json_ok(Some(ChallengeResponse { nonce, request_id })))
+    Ok(res_json_ok(Some(ChallengeResponse { nonce, request_id, expires_at, typed_data })))
 }
 
 /// 登录步骤2 验证挑战并登录 (generates JWT)
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400, 401, 500),
+    status_codes(200, 400, 401, 403, 500),
     request_body = LoginRequest,
     responses(
         (status_code = 200, description = "Login successful, JWT returned.", body = LoginResponse),
-        (status_code = 400, description = "Nonce not found or expired / Invalid signature format."),
+        (status_code = 400, description = "Nonce not found or expired / Challenge expired (CHALLENGE_EXPIRED) / Invalid signature format."),
         (status_code = 401, description = "Invalid signature (verification failed)."),
+        (status_code = 403, description = "ACCOUNT_DELETED: the wallet belongs to a deleted account."),
         (status_code = 500, description = "Internal server error during login processing."),
     )
 )]
@@ -153,136 +271,526 @@ pub async fn login(req: JsonBody<LoginRequest>, depot: &mut Depot, request: &mut
     // Create user repository
     let user_repo = UserRepository::new(&mongodb);
 
-    // 1. Retrieve the nonce from the cache
-    let request_id = &req.request_id;
-    let signature_str = &req.signature;
+    // 1-4. 取出 nonce (一次性) 并校验签名: 普通钱包走 ECDSA 恢复，合约钱包 (或恢复失败且声明了地址) 走 EIP-1271
+    let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot").clone();
+    let validator = depot.obtain::<Arc<Eip1271Verifier<Provider<Http>>>>().ok().cloned();
+    let recovered_address_str = match verify_login_challenge(
+        nonce_store.as_ref(),
+        &req,
+        validator.as_deref().map(|v| v as &dyn SignatureValidator),
+        Utc::now().timestamp(),
+    )
+    .await
+    {
+        Ok(addr) => addr,
+        Err(code) => return Err(ApiError::new(code).to_json(depot)),
+    };
+    tracing::info!("Successfully verified login signature for: {}", recovered_address_str);
 
-    let nonce = match NONCE_CACHE.get(request_id).await {
-        Some(n) => {
-            // Invalidate the nonce after retrieval to prevent reuse
-            NONCE_CACHE.invalidate(request_id).await;
-            n
+    // 5. Process user login (find or create user based on recovered address)
+    let user = match user_repo.process_login(&recovered_address_str).await {
+        Ok(db_user) => {
+            tracing::info!("Processed login for user: {}", recovered_address_str);
+            db_user // Keep the user object if needed later, otherwise ignore
         }
-        None => {
-            warn!("Nonce not found or expired for request ID: {}", request_id);
-            return Err(res_json_custom(400, "NonceNotFoundOrExpired"));
+        Err(ServiceError::AccountDeleted(_)) => {
+            tracing::warn!("Rejected login of deleted account {}", recovered_address_str);
+            return Err(ApiError::new(ErrorCode::AccountDeleted).to_json(depot));
+        }
+        Err(e) => {
+            tracing::error!("Database error processing user login for {}: {}", recovered_address_str, e);
+            // Return 500 for internal errors
+            return Err(ApiError::new(ErrorCode::LoginProcessingError).to_json(depot));
         }
     };
 
-    // 2. Prepare the message that was signed (should match exactly what frontend signed)
-    let message_to_verify = nonce; 
+    // 6. Generate JWT (关联钱包登录时 sub 为账户主钱包)
+    let claims = login_claims(&user, Utc::now().timestamp());
 
-    // 3. Parse the signature
-    let signature: Signature = match signature_str.parse() {
-        Ok(sig) => sig,
+    let token = match sign_claims(&claims) {
+        Ok(t) => t,
         Err(e) => {
-            warn!("Invalid signature format provided: {}", e);
-            // Return 400 for bad format
-            return Err(res_json_custom(400, "InvalidSignatureFormat"));
+            tracing::error!("Failed to generate JWT: {}", e);
+            return Err(ApiError::new(ErrorCode::TokenGenerationError).to_json(depot));
         }
     };
 
-    // 4. Recover the address that signed the message
-    let recovered_address = match signature.recover(message_to_verify) {
-        Ok(addr) => addr,
+    // 7. Return successful response with JWT and wallet address
+    Ok(res_json_ok(Some(LoginResponse {
+        token,
+        wallet_address: claims.sub,
+    })))    
+}
+
+/// 刷新 JWT
+///
+/// 接受仍有效或过期未超过 `jwt.refresh_grace_secs` 的令牌，按数据库中的用户重新签发 (角色以数据库为准)。
+/// 换发后原令牌写入黑名单，不能再次刷新或访问接口；没有 jti 的旧令牌不能刷新。
+/// 钱包地址已不对应任何用户 (或已对应另一账户) 时拒绝刷新。
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    status_codes(200, 401, 403, 500, 503),
+    request_body = RefreshTokenRequest,
+    responses(
+        (status_code = 200, description = "Token refreshed.", body = LoginResponse),
+        (status_code = 401, description = "Invalid token, expired beyond grace window, or user no longer exists."),
+        (status_code = 403, description = "ACCOUNT_DELETED: the account has been deleted."),
+        (status_code = 500, description = "Internal server error during refresh."),
+        (status_code = 503, description = "TOKEN_DENYLIST_UNAVAILABLE: revocation status could not be checked."),
+    )
+)]
+pub async fn refresh_token(req: JsonBody<RefreshTokenRequest>, depot: &mut Depot) -> Res<LoginResponse> {
+    let now = Utc::now().timestamp();
+    let claims = match decode_refreshable(&req.token, &JWT_KEYS, now, CFG.jwt.refresh_grace_secs) {
+        Ok(c) => c,
+        Err(reason) => {
+            warn!("Rejected token refresh: {:?}", reason);
+            return Err(ApiError::new(reason.error_code()).to_json(depot));
+        }
+    };
+
+    // 已注销的令牌不能再换发；角色变更后须重新登录以取得新角色
+    let denylist = depot.obtain::<Arc<AuthTokenDenylist>>().expect("AuthTokenDenylist not found in depot");
+    let revoked = match denylist.is_revoked(&claims.jti).await {
+        Ok(true) => Ok(true),
+        Ok(false) => issued_before_user_cutoff(denylist.as_ref(), &claims.user_id, claims.issued_at()).await,
+        Err(e) => Err(e),
+    };
+    match revoked {
+        Ok(false) => {}
+        Ok(true) => {
+            warn!("Rejected refresh of revoked token {} of {}", claims.jti, claims.sub);
+            return Err(ApiError::new(ErrorCode::TokenRevoked).to_json(depot));
+        }
+        Err(e) => {
+            error!("Failed to check token denylist for {}: {}", claims.jti, e);
+            return Err(ApiError::new(ErrorCode::TokenDenylistUnavailable).to_json(depot));
+        }
+    }
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("MongoDB Database connection not found in Depot").clone();
+    let user_repo = UserRepository::new(&mongodb);
+    let user = match user_repo.find_by_wallet_address(&claims.sub).await {
+        Ok(Some(user)) if user.deleted_at.is_some() => {
+            warn!("Rejected token refresh of deleted account {}", claims.sub);
+            return Err(ApiError::new(ErrorCode::AccountDeleted).to_json(depot));
+        }
+        Ok(Some(user)) if is_token_owner(&user, &claims) => user,
+        Ok(_) => {
+            warn!("Token refresh for unknown wallet: {}", claims.sub);
+            return Err(ApiError::new(ErrorCode::UserNotFound).to_json(depot));
+        }
+        Err(e) => {
+            error!("Database error during token refresh for {}: {}", claims.sub, e);
+            return Err(ApiError::new(ErrorCode::TokenRefreshError).to_json(depot));
+        }
+    };
+
+    let refreshed = login_claims(&user, now);
+    if refreshed.role != claims.role {
+        info!("Role of {} changed from {} to {} since the token was issued", refreshed.sub, claims.role, refreshed.role);
+    }
+    let token = match sign_claims(&refreshed) {
+        Ok(t) => t,
         Err(e) => {
-            warn!("Failed to recover address from signature: {}", e);
-            // Return 401 as signature verification failed
-            return Err(res_json_custom(401, "InvalidSignature"));
+            error!("Failed to generate JWT: {}", e);
+            return Err(ApiError::new(ErrorCode::TokenGenerationError).to_json(depot));
         }
     };
 
-    // Format recovered address consistently (lowercase hex)
-    let recovered_address_str = format!("0x{:x}", recovered_address).to_lowercase();
-    info!("Successfully recovered address: {}", recovered_address_str);
+    // 原令牌注销到刷新窗口结束，注销失败时不返回新令牌，避免同一令牌被反复换发
+    if let Err(e) = revoke_claims(denylist.as_ref(), &claims.jti, claims.exp, CFG.jwt.refresh_grace_secs, now).await {
+        error!("Failed to revoke refreshed token {}: {}", claims.jti, e);
+        return Err(ApiError::new(ErrorCode::TokenRefreshError).to_json(depot));
+    }
+    info!("Refreshed token {} of {} as {}", claims.jti, refreshed.sub, refreshed.jti);
 
-    // 5. Process user login (find or create user based on recovered address)
-    let user = match user_repo.process_login(&recovered_address_str).await {
-        Ok(db_user) => {
-            info!("Processed login for user: {}", recovered_address_str);
-            db_user // Keep the user object if needed later, otherwise ignore
+    Ok(res_json_ok(Some(LoginResponse {
+        token,
+        wallet_address: refreshed.sub,
+    })))
+}
+
+/// 注销当前令牌 (Requires authentication)
+///
+/// 令牌的 jti 写入黑名单直到刷新宽限期结束，之后携带该令牌的请求返回 401，也不能再刷新。
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 500),
+    responses(
+        (status_code = 200, description = "Token revoked."),
+        (status_code = 400, description = "Token was issued without a jti and cannot be revoked."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 500, description = "Failed to record the revocation."),
+    )
+)]
+pub async fn logout(user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let Some(claims) = user.claims else {
+        tracing::error!("Claims not found in depot for {}", user.address);
+        return Err(ApiError::new(ErrorCode::Unauthenticated).to_json(depot));
+    };
+
+    let denylist = depot.obtain::<Arc<AuthTokenDenylist>>().expect("AuthTokenDenylist not found in depot").clone();
+    match revoke_claims(denylist.as_ref(), &claims.jti, claims.exp, CFG.jwt.refresh_grace_secs, Utc::now().timestamp()).await {
+        Ok(true) => {
+            info!("Revoked token {} of {}", claims.jti, claims.sub);
+            Ok(res_json_ok(None))
         }
+        Ok(false) => Err(ApiError::new(ErrorCode::TokenNotRevocable).to_json(depot)),
         Err(e) => {
-            error!("Database error processing user login for {}: {}", recovered_address_str, e);
-            // Return 500 for internal errors
-            return Err(res_json_custom(500, "LoginProcessingError"));
+            error!("Failed to revoke token {}: {}", claims.jti, e);
+            Err(ApiError::new(ErrorCode::TokenRevocationError).to_json(depot))
+        }
+    }
+}
+
+/// 令牌有效期
+pub const TOKEN_LIFETIME_SECS: i64 = 24 * 3600;
+
+fn token_expiry(now: i64) -> usize {
+    (now + TOKEN_LIFETIME_SECS) as usize
+}
+
+fn login_claims(user: &User, now: i64) -> Claims {
+    Claims {
+        sub: user.wallet_address.to_lowercase(),
+        exp: token_expiry(now),
+        user_id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+        role: role_claim(&user.role).to_string(),
+        jti: Uuid::new_v4().to_string(),
+        iat: now as usize,
+    }
+}
+
+/// 令牌签发给的账户仍是该钱包所属的账户 (旧令牌没有 user_id 时只比较钱包)
+fn is_token_owner(user: &User, claims: &Claims) -> bool {
+    claims.user_id.is_empty() || user.id.is_some_and(|id| id.to_hex() == claims.user_id)
+}
+
+/// 降级后旧令牌中的角色高于实际角色，必须作废
+fn is_demotion(from: &UserRole, to: &UserRole) -> bool {
+    matches!(
+        (from, to),
+        (UserRole::PlatformAdmin, UserRole::EnterpriseAdmin | UserRole::Investor) | (UserRole::EnterpriseAdmin, UserRole::Investor)
+    )
+}
+
+/// 使用户此前签发的令牌全部失效 (截止时间为当前时间)，失败只记录日志
+async fn revoke_user_tokens(depot: &Depot, user_id: &str, reason: &str) {
+    let denylist = depot.obtain::<Arc<AuthTokenDenylist>>().expect("AuthTokenDenylist not found in depot");
+    let ttl = (TOKEN_LIFETIME_SECS + CFG.jwt.refresh_grace_secs.max(0)) as u64;
+    match denylist.revoke_user(user_id, Utc::now().timestamp(), ttl).await {
+        Ok(()) => info!("Revoked tokens of user {} ({})", user_id, reason),
+        Err(e) => error!("Failed to revoke tokens of user {} ({}): {}", user_id, reason, e),
+    }
+}
+
+fn role_claim(role: &UserRole) -> &'static str {
+    match role {
+        UserRole::Investor => "investor",
+        UserRole::EnterpriseAdmin => "creditor",
+        UserRole::PlatformAdmin => "admin",
+    }
+}
+
+// 使用当前密钥签名 (配置了 jwt.current_kid 时带 kid 头)
+fn sign_claims(claims: &Claims) -> Result<String, jsonwebtoken::errors::Error> {
+    JWT_KEYS.sign(claims)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum RefreshRejection {
+    InvalidToken,
+    TokenExpired,
+}
+
+impl RefreshRejection {
+    fn error_code(&self) -> ErrorCode {
+        match self {
+            RefreshRejection::InvalidToken => ErrorCode::InvalidToken,
+            RefreshRejection::TokenExpired => ErrorCode::TokenExpiredBeyondGrace,
+        }
+    }
+}
+
+/// 校验签名但跳过 exp 检查，再按宽限期判断是否还能刷新
+fn decode_refreshable(token: &str, keys: &JwtKeySet, now: i64, grace_secs: i64) -> Result<Claims, RefreshRejection> {
+    let claims = keys
+        .decode::<Claims>(token, |validation| validation.validate_exp = false)
+        .map_err(|_| RefreshRejection::InvalidToken)?
+        .claims;
+    // 没有 jti 的令牌无法在换发后注销
+    if claims.jti.is_empty() {
+        return Err(RefreshRejection::InvalidToken);
+    }
+    if claims.exp as i64 + grace_secs.max(0) < now {
+        return Err(RefreshRejection::TokenExpired);
+    }
+    Ok(claims)
+}
+
+/// 登录请求中表示合约钱包的 walletType
+const CONTRACT_WALLET_TYPE: &str = "contract";
+
+/// 取出并删除 request_id 对应的 nonce 后校验签名
+///
+/// take 是原子的 (Redis GETDEL / 进程内缓存的 remove)，同一 request_id 的并发登录只有一个能拿到 nonce，
+/// 其余返回 `NonceNotFoundOrExpired`，签名不能被重放。挑战中签入的过期时间早于 `now` 时返回
+/// `ChallengeExpired`，不依赖缓存是否已按 TTL 淘汰。
+async fn verify_login_challenge(
+    nonce_store: &dyn NonceStore,
+    req: &LoginRequest,
+    validator: Option<&dyn SignatureValidator>,
+    now: i64,
+) -> Result<String, ErrorCode> {
+    let nonce = match nonce_store.take(&req.request_id).await {
+        Ok(Some(n)) => n,
+        Ok(None) => {
+            tracing::warn!("Nonce not found or expired for request ID: {}", req.request_id);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
         }
+        Err(e) => {
+            tracing::error!("Failed to read nonce for request ID {}: {}", req.request_id, e);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
+        }
+    };
+
+    let contract_hint = req.wallet_type.as_deref() == Some(CONTRACT_WALLET_TYPE);
+    let typed = match decode_stored(&nonce) {
+        // 字符串挑战：与 personal_sign 一致，对 EIP-191 前缀后的消息哈希签名
+        None => {
+            ensure_challenge_not_expired(nonce_store::challenge_expires_at(&nonce), now, &req.request_id)?;
+            return resolve_login_address(hash_message(&nonce), &req.signature, req.address.as_deref(), contract_hint, validator).await;
+        }
+        Some(Ok(typed)) => typed,
+        Some(Err(e)) => {
+            tracing::error!("Stored challenge for request ID {} is unreadable: {}", req.request_id, e);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
+        }
+    };
+    ensure_challenge_not_expired(typed_challenge::challenge_expires_at(&typed), now, &req.request_id)?;
+    let (digest, expected) = match (challenge_digest(&typed), challenge_address(&typed)) {
+        (Ok(digest), Some(expected)) => (digest, expected),
+        _ => {
+            tracing::error!("Typed challenge for request ID {} cannot be hashed", req.request_id);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
+        }
+    };
+    // 类型化挑战绑定了钱包地址，签名者必须是该地址
+    let address = resolve_login_address(digest, &req.signature, req.address.as_deref(), contract_hint, validator).await?;
+    if address != format!("0x{:x}", expected) {
+        warn!("Typed challenge issued for {:x} but signed by {}", expected, address);
+        return Err(ErrorCode::InvalidSignature);
+    }
+    Ok(address)
+}
+
+// 升级前签发的挑战没有过期时间，仍只受 nonce 缓存 TTL 限制
+fn ensure_challenge_not_expired(expires_at: Option<i64>, now: i64, request_id: &str) -> Result<(), ErrorCode> {
+    match expires_at {
+        Some(expires_at) if now > expires_at => {
+            warn!("Challenge {} expired at {} (now {})", request_id, expires_at, now);
+            Err(ErrorCode::ChallengeExpired)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// 校验登录签名，返回登录地址 (小写)
+///
+/// `digest` 为钱包实际签名的摘要 (字符串挑战为 EIP-191 消息哈希，类型化挑战为 EIP-712 摘要)。
+/// 未提示合约钱包时先做 ECDSA 恢复；恢复失败或恢复出的地址与声明地址不一致时，
+/// 若声明了地址则改为调用该地址合约的 `isValidSignature` (EIP-1271)。
+async fn resolve_login_address(
+    digest: H256,
+    signature_str: &str,
+    claimed: Option<&str>,
+    contract_hint: bool,
+    validator: Option<&dyn SignatureValidator>,
+) -> Result<String, ErrorCode> {
+    let claimed = match claimed.map(|a| normalize_address(a).map(|n| n.parse::<Address>())) {
+        Some(Ok(Ok(addr))) => Some(addr),
+        Some(_) => return Err(ErrorCode::InvalidAddress),
+        None => None,
     };
 
-    // 6. Generate JWT
-    let now = Utc::now();
-    // Set expiration (e.g., 1 day from now)
-    let expiration_time = now + chrono::Duration::days(1);
-    let exp_timestamp = expiration_time.timestamp() as usize;
+    if !contract_hint {
+        let recovered = match signature_str.parse::<Signature>() {
+            Ok(sig) => sig.recover(digest).map_err(|e| {
+                warn!("Failed to recover address from signature: {}", e);
+                ErrorCode::InvalidSignature
+            }),
+            Err(e) => {
+                warn!("Invalid signature format provided: {}", e);
+                Err(ErrorCode::InvalidSignatureFormat)
+            }
+        };
+        match (recovered, claimed) {
+            (Ok(addr), None) => return Ok(format!("0x{:x}", addr)),
+            (Ok(addr), Some(c)) if addr == c => return Ok(format!("0x{:x}", addr)),
+            // 未声明地址时无法走 EIP-1271，保持原有错误
+            (Err(err), None) => return Err(err),
+            (Ok(addr), Some(c)) => info!("Recovered {:x} differs from claimed {:x}, trying EIP-1271", addr, c),
+            (Err(_), Some(c)) => info!("ECDSA recovery failed for claimed {:x}, trying EIP-1271", c),
+        }
+    }
+
+    let wallet = claimed.ok_or(ErrorCode::AddressRequiredForContractWallet)?;
+    let validator = validator.ok_or_else(|| {
+        warn!("Contract wallet login for {:x} but no signature verifier is configured", wallet);
+        ErrorCode::ContractWalletVerificationUnavailable
+    })?;
+    let signature = signature_str.parse::<Bytes>().map_err(|_| ErrorCode::InvalidSignatureFormat)?;
+    match validator.is_valid_signature(wallet, digest, signature).await {
+        Ok(true) => Ok(format!("0x{:x}", wallet)),
+        Ok(false) => Err(ErrorCode::InvalidSignature),
+        Err(e) => {
+            error!("EIP-1271 verification for {:x} failed: {}", wallet, e);
+            Err(ErrorCode::ContractWalletVerificationFailed)
+        }
+    }
+}
 
-    // Convert user role to string
-    let role_str = match user.role {
-        common::domain::entity::UserRole::Investor => "investor",
-        common::domain::entity::UserRole::EnterpriseAdmin => "creditor",
-        common::domain::entity::UserRole::PlatformAdmin => "admin",
+/// 生成绑定企业的签名挑战 (Requires authentication)
+///
+/// 挑战消息包含用户地址、企业地址和一次性 nonce，只能用于绑定该企业，复用登录的 nonce 存储。
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401),
+    request_body = BindChallengeRequest,
+    responses(
+        (status_code = 200, description = "Binding challenge generated.", body = BindChallengeResponse),
+        (status_code = 400, description = "Invalid enterprise address format."),
+        (status_code = 401, description = "User not authenticated."),
+    )
+)]
+pub async fn bind_enterprise_challenge(req: JsonBody<BindChallengeRequest>, user: AuthedUser, depot: &mut Depot) -> Res<BindChallengeResponse> {
+    let user_address = user.address;
+    let enterprise_address = match normalize_address(&req.enterprise_address) {
+        Ok(addr) => addr,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot)),
     };
 
-    let claims = Claims {
-        sub: recovered_address_str.clone(), // Use recovered address as subject
-        exp: exp_timestamp,
-        user_id: user.id.unwrap().to_hex(), // Add user_id field
-        role: role_str.to_string(), // Add role field
+    let message = bind_challenge_message(&user_address, &enterprise_address, &generate_nonce());
+    let request_id = Uuid::new_v4().to_string();
+    let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot");
+    if let Err(e) = nonce_store.put(&request_id, &message).await {
+        error!("Failed to store binding challenge {}: {}", request_id, e);
+        return Err(ApiError::new(ErrorCode::ChallengeGenerationFailed).to_json(depot));
+    }
+    info!("Generated binding challenge {} for user {}", request_id, user_address);
+
+    Ok(res_json_ok(Some(BindChallengeResponse { message, request_id })))
+}
+
+// 绑定挑战消息，地址统一小写，保证签名与校验时生成的消息一致
+fn bind_challenge_message(user_address: &str, enterprise_address: &str, nonce: &str) -> String {
+    format!(
+        "Pharos: bind wallet {} to enterprise {}\nNonce: {}",
+        user_address.to_lowercase(),
+        enterprise_address.to_lowercase(),
+        nonce
+    )
+}
+
+// 校验绑定签名：挑战必须存在且属于当前用户和目标企业，签名者必须是当前用户
+async fn verify_bind_signature(req: &BindEnterpriseRequest, user_address: &str, nonce_store: &AuthNonceStore) -> Result<(), ErrorCode> {
+    let (Some(request_id), Some(signature_str)) = (&req.request_id, &req.signature) else {
+        return Err(ErrorCode::BindSignatureRequired);
     };
 
-    // Retrieve the secret key from configuration
-    let secret = &CFG.jwt.secret;
-    let encoding_key = EncodingKey::from_secret(secret.as_ref());
-    
-    let token = match encode(&Header::new(Algorithm::HS256), &claims, &encoding_key) {
-        Ok(t) => t,
+    // 无论校验成功与否，挑战都只能使用一次
+    let message = match nonce_store.take(request_id).await {
+        Ok(Some(m)) => m,
+        Ok(None) => return Err(ErrorCode::NonceNotFoundOrExpired),
         Err(e) => {
-            error!("Failed to generate JWT: {}", e);
-            return Err(res_json_custom(500, "TokenGenerationError"));
+            error!("Failed to read binding challenge {}: {}", request_id, e);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
         }
     };
+    let expected_prefix = bind_challenge_message(user_address, &req.enterprise_address, "");
+    if !message.starts_with(&expected_prefix) {
+        warn!("Binding challenge {} does not match user {} / enterprise {}", request_id, user_address, req.enterprise_address);
+        return Err(ErrorCode::InvalidBindChallenge);
+    }
 
-    // 7. Return successful response with JWT and wallet address
-    Ok(res_json_ok(Some(LoginResponse {
-        token,
-        wallet_address: recovered_address_str,
-    })))    
+    let signature: Signature = signature_str.parse().map_err(|_| ErrorCode::InvalidSignatureFormat)?;
+    let recovered = signature.recover(message).map_err(|_| ErrorCode::InvalidSignature)?;
+    if !format!("0x{:x}", recovered).eq_ignore_ascii_case(user_address) {
+        warn!("Binding signature signed by 0x{:x}, expected {}", recovered, user_address);
+        return Err(ErrorCode::InvalidSignature);
+    }
+    Ok(())
+}
+
+// 链上确认用户钱包是企业钱包的签名人/所有者；未连接合约或查询失败时无法确认，不放行
+async fn verify_enterprise_authorization(
+    querier: Option<&(dyn ContractQuerier + Send + Sync)>,
+    enterprise_address: &str,
+    user_address: &str,
+) -> Result<(), ErrorCode> {
+    let querier = querier.ok_or_else(|| {
+        warn!("On-chain enterprise authorization required but no contract connection is configured");
+        ErrorCode::BlockchainUnavailable
+    })?;
+    let (Ok(enterprise), Ok(account)) = (enterprise_address.parse::<Address>(), user_address.parse::<Address>()) else {
+        return Err(ErrorCode::InvalidAddress);
+    };
+    match querier.is_enterprise_signer(enterprise, account).await {
+        Ok(true) => Ok(()),
+        Ok(false) => {
+            warn!("User {} is not an authorized signer of enterprise {}", user_address, enterprise_address);
+            Err(ErrorCode::NotAuthorizedForEnterprise)
+        }
+        Err(e) => {
+            error!("Failed to check signers of enterprise {} for {}: {}", enterprise_address, user_address, e);
+            Err(ErrorCode::BlockchainUnavailable)
+        }
+    }
 }
 
 /// 绑定用户到企业 (Requires authentication)
+///
+/// 开启 `enterprise_binding.require_onchain_authorization` 时，用户钱包必须是企业钱包的链上签名人/所有者。
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 400, 401, 404, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 500, 503),
     request_body = BindEnterpriseRequest,
     responses(
         (status_code = 200, description = "Successfully bound user to enterprise."),
-        (status_code = 400, description = "Invalid enterprise address format."),
-        (status_code = 401, description = "User not authenticated."),
+        (status_code = 400, description = "Invalid enterprise address format, or binding signature missing/expired."),
+        (status_code = 401, description = "User not authenticated, or binding signature invalid."),
+        (status_code = 403, description = "Wallet is not an authorized signer/owner of the enterprise wallet."),
         (status_code = 404, description = "Enterprise not found with the provided address."),
         (status_code = 500, description = "Internal server error."),
+        (status_code = 503, description = "On-chain authorization could not be checked."),
     )
 )]
-pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, depot: &mut Depot) -> Res<()> { // Returns Res<()> for success/failure
-    // 1. Get authenticated user address from depot (inserted by auth_token middleware)
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => {
-            address_ref.as_str()
-        },
-        Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
-    };
+pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, user: AuthedUser, depot: &mut Depot) -> Res<()> { // Returns Res<()> for success/failure
+    let user_address = user.address.as_str();
 
     // 2. Get dependencies
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let user_repo = UserRepository::new(&mongodb);
     let enterprise_repo = EnterpriseRepository::new(&mongodb);
 
-    // 3. Validate enterprise address format
-    let enterprise_address = &req.enterprise_address;
-    if !enterprise_address.starts_with("0x") || enterprise_address.len() != 42 {
-        warn!("Invalid enterprise address format provided for binding: {}", enterprise_address);
-        return Err(res_json_err( "InvalidEnterpriseAddressFormat"));
+    // 3. Validate enterprise address format (EIP-55 校验和)，后续统一使用小写形式
+    let enterprise_address = match normalize_address(&req.enterprise_address) {
+        Ok(addr) => addr,
+        Err(e) => {
+            tracing::warn!("Invalid enterprise address provided for binding: {} ({})", req.enterprise_address, e);
+            return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot));
+        }
+    };
+    let enterprise_address = &enterprise_address;
+
+    // 3.1 按配置要求对绑定挑战签名，防止仅凭被盗的 JWT 完成绑定
+    if CFG.enterprise_binding.require_signature {
+        let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot");
+        verify_bind_signature(&req, user_address, nonce_store).await.map_err(|code| ApiError::new(code).to_json(depot))?;
     }
 
     // 4. Find the enterprise by its wallet address
@@ -291,122 +799,984 @@ pub async fn bind_enterprise(req: JsonBody<BindEnterpriseRequest>, depot: &mut D
             if let Some(id) = enterprise.id {
                 id // Return the ObjectId
             } else {
-                 error!("Enterprise found by address {} but has no ObjectId", enterprise_address);
-                 return Err(res_json_custom(500, "EnterpriseMissingId"));
+                 tracing::error!("Enterprise found by address {} but has no ObjectId", enterprise_address);
+                 return Err(ApiError::new(ErrorCode::EnterpriseMissingId).to_json(depot));
             }
         },
         Ok(None) => {
-            log::warn!("Enterprise not found with address: {}", enterprise_address);
-            return Err(res_json_custom(404, "EnterpriseNotFound"));
+            tracing::warn!("Enterprise not found with address: {}", enterprise_address);
+            return Err(ApiError::new(ErrorCode::EnterpriseNotFound).to_json(depot));
         }
         Err(e) => {
-            error!("Database error finding enterprise by address {}: {}", enterprise_address, e);
-            return Err(res_json_custom(500, "DatabaseError"));
+            tracing::error!("Database error finding enterprise by address {}: {}", enterprise_address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
         }
     };
 
+    // 4.1 按配置在链上确认用户有权代表该企业 (测试网可关闭)
+    if CFG.enterprise_binding.require_onchain_authorization {
+        let contract = depot.obtain::<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>().ok().cloned();
+        let querier = contract.as_deref().map(|c| c as &(dyn ContractQuerier + Send + Sync));
+        verify_enterprise_authorization(querier, enterprise_address, user_address).await.map_err(|code| ApiError::new(code).to_json(depot))?;
+    }
+
     // 5. Bind the user to the enterprise
     match user_repo.bind_enterprise(&user_address, enterprise_oid).await {
         Ok(true) => {
-            info!("Successfully bound user {} to enterprise {}", user_address, enterprise_oid);
+            tracing::info!("Successfully bound user {} to enterprise {}", user_address, enterprise_oid);
+            invalidate_enterprise_info(&enterprise_info_cache(depot), user_address).await;
+            // 6. 已在链上确认企业钱包所有权时按配置提升为企业管理员 (已是更高角色则不变)
+            if CFG.enterprise_binding.promotes_on_bind() {
+                change_role_for_binding(&mongodb, user_address, UserRole::EnterpriseAdmin, "enterprise_bind", enterprise_oid).await;
+            }
             Ok(res_json_ok(None)) // Return 200 OK with no body
         }
         Ok(false) => {
              // This means the user address wasn't found, which shouldn't happen if they are authenticated
-             error!("Authenticated user {} not found in DB for binding update?", user_address);
-             Err(res_json_custom(500, "AuthenticatedUserNotFoundInDB"))
+             tracing::error!("Authenticated user {} not found in DB for binding update?", user_address);
+             Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot))
         }
         Err(e) => {
-            error!("Database error binding user {} to enterprise {}: {}", user_address, enterprise_oid, e);
-            Err(res_json_custom(500, "DatabaseError"))
+            tracing::error!("Database error binding user {} to enterprise {}: {}", user_address, enterprise_oid, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
     }
 }
 
-/// 获取用户绑定的企业信息 (需要认证)
+/// 生成关联钱包的签名挑战 (Requires authentication)
+///
+/// 以主钱包登录后请求，挑战消息需由要关联的新钱包签名。
 #[salvo::oapi::endpoint(
     tags("用户"),
-    status_codes(200, 401, 500),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401),
+    request_body = LinkWalletChallengeRequest,
     responses(
-        (status_code = 200, description = "获取用户绑定的企业信息", body = EnterpriseInfoResponse),
-        (status_code = 401, description = "用户未认证"),
-        (status_code = 500, description = "内部服务器错误"),
+        (status_code = 200, description = "Link challenge generated.", body = BindChallengeResponse),
+        (status_code = 400, description = "Invalid wallet address format."),
+        (status_code = 401, description = "User not authenticated."),
     )
 )]
-pub async fn get_enterprise_info(depot: &mut Depot) -> Res<EnterpriseInfoResponse> {
-    // 1. 获取已认证用户的地址（由auth_token中间件插入）
-    let user_address = match depot.get::<String>("user_address") {
-        Ok(address_ref) => {
-            address_ref.as_str()
-        },
-        Err(e) => {
-            log::error!("Authenticated user address not found or wrong type in depot: {:?}", e);
-            return Err(res_json_err("User not authenticated"));
-        }
+pub async fn link_wallet_challenge(req: JsonBody<LinkWalletChallengeRequest>, user: AuthedUser, depot: &mut Depot) -> Res<BindChallengeResponse> {
+    let user_address = user.address;
+    let wallet_address = match normalize_address(&req.wallet_address) {
+        Ok(addr) => addr,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot)),
+    };
+
+    let message = link_challenge_message(&user_address, &wallet_address, &generate_nonce());
+    let request_id = Uuid::new_v4().to_string();
+    let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot");
+    if let Err(e) = nonce_store.put(&request_id, &message).await {
+        error!("Failed to store link challenge {}: {}", request_id, e);
+        return Err(ApiError::new(ErrorCode::ChallengeGenerationFailed).to_json(depot));
+    }
+    info!("Generated wallet link challenge {} for user {}", request_id, user_address);
+
+    Ok(res_json_ok(Some(BindChallengeResponse { message, request_id })))
+}
+
+/// 关联新钱包到当前账户 (Requires authentication)
+///
+/// 关联后用新钱包登录得到的是同一账户 (相同 user_id)。
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 409, 500),
+    request_body = LinkWalletRequest,
+    responses(
+        (status_code = 200, description = "Wallet linked.", body = LinkedWalletsResponse),
+        (status_code = 400, description = "Invalid address, or challenge missing/expired."),
+        (status_code = 401, description = "User not authenticated, or signature not from the new wallet."),
+        (status_code = 409, description = "Wallet already linked to this or another account."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn link_wallet(req: JsonBody<LinkWalletRequest>, user: AuthedUser, depot: &mut Depot) -> Res<LinkedWalletsResponse> {
+    let user_address = user.address;
+    let wallet_address = match normalize_address(&req.wallet_address) {
+        Ok(addr) => addr,
+        Err(_) => return Err(ApiError::new(ErrorCode::InvalidAddress).to_json(depot)),
     };
 
-    // 2. 获取依赖
+    let nonce_store = depot.obtain::<Arc<AuthNonceStore>>().expect("AuthNonceStore not found in depot");
+    verify_link_signature(&req, &user_address, nonce_store).await.map_err(|code| ApiError::new(code).to_json(depot))?;
+
     let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
     let user_repo = UserRepository::new(&mongodb);
-    let enterprise_repo = EnterpriseRepository::new(&mongodb);
-
-    // 3. 查找用户
-    let user = match user_repo.find_by_wallet_address(user_address).await {
+    let user = match user_repo.find_by_wallet_address(&user_address).await {
         Ok(Some(user)) => user,
-        Ok(None) => {
-            error!("Authenticated user not found in database: {}", user_address);
-            return Err(res_json_custom(500, "AuthenticatedUserNotFound"));
-        },
+        Ok(None) => return Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
         Err(e) => {
-            error!("Database error finding user by address {}: {}", user_address, e);
-            return Err(res_json_custom(500, "DatabaseError"));
-        }
-    };
-
-    // 4. 检查用户是否绑定了企业
-    if let Some(enterprise_id) = user.enterprise_id {
-        // 5. 如果绑定了企业，获取企业信息
-        match enterprise_repo.find_by_id(enterprise_id).await {
-            Ok(Some(enterprise)) => {
-                let response = EnterpriseInfoResponse {
-                    is_enterprise_bound: true,
-                    enterprise_name: Some(enterprise.name),
-                    enterprise_address: Some(enterprise.wallet_address),
-                    enterprise_id: Some(enterprise_id.to_string()),
-                };
-                Ok(res_json_ok(Some(response)))
-            },
-            Ok(None) => {
-                // 找到了用户绑定的企业ID，但企业不存在
-                warn!("Enterprise with ID {} bound to user {} not found", enterprise_id, user_address);
-                let response = EnterpriseInfoResponse {
-                    is_enterprise_bound: true,
-                    enterprise_name: None,
-                    enterprise_address: None,
-                    enterprise_id: Some(enterprise_id.to_string()),
-                };
-                Ok(res_json_ok(Some(response)))
-            },
-            Err(e) => {
-                error!("Database error finding enterprise by ID {}: {}", enterprise_id, e);
-                return Err(res_json_custom(500, "DatabaseError"));
-            }
+            error!("Database error loading user {}: {}", user_address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+    let owner = match user_repo.find_by_any_wallet(&wallet_address).await {
+        Ok(owner) => owner,
+        Err(e) => {
+            error!("Database error looking up wallet {}: {}", wallet_address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+    if let Err(code) = check_link_target(&user, owner.as_ref(), &wallet_address) {
+        return Err(ApiError::new(code).to_json(depot));
+    }
+
+    let user_id = user.id.expect("user loaded from db has an id");
+    match user_repo.add_linked_wallet(user_id, &wallet_address).await {
+        Ok(true) => {
+            info!("Linked wallet {} to user {}", wallet_address, user_address);
+            let mut linked_wallets = user.linked_wallets;
+            linked_wallets.push(wallet_address);
+            Ok(res_json_ok(Some(LinkedWalletsResponse { wallet_address: user.wallet_address, linked_wallets })))
+        }
+        // 并发请求已先一步关联
+        Ok(false) => Err(ApiError::new(ErrorCode::WalletAlreadyLinked).to_json(depot)),
+        Err(e) => {
+            error!("Database error linking wallet {} to user {}: {}", wallet_address, user_address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+// 关联钱包挑战消息，地址统一小写
+fn link_challenge_message(user_address: &str, wallet_address: &str, nonce: &str) -> String {
+    format!(
+        "Pharos: link wallet {} to account {}\nNonce: {}",
+        wallet_address.to_lowercase(),
+        user_address.to_lowercase(),
+        nonce
+    )
+}
+
+// 校验关联签名：挑战必须属于当前账户和目标钱包，签名者必须是新钱包
+async fn verify_link_signature(req: &LinkWalletRequest, user_address: &str, nonce_store: &AuthNonceStore) -> Result<(), ErrorCode> {
+    let message = match nonce_store.take(&req.request_id).await {
+        Ok(Some(m)) => m,
+        Ok(None) => return Err(ErrorCode::NonceNotFoundOrExpired),
+        Err(e) => {
+            error!("Failed to read link challenge {}: {}", req.request_id, e);
+            return Err(ErrorCode::NonceNotFoundOrExpired);
+        }
+    };
+    let expected_prefix = link_challenge_message(user_address, &req.wallet_address, "");
+    if !message.starts_with(&expected_prefix) {
+        warn!("Link challenge {} does not match user {} / wallet {}", req.request_id, user_address, req.wallet_address);
+        return Err(ErrorCode::InvalidLinkChallenge);
+    }
+
+    let signature: Signature = req.signature.parse().map_err(|_| ErrorCode::InvalidSignatureFormat)?;
+    let recovered = signature.recover(message).map_err(|_| ErrorCode::InvalidSignature)?;
+    if !format!("0x{:x}", recovered).eq_ignore_ascii_case(&req.wallet_address) {
+        warn!("Link signature signed by 0x{:x}, expected {}", recovered, req.wallet_address);
+        return Err(ErrorCode::InvalidSignature);
+    }
+    Ok(())
+}
+
+// 新钱包不能已属于任何账户 (包括当前账户的主钱包或关联钱包)
+fn check_link_target(user: &User, owner: Option<&User>, wallet_address: &str) -> Result<(), ErrorCode> {
+    if user.owns_wallet(wallet_address) {
+        return Err(ErrorCode::WalletAlreadyLinked);
+    }
+    if owner.is_some() {
+        return Err(ErrorCode::WalletBelongsToAnotherUser);
+    }
+    Ok(())
+}
+
+/// 解除用户与企业的绑定 (Requires authentication)
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 404, 500),
+    responses(
+        (status_code = 200, description = "Successfully unbound user from enterprise."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 404, description = "EnterpriseNotBound: user is not bound to any enterprise."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn unbind_enterprise(user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let user_address = user.address;
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let user_repo = UserRepository::new(&mongodb);
+
+    let (user_id, enterprise_oid) = match user_repo.find_by_wallet_address(&user_address).await {
+        Ok(Some(user)) => match bound_enterprise(&user) {
+            Ok(id) => (user.id, id),
+            Err(code) => return Err(ApiError::new(code).to_json(depot)),
+        },
+        Ok(None) => return Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
+        Err(e) => {
+            error!("Database error loading user {}: {}", user_address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    match user_repo.unbind_enterprise(&user_address).await {
+        Ok(true) => {
+            info!("Successfully unbound user {} from enterprise {}", user_address, enterprise_oid);
+            invalidate_enterprise_info(&enterprise_info_cache(depot), &user_address).await;
+            if CFG.enterprise_binding.revert_on_unbind
+                && change_role_for_binding(&mongodb, &user_address, UserRole::Investor, "enterprise_unbind", enterprise_oid).await
+            {
+                if let Some(user_id) = user_id {
+                    revoke_user_tokens(depot, &user_id.to_hex(), "enterprise_unbind").await;
+                }
+            }
+            Ok(res_json_ok(None))
+        }
+        // 并发解绑，已被其他请求处理
+        Ok(false) => Err(ApiError::new(ErrorCode::EnterpriseNotBound).to_json(depot)),
+        Err(e) => {
+            error!("Database error unbinding user {}: {}", user_address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+// 绑定/解绑企业时调整角色并写审计日志。
+// 提升只针对投资人，恢复只针对企业管理员，平台管理员不受影响。
+// 角色变更失败不影响绑定结果，新角色在用户下次登录或刷新令牌后生效；返回角色是否已变更。
+async fn change_role_for_binding(mongodb: &Database, user_address: &str, target: UserRole, reason: &str, enterprise_oid: ObjectId) -> bool {
+    let expected = match target {
+        UserRole::EnterpriseAdmin => UserRole::Investor,
+        UserRole::Investor => UserRole::EnterpriseAdmin,
+        UserRole::PlatformAdmin => return false,
+    };
+    let user_repo = UserRepository::new(mongodb);
+    match user_repo.update_role(user_address, &expected, &target).await {
+        Ok(true) => {
+            info!("Changed role of user {} from {:?} to {:?} ({})", user_address, expected, target, reason);
+            let entry = AuditLog::new(
+                user_address,
+                "user.role_change",
+                "user",
+                &user_address.to_lowercase(),
+                Some(mongodb::bson::doc! {
+                    "from": format!("{:?}", expected),
+                    "to": format!("{:?}", target),
+                    "reason": reason,
+                    "enterprise_id": enterprise_oid,
+                }),
+            );
+            if let Err(e) = AuditLogRepository::new(mongodb).create(&entry).await {
+                error!("Failed to write role change audit entry for user {}: {}", user_address, e);
+            }
+            true
+        }
+        // 角色不是预期值 (例如已是更高角色)，无需变更
+        Ok(false) => false,
+        Err(e) => {
+            error!("Failed to change role of user {} to {:?}: {}", user_address, target, e);
+            false
+        }
+    }
+}
+
+/// 获取用户绑定的企业信息 (需要认证)
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "获取用户绑定的企业信息", body = EnterpriseInfoResponse),
+        (status_code = 401, description = "用户未认证"),
+        (status_code = 500, description = "内部服务器错误"),
+    )
+)]
+pub async fn get_enterprise_info(user: AuthedUser, depot: &mut Depot) -> Res<EnterpriseInfoResponse> {
+    // 1. 获取已认证用户的地址（由auth_token中间件插入）
+    let user_address = user.address;
+
+    // 2. 优先读缓存，未命中时查库
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let cache = enterprise_info_cache(depot);
+    match cached_enterprise_info(&cache, &user_address, || load_enterprise_info(&mongodb, &user_address)).await {
+        Ok(info) => Ok(res_json_ok(Some(info))),
+        Err(code) => Err(ApiError::new(code).to_json(depot)),
+    }
+}
+
+async fn load_enterprise_info(mongodb: &Database, user_address: &str) -> Result<LoadedEnterpriseInfo, ErrorCode> {
+    let user_repo = UserRepository::new(mongodb);
+    let enterprise_repo = EnterpriseRepository::new(mongodb);
+
+    // 查找用户
+    let user = match user_repo.find_by_wallet_address(user_address).await {
+        Ok(Some(user)) => user,
+        Ok(None) => {
+            error!("Authenticated user not found in database: {}", user_address);
+            return Err(ErrorCode::AuthenticatedUserNotFound);
+        },
+        Err(e) => {
+            error!("Database error finding user by address {}: {}", user_address, e);
+            return Err(ErrorCode::DatabaseError);
+        }
+    };
+
+    // 检查用户是否绑定了企业，未绑定时返回未绑定状态
+    let Some(enterprise_id) = user.enterprise_id else {
+        return Ok(LoadedEnterpriseInfo { info: enterprise_info(&user, None), cacheable: true });
+    };
+
+    // 如果绑定了企业，获取企业信息
+    match enterprise_repo.find_by_id(enterprise_id).await {
+        Ok(enterprise) => {
+            if enterprise.is_none() {
+                // 找到了用户绑定的企业ID，但企业不存在；不缓存，数据修复后立即可见
+                warn!("Enterprise with ID {} bound to user {} not found", enterprise_id, user_address);
+            }
+            Ok(LoadedEnterpriseInfo { info: enterprise_info(&user, enterprise.as_ref()), cacheable: enterprise.is_some() })
+        }
+        Err(e) => {
+            error!("Database error finding enterprise by ID {}: {}", enterprise_id, e);
+            Err(ErrorCode::DatabaseError)
+        }
+    }
+}
+
+/// 获取当前登录用户的地址、角色与企业绑定概况 (需要认证)
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "Authenticated user profile.", body = CurrentUserResponse),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn get_current_user(authed: AuthedUser, depot: &mut Depot) -> Res<CurrentUserResponse> {
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    match UserRepository::new(&mongodb).find_by_wallet_address(&authed.address).await {
+        Ok(Some(user)) => Ok(res_json_ok(Some(current_user(&user, authed.claims.as_ref())))),
+        Ok(None) => {
+            error!("Authenticated user not found in database: {}", authed.address);
+            Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot))
+        }
+        Err(e) => {
+            error!("Database error finding user by address {}: {}", authed.address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 导出当前用户的全部数据 (账户、绑定企业、认购与交易记录)
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 500),
+    responses(
+        (status_code = 200, description = "User data export bundle.", body = UserDataExportDto),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn export_user_data(user: AuthedUser, depot: &mut Depot) -> Res<UserDataExportDto> {
+    let user_address = user.address;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    match UserAccountService::new(&mongodb).export(&user_address).await {
+        Ok(export) => {
+            info!("User {} exported account data ({} purchases, {} transactions)", user_address, export.purchases.len(), export.transactions.len());
+            Ok(res_json_ok(Some(export)))
+        }
+        Err(ServiceError::UserNotFound(_)) => Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
+        Err(e) => {
+            error!("Failed to export data of user {}: {}", user_address, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
+        }
+    }
+}
+
+/// 注销账户：匿名化个人信息并解除企业绑定，持仓、交易等与链上记录对应的数据保留。
+/// 存在持有中的仓位时返回 409。注销成功后当前令牌失效
+#[salvo::oapi::endpoint(
+    tags("用户"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 401, 409, 500),
+    responses(
+        (status_code = 200, description = "Account anonymized."),
+        (status_code = 401, description = "User not authenticated."),
+        (status_code = 409, description = "AccountHasActivePositions: the account still holds active financed positions."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn delete_account(user: AuthedUser, depot: &mut Depot) -> Res<()> {
+    let user_address = user.address;
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+
+    let deletion = match UserAccountService::new(&mongodb).delete_account(&user_address).await {
+        Ok(deletion) => deletion,
+        Err(ServiceError::ActivePositions(reason)) => {
+            warn!("Refused to delete account {}: {}", user_address, reason);
+            return Err(ApiError::new(ErrorCode::AccountHasActivePositions).to_json(depot));
+        }
+        Err(ServiceError::UserNotFound(_)) => return Err(ApiError::new(ErrorCode::AuthenticatedUserNotFound).to_json(depot)),
+        Err(e) => {
+            error!("Failed to delete account {}: {}", user_address, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    let cache = enterprise_info_cache(depot);
+    invalidate_enterprise_info(&cache, &user_address).await;
+    for wallet in &deletion.removed_wallets {
+        invalidate_enterprise_info(&cache, wallet).await;
+    }
+    if let Some(enterprise_oid) = deletion.unbound_enterprise {
+        if CFG.enterprise_binding.revert_on_unbind {
+            change_role_for_binding(&mongodb, &user_address, UserRole::Investor, "account_delete", enterprise_oid).await;
+        }
+    }
+
+    let entry = AuditLog::new(&user_address, "user.delete", "user", &user_address, None);
+    if let Err(e) = AuditLogRepository::new(&mongodb).create(&entry).await {
+        error!("Failed to write account deletion audit entry for user {}: {}", user_address, e);
+    }
+
+    // 该账户此前签发的全部令牌 (包括其他设备上的) 失效；已注销的账户不能再登录或刷新
+    revoke_user_tokens(depot, &deletion.user_id.to_hex(), "account_delete").await;
+    Ok(res_json_ok(None))
+}
+
+/// 分页查询用户 (管理员)，可按角色、是否绑定企业和创建时间筛选，并返回各角色用户数
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 500),
+    parameters(
+        ("role" = Option<UserRole>, Query, description = "User role"),
+        ("bound" = Option<bool>, Query, description = "true: bound to an enterprise, false: not bound"),
+        ("created_from" = Option<i64>, Query, description = "Created at or after (ms timestamp, inclusive)"),
+        ("created_to" = Option<i64>, Query, description = "Created before (ms timestamp, exclusive)"),
+        ("page" = Option<u64>, Query, description = "Page number, starting from 1"),
+        ("page_size" = Option<i64>, Query, description = "Page size (capped per `pagination.overrides`)")
+    ),
+    responses(
+        (status_code = 200, description = "Matching users, newest first, with total and per-role counts.", body = AdminUserPageDto),
+        (status_code = 400, description = "Invalid filter."),
+        (status_code = 401, description = "Unauthenticated."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn list_users_admin(
+    role: QueryParam<UserRole, false>,
+    bound: QueryParam<bool, false>,
+    created_from: QueryParam<i64, false>,
+    created_to: QueryParam<i64, false>,
+    page: QueryParam<u64, false>,
+    page_size: QueryParam<i64, false>,
+    depot: &mut Depot,
+) -> Res<AdminUserPageDto> {
+    admin_controller::require_admin(depot)?;
+    let created_between = match invoice_controller::created_range(created_from.into_inner(), created_to.into_inner()) {
+        Ok(range) => range,
+        Err(msg) => return Err(ApiError::new(ErrorCode::BadRequest).with_detail(msg).to_json(depot)),
+    };
+    let filter = UserFilter { role: role.into_inner(), bound: bound.into_inner(), created_between };
+    let pagination = OffsetPagination::new(page.into_inner().unwrap_or(1), pagination::page_size("admin.users", page_size.into_inner()) as u64);
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let repo = UserRepository::new(&mongodb);
+    let result = match repo.list(&filter, pagination).await {
+        Ok((users, total)) => repo.count_by_role(&filter).await.map(|role_counts| (users, total, role_counts)),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok((users, total, role_counts)) => Ok(res_json_ok(Some(AdminUserPageDto {
+            rows: users.into_iter().map(AdminUserDto::from).collect(),
+            total,
+            role_counts,
+        }))),
+        Err(e) => {
+            error!("Failed to list users with {:?}: {}", filter, e);
+            Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot))
         }
-    } else {
-        // 6. 如果未绑定企业，返回未绑定状态
-        let response = EnterpriseInfoResponse {
-            is_enterprise_bound: false,
-            enterprise_name: None,
-            enterprise_address: None,
-            enterprise_id: None,
-        };
-        Ok(res_json_ok(Some(response)))
+    }
+}
+
+/// 修改用户角色 (管理员)
+///
+/// 只允许投资人与企业管理员互转、企业管理员提升为平台管理员、平台管理员降级；最后一个平台管理员不能被降级。
+/// 降级时该用户此前签发的令牌总是失效；提升时仅在 `forceRelogin` 为 true 时失效。
+#[salvo::oapi::endpoint(
+    tags("管理员"),
+    security(("bearerAuth" = [])),
+    status_codes(200, 400, 401, 403, 404, 409, 500),
+    request_body = ChangeRoleRequest,
+    responses(
+        (status_code = 200, description = "Role changed.", body = AdminUserDto),
+        (status_code = 400, description = "Invalid user ID."),
+        (status_code = 401, description = "Unauthenticated."),
+        (status_code = 403, description = "Admin role required."),
+        (status_code = 404, description = "User not found or deleted."),
+        (status_code = 409, description = "ROLE_TRANSITION_NOT_ALLOWED or LAST_ADMIN."),
+        (status_code = 500, description = "Internal server error."),
+    )
+)]
+pub async fn change_user_role(id: PathParam<String>, req: JsonBody<ChangeRoleRequest>, depot: &mut Depot) -> Res<AdminUserDto> {
+    let actor = admin_controller::require_admin(depot)?.sub.clone();
+    let user_id = parse_object_id(&id.into_inner(), depot)?;
+    let ChangeRoleRequest { role, force_relogin } = req.into_inner();
+
+    let mongodb = depot.obtain::<Arc<Database>>().expect("Database connection not found").clone();
+    let change = match UserAccountService::new(&mongodb).change_role(user_id, role.clone()).await {
+        Ok(change) => change,
+        Err(ServiceError::UserNotFound(_)) => return Err(ApiError::new(ErrorCode::NotFound).to_json(depot)),
+        Err(e @ ServiceError::RoleTransitionNotAllowed { .. }) => {
+            warn!("Admin {} attempted disallowed role change of user {}: {}", actor, user_id, e);
+            return Err(ApiError::new(ErrorCode::RoleTransitionNotAllowed).to_json(depot));
+        }
+        Err(ServiceError::LastAdmin(reason)) => {
+            warn!("Admin {} attempted to demote the last platform admin {}: {}", actor, user_id, reason);
+            return Err(ApiError::new(ErrorCode::LastAdmin).to_json(depot));
+        }
+        Err(e) => {
+            error!("Failed to change role of user {} to {:?}: {}", user_id, role, e);
+            return Err(ApiError::new(ErrorCode::DatabaseError).to_json(depot));
+        }
+    };
+
+    let entry = AuditLog::new(
+        &actor,
+        "user.role_change",
+        "user",
+        &change.user.wallet_address.to_lowercase(),
+        Some(mongodb::bson::doc! {
+            "from": format!("{:?}", change.previous),
+            "to": format!("{:?}", change.user.role),
+            "reason": "admin",
+            "force_relogin": force_relogin,
+        }),
+    );
+    if let Err(e) = AuditLogRepository::new(&mongodb).create(&entry).await {
+        error!("Failed to write role change audit entry for user {}: {}", user_id, e);
+    }
+
+    // 角色已变更，强制重新登录失败只记录日志，刷新令牌时按数据库中的角色签发
+    if force_relogin || is_demotion(&change.previous, &change.user.role) {
+        revoke_user_tokens(depot, &user_id.to_hex(), "role_change").await;
+    }
+    Ok(res_json_ok(Some(AdminUserDto::from(change.user))))
+}
+
+// 使用注入的 Redis 客户端
+fn enterprise_info_cache(depot: &Depot) -> RedisEnterpriseInfoCache {
+    let client = depot.obtain::<Arc<RedisClient>>().expect("Redis client not found in depot");
+    RedisEnterpriseInfoCache::new((**client).clone(), CFG.enterprise_binding.info_cache_ttl_secs)
+}
+
+// 用户当前绑定的企业，未绑定时返回 EnterpriseNotBound
+fn bound_enterprise(user: &User) -> Result<ObjectId, ErrorCode> {
+    user.enterprise_id.ok_or(ErrorCode::EnterpriseNotBound)
+}
+
+fn enterprise_info(user: &User, enterprise: Option<&Enterprise>) -> EnterpriseInfoResponse {
+    EnterpriseInfoResponse {
+        is_enterprise_bound: user.enterprise_id.is_some(),
+        enterprise_name: enterprise.map(|e| e.name.clone()),
+        enterprise_address: enterprise.map(|e| e.wallet_address.clone()),
+        enterprise_id: user.enterprise_id.map(|id| id.to_string()),
+    }
+}
+
+// 角色以数据库为准 (管理员改过角色后，旧令牌中的 role 可能已过期)
+fn current_user(user: &User, claims: Option<&Claims>) -> CurrentUserResponse {
+    CurrentUserResponse {
+        address: claims.map(|c| c.sub.clone()).unwrap_or_else(|| user.wallet_address.to_lowercase()),
+        role: role_claim(&user.role).to_string(),
+        user_id: user.id.map(|id| id.to_hex()).or_else(|| claims.map(|c| c.user_id.clone())).unwrap_or_default(),
+        enterprise: enterprise_info(user, None),
+        created_at: user.created_at.timestamp_millis(),
     }
 }
 
 // --- Helper Functions ---
+/// 32 字节随机数的十六进制表示
 fn generate_nonce() -> String {
     let mut bytes = [0u8; 32];
     rand::thread_rng().fill_bytes(&mut bytes);
-    format!("pharos-auth-{}", hex::encode(bytes))
+    hex::encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pharos_interact::mock::MockContract;
+
+    const SECRET: &str = "test-secret-test-secret-test-secret";
+
+    fn token_with_exp(exp: i64) -> String {
+        let claims = Claims {
+            sub: "0xabc".to_string(),
+            exp: exp as usize,
+            user_id: "64b000000000000000000001".to_string(),
+            role: "investor".to_string(),
+            jti: "jti-1".to_string(),
+            iat: 0,
+        };
+        JwtKeySet::hs256(SECRET).sign(&claims).unwrap()
+    }
+
+    #[test]
+    fn refresh_accepts_valid_and_recently_expired_tokens() {
+        let now = 1_700_000_000;
+        let claims = decode_refreshable(&token_with_exp(now + 600), &JwtKeySet::hs256(SECRET), now, 3600).unwrap();
+        assert_eq!(claims.role, "investor");
+        assert_eq!(claims.user_id, "64b000000000000000000001");
+        assert!(decode_refreshable(&token_with_exp(now - 3600), &JwtKeySet::hs256(SECRET), now, 3600).is_ok());
+    }
+
+    #[test]
+    fn refresh_rejects_tokens_expired_beyond_grace() {
+        let now = 1_700_000_000;
+        let err = decode_refreshable(&token_with_exp(now - 3601), &JwtKeySet::hs256(SECRET), now, 3600).unwrap_err();
+        assert_eq!(err, RefreshRejection::TokenExpired);
+        let err = decode_refreshable(&token_with_exp(now - 1), &JwtKeySet::hs256(SECRET), now, 0).unwrap_err();
+        assert_eq!(err, RefreshRejection::TokenExpired);
+    }
+
+    #[test]
+    fn refresh_rejects_foreign_signature() {
+        let now = 1_700_000_000;
+        let err = decode_refreshable(&token_with_exp(now + 600), &JwtKeySet::hs256("other-secret"), now, 3600).unwrap_err();
+        assert_eq!(err, RefreshRejection::InvalidToken);
+    }
+
+    #[test]
+    fn refresh_rejects_tokens_without_jti() {
+        let now = 1_700_000_000;
+        let claims = Claims { sub: "0xabc".to_string(), exp: (now + 600) as usize, user_id: String::new(), role: "investor".to_string(), jti: String::new(), iat: 0 };
+        let token = JwtKeySet::hs256(SECRET).sign(&claims).unwrap();
+        assert_eq!(decode_refreshable(&token, &JwtKeySet::hs256(SECRET), now, 3600).unwrap_err(), RefreshRejection::InvalidToken);
+    }
+
+    // 刷新后原令牌注销到刷新窗口结束，宽限期内已过期的令牌也不能再次换发
+    #[tokio::test]
+    async fn refreshed_token_is_revoked_through_grace_window() {
+        let denylist = crate::utils::token_denylist::MemoryTokenDenylist::new(std::time::Duration::from_secs(60));
+        let now = 1_700_000_000;
+        let claims = decode_refreshable(&token_with_exp(now - 600), &JwtKeySet::hs256(SECRET), now, 3600).unwrap();
+        assert!(revoke_claims(&denylist, &claims.jti, claims.exp, 3600, now).await.unwrap());
+        assert!(denylist.is_revoked(&claims.jti).await.unwrap());
+    }
+
+    #[test]
+    fn refresh_requires_the_same_account() {
+        let user = user_with_wallet(PRIMARY);
+        let claims = login_claims(&user, 1_700_000_000);
+        assert!(is_token_owner(&user, &claims));
+        // 钱包已归属重新注册的另一账户
+        assert!(!is_token_owner(&user_with_wallet(PRIMARY), &claims));
+        assert!(is_token_owner(&user, &Claims { user_id: String::new(), ..claims }));
+    }
+
+    fn mocked_verifier(magic: [u8; 4]) -> Eip1271Verifier<Provider<ethers::providers::MockProvider>> {
+        let (provider, mock) = Provider::mocked();
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(&magic);
+        mock.push::<Bytes, _>(Bytes::from(word.to_vec())).unwrap();
+        Eip1271Verifier::new(Arc::new(provider))
+    }
+
+    const SAFE: &str = "0x1111111111111111111111111111111111111111";
+
+    #[tokio::test]
+    async fn eoa_login_recovers_signer() {
+        use ethers::signers::{LocalWallet, Signer};
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let signature = wallet.sign_message("pharos-auth-nonce").await.unwrap().to_string();
+
+        let addr = resolve_login_address(hash_message("pharos-auth-nonce"), &signature, None, false, None).await.unwrap();
+        assert_eq!(addr, format!("0x{:x}", wallet.address()));
+    }
+
+    #[tokio::test]
+    async fn contract_wallet_login_accepts_magic_value() {
+        let verifier = mocked_verifier(pharos_interact::EIP1271_MAGIC_VALUE);
+        let addr = resolve_login_address(hash_message("pharos-auth-nonce"), "0x1234", Some(SAFE), true, Some(&verifier)).await.unwrap();
+        assert_eq!(addr, SAFE);
+    }
+
+    #[tokio::test]
+    async fn contract_wallet_login_rejects_other_values() {
+        let verifier = mocked_verifier([0xff, 0xff, 0xff, 0xff]);
+        let err = resolve_login_address(hash_message("pharos-auth-nonce"), "0x1234", Some(SAFE), true, Some(&verifier)).await.unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidSignature);
+    }
+
+    #[tokio::test]
+    async fn failed_recovery_falls_back_to_eip1271_for_claimed_address() {
+        // 非 65 字节的 Safe 签名无法按 ECDSA 解析，未给 walletType 提示时也应走合约校验
+        let verifier = mocked_verifier(pharos_interact::EIP1271_MAGIC_VALUE);
+        let addr = resolve_login_address(hash_message("pharos-auth-nonce"), "0x1234", Some(SAFE), false, Some(&verifier)).await.unwrap();
+        assert_eq!(addr, SAFE);
+
+        let err = resolve_login_address(hash_message("pharos-auth-nonce"), "0x1234", None, false, Some(&verifier)).await.unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidSignatureFormat);
+    }
+
+    fn user_with_wallet(wallet: &str) -> User {
+        let mut user = User::new(wallet.to_string(), "".to_string(), UserRole::Investor);
+        user.id = Some(ObjectId::new());
+        user
+    }
+
+    const PRIMARY: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const SECOND: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    #[test]
+    fn current_user_reports_investor_role() {
+        let user = user_with_wallet(PRIMARY);
+        let claims = login_claims(&user, 1_700_000_000);
+        let me = current_user(&user, Some(&claims));
+
+        assert_eq!(me.role, "investor");
+        assert_eq!(me.address, PRIMARY);
+        assert_eq!(me.user_id, user.id.unwrap().to_hex());
+        assert_eq!(me.created_at, user.created_at.timestamp_millis());
+        assert!(!me.enterprise.is_enterprise_bound);
+
+        let json = serde_json::to_value(&me).unwrap();
+        assert_eq!(json["role"], "investor");
+        assert_eq!(json["userId"], me.user_id);
+        assert_eq!(json["enterprise"], serde_json::json!({ "isEnterpriseBound": false }));
+    }
+
+    #[test]
+    fn current_user_reports_creditor_role_and_binding() {
+        let mut user = user_with_wallet(PRIMARY);
+        let enterprise_id = ObjectId::new();
+        user.enterprise_id = Some(enterprise_id);
+        user.role = UserRole::EnterpriseAdmin;
+        // 令牌签发于角色变更之前，仍以数据库中的角色为准
+        let stale = Claims { role: "investor".to_string(), ..login_claims(&user, 1_700_000_000) };
+        let me = current_user(&user, Some(&stale));
+
+        assert_eq!(me.role, "creditor");
+        assert!(me.enterprise.is_enterprise_bound);
+        assert_eq!(me.enterprise.enterprise_id, Some(enterprise_id.to_string()));
+        assert_eq!(serde_json::to_value(&me).unwrap()["role"], "creditor");
+    }
+
+    #[test]
+    fn login_claims_record_issue_time() {
+        let user = user_with_wallet(PRIMARY);
+        let claims = login_claims(&user, 1_700_000_000);
+        assert_eq!(claims.iat, 1_700_000_000);
+        assert_eq!(claims.issued_at(), 1_700_000_000);
+        // 没有 iat 的旧令牌按有效期推算
+        let legacy = Claims { iat: 0, ..claims };
+        assert_eq!(legacy.issued_at(), 1_700_000_000);
+    }
+
+    #[test]
+    fn demotions_revoke_tokens() {
+        use UserRole::*;
+        assert!(is_demotion(&PlatformAdmin, &EnterpriseAdmin));
+        assert!(is_demotion(&PlatformAdmin, &Investor));
+        assert!(is_demotion(&EnterpriseAdmin, &Investor));
+        assert!(!is_demotion(&Investor, &EnterpriseAdmin));
+        assert!(!is_demotion(&EnterpriseAdmin, &PlatformAdmin));
+        assert!(!is_demotion(&Investor, &Investor));
+    }
+
+    #[test]
+    fn link_accepts_unowned_wallet() {
+        let user = user_with_wallet(PRIMARY);
+        assert_eq!(check_link_target(&user, None, SECOND), Ok(()));
+    }
+
+    #[test]
+    fn link_rejects_duplicates() {
+        let mut user = user_with_wallet(PRIMARY);
+        assert_eq!(check_link_target(&user, Some(&user.clone()), PRIMARY), Err(ErrorCode::WalletAlreadyLinked));
+
+        user.linked_wallets.push(SECOND.to_string());
+        assert_eq!(check_link_target(&user, Some(&user.clone()), &SECOND.to_uppercase().replace("0X", "0x")), Err(ErrorCode::WalletAlreadyLinked));
+
+        let other = user_with_wallet("0xcccccccccccccccccccccccccccccccccccccccc");
+        assert_eq!(check_link_target(&other, Some(&user), SECOND), Err(ErrorCode::WalletBelongsToAnotherUser));
+    }
+
+    #[test]
+    fn login_via_linked_wallet_keeps_user_id() {
+        let mut user = user_with_wallet(PRIMARY);
+        user.linked_wallets.push(SECOND.to_string());
+        // process_login 通过关联钱包找到的是同一账户文档
+        assert!(user.owns_wallet(SECOND));
+
+        let claims = login_claims(&user, 1_700_000_000);
+        assert_eq!(claims.user_id, user.id.unwrap().to_hex());
+        assert_eq!(claims.sub, PRIMARY);
+    }
+
+    #[test]
+    fn link_challenge_is_bound_to_account_and_wallet() {
+        let message = link_challenge_message(PRIMARY, &SECOND.to_uppercase().replace("0X", "0x"), "n1");
+        assert!(message.starts_with(&link_challenge_message(PRIMARY, SECOND, "")));
+        assert!(!message.starts_with(&link_challenge_message(SECOND, PRIMARY, "")));
+    }
+
+    #[test]
+    fn promotion_on_bind_requires_onchain_authorization() {
+        let binding = |promote_on_bind, require_onchain_authorization| configs::cfgs::EnterpriseBinding {
+            promote_on_bind,
+            require_onchain_authorization,
+            ..Default::default()
+        };
+        assert!(binding(true, true).promotes_on_bind());
+        // 未在链上确认所有权时，绑定任意企业都不能获得企业管理员权限
+        assert!(!binding(true, false).promotes_on_bind());
+        assert!(!binding(false, true).promotes_on_bind());
+    }
+
+    #[test]
+    fn unbind_requires_bound_enterprise() {
+        let mut user = user_with_wallet(PRIMARY);
+        assert_eq!(bound_enterprise(&user), Err(ErrorCode::EnterpriseNotBound));
+
+        let enterprise_id = ObjectId::new();
+        user.enterprise_id = Some(enterprise_id);
+        assert_eq!(bound_enterprise(&user), Ok(enterprise_id));
+        assert!(enterprise_info(&user, None).is_enterprise_bound);
+
+        // unbind_enterprise 把 enterprise_id 置空后，再次解绑返回 EnterpriseNotBound，企业信息显示未绑定
+        user.enterprise_id = None;
+        assert_eq!(bound_enterprise(&user), Err(ErrorCode::EnterpriseNotBound));
+        let info = enterprise_info(&user, None);
+        assert!(!info.is_enterprise_bound);
+        assert!(info.enterprise_id.is_none());
+    }
+
+    #[tokio::test]
+    async fn concurrent_logins_with_same_request_id_succeed_once() {
+        use crate::utils::nonce_store::MemoryNonceStore;
+        use ethers::signers::{LocalWallet, Signer};
+        use std::time::Duration;
+
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+        store.put("req-1", "pharos-auth-nonce").await.unwrap();
+        let signature = wallet.sign_message("pharos-auth-nonce").await.unwrap().to_string();
+
+        let login = |store: Arc<MemoryNonceStore>, signature: String| {
+            tokio::spawn(async move {
+                let req = LoginRequest { request_id: "req-1".to_string(), signature, address: None, wallet_type: None };
+                verify_login_challenge(store.as_ref(), &req, None, 1_700_000_000).await
+            })
+        };
+        let (first, second) = tokio::join!(login(store.clone(), signature.clone()), login(store.clone(), signature));
+        let results = [first.unwrap(), second.unwrap()];
+
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        assert!(results.contains(&Err(ErrorCode::NonceNotFoundOrExpired)));
+        assert!(results.contains(&Ok(format!("0x{:x}", wallet.address()))));
+    }
+
+    #[tokio::test]
+    async fn typed_challenge_login_recovers_bound_signer() {
+        use crate::utils::nonce_store::MemoryNonceStore;
+        use ethers::signers::{LocalWallet, Signer};
+        use std::time::Duration;
+
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let other: LocalWallet = "0x1111111111111111111111111111111111111111111111111111111111111111".parse().unwrap();
+        let address = format!("0x{:x}", wallet.address());
+        let typed = build_login_challenge("Pharos-RWA", Some(1), &address, "abc123", 1_700_000_000, 1_700_000_300).unwrap();
+        let store = MemoryNonceStore::new(Duration::from_secs(60));
+
+        let login = |request_id: &str, signature: Signature| LoginRequest {
+            request_id: request_id.to_string(),
+            signature: signature.to_string(),
+            address: None,
+            wallet_type: None,
+        };
+
+        store.put("typed-1", &encode_stored(&typed).unwrap()).await.unwrap();
+        let req = login("typed-1", wallet.sign_typed_data(&typed).await.unwrap());
+        assert_eq!(verify_login_challenge(&store, &req, None, 1_700_000_100).await, Ok(address.clone()));
+
+        // 其他钱包对同一挑战签名
+        store.put("typed-2", &encode_stored(&typed).unwrap()).await.unwrap();
+        let req = login("typed-2", other.sign_typed_data(&typed).await.unwrap());
+        assert_eq!(verify_login_challenge(&store, &req, None, 1_700_000_100).await, Err(ErrorCode::InvalidSignature));
+
+        // 类型化挑战不接受 personal_sign 签名
+        store.put("typed-3", &encode_stored(&typed).unwrap()).await.unwrap();
+        let req = login("typed-3", wallet.sign_message("abc123").await.unwrap());
+        assert_eq!(verify_login_challenge(&store, &req, None, 1_700_000_100).await, Err(ErrorCode::InvalidSignature));
+    }
+
+    #[tokio::test]
+    async fn login_rejects_signed_challenge_past_embedded_expiry() {
+        use crate::utils::nonce_store::MemoryNonceStore;
+        use ethers::signers::{LocalWallet, Signer};
+        use std::time::Duration;
+
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let address = format!("0x{:x}", wallet.address());
+        // 缓存 TTL 远长于挑战有效期：条目仍在，但签入的过期时间已过
+        let store = MemoryNonceStore::new(Duration::from_secs(3600));
+        let (issued_at, expires_at) = (1_700_000_000, 1_700_000_300);
+        let login = |request_id: &str, signature: Signature| LoginRequest {
+            request_id: request_id.to_string(),
+            signature: signature.to_string(),
+            address: None,
+            wallet_type: None,
+        };
+
+        let message = with_validity("pharos-auth-abc123", issued_at, expires_at);
+        let signature = wallet.sign_message(&message).await.unwrap();
+        store.put("plain-1", &message).await.unwrap();
+        assert_eq!(verify_login_challenge(&store, &login("plain-1", signature), None, expires_at + 1).await, Err(ErrorCode::ChallengeExpired));
+        // 挑战只能使用一次，过期被拒后也已删除
+        assert_eq!(store.take("plain-1").await.unwrap(), None);
+        store.put("plain-2", &message).await.unwrap();
+        assert_eq!(verify_login_challenge(&store, &login("plain-2", signature), None, expires_at).await, Ok(address.clone()));
+
+        let typed = build_login_challenge("Pharos-RWA", Some(1), &address, "abc123", issued_at as u64, expires_at as u64).unwrap();
+        let signature = wallet.sign_typed_data(&typed).await.unwrap();
+        store.put("typed-1", &encode_stored(&typed).unwrap()).await.unwrap();
+        assert_eq!(verify_login_challenge(&store, &login("typed-1", signature), None, expires_at + 1).await, Err(ErrorCode::ChallengeExpired));
+        store.put("typed-2", &encode_stored(&typed).unwrap()).await.unwrap();
+        assert_eq!(verify_login_challenge(&store, &login("typed-2", signature), None, issued_at).await, Ok(address));
+    }
+
+    #[tokio::test]
+    async fn login_rejects_claimed_address_with_bad_checksum() {
+        let err = resolve_login_address(hash_message("pharos-auth-nonce"), "0x1234", Some("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD"), true, None)
+            .await
+            .unwrap_err();
+        assert_eq!(err, ErrorCode::InvalidAddress);
+    }
+
+    const ENTERPRISE: &str = "0x2222222222222222222222222222222222222222";
+    const SIGNER: &str = "0x3333333333333333333333333333333333333333";
+    const STRANGER: &str = "0x4444444444444444444444444444444444444444";
+
+    #[tokio::test]
+    async fn bind_requires_onchain_signer() {
+        let contract = MockContract::default().with_signers(vec![SIGNER.parse().unwrap()]);
+        assert_eq!(verify_enterprise_authorization(Some(&contract), ENTERPRISE, SIGNER).await, Ok(()));
+        assert_eq!(
+            verify_enterprise_authorization(Some(&contract), ENTERPRISE, STRANGER).await,
+            Err(ErrorCode::NotAuthorizedForEnterprise)
+        );
+        assert_eq!(ErrorCode::NotAuthorizedForEnterprise.status(), 403);
+    }
+
+    #[tokio::test]
+    async fn bind_authorization_fails_closed_without_chain() {
+        let failing = MockContract::default().unavailable("rpc unavailable");
+        assert_eq!(verify_enterprise_authorization(Some(&failing), ENTERPRISE, SIGNER).await, Err(ErrorCode::BlockchainUnavailable));
+        assert_eq!(verify_enterprise_authorization(None, ENTERPRISE, SIGNER).await, Err(ErrorCode::BlockchainUnavailable));
+    }
 }
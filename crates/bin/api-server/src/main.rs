@@ -5,27 +5,55 @@ mod utils;
 
 use common::config::logger;
 use configs::CFG;
-use log::{info, error};
+use tracing::{info, error};
 use salvo::cors::Cors;
 use salvo::prelude::*;
 use salvo::oapi::OpenApi;
-use service::{db::init_mongodb};
+use service::db::{create_audit_log_indexes, create_enterprise_indexes, create_holding_indexes, create_invoice_indexes, create_onchain_transaction_indexes, create_repayment_payout_indexes, create_token_mint_indexes, create_transaction_indexes, create_user_indexes, exempt_legacy_listed_invoices, init_mongodb};
+use service::service::PendingTransactionTracker;
 
 use std::sync::Arc;
-use pharos_interact::initialize_contract_from_env;
+use std::time::Duration;
+use pharos_interact::{initialize_contract, initialize_signature_verifier, ChainSettings, RetryConfig};
 use anyhow::Context;
 use service::cache::init_redis_client;
 
 #[tokio::main]
 async fn main() {
     // Initialize logging
-    log4rs::init_file("config/log4rs.yaml", Default::default()).context("Failed to initialize log4rs").expect("Failed to initialize log4rs");
+    log4rs::init_file("config/log4rs.yaml", utils::log_buffer::deserializers()).context("Failed to initialize log4rs").expect("Failed to initialize log4rs");
+    // 请求范围的 JSON 日志 (带 request_id)
+    utils::request_id::init_json_logging();
 
     let db_config = CFG.database.clone();
     let redis_config = CFG.redis.clone();
     let server_config = CFG.server.clone();
     info!("Configuration loaded successfully.");
 
+    if let Err(e) = utils::pagination::validate_config() {
+        error!("Invalid pagination config: {}", e);
+        panic!("Invalid pagination config: {}", e);
+    }
+
+    if let Err(e) = utils::nonce_store::validate_config() {
+        error!("Invalid auth challenge config: {}", e);
+        panic!("Invalid auth challenge config: {}", e);
+    }
+
+    if let Err(e) = utils::secrets::validate_config() {
+        error!("Refusing to start with invalid secrets: {}", e);
+        panic!("Refusing to start with invalid secrets: {}", e);
+    }
+
+    if let Err(e) = utils::jwt_keys::validate_config() {
+        error!("Invalid JWT key config: {}", e);
+        panic!("Invalid JWT key config: {}", e);
+    }
+
+    if CFG.enterprise_binding.promote_on_bind && !CFG.enterprise_binding.require_onchain_authorization {
+        tracing::warn!("enterprise_binding.promote_on_bind is ignored because require_onchain_authorization is disabled");
+    }
+
     // Initialize MongoDB connection (async)
     let mongodb = match init_mongodb(&db_config).await {
         Ok(db) => Arc::new(db),
@@ -35,6 +63,38 @@ async fn main() {
             panic!("MongoDB connection failed!");
         }
     };
+    if let Err(e) = create_invoice_indexes(&mongodb).await {
+        error!("Failed to create invoice indexes: {}", e);
+    }
+    if let Err(e) = create_transaction_indexes(&mongodb).await {
+        error!("Failed to create transaction indexes: {}", e);
+    }
+    if let Err(e) = create_onchain_transaction_indexes(&mongodb).await {
+        error!("Failed to create onchain transaction indexes: {}", e);
+    }
+    if let Err(e) = create_audit_log_indexes(&mongodb).await {
+        error!("Failed to create audit log indexes: {}", e);
+    }
+    if let Err(e) = create_enterprise_indexes(&mongodb).await {
+        error!("Failed to create enterprise indexes: {}", e);
+    }
+    if let Err(e) = create_repayment_payout_indexes(&mongodb).await {
+        error!("Failed to create repayment payout indexes: {}", e);
+    }
+    if let Err(e) = create_token_mint_indexes(&mongodb).await {
+        error!("Failed to create token mint indexes: {}", e);
+    }
+    if let Err(e) = create_holding_indexes(&mongodb).await {
+        error!("Failed to create holding indexes: {}", e);
+    }
+    if let Err(e) = create_user_indexes(&mongodb).await {
+        error!("Failed to create user indexes: {}", e);
+    }
+    match exempt_legacy_listed_invoices(&mongodb).await {
+        Ok(0) => {}
+        Ok(count) => info!("Exempted {} legacy listed invoices from funding terms acceptance", count),
+        Err(e) => error!("Failed to migrate legacy listed invoices: {}", e),
+    }
 
     // Initialize Redis Client (sync)
     let redis_client = match init_redis_client(&redis_config) {
@@ -47,14 +107,55 @@ async fn main() {
     };
 
     // Initialize blockchain contract connection (async)
-    let contract = match initialize_contract_from_env().await {
-        Ok(contract) => {
-            info!("Blockchain contract connection initialized successfully");
-            Some(Arc::new(contract))
-        },
+    // 环境变量 (含 .env) 优先于 config.toml 的 [chain]
+    let chain = match (ChainSettings {
+        chain_id: CFG.chain.chain_id,
+        rpc_url: CFG.chain.rpc_url.clone(),
+        invoice_contract: CFG.chain.invoice_contract.clone(),
+        stable_tokens: CFG.chain.stable_tokens.clone(),
+    })
+    .with_env_overrides()
+    {
+        Ok(chain) => chain,
+        Err(e) => {
+            error!("Invalid chain configuration: {}", e);
+            panic!("Invalid chain configuration: {}", e);
+        }
+    };
+    if chain.chain_id == 0 {
+        tracing::warn!("chain.chain_id is not configured, skipping RPC chain ID check");
+    }
+    let contract = if !chain.is_configured() {
+        tracing::warn!("chain.rpc_url / chain.invoice_contract not configured, starting without contract capability");
+        None
+    } else {
+        match initialize_contract(&chain).await {
+            Ok(contract) => {
+                info!("Blockchain contract connection initialized successfully");
+                let retry = &CFG.contract_retry;
+                let contract = contract
+                    .with_retry(RetryConfig {
+                        max_attempts: retry.max_attempts.max(1),
+                        base_delay_ms: retry.base_delay_ms,
+                        max_delay_ms: retry.max_delay_ms,
+                    })
+                    // 广播后立即登记待确认交易，供 GET /transaction/{tx_hash} 和回执轮询使用
+                    .with_broadcast_observer(Arc::new(PendingTransactionTracker::new(&mongodb)));
+                Some(Arc::new(contract))
+            }
+            Err(e) => {
+                // 已配置链却连不上或连错网络 (ChainIdMismatch) 时直接拒绝启动，避免带着错误的链上状态运行
+                error!("Refusing to start: {:#} (rpc_url: {})", e, chain.rpc_url);
+                panic!("Refusing to start: {:#}", e);
+            }
+        }
+    };
+
+    // EIP-1271 合约钱包登录校验 (只读 provider)
+    let signature_verifier = match initialize_signature_verifier(&chain.rpc_url) {
+        Ok(verifier) => Some(Arc::new(verifier)),
         Err(e) => {
-            error!("Failed to initialize blockchain contract connection: {}", e);
-            // Don't panic, just continue without contract capability
+            error!("Failed to initialize contract wallet signature verifier: {}", e);
             None
         }
     };
@@ -64,7 +165,8 @@ async fn main() {
 
 
     // Initialize services and create the main router
-    let service = router::init_service(mongodb, redis_client, contract) ;// Returns Router
+    let shutdown = utils::shutdown::Shutdown::new();
+    let service = router::init_service(mongodb, redis_client, contract, signature_verifier, &shutdown) ;// Returns Router
 
 
     // Setup server address
@@ -75,5 +177,20 @@ async fn main() {
     // info!("Swagger UI available at http://{:?}/swagger-ui", address);
 
     // Start Server with the configured router
-    Server::new(listener).serve(service).await;
+    let server = Server::new(listener);
+    let handle = server.handle();
+    let grace = Duration::from_secs(server_config.shutdown_grace_secs);
+    // 收到停机信号后停止接受新连接，宽限期内等待进行中的请求与后台任务结束
+    let drained = tokio::spawn(async move {
+        utils::shutdown::wait_for_signal().await;
+        info!("Shutting down, waiting up to {:?} for in-flight requests and background tasks", grace);
+        handle.stop_graceful(Some(grace));
+        let unfinished = shutdown.drain(grace).await;
+        if !unfinished.is_empty() {
+            error!("Forced shutdown with unfinished work: {:?}", unfinished);
+        }
+    });
+    server.serve(service).await;
+    let _ = drained.await;
+    info!("Server stopped");
 }
@@ -1,14 +1,25 @@
 use std::time::Instant;
 
-use log::info;
 use salvo::{Depot, FlowCtrl, Request, Response, handler};
 
-use crate::utils::res::res_json_custom;
-use salvo::{prelude::*, http::StatusCode};
+use crate::controller::Claims;
+use crate::utils::api_error::{ApiError, ErrorCode};
+use crate::utils::client_ip::{IpAllowlist, client_ip, forwarded_https};
+use crate::utils::feature_flags::{FEATURE_HEADER, FEATURE_OVERRIDES_KEY, parse_overrides};
+use crate::utils::i18n::{LOCALE_KEY, Locale};
+use crate::utils::metrics;
+use crate::utils::request_id::{REQUEST_ID_HEADER, REQUEST_ID_KEY, resolve_request_id};
+use tracing::Instrument;
+use crate::utils::rate_limiter::{RateDecision, RateLimiter, RedisRateLimiter};
+use configs::cfgs::RateLimit as RateLimitCfg;
+use redis::Client as RedisClient;
+use std::net::IpAddr;
+use std::sync::Arc;
+use salvo::{prelude::*, http::{Method, StatusCode}};
 use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
 use serde::{Deserialize, Serialize};
 use configs::CFG;
-use log::{error, warn, debug};
+use tracing::{debug, error, info, warn};
 #[handler]
 pub async fn route_logger(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
     // 记录开始时间
@@ -29,7 +40,7 @@ pub async fn route_logger(req: &mut Request, depot: &mut Depot, res: &mut Respon
     res.headers_mut().insert("X-Response-Time", format!("{:.3}ms", duration_ms).parse().unwrap());
 
     // 输出日志
-    log::warn!(
+    tracing::warn!(
         target: "response_time",
         "{} {} - {:.3}ms",
         method,
@@ -38,6 +49,29 @@ pub async fn route_logger(req: &mut Request, depot: &mut Depot, res: &mut Respon
     );
 }
 
+/// 生成或沿用 `X-Request-Id`，写入 depot 与响应头，后续处理在带 request_id 的 span 中执行
+#[handler]
+pub async fn request_id(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let id = resolve_request_id(req.header::<String>(REQUEST_ID_HEADER).as_deref());
+    depot.insert(REQUEST_ID_KEY, id.clone());
+    if let Ok(value) = id.parse() {
+        res.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let span = tracing::info_span!("request", request_id = %id, method = %req.method(), path = %req.uri().path());
+    ctrl.call_next(req, depot, res).instrument(span).await;
+}
+
+/// 记录请求数、状态码与耗时。路径标签使用路由模板而不是原始路径
+#[handler]
+pub async fn track_metrics(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let start = Instant::now();
+    ctrl.call_next(req, depot, res).await;
+
+    let status = res.status_code.unwrap_or(StatusCode::OK).as_u16();
+    let path = metrics::path_label(req.uri().path(), req.params().iter().map(|(k, v)| (k.as_str(), v.as_str())), status);
+    metrics::observe_request(req.method().as_str(), &path, status, start.elapsed().as_secs_f64());
+}
 
 // JWT声明结构
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,3 +82,659 @@ pub struct Claims {
     pub iat: Option<usize>,      // 签发时间（可选）
 }
 
+
+/// 设置安全响应头，并按配置对非 HTTPS 请求进行重定向或拒绝
+#[handler]
+pub async fn security_headers(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let cfg = &CFG.security;
+
+    if cfg.https_enforcement != "off" && !is_https_request(req, cfg.trust_forwarded_proto) {
+        ctrl.skip_rest();
+        if cfg.https_enforcement == "redirect" {
+            let host = req.header::<String>("host").unwrap_or_default();
+            let path = req.uri().path_and_query().map(|p| p.as_str()).unwrap_or("/");
+            res.render(Redirect::permanent(format!("https://{}{}", host, path)));
+        } else {
+            warn!("Rejected plaintext request to {}", req.uri().path());
+            res.status_code(StatusCode::FORBIDDEN);
+            res.render(ApiError::new(ErrorCode::HttpsRequired).render::<()>(Locale::from_request(req)));
+        }
+        set_security_headers(res);
+        return;
+    }
+
+    ctrl.call_next(req, depot, res).await;
+    set_security_headers(res);
+}
+
+fn is_https_request(req: &Request, trust_forwarded_proto: bool) -> bool {
+    // X-Forwarded-Proto 只采信来自 security.trusted_proxies 的请求
+    if trust_forwarded_proto {
+        if let Some(https) = forwarded_https(req) {
+            return https;
+        }
+    }
+    req.uri().scheme_str() == Some("https")
+}
+
+fn set_security_headers(res: &mut Response) {
+    let cfg = &CFG.security;
+    let headers = [
+        ("strict-transport-security", &cfg.hsts),
+        ("x-content-type-options", &cfg.content_type_options),
+        ("x-frame-options", &cfg.frame_options),
+        ("content-security-policy", &cfg.content_security_policy),
+    ];
+    for (name, value) in headers {
+        if value.is_empty() {
+            continue;
+        }
+        match value.parse() {
+            Ok(v) => {
+                res.headers_mut().insert(name, v);
+            }
+            Err(_) => error!("Invalid value configured for header {}: {}", name, value),
+        }
+    }
+}
+
+/// 根据 Accept-Language 确定错误信息语言，写入 depot
+#[handler]
+pub async fn detect_locale(req: &mut Request, depot: &mut Depot) {
+    depot.insert(LOCALE_KEY, Locale::from_request(req));
+}
+
+/// 解析 `X-Feature-Flags` 请求头写入 depot，是否生效由 `depot.feature()` 根据管理员身份判断
+#[handler]
+pub async fn parse_feature_overrides(req: &mut Request, depot: &mut Depot) {
+    if let Some(header) = req.header::<String>(FEATURE_HEADER) {
+        depot.insert(FEATURE_OVERRIDES_KEY, parse_overrides(&header));
+    }
+}
+
+static ADMIN_IP_ALLOWLIST: once_cell::sync::Lazy<IpAllowlist> = once_cell::sync::Lazy::new(|| IpAllowlist::from_config(&CFG.admin.ip_allowlist));
+
+/// 管理接口来源 IP 白名单，`admin.ip_allowlist` 为空时不做限制，配置了但全部非法时拒绝所有来源
+#[handler]
+pub async fn admin_ip_allowlist(req: &mut Request, res: &mut Response, ctrl: &mut FlowCtrl) {
+    if !ADMIN_IP_ALLOWLIST.allows(client_ip(req)) {
+        warn!("Rejected admin request to {} from {:?}", req.uri().path(), client_ip(req));
+        ctrl.skip_rest();
+        res.status_code(StatusCode::FORBIDDEN);
+        res.render(ApiError::new(ErrorCode::Forbidden).render::<()>(Locale::from_request(req)));
+    }
+}
+
+/// 按 JWT 中的 `role` 限制访问，须挂在 `auth_token` 之后 (依赖 depot 中的 claims)
+pub struct RequireRole(pub Vec<String>);
+
+impl RequireRole {
+    pub fn new(roles: &[&str]) -> Self {
+        Self(roles.iter().map(|r| r.to_string()).collect())
+    }
+
+    fn allows(&self, role: &str) -> bool {
+        self.0.iter().any(|r| r == role)
+    }
+}
+
+#[async_trait]
+impl Handler for RequireRole {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let role = match depot.get::<Claims>("claims") {
+            Ok(claims) if self.allows(&claims.role) => return,
+            Ok(claims) => {
+                warn!("User {} with role {} rejected from {} (allowed: {:?})", claims.sub, claims.role, req.uri().path(), self.0);
+                claims.role.clone()
+            }
+            Err(_) => {
+                ctrl.skip_rest();
+                res.render(ApiError::new(ErrorCode::Unauthenticated).to_json::<()>(depot));
+                return;
+            }
+        };
+        let code = if self.0.len() == 1 && self.0[0] == "admin" { ErrorCode::AdminRoleRequired } else { ErrorCode::Forbidden };
+        debug!("Rejected role {} with {}", role, code.as_str());
+        ctrl.skip_rest();
+        res.status_code(StatusCode::FORBIDDEN);
+        res.render(ApiError::new(code).to_json::<()>(depot));
+    }
+}
+
+/// 限制 POST / PUT / PATCH 请求体大小，超出时在解析请求体之前返回 413。
+/// 有 Content-Length 时直接比较；分块传输时最多读取上限字节，读取的内容缓存在请求中供 handler 解析。
+/// 票据附件上传等接口通过 [`BodySizeLimit::allow_multipart`] 登记，只有这些接口的 multipart 请求
+/// 由接口自身的文件大小限制约束，其他接口的 multipart 请求照常检查
+pub struct BodySizeLimit {
+    max_bytes: usize,
+    /// 允许大体积 multipart 的 (方法, 路由模板)，模板与指标标签相同 (`/rwa/invoice/{id}/document`)
+    multipart_routes: Vec<(Method, String)>,
+}
+
+impl BodySizeLimit {
+    pub fn new(max_bytes: usize) -> Self {
+        Self { max_bytes, multipart_routes: Vec::new() }
+    }
+
+    pub fn allow_multipart(mut self, method: Method, pattern: impl Into<String>) -> Self {
+        self.multipart_routes.push((method, pattern.into()));
+        self
+    }
+
+    fn has_limited_body(&self, req: &Request) -> bool {
+        if !matches!(*req.method(), Method::POST | Method::PUT | Method::PATCH) {
+            return false;
+        }
+        let multipart = req
+            .headers()
+            .get(salvo::http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.trim_start().to_ascii_lowercase().starts_with("multipart/"));
+        !(multipart && self.is_upload_route(req))
+    }
+
+    fn is_upload_route(&self, req: &Request) -> bool {
+        if self.multipart_routes.is_empty() {
+            return false;
+        }
+        let Some(pattern) = metrics::route_pattern(req.uri().path(), req.params().iter().map(|(k, v)| (k.as_str(), v.as_str()))) else {
+            return false;
+        };
+        self.multipart_routes.iter().any(|(method, route)| method == req.method() && *route == pattern)
+    }
+}
+
+fn declared_length(req: &Request) -> Option<u64> {
+    req.headers()
+        .get(salvo::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok())
+}
+
+#[async_trait]
+impl Handler for BodySizeLimit {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if !self.has_limited_body(req) {
+            return;
+        }
+        let too_large = match declared_length(req) {
+            Some(len) => len > self.max_bytes as u64,
+            None => req.payload_with_max_size(self.max_bytes).await.is_err(),
+        };
+        if !too_large {
+            // handler 解析请求体时同样不超过上限
+            req.set_secure_max_size(self.max_bytes);
+            return;
+        }
+        warn!("Rejected {} {} with body over {} bytes", req.method(), req.uri().path(), self.max_bytes);
+        ctrl.skip_rest();
+        res.status_code(StatusCode::PAYLOAD_TOO_LARGE);
+        res.render(ApiError::new(ErrorCode::PayloadTooLarge).to_json::<()>(depot));
+    }
+}
+
+/// 请求处理时限：超时后丢弃后续 handler 的 future (取消进行中的 RPC / 数据库调用) 并返回 504。
+/// SSE 等流式接口在 handler 返回后才开始推送，不受此限制。
+/// 提交链上交易的接口 (结算、撤销、批量登记等) 通过 [`RequestTimeout::exempt`] 豁免：
+/// 交易广播后取消 future 会丢掉回执与后续的落库，造成链上已执行而数据库未更新
+pub struct RequestTimeout {
+    timeout: std::time::Duration,
+    /// 豁免的 (方法, 路由模板)，模板与指标标签相同 (`/rwa/invoice/{id}/settle`)
+    exempt: Vec<(Method, String)>,
+}
+
+impl RequestTimeout {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self { timeout: std::time::Duration::from_millis(timeout_ms), exempt: Vec::new() }
+    }
+
+    pub fn exempt(mut self, method: Method, pattern: impl Into<String>) -> Self {
+        self.exempt.push((method, pattern.into()));
+        self
+    }
+
+    fn is_exempt(&self, req: &Request) -> bool {
+        if self.exempt.is_empty() {
+            return false;
+        }
+        let Some(pattern) = metrics::route_pattern(req.uri().path(), req.params().iter().map(|(k, v)| (k.as_str(), v.as_str()))) else {
+            return false;
+        };
+        self.exempt.iter().any(|(method, exempt)| method == req.method() && *exempt == pattern)
+    }
+}
+
+#[async_trait]
+impl Handler for RequestTimeout {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        if self.timeout.is_zero() || self.is_exempt(req) {
+            return;
+        }
+        if tokio::time::timeout(self.timeout, ctrl.call_next(req, depot, res)).await.is_ok() {
+            return;
+        }
+        warn!("{} {} timed out after {:?}", req.method(), req.uri().path(), self.timeout);
+        ctrl.skip_rest();
+        res.status_code(StatusCode::GATEWAY_TIMEOUT);
+        res.render(ApiError::new(ErrorCode::RequestTimeout).to_json::<()>(depot));
+    }
+}
+
+/// 登录挑战限流 (按来源 IP 与钱包地址计数)，超出时返回 429 并带 Retry-After
+#[handler]
+pub async fn challenge_rate_limit(req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+    let cfg = &CFG.rate_limit;
+    if !cfg.enabled {
+        return;
+    }
+    let Ok(redis_client) = depot.obtain::<Arc<RedisClient>>() else {
+        warn!("Redis client not found in depot, skipping challenge rate limit");
+        return;
+    };
+    let limiter = RedisRateLimiter::new((**redis_client).clone());
+    // 请求体会被缓存，后续 handler 仍可读取
+    let address = req
+        .parse_json::<serde_json::Value>()
+        .await
+        .ok()
+        .and_then(|body| body.get("address").and_then(|a| a.as_str()).map(|a| a.to_lowercase()));
+
+    if let Some(retry_after_secs) = check_challenge_limits(&limiter, client_ip(req), address.as_deref(), cfg).await {
+        ctrl.skip_rest();
+        res.status_code(StatusCode::TOO_MANY_REQUESTS);
+        res.add_header("Retry-After", retry_after_secs.to_string(), true).ok();
+        res.render(ApiError::new(ErrorCode::TooManyRequests).to_json::<()>(depot));
+    }
+}
+
+/// 依次检查 IP 与地址计数，返回需要等待的秒数。限流存储不可用时放行
+pub(crate) async fn check_challenge_limits<L: RateLimiter + ?Sized>(
+    limiter: &L,
+    ip: Option<IpAddr>,
+    address: Option<&str>,
+    cfg: &RateLimitCfg,
+) -> Option<u64> {
+    let mut checks = Vec::with_capacity(2);
+    if let Some(ip) = ip {
+        checks.push((format!("challenge:ip:{}", ip), cfg.challenge_per_ip));
+    }
+    if let Some(address) = address {
+        checks.push((format!("challenge:addr:{}", address), cfg.challenge_per_address));
+    }
+    for (key, limit) in checks {
+        match limiter.hit(&key, limit, cfg.window_secs).await {
+            Ok(RateDecision::Allowed) => {}
+            Ok(RateDecision::Limited { retry_after_secs }) => {
+                warn!("Challenge rate limit exceeded for {}", key);
+                return Some(retry_after_secs);
+            }
+            Err(e) => warn!("Rate limiter unavailable for {}: {}", key, e),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{admin_controller, common_controller, enterprise_controller, stats_controller, user_controller};
+    use salvo::test::{ResponseExt, TestClient};
+    use std::io::Write;
+    use std::sync::Mutex;
+
+    /// 模拟 auth_token 写入 claims
+    struct WithRole(&'static str);
+
+    #[async_trait]
+    impl Handler for WithRole {
+        async fn handle(&self, _req: &mut Request, depot: &mut Depot, _res: &mut Response, _ctrl: &mut FlowCtrl) {
+            depot.insert(
+                "claims",
+                Claims {
+                    sub: "0xabc".to_string(),
+                    exp: usize::MAX,
+                    user_id: "64b000000000000000000001".to_string(),
+                    role: self.0.to_string(),
+                    jti: String::new(),
+                    iat: 0,
+                },
+            );
+        }
+    }
+
+    #[handler]
+    async fn ok() -> &'static str {
+        "ok"
+    }
+
+    #[handler]
+    async fn not_found(res: &mut Response) {
+        res.status_code(StatusCode::NOT_FOUND);
+    }
+
+    fn service(role: &'static str, allowed: &[&str], route: Router) -> Service {
+        Service::new(Router::new().hoop(WithRole(role)).hoop(RequireRole::new(allowed)).push(route))
+    }
+
+    #[tokio::test]
+    async fn test_investor_is_rejected_from_admin_route() {
+        // 角色不符时在进入管理员 handler 之前返回 403
+        let service = service("investor", &["admin"], Router::with_path("admin/features").get(admin_controller::list_features));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/features").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        let service = service("creditor", &["admin"], Router::with_path("admin/features").get(admin_controller::list_features));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/features").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        // 平台统计未注入 StatsService，能返回 403 说明未进入 handler
+        let service = service("investor", &["admin"], Router::with_path("admin/stats").get(stats_controller::admin_stats));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/stats").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        // 企业列表同样未注入数据库连接
+        let service = service("investor", &["admin"], Router::with_path("admin/enterprises").get(enterprise_controller::list_enterprises_admin));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/enterprises?name=acme").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        // 用户列表同理
+        let service = service("creditor", &["admin"], Router::with_path("admin/users").get(user_controller::list_users_admin));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/users?role=Investor&bound=false").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+
+        // 未挂 RequireRole 时 handler 自身同样拒绝非管理员
+        let service = Service::new(Router::new().hoop(WithRole("investor")).push(Router::with_path("admin/users").get(user_controller::list_users_admin)));
+        let mut res = TestClient::get("http://127.0.0.1:5800/admin/users").send(&service).await;
+        let body: serde_json::Value = res.take_json().await.unwrap();
+        assert_eq!(body["code"], 403);
+        assert_eq!(body["error"]["code"], "ADMIN_ROLE_REQUIRED");
+    }
+
+    #[tokio::test]
+    async fn test_allowed_roles_pass() {
+        let service = service("admin", &["admin"], Router::with_path("admin/ping").get(ok));
+        let res = TestClient::get("http://127.0.0.1:5800/admin/ping").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let service = service("creditor", &["creditor", "admin"], Router::with_path("enterprise/create").post(ok));
+        let res = TestClient::post("http://127.0.0.1:5800/enterprise/create").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        let service = service("investor", &["creditor", "admin"], Router::with_path("enterprise/create").post(ok));
+        let res = TestClient::post("http://127.0.0.1:5800/enterprise/create").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::FORBIDDEN));
+    }
+
+    /// 记录 handler 是否被调用
+    #[derive(Clone, Default)]
+    struct Reached(Arc<std::sync::atomic::AtomicBool>);
+
+    #[async_trait]
+    impl Handler for Reached {
+        async fn handle(&self, req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            let body: serde_json::Value = req.parse_json().await.unwrap_or_default();
+            res.render(Text::Plain(body.to_string()));
+        }
+    }
+
+    fn limited_service(max_bytes: usize, reached: &Reached) -> Service {
+        Service::new(
+            Router::new()
+                .hoop(BodySizeLimit::new(max_bytes).allow_multipart(Method::POST, "/invoice/{id}/document"))
+                .push(Router::with_path("user/login").post(reached.clone()).put(reached.clone()).get(reached.clone()))
+                .push(Router::with_path("invoice/{id}/document").post(reached.clone())),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_rejected_before_handler() {
+        let reached = Reached::default();
+        let service = limited_service(64, &reached);
+        let body = serde_json::json!({ "address": "0xabc", "signature": "f".repeat(200) });
+
+        let res = TestClient::post("http://127.0.0.1:5800/user/login").json(&body).send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::PAYLOAD_TOO_LARGE));
+        let res = TestClient::put("http://127.0.0.1:5800/user/login").json(&body).send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(!reached.0.load(std::sync::atomic::Ordering::SeqCst), "handler must not run for oversized bodies");
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_reaches_handler() {
+        let reached = Reached::default();
+        let service = limited_service(64, &reached);
+        let mut res = TestClient::post("http://127.0.0.1:5800/user/login")
+            .json(&serde_json::json!({ "address": "0xabc" }))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        assert_eq!(res.take_string().await.unwrap(), r#"{"address":"0xabc"}"#);
+        assert!(reached.0.load(std::sync::atomic::Ordering::SeqCst));
+
+        // GET 与登记过的附件上传接口不受限制
+        let res = TestClient::get("http://127.0.0.1:5800/user/login").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let res = TestClient::post("http://127.0.0.1:5800/invoice/64b0c0ffee0000000000abcd/document")
+            .add_header("content-type", "multipart/form-data; boundary=x", true)
+            .body("x".repeat(200))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_multipart_limited_outside_upload_routes() {
+        let reached = Reached::default();
+        let service = limited_service(64, &reached);
+        let res = TestClient::post("http://127.0.0.1:5800/user/login")
+            .add_header("content-type", "multipart/form-data; boundary=x", true)
+            .body("x".repeat(200))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PAYLOAD_TOO_LARGE));
+        // 上传接口的其他方法照常检查
+        let res = TestClient::put("http://127.0.0.1:5800/user/login")
+            .add_header("content-type", "multipart/form-data; boundary=x", true)
+            .body("x".repeat(200))
+            .send(&service)
+            .await;
+        assert_eq!(res.status_code, Some(StatusCode::PAYLOAD_TOO_LARGE));
+        assert!(!reached.0.load(std::sync::atomic::Ordering::SeqCst));
+    }
+
+    /// 模拟卡住的下游调用，记录 handler future 是否被丢弃
+    #[derive(Clone, Default)]
+    struct SlowUpstream {
+        dropped: Arc<std::sync::atomic::AtomicBool>,
+    }
+
+    struct DropFlag(Arc<std::sync::atomic::AtomicBool>);
+
+    impl Drop for DropFlag {
+        fn drop(&mut self) {
+            self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[async_trait]
+    impl Handler for SlowUpstream {
+        async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+            let _guard = DropFlag(self.dropped.clone());
+            tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            res.render(Text::Plain("finished"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_handler_times_out_with_504() {
+        let upstream = SlowUpstream::default();
+        let service = Service::new(
+            Router::new()
+                .hoop(RequestTimeout::new(50))
+                .push(Router::with_path("purchase").post(upstream.clone())),
+        );
+
+        let started = Instant::now();
+        let res = TestClient::post("http://127.0.0.1:5800/purchase").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::GATEWAY_TIMEOUT));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(upstream.dropped.load(std::sync::atomic::Ordering::SeqCst), "handler future must be dropped on timeout");
+    }
+
+    #[tokio::test]
+    async fn test_fast_handler_within_timeout() {
+        let reached = Reached::default();
+        let service = Service::new(
+            Router::new()
+                .hoop(RequestTimeout::new(1_000))
+                .push(Router::with_path("user/login").post(reached.clone())),
+        );
+        let res = TestClient::post("http://127.0.0.1:5800/user/login").json(&serde_json::json!({})).send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+
+        // 0 表示不限制
+        let service = Service::new(Router::new().hoop(RequestTimeout::new(0)).push(Router::with_path("user/login").post(reached.clone())));
+        let res = TestClient::post("http://127.0.0.1:5800/user/login").json(&serde_json::json!({})).send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+    }
+
+    #[tokio::test]
+    async fn test_exempt_route_is_not_cancelled() {
+        let upstream = SlowUpstream::default();
+        let fast = Reached::default();
+        let service = Service::new(
+            Router::new()
+                .hoop(RequestTimeout::new(50).exempt(Method::POST, "/invoice/{id}/settle"))
+                .push(Router::with_path("invoice/{id}/settle").post(fast.clone()))
+                .push(Router::with_path("invoice/{id}/cancel").post(upstream.clone())),
+        );
+
+        let res = TestClient::post("http://127.0.0.1:5800/invoice/64b000000000000000000001/settle").json(&serde_json::json!({})).send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let res = TestClient::post("http://127.0.0.1:5800/invoice/64b000000000000000000001/cancel").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::GATEWAY_TIMEOUT));
+
+        // 豁免的路由超过时限后仍在执行，不返回 504
+        let service = Service::new(
+            Router::new()
+                .hoop(RequestTimeout::new(50).exempt(Method::POST, "/admin/settle/batch"))
+                .push(Router::with_path("admin/settle/batch").post(SlowUpstream::default())),
+        );
+        let request = TestClient::post("http://127.0.0.1:5800/admin/settle/batch").send(&service);
+        assert!(tokio::time::timeout(std::time::Duration::from_millis(300), request).await.is_err(), "exempt handler must keep running");
+    }
+
+    fn limits() -> RateLimitCfg {
+        RateLimitCfg { enabled: true, window_secs: 60, challenge_per_ip: 3, challenge_per_address: 2 }
+    }
+
+    #[tokio::test]
+    async fn test_challenge_limited_per_address() {
+        let limiter = crate::utils::rate_limiter::MemoryRateLimiter::default();
+        let cfg = limits();
+        // 同一地址从不同 IP 请求
+        for i in 0..2 {
+            let ip: IpAddr = format!("10.0.0.{}", i).parse().unwrap();
+            assert_eq!(check_challenge_limits(&limiter, Some(ip), Some("0xabc"), &cfg).await, None);
+        }
+        let retry = check_challenge_limits(&limiter, Some("10.0.0.9".parse().unwrap()), Some("0xabc"), &cfg).await;
+        assert!(matches!(retry, Some(secs) if secs >= 1 && secs <= 60));
+        assert_eq!(check_challenge_limits(&limiter, Some("10.0.0.9".parse().unwrap()), Some("0xdef"), &cfg).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_challenge_limited_per_ip() {
+        let limiter = crate::utils::rate_limiter::MemoryRateLimiter::default();
+        let cfg = limits();
+        let ip: IpAddr = "192.168.1.7".parse().unwrap();
+        // 同一 IP 轮换地址
+        for i in 0..3 {
+            assert_eq!(check_challenge_limits(&limiter, Some(ip), Some(format!("0x{}", i).as_str()), &cfg).await, None);
+        }
+        assert!(check_challenge_limits(&limiter, Some(ip), Some("0xnew"), &cfg).await.is_some());
+        assert_eq!(check_challenge_limits(&limiter, Some("192.168.1.8".parse().unwrap()), None, &cfg).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_metrics_scrape_counts_requests_by_route() {
+        let service = Service::new(
+            Router::new()
+                .hoop(track_metrics)
+                .push(Router::with_path("metrics-test/{id}").get(ok))
+                .push(Router::with_path("metrics").get(common_controller::metrics))
+                .push(Router::with_path("{**path}").get(not_found)),
+        );
+        for id in ["a1", "b2", "c3"] {
+            let res = TestClient::get(format!("http://127.0.0.1:5800/metrics-test/{}", id)).send(&service).await;
+            assert_eq!(res.status_code, Some(StatusCode::OK));
+        }
+        // 扫描器探测的随机路径由兜底路由返回 404
+        for probe in ["wp-login.php", ".git/config", "random/probe/path"] {
+            let res = TestClient::get(format!("http://127.0.0.1:5800/{}", probe)).send(&service).await;
+            assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+        }
+
+        let mut res = TestClient::get("http://127.0.0.1:5800/metrics").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::OK));
+        let body = res.take_string().await.unwrap();
+        assert!(body.contains(r#"http_requests_total{method="GET",path="/metrics-test/{id}",status="200"} 3"#), "{}", body);
+        assert!(body.contains(r#"http_request_duration_seconds_count{method="GET",path="/metrics-test/{id}"} 3"#));
+        assert!(!body.contains("/metrics-test/a1"));
+        assert!(body.contains(r#"http_requests_total{method="GET",path="unmatched",status="404"} 3"#), "{}", body);
+        assert!(!body.contains("wp-login.php"));
+    }
+
+    /// 收集 JSON 日志行
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> tracing_subscriber::fmt::MakeWriter<'a> for CapturedLogs {
+        type Writer = CapturedLogs;
+
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[handler]
+    async fn two_log_lines() -> &'static str {
+        tracing::info!("challenge issued");
+        tokio::task::yield_now().await;
+        tracing::info!("login verified");
+        "ok"
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_echoed_and_logged() {
+        let logs = CapturedLogs::default();
+        let _guard = tracing::subscriber::set_default(crate::utils::request_id::json_subscriber(logs.clone()));
+        let service = Service::new(Router::new().hoop(request_id).push(Router::with_path("login").post(two_log_lines)));
+
+        let res = TestClient::post("http://127.0.0.1:5800/login").add_header("X-Request-Id", "trace-abc-1", true).send(&service).await;
+        assert_eq!(res.headers().get("x-request-id").unwrap(), "trace-abc-1");
+
+        // 未携带时生成新的 ID
+        let res = TestClient::post("http://127.0.0.1:5800/login").send(&service).await;
+        let generated = res.headers().get("x-request-id").unwrap().to_str().unwrap().to_string();
+        assert_ne!(generated, "trace-abc-1");
+
+        let output = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<serde_json::Value> = output.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 4);
+        for (line, expected) in lines.iter().zip(["trace-abc-1", "trace-abc-1", generated.as_str(), generated.as_str()]) {
+            assert_eq!(line["span"]["request_id"], expected, "{}", line);
+        }
+    }
+}
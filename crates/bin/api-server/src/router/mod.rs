@@ -1,13 +1,17 @@
 use crate::{
-    controller::{common_controller, swagger_controller},
-    router::middware::route_logger,
+    controller::{common_controller, swagger_controller, user_controller},
+    router::middware::{BodySizeLimit, RequestTimeout, detect_locale, parse_feature_overrides, request_id, route_logger, security_headers, track_metrics},
+    utils::nonce_store::{AuthNonceStore, auth_nonce_store},
+    utils::token_denylist::{self, AuthTokenDenylist, auth_token_denylist},
+    utils::cors::build_cors,
+    utils::health::{HealthChecks, HealthProbe, MongoProbe, RedisProbe, RpcProbe},
+    utils::shutdown::Shutdown,
 };
 
 use configs::{cfgs::Redis as RedisConfig, CFG};
 use mongodb::Database; // Changed from sea_orm::DatabaseConnection
 use redis::Client as RedisClient;
 use salvo::Handler;
-use salvo::cors::Cors;
 use salvo::http::Method;
 use salvo::{
     Router,
@@ -16,18 +20,24 @@ use salvo::{
     catcher::Catcher,
     handler, // Import async_trait and handler macros
     logging::Logger,
-    oapi::{OpenApi, Operation, swagger_ui::SwaggerUi},
+    oapi::{
+        OpenApi, Operation,
+        security::{Http, HttpAuthScheme, SecurityScheme},
+        swagger_ui::SwaggerUi,
+    },
     prelude::{CatchPanic, Depot, FlowCtrl, Request, Response, SessionHandler},
     serve_static::StaticDir,
     session::CookieStore,
 };
 use service::invoice::InvoiceService; // Import InvoiceService
 use service::service::PurchaseService; // Import PurchaseService
-use service::cache::InvoiceRedisService;
+use service::cache::{InvoiceEventBus, InvoiceRedisService, ReservationService, TokenHolderCache};
 use service::repository::{TokenRepository, InvoiceRepository, EnterpriseRepository, UserRepository};
-use service::service::TokenService;
-use std::{env, sync::Arc};
-use pharos_interact::{InvoiceContract, ContractQuerier, ContractWriter}; // Import for contract interaction
+use service::service::{MongoTransferStore, RecordingContractWriter, SharedContractWriter, StatsService, TokenMintConfig, TokenMintService, TokenService, TransactionPoller, TransactionPollerConfig, WebhookService};
+use service::service::webhook_service::WebhookConfig;
+use std::{env, path::{Path, PathBuf}, sync::Arc, time::Duration};
+use pharos_interact::{InvoiceContract, ContractQuerier, ContractWriter, Eip1271Verifier}; // Import for contract interaction
+use pharos_interact::{RpcTransferSource, TransferIndexer, TransferIndexerConfig, TransferSyncHandle};
 use ethers::middleware::SignerMiddleware;
 use ethers::providers::{Http, Provider};
 use ethers::signers::LocalWallet;
@@ -37,7 +47,7 @@ pub mod router;
 use router::{
     init_user_router, init_enterprise_router, init_invoice_router, 
     init_purchase_router, init_admin_router, init_transaction_router, 
-    init_interest_router, init_token_router
+    init_interest_router, init_token_router, init_stats_router, init_reservation_router, init_chain_router
 }; 
 
 // --- Injection Middleware Struct ---
@@ -46,9 +56,19 @@ struct InjectConnections {
     mongodb: Arc<Database>, // Changed from db_conn: Arc<DatabaseConnection>
     redis_client: Arc<RedisClient>,
     contract: Option<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>, // Contract connection
+    contract_writer: Option<SharedContractWriter>, // 链上写操作入口，记录每次提交并登记待确认交易
     invoice_service: Arc<InvoiceService>, // Add InvoiceService
     purchase_service: Arc<PurchaseService>, // Add PurchaseService
     token_service: Arc<TokenService>, // Add TokenService
+    stats_service: Arc<StatsService>,
+    webhook_service: Arc<WebhookService>,
+    invoice_event_bus: Arc<InvoiceEventBus>,
+    reservation_service: Arc<ReservationService>,
+    nonce_store: Arc<AuthNonceStore>,
+    token_denylist: Arc<AuthTokenDenylist>,
+    signature_verifier: Option<Arc<Eip1271Verifier<Provider<Http>>>>, // EIP-1271 contract wallet login
+    health_checks: Arc<HealthChecks>,
+    transfer_sync: Option<TransferSyncHandle>, // ERC20 转账索引状态，未启动时为空
 }
 
 #[async_trait]
@@ -59,11 +79,27 @@ impl Handler for InjectConnections {
         depot.inject(self.invoice_service.clone()); // Inject InvoiceService
         depot.inject(self.purchase_service.clone()); // Inject PurchaseService
         depot.inject(self.token_service.clone()); // Inject TokenService
+        depot.inject(self.stats_service.clone());
+        depot.inject(self.webhook_service.clone());
+        depot.inject(self.invoice_event_bus.clone());
+        depot.inject(self.reservation_service.clone());
+        depot.inject(self.nonce_store.clone());
+        depot.inject(self.token_denylist.clone());
+        depot.inject(self.health_checks.clone());
         
         // Inject contract connection if available
         if let Some(contract) = &self.contract {
             depot.inject(contract.clone());
         }
+        if let Some(contract_writer) = &self.contract_writer {
+            depot.inject(contract_writer.clone());
+        }
+        if let Some(verifier) = &self.signature_verifier {
+            depot.inject(verifier.clone());
+        }
+        if let Some(transfer_sync) = &self.transfer_sync {
+            depot.inject(transfer_sync.clone());
+        }
         
         // Indicate that the next handler should be called
         ctrl.call_next(req, depot, res).await;
@@ -73,11 +109,29 @@ impl Handler for InjectConnections {
 // init_router remains mostly the same, but doesn't add inject_connections middleware here
 pub fn init_router() -> Router {
     let current_dir = env::current_dir().unwrap();
-    log::warn!("Current working directory: {:?}", current_dir);
-    let static_router = Router::with_path("/<**path>").get(StaticDir::new(current_dir.join("/static")).defaults("index.html").auto_list(true));
+    tracing::info!("Current working directory: {:?}", current_dir);
+    let static_dir = resolve_static_dir(&current_dir, &CFG.server.static_dir);
+    if !static_dir.is_dir() {
+        tracing::warn!("Static directory {:?} does not exist, static files will not be served", static_dir);
+    }
+    let static_router = static_router(static_dir);
+
+    // 存活 / 就绪探针，不带 API 前缀且无需认证
+    let health_router = Router::new()
+        .push(Router::with_path("healthz").get(common_controller::healthz))
+        .push(Router::with_path("readyz").get(common_controller::readyz))
+        .push(Router::with_path("metrics").get(common_controller::metrics));
 
     // Base router without connection injection yet
-    let router = Router::new().hoop(Logger::new()).hoop(CatchPanic::new()).push(static_router);
+    let router = Router::new()
+        .hoop(request_id)
+        .hoop(Logger::new())
+        .hoop(CatchPanic::new())
+        .hoop(track_metrics)
+        .hoop(document_uploads_allowed(BodySizeLimit::new(CFG.server.max_body_bytes), &CFG.server.api_prefix))
+        .hoop(onchain_writes_exempt(RequestTimeout::new(CFG.server.request_timeout_ms), &CFG.server.api_prefix))
+        .push(health_router)
+        .push(static_router);
 
     // Business routes under /rwa prefix
     let api_router = Router::with_path(&CFG.server.api_prefix) // Use configured prefix
@@ -85,21 +139,24 @@ pub fn init_router() -> Router {
         .push(init_enterprise_router()) // Add enterprise routes
         .push(init_invoice_router()) // Keep non-RWA invoice routes if needed
         .push(init_purchase_router()) // Add RWA purchase routes
+        .push(init_reservation_router())
+        .push(init_chain_router())
         .push(init_admin_router()) // Add admin routes
         .push(init_transaction_router()) // Add transaction routes 
         .push(init_interest_router()) // Add interest routes
-        .push(init_token_router()); // Add token routes
-
+        .push(init_token_router()) // Add token routes
+        .push(init_stats_router()); // Add public stats routes
 
     let router = router.push(api_router);
 
     // Swagger UI and docs setup
-    let session_handler = SessionHandler::builder(CookieStore::new(), b"salvo-adminsalvo-adminalvo-adminsalvo-admin2023salvo-admin2023salvo-admin2023")
+    // 密钥长度已在启动时由 secrets::validate_config 校验
+    let session_handler = SessionHandler::builder(CookieStore::new(), CFG.session.secret.as_bytes())
         .build()
-        .unwrap();
+        .expect("session.secret (SESSION_SECRET) must be at least 64 bytes");
 
     // OpenAPI Documentation
-    let doc = OpenApi::new("Pharos-RWA", "0.1.1").merge_router(&router);
+    let doc = api_doc(&router);
 
     let router = router.push(
         Router::new()
@@ -115,25 +172,129 @@ pub fn init_router() -> Router {
     router
 }
 
+/// 提交链上交易的接口不受请求时限约束 (代币铸造已在后台任务中提交，不经过请求)
+fn onchain_writes_exempt(timeout: RequestTimeout, api_prefix: &str) -> RequestTimeout {
+    let prefix = api_prefix.trim_end_matches('/');
+    [
+        "/invoice/batch",
+        "/invoice/{id}/settle",
+        "/admin/settle/batch",
+        "/admin/invoice/{id}/cancel",
+    ]
+    .into_iter()
+    .fold(timeout, |timeout, path| timeout.exempt(Method::POST, format!("{}{}", prefix, path)))
+}
+
+/// 票据附件上传由接口自身的文件大小限制约束，只有这里列出的接口允许超出请求体上限的 multipart
+fn document_uploads_allowed(limit: BodySizeLimit, api_prefix: &str) -> BodySizeLimit {
+    let prefix = api_prefix.trim_end_matches('/');
+    limit.allow_multipart(Method::POST, format!("{}/invoice/{{id}}/document", prefix))
+}
+
+/// 解析静态文件目录：相对路径基于 `base` (工作目录)，绝对路径保持不变
+pub fn resolve_static_dir(base: &Path, configured: &str) -> PathBuf {
+    base.join(configured.trim())
+}
+
+fn static_router(dir: PathBuf) -> Router {
+    Router::with_path("/<**path>").get(StaticDir::new(dir).defaults("index.html").auto_list(true))
+}
+
+/// 需要认证的接口在 `#[endpoint]` 中声明 `security(("bearerAuth" = []))`，Swagger UI 据此显示 Authorize 按钮
+pub const BEARER_AUTH_SCHEME: &str = "bearerAuth";
+
+/// 生成 OpenAPI 文档并注册 JWT Bearer 认证方式
+pub fn api_doc(router: &Router) -> OpenApi {
+    OpenApi::new("Pharos-RWA", "0.1.1")
+        .add_security_scheme(
+            BEARER_AUTH_SCHEME,
+            SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer).bearer_format("JWT").description("登录接口返回的 access token")),
+        )
+        .merge_router(router)
+}
+
+/// 所有链上写操作经过 RecordingContractWriter：记录操作结果和写入指标 (待确认交易由合约广播时登记)
+fn recording_writer<W: ContractWriter + Send + Sync + 'static>(contract: Arc<W>, mongodb: &Database) -> SharedContractWriter {
+    Arc::new(RecordingContractWriter::new(contract, mongodb))
+}
+
 // Modify init_service to create and inject InvoiceService
 pub fn init_service(
     mongodb: Arc<Database>, 
     redis_client: Arc<RedisClient>,
     contract: Option<Arc<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>>>,
+    signature_verifier: Option<Arc<Eip1271Verifier<Provider<Http>>>>,
+    shutdown: &Shutdown,
 ) -> Service {
     let router = init_router();
 
-    // Create InvoiceService instance 
-    let invoice_service = Arc::new(InvoiceService::new((*mongodb).clone(), (*redis_client).clone()));
+    // Create WebhookService and resume retries left over from the previous run
+    let webhook_service = Arc::new(WebhookService::new(mongodb.clone(), WebhookConfig {
+        max_attempts: CFG.webhook.max_attempts,
+        base_backoff_secs: CFG.webhook.base_backoff_secs,
+        timeout_secs: CFG.webhook.timeout_secs,
+        signing_key: CFG.webhook.signing_key.clone(),
+        allow_private_targets: CFG.webhook.allow_private_targets,
+    }));
+    {
+        let webhook_service = webhook_service.clone();
+        shutdown.register_task("webhook_resume", tokio::spawn(async move {
+            if let Err(e) = webhook_service.resume_pending_deliveries().await {
+                tracing::error!("Failed to resume pending webhook deliveries: {}", e);
+            }
+        }));
+    }
+    shutdown.register_task("webhook_deliveries", webhook_service.spawn_drain(shutdown.subscribe()));
+
+    // Create InvoiceService instance, status changes are pushed to enterprise webhooks and SSE subscribers
+    let invoice_event_bus = Arc::new(InvoiceEventBus::new((*redis_client).clone()));
+    let invoice_service = Arc::new(
+        InvoiceService::new((*mongodb).clone(), (*redis_client).clone())
+            .with_status_notifier(webhook_service.clone())
+            .with_status_notifier(invoice_event_bus.clone())
+            .with_day_count(CFG.interest.day_count)
+    );
 
     // Create Redis service for the PurchaseService
     let redis_service = Arc::new(InvoiceRedisService::new((*redis_client).clone()));
     
+    // Create ReservationService instance (shares the invoice cache with purchases)
+    let reservation_service = Arc::new(
+        ReservationService::new((*redis_client).clone(), redis_service.clone(), CFG.reservation.ttl_secs)
+            .with_purchase_lock_ttl(CFG.purchase.lock_ttl_ms)
+    );
+    shutdown.register_task("reservation_sweeper", reservation_service.spawn_sweeper(
+        Duration::from_secs(CFG.reservation.sweep_interval_secs),
+        CFG.reservation.sweep_batch_size,
+        shutdown.subscribe(),
+    ));
+
+    let contract_writer = contract.as_ref().map(|contract| recording_writer(contract.clone(), &mongodb));
+
     // Create PurchaseService instance
-    let purchase_service = Arc::new(PurchaseService::new(Arc::new(mongodb.client().clone()), redis_service));
+    let purchase_service = Arc::new(PurchaseService::new(mongodb.clone(), redis_service)
+        .with_reservations(reservation_service.clone())
+        .with_repayment_lock(&CFG.settlement)
+        .with_purchase_lock_ttl(CFG.purchase.lock_ttl_ms)
+        .with_status_notifier(webhook_service.clone())
+        .with_status_notifier(invoice_event_bus.clone())
+        .with_self_funding_check(CFG.purchase.prevent_self_funding)
+        .with_issuer_verification_check(CFG.purchase.require_verified_issuer));
+
+    // 认购写入的代币铸造记录由后台任务上链，需要区块链连接
+    if let Some(contract_writer) = &contract_writer {
+        let token_mint_service = Arc::new(TokenMintService::new(&mongodb, contract_writer.clone(), TokenMintConfig {
+            interval_secs: CFG.token_mint.interval_secs,
+            batch_size: CFG.token_mint.batch_size,
+            max_attempts: CFG.token_mint.max_attempts.max(1),
+            base_backoff_secs: CFG.token_mint.base_backoff_secs,
+        }));
+        shutdown.register_task("token_mint_worker", token_mint_service.spawn_worker(shutdown.subscribe()));
+    }
 
     // Create repositories for the TokenService
     let token_repository = Arc::new(TokenRepository::new(mongodb.clone()));
+    let transfer_token_repository = token_repository.clone();
     let invoice_repository = Arc::new(InvoiceRepository::new(&mongodb));
     let enterprise_repository = Arc::new(EnterpriseRepository::new(&mongodb));
     let user_repository = Arc::new(UserRepository::new(&mongodb));
@@ -144,25 +305,158 @@ pub fn init_service(
         invoice_repository,
         enterprise_repository,
         user_repository,
-        mongodb.clone()
+        mongodb.clone(),
+        TokenHolderCache::new((*redis_client).clone(), CFG.stats.holders_cache_ttl_secs),
     ));
 
+    // Create StatsService instance
+    let stats_service = Arc::new(StatsService::new(mongodb.clone(), (*redis_client).clone(), CFG.stats.cache_ttl_secs)
+        .with_admin_cache_ttl(CFG.stats.admin_cache_ttl_secs));
+
+    // 链上交易回执轮询，需要区块链连接
+    if let Some(contract) = &contract {
+        let transaction_poller = Arc::new(TransactionPoller::new(contract.client(), &mongodb, TransactionPollerConfig {
+            confirmations: CFG.transaction_poller.confirmations,
+            interval_secs: CFG.transaction_poller.interval_secs,
+            batch_size: CFG.transaction_poller.batch_size,
+        }));
+        shutdown.register_task("transaction_poller", transaction_poller.spawn_poller(shutdown.subscribe()));
+    }
+
+    // ERC20 Transfer 事件索引，同步代币链上余额与转账历史，需要区块链连接
+    let transfer_sync = match &contract {
+        Some(contract) if CFG.transfer_indexer.enabled => {
+            let indexer = Arc::new(TransferIndexer::new(
+                RpcTransferSource::new(contract.client()),
+                MongoTransferStore::new(&mongodb, transfer_token_repository),
+                TransferIndexerConfig {
+                    start_block: CFG.transfer_indexer.start_block,
+                    confirmation_depth: CFG.transfer_indexer.confirmation_depth,
+                    max_block_range: CFG.transfer_indexer.max_block_range,
+                    interval_secs: CFG.transfer_indexer.interval_secs,
+                },
+            ));
+            shutdown.register_task("transfer_indexer", indexer.spawn(shutdown.subscribe()));
+            Some(indexer.status())
+        }
+        _ => None,
+    };
+
+    // 登录挑战 nonce 存储 (Redis，多实例共享)
+    let nonce_store = Arc::new(auth_nonce_store((*redis_client).clone(), Duration::from_secs(CFG.auth.nonce_ttl_secs)));
+    // 已注销令牌黑名单，进程内副本保留到令牌最长可用时间 (有效期 + 刷新宽限期)
+    let token_denylist = Arc::new(auth_token_denylist(
+        (*redis_client).clone(),
+        Duration::from_secs((user_controller::TOKEN_LIFETIME_SECS + CFG.jwt.refresh_grace_secs.max(0)) as u64),
+    ));
+    if CFG.auth.denylist_cleanup_interval_secs > 0 {
+        shutdown.register_task("token_denylist_cleanup", token_denylist::spawn_cleanup(
+            token_denylist.clone(),
+            Duration::from_secs(CFG.auth.denylist_cleanup_interval_secs),
+            shutdown.subscribe(),
+        ));
+    }
+
+    // 就绪检查依赖
+    let probes: Vec<Arc<dyn HealthProbe>> = vec![
+        Arc::new(MongoProbe(mongodb.clone())),
+        Arc::new(RedisProbe(redis_client.clone())),
+        Arc::new(RpcProbe(contract.as_ref().map(|c| c.client()))),
+    ];
+    let health_checks = Arc::new(HealthChecks::new(probes));
+
     // Create the injector instance
     let injector = InjectConnections {
         mongodb, 
         redis_client,
         contract,
+        contract_writer,
         invoice_service, // Inject the created service
         purchase_service, // Inject the PurchaseService
         token_service, // Inject the TokenService
+        stats_service,
+        webhook_service,
+        invoice_event_bus,
+        reservation_service,
+        nonce_store,
+        token_denylist,
+        signature_verifier,
+        health_checks,
+        transfer_sync,
     };
-    let cors = Cors::new()
-        .allow_origin("*")
-        .allow_methods(vec![Method::GET, Method::POST,Method::PUT, Method::DELETE])
-        .into_handler();
+    let cors = build_cors(&CFG.server.cors_origins, CFG.is_production());
     // Apply CORS, then injection, then catcher, then router
     Service::new(router)
+        .hoop(shutdown.in_flight()) // 最外层登记进行中的请求，停机时等待其结束
+        .hoop(detect_locale)
+        .hoop(parse_feature_overrides)
+        .hoop(security_headers)
         .hoop(cors)
         .hoop(injector) // Use the injector instance
         .catcher(Catcher::default().hoop(common_controller::catcher_err))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_declares_bearer_auth() {
+        let doc = serde_json::to_value(api_doc(&Router::new().push(init_user_router()))).unwrap();
+
+        let scheme = &doc["components"]["securitySchemes"][BEARER_AUTH_SCHEME];
+        assert_eq!(scheme["type"], "http");
+        assert_eq!(scheme["scheme"], "bearer");
+
+        let paths = &doc["paths"];
+        assert_eq!(paths["/user/enterprise-info"]["get"]["security"], serde_json::json!([{ BEARER_AUTH_SCHEME: [] }]));
+        // 未认证接口保持开放
+        assert!(paths["/user/challenge"]["post"].get("security").is_none());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_writes_through_wired_writer_are_recorded() {
+        use pharos_interact::mock::MockContract;
+        use service::repository::ContractOperationRepository;
+        use service::test_support::TestDb;
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let writer = recording_writer(Arc::new(MockContract::default().failing_writes("execution reverted")), &test_db);
+
+        assert!(writer.invalidate_invoice("INV-1".to_string()).await.is_err());
+        let (failures, total) = ContractOperationRepository::new(&test_db).find_failures(Some("invalidate_invoice"), 0, 10).await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(total, 1);
+        assert_eq!(failures[0].reference, "INV-1");
+    }
+
+    #[test]
+    fn test_static_dir_is_relative_to_working_dir() {
+        let base = Path::new("/srv/app");
+        assert_eq!(resolve_static_dir(base, "./static"), PathBuf::from("/srv/app/./static"));
+        assert_eq!(resolve_static_dir(base, "static"), PathBuf::from("/srv/app/static"));
+        assert_eq!(resolve_static_dir(base, "/var/www"), PathBuf::from("/var/www"));
+    }
+
+    #[tokio::test]
+    async fn test_serves_file_from_configured_dir() {
+        use salvo::http::StatusCode;
+        use salvo::test::{ResponseExt, TestClient};
+
+        let base = env::temp_dir().join(format!("static-dir-test-{}", std::process::id()));
+        let dir = resolve_static_dir(&base, "./static");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hello.txt"), "hello static").unwrap();
+
+        let service = Service::new(static_router(dir));
+        let mut res = TestClient::get("http://127.0.0.1:5800/hello.txt").send(&service).await;
+        assert_eq!(res.status_code.unwrap_or(StatusCode::OK), StatusCode::OK);
+        assert_eq!(res.take_string().await.unwrap(), "hello static");
+        let res = TestClient::get("http://127.0.0.1:5800/missing.txt").send(&service).await;
+        assert_eq!(res.status_code, Some(StatusCode::NOT_FOUND));
+
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
@@ -1,7 +1,8 @@
 use salvo::Router;
 
+use crate::router::middware::{RequireRole, admin_ip_allowlist, challenge_rate_limit};
 use crate::controller::{
-    common_controller, enterprise_controller, interest_controller, invoice_controller, purchase_controller, token_controller, transaction_controller, user_controller,
+    admin_controller, chain_controller, common_controller, enterprise_controller, interest_controller, invoice_controller, purchase_controller, reservation_controller, stats_controller, token_controller, transaction_controller, user_controller,
 };
 
 pub fn init_user_router() -> Router {
@@ -9,20 +10,68 @@ pub fn init_user_router() -> Router {
     // 账户相关路由
     router
         // Web3 认证相关路由
-        .push(Router::with_path("/challenge").post(user_controller::challenge))
+        .push(
+            Router::with_path("/challenge")
+                .hoop(challenge_rate_limit)
+                .post(user_controller::challenge),
+        )
         .push(Router::with_path("/login").post(user_controller::login))
+        .push(Router::with_path("/refresh").post(user_controller::refresh_token))
+        .push(
+            Router::with_path("/logout")
+                .hoop(common_controller::auth_token)
+                .post(user_controller::logout),
+        )
         // 绑定企业路由 (需要认证)
         .push(
             Router::with_path("/bind-enterprise")
                 .hoop(common_controller::auth_token)
                 .post(user_controller::bind_enterprise),
         )
+        .push(
+            Router::with_path("/bind-enterprise/challenge")
+                .hoop(common_controller::auth_token)
+                .post(user_controller::bind_enterprise_challenge),
+        )
+        // 关联多个钱包到同一账户 (需要认证)
+        .push(
+            Router::with_path("/wallets/link/challenge")
+                .hoop(common_controller::auth_token)
+                .post(user_controller::link_wallet_challenge),
+        )
+        .push(
+            Router::with_path("/wallets/link")
+                .hoop(common_controller::auth_token)
+                .post(user_controller::link_wallet),
+        )
+        .push(
+            Router::with_path("/enterprise")
+                .hoop(common_controller::auth_token)
+                .delete(user_controller::unbind_enterprise),
+        )
+        // 当前登录用户信息 (需要认证)
+        .push(
+            Router::with_path("/me")
+                .hoop(common_controller::auth_token)
+                .get(user_controller::get_current_user),
+        )
         // 获取用户绑定的企业信息路由 (需要认证)
         .push(
             Router::with_path("/enterprise-info")
                 .hoop(common_controller::auth_token)
                 .get(user_controller::get_enterprise_info),
         )
+        // 个人数据导出与账户注销 (需要认证)
+        .push(
+            Router::with_path("/export")
+                .hoop(common_controller::auth_token)
+                .get(user_controller::export_user_data),
+        )
+        .push(
+            Router::new()
+                .hoop(common_controller::auth_token)
+                .delete(user_controller::delete_account),
+        )
 }
 
 pub fn init_enterprise_router() -> Router {
@@ -30,18 +79,40 @@ pub fn init_enterprise_router() -> Router {
     Router::with_path("/enterprise")
         .push(Router::with_path("/list").get(enterprise_controller::list_enterprises))
         .push(Router::with_path("/detail").get(enterprise_controller::get_enterprise_by_id))
-        .push(Router::with_path("/del").delete(enterprise_controller::delete_enterprise))
+        // 企业管理 (需要企业管理员或平台管理员)
         .push(
-            Router::with_path("/create")
-                // .hoop(common_controller::auth_token)
-                .post(enterprise_controller::create_enterprise),
+            Router::new()
+                .hoop(common_controller::auth_token)
+                .hoop(RequireRole::new(&["creditor", "admin"]))
+                .push(Router::with_path("/del").delete(enterprise_controller::delete_enterprise))
+                .push(Router::with_path("/create").post(enterprise_controller::create_enterprise))
+                .push(
+                    Router::with_path("/webhooks")
+                        .get(enterprise_controller::list_webhooks)
+                        .post(enterprise_controller::register_webhook)
+                        .push(Router::with_path("{id}").delete(enterprise_controller::delete_webhook)),
+                ),
+        )
+        .push(
+            Router::with_path("/{id}/export")
+                .hoop(common_controller::auth_token)
+                .get(enterprise_controller::export_enterprise),
+        )
+        // 当前用户所属企业的票据 (需要认证，按绑定关系限定范围)
+        .push(
+            Router::with_path("/invoices")
+                .hoop(common_controller::auth_token)
+                .get(invoice_controller::list_enterprise_invoices),
         )
 }
 
 pub fn init_invoice_router() -> Router {
     // 创建公共路由（无需认证）
     let public_routes = Router::new()
+        .hoop(common_controller::optional_auth) // 管理员可查看已删除票据
+        .get(invoice_controller::list_invoices_page)
         .push(Router::with_path("/list").get(invoice_controller::list_invoices))
+        .push(Router::with_path("/search").get(invoice_controller::search_invoices))
         .push(Router::with_path("/detail").get(invoice_controller::query_invoice_data));
     
     // 创建需要认证的路由
@@ -49,11 +120,25 @@ pub fn init_invoice_router() -> Router {
         .hoop(common_controller::auth_token) // 复用认证中间件
         .push(Router::with_path("/del").delete(invoice_controller::delete_invoice))
         .push(Router::with_path("/create").post(invoice_controller::create_invoice))
+        .push(Router::with_path("/batch").post(invoice_controller::create_invoices_batch))
         .push(Router::with_path("/holding/interest-details").get(invoice_controller::get_holding_interest_details))
         .push(Router::with_path("/verify").post(invoice_controller::verify_invoice))
         .push(Router::with_path("/issue").post(invoice_controller::issue_invoices))
         .push(Router::with_path("/batches").get(invoice_controller::list_user_invoice_batches))
-        .push(Router::with_path("/batch/:id").get(invoice_controller::get_invoice_batch_by_id));
+        .push(Router::with_path("/batch/:id").get(invoice_controller::get_invoice_batch_by_id))
+        .push(Router::with_path("/{id}").get(invoice_controller::get_invoice).put(invoice_controller::update_invoice))
+        .push(Router::with_path("/{id}/document").post(invoice_controller::upload_invoice_document))
+        .push(Router::with_path("/{id}/document/{doc_id}").delete(invoice_controller::delete_invoice_document))
+        .push(Router::with_path("/{id}/accept-terms").post(invoice_controller::accept_invoice_terms))
+        .push(Router::with_path("/{id}/timeline").get(invoice_controller::get_invoice_timeline))
+        .push(Router::with_path("/{id}/ledger").get(invoice_controller::get_invoice_ledger))
+        .push(Router::with_path("/{id}/history").get(invoice_controller::get_invoice_history))
+        .push(Router::with_path("/{id}/events").get(invoice_controller::stream_invoice_events))
+        .push(
+            Router::with_path("/{id}/settle")
+                .hoop(RequireRole::new(&["creditor"]))
+                .post(invoice_controller::settle_invoice),
+        );
 
     
     // 合并路由
@@ -69,25 +154,60 @@ pub fn init_purchase_router() -> Router {
             Router::new()
                 .hoop(common_controller::auth_token)
                 .push(Router::with_path("/purchase").post(purchase_controller::purchase_invoice))
-                .push(Router::with_path("/holdings").get(purchase_controller::list_my_holdings)),
+                .push(Router::with_path("/holdings").get(purchase_controller::list_my_holdings))
+                .push(Router::with_path("/history").get(purchase_controller::get_purchase_history))
+                .push(Router::with_path("/estimate").get(purchase_controller::estimate_purchase_gas))
+                .push(Router::with_path("/{id}/projection").get(purchase_controller::get_settlement_projection)),
         )
 }
 
+pub fn init_chain_router() -> Router {
+    Router::with_path("/chain")
+        .push(Router::with_path("/contract-status").get(chain_controller::get_contract_status))
+}
+
+pub fn init_reservation_router() -> Router {
+    Router::with_path("/reservations")
+        .hoop(common_controller::auth_token)
+        .get(reservation_controller::list_reservations)
+        .post(reservation_controller::create_reservation)
+        .push(Router::with_path("{id}").delete(reservation_controller::cancel_reservation))
+}
+
 pub fn init_admin_router() -> Router {
     Router::with_path("/admin")
-        // TODO: Add proper admin authentication middleware
-        .hoop(common_controller::auth_token) // Temporarily reuse standard auth, should be replaced with admin-specific auth
+        .hoop(admin_ip_allowlist)
+        .hoop(common_controller::auth_token)
+        .hoop(RequireRole::new(&["admin"]))
         .push(Router::with_path("/calc-interest").get(invoice_controller::trigger_daily_interest_calculation))
         .push(Router::with_path("/process-maturity").get(invoice_controller::trigger_maturity_payments))
+        .push(Router::with_path("/settle/batch").post(invoice_controller::settle_matured_batch))
+        .push(Router::with_path("/invoices/transition").post(invoice_controller::bulk_transition_invoices))
+        .push(Router::with_path("/invoice/{id}/reconcile").get(invoice_controller::reconcile_invoice))
+        .push(Router::with_path("/invoice/{id}/cancel").post(invoice_controller::cancel_invoice))
+        .push(Router::with_path("/auth/decode-token").post(admin_controller::decode_token))
+        .push(Router::with_path("/features").get(admin_controller::list_features))
+        .push(Router::with_path("/stats").get(stats_controller::admin_stats))
+        .push(Router::with_path("/logs/stream").get(admin_controller::stream_logs))
+        .push(Router::with_path("/enterprises").get(enterprise_controller::list_enterprises_admin))
+        .push(Router::with_path("/users").get(user_controller::list_users_admin))
+        .push(Router::with_path("/users/{id}/role").post(user_controller::change_user_role))
+        .push(Router::with_path("/enterprise/{id}/performance").get(enterprise_controller::get_enterprise_performance))
+        .push(Router::with_path("/enterprise/{id}/verify").post(enterprise_controller::verify_enterprise))
+        .push(Router::with_path("/enterprise/{id}/reject").post(enterprise_controller::reject_enterprise))
+        .push(Router::with_path("/onchain/failures").get(chain_controller::list_onchain_failures))
+        .push(Router::with_path("/onchain/transfer-indexer").get(chain_controller::get_transfer_indexer_status))
 }
 
 // 新增交易相关路由
 pub fn init_transaction_router() -> Router {
     Router::with_path("/transaction")
         .hoop(common_controller::auth_token) // 所有交易查询接口都需要认证
+        .get(transaction_controller::list_onchain_transactions)
         .push(Router::with_path("/list").get(transaction_controller::list_user_transactions))
         .push(Router::with_path("/by-holding").get(transaction_controller::list_holding_transactions))
         .push(Router::with_path("/by-type").get(transaction_controller::list_transactions_by_type))
+        .push(Router::with_path("/{tx_hash}").get(transaction_controller::get_transaction_status))
 }
 
 // 新增利息相关路由
@@ -96,6 +216,9 @@ pub fn init_interest_router() -> Router {
         .hoop(common_controller::auth_token) // 所有利息查询接口都需要认证
         .push(Router::with_path("/list").get(interest_controller::list_user_interest_accruals))
         .push(Router::with_path("/by-holding").get(interest_controller::list_holding_interest_accruals))
+        .push(Router::with_path("/portfolio").get(interest_controller::get_portfolio_interest))
+        .push(Router::with_path("/{invoice_id}").get(interest_controller::get_accrued_interest))
+        .push(Router::with_path("/{invoice_id}/schedule").get(interest_controller::get_payment_schedule))
 }
 
 // 新增 Token 相关路由
@@ -104,12 +227,20 @@ pub fn init_token_router() -> Router {
         .push(Router::with_path("/markets").get(token_controller::list_token_markets))
         .push(Router::with_path("/batches").get(token_controller::list_token_batches))
         .push(Router::with_path("/create").post(token_controller::create_token_batch))
+        .push(Router::with_path("/{id}/holders").get(token_controller::get_token_holders))
         .push(
             Router::new()
                 .hoop(common_controller::auth_token) // Token routes requiring authentication
                 
                 .push(Router::with_path("/purchase").post(token_controller::purchase_tokens))
                 .push(Router::with_path("/holdings").get(token_controller::get_user_token_holdings))
+                .push(Router::with_path("/{token_address}/balance").get(token_controller::get_token_balance))
                 .push(Router::with_path("/from_invoice_batch").post(token_controller::create_token_batch_from_invoice_batch))
         )
 }
+
+// 平台公开统计路由 (无需认证)
+pub fn init_stats_router() -> Router {
+    Router::with_path("/stats")
+        .push(Router::with_path("/platform").get(stats_controller::platform_stats))
+}
@@ -0,0 +1,567 @@
+//! 带稳定错误码的接口错误，按请求语言渲染消息 (见 `utils::i18n`)
+//!
+//! 错误响应在 `ResObj.error` 中携带 `{ code, message, requestId }`：
+//! `code` 为下表中的机器可读错误码，客户端应按它判断错误类型，不要匹配 `msg` 文本。
+//! 4xx 错误可能附带未本地化的 `detail` (如哪个参数不合法)，仅供排查使用。
+
+use salvo::{Depot, oapi::ToSchema, prelude::Json};
+use serde::Serialize;
+use service::error::ServiceError;
+
+use crate::utils::i18n::{self, Locale};
+use crate::utils::request_id::REQUEST_ID_KEY;
+use crate::utils::res::ResObj;
+
+/// 序列化为 `SCREAMING_SNAKE_CASE`，与 `as_str` 一致
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    Unauthenticated,
+    InvalidToken,
+    InvalidAuthHeader,
+    AuthHeaderMissing,
+    AdminRoleRequired,
+    Forbidden,
+    HttpsRequired,
+    NotFound,
+    BadRequest,
+    InternalError,
+    RequestFailed,
+    BlockchainUnavailable,
+    ContractQueryFailed,
+    SelfFundingNotAllowed,
+    TokenRevoked,
+    TooManyRequests,
+    PayloadTooLarge,
+    PurchaseInProgress,
+    EnterpriseNotVerified,
+    // --- 登录 / 账户 ---
+    InvalidAddress,
+    InvalidSignatureFormat,
+    InvalidSignature,
+    NonceNotFoundOrExpired,
+    ChallengeExpired,
+    ChallengeGenerationFailed,
+    AddressRequiredForContractWallet,
+    ContractWalletVerificationFailed,
+    ContractWalletVerificationUnavailable,
+    LoginProcessingError,
+    TokenGenerationError,
+    TokenExpiredBeyondGrace,
+    TokenRefreshError,
+    TokenNotRevocable,
+    TokenRevocationError,
+    UserNotFound,
+    AuthenticatedUserNotFound,
+    DatabaseError,
+    // --- 企业绑定 / 关联钱包 ---
+    BindSignatureRequired,
+    InvalidBindChallenge,
+    InvalidLinkChallenge,
+    EnterpriseNotFound,
+    EnterpriseNotBound,
+    EnterpriseMissingId,
+    WalletAlreadyLinked,
+    WalletBelongsToAnotherUser,
+    AccountHasActivePositions,
+    // --- 票据 ---
+    InvoiceAmountNotPositive,
+    InvoiceAmountNotInteger,
+    InvoiceAmountExceedsCap,
+    UnsupportedCurrency,
+    InvoiceDueDateNotInFuture,
+    StaleWrite,
+    InsufficientCapacity,
+    RequestTimeout,
+    InvalidId,
+    InvoiceNotFound,
+    InvoiceBatchTooLarge,
+    InvalidStatusTransition,
+    EnterpriseExists,
+    RoleTransitionNotAllowed,
+    LastAdmin,
+    NotAuthorizedForEnterprise,
+    // --- 合约回滚 ---
+    ContractReverted,
+    ContractPaused,
+    ContractCallerUnauthorized,
+    InsufficientTokenBalance,
+    InsufficientTokenAllowance,
+    InvoiceAlreadyOnChain,
+    TokenBatchNotFoundOnChain,
+    TokenBatchNotActive,
+    InvoiceNotDeletable,
+    TokenDenylistUnavailable,
+    AccountDeleted,
+    WebhookUrlNotAllowed,
+    // --- 认购 / 兑付 ---
+    TermsNotAccepted,
+    TermsNotAcceptable,
+    InvoiceNotAvailable,
+    InvalidPurchaseShares,
+    InsufficientFunds,
+    InvoiceNotEditable,
+    DocumentLimitReached,
+    InvoiceNotOnChain,
+    SettlementBeforeMaturity,
+    SettlementInProgress,
+    InvoiceAlreadySettled,
+    InvoiceNotFinanced,
+    RepaymentNotAllowed,
+    RefundNotAllowed,
+    InterestAlreadyAccrued,
+    IdempotencyKeyInProgress,
+    IdempotencyKeyReused,
+    ChainTransactionFailed,
+}
+
+impl ErrorCode {
+    /// 全部错误码，用于校验文档与消息表
+    pub const ALL: &'static [ErrorCode] = &[
+        ErrorCode::Unauthenticated,
+        ErrorCode::InvalidToken,
+        ErrorCode::InvalidAuthHeader,
+        ErrorCode::AuthHeaderMissing,
+        ErrorCode::AdminRoleRequired,
+        ErrorCode::Forbidden,
+        ErrorCode::HttpsRequired,
+        ErrorCode::NotFound,
+        ErrorCode::BadRequest,
+        ErrorCode::InternalError,
+        ErrorCode::RequestFailed,
+        ErrorCode::BlockchainUnavailable,
+        ErrorCode::ContractQueryFailed,
+        ErrorCode::SelfFundingNotAllowed,
+        ErrorCode::TokenRevoked,
+        ErrorCode::TooManyRequests,
+        ErrorCode::PayloadTooLarge,
+        ErrorCode::PurchaseInProgress,
+        ErrorCode::EnterpriseNotVerified,
+        ErrorCode::InvalidAddress,
+        ErrorCode::InvalidSignatureFormat,
+        ErrorCode::InvalidSignature,
+        ErrorCode::NonceNotFoundOrExpired,
+        ErrorCode::ChallengeGenerationFailed,
+        ErrorCode::AddressRequiredForContractWallet,
+        ErrorCode::ContractWalletVerificationFailed,
+        ErrorCode::ContractWalletVerificationUnavailable,
+        ErrorCode::LoginProcessingError,
+        ErrorCode::TokenGenerationError,
+        ErrorCode::TokenExpiredBeyondGrace,
+        ErrorCode::TokenRefreshError,
+        ErrorCode::TokenNotRevocable,
+        ErrorCode::TokenRevocationError,
+        ErrorCode::UserNotFound,
+        ErrorCode::AuthenticatedUserNotFound,
+        ErrorCode::DatabaseError,
+        ErrorCode::BindSignatureRequired,
+        ErrorCode::InvalidBindChallenge,
+        ErrorCode::InvalidLinkChallenge,
+        ErrorCode::EnterpriseNotFound,
+        ErrorCode::EnterpriseNotBound,
+        ErrorCode::EnterpriseMissingId,
+        ErrorCode::WalletAlreadyLinked,
+        ErrorCode::WalletBelongsToAnotherUser,
+        ErrorCode::AccountHasActivePositions,
+        ErrorCode::InvoiceAmountNotPositive,
+        ErrorCode::InvoiceAmountNotInteger,
+        ErrorCode::InvoiceAmountExceedsCap,
+        ErrorCode::UnsupportedCurrency,
+        ErrorCode::InvoiceDueDateNotInFuture,
+        ErrorCode::StaleWrite,
+        ErrorCode::InsufficientCapacity,
+        ErrorCode::RequestTimeout,
+        ErrorCode::InvalidId,
+        ErrorCode::InvoiceNotFound,
+        ErrorCode::InvoiceBatchTooLarge,
+        ErrorCode::InvalidStatusTransition,
+        ErrorCode::EnterpriseExists,
+        ErrorCode::RoleTransitionNotAllowed,
+        ErrorCode::LastAdmin,
+        ErrorCode::NotAuthorizedForEnterprise,
+        ErrorCode::ContractReverted,
+        ErrorCode::ContractPaused,
+        ErrorCode::ContractCallerUnauthorized,
+        ErrorCode::InsufficientTokenBalance,
+        ErrorCode::InsufficientTokenAllowance,
+        ErrorCode::InvoiceAlreadyOnChain,
+        ErrorCode::TokenBatchNotFoundOnChain,
+        ErrorCode::TokenBatchNotActive,
+        ErrorCode::ChallengeExpired,
+        ErrorCode::InvoiceNotDeletable,
+        ErrorCode::TokenDenylistUnavailable,
+        ErrorCode::AccountDeleted,
+        ErrorCode::WebhookUrlNotAllowed,
+        ErrorCode::TermsNotAccepted,
+        ErrorCode::TermsNotAcceptable,
+        ErrorCode::InvoiceNotAvailable,
+        ErrorCode::InvalidPurchaseShares,
+        ErrorCode::InsufficientFunds,
+        ErrorCode::InvoiceNotEditable,
+        ErrorCode::DocumentLimitReached,
+        ErrorCode::InvoiceNotOnChain,
+        ErrorCode::SettlementBeforeMaturity,
+        ErrorCode::SettlementInProgress,
+        ErrorCode::InvoiceAlreadySettled,
+        ErrorCode::InvoiceNotFinanced,
+        ErrorCode::RepaymentNotAllowed,
+        ErrorCode::RefundNotAllowed,
+        ErrorCode::InterestAlreadyAccrued,
+        ErrorCode::IdempotencyKeyInProgress,
+        ErrorCode::IdempotencyKeyReused,
+        ErrorCode::ChainTransactionFailed,
+    ];
+
+    /// 机器可读的错误码，不随语言变化
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Unauthenticated => "UNAUTHENTICATED",
+            ErrorCode::InvalidToken => "INVALID_TOKEN",
+            ErrorCode::InvalidAuthHeader => "INVALID_AUTH_HEADER",
+            ErrorCode::AuthHeaderMissing => "AUTH_HEADER_MISSING",
+            ErrorCode::AdminRoleRequired => "ADMIN_ROLE_REQUIRED",
+            ErrorCode::Forbidden => "FORBIDDEN",
+            ErrorCode::HttpsRequired => "HTTPS_REQUIRED",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::BadRequest => "BAD_REQUEST",
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::RequestFailed => "REQUEST_FAILED",
+            ErrorCode::BlockchainUnavailable => "BLOCKCHAIN_UNAVAILABLE",
+            ErrorCode::ContractQueryFailed => "CONTRACT_QUERY_FAILED",
+            ErrorCode::SelfFundingNotAllowed => "SELF_FUNDING_NOT_ALLOWED",
+            ErrorCode::TokenRevoked => "TOKEN_REVOKED",
+            ErrorCode::TooManyRequests => "TOO_MANY_REQUESTS",
+            ErrorCode::PayloadTooLarge => "PAYLOAD_TOO_LARGE",
+            ErrorCode::PurchaseInProgress => "PURCHASE_IN_PROGRESS",
+            ErrorCode::EnterpriseNotVerified => "ENTERPRISE_NOT_VERIFIED",
+            ErrorCode::InvalidAddress => "INVALID_ADDRESS",
+            ErrorCode::InvalidSignatureFormat => "INVALID_SIGNATURE_FORMAT",
+            ErrorCode::InvalidSignature => "INVALID_SIGNATURE",
+            ErrorCode::NonceNotFoundOrExpired => "NONCE_NOT_FOUND_OR_EXPIRED",
+            ErrorCode::ChallengeGenerationFailed => "CHALLENGE_GENERATION_FAILED",
+            ErrorCode::AddressRequiredForContractWallet => "ADDRESS_REQUIRED_FOR_CONTRACT_WALLET",
+            ErrorCode::ContractWalletVerificationFailed => "CONTRACT_WALLET_VERIFICATION_FAILED",
+            ErrorCode::ContractWalletVerificationUnavailable => "CONTRACT_WALLET_VERIFICATION_UNAVAILABLE",
+            ErrorCode::LoginProcessingError => "LOGIN_PROCESSING_ERROR",
+            ErrorCode::TokenGenerationError => "TOKEN_GENERATION_ERROR",
+            ErrorCode::TokenExpiredBeyondGrace => "TOKEN_EXPIRED_BEYOND_GRACE",
+            ErrorCode::TokenRefreshError => "TOKEN_REFRESH_ERROR",
+            ErrorCode::TokenNotRevocable => "TOKEN_NOT_REVOCABLE",
+            ErrorCode::TokenRevocationError => "TOKEN_REVOCATION_ERROR",
+            ErrorCode::UserNotFound => "USER_NOT_FOUND",
+            ErrorCode::AuthenticatedUserNotFound => "AUTHENTICATED_USER_NOT_FOUND",
+            ErrorCode::DatabaseError => "DATABASE_ERROR",
+            ErrorCode::BindSignatureRequired => "BIND_SIGNATURE_REQUIRED",
+            ErrorCode::InvalidBindChallenge => "INVALID_BIND_CHALLENGE",
+            ErrorCode::InvalidLinkChallenge => "INVALID_LINK_CHALLENGE",
+            ErrorCode::EnterpriseNotFound => "ENTERPRISE_NOT_FOUND",
+            ErrorCode::EnterpriseNotBound => "ENTERPRISE_NOT_BOUND",
+            ErrorCode::EnterpriseMissingId => "ENTERPRISE_MISSING_ID",
+            ErrorCode::WalletAlreadyLinked => "WALLET_ALREADY_LINKED",
+            ErrorCode::WalletBelongsToAnotherUser => "WALLET_BELONGS_TO_ANOTHER_USER",
+            ErrorCode::AccountHasActivePositions => "ACCOUNT_HAS_ACTIVE_POSITIONS",
+            ErrorCode::InvoiceAmountNotPositive => "INVOICE_AMOUNT_NOT_POSITIVE",
+            ErrorCode::InvoiceAmountNotInteger => "INVOICE_AMOUNT_NOT_INTEGER",
+            ErrorCode::InvoiceAmountExceedsCap => "INVOICE_AMOUNT_EXCEEDS_CAP",
+            ErrorCode::UnsupportedCurrency => "UNSUPPORTED_CURRENCY",
+            ErrorCode::InvoiceDueDateNotInFuture => "INVOICE_DUE_DATE_NOT_IN_FUTURE",
+            ErrorCode::StaleWrite => "STALE_WRITE",
+            ErrorCode::InsufficientCapacity => "INSUFFICIENT_CAPACITY",
+            ErrorCode::RequestTimeout => "REQUEST_TIMEOUT",
+            ErrorCode::InvalidId => "INVALID_ID",
+            ErrorCode::InvoiceNotFound => "INVOICE_NOT_FOUND",
+            ErrorCode::InvoiceBatchTooLarge => "INVOICE_BATCH_TOO_LARGE",
+            ErrorCode::EnterpriseExists => "ENTERPRISE_EXISTS",
+            ErrorCode::RoleTransitionNotAllowed => "ROLE_TRANSITION_NOT_ALLOWED",
+            ErrorCode::LastAdmin => "LAST_ADMIN",
+            ErrorCode::NotAuthorizedForEnterprise => "NOT_AUTHORIZED_FOR_ENTERPRISE",
+            ErrorCode::ContractReverted => "CONTRACT_REVERTED",
+            ErrorCode::ContractPaused => "CONTRACT_PAUSED",
+            ErrorCode::ContractCallerUnauthorized => "CONTRACT_CALLER_UNAUTHORIZED",
+            ErrorCode::InsufficientTokenBalance => "INSUFFICIENT_TOKEN_BALANCE",
+            ErrorCode::InsufficientTokenAllowance => "INSUFFICIENT_TOKEN_ALLOWANCE",
+            ErrorCode::InvoiceAlreadyOnChain => "INVOICE_ALREADY_ON_CHAIN",
+            ErrorCode::TokenBatchNotFoundOnChain => "TOKEN_BATCH_NOT_FOUND_ON_CHAIN",
+            ErrorCode::TokenBatchNotActive => "TOKEN_BATCH_NOT_ACTIVE",
+            ErrorCode::ChallengeExpired => "CHALLENGE_EXPIRED",
+            ErrorCode::InvalidStatusTransition => "INVALID_STATUS_TRANSITION",
+            ErrorCode::InvoiceNotDeletable => "INVOICE_NOT_DELETABLE",
+            ErrorCode::TokenDenylistUnavailable => "TOKEN_DENYLIST_UNAVAILABLE",
+            ErrorCode::AccountDeleted => "ACCOUNT_DELETED",
+            ErrorCode::WebhookUrlNotAllowed => "WEBHOOK_URL_NOT_ALLOWED",
+            ErrorCode::TermsNotAccepted => "TERMS_NOT_ACCEPTED",
+            ErrorCode::TermsNotAcceptable => "TERMS_NOT_ACCEPTABLE",
+            ErrorCode::InvoiceNotAvailable => "INVOICE_NOT_AVAILABLE",
+            ErrorCode::InvalidPurchaseShares => "INVALID_PURCHASE_SHARES",
+            ErrorCode::InsufficientFunds => "INSUFFICIENT_FUNDS",
+            ErrorCode::InvoiceNotEditable => "INVOICE_NOT_EDITABLE",
+            ErrorCode::DocumentLimitReached => "DOCUMENT_LIMIT_REACHED",
+            ErrorCode::InvoiceNotOnChain => "INVOICE_NOT_ON_CHAIN",
+            ErrorCode::SettlementBeforeMaturity => "SETTLEMENT_BEFORE_MATURITY",
+            ErrorCode::SettlementInProgress => "SETTLEMENT_IN_PROGRESS",
+            ErrorCode::InvoiceAlreadySettled => "INVOICE_ALREADY_SETTLED",
+            ErrorCode::InvoiceNotFinanced => "INVOICE_NOT_FINANCED",
+            ErrorCode::RepaymentNotAllowed => "REPAYMENT_NOT_ALLOWED",
+            ErrorCode::RefundNotAllowed => "REFUND_NOT_ALLOWED",
+            ErrorCode::InterestAlreadyAccrued => "INTEREST_ALREADY_ACCRUED",
+            ErrorCode::IdempotencyKeyInProgress => "IDEMPOTENCY_KEY_IN_PROGRESS",
+            ErrorCode::IdempotencyKeyReused => "IDEMPOTENCY_KEY_REUSED",
+            ErrorCode::ChainTransactionFailed => "CHAIN_TRANSACTION_FAILED",
+        }
+    }
+
+    /// 响应体中的 `code`
+    pub fn status(&self) -> i32 {
+        match self {
+            ErrorCode::Unauthenticated
+            | ErrorCode::InvalidToken
+            | ErrorCode::InvalidAuthHeader
+            | ErrorCode::AuthHeaderMissing
+            | ErrorCode::TokenRevoked
+            | ErrorCode::InvalidSignature
+            | ErrorCode::TokenExpiredBeyondGrace
+            | ErrorCode::UserNotFound
+            | ErrorCode::InvalidBindChallenge
+            | ErrorCode::InvalidLinkChallenge => 401,
+            ErrorCode::AdminRoleRequired | ErrorCode::Forbidden | ErrorCode::HttpsRequired | ErrorCode::SelfFundingNotAllowed
+            | ErrorCode::NotAuthorizedForEnterprise
+            | ErrorCode::AccountDeleted => 403,
+            ErrorCode::NotFound | ErrorCode::EnterpriseNotFound | ErrorCode::EnterpriseNotBound
+            | ErrorCode::InvoiceNotFound => 404,
+            ErrorCode::BadRequest
+            | ErrorCode::InvalidAddress
+            | ErrorCode::InvalidSignatureFormat
+            | ErrorCode::NonceNotFoundOrExpired
+            | ErrorCode::AddressRequiredForContractWallet
+            | ErrorCode::TokenNotRevocable
+            | ErrorCode::BindSignatureRequired
+            | ErrorCode::InvoiceAmountNotPositive
+            | ErrorCode::InvoiceAmountNotInteger
+            | ErrorCode::InvoiceAmountExceedsCap
+            | ErrorCode::UnsupportedCurrency
+            | ErrorCode::InvoiceDueDateNotInFuture
+            | ErrorCode::InvalidId
+            | ErrorCode::InvoiceBatchTooLarge
+            | ErrorCode::ChallengeExpired
+            | ErrorCode::WebhookUrlNotAllowed => 400,
+            ErrorCode::InternalError
+            | ErrorCode::RequestFailed
+            | ErrorCode::ChallengeGenerationFailed
+            | ErrorCode::LoginProcessingError
+            | ErrorCode::TokenGenerationError
+            | ErrorCode::TokenRefreshError
+            | ErrorCode::TokenRevocationError
+            | ErrorCode::AuthenticatedUserNotFound
+            | ErrorCode::DatabaseError
+            | ErrorCode::EnterpriseMissingId => 500,
+            ErrorCode::PurchaseInProgress | ErrorCode::EnterpriseNotVerified | ErrorCode::WalletAlreadyLinked | ErrorCode::WalletBelongsToAnotherUser
+            | ErrorCode::AccountHasActivePositions
+            | ErrorCode::StaleWrite
+            | ErrorCode::InsufficientCapacity
+            | ErrorCode::EnterpriseExists
+            | ErrorCode::RoleTransitionNotAllowed
+            | ErrorCode::LastAdmin
+            | ErrorCode::ContractReverted
+            | ErrorCode::ContractPaused
+            | ErrorCode::ContractCallerUnauthorized
+            | ErrorCode::InsufficientTokenBalance
+            | ErrorCode::InsufficientTokenAllowance
+            | ErrorCode::InvoiceAlreadyOnChain
+            | ErrorCode::TokenBatchNotFoundOnChain
+            | ErrorCode::TokenBatchNotActive
+            | ErrorCode::InvalidStatusTransition
+            | ErrorCode::InvoiceNotDeletable
+            | ErrorCode::TermsNotAccepted
+            | ErrorCode::TermsNotAcceptable
+            | ErrorCode::InvoiceNotAvailable
+            | ErrorCode::InvalidPurchaseShares
+            | ErrorCode::InsufficientFunds
+            | ErrorCode::InvoiceNotEditable
+            | ErrorCode::DocumentLimitReached
+            | ErrorCode::InvoiceNotOnChain
+            | ErrorCode::SettlementBeforeMaturity
+            | ErrorCode::SettlementInProgress
+            | ErrorCode::InvoiceAlreadySettled
+            | ErrorCode::InvoiceNotFinanced
+            | ErrorCode::RepaymentNotAllowed
+            | ErrorCode::RefundNotAllowed
+            | ErrorCode::InterestAlreadyAccrued
+            | ErrorCode::IdempotencyKeyInProgress => 409,
+            ErrorCode::PayloadTooLarge => 413,
+            ErrorCode::IdempotencyKeyReused => 422,
+            ErrorCode::TooManyRequests => 429,
+            ErrorCode::ContractQueryFailed | ErrorCode::ContractWalletVerificationFailed | ErrorCode::ChainTransactionFailed => 502,
+            ErrorCode::BlockchainUnavailable | ErrorCode::ContractWalletVerificationUnavailable
+            | ErrorCode::TokenDenylistUnavailable => 503,
+            ErrorCode::RequestTimeout => 504,
+        }
+    }
+}
+
+/// 错误详情 (参考 RFC 7807)，`message` 按请求语言本地化，`code` 不变
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ErrorBody {
+    pub code: ErrorCode,
+    pub message: String,
+    /// 与响应头 `X-Request-Id` 相同，便于按请求排查日志
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    /// 覆盖默认的 `code` (如把 HTTP 状态码透传给客户端)
+    pub status: Option<i32>,
+    /// 未本地化的补充说明，原样放入 `ErrorBody.detail`
+    pub detail: Option<String>,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode) -> Self {
+        Self { code, status: None, detail: None }
+    }
+
+    pub fn with_status(mut self, status: i32) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    pub fn render<T: ToSchema>(&self, locale: Locale) -> Json<ResObj<T>> {
+        self.render_with_request_id(locale, None)
+    }
+
+    pub fn render_with_request_id<T: ToSchema>(&self, locale: Locale, request_id: Option<String>) -> Json<ResObj<T>> {
+        let status = self.status.unwrap_or_else(|| self.code.status());
+        let message = i18n::message(self.code.as_str(), locale).to_string();
+        Json(ResObj::custom_code(status, message.clone()).with_error(ErrorBody {
+            code: self.code,
+            message,
+            request_id,
+            detail: self.detail.clone(),
+        }))
+    }
+
+    /// 按 depot 中的请求语言渲染，并带上 `request_id` 中间件生成的请求 ID
+    pub fn to_json<T: ToSchema>(&self, depot: &Depot) -> Json<ResObj<T>> {
+        self.render_with_request_id(Locale::from_depot(depot), depot.get::<String>(REQUEST_ID_KEY).ok().cloned())
+    }
+}
+
+impl From<ErrorCode> for ApiError {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code)
+    }
+}
+
+impl From<&ServiceError> for ApiError {
+    /// 服务层错误 → 错误码。客户端错误带上服务层的说明作为 `detail`；
+    /// 数据库、缓存等内部错误只返回错误码，具体原因由调用方记录日志
+    fn from(err: &ServiceError) -> Self {
+        let code = match err {
+            ServiceError::NotFound(_)
+            | ServiceError::UserNotFound(_)
+            | ServiceError::HoldingNotFound(_)
+            | ServiceError::ReservationNotFound(_) => ErrorCode::NotFound,
+            ServiceError::InvoiceNotFound(_) => ErrorCode::InvoiceNotFound,
+            ServiceError::InvoiceNotIssue(_)
+            | ServiceError::InvalidPurchaseAmount(_)
+            | ServiceError::InvalidRepaymentAmount(_)
+            | ServiceError::InvalidCursor(_) => ErrorCode::BadRequest,
+            ServiceError::InvalidWebhookUrl(_) => ErrorCode::WebhookUrlNotAllowed,
+            ServiceError::Forbidden(_) => ErrorCode::Forbidden,
+            ServiceError::AccountDeleted(_) => ErrorCode::AccountDeleted,
+            ServiceError::SelfFundingNotAllowed(_) => ErrorCode::SelfFundingNotAllowed,
+            ServiceError::EnterpriseNotVerified(_) => ErrorCode::EnterpriseNotVerified,
+            ServiceError::InvoiceNotAvailable(_) => ErrorCode::InvoiceNotAvailable,
+            ServiceError::InvalidPurchaseShares(..) => ErrorCode::InvalidPurchaseShares,
+            ServiceError::InsufficientFunds(..) => ErrorCode::InsufficientFunds,
+            ServiceError::InsufficientCapacity { .. } => ErrorCode::InsufficientCapacity,
+            ServiceError::PurchaseInProgress(_) => ErrorCode::PurchaseInProgress,
+            ServiceError::TermsNotAccepted(_) => ErrorCode::TermsNotAccepted,
+            ServiceError::TermsNotAcceptable(_) => ErrorCode::TermsNotAcceptable,
+            ServiceError::SettlementBeforeMaturity(_) => ErrorCode::SettlementBeforeMaturity,
+            ServiceError::SettlementInProgress(_) => ErrorCode::SettlementInProgress,
+            ServiceError::InvoiceAlreadySettled(_) => ErrorCode::InvoiceAlreadySettled,
+            ServiceError::InvoiceNotFinanced(_) => ErrorCode::InvoiceNotFinanced,
+            ServiceError::RepaymentNotAllowed(_) => ErrorCode::RepaymentNotAllowed,
+            ServiceError::RefundNotAllowed(_) => ErrorCode::RefundNotAllowed,
+            ServiceError::InterestAlreadyAccrued(..) => ErrorCode::InterestAlreadyAccrued,
+            ServiceError::IdempotencyKeyInProgress(_) => ErrorCode::IdempotencyKeyInProgress,
+            ServiceError::IdempotencyKeyReused(_) => ErrorCode::IdempotencyKeyReused,
+            ServiceError::InvalidStatusTransition { .. } => ErrorCode::InvalidStatusTransition,
+            ServiceError::InvoiceNotDeletable(_) => ErrorCode::InvoiceNotDeletable,
+            ServiceError::StaleWrite { .. } => ErrorCode::StaleWrite,
+            ServiceError::ActivePositions(_) => ErrorCode::AccountHasActivePositions,
+            ServiceError::RoleTransitionNotAllowed { .. } => ErrorCode::RoleTransitionNotAllowed,
+            ServiceError::LastAdmin(_) => ErrorCode::LastAdmin,
+            ServiceError::ChainRpcError(_) => ErrorCode::BlockchainUnavailable,
+            ServiceError::MongoDbError(_) | ServiceError::MongoDbTransactionError(_) | ServiceError::TransientTransaction(_) => {
+                ErrorCode::DatabaseError
+            }
+            ServiceError::CacheError(_)
+            | ServiceError::SerializationError(_)
+            | ServiceError::ConfigError(_)
+            | ServiceError::InitializationError(_)
+            | ServiceError::BalanceUpdateFailed(_)
+            | ServiceError::DecimalConversionError(_)
+            | ServiceError::InternalError(_)
+            | ServiceError::AnyhowError(_) => ErrorCode::InternalError,
+        };
+        let api_error = ApiError::new(code);
+        if code.status() < 500 { api_error.with_detail(err.to_string()) } else { api_error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_codes_serialize_to_documented_code() {
+        for code in ErrorCode::ALL {
+            assert_eq!(serde_json::to_value(code).unwrap(), code.as_str(), "{:?}", code);
+            assert_ne!(i18n::message(code.as_str(), Locale::En), code.as_str(), "missing message for {:?}", code);
+        }
+        // 状态码保持原有约定
+        assert_eq!(ErrorCode::NonceNotFoundOrExpired.status(), 400);
+        assert_eq!(ErrorCode::InvalidSignature.status(), 401);
+        assert_eq!(ErrorCode::EnterpriseNotBound.status(), 404);
+        assert_eq!(ErrorCode::WalletAlreadyLinked.status(), 409);
+        assert_eq!(ErrorCode::DatabaseError.status(), 500);
+    }
+
+    #[test]
+    fn test_error_body_shape() {
+        let mut depot = Depot::new();
+        depot.insert(REQUEST_ID_KEY, "req-1".to_string());
+        let body = serde_json::to_value(&ApiError::new(ErrorCode::InvalidSignature).to_json::<()>(&depot).0).unwrap();
+        assert_eq!(body["code"], 401);
+        assert_eq!(body["error"], serde_json::json!({ "code": "INVALID_SIGNATURE", "message": "Invalid signature", "requestId": "req-1" }));
+    }
+
+    #[test]
+    fn test_service_error_mapping() {
+        let depot = Depot::new();
+        let not_found = ApiError::from(&ServiceError::InvoiceNotFound("INV-1".to_string()));
+        let body = serde_json::to_value(&not_found.to_json::<()>(&depot).0).unwrap();
+        assert_eq!(body["code"], 404);
+        assert_eq!(body["error"]["code"], "INVOICE_NOT_FOUND");
+        assert_eq!(body["error"]["detail"], "Invoice not found: INV-1");
+
+        // 内部错误不把原因返回给客户端
+        let db = ApiError::from(&ServiceError::MongoDbError("connection refused".to_string()));
+        assert_eq!(db.code, ErrorCode::DatabaseError);
+        assert!(db.detail.is_none());
+        assert_eq!(ApiError::from(&ServiceError::ChainRpcError("timeout".to_string())).code, ErrorCode::BlockchainUnavailable);
+        assert_eq!(ApiError::from(&ServiceError::IdempotencyKeyReused("k".to_string())).code.status(), 422);
+    }
+}
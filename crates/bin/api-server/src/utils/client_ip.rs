@@ -0,0 +1,205 @@
+//! 客户端 IP 提取与 CIDR 匹配
+//!
+//! 只有当直连地址属于 `security.trusted_proxies` 时才信任 `X-Forwarded-For` / `X-Forwarded-Proto`，
+//! `X-Forwarded-For` 从右往左取第一个不属于可信代理的地址，防止客户端伪造请求头。
+
+use std::net::IpAddr;
+
+use configs::CFG;
+use once_cell::sync::Lazy;
+use salvo::Request;
+
+static TRUSTED_PROXIES: Lazy<Vec<IpCidr>> = Lazy::new(|| parse_cidrs(&CFG.security.trusted_proxies));
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IpCidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl IpCidr {
+    /// 解析 `10.0.0.0/8`、`::1/128`，也接受不带前缀的单个地址
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (addr_str, prefix_str) = match s.split_once('/') {
+            Some((a, p)) => (a, Some(p)),
+            None => (s, None),
+        };
+        let addr: IpAddr = addr_str.parse().ok()?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix_str {
+            Some(p) => p.parse::<u8>().ok().filter(|p| *p <= max)?,
+            None => max,
+        };
+        Some(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, normalize(*ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = if self.prefix == 0 { 0 } else { u32::MAX << (32 - self.prefix) };
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = if self.prefix == 0 { 0 } else { u128::MAX << (128 - self.prefix) };
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+// IPv4-mapped IPv6 地址 (::ffff:1.2.3.4) 按 IPv4 处理
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+        v4 => v4,
+    }
+}
+
+pub fn parse_cidrs(list: &[String]) -> Vec<IpCidr> {
+    list.iter()
+        .filter_map(|s| {
+            let cidr = IpCidr::parse(s);
+            if cidr.is_none() {
+                tracing::error!("Ignoring invalid CIDR in config: {}", s);
+            }
+            cidr
+        })
+        .collect()
+}
+
+pub fn matches_any(cidrs: &[IpCidr], ip: &IpAddr) -> bool {
+    cidrs.iter().any(|c| c.contains(ip))
+}
+
+/// 来源 IP 白名单。配置为空时不限制；配置了但没有一条合法的 CIDR 时拒绝所有来源，
+/// 避免写错配置反而放开访问
+#[derive(Debug, Clone, PartialEq)]
+pub struct IpAllowlist {
+    configured: bool,
+    cidrs: Vec<IpCidr>,
+}
+
+impl IpAllowlist {
+    pub fn from_config(list: &[String]) -> Self {
+        let cidrs = parse_cidrs(list);
+        if !list.is_empty() && cidrs.is_empty() {
+            tracing::error!("IP allowlist has no valid CIDR, all requests will be rejected");
+        }
+        Self { configured: !list.is_empty(), cidrs }
+    }
+
+    pub fn allows(&self, ip: Option<IpAddr>) -> bool {
+        if !self.configured {
+            return true;
+        }
+        ip.map(|ip| matches_any(&self.cidrs, &ip)).unwrap_or(false)
+    }
+}
+
+/// 根据直连地址和 X-Forwarded-For 计算真实客户端 IP
+pub fn resolve_client_ip(remote: Option<IpAddr>, forwarded_for: Option<&str>, trusted_proxies: &[IpCidr]) -> Option<IpAddr> {
+    let remote = remote.map(normalize)?;
+    if !matches_any(trusted_proxies, &remote) {
+        return Some(remote);
+    }
+    let Some(header) = forwarded_for else { return Some(remote) };
+    let mut client = remote;
+    for hop in header.split(',').rev() {
+        match hop.trim().parse::<IpAddr>() {
+            Ok(ip) => {
+                client = normalize(ip);
+                if !matches_any(trusted_proxies, &client) {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    Some(client)
+}
+
+/// 直连地址来自可信代理时按 `X-Forwarded-Proto` 判断是否 HTTPS，否则返回 `None` (请求头不可信或不存在)
+pub fn resolve_forwarded_https(remote: Option<IpAddr>, forwarded_proto: Option<&str>, trusted_proxies: &[IpCidr]) -> Option<bool> {
+    let remote = remote.map(normalize)?;
+    if !matches_any(trusted_proxies, &remote) {
+        return None;
+    }
+    // 多级代理时取第一个值 (最靠近客户端)
+    let proto = forwarded_proto?.split(',').next()?.trim();
+    Some(proto.eq_ignore_ascii_case("https"))
+}
+
+fn remote_ip(req: &Request) -> Option<IpAddr> {
+    req.remote_addr().as_ipv4().map(|a| IpAddr::V4(*a.ip()))
+        .or_else(|| req.remote_addr().as_ipv6().map(|a| IpAddr::V6(*a.ip())))
+}
+
+/// 从请求中取客户端 IP (遵循 `security.trusted_proxies` 配置)
+pub fn client_ip(req: &Request) -> Option<IpAddr> {
+    let forwarded = req.header::<String>("x-forwarded-for");
+    resolve_client_ip(remote_ip(req), forwarded.as_deref(), &TRUSTED_PROXIES)
+}
+
+/// 可信代理传入的 `X-Forwarded-Proto` 是否为 https (遵循 `security.trusted_proxies` 配置)
+pub fn forwarded_https(req: &Request) -> Option<bool> {
+    let forwarded = req.header::<String>("x-forwarded-proto");
+    resolve_forwarded_https(remote_ip(req), forwarded.as_deref(), &TRUSTED_PROXIES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_contains() {
+        let net = IpCidr::parse("10.1.0.0/16").unwrap();
+        assert!(net.contains(&ip("10.1.200.3")));
+        assert!(!net.contains(&ip("10.2.0.1")));
+        assert!(net.contains(&ip("::ffff:10.1.0.9")));
+        assert!(IpCidr::parse("::1").unwrap().contains(&ip("::1")));
+        assert!(IpCidr::parse("0.0.0.0/0").unwrap().contains(&ip("8.8.8.8")));
+        assert!(IpCidr::parse("10.0.0.0/33").is_none());
+    }
+
+    #[test]
+    fn test_allowlist_with_only_invalid_cidrs_rejects_all() {
+        let list = |items: &[&str]| items.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(IpAllowlist::from_config(&[]).allows(Some(ip("8.8.8.8"))));
+
+        let invalid = IpAllowlist::from_config(&list(&["10.0.0.0/33", "not-an-ip"]));
+        assert!(!invalid.allows(Some(ip("10.0.0.1"))));
+        assert!(!invalid.allows(None));
+
+        let partial = IpAllowlist::from_config(&list(&["bogus", "10.0.0.0/8"]));
+        assert!(partial.allows(Some(ip("10.0.0.1"))));
+        assert!(!partial.allows(Some(ip("8.8.8.8"))));
+    }
+
+    #[test]
+    fn test_forwarded_proto_only_trusted_from_proxy() {
+        let proxies = vec![IpCidr::parse("172.16.0.0/12").unwrap()];
+        // 客户端直连时伪造的 X-Forwarded-Proto 被忽略
+        assert_eq!(resolve_forwarded_https(Some(ip("1.2.3.4")), Some("https"), &proxies), None);
+        assert_eq!(resolve_forwarded_https(Some(ip("172.16.0.2")), Some("https, http"), &proxies), Some(true));
+        assert_eq!(resolve_forwarded_https(Some(ip("172.16.0.2")), Some("http"), &proxies), Some(false));
+        assert_eq!(resolve_forwarded_https(Some(ip("172.16.0.2")), None, &proxies), None);
+    }
+
+    #[test]
+    fn test_forwarded_for_only_trusted_from_proxy() {
+        let proxies = vec![IpCidr::parse("172.16.0.0/12").unwrap()];
+        // 直连不是可信代理，忽略伪造的 X-Forwarded-For
+        assert_eq!(resolve_client_ip(Some(ip("1.2.3.4")), Some("9.9.9.9"), &proxies), Some(ip("1.2.3.4")));
+        // 经过可信代理，取最右侧的非代理地址
+        assert_eq!(
+            resolve_client_ip(Some(ip("172.16.0.2")), Some("9.9.9.9, 5.6.7.8, 172.16.0.5"), &proxies),
+            Some(ip("5.6.7.8"))
+        );
+    }
+}
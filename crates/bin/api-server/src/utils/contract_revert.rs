@@ -0,0 +1,91 @@
+//! 合约回滚原因到错误码的映射
+//!
+//! 写操作失败时服务层只保留错误信息字符串，这里解码其中的 revert 原因：已知的回滚映射为具体错误码，
+//! 其余统一为 `CONTRACT_REVERTED`，原始数据只记录在日志中。映射出的错误码均为 409。
+
+use tracing::warn;
+use pharos_interact::{decode_revert, RevertError};
+
+use crate::utils::api_error::ErrorCode;
+
+/// 已知的 revert 原因 → 错误码
+pub fn revert_error_code(revert: &RevertError) -> ErrorCode {
+    let code = match revert {
+        RevertError::Custom { name, .. } => match name.as_str() {
+            "EnforcedPause" => Some(ErrorCode::ContractPaused),
+            "OwnableUnauthorizedAccount" | "AccessControlUnauthorizedAccount" => Some(ErrorCode::ContractCallerUnauthorized),
+            "ERC20InsufficientBalance" => Some(ErrorCode::InsufficientTokenBalance),
+            "ERC20InsufficientAllowance" => Some(ErrorCode::InsufficientTokenAllowance),
+            _ => None,
+        },
+        RevertError::Message(reason) => message_error_code(reason),
+        RevertError::Panic(_) | RevertError::Unknown(_) => None,
+    };
+    code.unwrap_or_else(|| {
+        warn!("Unrecognized contract revert: {} ({:?})", revert, revert);
+        ErrorCode::ContractReverted
+    })
+}
+
+// require 字符串 (票据合约与 OpenZeppelin 4.x)
+fn message_error_code(reason: &str) -> Option<ErrorCode> {
+    let reason = reason.to_ascii_lowercase();
+    let code = if reason.contains("paused") {
+        ErrorCode::ContractPaused
+    } else if reason.contains("not the owner") || reason.contains("missing role") {
+        ErrorCode::ContractCallerUnauthorized
+    } else if reason.contains("exceeds balance") || reason.contains("insufficient balance") {
+        ErrorCode::InsufficientTokenBalance
+    } else if reason.contains("insufficient allowance") {
+        ErrorCode::InsufficientTokenAllowance
+    } else if reason.contains("invoice exists") || reason.contains("invoice already exists") {
+        ErrorCode::InvoiceAlreadyOnChain
+    } else if reason.contains("batch not found") {
+        ErrorCode::TokenBatchNotFoundOnChain
+    } else if reason.contains("batch not active") {
+        ErrorCode::TokenBatchNotActive
+    } else {
+        return None;
+    };
+    Some(code)
+}
+
+/// 从链上调用的错误信息中识别合约回滚，与回滚无关 (如 RPC 超时) 时返回 None
+pub fn chain_error_code(message: &str) -> Option<ErrorCode> {
+    decode_revert(message).map(|revert| revert_error_code(&revert))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_reverts_map_to_typed_codes() {
+        let custom = |name: &str| RevertError::Custom { name: name.to_string(), args: vec![] };
+        assert_eq!(revert_error_code(&custom("EnforcedPause")), ErrorCode::ContractPaused);
+        assert_eq!(revert_error_code(&custom("ERC20InsufficientAllowance")), ErrorCode::InsufficientTokenAllowance);
+        assert_eq!(revert_error_code(&RevertError::Message("Batch not active".to_string())), ErrorCode::TokenBatchNotActive);
+        assert_eq!(revert_error_code(&RevertError::Message("Pausable: paused".to_string())), ErrorCode::ContractPaused);
+        assert_eq!(ErrorCode::TokenBatchNotActive.status(), 409);
+    }
+
+    #[test]
+    fn test_unknown_reverts_fall_back_to_generic_code() {
+        assert_eq!(revert_error_code(&RevertError::Unknown(Some("0xdeadbeef".to_string()))), ErrorCode::ContractReverted);
+        assert_eq!(revert_error_code(&RevertError::Message("something else".to_string())), ErrorCode::ContractReverted);
+        assert_eq!(revert_error_code(&RevertError::Panic("0x11".to_string())), ErrorCode::ContractReverted);
+        assert_eq!(ErrorCode::ContractReverted.status(), 409);
+    }
+
+    #[test]
+    fn test_chain_error_code_from_service_message() {
+        // ERC20InsufficientBalance(0x11..11, 5, 20)
+        let data = ["e450d38c".to_string(), format!("{:0>64}", "11".repeat(20)), format!("{:0>64}", "5"), format!("{:0>64}", "14")].concat();
+        let message = format!(
+            "Refund distribution for invoice INV-1 failed: Failed to send distributeRepayment transaction: (code: 3, message: execution reverted, data: Some(String(\"0x{}\")))",
+            data
+        );
+        assert_eq!(chain_error_code(&message), Some(ErrorCode::InsufficientTokenBalance));
+        assert_eq!(chain_error_code("Refund distribution for invoice INV-1 failed: error sending request"), None);
+    }
+}
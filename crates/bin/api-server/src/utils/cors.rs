@@ -0,0 +1,105 @@
+//! 跨域配置
+//!
+//! 配置了 `server.cors_origins` 时只允许列表中的来源，并允许携带凭证 (JWT / swagger 会话 Cookie)；
+//! 未配置时开发环境允许本机任意端口的来源，生产环境不允许跨域。不在允许范围内的来源不返回 CORS 响应头。
+
+use salvo::cors::{AllowOrigin, Cors, CorsHandler};
+use salvo::http::{HeaderName, HeaderValue, Method};
+use salvo::{Depot, Request};
+
+use crate::utils::feature_flags::FEATURE_HEADER;
+use crate::utils::pagination::TOTAL_COUNT_HEADER;
+
+/// 允许跨域请求携带的请求头
+const ALLOWED_HEADERS: &[&str] = &["authorization", "content-type", "accept", "accept-language", "idempotency-key", FEATURE_HEADER];
+
+/// 允许前端读取的响应头 (分页元数据)
+const EXPOSED_HEADERS: &[&str] = &["link", TOTAL_COUNT_HEADER];
+
+/// 去掉首尾空白和末尾的 `/`，`https://app.example.com/` 与浏览器发送的 `https://app.example.com` 一致
+pub fn normalize_origin(origin: &str) -> String {
+    origin.trim().trim_end_matches('/').to_string()
+}
+
+/// 开发环境默认允许的来源：localhost / 127.0.0.1 的任意端口
+pub fn is_loopback_origin(origin: &str) -> bool {
+    let Some(rest) = origin.strip_prefix("http://").or_else(|| origin.strip_prefix("https://")) else {
+        return false;
+    };
+    let host = rest.split(':').next().unwrap_or_default();
+    host == "localhost" || host == "127.0.0.1"
+}
+
+pub fn build_cors(origins: &[String], production: bool) -> CorsHandler {
+    let origins: Vec<String> = origins.iter().map(|o| normalize_origin(o)).filter(|o| !o.is_empty()).collect();
+    let allow_origin = if !origins.is_empty() {
+        AllowOrigin::list(origins.iter().filter_map(|o| HeaderValue::from_str(o).ok()))
+    } else if production {
+        tracing::warn!("server.cors_origins is empty, cross-origin requests are disabled");
+        AllowOrigin::list(Vec::<HeaderValue>::new())
+    } else {
+        tracing::warn!("server.cors_origins is empty, allowing loopback origins (development only)");
+        AllowOrigin::judge(|origin: &HeaderValue, _req: &Request, _depot: &Depot| origin.to_str().map(is_loopback_origin).unwrap_or(false))
+    };
+    Cors::new()
+        .allow_origin(allow_origin)
+        .allow_credentials(true)
+        .allow_methods(vec![Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers(ALLOWED_HEADERS.iter().map(|h| HeaderName::from_static(h)).collect::<Vec<_>>())
+        .expose_headers(EXPOSED_HEADERS.iter().map(|h| HeaderName::from_static(h)).collect::<Vec<_>>())
+        .into_handler()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use salvo::prelude::*;
+    use salvo::test::TestClient;
+
+    #[handler]
+    async fn ok() -> &'static str {
+        "ok"
+    }
+
+    fn service(origins: &[&str], production: bool) -> Service {
+        let origins: Vec<String> = origins.iter().map(|o| o.to_string()).collect();
+        Service::new(Router::with_path("ping").get(ok)).hoop(build_cors(&origins, production))
+    }
+
+    async fn request(service: &Service, origin: &str) -> salvo::Response {
+        TestClient::get("http://127.0.0.1:5800/ping").add_header("Origin", origin, true).send(service).await
+    }
+
+    #[tokio::test]
+    async fn test_configured_origins() {
+        let service = service(&["https://app.example.com/"], true);
+
+        let res = request(&service, "https://app.example.com").await;
+        assert_eq!(res.headers().get("access-control-allow-origin").unwrap(), "https://app.example.com");
+        assert_eq!(res.headers().get("access-control-allow-credentials").unwrap(), "true");
+
+        let res = request(&service, "https://evil.example.com").await;
+        assert!(res.headers().get("access-control-allow-origin").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_default_origins() {
+        let dev = service(&[], false);
+        let res = request(&dev, "http://localhost:5173").await;
+        assert_eq!(res.headers().get("access-control-allow-origin").unwrap(), "http://localhost:5173");
+        let res = request(&dev, "https://app.example.com").await;
+        assert!(res.headers().get("access-control-allow-origin").is_none());
+
+        let prod = service(&[], true);
+        let res = request(&prod, "http://localhost:5173").await;
+        assert!(res.headers().get("access-control-allow-origin").is_none());
+    }
+
+    #[test]
+    fn test_loopback_origin() {
+        assert!(is_loopback_origin("http://127.0.0.1:8080"));
+        assert!(is_loopback_origin("https://localhost"));
+        assert!(!is_loopback_origin("http://localhost.evil.com"));
+        assert!(!is_loopback_origin("null"));
+    }
+}
@@ -0,0 +1,222 @@
+//! 用户绑定企业信息 (`GET /user/enterprise-info`) 的缓存
+//!
+//! 按用户地址缓存组合后的 `EnterpriseInfoResponse` (`user:enterprise-info:{address}`，SETEX 过期)，
+//! 绑定 / 解绑企业时删除；企业改名等变更不主动失效，依赖较短的 TTL。
+//! Redis 不可用或缓存内容无法解析时直接查库，并尝试用查询结果覆盖缓存。
+
+use std::future::Future;
+
+use tracing::warn;
+use redis::{AsyncCommands, Client as RedisClient};
+use salvo::async_trait;
+
+use crate::controller::EnterpriseInfoResponse;
+
+#[async_trait]
+pub trait EnterpriseInfoCache: Send + Sync {
+    async fn get(&self, address: &str) -> Result<Option<String>, String>;
+    async fn set(&self, address: &str, value: &str) -> Result<(), String>;
+    async fn invalidate(&self, address: &str) -> Result<(), String>;
+}
+
+pub struct RedisEnterpriseInfoCache {
+    client: RedisClient,
+    ttl_secs: u64,
+}
+
+impl RedisEnterpriseInfoCache {
+    /// `ttl_secs` 为 0 时不读写缓存
+    pub fn new(client: RedisClient, ttl_secs: u64) -> Self {
+        Self { client, ttl_secs }
+    }
+
+    fn key(address: &str) -> String {
+        format!("user:enterprise-info:{}", address.to_lowercase())
+    }
+}
+
+#[async_trait]
+impl EnterpriseInfoCache for RedisEnterpriseInfoCache {
+    async fn get(&self, address: &str) -> Result<Option<String>, String> {
+        if self.ttl_secs == 0 {
+            return Ok(None);
+        }
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.get(Self::key(address)).await.map_err(|e| e.to_string())
+    }
+
+    async fn set(&self, address: &str, value: &str) -> Result<(), String> {
+        if self.ttl_secs == 0 {
+            return Ok(());
+        }
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.set_ex::<_, _, ()>(Self::key(address), value, self.ttl_secs).await.map_err(|e| e.to_string())
+    }
+
+    async fn invalidate(&self, address: &str) -> Result<(), String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.del::<_, ()>(Self::key(address)).await.map_err(|e| e.to_string())
+    }
+}
+
+/// 查库得到的企业信息；用户绑定的企业已不存在时 `cacheable` 为 false，不写入缓存
+pub struct LoadedEnterpriseInfo {
+    pub info: EnterpriseInfoResponse,
+    pub cacheable: bool,
+}
+
+/// 优先返回缓存，未命中时调用 `load` 查库并回写
+pub async fn cached_enterprise_info<C, F, Fut, E>(cache: &C, address: &str, load: F) -> Result<EnterpriseInfoResponse, E>
+where
+    C: EnterpriseInfoCache + ?Sized,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<LoadedEnterpriseInfo, E>>,
+{
+    match cache.get(address).await {
+        Ok(Some(json)) => match serde_json::from_str(&json) {
+            Ok(info) => return Ok(info),
+            Err(e) => warn!("Discarding unreadable enterprise info cache of {}: {}", address, e),
+        },
+        Ok(None) => {}
+        Err(e) => warn!("Enterprise info cache unavailable for {}: {}", address, e),
+    }
+
+    let loaded = load().await?;
+    if loaded.cacheable {
+        match serde_json::to_string(&loaded.info) {
+            Ok(json) => {
+                if let Err(e) = cache.set(address, &json).await {
+                    warn!("Failed to cache enterprise info of {}: {}", address, e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize enterprise info of {}: {}", address, e),
+        }
+    }
+    Ok(loaded.info)
+}
+
+/// 绑定关系变化后调用；删除失败只记录日志，旧数据最多保留一个 TTL
+pub async fn invalidate_enterprise_info<C: EnterpriseInfoCache + ?Sized>(cache: &C, address: &str) {
+    if let Err(e) = cache.invalidate(address).await {
+        warn!("Failed to invalidate enterprise info cache of {}: {}", address, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[derive(Default)]
+    struct MemoryCache(Mutex<HashMap<String, String>>);
+
+    #[async_trait]
+    impl EnterpriseInfoCache for MemoryCache {
+        async fn get(&self, address: &str) -> Result<Option<String>, String> {
+            Ok(self.0.lock().unwrap().get(address).cloned())
+        }
+
+        async fn set(&self, address: &str, value: &str) -> Result<(), String> {
+            self.0.lock().unwrap().insert(address.to_string(), value.to_string());
+            Ok(())
+        }
+
+        async fn invalidate(&self, address: &str) -> Result<(), String> {
+            self.0.lock().unwrap().remove(address);
+            Ok(())
+        }
+    }
+
+    struct UnavailableCache;
+
+    #[async_trait]
+    impl EnterpriseInfoCache for UnavailableCache {
+        async fn get(&self, _: &str) -> Result<Option<String>, String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn set(&self, _: &str, _: &str) -> Result<(), String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn invalidate(&self, _: &str) -> Result<(), String> {
+            Err("connection refused".to_string())
+        }
+    }
+
+    const ADDRESS: &str = "0xabc";
+
+    fn info(enterprise: Option<&str>) -> EnterpriseInfoResponse {
+        EnterpriseInfoResponse {
+            is_enterprise_bound: enterprise.is_some(),
+            enterprise_name: enterprise.map(|name| name.to_string()),
+            enterprise_address: enterprise.map(|_| "0xdef".to_string()),
+            enterprise_id: enterprise.map(|_| "6650f1c2a1b2c3d4e5f60718".to_string()),
+        }
+    }
+
+    /// 模拟查库，记录调用次数
+    async fn load(calls: &AtomicU32, enterprise: Option<&str>, cacheable: bool) -> Result<LoadedEnterpriseInfo, ()> {
+        calls.fetch_add(1, Ordering::SeqCst);
+        Ok(LoadedEnterpriseInfo { info: info(enterprise), cacheable })
+    }
+
+    #[tokio::test]
+    async fn test_second_call_served_from_cache() {
+        let cache = MemoryCache::default();
+        let calls = AtomicU32::new(0);
+
+        let first = cached_enterprise_info(&cache, ADDRESS, || load(&calls, Some("Acme"), true)).await.unwrap();
+        let second = cached_enterprise_info(&cache, ADDRESS, || load(&calls, Some("Other"), true)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(first.enterprise_name.as_deref(), Some("Acme"));
+        assert_eq!(second.enterprise_name.as_deref(), Some("Acme"));
+        assert_eq!(second.enterprise_id, first.enterprise_id);
+    }
+
+    #[tokio::test]
+    async fn test_binding_busts_cache() {
+        let cache = MemoryCache::default();
+        let calls = AtomicU32::new(0);
+
+        let before = cached_enterprise_info(&cache, ADDRESS, || load(&calls, None, true)).await.unwrap();
+        assert!(!before.is_enterprise_bound);
+
+        // bind_enterprise 成功后删除缓存，下一次读取拿到新绑定的企业
+        invalidate_enterprise_info(&cache, ADDRESS).await;
+        let after = cached_enterprise_info(&cache, ADDRESS, || load(&calls, Some("Acme"), true)).await.unwrap();
+        assert!(after.is_enterprise_bound);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_enterprise_is_not_cached() {
+        let cache = MemoryCache::default();
+        let calls = AtomicU32::new(0);
+
+        cached_enterprise_info(&cache, ADDRESS, || load(&calls, None, false)).await.unwrap();
+        cached_enterprise_info(&cache, ADDRESS, || load(&calls, None, false)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        assert!(cache.get(ADDRESS).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_or_unavailable_cache_falls_back_to_load() {
+        let cache = MemoryCache::default();
+        cache.set(ADDRESS, "not json").await.unwrap();
+        let calls = AtomicU32::new(0);
+
+        let info = cached_enterprise_info(&cache, ADDRESS, || load(&calls, Some("Acme"), true)).await.unwrap();
+        assert_eq!(info.enterprise_name.as_deref(), Some("Acme"));
+        // 损坏的缓存被查询结果覆盖
+        cached_enterprise_info(&cache, ADDRESS, || load(&calls, Some("Other"), true)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = AtomicU32::new(0);
+        cached_enterprise_info(&UnavailableCache, ADDRESS, || load(&calls, Some("Acme"), true)).await.unwrap();
+        cached_enterprise_info(&UnavailableCache, ADDRESS, || load(&calls, Some("Acme"), true)).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,89 @@
+//! 请求级功能开关
+//!
+//! 处理函数通过 `depot.feature("new_fee_model")` 判断是否启用新逻辑，判定顺序：
+//! 1. 管理员请求且 `features.allow_admin_override = true` 时，以 `X-Feature-Flags` 请求头中的值为准；
+//! 2. 否则取配置 `[features.flags]` 中的值；
+//! 3. 未配置的开关一律视为关闭。
+//!
+//! 请求头格式为逗号分隔的 `name=on|off` (也接受 true/false/1/0)，无法解析的项直接忽略。
+//! 请求头由 `parse_feature_overrides` 中间件提前解析，但只有在调用 `feature()` 时 depot 中
+//! 已有管理员 claims 才会生效，因此未认证的接口无法通过请求头打开开关。
+
+use std::collections::HashMap;
+
+use configs::CFG;
+use salvo::Depot;
+
+use crate::controller::Claims;
+
+/// 管理员覆盖开关的请求头
+pub const FEATURE_HEADER: &str = "x-feature-flags";
+/// depot 中保存请求头覆盖值的 key
+pub const FEATURE_OVERRIDES_KEY: &str = "feature_overrides";
+
+/// 解析 `X-Feature-Flags` 请求头
+pub fn parse_overrides(header: &str) -> HashMap<String, bool> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let (name, value) = item.split_once('=')?;
+            let enabled = match value.trim().to_ascii_lowercase().as_str() {
+                "on" | "true" | "1" => true,
+                "off" | "false" | "0" => false,
+                _ => return None,
+            };
+            let name = name.trim();
+            (!name.is_empty()).then(|| (name.to_string(), enabled))
+        })
+        .collect()
+}
+
+/// 按 覆盖值 -> 配置默认值 -> 关闭 的顺序判定开关
+pub fn evaluate(name: &str, defaults: &HashMap<String, bool>, overrides: Option<&HashMap<String, bool>>) -> bool {
+    overrides
+        .and_then(|o| o.get(name))
+        .or_else(|| defaults.get(name))
+        .copied()
+        .unwrap_or(false)
+}
+
+pub trait FeatureFlags {
+    /// 当前请求是否启用开关 `name`
+    fn feature(&self, name: &str) -> bool;
+}
+
+impl FeatureFlags for Depot {
+    fn feature(&self, name: &str) -> bool {
+        let is_admin = matches!(self.get::<Claims>("claims"), Ok(claims) if claims.is_admin());
+        let overrides = if CFG.features.allow_admin_override && is_admin {
+            self.get::<HashMap<String, bool>>(FEATURE_OVERRIDES_KEY).ok()
+        } else {
+            None
+        };
+        evaluate(name, &CFG.features.flags, overrides)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_overrides() {
+        let overrides = parse_overrides("new_fee_model=on, auto_tokenization = off,bad,x=maybe,=on,legacy=1");
+        assert_eq!(overrides.len(), 3);
+        assert_eq!(overrides.get("new_fee_model"), Some(&true));
+        assert_eq!(overrides.get("auto_tokenization"), Some(&false));
+        assert_eq!(overrides.get("legacy"), Some(&true));
+    }
+
+    #[test]
+    fn test_evaluate_precedence() {
+        let defaults = HashMap::from([("a".to_string(), true), ("b".to_string(), false)]);
+        let overrides = HashMap::from([("a".to_string(), false)]);
+        assert!(evaluate("a", &defaults, None));
+        assert!(!evaluate("a", &defaults, Some(&overrides)));
+        assert!(!evaluate("b", &defaults, Some(&overrides)));
+        assert!(!evaluate("unknown", &defaults, None));
+    }
+}
@@ -0,0 +1,141 @@
+//! 就绪检查：逐个探测 MongoDB、Redis 与区块链 RPC
+//!
+//! 各探测并发执行并带超时，任何一个失败、超时或 panic 时 `/readyz` 返回 503；
+//! 没有注册任何探测时同样视为未就绪。
+
+use std::collections::BTreeMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ethers::providers::Middleware;
+use futures::FutureExt;
+use mongodb::{Database, bson::doc};
+use redis::Client as RedisClient;
+use salvo::async_trait;
+use salvo::oapi::ToSchema;
+use serde::Serialize;
+
+/// 单个探测的超时时间
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// 依赖名称，作为响应中的键
+    fn name(&self) -> &'static str;
+    async fn check(&self) -> Result<(), String>;
+}
+
+pub struct MongoProbe(pub Arc<Database>);
+
+#[async_trait]
+impl HealthProbe for MongoProbe {
+    fn name(&self) -> &'static str {
+        "mongodb"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        self.0.run_command(doc! { "ping": 1 }).await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+pub struct RedisProbe(pub Arc<RedisClient>);
+
+#[async_trait]
+impl HealthProbe for RedisProbe {
+    fn name(&self) -> &'static str {
+        "redis"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let mut conn = self.0.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        let _: String = redis::cmd("PING").query_async(&mut conn).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+}
+
+/// 区块链 RPC，`None` 表示启动时未能建立连接
+pub struct RpcProbe<M>(pub Option<Arc<M>>);
+
+#[async_trait]
+impl<M: Middleware + 'static> HealthProbe for RpcProbe<M> {
+    fn name(&self) -> &'static str {
+        "rpc"
+    }
+
+    async fn check(&self) -> Result<(), String> {
+        let client = self.0.as_ref().ok_or_else(|| "blockchain connection not initialized".to_string())?;
+        client.get_block_number().await.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone, PartialEq)]
+pub struct DependencyHealth {
+    /// up / down
+    pub status: String,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema, Debug, Clone)]
+pub struct ReadinessReport {
+    /// ready / not_ready
+    pub status: String,
+    pub dependencies: BTreeMap<String, DependencyHealth>,
+}
+
+impl ReadinessReport {
+    /// 无法执行就绪检查 (如未注册任何探测) 时的报告
+    pub fn not_ready() -> Self {
+        Self { status: "not_ready".to_string(), dependencies: BTreeMap::new() }
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.status == "ready"
+    }
+}
+
+/// 注入 depot 的就绪检查集合
+#[derive(Clone, Default)]
+pub struct HealthChecks {
+    probes: Vec<Arc<dyn HealthProbe>>,
+}
+
+impl HealthChecks {
+    pub fn new(probes: Vec<Arc<dyn HealthProbe>>) -> Self {
+        Self { probes }
+    }
+
+    pub async fn run(&self) -> ReadinessReport {
+        if self.probes.is_empty() {
+            tracing::error!("No readiness probes registered, reporting not ready");
+            return ReadinessReport::not_ready();
+        }
+        let results = futures::future::join_all(self.probes.iter().map(|probe| async move {
+            let started = Instant::now();
+            let check = AssertUnwindSafe(probe.check()).catch_unwind();
+            let result = match tokio::time::timeout(PROBE_TIMEOUT, check).await {
+                Ok(Ok(result)) => result,
+                Ok(Err(_)) => Err("probe panicked".to_string()),
+                Err(_) => Err(format!("timed out after {:?}", PROBE_TIMEOUT)),
+            };
+            let latency_ms = started.elapsed().as_millis() as u64;
+            let health = match result {
+                Ok(()) => DependencyHealth { status: "up".to_string(), latency_ms, error: None },
+                Err(e) => {
+                    tracing::warn!("Readiness probe {} failed: {}", probe.name(), e);
+                    DependencyHealth { status: "down".to_string(), latency_ms, error: Some(e) }
+                }
+            };
+            (probe.name().to_string(), health)
+        }))
+        .await;
+
+        let ready = results.iter().all(|(_, health)| health.status == "up");
+        ReadinessReport {
+            status: if ready { "ready" } else { "not_ready" }.to_string(),
+            dependencies: results.into_iter().collect(),
+        }
+    }
+}
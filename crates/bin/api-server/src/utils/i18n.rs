@@ -0,0 +1,281 @@
+//! 错误信息国际化
+//!
+//! 按错误码维护各语言的消息表，通过 `Accept-Language` 选择语言；
+//! 请求的语言不支持时回退到英文，错误码本身在各语言间保持不变。
+
+use salvo::{Depot, Request};
+
+/// depot 中保存请求语言的 key
+pub const LOCALE_KEY: &str = "locale";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Zh,
+}
+
+impl Locale {
+    fn from_tag(tag: &str) -> Option<Self> {
+        let primary = tag.split(['-', '_']).next().unwrap_or_default().trim();
+        match primary.to_ascii_lowercase().as_str() {
+            "en" => Some(Locale::En),
+            "zh" => Some(Locale::Zh),
+            _ => None,
+        }
+    }
+
+    /// 解析 `Accept-Language` (如 `zh-CN,zh;q=0.9,en;q=0.8`)，按 q 值选择第一个支持的语言
+    pub fn negotiate(accept_language: Option<&str>) -> Self {
+        let Some(header) = accept_language else { return Locale::default() };
+        let mut candidates: Vec<(f32, Locale)> = header
+            .split(',')
+            .filter_map(|part| {
+                let mut pieces = part.split(';');
+                let locale = Locale::from_tag(pieces.next()?)?;
+                let q = pieces
+                    .find_map(|p| p.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()))
+                    .unwrap_or(1.0);
+                (q > 0.0).then_some((q, locale))
+            })
+            .collect();
+        // 稳定排序，q 值相同时保持请求头中的顺序
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.first().map(|(_, l)| *l).unwrap_or_default()
+    }
+
+    pub fn from_request(req: &Request) -> Self {
+        Self::negotiate(req.header::<String>("accept-language").as_deref())
+    }
+
+    /// 取 `detect_locale` 中间件写入的语言，未写入时为英文
+    pub fn from_depot(depot: &Depot) -> Self {
+        depot.get::<Locale>(LOCALE_KEY).copied().unwrap_or_default()
+    }
+}
+
+const EN: &[(&str, &str)] = &[
+    ("UNAUTHENTICATED", "User not authenticated"),
+    ("INVALID_TOKEN", "Invalid or expired token"),
+    ("INVALID_AUTH_HEADER", "Invalid Authorization header format"),
+    ("AUTH_HEADER_MISSING", "Authorization header missing"),
+    ("ADMIN_ROLE_REQUIRED", "Admin role required"),
+    ("FORBIDDEN", "Forbidden"),
+    ("HTTPS_REQUIRED", "HTTPS required"),
+    ("NOT_FOUND", "The requested resource does not exist"),
+    ("BAD_REQUEST", "Invalid request"),
+    ("INTERNAL_ERROR", "Internal server error, please try again later"),
+    ("REQUEST_FAILED", "Request failed"),
+    ("BLOCKCHAIN_UNAVAILABLE", "Blockchain connection is unavailable"),
+    ("CONTRACT_QUERY_FAILED", "Failed to query the contract"),
+    ("SELF_FUNDING_NOT_ALLOWED", "Enterprise members cannot fund their own enterprise's invoices"),
+    ("TOKEN_REVOKED", "Token has been revoked, please log in again"),
+    ("TOO_MANY_REQUESTS", "Too many requests, please try again later"),
+    ("PAYLOAD_TOO_LARGE", "Request body is too large"),
+    ("PURCHASE_IN_PROGRESS", "Another purchase of this invoice is in progress, please try again"),
+    ("ENTERPRISE_NOT_VERIFIED", "The issuing enterprise has not been verified"),
+    ("INVALID_ADDRESS", "Invalid wallet address"),
+    ("INVALID_SIGNATURE_FORMAT", "Invalid signature format"),
+    ("INVALID_SIGNATURE", "Invalid signature"),
+    ("NONCE_NOT_FOUND_OR_EXPIRED", "Challenge not found or expired, please request a new one"),
+    ("CHALLENGE_GENERATION_FAILED", "Failed to generate challenge"),
+    ("ADDRESS_REQUIRED_FOR_CONTRACT_WALLET", "Contract wallet login requires the wallet address"),
+    ("CONTRACT_WALLET_VERIFICATION_FAILED", "Failed to verify the contract wallet signature"),
+    ("CONTRACT_WALLET_VERIFICATION_UNAVAILABLE", "Contract wallet login is not available"),
+    ("LOGIN_PROCESSING_ERROR", "Failed to process login"),
+    ("TOKEN_GENERATION_ERROR", "Failed to issue token"),
+    ("TOKEN_EXPIRED_BEYOND_GRACE", "Token expired too long ago to be refreshed, please log in again"),
+    ("TOKEN_REFRESH_ERROR", "Failed to refresh token"),
+    ("TOKEN_NOT_REVOCABLE", "This token cannot be revoked"),
+    ("TOKEN_REVOCATION_ERROR", "Failed to revoke token"),
+    ("USER_NOT_FOUND", "User not found"),
+    ("AUTHENTICATED_USER_NOT_FOUND", "Authenticated user not found"),
+    ("DATABASE_ERROR", "Database error, please try again later"),
+    ("BIND_SIGNATURE_REQUIRED", "A signed binding challenge is required"),
+    ("INVALID_BIND_CHALLENGE", "Binding challenge does not match this user or enterprise"),
+    ("INVALID_LINK_CHALLENGE", "Link challenge does not match this account or wallet"),
+    ("ENTERPRISE_NOT_FOUND", "Enterprise not found"),
+    ("ENTERPRISE_NOT_BOUND", "User is not bound to any enterprise"),
+    ("ENTERPRISE_MISSING_ID", "Enterprise record is incomplete"),
+    ("WALLET_ALREADY_LINKED", "Wallet is already linked to this account"),
+    ("WALLET_BELONGS_TO_ANOTHER_USER", "Wallet belongs to another account"),
+    ("ACCOUNT_HAS_ACTIVE_POSITIONS", "Account cannot be deleted while it holds active financed positions"),
+    ("INVOICE_AMOUNT_NOT_POSITIVE", "Invoice amount must be greater than zero"),
+    ("INVOICE_AMOUNT_NOT_INTEGER", "Invoice amount must be an integer in minor currency units"),
+    ("INVOICE_AMOUNT_EXCEEDS_CAP", "Invoice amount exceeds the allowed maximum"),
+    ("UNSUPPORTED_CURRENCY", "Currency is not supported"),
+    ("INVOICE_DUE_DATE_NOT_IN_FUTURE", "Invoice due date must be in the future"),
+    ("STALE_WRITE", "Invoice was modified by another request; reload and retry"),
+    ("INSUFFICIENT_CAPACITY", "Requested shares exceed the remaining capacity of the invoice"),
+    ("REQUEST_TIMEOUT", "Request timed out, please retry later"),
+    ("INVALID_ID", "Invalid ID format"),
+    ("INVOICE_NOT_FOUND", "Invoice not found"),
+    ("INVOICE_BATCH_TOO_LARGE", "Too many invoices in one batch"),
+    ("ENTERPRISE_EXISTS", "An enterprise with this wallet address already exists"),
+    ("ROLE_TRANSITION_NOT_ALLOWED", "This role change is not allowed"),
+    ("LAST_ADMIN", "The last platform admin cannot be demoted"),
+    ("NOT_AUTHORIZED_FOR_ENTERPRISE", "Wallet is not an authorized signer of the enterprise"),
+    ("CONTRACT_REVERTED", "The transaction was rejected by the smart contract"),
+    ("CONTRACT_PAUSED", "The contract is paused"),
+    ("CONTRACT_CALLER_UNAUTHORIZED", "The platform account is not authorized for this contract operation"),
+    ("INSUFFICIENT_TOKEN_BALANCE", "Insufficient token balance for this operation"),
+    ("INSUFFICIENT_TOKEN_ALLOWANCE", "Insufficient token allowance, approve the contract first"),
+    ("INVOICE_ALREADY_ON_CHAIN", "The invoice is already registered on chain"),
+    ("TOKEN_BATCH_NOT_FOUND_ON_CHAIN", "The token batch does not exist on chain"),
+    ("TOKEN_BATCH_NOT_ACTIVE", "The token batch is not active"),
+    ("CHALLENGE_EXPIRED", "The login challenge has expired, request a new one"),
+    ("INVALID_STATUS_TRANSITION", "Invoice status transition not allowed"),
+    ("INVOICE_NOT_DELETABLE", "Only pending invoices without any funding can be deleted"),
+    ("TOKEN_DENYLIST_UNAVAILABLE", "Token revocation status could not be checked, please try again later"),
+    ("ACCOUNT_DELETED", "This account has been deleted"),
+    ("WEBHOOK_URL_NOT_ALLOWED", "Webhook URL must be a public http(s) address"),
+    ("TERMS_NOT_ACCEPTED", "Funding terms have not been accepted for this invoice"),
+    ("TERMS_NOT_ACCEPTABLE", "Funding terms cannot be accepted for this invoice right now"),
+    ("INVOICE_NOT_AVAILABLE", "Invoice is not available for purchase"),
+    ("INVALID_PURCHASE_SHARES", "Requested shares exceed the shares available"),
+    ("INSUFFICIENT_FUNDS", "Insufficient balance for this purchase"),
+    ("INVOICE_NOT_EDITABLE", "Only pending invoices can be edited"),
+    ("DOCUMENT_LIMIT_REACHED", "Invoice already has the maximum number of documents"),
+    ("INVOICE_NOT_ON_CHAIN", "Invoice has not been issued on chain yet"),
+    ("SETTLEMENT_BEFORE_MATURITY", "Invoice cannot be settled before maturity"),
+    ("SETTLEMENT_IN_PROGRESS", "Settlement of this invoice is already in progress"),
+    ("INVOICE_ALREADY_SETTLED", "Invoice has already been settled"),
+    ("INVOICE_NOT_FINANCED", "Invoice has not been financed"),
+    ("REPAYMENT_NOT_ALLOWED", "Repayment cannot be settled for this invoice"),
+    ("REFUND_NOT_ALLOWED", "Invoice cannot be refunded in its current state"),
+    ("INTEREST_ALREADY_ACCRUED", "Interest has already been accrued for this date"),
+    ("IDEMPOTENCY_KEY_IN_PROGRESS", "A request with this idempotency key is still in progress"),
+    ("IDEMPOTENCY_KEY_REUSED", "Idempotency key was already used for a different request"),
+    ("CHAIN_TRANSACTION_FAILED", "The on-chain transaction failed"),
+];
+
+const ZH: &[(&str, &str)] = &[
+    ("UNAUTHENTICATED", "用户未认证"),
+    ("INVALID_TOKEN", "令牌无效或已过期"),
+    ("INVALID_AUTH_HEADER", "Authorization 请求头格式错误"),
+    ("AUTH_HEADER_MISSING", "缺少 Authorization 请求头"),
+    ("ADMIN_ROLE_REQUIRED", "需要管理员权限"),
+    ("FORBIDDEN", "禁止访问"),
+    ("HTTPS_REQUIRED", "请使用 HTTPS 访问"),
+    ("NOT_FOUND", "请求的资源不存在"),
+    ("BAD_REQUEST", "请求参数错误"),
+    ("INTERNAL_ERROR", "服务器内部错误，请稍后重试"),
+    ("REQUEST_FAILED", "请求处理失败"),
+    ("BLOCKCHAIN_UNAVAILABLE", "区块链连接不可用"),
+    ("CONTRACT_QUERY_FAILED", "查询合约失败"),
+    ("SELF_FUNDING_NOT_ALLOWED", "不能认购本企业发行的票据"),
+    ("TOKEN_REVOKED", "令牌已注销，请重新登录"),
+    ("TOO_MANY_REQUESTS", "请求过于频繁，请稍后再试"),
+    ("PAYLOAD_TOO_LARGE", "请求体过大"),
+    ("PURCHASE_IN_PROGRESS", "该票据正在被其他用户认购，请稍后重试"),
+    ("ENTERPRISE_NOT_VERIFIED", "出票企业尚未通过审核"),
+    ("INVALID_ADDRESS", "钱包地址无效"),
+    ("INVALID_SIGNATURE_FORMAT", "签名格式错误"),
+    ("INVALID_SIGNATURE", "签名无效"),
+    ("NONCE_NOT_FOUND_OR_EXPIRED", "挑战不存在或已过期，请重新获取"),
+    ("CHALLENGE_GENERATION_FAILED", "生成挑战失败"),
+    ("ADDRESS_REQUIRED_FOR_CONTRACT_WALLET", "合约钱包登录需要提供钱包地址"),
+    ("CONTRACT_WALLET_VERIFICATION_FAILED", "合约钱包签名校验失败"),
+    ("CONTRACT_WALLET_VERIFICATION_UNAVAILABLE", "暂不支持合约钱包登录"),
+    ("LOGIN_PROCESSING_ERROR", "登录处理失败"),
+    ("TOKEN_GENERATION_ERROR", "签发令牌失败"),
+    ("TOKEN_EXPIRED_BEYOND_GRACE", "令牌过期时间过长，无法刷新，请重新登录"),
+    ("TOKEN_REFRESH_ERROR", "刷新令牌失败"),
+    ("TOKEN_NOT_REVOCABLE", "该令牌无法注销"),
+    ("TOKEN_REVOCATION_ERROR", "注销令牌失败"),
+    ("USER_NOT_FOUND", "用户不存在"),
+    ("AUTHENTICATED_USER_NOT_FOUND", "当前登录用户不存在"),
+    ("DATABASE_ERROR", "数据库错误，请稍后重试"),
+    ("BIND_SIGNATURE_REQUIRED", "绑定企业需要提供签名挑战"),
+    ("INVALID_BIND_CHALLENGE", "绑定挑战与当前用户或企业不匹配"),
+    ("INVALID_LINK_CHALLENGE", "关联挑战与当前账户或钱包不匹配"),
+    ("ENTERPRISE_NOT_FOUND", "企业不存在"),
+    ("ENTERPRISE_NOT_BOUND", "用户未绑定企业"),
+    ("ENTERPRISE_MISSING_ID", "企业数据不完整"),
+    ("WALLET_ALREADY_LINKED", "该钱包已关联到当前账户"),
+    ("WALLET_BELONGS_TO_ANOTHER_USER", "该钱包已属于其他账户"),
+    ("ACCOUNT_HAS_ACTIVE_POSITIONS", "账户存在持有中的融资仓位，暂不能注销"),
+    ("INVOICE_AMOUNT_NOT_POSITIVE", "票据金额必须大于零"),
+    ("INVOICE_AMOUNT_NOT_INTEGER", "票据金额必须为以最小货币单位计的整数"),
+    ("INVOICE_AMOUNT_EXCEEDS_CAP", "票据金额超过允许的上限"),
+    ("UNSUPPORTED_CURRENCY", "不支持该币种"),
+    ("INVOICE_DUE_DATE_NOT_IN_FUTURE", "票据到期日必须晚于当前时间"),
+    ("STALE_WRITE", "票据已被其他请求修改，请刷新后重试"),
+    ("INSUFFICIENT_CAPACITY", "认购份数超过票据剩余可认购份数"),
+    ("REQUEST_TIMEOUT", "请求处理超时，请稍后重试"),
+    ("INVALID_ID", "ID 格式无效"),
+    ("INVOICE_NOT_FOUND", "票据不存在"),
+    ("INVOICE_BATCH_TOO_LARGE", "单次批量处理的票据数量超过上限"),
+    ("ENTERPRISE_EXISTS", "该钱包地址已登记企业"),
+    ("ROLE_TRANSITION_NOT_ALLOWED", "不允许该角色变更"),
+    ("LAST_ADMIN", "不能降级最后一个平台管理员"),
+    ("NOT_AUTHORIZED_FOR_ENTERPRISE", "当前钱包不是该企业钱包的授权签名人"),
+    ("CONTRACT_REVERTED", "交易被智能合约拒绝"),
+    ("CONTRACT_PAUSED", "合约已暂停"),
+    ("CONTRACT_CALLER_UNAUTHORIZED", "平台账户无权执行该合约操作"),
+    ("INSUFFICIENT_TOKEN_BALANCE", "代币余额不足"),
+    ("INSUFFICIENT_TOKEN_ALLOWANCE", "代币授权额度不足，请先授权"),
+    ("INVOICE_ALREADY_ON_CHAIN", "票据已在链上登记"),
+    ("TOKEN_BATCH_NOT_FOUND_ON_CHAIN", "链上不存在该代币批次"),
+    ("TOKEN_BATCH_NOT_ACTIVE", "代币批次未处于发行状态"),
+    ("CHALLENGE_EXPIRED", "登录挑战已过期，请重新获取"),
+    ("INVALID_STATUS_TRANSITION", "票据当前状态不允许变更为目标状态"),
+    ("INVOICE_NOT_DELETABLE", "只能删除尚未认购的待审核票据"),
+    ("TOKEN_DENYLIST_UNAVAILABLE", "暂时无法校验令牌状态，请稍后重试"),
+    ("ACCOUNT_DELETED", "该账户已注销"),
+    ("WEBHOOK_URL_NOT_ALLOWED", "回调地址必须是公网可访问的 http(s) 地址"),
+    ("TERMS_NOT_ACCEPTED", "该票据尚未接受融资条款"),
+    ("TERMS_NOT_ACCEPTABLE", "该票据当前无法接受融资条款"),
+    ("INVOICE_NOT_AVAILABLE", "该票据当前不可认购"),
+    ("INVALID_PURCHASE_SHARES", "认购份数超过可认购份数"),
+    ("INSUFFICIENT_FUNDS", "余额不足"),
+    ("INVOICE_NOT_EDITABLE", "只能编辑待审核的票据"),
+    ("DOCUMENT_LIMIT_REACHED", "票据附件数量已达上限"),
+    ("INVOICE_NOT_ON_CHAIN", "票据尚未上链发行"),
+    ("SETTLEMENT_BEFORE_MATURITY", "票据未到期，不能兑付"),
+    ("SETTLEMENT_IN_PROGRESS", "该票据正在兑付中"),
+    ("INVOICE_ALREADY_SETTLED", "该票据已兑付"),
+    ("INVOICE_NOT_FINANCED", "该票据尚未融资"),
+    ("REPAYMENT_NOT_ALLOWED", "该票据当前不能登记还款"),
+    ("REFUND_NOT_ALLOWED", "该票据当前状态不能退款"),
+    ("INTEREST_ALREADY_ACCRUED", "该日期的利息已计提"),
+    ("IDEMPOTENCY_KEY_IN_PROGRESS", "相同幂等键的请求仍在处理中"),
+    ("IDEMPOTENCY_KEY_REUSED", "幂等键已用于其他请求"),
+    ("CHAIN_TRANSACTION_FAILED", "链上交易失败"),
+];
+
+fn catalog(locale: Locale) -> &'static [(&'static str, &'static str)] {
+    match locale {
+        Locale::En => EN,
+        Locale::Zh => ZH,
+    }
+}
+
+/// 按语言查找错误码对应的消息，缺失时依次回退到英文、错误码本身
+pub fn message(code: &'static str, locale: Locale) -> &'static str {
+    let lookup = |table: &'static [(&'static str, &'static str)]| table.iter().find(|(c, _)| *c == code).map(|(_, m)| *m);
+    lookup(catalog(locale)).or_else(|| lookup(EN)).unwrap_or(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_accept_language() {
+        assert_eq!(Locale::negotiate(None), Locale::En);
+        assert_eq!(Locale::negotiate(Some("zh-CN,zh;q=0.9,en;q=0.8")), Locale::Zh);
+        assert_eq!(Locale::negotiate(Some("fr-FR,en;q=0.5,zh;q=0.7")), Locale::Zh);
+        assert_eq!(Locale::negotiate(Some("de,fr;q=0.8")), Locale::En);
+        assert_eq!(Locale::negotiate(Some("zh;q=0,en")), Locale::En);
+    }
+
+    #[test]
+    fn test_catalogs_cover_same_codes() {
+        for (code, _) in EN {
+            assert!(ZH.iter().any(|(c, _)| c == code), "missing zh message for {}", code);
+        }
+        assert_eq!(message("NOT_FOUND", Locale::Zh), "请求的资源不存在");
+        assert_eq!(message("UNKNOWN_CODE", Locale::Zh), "UNKNOWN_CODE");
+    }
+}
@@ -0,0 +1,265 @@
+//! JWT 签名与校验密钥 (支持按 kid 轮换)
+//!
+//! 未配置 `jwt.current_kid` 时使用 `jwt.secret` 以 HS256 签名，令牌不带 kid 头。
+//! 配置后新令牌按 `jwt.algorithm` 使用当前密钥签名并写入 kid；校验时按 kid 选择密钥，
+//! 未知 kid 一律拒绝。不带 kid 的令牌只按 HS256 + `jwt.secret` 校验，便于从旧配置迁移。
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use configs::CFG;
+use configs::cfgs::Jwt as JwtCfg;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation, decode, decode_header, encode};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use thiserror::Error;
+
+/// 按配置构建的密钥集，配置错误在启动时由 `validate_config` 报告
+pub static JWT_KEYS: Lazy<JwtKeySet> = Lazy::new(|| JwtKeySet::from_config(&CFG.jwt).expect("invalid [jwt] key configuration"));
+
+#[derive(Debug, Error)]
+pub enum JwtKeyError {
+    #[error("unknown key id {0}")]
+    UnknownKid(String),
+    #[error("token has no key id and no legacy secret is configured")]
+    MissingKid,
+    #[error(transparent)]
+    Jwt(#[from] jsonwebtoken::errors::Error),
+}
+
+pub struct JwtKeySet {
+    algorithm: Algorithm,
+    /// 当前签名密钥
+    signing: Option<(String, EncodingKey)>,
+    /// `jwt.secret`：不带 kid 的令牌
+    legacy: Option<(EncodingKey, DecodingKey)>,
+    verifying: HashMap<String, DecodingKey>,
+}
+
+impl JwtKeySet {
+    /// 只使用单个 HS256 密钥 (不带 kid)
+    pub fn hs256(secret: &str) -> Self {
+        Self::new(Algorithm::HS256, secret)
+    }
+
+    /// `algorithm` 为密钥集的算法，`legacy_secret` 为空时不接受不带 kid 的令牌
+    pub fn new(algorithm: Algorithm, legacy_secret: &str) -> Self {
+        let legacy = (!legacy_secret.is_empty())
+            .then(|| (EncodingKey::from_secret(legacy_secret.as_bytes()), DecodingKey::from_secret(legacy_secret.as_bytes())));
+        Self { algorithm, signing: None, legacy, verifying: HashMap::new() }
+    }
+
+    /// 添加只用于校验的密钥 (轮换中的旧密钥)
+    pub fn with_verifying_key(mut self, kid: &str, key: DecodingKey) -> Self {
+        self.verifying.insert(kid.to_string(), key);
+        self
+    }
+
+    /// 设置签发新令牌使用的密钥
+    pub fn with_signing_key(mut self, kid: &str, signing: EncodingKey, verifying: DecodingKey) -> Self {
+        self.signing = Some((kid.to_string(), signing));
+        self.with_verifying_key(kid, verifying)
+    }
+
+    pub fn from_config(cfg: &JwtCfg) -> Result<Self, String> {
+        let algorithm = Algorithm::from_str(cfg.algorithm.trim()).map_err(|_| format!("jwt.algorithm {} is not supported", cfg.algorithm))?;
+        if !matches!(algorithm, Algorithm::HS256 | Algorithm::RS256) {
+            return Err(format!("jwt.algorithm must be HS256 or RS256 (got {:?})", algorithm));
+        }
+        let mut keys = Self::new(algorithm, &cfg.secret);
+        for key in &cfg.keys {
+            if key.kid.trim().is_empty() {
+                return Err("jwt.keys entries must have a kid".to_string());
+            }
+            let (signing, verifying) = match algorithm {
+                Algorithm::HS256 => {
+                    if key.secret.is_empty() {
+                        return Err(format!("jwt key {} has no secret", key.kid));
+                    }
+                    (Some(EncodingKey::from_secret(key.secret.as_bytes())), DecodingKey::from_secret(key.secret.as_bytes()))
+                }
+                _ => {
+                    let public = read_pem(&key.kid, "public_key_path", &key.public_key_path)?;
+                    let verifying = DecodingKey::from_rsa_pem(&public).map_err(|e| format!("jwt key {} public key: {}", key.kid, e))?;
+                    let signing = if key.private_key_path.is_empty() {
+                        None
+                    } else {
+                        let private = read_pem(&key.kid, "private_key_path", &key.private_key_path)?;
+                        Some(EncodingKey::from_rsa_pem(&private).map_err(|e| format!("jwt key {} private key: {}", key.kid, e))?)
+                    };
+                    (signing, verifying)
+                }
+            };
+            keys = match signing {
+                Some(signing) if key.kid == cfg.current_kid => keys.with_signing_key(&key.kid, signing, verifying),
+                _ => keys.with_verifying_key(&key.kid, verifying),
+            };
+        }
+        if cfg.current_kid.is_empty() {
+            if keys.legacy.is_none() {
+                return Err("jwt.secret is required when jwt.current_kid is empty".to_string());
+            }
+        } else if keys.signing.is_none() {
+            return Err(format!("jwt.current_kid {} has no signing key in jwt.keys", cfg.current_kid));
+        }
+        Ok(keys)
+    }
+
+    /// 签发令牌：有当前密钥时写入 kid，否则使用 `jwt.secret`
+    pub fn sign<T: Serialize>(&self, claims: &T) -> Result<String, jsonwebtoken::errors::Error> {
+        match (&self.signing, &self.legacy) {
+            (Some((kid, key)), _) => {
+                let mut header = Header::new(self.algorithm);
+                header.kid = Some(kid.clone());
+                encode(&header, claims, key)
+            }
+            (None, Some((key, _))) => encode(&Header::new(Algorithm::HS256), claims, key),
+            (None, None) => Err(jsonwebtoken::errors::ErrorKind::InvalidKeyFormat.into()),
+        }
+    }
+
+    /// 按 kid 选择密钥校验签名；`configure` 可调整其余校验项 (如跳过 exp)
+    pub fn decode<T: DeserializeOwned>(&self, token: &str, configure: impl FnOnce(&mut Validation)) -> Result<TokenData<T>, JwtKeyError> {
+        let header = decode_header(token)?;
+        // 算法由密钥决定，不信任令牌头中的 alg
+        let (algorithm, key) = match header.kid {
+            Some(kid) => (self.algorithm, self.verifying.get(&kid).ok_or(JwtKeyError::UnknownKid(kid))?),
+            None => (Algorithm::HS256, self.legacy.as_ref().map(|(_, key)| key).ok_or(JwtKeyError::MissingKid)?),
+        };
+        let mut validation = Validation::new(algorithm);
+        configure(&mut validation);
+        Ok(decode::<T>(token, key, &validation)?)
+    }
+}
+
+fn read_pem(kid: &str, field: &str, path: &str) -> Result<Vec<u8>, String> {
+    if path.is_empty() {
+        return Err(format!("jwt key {} requires {}", kid, field));
+    }
+    std::fs::read(path).map_err(|e| format!("jwt key {} {} {}: {}", kid, field, path, e))
+}
+
+/// 启动时校验 `[jwt]` 密钥配置
+pub fn validate_config() -> Result<(), String> {
+    JwtKeySet::from_config(&CFG.jwt).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::Claims;
+
+    const CURRENT_PRIVATE: &str = include_str!("testdata/jwt_rs256_current.pem");
+    const CURRENT_PUBLIC: &str = include_str!("testdata/jwt_rs256_current.pub.pem");
+    const RETIRED_PRIVATE: &str = include_str!("testdata/jwt_rs256_retired.pem");
+    const RETIRED_PUBLIC: &str = include_str!("testdata/jwt_rs256_retired.pub.pem");
+    const SECRET: &str = "test-secret-test-secret-test-secret";
+
+    fn claims() -> Claims {
+        Claims {
+            sub: "0xabc".to_string(),
+            exp: (chrono::Utc::now().timestamp() + 600) as usize,
+            user_id: "64b000000000000000000001".to_string(),
+            role: "investor".to_string(),
+            jti: "jti-1".to_string(),
+            iat: 0,
+        }
+    }
+
+    fn rs256(kid: &str, private: &str, public: &str) -> JwtKeySet {
+        JwtKeySet::new(Algorithm::RS256, "").with_signing_key(
+            kid,
+            EncodingKey::from_rsa_pem(private.as_bytes()).unwrap(),
+            DecodingKey::from_rsa_pem(public.as_bytes()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_rs256_token_round_trips_with_kid() {
+        let keys = rs256("2026-10", CURRENT_PRIVATE, CURRENT_PUBLIC);
+        let token = keys.sign(&claims()).unwrap();
+
+        let header = decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::RS256);
+        assert_eq!(header.kid.as_deref(), Some("2026-10"));
+        let decoded = keys.decode::<Claims>(&token, |_| {}).unwrap();
+        assert_eq!(decoded.claims.sub, "0xabc");
+        assert_eq!(decoded.claims.jti, "jti-1");
+    }
+
+    #[test]
+    fn test_retired_key_verifies_during_overlap() {
+        let old_keys = rs256("2026-09", RETIRED_PRIVATE, RETIRED_PUBLIC);
+        let old_token = old_keys.sign(&claims()).unwrap();
+
+        // 轮换后：当前密钥签发新令牌，旧密钥只保留公钥
+        let rotated = rs256("2026-10", CURRENT_PRIVATE, CURRENT_PUBLIC)
+            .with_verifying_key("2026-09", DecodingKey::from_rsa_pem(RETIRED_PUBLIC.as_bytes()).unwrap());
+        assert!(rotated.decode::<Claims>(&old_token, |_| {}).is_ok());
+        let new_token = rotated.sign(&claims()).unwrap();
+        assert_eq!(decode_header(&new_token).unwrap().kid.as_deref(), Some("2026-10"));
+        assert!(rotated.decode::<Claims>(&new_token, |_| {}).is_ok());
+
+        // 重叠期结束、旧密钥移除后，旧令牌的 kid 未知
+        let after = rs256("2026-10", CURRENT_PRIVATE, CURRENT_PUBLIC);
+        assert!(matches!(after.decode::<Claims>(&old_token, |_| {}), Err(JwtKeyError::UnknownKid(kid)) if kid == "2026-09"));
+    }
+
+    #[test]
+    fn test_unknown_kid_and_forged_signature_rejected() {
+        let keys = rs256("2026-10", CURRENT_PRIVATE, CURRENT_PUBLIC);
+        // 使用另一把私钥但冒用当前 kid
+        let forged = rs256("2026-10", RETIRED_PRIVATE, RETIRED_PUBLIC).sign(&claims()).unwrap();
+        assert!(matches!(keys.decode::<Claims>(&forged, |_| {}), Err(JwtKeyError::Jwt(_))));
+
+        let other = rs256("unknown", RETIRED_PRIVATE, RETIRED_PUBLIC).sign(&claims()).unwrap();
+        assert!(matches!(keys.decode::<Claims>(&other, |_| {}), Err(JwtKeyError::UnknownKid(_))));
+    }
+
+    #[test]
+    fn test_legacy_hs256_tokens_without_kid() {
+        let legacy = JwtKeySet::hs256(SECRET);
+        let token = legacy.sign(&claims()).unwrap();
+        assert!(decode_header(&token).unwrap().kid.is_none());
+        assert!(legacy.decode::<Claims>(&token, |_| {}).is_ok());
+
+        // 迁移到 RS256 后仍接受旧的 HS256 令牌；未配置 secret 时拒绝
+        let migrated = JwtKeySet::new(Algorithm::RS256, SECRET).with_signing_key(
+            "2026-10",
+            EncodingKey::from_rsa_pem(CURRENT_PRIVATE.as_bytes()).unwrap(),
+            DecodingKey::from_rsa_pem(CURRENT_PUBLIC.as_bytes()).unwrap(),
+        );
+        assert!(migrated.decode::<Claims>(&token, |_| {}).is_ok());
+        let strict = rs256("2026-10", CURRENT_PRIVATE, CURRENT_PUBLIC);
+        assert!(matches!(strict.decode::<Claims>(&token, |_| {}), Err(JwtKeyError::MissingKid)));
+    }
+
+    #[test]
+    fn test_from_config_requires_signing_key_for_current_kid() {
+        let mut cfg = JwtCfg {
+            secret: String::new(),
+            refresh_grace_secs: 3600,
+            algorithm: "HS256".to_string(),
+            current_kid: String::new(),
+            keys: Vec::new(),
+        };
+        assert!(JwtKeySet::from_config(&cfg).is_err());
+
+        cfg.current_kid = "k1".to_string();
+        cfg.keys.push(configs::cfgs::JwtKey {
+            kid: "k1".to_string(),
+            secret: SECRET.to_string(),
+            private_key_path: String::new(),
+            public_key_path: String::new(),
+        });
+        let keys = JwtKeySet::from_config(&cfg).unwrap();
+        let token = keys.sign(&claims()).unwrap();
+        assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some("k1"));
+
+        cfg.algorithm = "ES256".to_string();
+        assert!(JwtKeySet::from_config(&cfg).is_err());
+        cfg.algorithm = "RS256".to_string();
+        assert!(JwtKeySet::from_config(&cfg).is_err(), "RS256 keys need PEM paths");
+    }
+}
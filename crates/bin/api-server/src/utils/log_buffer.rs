@@ -0,0 +1,160 @@
+//! 进程内日志环形缓冲区
+//!
+//! log4rs 通过 `ring_buffer` appender 把日志写入这里，
+//! 管理员可通过 `GET /admin/logs/stream` 以 SSE 的方式实时查看。
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::Local;
+use configs::CFG;
+use log::{Level, Record};
+use log4rs::append::Append;
+use log4rs::config::{Deserialize, Deserializers};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+pub static LOG_BUFFER: Lazy<LogBuffer> = Lazy::new(|| LogBuffer::new(CFG.admin.log_buffer_capacity));
+
+// 日志中可能出现的敏感信息：JWT、Bearer token、私钥/密码键值对
+static REDACT_PATTERNS: Lazy<Vec<(Regex, &'static str)>> = Lazy::new(|| {
+    vec![
+        (Regex::new(r"eyJ[A-Za-z0-9_-]+\.[A-Za-z0-9_-]+\.[A-Za-z0-9_-]*").unwrap(), "[REDACTED_JWT]"),
+        (Regex::new(r"(?i)bearer\s+\S+").unwrap(), "Bearer [REDACTED]"),
+        (Regex::new(r#"(?i)(secret|password|private_key|signature)(["']?\s*[:=]\s*["']?)[^\s,"'}]+"#).unwrap(), "$1$2[REDACTED]"),
+    ]
+});
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LogEntry {
+    pub timestamp: String,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+impl LogEntry {
+    pub fn level(&self) -> Level {
+        self.level.parse().unwrap_or(Level::Trace)
+    }
+}
+
+pub struct LogBuffer {
+    capacity: usize,
+    entries: Mutex<VecDeque<LogEntry>>,
+    sender: broadcast::Sender<LogEntry>,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        let (sender, _) = broadcast::channel(capacity);
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+            sender,
+        }
+    }
+
+    pub fn push(&self, entry: LogEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+        // 没有订阅者时 send 会返回错误，忽略即可
+        let _ = self.sender.send(entry);
+    }
+
+    /// 返回缓冲区中级别不低于 `min_level` 的日志快照
+    pub fn snapshot(&self, min_level: Level) -> Vec<LogEntry> {
+        match self.entries.lock() {
+            Ok(entries) => entries.iter().filter(|e| e.level() <= min_level).cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<LogEntry> {
+        self.sender.subscribe()
+    }
+}
+
+pub fn redact(message: &str) -> String {
+    REDACT_PATTERNS
+        .iter()
+        .fold(message.to_string(), |acc, (re, replacement)| re.replace_all(&acc, *replacement).into_owned())
+}
+
+/// log4rs appender, 写入 [`LOG_BUFFER`]
+#[derive(Debug)]
+pub struct RingBufferAppender;
+
+impl Append for RingBufferAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        LOG_BUFFER.push(LogEntry {
+            timestamp: Local::now().format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: redact(&record.args().to_string()),
+        });
+        Ok(())
+    }
+
+    fn flush(&self) {}
+}
+
+#[derive(serde::Deserialize)]
+pub struct RingBufferAppenderConfig {}
+
+pub struct RingBufferAppenderDeserializer;
+
+impl Deserialize for RingBufferAppenderDeserializer {
+    type Trait = dyn Append;
+    type Config = RingBufferAppenderConfig;
+
+    fn deserialize(&self, _config: RingBufferAppenderConfig, _: &Deserializers) -> anyhow::Result<Box<dyn Append>> {
+        Ok(Box::new(RingBufferAppender))
+    }
+}
+
+/// 默认的 log4rs deserializers 再加上 `ring_buffer` appender
+pub fn deserializers() -> Deserializers {
+    let mut deserializers = Deserializers::default();
+    deserializers.insert("ring_buffer", RingBufferAppenderDeserializer);
+    deserializers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sensitive_values() {
+        let msg = "auth Bearer abc.def.ghi password=hunter2 token eyJhbGciOi.eyJzdWIi.sig";
+        let redacted = redact(msg);
+        assert!(!redacted.contains("hunter2"));
+        assert!(!redacted.contains("eyJhbGciOi"));
+        assert!(!redacted.contains("abc.def.ghi"));
+    }
+
+    #[test]
+    fn test_buffer_capacity_and_level_filter() {
+        let buffer = LogBuffer::new(2);
+        for (i, level) in ["INFO", "ERROR", "DEBUG"].iter().enumerate() {
+            buffer.push(LogEntry {
+                timestamp: i.to_string(),
+                level: level.to_string(),
+                target: "test".to_string(),
+                message: format!("m{}", i),
+            });
+        }
+        // 容量为 2，最早的 INFO 被挤出
+        assert_eq!(buffer.snapshot(Level::Trace).len(), 2);
+        let errors = buffer.snapshot(Level::Warn);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "m1");
+    }
+}
@@ -0,0 +1,119 @@
+//! HTTP 请求指标，与合约写操作等业务指标共用 `service::metrics::REGISTRY`
+
+use once_cell::sync::Lazy;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, Opts, TextEncoder};
+use service::metrics::{CONTRACT_WRITES, REGISTRY, TRANSFERS_SKIPPED};
+
+pub static HTTP_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    let counter = IntCounterVec::new(Opts::new("http_requests_total", "HTTP requests by route and status"), &["method", "path", "status"])
+        .expect("valid http_requests_total metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("http_requests_total registered once");
+    counter
+});
+
+pub static HTTP_REQUEST_DURATION: Lazy<HistogramVec> = Lazy::new(|| {
+    let histogram = HistogramVec::new(
+        HistogramOpts::new("http_request_duration_seconds", "HTTP request latency by route"),
+        &["method", "path"],
+    )
+    .expect("valid http_request_duration_seconds metric");
+    REGISTRY.register(Box::new(histogram.clone())).expect("http_request_duration_seconds registered once");
+    histogram
+});
+
+pub static AUTH_DENYLIST_PRUNED: Lazy<IntCounter> = Lazy::new(|| {
+    let counter = IntCounter::new("auth_denylist_pruned_total", "Expired in-process token denylist entries removed by the cleanup task")
+        .expect("valid auth_denylist_pruned_total metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("auth_denylist_pruned_total registered once");
+    counter
+});
+
+pub fn observe_request(method: &str, path: &str, status: u16, seconds: f64) {
+    HTTP_REQUESTS.with_label_values(&[method, path, &status.to_string()]).inc();
+    HTTP_REQUEST_DURATION.with_label_values(&[method, path]).observe(seconds);
+}
+
+/// 以文本格式导出所有指标
+pub fn render() -> Result<String, String> {
+    // 指标在首次使用时才注册到 REGISTRY
+    Lazy::force(&HTTP_REQUESTS);
+    Lazy::force(&HTTP_REQUEST_DURATION);
+    Lazy::force(&AUTH_DENYLIST_PRUNED);
+    Lazy::force(&CONTRACT_WRITES);
+    Lazy::force(&TRANSFERS_SKIPPED);
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&REGISTRY.gather(), &mut buffer).map_err(|e| e.to_string())?;
+    String::from_utf8(buffer).map_err(|e| e.to_string())
+}
+
+/// 未匹配到业务路由的请求 (静态目录兜底返回 404、参数无法还原为模板) 统一使用的路径标签
+pub const UNMATCHED_PATH: &str = "unmatched";
+
+/// 请求的路径标签：匹配到的路由模板，未匹配的请求归入 [`UNMATCHED_PATH`]，避免扫描等随机路径撑大标签基数
+pub fn path_label<'a>(path: &str, params: impl IntoIterator<Item = (&'a str, &'a str)>, status: u16) -> String {
+    match route_pattern(path, params) {
+        // 通配路由 (静态目录) 返回 404 说明没有对应的文件或接口
+        Some(pattern) if !(status == 404 && pattern.contains("{**")) => pattern,
+        _ => UNMATCHED_PATH.to_string(),
+    }
+}
+
+/// 把路径中的参数值还原为路由模板 (`/invoice/64b0…/timeline` -> `/invoice/{id}/timeline`)，避免标签基数随 ID 增长
+///
+/// 参数值在路径中找不到 (如经过百分号编码) 时返回 `None`
+pub fn route_pattern<'a>(path: &str, params: impl IntoIterator<Item = (&'a str, &'a str)>) -> Option<String> {
+    let mut pattern = path.to_string();
+    for (name, value) in params {
+        if value.is_empty() {
+            continue;
+        }
+        if value.contains('/') {
+            // 通配参数 (`{**path}`) 匹配多段路径
+            let prefix = pattern.strip_suffix(value)?;
+            pattern = format!("{}{{**{}}}", prefix, name);
+            continue;
+        }
+        let segments: Vec<String> = pattern.split('/').map(str::to_string).collect();
+        if !segments.iter().any(|segment| segment == value) {
+            return None;
+        }
+        pattern = segments
+            .into_iter()
+            .map(|segment| if segment == value { format!("{{{}}}", name) } else { segment })
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+    Some(pattern)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_pattern() {
+        assert_eq!(
+            route_pattern("/rwa/invoice/64b000000000000000000001/timeline", [("id", "64b000000000000000000001")]).as_deref(),
+            Some("/rwa/invoice/{id}/timeline")
+        );
+        assert_eq!(
+            route_pattern("/rwa/invoice/abc/document/def", [("id", "abc"), ("doc_id", "def")]).as_deref(),
+            Some("/rwa/invoice/{id}/document/{doc_id}")
+        );
+        assert_eq!(route_pattern("/static/js/app.js", [("path", "static/js/app.js")]).as_deref(), Some("/{**path}"));
+        assert_eq!(route_pattern("/rwa/purchase/history", []).as_deref(), Some("/rwa/purchase/history"));
+        // 路径经过编码，参数值是解码后的
+        assert_eq!(route_pattern("/rwa/invoice/a%20b", [("id", "a b")]), None);
+    }
+
+    #[test]
+    fn test_unmatched_paths_share_one_label() {
+        assert_eq!(path_label("/wp-admin/setup.php", [("path", "wp-admin/setup.php")], 404), UNMATCHED_PATH);
+        assert_eq!(path_label("/.env", [("path", ".env")], 404), UNMATCHED_PATH);
+        assert_eq!(path_label("/rwa/invoice/a%20b", [("id", "a b")], 400), UNMATCHED_PATH);
+        // 静态文件存在时保留通配模板，业务路由返回 404 时保留路由模板
+        assert_eq!(path_label("/js/app.js", [("path", "js/app.js")], 200), "/{**path}");
+        assert_eq!(path_label("/rwa/invoice/abc", [("id", "abc")], 404), "/rwa/invoice/{id}");
+    }
+}
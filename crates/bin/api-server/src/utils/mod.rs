@@ -1,4 +1,23 @@
+pub mod api_error;
 pub mod captcha;
+pub mod client_ip;
+pub mod contract_revert;
+pub mod cors;
+pub mod enterprise_info_cache;
+pub mod feature_flags;
+pub mod health;
+pub mod jwt_keys;
+pub mod i18n;
+pub mod log_buffer;
 pub mod md5;
+pub mod metrics;
+pub mod nonce_store;
+pub mod pagination;
+pub mod rate_limiter;
+pub mod request_id;
 pub mod res;
+pub mod secrets;
+pub mod shutdown;
+pub mod token_denylist;
+pub mod typed_challenge;
 
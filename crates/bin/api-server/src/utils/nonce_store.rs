@@ -0,0 +1,243 @@
+//! 登录/绑定挑战的 nonce 存储
+//!
+//! nonce 保存在 Redis (`auth:nonce:{request_id}`，SETEX 过期)，多个 api-server 实例共享且重启不丢失；
+//! 读取使用 GETDEL 保证一次性使用。Redis 不可用时回退到进程内缓存，此时挑战只能在同一实例上完成。
+
+use std::time::Duration;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use configs::CFG;
+use tracing::warn;
+use moka::future::Cache;
+use redis::{AsyncCommands, Client as RedisClient};
+use salvo::async_trait;
+
+/// 挑战消息模板中的随机数占位符
+pub const NONCE_PLACEHOLDER: &str = "{nonce}";
+
+/// 按模板生成待签名的挑战消息
+pub fn render_challenge(template: &str, nonce: &str) -> String {
+    template.replace(NONCE_PLACEHOLDER, nonce)
+}
+
+/// 字符串挑战中签发时间 / 过期时间所在行的前缀 (与 EIP-4361 的字段名一致)
+const ISSUED_AT_PREFIX: &str = "Issued At: ";
+const EXPIRATION_TIME_PREFIX: &str = "Expiration Time: ";
+
+/// 在挑战消息末尾附加签发时间和过期时间 (RFC 3339 UTC)，两者随消息一起被签名
+pub fn with_validity(message: &str, issued_at: i64, expires_at: i64) -> String {
+    format!("{}\n{}{}\n{}{}", message, ISSUED_AT_PREFIX, rfc3339(issued_at), EXPIRATION_TIME_PREFIX, rfc3339(expires_at))
+}
+
+/// 字符串挑战中的过期时间 (Unix 秒)，未包含过期时间的旧格式挑战返回 None
+pub fn challenge_expires_at(message: &str) -> Option<i64> {
+    let line = message.lines().rev().find_map(|line| line.strip_prefix(EXPIRATION_TIME_PREFIX))?;
+    DateTime::parse_from_rfc3339(line.trim()).ok().map(|t| t.timestamp())
+}
+
+fn rfc3339(secs: i64) -> String {
+    DateTime::<Utc>::from_timestamp(secs, 0).unwrap_or_default().to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// 模板必须包含随机数，否则所有挑战相同，签名可以被重放
+pub fn validate_template(template: &str) -> Result<(), String> {
+    if template.contains(NONCE_PLACEHOLDER) {
+        Ok(())
+    } else {
+        Err(format!("auth.message_template must contain the {} placeholder", NONCE_PLACEHOLDER))
+    }
+}
+
+/// 启动时校验登录挑战配置，配置错误时拒绝启动
+pub fn validate_config() -> Result<(), String> {
+    if CFG.auth.nonce_ttl_secs == 0 {
+        return Err("auth.nonce_ttl_secs must be greater than 0".to_string());
+    }
+    validate_template(&CFG.auth.message_template)
+}
+
+#[async_trait]
+pub trait NonceStore: Send + Sync {
+    async fn put(&self, request_id: &str, value: &str) -> Result<(), String>;
+    /// 取出并删除，不存在或已过期时返回 None
+    async fn take(&self, request_id: &str) -> Result<Option<String>, String>;
+}
+
+pub struct RedisNonceStore {
+    client: RedisClient,
+    ttl_secs: u64,
+}
+
+impl RedisNonceStore {
+    pub fn new(client: RedisClient, ttl_secs: u64) -> Self {
+        Self { client, ttl_secs }
+    }
+
+    fn key(request_id: &str) -> String {
+        format!("auth:nonce:{}", request_id)
+    }
+}
+
+#[async_trait]
+impl NonceStore for RedisNonceStore {
+    async fn put(&self, request_id: &str, value: &str) -> Result<(), String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.set_ex::<_, _, ()>(Self::key(request_id), value, self.ttl_secs).await.map_err(|e| e.to_string())
+    }
+
+    async fn take(&self, request_id: &str) -> Result<Option<String>, String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        // GETDEL 需要 Redis >= 6.2
+        redis::cmd("GETDEL").arg(Self::key(request_id)).query_async(&mut conn).await.map_err(|e| e.to_string())
+    }
+}
+
+/// 进程内 nonce 缓存
+pub struct MemoryNonceStore {
+    cache: Cache<String, String>,
+}
+
+impl MemoryNonceStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self { cache: Cache::builder().time_to_live(ttl).max_capacity(10_000).build() }
+    }
+}
+
+#[async_trait]
+impl NonceStore for MemoryNonceStore {
+    async fn put(&self, request_id: &str, value: &str) -> Result<(), String> {
+        self.cache.insert(request_id.to_string(), value.to_string()).await;
+        Ok(())
+    }
+
+    async fn take(&self, request_id: &str) -> Result<Option<String>, String> {
+        Ok(self.cache.remove(request_id).await)
+    }
+}
+
+/// 优先使用 `primary`，出错时回退到进程内缓存
+pub struct FallbackNonceStore<P> {
+    primary: P,
+    fallback: MemoryNonceStore,
+}
+
+impl<P: NonceStore> FallbackNonceStore<P> {
+    pub fn new(primary: P, ttl: Duration) -> Self {
+        Self { primary, fallback: MemoryNonceStore::new(ttl) }
+    }
+}
+
+#[async_trait]
+impl<P: NonceStore> NonceStore for FallbackNonceStore<P> {
+    async fn put(&self, request_id: &str, value: &str) -> Result<(), String> {
+        if let Err(e) = self.primary.put(request_id, value).await {
+            warn!("Nonce store unavailable, keeping challenge {} in memory: {}", request_id, e);
+            return self.fallback.put(request_id, value).await;
+        }
+        Ok(())
+    }
+
+    async fn take(&self, request_id: &str) -> Result<Option<String>, String> {
+        match self.primary.take(request_id).await {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => {}
+            Err(e) => warn!("Nonce store unavailable when reading challenge {}: {}", request_id, e),
+        }
+        // 写入时可能已回退到内存
+        self.fallback.take(request_id).await
+    }
+}
+
+/// 注入 depot 的 nonce 存储类型
+pub type AuthNonceStore = FallbackNonceStore<RedisNonceStore>;
+
+/// `ttl` 同时用于 Redis 过期时间与进程内回退缓存
+pub fn auth_nonce_store(client: RedisClient, ttl: Duration) -> AuthNonceStore {
+    FallbackNonceStore::new(RedisNonceStore::new(client, ttl.as_secs().max(1)), ttl)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    struct UnavailableStore;
+
+    #[async_trait]
+    impl NonceStore for UnavailableStore {
+        async fn put(&self, _: &str, _: &str) -> Result<(), String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn take(&self, _: &str) -> Result<Option<String>, String> {
+            Err("connection refused".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nonce_is_single_use_and_expires() {
+        let store = MemoryNonceStore::new(Duration::from_millis(50));
+        store.put("req-1", "nonce-1").await.unwrap();
+        assert_eq!(store.take("req-1").await.unwrap().as_deref(), Some("nonce-1"));
+        assert_eq!(store.take("req-1").await.unwrap(), None);
+        assert_eq!(store.take("unknown").await.unwrap(), None);
+
+        store.put("req-2", "nonce-2").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(store.take("req-2").await.unwrap(), None);
+    }
+
+    #[test]
+    fn test_challenge_template() {
+        assert_eq!(render_challenge("Sign in to SFC: {nonce}", "abc123"), "Sign in to SFC: abc123");
+        assert_eq!(render_challenge("pharos-auth-{nonce}", "ff"), "pharos-auth-ff");
+        assert!(validate_template("Sign in to SFC: {nonce}").is_ok());
+        assert!(validate_template("Sign in to SFC").is_err());
+    }
+
+    #[test]
+    fn test_challenge_validity_lines() {
+        let message = with_validity("pharos-auth-ff", 1_700_000_000, 1_700_000_300);
+        assert_eq!(message, "pharos-auth-ff\nIssued At: 2023-11-14T22:13:20Z\nExpiration Time: 2023-11-14T22:18:20Z");
+        assert_eq!(challenge_expires_at(&message), Some(1_700_000_300));
+        assert_eq!(challenge_expires_at("pharos-auth-ff"), None);
+        assert_eq!(challenge_expires_at("pharos-auth-ff\nExpiration Time: tomorrow"), None);
+    }
+
+    #[tokio::test]
+    async fn test_configured_ttl_applies_to_fallback() {
+        // 无法连接的 Redis，挑战落入进程内缓存，过期时间仍按配置
+        let client = RedisClient::open("redis://127.0.0.1:1/").unwrap();
+        let store = auth_nonce_store(client, Duration::from_millis(100));
+        store.put("req-1", "nonce-1").await.unwrap();
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        assert_eq!(store.take("req-1").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_take_yields_nonce_once() {
+        let store = Arc::new(MemoryNonceStore::new(Duration::from_secs(60)));
+        store.put("req-1", "nonce-1").await.unwrap();
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.take("req-1").await.unwrap() })
+            })
+            .collect();
+        let mut taken = 0;
+        for task in tasks {
+            if task.await.unwrap().is_some() {
+                taken += 1;
+            }
+        }
+        assert_eq!(taken, 1);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_when_primary_unavailable() {
+        let store = FallbackNonceStore::new(UnavailableStore, Duration::from_secs(60));
+        store.put("req-1", "nonce-1").await.unwrap();
+        assert_eq!(store.take("req-1").await.unwrap().as_deref(), Some("nonce-1"));
+        assert_eq!(store.take("req-1").await.unwrap(), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! 按接口解析分页大小 (配置见 `[pagination]`)，以及列表接口的分页响应头
+
+use common::pagination::Page;
+use common::utils::pagination::PageSizePolicy;
+use configs::CFG;
+use once_cell::sync::Lazy;
+use salvo::http::HeaderValue;
+use salvo::oapi::ToSchema;
+use salvo::{Request, Response};
+
+/// 符合筛选条件的总数
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+static PAGE_SIZE_POLICY: Lazy<Result<PageSizePolicy, String>> = Lazy::new(|| {
+    let cfg = &CFG.pagination;
+    PageSizePolicy::new(cfg.default_page_size, cfg.max_page_size, cfg.hard_ceiling, cfg.overrides.clone())
+});
+
+/// 启动时校验分页配置，配置错误时拒绝启动
+pub fn validate_config() -> Result<(), String> {
+    PAGE_SIZE_POLICY.as_ref().map(|_| ()).map_err(|e| e.clone())
+}
+
+// validate_config 已在启动时拦截配置错误，这里仅作兜底，使用默认分页配置
+static DEFAULT_POLICY: Lazy<PageSizePolicy> = Lazy::new(|| {
+    let cfg = configs::cfgs::Pagination::default();
+    PageSizePolicy::new(cfg.default_page_size, cfg.max_page_size, cfg.hard_ceiling, cfg.overrides)
+        .expect("default pagination config is valid")
+});
+
+/// 计算接口 `endpoint` 的实际分页大小
+pub fn page_size(endpoint: &str, requested: Option<i64>) -> i64 {
+    let policy = PAGE_SIZE_POLICY.as_ref().unwrap_or(&DEFAULT_POLICY);
+    policy.resolve(endpoint, requested) as i64
+}
+
+/// 同 `page_size`，未指定时使用接口自己的默认值 `default` 而不是全局默认值
+pub fn page_size_or(endpoint: &str, requested: Option<i64>, default: u64) -> i64 {
+    let policy = PAGE_SIZE_POLICY.as_ref().unwrap_or(&DEFAULT_POLICY);
+    policy.resolve_or(endpoint, requested, default) as i64
+}
+
+/// 列表的翻页方式，用于生成 `Link` 响应头
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageLinks {
+    /// 页码分页：当前页码 (从 1 开始) 与每页条数，参数名为 `page` / `page_size`
+    Offset { page: u64, page_size: u64 },
+    /// 游标分页：下一页游标 (`Page::next_cursor`) 原样放入 `cursor` 参数。游标只能向后翻页，
+    /// 非第一页时以 `rel="first"` 代替 `rel="prev"`
+    Cursor,
+}
+
+/// 按 `page` 写入 `X-Total-Count` 与 RFC 5988 `Link` (next / prev / first) 响应头。
+/// 链接基于当前请求的路径和查询参数，只替换翻页参数
+pub fn set_page_headers<T: ToSchema + 'static>(req: &Request, res: &mut Response, page: &Page<T>, links: PageLinks) {
+    res.headers_mut().insert(TOTAL_COUNT_HEADER, HeaderValue::from(page.total));
+    let path = req.uri().path();
+    let query = req.uri().query().unwrap_or_default();
+    let mut rels = Vec::new();
+    match links {
+        PageLinks::Offset { page: number, page_size } => {
+            let number = number.max(1);
+            let size = page_size.to_string();
+            if number.saturating_mul(page_size) < page.total {
+                rels.push(("next", page_url(path, query, &[("page", Some(&(number + 1).to_string())), ("page_size", Some(&size))])));
+            }
+            if number > 1 {
+                rels.push(("prev", page_url(path, query, &[("page", Some(&(number - 1).to_string())), ("page_size", Some(&size))])));
+            }
+        }
+        PageLinks::Cursor => {
+            if let Some(cursor) = page.next_cursor.as_deref() {
+                rels.push(("next", page_url(path, query, &[("cursor", Some(cursor))])));
+            }
+            if query_param(query, "cursor").is_some_and(|c| !c.is_empty()) {
+                rels.push(("first", page_url(path, query, &[("cursor", None)])));
+            }
+        }
+    }
+    if rels.is_empty() {
+        return;
+    }
+    let link = rels.iter().map(|(rel, url)| format!("<{}>; rel=\"{}\"", url, rel)).collect::<Vec<_>>().join(", ");
+    if let Ok(value) = HeaderValue::from_str(&link) {
+        res.headers_mut().insert(salvo::http::header::LINK, value);
+    }
+}
+
+fn query_param<'q>(query: &'q str, name: &str) -> Option<&'q str> {
+    query.split('&').find_map(|pair| pair.split_once('=').filter(|(k, _)| *k == name).map(|(_, v)| v))
+}
+
+// 保留其余查询参数的顺序和原始编码，`overrides` 中值为 None 的参数被移除
+fn page_url(path: &str, query: &str, overrides: &[(&str, Option<&str>)]) -> String {
+    let mut pairs: Vec<String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(k, _)| k);
+            !overrides.iter().any(|(name, _)| *name == key)
+        })
+        .map(str::to_string)
+        .collect();
+    pairs.extend(overrides.iter().filter_map(|(name, value)| value.map(|v| format!("{}={}", name, encode_query_value(v)))));
+    if pairs.is_empty() { path.to_string() } else { format!("{}?{}", path, pairs.join("&")) }
+}
+
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use salvo::test::RequestBuilder;
+
+    fn offset_page(total: u64) -> Page<u64> {
+        Page { rows: Vec::new(), next_cursor: None, has_more: false, total }
+    }
+
+    fn cursor_page(next_cursor: Option<&str>, total: u64) -> Page<u64> {
+        Page { rows: Vec::new(), next_cursor: next_cursor.map(str::to_string), has_more: next_cursor.is_some(), total }
+    }
+
+    fn headers(uri: &str, page: Page<u64>, links: PageLinks) -> (Option<String>, Option<String>) {
+        let req: Request = RequestBuilder::new(uri, salvo::http::Method::GET).build();
+        let mut res = Response::new();
+        set_page_headers(&req, &mut res, &page, links);
+        let header = |name: &str| res.headers().get(name).map(|v| v.to_str().unwrap().to_string());
+        (header("link"), header(TOTAL_COUNT_HEADER))
+    }
+
+    #[test]
+    fn test_offset_middle_page_headers() {
+        let (link, total) = headers(
+            "http://localhost/rwa/invoice/search?status=Verified&page=3&page_size=10",
+            offset_page(95),
+            PageLinks::Offset { page: 3, page_size: 10 },
+        );
+        assert_eq!(total.as_deref(), Some("95"));
+        assert_eq!(
+            link.as_deref(),
+            Some(r#"</rwa/invoice/search?status=Verified&page=4&page_size=10>; rel="next", </rwa/invoice/search?status=Verified&page=2&page_size=10>; rel="prev""#)
+        );
+    }
+
+    #[test]
+    fn test_offset_edge_pages() {
+        let (link, _) = headers("http://localhost/rwa/purchase/history", offset_page(25), PageLinks::Offset { page: 1, page_size: 10 });
+        assert_eq!(link.as_deref(), Some(r#"</rwa/purchase/history?page=2&page_size=10>; rel="next""#));
+        let (link, _) = headers("http://localhost/rwa/purchase/history?page=3", offset_page(25), PageLinks::Offset { page: 3, page_size: 10 });
+        assert_eq!(link.as_deref(), Some(r#"</rwa/purchase/history?page=2&page_size=10>; rel="prev""#));
+        let (link, total) = headers("http://localhost/rwa/purchase/history", offset_page(0), PageLinks::Offset { page: 1, page_size: 10 });
+        assert_eq!((link, total.as_deref()), (None, Some("0")));
+    }
+
+    #[test]
+    fn test_cursor_middle_page_headers() {
+        let (link, total) = headers(
+            "http://localhost/rwa/invoice/page?limit=20&cursor=64b000000000000000000009",
+            cursor_page(Some("64b000000000000000000001"), 57),
+            PageLinks::Cursor,
+        );
+        // 游标分页同样返回总数
+        assert_eq!(total.as_deref(), Some("57"));
+        assert_eq!(
+            link.as_deref(),
+            Some(r#"</rwa/invoice/page?limit=20&cursor=64b000000000000000000001>; rel="next", </rwa/invoice/page?limit=20>; rel="first""#)
+        );
+
+        // 第一页没有 first，最后一页没有 next
+        let (link, _) = headers("http://localhost/rwa/invoice/page", cursor_page(Some("a b"), 2), PageLinks::Cursor);
+        assert_eq!(link.as_deref(), Some(r#"</rwa/invoice/page?cursor=a%20b>; rel="next""#));
+        let (link, total) = headers("http://localhost/rwa/invoice/page?cursor=abc", cursor_page(None, 7), PageLinks::Cursor);
+        assert_eq!(link.as_deref(), Some(r#"</rwa/invoice/page>; rel="first""#));
+        assert_eq!(total.as_deref(), Some("7"));
+    }
+}
@@ -0,0 +1,114 @@
+//! 固定窗口限流
+//!
+//! 计数保存在 Redis (`ratelimit:{key}`)，第一次计数时设置窗口过期时间，多个 api-server 实例共享同一计数。
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use redis::Client as RedisClient;
+use salvo::async_trait;
+use tokio::sync::Mutex;
+
+/// 原子地自增并在首次计数时设置过期时间，返回 {计数, 剩余秒数}
+const INCR_WINDOW_SCRIPT: &str = r#"
+local count = redis.call('INCR', KEYS[1])
+if count == 1 then
+    redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return {count, redis.call('TTL', KEYS[1])}
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateDecision {
+    Allowed,
+    /// 超出限制，需等待的秒数 (用于 Retry-After)
+    Limited { retry_after_secs: u64 },
+}
+
+#[async_trait]
+pub trait RateLimiter: Send + Sync {
+    /// 记录一次请求并判断是否超出 `limit`
+    async fn hit(&self, key: &str, limit: u32, window_secs: u64) -> Result<RateDecision, String>;
+}
+
+pub struct RedisRateLimiter {
+    client: RedisClient,
+}
+
+impl RedisRateLimiter {
+    pub fn new(client: RedisClient) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl RateLimiter for RedisRateLimiter {
+    async fn hit(&self, key: &str, limit: u32, window_secs: u64) -> Result<RateDecision, String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        let (count, ttl): (u64, i64) = redis::Script::new(INCR_WINDOW_SCRIPT)
+            .key(format!("ratelimit:{}", key))
+            .arg(window_secs)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(decide(count, limit, ttl, window_secs))
+    }
+}
+
+/// 进程内限流，用于测试或单实例部署
+#[derive(Default)]
+pub struct MemoryRateLimiter {
+    windows: Mutex<HashMap<String, (u64, Instant)>>,
+}
+
+#[async_trait]
+impl RateLimiter for MemoryRateLimiter {
+    async fn hit(&self, key: &str, limit: u32, window_secs: u64) -> Result<RateDecision, String> {
+        let now = Instant::now();
+        let window = Duration::from_secs(window_secs);
+        let mut windows = self.windows.lock().await;
+        let entry = windows.entry(key.to_string()).or_insert((0, now));
+        if now.duration_since(entry.1) >= window {
+            *entry = (0, now);
+        }
+        entry.0 += 1;
+        let remaining = window.saturating_sub(now.duration_since(entry.1)).as_secs() as i64;
+        Ok(decide(entry.0, limit, remaining, window_secs))
+    }
+}
+
+/// `ttl` 为窗口剩余秒数，异常值 (key 无过期时间等) 按整个窗口计算
+fn decide(count: u64, limit: u32, ttl: i64, window_secs: u64) -> RateDecision {
+    if count <= limit as u64 {
+        return RateDecision::Allowed;
+    }
+    let retry_after_secs = if ttl > 0 { ttl as u64 } else { window_secs };
+    RateDecision::Limited { retry_after_secs: retry_after_secs.max(1) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decide() {
+        assert_eq!(decide(5, 5, 30, 60), RateDecision::Allowed);
+        assert_eq!(decide(6, 5, 30, 60), RateDecision::Limited { retry_after_secs: 30 });
+        assert_eq!(decide(6, 5, -1, 60), RateDecision::Limited { retry_after_secs: 60 });
+        assert_eq!(decide(1, 0, 0, 60), RateDecision::Limited { retry_after_secs: 60 });
+    }
+
+    #[tokio::test]
+    async fn test_memory_limiter_over_threshold() {
+        let limiter = MemoryRateLimiter::default();
+        for _ in 0..3 {
+            assert_eq!(limiter.hit("ip:1.2.3.4", 3, 60).await.unwrap(), RateDecision::Allowed);
+        }
+        match limiter.hit("ip:1.2.3.4", 3, 60).await.unwrap() {
+            RateDecision::Limited { retry_after_secs } => assert!(retry_after_secs >= 1 && retry_after_secs <= 60),
+            RateDecision::Allowed => panic!("fourth request should be limited"),
+        }
+        // 其他 key 单独计数
+        assert_eq!(limiter.hit("ip:5.6.7.8", 3, 60).await.unwrap(), RateDecision::Allowed);
+    }
+}
@@ -0,0 +1,63 @@
+//! 请求 ID 与结构化日志
+//!
+//! 每个请求使用客户端传入的 `X-Request-Id` (格式合法时) 或新生成的 UUID，写入 depot 与响应头，
+//! 并在 `request` span 中执行后续处理；`tracing` 事件以 JSON 输出到标准输出，携带 span 中的 request_id。
+
+use tracing_subscriber::fmt::MakeWriter;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// depot 中保存请求 ID 的键
+pub const REQUEST_ID_KEY: &str = "request_id";
+
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// 客户端传入的 ID 只接受字母、数字与 `-_.`，否则生成新的，避免日志注入
+pub fn resolve_request_id(incoming: Option<&str>) -> String {
+    match incoming.map(str::trim) {
+        Some(id)
+            if !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LEN
+                && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')) =>
+        {
+            id.to_string()
+        }
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+/// JSON 格式的 tracing 订阅者，输出当前 span (含 request_id) 的字段
+pub fn json_subscriber<W>(writer: W) -> impl tracing::Subscriber + Send + Sync
+where
+    W: for<'a> MakeWriter<'a> + Send + Sync + 'static,
+{
+    tracing_subscriber::fmt()
+        .json()
+        .with_current_span(true)
+        .with_span_list(false)
+        .with_max_level(tracing::Level::INFO)
+        .with_writer(writer)
+        .finish()
+}
+
+/// 安装全局 JSON 订阅者，重复调用时忽略
+pub fn init_json_logging() {
+    if tracing::subscriber::set_global_default(json_subscriber(std::io::stdout)).is_err() {
+        tracing::warn!("tracing subscriber already installed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_request_id() {
+        assert_eq!(resolve_request_id(Some("req-123_a.b")), "req-123_a.b");
+        // 非法或过长的 ID 重新生成
+        for bad in [Some(""), Some("a b"), Some("x\ny"), None] {
+            let id = resolve_request_id(bad);
+            assert!(uuid::Uuid::parse_str(&id).is_ok(), "{:?} -> {}", bad, id);
+        }
+        assert_ne!(resolve_request_id(Some(&"a".repeat(MAX_REQUEST_ID_LEN + 1))).len(), MAX_REQUEST_ID_LEN + 1);
+    }
+}
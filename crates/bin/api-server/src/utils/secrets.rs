@@ -0,0 +1,231 @@
+//! 启动时校验 JWT / 会话 / webhook 签名密钥强度
+//!
+//! 生产环境 (`-e prod`) 下密钥过短、估算熵低于 `security.min_secret_entropy_bits`、等于已知示例值
+//! 或多个用途共用同一密钥时拒绝启动，开发环境只打印警告。
+//! 会话密钥不足 64 字节时无法创建 CookieStore，webhook 签名密钥为空时无法为回调签名，任何环境都拒绝启动。
+
+use configs::CFG;
+
+/// JWT 密钥最小长度 (HS256 建议不少于 256 bit)
+pub const MIN_JWT_SECRET_LEN: usize = 32;
+/// Cookie 会话密钥最小长度 (salvo CookieStore 要求至少 64 字节)
+pub const MIN_SESSION_SECRET_LEN: usize = 64;
+/// webhook 签名服务端密钥最小长度
+pub const MIN_WEBHOOK_SIGNING_KEY_LEN: usize = 32;
+
+/// 仓库中出现过的示例值及常见默认值
+const KNOWN_WEAK_SECRETS: &[&str] = &[
+    "pharos_rwa",
+    "salvo-adminsalvo-adminalvo-adminsalvo-admin2023salvo-admin2023salvo-admin2023",
+    "secret",
+    "changeme",
+    "change_me",
+    "your_secret",
+    "your-secret-key",
+    "jwt_secret",
+    "session_secret",
+    "default",
+    "test",
+    "pharos-dev-webhook-signing-key-change-me-in-prod",
+];
+
+/// 按字符频率估算的熵 (bit)：每字节的 Shannon 熵 × 长度。
+/// 只是上限估计，`"abcabcabc..."` 这类有规律的密钥会被高估，但能挡住重复字符和过小字符集
+pub fn estimate_entropy_bits(secret: &str) -> f64 {
+    let bytes = secret.as_bytes();
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0usize; 256];
+    for &b in bytes {
+        counts[b as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    let per_byte: f64 = counts
+        .iter()
+        .filter(|&&c| c > 0)
+        .map(|&c| {
+            let p = c as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    per_byte * len
+}
+
+/// 检查单个密钥，返回发现的问题
+pub fn check_secret(name: &str, secret: &str, min_len: usize, min_entropy_bits: u32) -> Vec<String> {
+    let mut problems = Vec::new();
+    if KNOWN_WEAK_SECRETS.iter().any(|weak| weak.eq_ignore_ascii_case(secret.trim())) {
+        problems.push(format!("{} is a known default/sample value", name));
+    }
+    if secret.len() < min_len {
+        problems.push(format!("{} must be at least {} bytes (got {})", name, min_len, secret.len()));
+    }
+    let entropy = estimate_entropy_bits(secret);
+    if !secret.is_empty() && entropy < min_entropy_bits as f64 {
+        problems.push(format!("{} has an estimated entropy of {:.0} bits, below the required {}", name, entropy, min_entropy_bits));
+    }
+    problems
+}
+
+/// 不同用途的密钥不能相同，否则泄露其中一个即可伪造另一种签名
+pub fn check_distinct(secrets: &[(&str, &str)]) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (i, (name, secret)) in secrets.iter().enumerate() {
+        if secret.is_empty() {
+            continue;
+        }
+        for (other, other_secret) in &secrets[i + 1..] {
+            if secret == other_secret {
+                problems.push(format!("{} and {} must not share the same value", name, other));
+            }
+        }
+    }
+    problems
+}
+
+/// 校验配置中的 JWT 签名密钥、会话密钥与 webhook 签名密钥
+pub fn validate_config() -> Result<(), String> {
+    let production = CFG.is_production();
+    let min_entropy_bits = CFG.security.min_secret_entropy_bits;
+    let jwt_secret = jwt_signing_secret(&CFG.jwt);
+    validate_secrets(jwt_secret.clone(), &CFG.session.secret, min_entropy_bits, production)?;
+    validate_webhook_signing_key(&CFG.webhook.signing_key, min_entropy_bits, production)?;
+
+    let mut secrets = vec![
+        ("session.secret (SESSION_SECRET)", CFG.session.secret.as_str()),
+        ("webhook.signing_key (WEBHOOK_SIGNING_KEY)", CFG.webhook.signing_key.as_str()),
+    ];
+    if let Some((name, secret)) = &jwt_secret {
+        secrets.push((name.as_str(), *secret));
+    }
+    report(check_distinct(&secrets), production)
+}
+
+/// 生产环境下有问题即返回错误，其他环境只打印警告
+fn report(problems: Vec<String>, production: bool) -> Result<(), String> {
+    if problems.is_empty() {
+        return Ok(());
+    }
+    if production {
+        return Err(problems.join("; "));
+    }
+    for problem in &problems {
+        tracing::warn!("Weak secret (allowed outside production): {}", problem);
+    }
+    Ok(())
+}
+
+/// 签发令牌使用的 HS256 密钥 (名称, 值)：未配置 `current_kid` 时为 `jwt.secret`，
+/// 否则为密钥集中的当前密钥；RS256 密钥集没有共享密钥，返回 `None`
+fn jwt_signing_secret(cfg: &configs::cfgs::Jwt) -> Option<(String, &str)> {
+    if cfg.current_kid.is_empty() {
+        return Some(("jwt.secret (JWT_SECRET)".to_string(), cfg.secret.as_str()));
+    }
+    if !cfg.algorithm.trim().eq_ignore_ascii_case("HS256") {
+        return None;
+    }
+    cfg.keys
+        .iter()
+        .find(|key| key.kid == cfg.current_kid)
+        .map(|key| (format!("jwt.keys[{}].secret", key.kid), key.secret.as_str()))
+}
+
+/// 会话密钥缺失或过短时总是返回错误；其他问题仅在生产环境返回错误
+pub fn validate_secrets(jwt_secret: Option<(String, &str)>, session_secret: &str, min_entropy_bits: u32, production: bool) -> Result<(), String> {
+    if session_secret.len() < MIN_SESSION_SECRET_LEN {
+        return Err(format!(
+            "session.secret (SESSION_SECRET) must be at least {} bytes (got {})",
+            MIN_SESSION_SECRET_LEN,
+            session_secret.len()
+        ));
+    }
+    let mut problems = match jwt_secret {
+        Some((name, secret)) => check_secret(&name, secret, MIN_JWT_SECRET_LEN, min_entropy_bits),
+        None => Vec::new(),
+    };
+    problems.extend(check_secret("session.secret (SESSION_SECRET)", session_secret, MIN_SESSION_SECRET_LEN, min_entropy_bits));
+    report(problems, production)
+}
+
+/// 未配置时总是返回错误；过短或为示例值仅在生产环境返回错误
+pub fn validate_webhook_signing_key(key: &str, min_entropy_bits: u32, production: bool) -> Result<(), String> {
+    const NAME: &str = "webhook.signing_key (WEBHOOK_SIGNING_KEY)";
+    if key.is_empty() {
+        return Err(format!("{} must be set", NAME));
+    }
+    report(check_secret(NAME, key, MIN_WEBHOOK_SIGNING_KEY_LEN, min_entropy_bits), production)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_ENTROPY: u32 = 96;
+    const JWT_SECRET: &str = "3f9c2b7e8a1d4c6f0b5e9a2d7c4f1e8b";
+
+    #[test]
+    fn test_rejects_sample_and_short_secrets() {
+        assert_eq!(check_secret("jwt", "pharos_rwa", MIN_JWT_SECRET_LEN, MIN_ENTROPY).len(), 3);
+        // 示例会话密钥长度和熵都足够，但仍是已知值
+        let sample = KNOWN_WEAK_SECRETS[1];
+        assert_eq!(check_secret("session", sample, MIN_SESSION_SECRET_LEN, MIN_ENTROPY).len(), 1);
+        assert_eq!(check_secret("jwt", "", MIN_JWT_SECRET_LEN, MIN_ENTROPY).len(), 1);
+        assert!(check_secret("jwt", JWT_SECRET, MIN_JWT_SECRET_LEN, MIN_ENTROPY).is_empty());
+    }
+
+    #[test]
+    fn test_entropy_estimate() {
+        assert_eq!(estimate_entropy_bits(""), 0.0);
+        assert_eq!(estimate_entropy_bits(&"k".repeat(64)), 0.0);
+        // 16 个字符各出现两次：每字节 4 bit
+        assert_eq!(estimate_entropy_bits("0123456789abcdef0123456789abcdef"), 128.0);
+        assert!(estimate_entropy_bits(JWT_SECRET) > MIN_ENTROPY as f64);
+
+        // 长度足够但字符单一的密钥只因熵不足被拒绝
+        let problems = check_secret("jwt", &"ab".repeat(32), MIN_JWT_SECRET_LEN, MIN_ENTROPY);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("entropy"), "{}", problems[0]);
+        // 阈值为 0 时不检查
+        assert!(check_secret("jwt", &"ab".repeat(32), MIN_JWT_SECRET_LEN, 0).is_empty());
+    }
+
+    #[test]
+    fn test_short_session_secret_aborts_in_every_environment() {
+        let jwt = || Some(("jwt.secret".to_string(), JWT_SECRET));
+        for production in [false, true] {
+            let err = validate_secrets(jwt(), "too-short", MIN_ENTROPY, production).unwrap_err();
+            assert!(err.contains("session.secret"), "{}", err);
+            assert!(validate_secrets(jwt(), "", MIN_ENTROPY, production).is_err());
+        }
+        // 示例值和低熵密钥长度足够，仅生产环境拒绝
+        for weak in [KNOWN_WEAK_SECRETS[1].to_string(), "k".repeat(MIN_SESSION_SECRET_LEN)] {
+            assert!(validate_secrets(jwt(), &weak, MIN_ENTROPY, false).is_ok());
+            assert!(validate_secrets(jwt(), &weak, MIN_ENTROPY, true).is_err());
+        }
+        assert!(validate_secrets(jwt(), &JWT_SECRET.repeat(2), MIN_ENTROPY, true).is_ok());
+    }
+
+    #[test]
+    fn test_secrets_must_be_distinct() {
+        let session = JWT_SECRET.repeat(2);
+        assert!(check_distinct(&[("jwt", JWT_SECRET), ("session", &session), ("webhook", "")]).is_empty());
+
+        let problems = check_distinct(&[("jwt", JWT_SECRET), ("session", &session), ("webhook", JWT_SECRET)]);
+        assert_eq!(problems, vec!["jwt and webhook must not share the same value".to_string()]);
+        assert!(report(problems.clone(), false).is_ok());
+        assert!(report(problems, true).is_err());
+    }
+
+    #[test]
+    fn test_webhook_signing_key() {
+        for production in [false, true] {
+            assert!(validate_webhook_signing_key("", MIN_ENTROPY, production).is_err());
+        }
+        let sample = "pharos-dev-webhook-signing-key-change-me-in-prod";
+        assert!(validate_webhook_signing_key(sample, MIN_ENTROPY, false).is_ok());
+        assert!(validate_webhook_signing_key(sample, MIN_ENTROPY, true).is_err());
+        assert!(validate_webhook_signing_key("short", MIN_ENTROPY, true).is_err());
+        assert!(validate_webhook_signing_key(JWT_SECRET, MIN_ENTROPY, true).is_ok());
+    }
+}
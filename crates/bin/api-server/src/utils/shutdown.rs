@@ -0,0 +1,259 @@
+//! 优雅停机
+//!
+//! 收到 SIGTERM / SIGINT 后先停止接受新连接 (salvo `stop_graceful`)，在宽限期内等待进行中的请求结束，
+//! 之后通知后台任务 (回执轮询、转账索引等) 退出并等待它们收尾；超时仍未结束的请求和任务会记录到日志。
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tracing::{info, warn};
+use salvo::async_trait;
+use salvo::prelude::*;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+/// 进行中的请求登记，作为最外层中间件挂在 Service 上
+#[derive(Clone, Default)]
+pub struct InFlightRequests {
+    next_id: Arc<AtomicU64>,
+    active: Arc<Mutex<HashMap<u64, String>>>,
+}
+
+/// 请求处理结束 (包括 handler panic 展开) 时注销
+struct InFlightGuard {
+    id: u64,
+    active: Arc<Mutex<HashMap<u64, String>>>,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.active.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.id);
+    }
+}
+
+impl InFlightRequests {
+    fn enter(&self, description: String) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.active.lock().unwrap_or_else(|e| e.into_inner()).insert(id, description);
+        InFlightGuard { id, active: self.active.clone() }
+    }
+
+    pub fn count(&self) -> usize {
+        self.active.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 进行中请求的描述 ("METHOD /path")
+    pub fn snapshot(&self) -> Vec<String> {
+        let mut requests: Vec<String> = self.active.lock().unwrap_or_else(|e| e.into_inner()).values().cloned().collect();
+        requests.sort();
+        requests
+    }
+
+    /// 等待所有请求结束，超过 `deadline` 时返回仍在处理的请求
+    pub async fn wait_idle(&self, deadline: Instant) -> Result<(), Vec<String>> {
+        loop {
+            if self.count() == 0 {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(self.snapshot());
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+}
+
+#[async_trait]
+impl Handler for InFlightRequests {
+    async fn handle(&self, req: &mut Request, depot: &mut Depot, res: &mut Response, ctrl: &mut FlowCtrl) {
+        let _guard = self.enter(format!("{} {}", req.method(), req.uri().path()));
+        ctrl.call_next(req, depot, res).await;
+    }
+}
+
+/// 停机协调：停机信号、进行中的请求与需要收尾的后台任务
+#[derive(Clone)]
+pub struct Shutdown {
+    signal: Arc<watch::Sender<bool>>,
+    in_flight: InFlightRequests,
+    tasks: Arc<Mutex<Vec<(&'static str, JoinHandle<()>)>>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            signal: Arc::new(watch::channel(false).0),
+            in_flight: InFlightRequests::default(),
+            tasks: Arc::default(),
+        }
+    }
+
+    /// 后台任务监听的停机信号，变为 true 时应尽快退出
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.signal.subscribe()
+    }
+
+    pub fn in_flight(&self) -> InFlightRequests {
+        self.in_flight.clone()
+    }
+
+    /// 登记停机时需要等待结束的后台任务
+    pub fn register_task(&self, name: &'static str, handle: JoinHandle<()>) {
+        self.tasks.lock().unwrap_or_else(|e| e.into_inner()).push((name, handle));
+    }
+
+    /// 在 `grace` 内依次等待进行中的请求结束、通知并等待后台任务退出。
+    /// 请求先结束，回执轮询收尾时才能看到这些请求刚广播的交易。返回超时未结束的请求与任务
+    pub async fn drain(&self, grace: Duration) -> Vec<String> {
+        let deadline = Instant::now() + grace;
+        let mut unfinished = Vec::new();
+
+        if let Err(requests) = self.in_flight.wait_idle(deadline).await {
+            warn!("Shutdown grace period elapsed with {} request(s) still running: {:?}", requests.len(), requests);
+            unfinished.extend(requests);
+        }
+
+        self.signal.send_replace(true);
+        let tasks = std::mem::take(&mut *self.tasks.lock().unwrap_or_else(|e| e.into_inner()));
+        for (name, mut handle) in tasks {
+            if tokio::time::timeout_at(deadline, &mut handle).await.is_err() {
+                warn!("Background task '{}' did not stop within the shutdown grace period, aborting", name);
+                handle.abort();
+                unfinished.push(name.to_string());
+            }
+        }
+
+        if unfinished.is_empty() {
+            info!("Graceful shutdown completed");
+        }
+        unfinished
+    }
+}
+
+/// 等待 SIGTERM (部署 / 容器停止) 或 SIGINT (Ctrl-C)
+pub async fn wait_for_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for SIGINT: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT"),
+        _ = terminate => info!("Received SIGTERM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+    use tokio::sync::Notify;
+
+    /// 等待放行后才返回的接口
+    struct SlowHandler(Arc<Notify>);
+
+    #[async_trait]
+    impl Handler for SlowHandler {
+        async fn handle(&self, _req: &mut Request, _depot: &mut Depot, res: &mut Response, _ctrl: &mut FlowCtrl) {
+            self.0.notified().await;
+            res.render("done");
+        }
+    }
+
+    async fn send_get(addr: &str, path: &str) -> std::io::Result<TcpStream> {
+        let mut stream = TcpStream::connect(addr).await?;
+        stream.write_all(format!("GET {} HTTP/1.1\r\nhost: localhost\r\nconnection: close\r\n\r\n", path).as_bytes()).await?;
+        Ok(stream)
+    }
+
+    async fn read_response(mut stream: TcpStream) -> String {
+        let mut response = Vec::new();
+        let _ = tokio::time::timeout(Duration::from_secs(5), stream.read_to_end(&mut response)).await;
+        String::from_utf8_lossy(&response).into_owned()
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_request_completes_during_shutdown() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let addr = format!("127.0.0.1:{}", port);
+        let release = Arc::new(Notify::new());
+        let shutdown = Shutdown::new();
+
+        let router = Router::new().push(Router::with_path("slow").get(SlowHandler(release.clone())));
+        let server = Server::new(TcpListener::new(addr.clone()).bind().await);
+        let handle = server.handle();
+        let serving = tokio::spawn(server.serve(Service::new(router).hoop(shutdown.in_flight())));
+
+        let in_flight = send_get(&addr, "/slow").await.unwrap();
+        while shutdown.in_flight().count() == 0 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // 停机期间的后台任务应收到信号后退出
+        let mut stop = shutdown.subscribe();
+        shutdown.register_task("poller", tokio::spawn(async move {
+            let _ = stop.wait_for(|stopped| *stopped).await;
+        }));
+
+        handle.stop_graceful(Some(Duration::from_secs(5)));
+        let drain = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { shutdown.drain(Duration::from_secs(5)).await }
+        });
+
+        // 停止接受后新连接不会被处理：连接被拒绝，或排队的连接在服务退出时被关闭
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let rejected = tokio::spawn(async move {
+            match send_get(&addr, "/slow").await {
+                Ok(stream) => read_response(stream).await,
+                Err(_) => String::new(),
+            }
+        });
+
+        release.notify_one();
+        let response = read_response(in_flight).await;
+        assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+        assert!(response.ends_with("done"), "{}", response);
+
+        assert!(drain.await.unwrap().is_empty());
+        serving.await.unwrap();
+        assert!(!rejected.await.unwrap().contains("200"));
+    }
+
+    #[tokio::test]
+    async fn test_drain_reports_unfinished_requests_and_tasks() {
+        let shutdown = Shutdown::new();
+        let _request = shutdown.in_flight().enter("POST /invoice/purchase".to_string());
+        shutdown.register_task("stuck", tokio::spawn(std::future::pending::<()>()));
+
+        let unfinished = shutdown.drain(Duration::from_millis(100)).await;
+        assert_eq!(unfinished, vec!["POST /invoice/purchase".to_string(), "stuck".to_string()]);
+    }
+}
@@ -0,0 +1,309 @@
+//! 已注销 JWT 的黑名单
+//!
+//! 以 `jti` 为键写入 Redis (`auth:denylist:{jti}`)，过期时间覆盖到令牌过期后的刷新宽限期结束，之后 Redis 自动清理。
+//! 写入时同时记录到进程内缓存。Redis 不可用时按失败处理 (fail closed)：本实例注销过的令牌仍被拒绝，
+//! 其余令牌无法确认是否已在其他实例注销，校验返回错误。
+//! 角色变更等需要强制重新登录时按用户写入 (`auth:denylist:user:{user_id}`)，该用户此前签发的令牌全部失效。
+//! 进程内缓存的过期条目由 [`spawn_cleanup`] 定期清理 (会话使用 Cookie 存储，服务端没有需要清理的会话)。
+
+use std::time::Duration;
+
+use std::sync::Arc;
+
+use tracing::{info, warn};
+use moka::future::Cache;
+use redis::{AsyncCommands, Client as RedisClient};
+use salvo::async_trait;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use crate::utils::metrics::AUTH_DENYLIST_PRUNED;
+
+#[async_trait]
+pub trait TokenDenylist: Send + Sync {
+    /// 注销 `jti`，`ttl_secs` 为令牌剩余有效期
+    async fn revoke(&self, jti: &str, ttl_secs: u64) -> Result<(), String>;
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String>;
+    /// 使用户在 `cutoff` (秒级时间戳) 之前签发的令牌失效，`ttl_secs` 不短于令牌最长有效期
+    async fn revoke_user(&self, user_id: &str, cutoff: i64, ttl_secs: u64) -> Result<(), String>;
+    /// 用户令牌的失效截止时间，未强制重新登录时为 None
+    async fn user_cutoff(&self, user_id: &str) -> Result<Option<i64>, String>;
+}
+
+pub struct RedisTokenDenylist {
+    client: RedisClient,
+}
+
+impl RedisTokenDenylist {
+    pub fn new(client: RedisClient) -> Self {
+        Self { client }
+    }
+
+    fn key(jti: &str) -> String {
+        format!("auth:denylist:{}", jti)
+    }
+
+    fn user_key(user_id: &str) -> String {
+        format!("auth:denylist:user:{}", user_id)
+    }
+}
+
+#[async_trait]
+impl TokenDenylist for RedisTokenDenylist {
+    async fn revoke(&self, jti: &str, ttl_secs: u64) -> Result<(), String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.set_ex::<_, _, ()>(Self::key(jti), 1, ttl_secs.max(1)).await.map_err(|e| e.to_string())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.exists(Self::key(jti)).await.map_err(|e| e.to_string())
+    }
+
+    async fn revoke_user(&self, user_id: &str, cutoff: i64, ttl_secs: u64) -> Result<(), String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.set_ex::<_, _, ()>(Self::user_key(user_id), cutoff, ttl_secs.max(1)).await.map_err(|e| e.to_string())
+    }
+
+    async fn user_cutoff(&self, user_id: &str) -> Result<Option<i64>, String> {
+        let mut conn = self.client.get_multiplexed_async_connection().await.map_err(|e| e.to_string())?;
+        conn.get(Self::user_key(user_id)).await.map_err(|e| e.to_string())
+    }
+}
+
+/// 进程内黑名单，条目统一保留 `max_ttl` (不短于令牌最长有效期)
+pub struct MemoryTokenDenylist {
+    cache: Cache<String, ()>,
+    users: Cache<String, i64>,
+}
+
+impl MemoryTokenDenylist {
+    pub fn new(max_ttl: Duration) -> Self {
+        Self {
+            cache: Cache::builder().time_to_live(max_ttl).max_capacity(100_000).build(),
+            users: Cache::builder().time_to_live(max_ttl).max_capacity(100_000).build(),
+        }
+    }
+
+    /// 立即移除已过期的条目，返回移除的数量。未过期的条目不受影响
+    pub async fn prune(&self) -> u64 {
+        let before = self.cache.entry_count() + self.users.entry_count();
+        self.cache.run_pending_tasks().await;
+        self.users.run_pending_tasks().await;
+        before.saturating_sub(self.cache.entry_count() + self.users.entry_count())
+    }
+}
+
+#[async_trait]
+impl TokenDenylist for MemoryTokenDenylist {
+    async fn revoke(&self, jti: &str, _ttl_secs: u64) -> Result<(), String> {
+        self.cache.insert(jti.to_string(), ()).await;
+        Ok(())
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        Ok(self.cache.contains_key(jti))
+    }
+
+    async fn revoke_user(&self, user_id: &str, cutoff: i64, _ttl_secs: u64) -> Result<(), String> {
+        self.users.insert(user_id.to_string(), cutoff).await;
+        Ok(())
+    }
+
+    async fn user_cutoff(&self, user_id: &str) -> Result<Option<i64>, String> {
+        Ok(self.users.get(user_id).await)
+    }
+}
+
+/// 同时写入 `primary` 与进程内缓存。`primary` 出错时写入返回错误；读取时进程内缓存命中仍判定为已注销，否则返回错误
+pub struct FallbackTokenDenylist<P> {
+    primary: P,
+    fallback: MemoryTokenDenylist,
+}
+
+impl<P: TokenDenylist> FallbackTokenDenylist<P> {
+    pub fn new(primary: P, max_ttl: Duration) -> Self {
+        Self { primary, fallback: MemoryTokenDenylist::new(max_ttl) }
+    }
+
+    /// 清理进程内副本中已过期的条目，`primary` 自行按 TTL 过期
+    pub async fn prune(&self) -> u64 {
+        self.fallback.prune().await
+    }
+}
+
+#[async_trait]
+impl<P: TokenDenylist> TokenDenylist for FallbackTokenDenylist<P> {
+    async fn revoke(&self, jti: &str, ttl_secs: u64) -> Result<(), String> {
+        self.fallback.revoke(jti, ttl_secs).await?;
+        self.primary.revoke(jti, ttl_secs).await.map_err(|e| {
+            warn!("Token denylist unavailable, token {} revoked on this instance only: {}", jti, e);
+            e
+        })
+    }
+
+    async fn is_revoked(&self, jti: &str) -> Result<bool, String> {
+        match self.primary.is_revoked(jti).await {
+            Ok(revoked) => Ok(revoked || self.fallback.is_revoked(jti).await?),
+            Err(_) if self.fallback.is_revoked(jti).await? => Ok(true),
+            Err(e) => {
+                warn!("Token denylist unavailable when checking {}: {}", jti, e);
+                Err(e)
+            }
+        }
+    }
+
+    async fn revoke_user(&self, user_id: &str, cutoff: i64, ttl_secs: u64) -> Result<(), String> {
+        self.fallback.revoke_user(user_id, cutoff, ttl_secs).await?;
+        self.primary.revoke_user(user_id, cutoff, ttl_secs).await.map_err(|e| {
+            warn!("Token denylist unavailable, tokens of user {} revoked on this instance only: {}", user_id, e);
+            e
+        })
+    }
+
+    async fn user_cutoff(&self, user_id: &str) -> Result<Option<i64>, String> {
+        let local = self.fallback.user_cutoff(user_id).await?;
+        match self.primary.user_cutoff(user_id).await {
+            Ok(cutoff) => Ok(cutoff.max(local)),
+            Err(_) if local.is_some() => Ok(local),
+            Err(e) => {
+                warn!("Token denylist unavailable when checking user {}: {}", user_id, e);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// 注入 depot 的黑名单类型
+pub type AuthTokenDenylist = FallbackTokenDenylist<RedisTokenDenylist>;
+
+pub fn auth_token_denylist(client: RedisClient, max_ttl: Duration) -> AuthTokenDenylist {
+    FallbackTokenDenylist::new(RedisTokenDenylist::new(client), max_ttl)
+}
+
+/// 启动进程内黑名单的定期清理，清理数量计入 `auth_denylist_pruned_total`。`shutdown` 变为 true 后退出
+pub fn spawn_cleanup(denylist: Arc<AuthTokenDenylist>, interval: Duration, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval.max(Duration::from_secs(1)));
+        while !*shutdown.borrow() {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.changed() => break,
+            }
+            let pruned = denylist.prune().await;
+            AUTH_DENYLIST_PRUNED.inc_by(pruned);
+            if pruned > 0 {
+                info!("Pruned {} expired token denylist entries", pruned);
+            }
+        }
+        info!("Token denylist cleanup stopped");
+    })
+}
+
+/// 令牌剩余有效期 (秒)，已过期返回 None
+pub fn remaining_lifetime(exp: usize, now: i64) -> Option<u64> {
+    let remaining = exp as i64 - now;
+    (remaining > 0).then_some(remaining as u64)
+}
+
+/// 注销令牌直到刷新宽限期结束 (过期后 `grace_secs` 内仍可换发新令牌)。
+/// 令牌没有 `jti` (旧版本签发) 或已超过宽限期时返回 false
+pub async fn revoke_claims<D: TokenDenylist + ?Sized>(denylist: &D, jti: &str, exp: usize, grace_secs: i64, now: i64) -> Result<bool, String> {
+    if jti.is_empty() {
+        return Ok(false);
+    }
+    match remaining_lifetime(exp + grace_secs.max(0) as usize, now) {
+        Some(ttl) => denylist.revoke(jti, ttl).await.map(|_| true),
+        None => Ok(false),
+    }
+}
+
+/// 令牌签发时间早于用户的失效截止时间时视为已注销。没有 `user_id` 的令牌不受影响
+pub async fn issued_before_user_cutoff<D: TokenDenylist + ?Sized>(denylist: &D, user_id: &str, issued_at: i64) -> Result<bool, String> {
+    if user_id.is_empty() {
+        return Ok(false);
+    }
+    Ok(denylist.user_cutoff(user_id).await?.is_some_and(|cutoff| issued_at < cutoff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct UnavailableDenylist;
+
+    #[async_trait]
+    impl TokenDenylist for UnavailableDenylist {
+        async fn revoke(&self, _: &str, _: u64) -> Result<(), String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn is_revoked(&self, _: &str) -> Result<bool, String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn revoke_user(&self, _: &str, _: i64, _: u64) -> Result<(), String> {
+            Err("connection refused".to_string())
+        }
+
+        async fn user_cutoff(&self, _: &str) -> Result<Option<i64>, String> {
+            Err("connection refused".to_string())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_primary_outage_fails_closed() {
+        let denylist = FallbackTokenDenylist::new(UnavailableDenylist, Duration::from_secs(60));
+        // 无法确认是否已在其他实例注销
+        assert!(denylist.is_revoked("jti-1").await.is_err());
+        assert!(denylist.user_cutoff("user-1").await.is_err());
+        // 写入失败如实返回，但本实例已记录
+        assert!(denylist.revoke("jti-1", 30).await.is_err());
+        assert!(denylist.is_revoked("jti-1").await.unwrap());
+        assert!(denylist.revoke_user("user-1", 1_000, 60).await.is_err());
+        assert!(issued_before_user_cutoff(&denylist, "user-1", 999).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revocation_covers_refresh_grace() {
+        let denylist = MemoryTokenDenylist::new(Duration::from_secs(60));
+        // 已过期但仍在宽限期内的令牌可以换发，注销必须生效
+        assert!(revoke_claims(&denylist, "jti-1", 1_000, 600, 1_200).await.unwrap());
+        assert!(denylist.is_revoked("jti-1").await.unwrap());
+        assert!(!revoke_claims(&denylist, "jti-2", 1_000, 600, 1_600).await.unwrap());
+        assert!(!revoke_claims(&denylist, "", 1_000, 600, 1_200).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_user_cutoff_revokes_earlier_tokens_only() {
+        let denylist = FallbackTokenDenylist::new(MemoryTokenDenylist::new(Duration::from_secs(60)), Duration::from_secs(60));
+        assert!(!issued_before_user_cutoff(&denylist, "user-1", 1_000).await.unwrap());
+        denylist.revoke_user("user-1", 1_000, 60).await.unwrap();
+        assert!(issued_before_user_cutoff(&denylist, "user-1", 999).await.unwrap());
+        // 截止时间之后重新登录签发的令牌有效
+        assert!(!issued_before_user_cutoff(&denylist, "user-1", 1_000).await.unwrap());
+        assert!(!issued_before_user_cutoff(&denylist, "user-2", 999).await.unwrap());
+        assert!(!issued_before_user_cutoff(&denylist, "", 999).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_prune_removes_only_expired_entries() {
+        let denylist = MemoryTokenDenylist::new(Duration::from_millis(200));
+        denylist.revoke("expired", 1).await.unwrap();
+        denylist.revoke_user("user-1", 1_000, 1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        denylist.revoke("valid", 1).await.unwrap();
+
+        assert_eq!(denylist.prune().await, 2);
+        assert!(denylist.is_revoked("valid").await.unwrap());
+        assert!(!denylist.is_revoked("expired").await.unwrap());
+        assert_eq!(denylist.prune().await, 0);
+    }
+
+    #[test]
+    fn test_remaining_lifetime() {
+        assert_eq!(remaining_lifetime(1_000, 400), Some(600));
+        assert_eq!(remaining_lifetime(1_000, 1_000), None);
+        assert_eq!(remaining_lifetime(1_000, 2_000), None);
+    }
+}
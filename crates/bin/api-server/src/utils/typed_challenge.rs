@@ -0,0 +1,130 @@
+//! EIP-712 登录挑战
+//!
+//! 钱包按结构化字段 (域 + `LoginChallenge { address, nonce, issuedAt, expiresAt }`) 展示待签名内容，替代不透明的字符串 nonce。
+//! 挑战以 [`TYPED_CHALLENGE_PREFIX`] 加类型化数据 JSON 的形式保存在 nonce 存储中，登录时据此选择校验方式，
+//! 原有字符串挑战不受影响。
+
+use ethers::types::transaction::eip712::{Eip712, TypedData};
+use ethers::types::{Address, H256};
+use serde_json::json;
+
+/// nonce 存储中类型化挑战的前缀
+pub const TYPED_CHALLENGE_PREFIX: &str = "eip712:";
+/// 域版本
+pub const DOMAIN_VERSION: &str = "1";
+/// 挑战结构体名称
+pub const PRIMARY_TYPE: &str = "LoginChallenge";
+
+/// 生成类型化登录挑战，`chain_id` 为空时域中不包含 chainId
+pub fn build_login_challenge(
+    domain_name: &str,
+    chain_id: Option<u64>,
+    address: &str,
+    nonce: &str,
+    issued_at: u64,
+    expires_at: u64,
+) -> Result<TypedData, String> {
+    let mut domain = json!({ "name": domain_name, "version": DOMAIN_VERSION });
+    let mut domain_fields = vec![json!({ "name": "name", "type": "string" }), json!({ "name": "version", "type": "string" })];
+    if let Some(chain_id) = chain_id {
+        domain["chainId"] = json!(chain_id);
+        domain_fields.push(json!({ "name": "chainId", "type": "uint256" }));
+    }
+    serde_json::from_value(json!({
+        "types": {
+            "EIP712Domain": domain_fields,
+            PRIMARY_TYPE: [
+                { "name": "address", "type": "address" },
+                { "name": "nonce", "type": "string" },
+                { "name": "issuedAt", "type": "uint256" },
+                { "name": "expiresAt", "type": "uint256" },
+            ],
+        },
+        "primaryType": PRIMARY_TYPE,
+        "domain": domain,
+        "message": { "address": address.to_lowercase(), "nonce": nonce, "issuedAt": issued_at, "expiresAt": expires_at },
+    }))
+    .map_err(|e| format!("Failed to build typed login challenge: {}", e))
+}
+
+/// 序列化后存入 nonce 存储
+pub fn encode_stored(typed: &TypedData) -> Result<String, String> {
+    serde_json::to_string(typed)
+        .map(|json| format!("{}{}", TYPED_CHALLENGE_PREFIX, json))
+        .map_err(|e| e.to_string())
+}
+
+/// 解析 nonce 存储中的值，字符串挑战返回 None
+pub fn decode_stored(stored: &str) -> Option<Result<TypedData, String>> {
+    stored
+        .strip_prefix(TYPED_CHALLENGE_PREFIX)
+        .map(|json| serde_json::from_str(json).map_err(|e| format!("Malformed typed login challenge: {}", e)))
+}
+
+/// 钱包签名的 EIP-712 摘要
+pub fn challenge_digest(typed: &TypedData) -> Result<H256, String> {
+    typed.encode_eip712().map(H256::from).map_err(|e| e.to_string())
+}
+
+/// 挑战签发给的钱包地址
+pub fn challenge_address(typed: &TypedData) -> Option<Address> {
+    typed.message.get("address").and_then(|a| a.as_str()).and_then(|a| a.parse().ok())
+}
+
+/// 挑战中签入的过期时间 (Unix 秒)，未包含过期时间的旧格式挑战返回 None
+pub fn challenge_expires_at(typed: &TypedData) -> Option<i64> {
+    typed.message.get("expiresAt").and_then(|t| t.as_i64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::Signature;
+
+    fn wallet() -> LocalWallet {
+        "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_recover_signer_from_typed_signature() {
+        let wallet = wallet();
+        let address = format!("0x{:x}", wallet.address());
+        let typed = build_login_challenge("Pharos-RWA", Some(688688), &address, "abc123", 1_700_000_000, 1_700_000_300).unwrap();
+
+        let signature: Signature = wallet.sign_typed_data(&typed).await.unwrap();
+        let digest = challenge_digest(&typed).unwrap();
+        assert_eq!(signature.recover(digest).unwrap(), wallet.address());
+        assert_eq!(challenge_address(&typed), Some(wallet.address()));
+        assert_eq!(challenge_expires_at(&typed), Some(1_700_000_300));
+
+        // 存储往返后摘要不变
+        let stored = encode_stored(&typed).unwrap();
+        let decoded = decode_stored(&stored).unwrap().unwrap();
+        assert_eq!(challenge_digest(&decoded).unwrap(), digest);
+    }
+
+    #[tokio::test]
+    async fn test_digest_binds_every_field() {
+        let wallet = wallet();
+        let address = format!("0x{:x}", wallet.address());
+        let typed = build_login_challenge("Pharos-RWA", None, &address, "abc123", 1_700_000_000, 1_700_000_300).unwrap();
+        let signature = wallet.sign_typed_data(&typed).await.unwrap();
+
+        for other in [
+            build_login_challenge("Pharos-RWA", None, &address, "abc124", 1_700_000_000, 1_700_000_300).unwrap(),
+            build_login_challenge("Pharos-RWA", None, &address, "abc123", 1_700_000_001, 1_700_000_300).unwrap(),
+            build_login_challenge("Pharos-RWA", None, &address, "abc123", 1_700_000_000, 1_700_009_999).unwrap(),
+            build_login_challenge("Pharos-RWA", Some(1), &address, "abc123", 1_700_000_000, 1_700_000_300).unwrap(),
+            build_login_challenge("Other", None, &address, "abc123", 1_700_000_000, 1_700_000_300).unwrap(),
+        ] {
+            assert_ne!(signature.recover(challenge_digest(&other).unwrap()).unwrap(), wallet.address());
+        }
+    }
+
+    #[test]
+    fn test_plain_challenges_are_not_typed() {
+        assert!(decode_stored("pharos-auth-abc123").is_none());
+        assert!(decode_stored("eip712:not-json").unwrap().is_err());
+    }
+}
@@ -6,7 +6,7 @@ use std::marker::PhantomData;
 use config::{Config, ConfigError, Environment, File};
 use serde::de::DeserializeOwned;
 use serde::Deserialize;
-use log::{info, error, warn};
+use tracing::{info, error, warn};
 use toml;
 use serde::Serialize;
 
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use std::collections::HashMap;
 
 use anyhow::Result;
-use log::{error, info, warn};
+use tracing::{error, info, warn};
 use nacos_sdk::api::config::ConfigResponse;
 use nacos_sdk::api::naming::{NamingChangeEvent, NamingEventListener};
 use nacos_sdk::api::{
@@ -8,12 +8,6 @@ pub struct ResObj<T: ToSchema + 'static> {
     pub msg: String,
 }
 
-#[derive(Debug, Serialize, ToSchema)]
-pub struct Page<T: ToSchema + 'static> {
-    pub rows: Vec<T>,
-    pub total: u64,
-}
-
 impl<T: ToSchema> ResObj<T> {
     pub fn ok(data: Option<T>) -> Self {
         Self {
@@ -0,0 +1,22 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 票据截至某日的应计利息
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AccruedInterestDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub principal: String,
+    pub annual_rate: String,
+    /// 日计数规则，"ACT/365" 或 "30/360"
+    pub day_count: String,
+    /// 计息方式，"simple" 或 "compound"
+    pub compounding: String,
+    /// 起息日 (融资日)
+    pub financed_on: NaiveDate,
+    /// 实际计息截止日，查询日晚于到期日时为到期日
+    pub accrued_to: NaiveDate,
+    pub accrual_days: i64,
+    pub accrued_interest: String,
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 管理后台平台统计数据
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdminStatsDto {
+    pub total_users: u64,
+    pub total_enterprises: u64,
+    /// 各状态票据数量，没有票据的状态不返回
+    pub invoices_by_status: Vec<StatusCountDto>,
+    /// 累计融资金额 (认购交易金额之和)
+    pub total_volume_financed: String,
+    /// 未兑付本金 (有效持仓当前本金之和)
+    pub outstanding_principal: String,
+    /// 累计发行代币数量
+    pub total_tokens_minted: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct StatusCountDto {
+    pub status: String,
+    pub count: u64,
+}
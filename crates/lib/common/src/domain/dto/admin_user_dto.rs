@@ -0,0 +1,67 @@
+use mongodb::bson::DateTime;
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::entity::{User, UserRole};
+
+/// 管理后台用户列表中的一行
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdminUserDto {
+    pub id: String,
+    pub name: String,
+    pub wallet_address: String,
+    pub role: UserRole,
+    /// 绑定的企业 ID，未绑定时为空
+    pub enterprise_id: Option<String>,
+    pub balance: String,
+    pub linked_wallets: Vec<String>,
+    pub created_at: DateTime,
+    pub login_timestamp: DateTime,
+    /// 账户注销时间，未注销时不返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime>,
+}
+
+impl From<User> for AdminUserDto {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: user.name,
+            wallet_address: user.wallet_address,
+            role: user.role,
+            enterprise_id: user.enterprise_id.map(|id| id.to_hex()),
+            balance: user.balance.to_string(),
+            linked_wallets: user.linked_wallets,
+            created_at: user.created_at,
+            login_timestamp: user.login_timestamp,
+            deleted_at: user.deleted_at,
+        }
+    }
+}
+
+/// 各角色用户数 (不受角色筛选影响，其余筛选条件照常生效)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct UserRoleCountsDto {
+    pub investor: u64,
+    pub enterprise_admin: u64,
+    pub platform_admin: u64,
+}
+
+impl UserRoleCountsDto {
+    pub fn add(&mut self, role: &UserRole, count: u64) {
+        match role {
+            UserRole::Investor => self.investor += count,
+            UserRole::EnterpriseAdmin => self.enterprise_admin += count,
+            UserRole::PlatformAdmin => self.platform_admin += count,
+        }
+    }
+}
+
+/// 管理后台用户列表
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AdminUserPageDto {
+    pub rows: Vec<AdminUserDto>,
+    /// 符合全部筛选条件的用户总数
+    pub total: u64,
+    pub role_counts: UserRoleCountsDto,
+}
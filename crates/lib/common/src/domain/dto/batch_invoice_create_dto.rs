@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 批量创建中单条票据的处理结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchCreateStatus {
+    /// 已写入数据库 (未上链，或上链失败，见 `error`)
+    Created,
+    /// 已写入数据库并完成链上登记
+    Registered,
+    /// 校验或写入失败，未创建
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchCreateItemDto {
+    /// 条目在请求数组中的下标
+    pub index: usize,
+    pub status: BatchCreateStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invoice_number: Option<String>,
+    /// 校验失败时的错误码 (与单条创建接口一致，如 "INVOICE_AMOUNT_NOT_POSITIVE")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 批量创建汇总，`results` 按请求顺序排列
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchCreateInvoicesDto {
+    pub total: u32,
+    /// 已创建的条目数 (含已上链登记)
+    pub created: u32,
+    pub failed: u32,
+    pub results: Vec<BatchCreateItemDto>,
+}
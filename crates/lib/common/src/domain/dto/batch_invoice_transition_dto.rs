@@ -0,0 +1,38 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::invoice_status::InvoiceStatus;
+
+/// 批量变更状态中单张票据的处理结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchTransitionStatus {
+    /// 已变更为目标状态并写入审计记录
+    Transitioned,
+    /// 未变更 (ID 无效、票据不存在或状态机不允许)
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchTransitionItemDto {
+    pub invoice_id: String,
+    pub status: BatchTransitionStatus,
+    /// 变更前的状态，票据不存在时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<InvoiceStatus>,
+    /// 失败时的错误码 (如 "INVALID_STATUS_TRANSITION"、"INVOICE_NOT_FOUND")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 批量变更状态汇总，`results` 按请求顺序排列
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchTransitionDto {
+    pub to: InvoiceStatus,
+    pub total: u32,
+    pub transitioned: u32,
+    pub failed: u32,
+    pub results: Vec<BatchTransitionItemDto>,
+}
@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 批量兑付中单张票据的处理结果
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchSettlementStatus {
+    /// 本次完成兑付
+    Settled,
+    /// 已有结算哈希 (其他请求或定时任务已兑付)，未重复打款
+    AlreadySettled,
+    /// 其他实例正在兑付该票据，本次跳过
+    InProgress,
+    /// 未到期 (含提前兑付窗口) 且无管理员覆盖，本次跳过
+    NotMatured,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchSettlementItemDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub status: BatchSettlementStatus,
+    /// 本次兑付成功的持仓数
+    pub paid_holdings: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement_tx_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchSettlementItemDto {
+    /// 初始状态为 `Failed`，兑付成功后再更新
+    pub fn new(invoice_id: String, invoice_number: String) -> Self {
+        Self {
+            invoice_id,
+            invoice_number,
+            status: BatchSettlementStatus::Failed,
+            paid_holdings: 0,
+            settlement_tx_hash: None,
+            error: None,
+        }
+    }
+}
+
+/// 批量兑付汇总
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BatchSettlementDto {
+    /// 满足条件 (已到期、已募满、未结算) 的票据数
+    pub eligible: u32,
+    pub settled: u32,
+    pub already_settled: u32,
+    pub in_progress: u32,
+    #[serde(default)]
+    pub not_matured: u32,
+    pub failed: u32,
+    pub results: Vec<BatchSettlementItemDto>,
+}
+
+impl BatchSettlementDto {
+    /// 根据逐张票据的结果汇总各状态数量
+    pub fn from_results(eligible: u32, results: Vec<BatchSettlementItemDto>) -> Self {
+        let count = |status: BatchSettlementStatus| results.iter().filter(|r| r.status == status).count() as u32;
+        Self {
+            eligible,
+            settled: count(BatchSettlementStatus::Settled),
+            already_settled: count(BatchSettlementStatus::AlreadySettled),
+            in_progress: count(BatchSettlementStatus::InProgress),
+            not_matured: count(BatchSettlementStatus::NotMatured),
+            failed: count(BatchSettlementStatus::Failed),
+            results,
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 企业历史履约表现 (按该企业作为付款方、已获融资且未作废的票据聚合)
+///
+/// 比率均以「已到期票据」(按时兑付 + 逾期兑付 + 逾期未兑付 + 违约) 为分母，保留 4 位小数
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct EnterprisePerformanceDto {
+    pub enterprise_id: String,
+    pub wallet_address: String,
+    /// 全部票据数量
+    pub total_invoices: u64,
+    /// 已到期票据数量
+    pub matured_invoices: u64,
+    /// 在到期日 + 宽限期内兑付
+    pub settled_on_time: u64,
+    /// 超过宽限期后兑付
+    pub settled_late: u64,
+    /// 已过宽限期仍未兑付 (尚未违约)
+    pub overdue: u64,
+    /// 已违约
+    pub defaulted: u64,
+    /// 尚未到期
+    pub outstanding: u64,
+    pub on_time_rate: String,
+    pub late_rate: String,
+    /// 违约率
+    pub default_rate: String,
+    /// 拖欠率 = (逾期兑付 + 逾期未兑付) / 已到期
+    pub delinquency_rate: String,
+    /// 判定逾期所用的宽限天数
+    pub grace_days: i64,
+}
+
+/// 企业详情中展示给投资人的履约摘要
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct EnterprisePerformanceSummaryDto {
+    pub matured_invoices: u64,
+    pub on_time_rate: String,
+    pub default_rate: String,
+    pub delinquency_rate: String,
+}
+
+impl From<&EnterprisePerformanceDto> for EnterprisePerformanceSummaryDto {
+    fn from(p: &EnterprisePerformanceDto) -> Self {
+        Self {
+            matured_invoices: p.matured_invoices,
+            on_time_rate: p.on_time_rate.clone(),
+            default_rate: p.default_rate.clone(),
+            delinquency_rate: p.delinquency_rate.clone(),
+        }
+    }
+}
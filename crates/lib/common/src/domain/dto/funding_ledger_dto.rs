@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 单笔认购记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FundingEventDto {
+    pub transaction_id: String,
+    pub holding_id: String,
+    /// 投资人钱包地址
+    pub investor: String,
+    pub amount: String,
+    /// 认购时间 (毫秒时间戳)
+    pub timestamp: i64,
+    /// 截至本笔的累计认购金额
+    pub running_total: String,
+    /// 代币铸造交易哈希，铸造完成前为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+}
+
+/// 票据融资台账：按时间顺序列出全部认购，是已募集金额的权威来源
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FundingLedgerDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub currency: String,
+    /// 票据融资目标金额
+    pub target_amount: String,
+    pub funded_total: String,
+    /// 剩余可募集金额，超募时为 0
+    pub remaining: String,
+    pub events: Vec<FundingEventDto>,
+}
@@ -1,6 +1,7 @@
 use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
 use mongodb::bson::{DateTime, Decimal128, oid::ObjectId};
+use rust_decimal::Decimal;
 use crate::domain::entity::HoldingStatus;
 use salvo::oapi::ToSchema;
 
@@ -14,7 +15,9 @@ pub struct HoldingDto {
     pub purchase_date: DateTime,
     pub current_balance: String,
     pub total_accrued_interest: String,
-    pub annual_rate: f64,
+    /// 年化利率 (百分数)
+    #[salvo(schema(value_type = String))]
+    pub annual_rate: Decimal,
     pub maturity_date: NaiveDate,
     pub status: HoldingStatus,
 }
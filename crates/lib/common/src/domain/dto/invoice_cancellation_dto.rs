@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::invoice_status::InvoiceStatus;
+use crate::domain::entity::RepaymentPayoutDto;
+
+/// 管理员作废票据的结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct InvoiceCancellationDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 作废前的票据状态
+    pub previous_status: InvoiceStatus,
+    /// 退款总额，等于各持有人退款金额之和；无人认购时为 0
+    pub refund_total: String,
+    /// 退款分配交易哈希，无需退款时为空
+    pub refund_tx_hash: Option<String>,
+    pub refunds: Vec<RepaymentPayoutDto>,
+}
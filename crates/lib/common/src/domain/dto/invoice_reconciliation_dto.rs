@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 票据数据库记录与链上数据的核对结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct InvoiceReconciliationDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 合约中是否存在该票据
+    pub onchain_found: bool,
+    /// 没有任何差异
+    pub consistent: bool,
+    pub diffs: Vec<ReconciliationDiffDto>,
+}
+
+/// 一项不一致的字段
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct ReconciliationDiffDto {
+    /// `amount`、`owner` 或 `status`
+    pub field: String,
+    pub db_value: String,
+    pub onchain_value: String,
+}
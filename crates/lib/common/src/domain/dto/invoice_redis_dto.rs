@@ -3,8 +3,10 @@ use serde::{Deserialize, Serialize};
 use chrono::NaiveDate;
 
 use mongodb::bson::{oid::ObjectId, DateTime};
+use rust_decimal::Decimal;
 use salvo::oapi::ToSchema;
 use crate::domain::entity::invoice_status::InvoiceStatus;
+use crate::utils::money::Money;
 
 #[derive(Debug, Clone,Serialize,PartialEq, Deserialize, ToSchema)]
 pub struct InvoiceRedisDto {
@@ -12,10 +14,13 @@ pub struct InvoiceRedisDto {
     pub invoice_number: String,
     pub title: String,
     pub description: Option<String>,
-    pub annual_rate: f64,
+    /// 年化利率 (百分数)，序列化为字符串，旧缓存中的数字同样可以读取
+    #[salvo(schema(value_type = String))]
+    pub annual_rate: Decimal,
     pub total_shares: u64,
     pub available_shares: u64,
-    pub share_price: f64,
+    /// 份额单价，旧缓存中的数字同样可以读取
+    pub share_price: Money,
     pub issue_date: NaiveDate,
     pub maturity_date: NaiveDate,
     pub status: InvoiceStatus,
@@ -23,9 +28,9 @@ pub struct InvoiceRedisDto {
 
 
 impl InvoiceRedisDto {
-    pub fn calculate_daily_rate(&self, is_leap_year: bool) -> f64 {
-        let days_in_year = if is_leap_year { 366.0 } else { 365.0 };
-        self.annual_rate / 100.0 / days_in_year
+    pub fn calculate_daily_rate(&self, is_leap_year: bool) -> Decimal {
+        let days_in_year = if is_leap_year { 366 } else { 365 };
+        self.annual_rate / Decimal::ONE_HUNDRED / Decimal::from(days_in_year)
     }
     
     pub fn is_available_for_purchase(&self) -> bool {
@@ -49,3 +54,33 @@ impl InvoiceRedisDto {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_annual_rate_reads_old_numeric_cache() {
+        let mut cached = serde_json::json!({
+            "invoice_id": "1",
+            "invoice_number": "INV-1",
+            "title": "Test",
+            "description": null,
+            "annual_rate": 7.3,
+            "total_shares": 100,
+            "available_shares": 100,
+            "share_price": 10,
+            "issue_date": "2026-01-01",
+            "maturity_date": "2026-12-31",
+            "status": "PACKAGED"
+        });
+        let old: InvoiceRedisDto = serde_json::from_value(cached.clone()).unwrap();
+        assert_eq!(old.annual_rate, Decimal::from_str("7.3").unwrap());
+
+        cached["annual_rate"] = serde_json::json!("7.3");
+        let new: InvoiceRedisDto = serde_json::from_value(cached).unwrap();
+        assert_eq!(new.annual_rate, old.annual_rate);
+        assert_eq!(new.calculate_daily_rate(false), Decimal::from_str("0.0002").unwrap());
+    }
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::invoice_status::InvoiceStatus;
+
+/// 票据状态变更事件，通过 `GET /invoice/{id}/events` 以 SSE 推送
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct InvoiceStatusEventDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 变更前状态
+    pub from: InvoiceStatus,
+    /// 变更后状态
+    pub to: InvoiceStatus,
+    /// 变更时间 (毫秒时间戳)
+    pub occurred_at: i64,
+}
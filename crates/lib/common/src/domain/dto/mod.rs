@@ -3,4 +3,26 @@ pub mod query_invoice_dto;
 pub mod interest_detail_dto;
 pub mod purchase_invoice_dto;
 pub mod holding_dto;
-pub mod invoice_redis_dto;
\ No newline at end of file
+pub mod invoice_redis_dto;
+pub mod platform_stats_dto;
+pub mod settlement_projection_dto;
+pub mod reservation_dto;
+pub mod timeline_dto;
+pub mod enterprise_performance_dto;
+pub mod token_holder_dto;
+pub mod token_balance_dto;
+pub mod batch_settlement_dto;
+pub mod funding_ledger_dto;
+pub mod purchase_history_dto;
+pub mod repayment_settlement_dto;
+pub mod accrued_interest_dto;
+pub mod payment_schedule_dto;
+pub mod admin_stats_dto;
+pub mod user_export_dto;
+pub mod invoice_reconciliation_dto;
+pub mod invoice_cancellation_dto;
+pub mod batch_invoice_create_dto;
+pub mod batch_invoice_transition_dto;
+pub mod portfolio_interest_dto;
+pub mod invoice_status_event_dto;
+pub mod admin_user_dto;
@@ -0,0 +1,26 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 票据预计还款计划
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PaymentScheduleDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 付息频率，"bullet"、"monthly" 或 "quarterly"
+    pub frequency: String,
+    pub financed_on: NaiveDate,
+    pub maturity_date: NaiveDate,
+    pub total_principal: String,
+    pub total_interest: String,
+    pub payments: Vec<ScheduledPaymentDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduledPaymentDto {
+    pub installment: u32,
+    pub due_date: NaiveDate,
+    pub principal: String,
+    pub interest: String,
+    pub remaining_balance: String,
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 平台公开统计数据 (不包含任何用户/企业身份信息)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PlatformStatsDto {
+    /// 累计融资金额
+    pub total_volume_financed: String,
+    /// 平均年化利率
+    pub average_apr: String,
+    /// 在售/打包中的票据数量
+    pub active_invoices: u64,
+    /// 累计投资人数
+    pub total_investors: u64,
+    /// 按月统计的融资金额
+    pub monthly_volume: Vec<MonthlyVolumeDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MonthlyVolumeDto {
+    /// 月份 (YYYY-MM)
+    pub month: String,
+    /// 当月融资金额
+    pub volume: String,
+}
@@ -0,0 +1,30 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 单个持仓截至某日的应计利息
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PositionInterestDto {
+    pub holding_id: String,
+    pub invoice_id: String,
+    pub invoice_number: String,
+    pub purchase_amount: String,
+    /// 持仓占票据本金的比例 (认购金额 / 票据金额)
+    pub ownership: String,
+    /// 整张票据的应计利息
+    pub invoice_accrued_interest: String,
+    /// 按持仓比例分得的应计利息
+    pub accrued_interest: String,
+    /// 实际计息截止日，票据尚未融资时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub accrued_to: Option<NaiveDate>,
+}
+
+/// 投资人全部活跃持仓的应计利息
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PortfolioInterestDto {
+    pub as_of: NaiveDate,
+    pub total_purchase_amount: String,
+    pub total_accrued_interest: String,
+    pub positions: Vec<PositionInterestDto>,
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::HoldingStatus;
+use crate::domain::entity::invoice_status::InvoiceStatus;
+use crate::pagination::Page;
+
+/// 投资人的一笔认购记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PurchaseHistoryItemDto {
+    pub holding_id: String,
+    pub invoice_id: String,
+    /// 票据编号 (票据已删除时为空)
+    pub invoice_number: Option<String>,
+    /// 认购金额
+    pub amount: String,
+    /// 认购时间 (毫秒时间戳)
+    pub purchased_at: i64,
+    /// 代币铸造交易哈希，铸造完成前为空
+    pub tx_hash: Option<String>,
+    /// 持仓状态
+    pub holding_status: HoldingStatus,
+    /// 票据当前状态
+    pub invoice_status: Option<InvoiceStatus>,
+}
+
+/// 认购汇总，按投资人全部认购计算 (不受分页影响)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct PurchaseSummaryDto {
+    /// 累计认购金额
+    pub total_invested: String,
+    /// 持有中的仓位数
+    pub active_positions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PurchaseHistoryDto {
+    /// 当前页认购记录 (`rows` / `has_more` / `total`)
+    #[serde(flatten)]
+    pub page: Page<PurchaseHistoryItemDto>,
+    pub summary: PurchaseSummaryDto,
+}
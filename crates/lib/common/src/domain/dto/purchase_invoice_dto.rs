@@ -1,8 +1,18 @@
 use serde::{Deserialize, Serialize};
 use salvo::oapi::ToSchema;
 
+use crate::utils::money::Money;
+
 #[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct PurchaseInvoiceDto {
     pub invoice_id: String,
-    pub purchase_amount: f64,
+    /// 认购金额，按份额单价折算为整数份 (指定 `units` 时忽略)
+    #[serde(default)]
+    pub purchase_amount: Money,
+    /// 认购份数，可只认购票据面值的一部分
+    #[serde(default)]
+    pub units: Option<u64>,
+    /// 使用的预约 (须为本人在该票据上未过期的预约)，预约的份数已从可用份数中扣除，认购时直接抵扣
+    #[serde(default)]
+    pub reservation_id: Option<String>,
 }
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::RepaymentPayoutDto;
+
+/// 企业还款兑付结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct RepaymentSettlementDto {
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 还款总额，等于各持有人兑付金额之和
+    pub total_amount: String,
+    pub settlement_tx_hash: String,
+    pub payouts: Vec<RepaymentPayoutDto>,
+}
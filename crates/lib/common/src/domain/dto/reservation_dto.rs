@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::utils::money::Money;
+
+/// 票据预约 (保存在 Redis 中，过期自动失效)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ReservationDto {
+    pub reservation_id: String,
+    pub user_id: String,
+    pub invoice_id: String,
+    pub invoice_number: String,
+    /// 预约份数
+    pub shares: u64,
+    /// 预约金额 (份数 × 每份价格)
+    pub reserved_amount: String,
+    /// 创建时间 (毫秒时间戳)
+    pub created_at: i64,
+    /// 过期时间 (毫秒时间戳)
+    pub expires_at: i64,
+}
+
+impl ReservationDto {
+    pub fn is_expired(&self, now_ms: i64) -> bool {
+        self.expires_at <= now_ms
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateReservationDto {
+    pub invoice_id: String,
+    pub amount: Money,
+}
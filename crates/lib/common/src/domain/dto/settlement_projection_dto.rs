@@ -0,0 +1,30 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 持仓兑付预估 (每次请求按当前计息状态重新计算，仅供参考)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SettlementProjectionDto {
+    pub holding_id: String,
+    pub invoice_id: String,
+    /// 固定为 true，所有金额均为预估值
+    pub is_estimate: bool,
+    /// 票据到期日
+    pub maturity_date: NaiveDate,
+    /// 预计兑付日 (到期日 + 宽限期)
+    pub projected_settlement_date: NaiveDate,
+    /// 本金
+    pub principal: String,
+    /// 已计提利息
+    pub accrued_interest: String,
+    /// 预计到期总利息 (已计提 + 剩余未计提)
+    pub projected_interest: String,
+    /// 预计平台服务费 (按利息收取)
+    pub projected_fees: String,
+    /// 预计到账金额 = 本金 + 预计利息 - 预计服务费
+    pub projected_payout: String,
+    /// 计算所用年化利率
+    pub annual_rate: String,
+    /// 计算所用服务费率
+    pub fee_rate: String,
+}
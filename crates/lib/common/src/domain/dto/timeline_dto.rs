@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 票据活动时间线中的一条记录
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TimelineEntryDto {
+    /// 发生时间 (毫秒时间戳)
+    pub timestamp: i64,
+    /// 来源: lifecycle / onchain / purchase / settlement / audit
+    pub source: String,
+    /// 事件名称，如 "invoice.created"、"purchase"
+    pub event: String,
+    /// 操作人地址 (对无权查看的用户隐藏)
+    pub actor: Option<String>,
+    /// 涉及金额
+    pub amount: Option<String>,
+    pub details: Option<serde_json::Value>,
+}
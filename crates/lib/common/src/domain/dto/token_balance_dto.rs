@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 用户在某代币合约上的余额对账结果
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenBalanceDto {
+    pub token_address: String,
+    pub wallet_address: String,
+    /// 数据库记录的余额 (已完成转账净额)
+    pub recorded_balance: String,
+    /// 链上 `balanceOf` 按代币精度换算后的余额 (超出表示范围时为最小单位原始值)，未请求 `onchain=true` 时为空
+    pub onchain_balance: Option<String>,
+    /// 两者是否不一致，未查询链上余额时为空
+    pub drift: Option<bool>,
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+/// 代币持有人及其持仓占比
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenHolderDto {
+    pub user_id: String,
+    /// 按已完成转账净额计算的持仓数量
+    pub balance: String,
+    /// 占批次总发行量的百分比 (保留 4 位小数)
+    pub percentage_of_supply: String,
+}
+
+/// 某个代币批次的完整持有人分布 (按持仓降序)，整体缓存在 Redis 中
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenHolderDistributionDto {
+    pub batch_id: String,
+    pub total_supply: String,
+    pub holders: Vec<TokenHolderDto>,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use salvo::oapi::ToSchema;
+
+use crate::domain::entity::HoldingStatus;
+
+/// 用户数据导出 (`GET /user/export`)：账户、绑定企业、认购与资金流水
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserDataExportDto {
+    /// 导出时间 (毫秒时间戳)
+    pub generated_at: i64,
+    pub user: ExportedUserDto,
+    /// 绑定的企业，未绑定或企业已不存在时为空
+    pub enterprise: Option<ExportedEnterpriseDto>,
+    pub purchases: Vec<ExportedPurchaseDto>,
+    pub transactions: Vec<ExportedTransactionDto>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedUserDto {
+    pub id: String,
+    pub name: String,
+    pub wallet_address: String,
+    pub linked_wallets: Vec<String>,
+    pub role: String,
+    pub balance: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub last_login_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedEnterpriseDto {
+    pub id: String,
+    pub name: String,
+    pub wallet_address: String,
+}
+
+/// 一笔认购 (持仓)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedPurchaseDto {
+    pub holding_id: String,
+    pub invoice_id: String,
+    /// 认购使用的钱包地址
+    pub wallet_address: String,
+    pub purchase_amount: String,
+    pub current_balance: String,
+    pub total_accrued_interest: String,
+    pub purchased_at: i64,
+    pub holding_status: HoldingStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ExportedTransactionDto {
+    pub id: String,
+    pub invoice_id: String,
+    pub holding_id: String,
+    pub wallet_address: String,
+    pub transaction_type: String,
+    pub amount: String,
+    pub status: String,
+    pub transaction_date: i64,
+}
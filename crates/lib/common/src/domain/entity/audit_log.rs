@@ -0,0 +1,114 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{self, Bson, DateTime, Document, doc, oid::ObjectId};
+use salvo_oapi::ToSchema;
+
+use super::invoice_status::InvoiceStatus;
+
+/// 系统任务 (定时兑付等) 的操作人
+pub const SYSTEM_ACTOR: &str = "system";
+/// 票据变更记录的目标类型
+pub const INVOICE_TARGET: &str = "invoice";
+
+/// 通用审计日志，记录管理员越权操作、角色变更、票据变更等敏感动作
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditLog {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// 操作人钱包地址 (系统任务为 "system")
+    pub actor: String,
+    /// 动作，如 "settlement.early_override"
+    pub action: String,
+    /// 目标类型，如 "invoice" / "user"
+    pub target_type: String,
+    /// 目标 ID
+    pub target_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Document>,
+    pub created_at: DateTime,
+}
+
+impl AuditLog {
+    pub fn new(actor: &str, action: &str, target_type: &str, target_id: &str, details: Option<Document>) -> Self {
+        Self {
+            id: None,
+            actor: actor.to_string(),
+            action: action.to_string(),
+            target_type: target_type.to_string(),
+            target_id: target_id.to_string(),
+            details,
+            created_at: DateTime::now(),
+        }
+    }
+
+    /// 票据变更记录，details 中保存变更前后的状态 (新建的票据没有变更前状态)。
+    /// 与票据的写入在同一 Mongo 事务中提交
+    pub fn invoice(invoice_id: ObjectId, actor: &str, action: &str, previous_status: Option<InvoiceStatus>, next_status: InvoiceStatus) -> Self {
+        let mut details = doc! { "next_status": status_bson(next_status) };
+        if let Some(previous) = previous_status {
+            details.insert("previous_status", status_bson(previous));
+        }
+        Self::new(&actor.to_lowercase(), action, INVOICE_TARGET, &invoice_id.to_hex(), Some(details))
+    }
+
+    /// 追加一项说明，如修改的字段
+    pub fn with_detail(mut self, key: &str, value: impl Into<Bson>) -> Self {
+        self.details.get_or_insert_with(Document::new).insert(key, value);
+        self
+    }
+
+    pub fn with_reason(self, reason: impl Into<String>) -> Self {
+        self.with_detail("reason", reason.into())
+    }
+
+    /// 票据变更后的状态
+    pub fn next_status(&self) -> Option<InvoiceStatus> {
+        self.detail_status("next_status")
+    }
+
+    fn detail_status(&self, key: &str) -> Option<InvoiceStatus> {
+        let value = self.details.as_ref()?.get(key)?.clone();
+        bson::from_bson(value).ok()
+    }
+}
+
+fn status_bson(status: InvoiceStatus) -> Bson {
+    bson::to_bson(&status).expect("InvoiceStatus serializes to a string")
+}
+
+/// 票据变更记录
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct InvoiceAuditDto {
+    pub id: String,
+    pub invoice_id: String,
+    /// 操作人钱包地址
+    pub actor: String,
+    /// 动作，如 "create"、"update"、"delete"、"verify"、"accept_terms"、"issue"、"finance"、"settle"
+    pub action: String,
+    /// 变更前的状态，新建票据时为空
+    pub previous_status: Option<InvoiceStatus>,
+    pub next_status: Option<InvoiceStatus>,
+    pub reason: Option<String>,
+    /// 修改的字段 (仅 "update")
+    pub changed_fields: Option<Vec<String>>,
+    /// 发生时间 (毫秒时间戳)
+    pub timestamp: i64,
+}
+
+impl InvoiceAuditDto {
+    pub fn from(data: &AuditLog) -> Self {
+        let details = data.details.as_ref();
+        Self {
+            id: data.id.map(|id| id.to_hex()).unwrap_or_default(),
+            invoice_id: data.target_id.clone(),
+            actor: data.actor.clone(),
+            action: data.action.clone(),
+            previous_status: data.detail_status("previous_status"),
+            next_status: data.next_status(),
+            reason: details.and_then(|d| d.get_str("reason").ok()).map(str::to_string),
+            changed_fields: details
+                .and_then(|d| d.get_array("changed_fields").ok())
+                .map(|fields| fields.iter().filter_map(|f| f.as_str().map(str::to_string)).collect()),
+            timestamp: data.created_at.timestamp_millis(),
+        }
+    }
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+/// 合约写操作记录，同一操作 (operation + reference) 的多次提交合并为一条，`attempts` 为累计提交次数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractOperation {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// 操作类型，如 "batch_create_invoices"、"purchase_shares"
+    pub operation: String,
+    /// 业务标识 (批次 ID / 票据号)
+    pub reference: String,
+    /// 最近一次提交的结果
+    pub status: ContractOperationStatus,
+    #[serde(default)]
+    pub tx_hash: Option<String>,
+    #[serde(default)]
+    pub revert_reason: Option<String>,
+    #[serde(default)]
+    pub error: Option<String>,
+    pub attempts: u32,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ContractOperationStatus {
+    Succeeded,
+    Failed,
+}
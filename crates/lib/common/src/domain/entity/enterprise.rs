@@ -4,6 +4,8 @@ use mongodb::bson::{DateTime, oid::ObjectId};
 use salvo_oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::domain::dto::enterprise_performance_dto::EnterprisePerformanceSummaryDto;
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Enterprise {
     #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
@@ -12,14 +14,19 @@ pub struct Enterprise {
     pub wallet_address: String, // Blockchain address for identification/signing
     pub status: EnterpriseStatus,
     pub kyc_details_ipfs_hash: Option<String>, // Link to KYC documents on IPFS
+    /// 审核驳回原因，仅 `Rejected` 时有值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize,ToSchema)]
+/// 企业审核 (KYC) 状态，只有 `Verified` 企业发行的票据可以被认购
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize,ToSchema)]
 pub enum EnterpriseStatus {
     PendingVerification,
     Verified,
+    Rejected,
     Suspended,
 }
 
@@ -33,6 +40,7 @@ impl Enterprise {
             wallet_address,
             status: EnterpriseStatus::PendingVerification,
             kyc_details_ipfs_hash: None,
+            rejection_reason: None,
             created_at: now,
             updated_at: now,
         }
@@ -47,8 +55,13 @@ pub struct EnterpriseDto {
     pub wallet_address: String, // Blockchain address for identification/signing
     pub status: EnterpriseStatus,
     pub kyc_details_ipfs_hash: Option<String>, // Link to KYC documents on IPFS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rejection_reason: Option<String>,
     pub created_at: DateTime,
     pub updated_at: DateTime,
+    /// 历史履约摘要，仅在企业详情中返回
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub performance: Option<EnterprisePerformanceSummaryDto>,
 }
 
 impl EnterpriseDto {
@@ -59,8 +72,10 @@ impl EnterpriseDto {
             wallet_address: data.wallet_address,
             status: data.status,
             kyc_details_ipfs_hash: data.kyc_details_ipfs_hash,
+            rejection_reason: data.rejection_reason,
             created_at: data.created_at,
             updated_at: data.updated_at,
+            performance: None,
         }
     }
 }
\ No newline at end of file
@@ -31,7 +31,36 @@ pub struct Invoice {
     pub token_batch: Option<String>,  // Token batch identifier (from DTO)
     pub is_cleared: Option<bool>,     // Blockchain clearance status
     pub is_valid: Option<bool>,       // Blockchain validity status
-    
+
+    // --- Attached documents quota ---
+    #[serde(default)]
+    pub document_count: u32,          // Number of uploaded documents
+    #[serde(default)]
+    pub document_total_bytes: u64,    // Cumulative size of uploaded documents
+
+    // --- Funding terms accepted by the enterprise before listing ---
+    #[serde(default)]
+    pub accepted_terms: Option<AcceptedTerms>,
+    // 条款确认上线前已上架的存量票据没有条款快照，由启动迁移标记后免于条款校验
+    #[serde(default)]
+    pub terms_exempt: bool,
+
+    // --- Settlement: 合约暂无还款接口时为链下兑付批次号 ---
+    #[serde(default)]
+    pub settlement_tx_hash: Option<String>,
+
+    // --- 已认购份数 (部分融资累计)，达到总份数时票据标记为已融资 ---
+    #[serde(default)]
+    pub funded_shares: u64,
+
+    // --- 乐观锁版本号，每次更新加 1 (旧文档没有该字段，视为 0) ---
+    #[serde(default)]
+    pub version: i64,
+
+    // --- 软删除时间，非空时默认查询不再返回该票据 ---
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime>,
+
     // --- Timestamps ---
     pub created_at: DateTime,
     pub updated_at: DateTime,
@@ -61,10 +90,36 @@ impl Invoice {
             token_batch: None,
             is_cleared: None,
             is_valid: None,
+            document_count: 0,
+            document_total_bytes: 0,
+            accepted_terms: None,
+            terms_exempt: false,
+            settlement_tx_hash: None,
+            funded_shares: 0,
+            version: 0,
+            deleted_at: None,
             created_at: now,
             updated_at: now,
         }
     }
+
+    /// 是否已被软删除
+    pub fn is_deleted(&self) -> bool {
+        self.deleted_at.is_some()
+    }
+}
+
+/// 企业接受的融资条款快照 (接受时平台报价)
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct AcceptedTerms {
+    /// 年化利率，如 "0.08"
+    pub apr: String,
+    /// 平台服务费率，如 "0.01"
+    pub platform_fee_rate: String,
+    /// 接受条款的用户钱包地址
+    pub accepted_by: String,
+    /// 接受时间
+    pub accepted_at: DateTime,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize,ToSchema)]
@@ -100,6 +155,18 @@ pub struct InvoiceDto {
     pub token_batch: Option<String>,  // Token batch identifier (from DTO)
     pub is_cleared: Option<bool>,     // Blockchain clearance status
     pub is_valid: Option<bool>,       // Blockchain validity status
+    /// 已接受的融资条款
+    pub accepted_terms: Option<AcceptedTerms>,
+    /// 结算哈希 (链下兑付时为兑付批次号)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settlement_tx_hash: Option<String>,
+    /// 已认购份数
+    pub funded_shares: u64,
+    /// 版本号，更新票据时原样带回，不一致时返回 409
+    pub version: i64,
+    /// 软删除时间 (仅管理员查询已删除票据时返回)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime>,
 
     // --- Timestamps ---
     pub created_at: DateTime,
@@ -124,6 +191,11 @@ impl InvoiceDto {
             token_batch: data.token_batch.clone(),
             is_cleared: data.is_cleared,
             is_valid: data.is_valid,
+            accepted_terms: data.accepted_terms.clone(),
+            settlement_tx_hash: data.settlement_tx_hash.clone(),
+            funded_shares: data.funded_shares,
+            version: data.version,
+            deleted_at: data.deleted_at,
             created_at: data.created_at,
             updated_at: data.updated_at,
         }
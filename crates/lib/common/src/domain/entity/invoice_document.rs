@@ -0,0 +1,46 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 票据附件 (合同、发票扫描件等)
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct InvoiceDocument {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub invoice_id: ObjectId,       // Reference to Invoice
+    pub file_name: String,          // Original file name
+    pub content_type: Option<String>,
+    pub size: u64,                  // Size in bytes
+    pub storage_path: String,       // Path on local storage
+    pub uploaded_by: String,        // Wallet address of uploader
+    pub created_at: DateTime,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct InvoiceDocumentDto {
+    /// 附件ID
+    pub id: String,
+    /// 票据ID
+    pub invoice_id: String,
+    /// 文件名
+    pub file_name: String,
+    /// 文件类型
+    pub content_type: Option<String>,
+    /// 文件大小 (字节)
+    pub size: u64,
+    /// 上传时间
+    pub created_at: DateTime,
+}
+
+impl InvoiceDocumentDto {
+    pub fn from(data: &InvoiceDocument) -> Self {
+        Self {
+            id: data.id.map(|id| id.to_string()).unwrap_or_default(),
+            invoice_id: data.invoice_id.to_string(),
+            file_name: data.file_name.clone(),
+            content_type: data.content_type.clone(),
+            size: data.size,
+            created_at: data.created_at,
+        }
+    }
+}
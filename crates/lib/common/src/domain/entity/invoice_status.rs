@@ -12,6 +12,8 @@ pub enum InvoiceStatus {
     Overdue,    // 已逾期
     Defaulted,  // 已违约
     OnSale,     // 在售
+    Financed,   // 已融资 (份额已全部认购)
+    Cancelled,  // 已作废 (管理员强制撤销并退款)
 }
 
 impl Display for InvoiceStatus {
@@ -24,6 +26,8 @@ impl Display for InvoiceStatus {
             InvoiceStatus::Overdue => "已逾期".to_string(),
             InvoiceStatus::Defaulted => "已违约".to_string(),
             InvoiceStatus::OnSale => "在售".to_string(),
+            InvoiceStatus::Financed => "已融资".to_string(),
+            InvoiceStatus::Cancelled => "已作废".to_string(),
         };
         write!(f, "{}", str)
     }
@@ -33,4 +37,94 @@ impl Default for InvoiceStatus {
     fn default() -> Self {
         Self::Pending
     }
-}
\ No newline at end of file
+}
+
+impl InvoiceStatus {
+    pub const ALL: [InvoiceStatus; 9] = [
+        InvoiceStatus::Pending,
+        InvoiceStatus::Verified,
+        InvoiceStatus::Packaged,
+        InvoiceStatus::OnSale,
+        InvoiceStatus::Financed,
+        InvoiceStatus::Overdue,
+        InvoiceStatus::Defaulted,
+        InvoiceStatus::Repaid,
+        InvoiceStatus::Cancelled,
+    ];
+
+    /// 状态机：是否允许从当前状态变更为 `next`。相同状态之间的变更视为非法 (没有实际变化的写入)
+    ///
+    /// Pending → Verified → Packaged/OnSale → Financed → Repaid，到期未兑付进入 Overdue，逾期后可兑付或违约。
+    /// 已上架但未募满的票据到期时同样可以兑付或逾期。兑付前可由管理员作废 (Cancelled)。Repaid、Defaulted、Cancelled 为终态。
+    pub fn can_transition_to(&self, next: InvoiceStatus) -> bool {
+        use InvoiceStatus::*;
+        matches!(
+            (self, next),
+            (Pending, Verified)
+                | (Verified, Packaged)
+                | (Verified, OnSale)
+                | (Packaged, OnSale)
+                | (Packaged | OnSale, Financed)
+                | (Packaged | OnSale | Financed, Overdue)
+                | (Packaged | OnSale | Financed | Overdue, Repaid)
+                | (Overdue, Defaulted)
+                | (Pending | Verified | Packaged | OnSale | Financed | Overdue, Cancelled)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use InvoiceStatus::*;
+
+    #[test]
+    fn test_transition_matrix() {
+        let allowed = [
+            (Pending, Verified),
+            (Verified, Packaged),
+            (Verified, OnSale),
+            (Packaged, OnSale),
+            (Packaged, Financed),
+            (Packaged, Overdue),
+            (Packaged, Repaid),
+            (OnSale, Financed),
+            (OnSale, Overdue),
+            (OnSale, Repaid),
+            (Financed, Overdue),
+            (Financed, Repaid),
+            (Overdue, Repaid),
+            (Overdue, Defaulted),
+            (Pending, Cancelled),
+            (Verified, Cancelled),
+            (Packaged, Cancelled),
+            (OnSale, Cancelled),
+            (Financed, Cancelled),
+            (Overdue, Cancelled),
+        ];
+        for from in InvoiceStatus::ALL {
+            for to in InvoiceStatus::ALL {
+                assert_eq!(from.can_transition_to(to), allowed.contains(&(from, to)), "{:?} -> {:?}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn test_self_transitions_rejected() {
+        for status in InvoiceStatus::ALL {
+            assert!(!status.can_transition_to(status), "{:?} -> {:?}", status, status);
+        }
+    }
+
+    #[test]
+    fn test_terminal_states() {
+        for to in InvoiceStatus::ALL {
+            assert!(!Repaid.can_transition_to(to));
+            assert!(!Defaulted.can_transition_to(to));
+            assert!(!Cancelled.can_transition_to(to));
+        }
+        // 禁止回退
+        assert!(!Repaid.can_transition_to(Pending));
+        assert!(!Financed.can_transition_to(Verified));
+    }
+}
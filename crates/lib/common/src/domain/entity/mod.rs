@@ -3,6 +3,7 @@ pub mod enterprise;
 pub mod user;
 pub mod invoice;
 pub mod invoice_batch;
+pub mod invoice_document;
 pub mod rbt_holding;
 pub mod repayment;
 pub mod settlement_nft;
@@ -14,8 +15,9 @@ pub mod token;
 // Re-export entity types for easier access
 pub use enterprise::{Enterprise, EnterpriseStatus};
 pub use user::{User, UserRole};
-pub use invoice::{Invoice};
+pub use invoice::{AcceptedTerms, Invoice};
 pub use invoice_batch::{InvoiceBatch, InvoiceBatchStatus};
+pub use invoice_document::{InvoiceDocument, InvoiceDocumentDto};
 pub use rbt_holding::RbtHolding;
 pub use repayment::Repayment;
 pub use settlement_nft::SettlementNft;
@@ -23,11 +25,25 @@ pub mod invoice_status;
 pub mod user_invoice_holding;
 pub mod daily_interest_accrual;
 pub mod transaction;
+pub mod webhook;
+pub mod audit_log;
+pub mod contract_operation;
+pub mod onchain_transaction;
+pub mod repayment_payout;
+pub mod token_transfer;
+pub mod token_mint;
 
 
 pub use user_invoice_holding::{UserInvoiceHolding, HoldingStatus};
 pub use daily_interest_accrual::DailyInterestAccrual;
 pub use transaction::{Transaction, TransactionType};
+pub use webhook::{WebhookDelivery, WebhookDeliveryStatus, WebhookSubscription};
+pub use audit_log::{AuditLog, InvoiceAuditDto};
+pub use contract_operation::{ContractOperation, ContractOperationStatus};
+pub use onchain_transaction::{OnchainTransaction, OnchainTxStatus};
+pub use repayment_payout::{RepaymentPayout, RepaymentPayoutDto};
+pub use token_transfer::{OnchainTokenBalance, TokenTransfer};
+pub use token_mint::{TokenMint, TokenMintStatus};
 pub use token::{
     TokenBatch, TokenBatchStatus, TokenMarket, TokenHolding, TokenHoldingStatus,
     TokenTransaction, TokenTransactionType, TokenTransactionStatus,
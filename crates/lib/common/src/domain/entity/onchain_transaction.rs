@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+/// 已广播的链上交易，由后台轮询回执更新状态 (`tx_hash` 唯一)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnchainTransaction {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// 0x 开头的小写交易哈希
+    pub tx_hash: String,
+    /// 操作类型，如 "purchase_shares"、"create_token_batch"
+    pub operation: String,
+    /// 业务标识 (批次 ID / 票据号)
+    pub reference: String,
+    /// 发送方地址 (小写)，历史记录可能为空
+    #[serde(default)]
+    pub from_address: Option<String>,
+    /// 接收方地址 (小写，通常为合约地址)
+    #[serde(default)]
+    pub to_address: Option<String>,
+    /// 交易代其操作的用户地址 (小写，如兑付持有人)，平台自身的操作为空
+    #[serde(default)]
+    pub accounts: Vec<String>,
+    pub status: OnchainTxStatus,
+    /// 交易所在区块，未打包时为空
+    #[serde(default)]
+    pub block_number: Option<i64>,
+    /// 最近一次轮询时的确认数 (含所在区块)
+    #[serde(default)]
+    pub confirmations: i64,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum OnchainTxStatus {
+    /// 未打包或确认数不足
+    Pending,
+    Confirmed,
+    /// 已打包但执行失败 (回执 status = 0)
+    Reverted,
+}
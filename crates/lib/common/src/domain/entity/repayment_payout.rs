@@ -0,0 +1,42 @@
+use mongodb::bson::{DateTime, Decimal128, oid::ObjectId};
+use salvo_oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 还款兑付给单个代币持有人的金额 (`repayment_payouts`)，同一票据同一持有人只有一条
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RepaymentPayout {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub invoice_id: ObjectId,
+    /// TokenBatch 的 _id
+    pub batch_id: ObjectId,
+    pub user_id: ObjectId,
+    /// 收款钱包地址 (小写)
+    pub wallet_address: String,
+    /// 兑付时的代币持仓
+    pub token_balance: Decimal128,
+    /// 分配的兑付金额 (稳定币)
+    pub amount: Decimal128,
+    /// 分配交易哈希
+    pub settlement_tx_hash: String,
+    pub created_at: DateTime,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct RepaymentPayoutDto {
+    pub user_id: String,
+    pub wallet_address: String,
+    pub token_balance: String,
+    pub amount: String,
+}
+
+impl RepaymentPayoutDto {
+    pub fn from(data: &RepaymentPayout) -> Self {
+        Self {
+            user_id: data.user_id.to_hex(),
+            wallet_address: data.wallet_address.clone(),
+            token_balance: data.token_balance.to_string(),
+            amount: data.amount.to_string(),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum TokenMintStatus {
+    /// 等待 (重新) 提交
+    Pending,
+    Minted,
+    /// 重试次数用尽，或交易可能已广播而无法确定结果，需要人工对账
+    Failed,
+}
+
+/// 认购对应的链上代币铸造，与认购在同一事务内写入，由后台任务提交 `purchaseSharesFor`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenMint {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// 对应的持仓 (唯一)
+    pub holding_id: String,
+    pub invoice_id: ObjectId,
+    /// 链上代币批次 ID
+    pub batch_id: String,
+    /// 接收代币的投资人钱包地址
+    pub recipient: String,
+    /// 铸造数量 (代币最小单位)
+    pub amount: String,
+    pub status: TokenMintStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime,
+    pub last_error: Option<String>,
+    pub tx_hash: Option<String>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl TokenMint {
+    pub fn new(holding_id: String, invoice_id: ObjectId, batch_id: String, recipient: String, amount: String) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: None,
+            holding_id,
+            invoice_id,
+            batch_id,
+            recipient,
+            amount,
+            status: TokenMintStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+            tx_hash: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use mongodb::bson::{DateTime, Decimal128, oid::ObjectId};
+
+/// 索引到的链上 ERC20 转账 (`tx_hash` + `log_index` 唯一)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenTransfer {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    /// 代币合约地址 (小写)
+    pub token_address: String,
+    /// 发送方地址 (小写)，铸造时为零地址
+    pub from_address: String,
+    /// 接收方地址 (小写)，销毁时为零地址
+    pub to_address: String,
+    /// 转账数量 (合约最小单位)
+    pub value: Decimal128,
+    pub block_number: i64,
+    /// 0x 开头的小写交易哈希
+    pub tx_hash: String,
+    pub log_index: i64,
+    pub created_at: DateTime,
+}
+
+/// 由转账事件累计的链上余额 (`token_address` + `wallet_address` 唯一)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OnchainTokenBalance {
+    pub token_address: String,
+    pub wallet_address: String,
+    pub balance: Decimal128,
+    pub updated_at: DateTime,
+}
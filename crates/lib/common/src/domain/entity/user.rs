@@ -1,5 +1,6 @@
 use std::str::FromStr;
 use mongodb::bson::{DateTime, oid::ObjectId, Decimal128};
+use salvo_oapi::ToSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -14,15 +15,36 @@ pub struct User {
     pub created_at: DateTime,
     pub updated_at: DateTime,
     pub login_timestamp: DateTime, // Keep track of the last login
+    /// 关联到该账户的其他钱包 (小写)，可用于登录同一账户
+    #[serde(default)]
+    pub linked_wallets: Vec<String>,
+    /// 账户注销 (匿名化) 时间，未注销时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, ToSchema)]
 pub enum UserRole {
     Investor,
     EnterpriseAdmin,
     PlatformAdmin,
 }
 
+impl UserRole {
+    /// 管理员可执行的角色变更：投资人与企业管理员互转，企业管理员可提升为平台管理员，
+    /// 平台管理员可降为任一角色。投资人不能直接提升为平台管理员
+    pub fn can_change_to(&self, target: &UserRole) -> bool {
+        matches!(
+            (self, target),
+            (UserRole::Investor, UserRole::EnterpriseAdmin)
+                | (UserRole::EnterpriseAdmin, UserRole::Investor)
+                | (UserRole::EnterpriseAdmin, UserRole::PlatformAdmin)
+                | (UserRole::PlatformAdmin, UserRole::EnterpriseAdmin)
+                | (UserRole::PlatformAdmin, UserRole::Investor)
+        )
+    }
+}
+
 // Helper methods
 impl User {
     pub fn new(wallet_address: String,name:String, role: UserRole) -> Self {
@@ -38,9 +60,17 @@ impl User {
             created_at: now,
             updated_at: now,
             login_timestamp: now,
+            linked_wallets: Vec::new(),
+            deleted_at: None,
         }
     }
     
+    /// 地址是否为该账户的主钱包或关联钱包 (不区分大小写)
+    pub fn owns_wallet(&self, address: &str) -> bool {
+        self.wallet_address.eq_ignore_ascii_case(address)
+            || self.linked_wallets.iter().any(|w| w.eq_ignore_ascii_case(address))
+    }
+
     pub fn update_login_time(&mut self) {
         self.login_timestamp = DateTime::now();
         self.updated_at = DateTime::now();
@@ -8,6 +8,8 @@ pub enum HoldingStatus {
     Active,
     Matured,
     Sold,
+    /// 票据被作废，认购资金已退回
+    Refunded,
 }
 
 impl Default for HoldingStatus {
@@ -0,0 +1,63 @@
+use mongodb::bson::{DateTime, oid::ObjectId};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum WebhookDeliveryStatus {
+    Pending,   // Waiting for (re)delivery
+    Succeeded, // Receiver answered with 2xx
+    Failed,    // Gave up after max attempts
+}
+
+/// 一次 webhook 投递记录，持久化后重启也能继续重试
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub enterprise_id: Option<ObjectId>, // Receiving enterprise
+    pub url: String,                     // Callback URL
+    pub event: String,                   // e.g. "invoice.status_changed"
+    pub payload: String,                 // Raw JSON body, sent as-is
+    pub signature: Option<String>,       // Signature header value, computed at enqueue time
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub next_attempt_at: DateTime,
+    pub last_error: Option<String>,
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
+
+impl WebhookDelivery {
+    pub fn new(enterprise_id: Option<ObjectId>, url: String, event: String, payload: String, signature: Option<String>, max_attempts: u32) -> Self {
+        let now = DateTime::now();
+        Self {
+            id: None,
+            enterprise_id,
+            url,
+            event,
+            payload,
+            signature,
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            max_attempts,
+            next_attempt_at: now,
+            last_error: None,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+/// 企业登记的 webhook 回调地址，票据状态变更时签名后推送
+///
+/// 签名密钥不落库：由服务端 `webhook.signing_key` 与 `secret_salt` 派生，只在登记时返回一次
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSubscription {
+    #[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+    pub id: Option<ObjectId>,
+    pub enterprise_id: ObjectId, // Owning enterprise
+    pub url: String,             // Callback URL
+    pub secret_salt: String,     // Random per-registration salt the signing secret is derived from
+    pub created_at: DateTime,
+    pub updated_at: DateTime,
+}
@@ -3,3 +3,4 @@ pub mod config;
 pub mod utils;
 pub mod validate;
 pub mod domain;
+pub mod pagination;
@@ -0,0 +1,214 @@
+//! 通用游标分页：以 `_id` 作为游标按 `_id` 倒序 (最新在前) 翻页，并发插入的新记录不会打乱后续页
+//!
+//! 游标列表使用 [`Pagination`]、页码列表使用 [`OffsetPagination`] 作为输入；所有列表接口 (游标与页码分页) 统一以 [`Page`] 作为输出。
+//! 游标对客户端不透明，只需把上一页的 `next_cursor` 原样传回。每页条数由调用方按分页配置
+//! (`PageSizePolicy`) 截断后传入，这里不再另设默认值和上限。
+
+use mongodb::bson::{doc, oid::ObjectId, Document};
+use mongodb::options::FindOptions;
+use salvo::oapi::ToSchema;
+use serde::{Deserialize, Serialize};
+
+/// 客户端传回的游标无法解析
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Invalid cursor: {0}")]
+pub struct InvalidCursor(pub String);
+
+/// 分页参数，`cursor` 为上一页返回的 `next_cursor`，为空时查询第一页
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pagination {
+    pub cursor: Option<String>,
+    pub limit: u64,
+}
+
+impl Pagination {
+    /// `limit` 需已按分页配置截断，至少为 1
+    pub fn new(cursor: Option<String>, limit: u64) -> Self {
+        Self { cursor, limit: limit.max(1) }
+    }
+
+    /// 上一页最后一条记录的 `_id`
+    pub fn after(&self) -> Result<Option<ObjectId>, InvalidCursor> {
+        decode_cursor(self.cursor.as_deref())
+    }
+
+    /// 在查询条件上追加游标条件 (`_id < 游标`)
+    pub fn apply_cursor(&self, filter: &mut Document) -> Result<(), InvalidCursor> {
+        if let Some(after) = self.after()? {
+            filter.insert("_id", doc! { "$lt": after });
+        }
+        Ok(())
+    }
+
+    /// `find` 选项：按 `_id` 倒序，多取一条用来判断是否还有下一页
+    pub fn find_options(&self) -> FindOptions {
+        FindOptions::builder()
+            .sort(doc! { "_id": -1 })
+            .limit(self.limit.max(1) as i64 + 1)
+            .build()
+    }
+}
+
+/// 页码分页参数，`page` 从 1 开始，`page_size` 需已按分页配置截断
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetPagination {
+    pub page: u64,
+    pub page_size: u64,
+}
+
+impl OffsetPagination {
+    /// `page` 来自查询参数，超大页码截断到跳过条数不超过 `i64::MAX` (MongoDB `skip` 上限)，
+    /// 这样的页码只会得到空页而不会溢出
+    pub fn new(page: u64, page_size: u64) -> Self {
+        let page_size = page_size.max(1);
+        let max_page = i64::MAX as u64 / page_size + 1;
+        Self { page: page.clamp(1, max_page), page_size }
+    }
+
+    /// 跳过的条数
+    pub fn skip(&self) -> u64 {
+        (self.page - 1) * self.page_size
+    }
+}
+
+/// 生成游标，格式与票据、交易列表已下发的游标兼容
+pub fn encode_cursor(id: &ObjectId) -> String {
+    id.to_hex()
+}
+
+/// 解析游标，空字符串视为第一页
+pub fn decode_cursor(cursor: Option<&str>) -> Result<Option<ObjectId>, InvalidCursor> {
+    match cursor.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(c) => ObjectId::parse_str(c).map(Some).map_err(|_| InvalidCursor(c.to_string())),
+        None => Ok(None),
+    }
+}
+
+/// `rows` 按 [`Pagination::find_options`] 查询得到 (limit + 1 条)，截断为一页并生成下一页游标
+pub fn take_page<R>(mut rows: Vec<R>, limit: u64, id_of: impl Fn(&R) -> Option<ObjectId>) -> (Vec<R>, Option<String>) {
+    let limit = limit.max(1);
+    let has_more = rows.len() as u64 > limit;
+    rows.truncate(limit as usize);
+    let next_cursor = if has_more { rows.last().and_then(id_of).map(|id| encode_cursor(&id)) } else { None };
+    (rows, next_cursor)
+}
+
+/// 分页结果。游标分页时把 `next_cursor` 原样传回即可获取下一页；页码分页时 `next_cursor` 不返回
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Page<T: ToSchema + 'static> {
+    pub rows: Vec<T>,
+    /// 下一页游标，没有更多数据或页码分页时为空
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    pub has_more: bool,
+    /// 符合筛选条件的总数
+    pub total: u64,
+}
+
+impl<T: ToSchema + 'static> Page<T> {
+    /// 由 `limit + 1` 条查询结果构建一页，`id_of` 取记录的 `_id`，`convert` 转换为返回类型
+    pub fn from_rows<R>(
+        rows: Vec<R>,
+        pagination: &Pagination,
+        total: u64,
+        id_of: impl Fn(&R) -> Option<ObjectId>,
+        convert: impl FnMut(R) -> T,
+    ) -> Self {
+        let (rows, next_cursor) = take_page(rows, pagination.limit, id_of);
+        Self { rows: rows.into_iter().map(convert).collect(), has_more: next_cursor.is_some(), next_cursor, total }
+    }
+
+    /// 页码分页的一页，`skip` 为跳过的条数
+    pub fn offset(rows: Vec<T>, total: u64, skip: u64) -> Self {
+        let has_more = skip + (rows.len() as u64) < total;
+        Self { rows, next_cursor: None, has_more, total }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip_is_stable() {
+        let id = ObjectId::new();
+        let cursor = encode_cursor(&id);
+        assert_eq!(decode_cursor(Some(&cursor)), Ok(Some(id)));
+        // 同一记录多次编码得到相同游标，前后空白不影响解析
+        assert_eq!(encode_cursor(&id), cursor);
+        assert_eq!(decode_cursor(Some(&format!(" {} ", cursor))), Ok(Some(id)));
+
+        assert_eq!(decode_cursor(None), Ok(None));
+        assert_eq!(decode_cursor(Some("")), Ok(None));
+        assert_eq!(decode_cursor(Some("not-a-cursor")), Err(InvalidCursor("not-a-cursor".to_string())));
+    }
+
+    #[test]
+    fn test_limit_is_at_least_one() {
+        assert_eq!(Pagination::new(None, 0).limit, 1);
+        assert_eq!(Pagination::new(None, 50).limit, 50);
+    }
+
+    #[test]
+    fn test_offset_pagination_skip() {
+        assert_eq!(OffsetPagination::new(0, 0), OffsetPagination { page: 1, page_size: 1 });
+        assert_eq!(OffsetPagination::new(1, 20).skip(), 0);
+        assert_eq!(OffsetPagination::new(3, 20).skip(), 40);
+        // 超大页码不溢出
+        assert!(OffsetPagination::new(u64::MAX, 20).skip() <= i64::MAX as u64);
+        assert!(OffsetPagination::new(u64::MAX, 1).skip() <= i64::MAX as u64);
+    }
+
+    #[test]
+    fn test_find_options_and_cursor_filter() {
+        let id = ObjectId::new();
+        let pagination = Pagination { cursor: Some(encode_cursor(&id)), limit: 10 };
+        let options = pagination.find_options();
+        assert_eq!(options.limit, Some(11));
+        assert_eq!(options.sort, Some(doc! { "_id": -1 }));
+
+        let mut filter = doc! { "status": "Active" };
+        pagination.apply_cursor(&mut filter).unwrap();
+        assert_eq!(filter, doc! { "status": "Active", "_id": { "$lt": id } });
+
+        let mut first_page = doc! {};
+        Pagination::new(None, 10).apply_cursor(&mut first_page).unwrap();
+        assert!(first_page.is_empty());
+    }
+
+    #[test]
+    fn test_page_from_rows() {
+        // 按 _id 倒序查询出 limit + 1 条
+        let mut ids: Vec<ObjectId> = (0..4).map(|_| ObjectId::new()).collect();
+        ids.reverse();
+        let pagination = Pagination::new(None, 3);
+        let page: Page<String> = Page::from_rows(ids.clone(), &pagination, 4, |id| Some(*id), |id| id.to_hex());
+        assert_eq!(page.rows.len(), 3);
+        assert!(page.has_more);
+        assert_eq!(page.total, 4);
+        assert_eq!(decode_cursor(page.next_cursor.as_deref()), Ok(Some(ids[2])));
+
+        // 最后一页没有游标
+        let last: Page<String> = Page::from_rows(vec![ids[3]], &pagination, 4, |id| Some(*id), |id| id.to_hex());
+        assert_eq!(last.rows, vec![ids[3].to_hex()]);
+        assert!(!last.has_more);
+        assert!(last.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_page_never_exceeds_limit() {
+        let ids: Vec<ObjectId> = (0..150).map(|_| ObjectId::new()).collect();
+        let page: Page<String> = Page::from_rows(ids, &Pagination::new(None, 100), 150, |id| Some(*id), |id| id.to_hex());
+        assert_eq!(page.rows.len(), 100);
+        assert!(page.has_more);
+    }
+
+    #[test]
+    fn test_offset_page() {
+        let middle = Page::offset(vec![1, 2], 5, 2);
+        assert!(middle.has_more);
+        assert!(middle.next_cursor.is_none());
+        assert!(!Page::offset(vec![5], 5, 4).has_more);
+        assert!(!Page::<u64>::offset(Vec::new(), 0, 0).has_more);
+    }
+}
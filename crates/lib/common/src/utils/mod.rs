@@ -3,4 +3,5 @@ pub mod wallet_utils;
 pub mod crypto_utils;
 pub mod pagination;
 pub mod snowflake_util;
-pub mod serde_format;
\ No newline at end of file
+pub mod serde_format;
+pub mod money;
@@ -0,0 +1,249 @@
+//! 金额类型
+//!
+//! `Money` 以十进制定点数 (`rust_decimal::Decimal`) 保存金额，加减乘除不会像 f64 那样累积二进制误差。
+//! 对外序列化为固定 8 位小数的字符串，反序列化同时接受字符串和 JSON 数字 (兼容旧客户端和旧缓存)。
+
+use std::fmt;
+use std::str::FromStr;
+
+use mongodb::bson::Decimal128;
+use rust_decimal::{Decimal, RoundingStrategy};
+use salvo::oapi::ToSchema;
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum MoneyError {
+    #[error("Invalid money amount: {0}")]
+    Invalid(String),
+
+    #[error("Money arithmetic overflow")]
+    Overflow,
+
+    #[error("Money division by zero")]
+    DivisionByZero,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, ToSchema)]
+pub struct Money(#[salvo(schema(value_type = String))] Decimal);
+
+impl Money {
+    /// 序列化和写入数据库时保留的小数位数
+    pub const SCALE: u32 = 8;
+    pub const ZERO: Money = Money(Decimal::ZERO);
+
+    pub fn new(amount: Decimal) -> Self {
+        Self(amount)
+    }
+
+    pub fn amount(self) -> Decimal {
+        self.0
+    }
+
+    /// 按 f64 的最短十进制表示转换 (0.1 得到精确的 0.1，而不是其二进制近似值)
+    pub fn from_f64(value: f64) -> Result<Self, MoneyError> {
+        if !value.is_finite() {
+            return Err(MoneyError::Invalid(value.to_string()));
+        }
+        value.to_string().parse()
+    }
+
+    pub fn from_decimal128(value: &Decimal128) -> Result<Self, MoneyError> {
+        value.to_string().parse()
+    }
+
+    /// 按 `SCALE` 位小数写入数据库
+    pub fn to_decimal128(self) -> Result<Decimal128, MoneyError> {
+        let text = self.to_string();
+        Decimal128::from_str(&text).map_err(|_| MoneyError::Invalid(text))
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > Decimal::ZERO
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < Decimal::ZERO
+    }
+
+    pub fn checked_add(self, rhs: Money) -> Result<Money, MoneyError> {
+        self.0.checked_add(rhs.0).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(self, rhs: Money) -> Result<Money, MoneyError> {
+        self.0.checked_sub(rhs.0).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    /// 乘以份数、利率等无量纲系数
+    pub fn checked_mul(self, factor: Decimal) -> Result<Money, MoneyError> {
+        self.0.checked_mul(factor).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_div(self, divisor: Decimal) -> Result<Money, MoneyError> {
+        if divisor.is_zero() {
+            return Err(MoneyError::DivisionByZero);
+        }
+        self.0.checked_div(divisor).map(Money).ok_or(MoneyError::Overflow)
+    }
+
+    /// 两笔金额之比，如认购金额按份额单价折算的份数
+    pub fn ratio(self, unit: Money) -> Result<Decimal, MoneyError> {
+        if unit.0.is_zero() {
+            return Err(MoneyError::DivisionByZero);
+        }
+        self.0.checked_div(unit.0).ok_or(MoneyError::Overflow)
+    }
+
+    /// 四舍五入到 `SCALE` 位小数
+    pub fn rounded(self) -> Money {
+        Money(self.0.round_dp_with_strategy(Self::SCALE, RoundingStrategy::MidpointAwayFromZero))
+    }
+}
+
+impl From<Decimal> for Money {
+    fn from(amount: Decimal) -> Self {
+        Self(amount)
+    }
+}
+
+impl From<u64> for Money {
+    fn from(amount: u64) -> Self {
+        Self(Decimal::from(amount))
+    }
+}
+
+impl FromStr for Money {
+    type Err = MoneyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // Decimal128 的字符串形式可能带指数 (如 "1.5E+3")
+        Decimal::from_str(s)
+            .or_else(|_| Decimal::from_scientific(s))
+            .map(Money)
+            .map_err(|_| MoneyError::Invalid(s.to_string()))
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.*}", Self::SCALE as usize, self.rounded().0)
+    }
+}
+
+impl Serialize for Money {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Money {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(MoneyVisitor)
+    }
+}
+
+struct MoneyVisitor;
+
+impl<'de> Visitor<'de> for MoneyVisitor {
+    type Value = Money;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal string or number")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Money, E> {
+        v.parse().map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Money, E> {
+        Ok(Money::from(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Money, E> {
+        Ok(Money(Decimal::from(v)))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Money, E> {
+        Money::from_f64(v).map_err(E::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn money(s: &str) -> Money {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_repeated_addition_is_exact() {
+        let dime = money("0.1");
+        let mut total = Money::ZERO;
+        let mut total_f64 = 0.0_f64;
+        for _ in 0..10 {
+            total = total.checked_add(dime).unwrap();
+            total_f64 += 0.1;
+        }
+        assert_eq!(total, money("1"));
+        // 同样的累加在 f64 下已经偏离
+        assert_ne!(total_f64, 1.0);
+    }
+
+    #[test]
+    fn test_principal_plus_interest_is_exact() {
+        let principal = money("1000.10");
+        let interest = money("0.20");
+        let total = principal.checked_add(interest).unwrap();
+        assert_eq!(total.to_string(), "1000.30000000");
+        assert_ne!(1000.10_f64 + 0.20_f64, 1000.30_f64);
+
+        let back = total.checked_sub(interest).unwrap();
+        assert_eq!(back, principal);
+    }
+
+    #[test]
+    fn test_share_math_is_exact() {
+        let share_price = money("0.07");
+        let shares = money("0.21").ratio(share_price).unwrap();
+        assert_eq!(shares, Decimal::from(3));
+        assert_eq!(share_price.checked_mul(shares).unwrap(), money("0.21"));
+        assert_ne!(0.07_f64 * 3.0, 0.21_f64);
+    }
+
+    #[test]
+    fn test_overflow_and_division_by_zero() {
+        let max = Money::new(Decimal::MAX);
+        assert_eq!(max.checked_add(money("1")), Err(MoneyError::Overflow));
+        assert_eq!(max.checked_mul(Decimal::from(2)), Err(MoneyError::Overflow));
+        assert_eq!(money("1").checked_div(Decimal::ZERO), Err(MoneyError::DivisionByZero));
+        assert_eq!(money("1").ratio(Money::ZERO), Err(MoneyError::DivisionByZero));
+    }
+
+    #[test]
+    fn test_serializes_as_fixed_scale_string() {
+        assert_eq!(serde_json::to_value(money("100.1")).unwrap(), serde_json::json!("100.10000000"));
+        assert_eq!(serde_json::to_value(money("0.123456789")).unwrap(), serde_json::json!("0.12345679"));
+        assert_eq!(serde_json::to_value(Money::ZERO).unwrap(), serde_json::json!("0.00000000"));
+    }
+
+    #[test]
+    fn test_deserializes_strings_and_numbers() {
+        let parse = |v: serde_json::Value| serde_json::from_value::<Money>(v).unwrap();
+        assert_eq!(parse(serde_json::json!("0.1")), money("0.1"));
+        assert_eq!(parse(serde_json::json!(0.1)), money("0.1"));
+        assert_eq!(parse(serde_json::json!(5)), money("5"));
+        assert_eq!(parse(serde_json::json!(-5)), money("-5"));
+        assert!(serde_json::from_value::<Money>(serde_json::json!("abc")).is_err());
+        assert!(serde_json::from_value::<Money>(serde_json::json!(true)).is_err());
+    }
+
+    #[test]
+    fn test_decimal128_round_trip() {
+        let amount = money("1234.5");
+        let stored = amount.to_decimal128().unwrap();
+        assert_eq!(Money::from_decimal128(&stored).unwrap(), amount);
+        assert_eq!(money("1.5E+3"), money("1500"));
+    }
+}
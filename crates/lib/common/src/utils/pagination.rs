@@ -1 +1,93 @@
-// Placeholder for pagination logic 
\ No newline at end of file
+use std::collections::HashMap;
+
+/// 分页大小策略
+///
+/// 每个接口使用一个稳定的名称 (如 `"token.markets"`) 查找上限：
+/// 有覆盖配置时使用覆盖值，否则使用全局 `max_page_size`；所有上限都不能超过 `hard_ceiling`。
+#[derive(Debug, Clone)]
+pub struct PageSizePolicy {
+    default_page_size: u64,
+    max_page_size: u64,
+    overrides: HashMap<String, u64>,
+}
+
+impl PageSizePolicy {
+    /// 校验配置并创建策略，任何上限为 0 或超过 `hard_ceiling` 都视为配置错误
+    pub fn new(
+        default_page_size: u64,
+        max_page_size: u64,
+        hard_ceiling: u64,
+        overrides: HashMap<String, u64>,
+    ) -> Result<Self, String> {
+        if max_page_size == 0 || max_page_size > hard_ceiling {
+            return Err(format!("max_page_size {} must be between 1 and hard_ceiling {}", max_page_size, hard_ceiling));
+        }
+        if default_page_size == 0 || default_page_size > max_page_size {
+            return Err(format!("default_page_size {} must be between 1 and max_page_size {}", default_page_size, max_page_size));
+        }
+        for (endpoint, max) in &overrides {
+            if *max == 0 || *max > hard_ceiling {
+                return Err(format!("page size override for '{}' ({}) must be between 1 and hard_ceiling {}", endpoint, max, hard_ceiling));
+            }
+        }
+        Ok(Self { default_page_size, max_page_size, overrides })
+    }
+
+    /// 接口允许的最大分页大小
+    pub fn max_for(&self, endpoint: &str) -> u64 {
+        self.overrides.get(endpoint).copied().unwrap_or(self.max_page_size)
+    }
+
+    /// 根据请求值计算实际分页大小：未传或非正数时使用默认值，超过上限时截断
+    pub fn resolve(&self, endpoint: &str, requested: Option<i64>) -> u64 {
+        self.resolve_or(endpoint, requested, self.default_page_size)
+    }
+
+    /// 同 `resolve`，但未传或非正数时使用接口自己的默认值 `default` (同样不超过上限)
+    pub fn resolve_or(&self, endpoint: &str, requested: Option<i64>, default: u64) -> u64 {
+        let max = self.max_for(endpoint);
+        match requested {
+            Some(size) if size > 0 => (size as u64).min(max),
+            _ => default.clamp(1, max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> PageSizePolicy {
+        let overrides = HashMap::from([("export".to_string(), 500), ("market".to_string(), 20)]);
+        PageSizePolicy::new(10, 100, 1000, overrides).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_uses_endpoint_override() {
+        let policy = policy();
+        assert_eq!(policy.resolve("export", Some(400)), 400);
+        assert_eq!(policy.resolve("export", Some(5000)), 500);
+        assert_eq!(policy.resolve("market", Some(50)), 20);
+        assert_eq!(policy.resolve("other", Some(150)), 100);
+        assert_eq!(policy.resolve("other", None), 10);
+        assert_eq!(policy.resolve("other", Some(-1)), 10);
+    }
+
+    #[test]
+    fn test_resolve_with_endpoint_default() {
+        let policy = policy();
+        assert_eq!(policy.resolve_or("export", None, 50), 50);
+        assert_eq!(policy.resolve_or("export", Some(0), 50), 50);
+        assert_eq!(policy.resolve_or("export", Some(120), 50), 120);
+        // 接口默认值同样受上限约束
+        assert_eq!(policy.resolve_or("market", None, 50), 20);
+    }
+
+    #[test]
+    fn test_overrides_validated_against_hard_ceiling() {
+        let overrides = HashMap::from([("export".to_string(), 5000)]);
+        assert!(PageSizePolicy::new(10, 100, 1000, overrides).is_err());
+        assert!(PageSizePolicy::new(10, 2000, 1000, HashMap::new()).is_err());
+        assert!(PageSizePolicy::new(0, 100, 1000, HashMap::new()).is_err());
+    }
+}
@@ -1 +1,57 @@
-// Placeholder for wallet utils 
\ No newline at end of file
+//! 钱包地址工具
+
+use ethers::types::Address;
+use ethers::utils::to_checksum;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum AddressError {
+    #[error("invalid address format")]
+    InvalidFormat,
+    #[error("invalid EIP-55 checksum")]
+    InvalidChecksum,
+}
+
+/// 校验并规范化钱包地址，返回小写形式 (数据库统一存储该形式)
+///
+/// 全小写或全大写视为未带校验和直接接受；大小写混合时必须符合 EIP-55 校验和。
+pub fn normalize_address(address: &str) -> Result<String, AddressError> {
+    let hex = address.strip_prefix("0x").ok_or(AddressError::InvalidFormat)?;
+    if hex.len() != 40 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AddressError::InvalidFormat);
+    }
+    let has_lower = hex.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = hex.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        let parsed: Address = address.parse().map_err(|_| AddressError::InvalidFormat)?;
+        if to_checksum(&parsed, None) != address {
+            return Err(AddressError::InvalidChecksum);
+        }
+    }
+    Ok(format!("0x{}", hex.to_ascii_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // EIP-55 规范中的示例地址
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    const LOWERCASE: &str = "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed";
+    const BAD_CHECKSUM: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+
+    #[test]
+    fn test_normalize_address() {
+        assert_eq!(normalize_address(CHECKSUMMED).unwrap(), LOWERCASE);
+        assert_eq!(normalize_address(LOWERCASE).unwrap(), LOWERCASE);
+        assert_eq!(normalize_address(&LOWERCASE.to_uppercase().replacen("0X", "0x", 1)).unwrap(), LOWERCASE);
+        assert_eq!(normalize_address(BAD_CHECKSUM), Err(AddressError::InvalidChecksum));
+    }
+
+    #[test]
+    fn test_normalize_rejects_malformed() {
+        assert_eq!(normalize_address("5aaeb6053f3e94c9b9a09f33669435e7ef1beaed"), Err(AddressError::InvalidFormat));
+        assert_eq!(normalize_address("0x5aaeb6053f3e94c9b9a09f33669435e7ef1beae"), Err(AddressError::InvalidFormat));
+        assert_eq!(normalize_address("0xzaaeb6053f3e94c9b9a09f33669435e7ef1beaed"), Err(AddressError::InvalidFormat));
+    }
+}
@@ -1,17 +1,96 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 use structopt::StructOpt;
 
 /// 配置文件
 #[derive(Debug, Deserialize)]
 pub struct Configs {
+    /// 运行环境 (启动参数 `-e`)，不从配置文件读取
+    #[serde(skip)]
+    pub env: String,
     /// 程序配置
     pub server: Server,
     pub redis: Redis,
     pub jwt: Jwt,
+    /// 钱包签名登录配置
+    #[serde(default)]
+    pub auth: Auth,
     pub kafka: Kafka,
     ///  数据库 配置
     pub database: Database,
+    /// 票据相关配置
+    #[serde(default)]
+    pub invoice: InvoiceCfg,
+    /// 管理后台配置
+    #[serde(default)]
+    pub admin: Admin,
+    /// 安全响应头 / HTTPS 配置
+    #[serde(default)]
+    pub security: Security,
+    /// 统计数据配置
+    #[serde(default)]
+    pub stats: Stats,
+    /// Webhook 投递配置
+    #[serde(default)]
+    pub webhook: Webhook,
+    /// 兑付配置
+    #[serde(default)]
+    pub settlement: Settlement,
+    /// 票据计息配置
+    #[serde(default)]
+    pub interest: Interest,
+    /// 票据预约配置
+    #[serde(default)]
+    pub reservation: Reservation,
+    /// 用户绑定企业配置
+    #[serde(default)]
+    pub enterprise_binding: EnterpriseBinding,
+    /// 企业登记配置
+    #[serde(default)]
+    pub enterprise: EnterpriseCfg,
+    /// 分页配置
+    #[serde(default)]
+    pub pagination: Pagination,
+    /// 会话 (Swagger 登录) 配置
+    #[serde(default)]
+    pub session: Session,
+    /// 功能开关配置
+    #[serde(default)]
+    pub features: Features,
+    /// 认购配置
+    #[serde(default)]
+    pub purchase: Purchase,
+    /// 接口限流配置
+    #[serde(default)]
+    pub rate_limit: RateLimit,
+    /// 区块链网络与合约地址配置
+    #[serde(default)]
+    pub chain: Chain,
+    /// 合约调用重试配置
+    #[serde(default)]
+    pub contract_retry: ContractRetry,
+    /// 链上交易回执轮询配置
+    #[serde(default)]
+    pub transaction_poller: TransactionPoller,
+    /// 认购代币铸造任务配置
+    #[serde(default)]
+    pub token_mint: TokenMint,
+    /// ERC20 Transfer 事件索引配置
+    #[serde(default)]
+    pub transfer_indexer: TransferIndexer,
+}
+
+impl Configs {
+    /// `-e prod` 启动时视为生产环境
+    pub fn is_production(&self) -> bool {
+        self.env == "prod"
+    }
 
+    /// EIP-712 登录挑战域中的 chainId
+    pub fn eip712_chain_id(&self) -> Option<u64> {
+        self.auth.eip712_chain_id.or(Some(self.chain.chain_id).filter(|id| *id != 0))
+    }
 }
 
 /// server 配置文件
@@ -29,6 +108,37 @@ pub struct Server {
     pub port: i32,
     /// 服务器名称
     pub api_prefix: String,
+    /// 允许跨域访问的来源 (如 https://app.example.com)，为空时开发环境只允许本机来源，生产环境不允许跨域
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    /// 停机宽限期 (秒)：收到 SIGTERM / SIGINT 后等待进行中的请求与后台任务结束的最长时间
+    #[serde(default = "default_shutdown_grace_secs")]
+    pub shutdown_grace_secs: u64,
+    /// POST / PUT / PATCH 请求体大小上限 (字节)，超出返回 413；multipart 上传由各接口自行限制
+    #[serde(default = "default_max_body_bytes")]
+    pub max_body_bytes: usize,
+    /// 单个请求的处理时限 (毫秒)，超时返回 504 并取消 handler；0 表示不限制
+    #[serde(default = "default_request_timeout_ms")]
+    pub request_timeout_ms: u64,
+    /// 静态文件目录，相对路径基于进程工作目录
+    #[serde(default = "default_static_dir")]
+    pub static_dir: String,
+}
+
+fn default_shutdown_grace_secs() -> u64 {
+    30
+}
+
+fn default_max_body_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_request_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_static_dir() -> String {
+    "./static".to_string()
 }
 
 /// Redis 配置文件
@@ -39,9 +149,524 @@ pub struct Redis {
 
 #[derive(Clone,Debug, Deserialize)]
 pub struct Jwt {
+    /// 可通过环境变量 JWT_SECRET 覆盖
+    pub secret: String,
+    /// 过期后仍允许 /user/refresh 换发新令牌的宽限期 (秒)
+    #[serde(default = "default_refresh_grace_secs")]
+    pub refresh_grace_secs: i64,
+    /// 密钥集 (`keys`) 使用的签名算法：HS256 或 RS256
+    #[serde(default = "default_jwt_algorithm")]
+    pub algorithm: String,
+    /// 签发新令牌使用的密钥 kid，为空时使用 `secret` (HS256) 签名且不写 kid 头
+    #[serde(default)]
+    pub current_kid: String,
+    /// 按 kid 校验的密钥；轮换时保留旧密钥直到其签发的令牌全部过期
+    #[serde(default)]
+    pub keys: Vec<JwtKey>,
+}
+
+/// 密钥集中的一个密钥
+#[derive(Clone, Debug, Deserialize)]
+pub struct JwtKey {
+    pub kid: String,
+    /// HS256 密钥
+    #[serde(default)]
+    pub secret: String,
+    /// RS256 私钥 (PEM) 路径，只有 `current_kid` 对应的密钥需要
+    #[serde(default)]
+    pub private_key_path: String,
+    /// RS256 公钥 (PEM) 路径
+    #[serde(default)]
+    pub public_key_path: String,
+}
+
+fn default_refresh_grace_secs() -> i64 {
+    3600
+}
+
+fn default_jwt_algorithm() -> String {
+    "HS256".to_string()
+}
+
+/// 钱包签名登录配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Auth {
+    /// 登录挑战有效期 (秒)
+    pub nonce_ttl_secs: u64,
+    /// 待签名的挑战消息模板，`{nonce}` 替换为随机数，启动时校验必须包含该占位符
+    pub message_template: String,
+    /// EIP-712 登录挑战的域名称
+    pub eip712_domain_name: String,
+    /// EIP-712 登录挑战域中的 chainId，为空时使用 `chain.chain_id` (为 0 时不包含)
+    pub eip712_chain_id: Option<u64>,
+    /// 清理进程内已过期令牌黑名单条目的间隔 (秒)，0 表示不清理 (仍会在容量满时淘汰)。Redis 中的条目按 TTL 自动过期
+    pub denylist_cleanup_interval_secs: u64,
+}
+
+impl Default for Auth {
+    fn default() -> Self {
+        Self {
+            nonce_ttl_secs: 300,
+            message_template: "pharos-auth-{nonce}".to_string(),
+            eip712_domain_name: "Pharos-RWA".to_string(),
+            eip712_chain_id: None,
+            denylist_cleanup_interval_secs: 300,
+        }
+    }
+}
+
+/// 会话配置
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    /// Cookie 会话签名密钥，至少 64 字节；可通过环境变量 SESSION_SECRET 覆盖
     pub secret: String,
 }
 
+/// 票据配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct InvoiceCfg {
+    /// 单张票据最多可上传的附件数量
+    pub max_documents: u32,
+    /// 单张票据附件累计最大字节数
+    pub max_documents_total_bytes: u64,
+    /// 附件存储目录
+    pub document_dir: String,
+    /// 平台当前报价的年化利率
+    pub quoted_apr: String,
+    /// 平台当前报价的服务费率
+    pub platform_fee_rate: String,
+    /// 到期后的兑付宽限期 (天)
+    pub settlement_grace_days: i64,
+    /// 单张票据金额上限 (最小货币单位，如分)
+    pub max_amount: u64,
+    /// 允许创建票据的币种，为空时拒绝所有创建请求
+    pub supported_currencies: Vec<String>,
+    /// 批量创建、批量变更状态单次最多条数
+    pub max_batch_size: usize,
+    /// 批量创建时写库与上链登记的最大并发数
+    pub batch_concurrency: usize,
+}
+
+impl Default for InvoiceCfg {
+    fn default() -> Self {
+        Self {
+            max_documents: 10,
+            max_documents_total_bytes: 50 * 1024 * 1024,
+            document_dir: "./uploads/invoice".to_string(),
+            quoted_apr: "0.08".to_string(),
+            platform_fee_rate: "0.01".to_string(),
+            settlement_grace_days: 3,
+            max_amount: 1_000_000_000_000,
+            supported_currencies: vec!["CNY".to_string(), "USD".to_string(), "USDC".to_string(), "USDT".to_string()],
+            max_batch_size: 50,
+            batch_concurrency: 4,
+        }
+    }
+}
+
+/// 管理后台配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Admin {
+    /// 内存日志环形缓冲区容量 (条)
+    pub log_buffer_capacity: usize,
+    /// 日志 SSE 连接最长持续时间 (秒)
+    pub log_stream_max_secs: u64,
+    /// 允许访问管理接口的来源 IP (CIDR)，为空则不限制；配置了但没有合法项时拒绝所有来源
+    pub ip_allowlist: Vec<String>,
+}
+
+impl Default for Admin {
+    fn default() -> Self {
+        Self {
+            log_buffer_capacity: 1000,
+            log_stream_max_secs: 600,
+            ip_allowlist: Vec::new(),
+        }
+    }
+}
+
+/// 安全响应头 / HTTPS 配置，响应头配置为空字符串时不设置该响应头
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Security {
+    /// Strict-Transport-Security
+    pub hsts: String,
+    /// X-Content-Type-Options
+    pub content_type_options: String,
+    /// X-Frame-Options
+    pub frame_options: String,
+    /// Content-Security-Policy
+    pub content_security_policy: String,
+    /// 非 HTTPS 请求的处理方式: off / redirect / reject
+    pub https_enforcement: String,
+    /// 是否信任反向代理传入的 X-Forwarded-Proto (仅限来自 `trusted_proxies` 的请求)
+    pub trust_forwarded_proto: bool,
+    /// 可信反向代理 (CIDR)，只有来自这些地址的 X-Forwarded-For 才会被采信
+    pub trusted_proxies: Vec<String>,
+    /// JWT / 会话 / webhook 签名密钥的最小估算熵 (bit)，按字符频率估算；0 表示不检查
+    pub min_secret_entropy_bits: u32,
+}
+
+impl Default for Security {
+    fn default() -> Self {
+        Self {
+            hsts: "max-age=31536000; includeSubDomains".to_string(),
+            content_type_options: "nosniff".to_string(),
+            frame_options: "DENY".to_string(),
+            content_security_policy: String::new(),
+            https_enforcement: "off".to_string(),
+            trust_forwarded_proto: false,
+            trusted_proxies: Vec::new(),
+            min_secret_entropy_bits: 96,
+        }
+    }
+}
+
+/// 统计数据配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// 统计结果在 Redis 中的缓存时间 (秒)
+    pub cache_ttl_secs: u64,
+    /// 代币持有人分布的缓存时间 (秒)，有新转账时会主动失效，0 表示不缓存
+    pub holders_cache_ttl_secs: u64,
+    /// 管理后台统计 (GET /admin/stats) 的缓存时间 (秒)，0 表示不缓存
+    pub admin_cache_ttl_secs: u64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self { cache_ttl_secs: 300, holders_cache_ttl_secs: 3600, admin_cache_ttl_secs: 60 }
+    }
+}
+
+/// Webhook 投递配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Webhook {
+    /// 单条投递最多尝试次数
+    pub max_attempts: u32,
+    /// 重试退避基数 (秒)，按 2^n 递增
+    pub base_backoff_secs: u64,
+    /// 单次请求超时 (秒)
+    pub timeout_secs: u64,
+    /// 派生各回调签名密钥的服务端密钥，可通过环境变量 WEBHOOK_SIGNING_KEY 覆盖；更换后所有已登记回调需重新登记
+    pub signing_key: String,
+    /// 允许回调到本机、内网及链路本地地址，仅供本地联调
+    pub allow_private_targets: bool,
+}
+
+impl Default for Webhook {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_backoff_secs: 10,
+            timeout_secs: 10,
+            signing_key: String::new(),
+            allow_private_targets: false,
+        }
+    }
+}
+
+/// 兑付配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Settlement {
+    /// 到期日前允许提前兑付的窗口 (秒)，0 表示必须到期后才能兑付
+    pub early_window_secs: i64,
+    /// 批量兑付时同时处理的票据数
+    pub batch_concurrency: usize,
+    /// 稳定币精度，企业还款按该精度拆分给持有人并换算为链上最小单位
+    pub payout_decimals: u32,
+    /// 企业还款、作废退款的票据锁 TTL (毫秒)。持锁期间每隔 TTL 的三分之一续期，进程崩溃后最多 TTL 后释放
+    pub lock_ttl_ms: u64,
+    /// 其他请求正在兑付同一票据时的最长等待时间 (毫秒)
+    pub lock_wait_ms: u64,
+}
+
+impl Default for Settlement {
+    fn default() -> Self {
+        Self { early_window_secs: 0, batch_concurrency: 4, payout_decimals: 6, lock_ttl_ms: 60_000, lock_wait_ms: 5_000 }
+    }
+}
+
+/// 日计数规则
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+pub enum DayCountConvention {
+    /// 实际天数 / 365 (固定分母，闰年不做调整)
+    #[default]
+    #[serde(rename = "ACT/365")]
+    Act365,
+    /// 每月按 30 天、每年按 360 天 (30/360 Bond Basis)
+    #[serde(rename = "30/360")]
+    Thirty360,
+}
+
+/// 计息方式
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compounding {
+    /// 单利
+    #[default]
+    Simple,
+    /// 按年复利，不足一年的部分按单利
+    Compound,
+}
+
+/// 付息频率
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaymentFrequency {
+    /// 每月等额还本付息
+    Monthly,
+    /// 每季度等额还本付息
+    Quarterly,
+    /// 到期一次还本付息
+    #[default]
+    Bullet,
+}
+
+/// 票据计息配置 (GET /interest/{invoice_id})
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Interest {
+    pub day_count: DayCountConvention,
+    pub compounding: Compounding,
+    /// 还款计划 (GET /interest/{invoice_id}/schedule) 的付息频率
+    pub payment_frequency: PaymentFrequency,
+}
+
+/// 票据预约配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Reservation {
+    /// 预约有效期 (秒)，过期后份数自动归还
+    pub ttl_secs: i64,
+    /// 过期预约清理任务的执行间隔 (秒)
+    pub sweep_interval_secs: u64,
+    /// 每轮最多清理的预约数
+    pub sweep_batch_size: isize,
+}
+
+impl Default for Reservation {
+    fn default() -> Self {
+        Self { ttl_secs: 900, sweep_interval_secs: 30, sweep_batch_size: 200 }
+    }
+}
+
+/// 用户绑定企业配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct EnterpriseBinding {
+    /// 绑定企业后自动将投资人提升为企业管理员，仅在同时开启 `require_onchain_authorization` 时生效
+    pub promote_on_bind: bool,
+    /// 解绑企业后将企业管理员恢复为投资人
+    pub revert_on_unbind: bool,
+    /// 绑定企业时要求钱包对绑定挑战签名
+    pub require_signature: bool,
+    /// 绑定企业时通过链上查询确认用户是企业钱包的签名人/所有者 (测试网可关闭)
+    pub require_onchain_authorization: bool,
+    /// 用户绑定企业信息的缓存时间 (秒)，0 表示不缓存
+    pub info_cache_ttl_secs: u64,
+}
+
+impl EnterpriseBinding {
+    /// 绑定时是否提升角色：只有链上确认了企业钱包所有权才提升，否则任何人绑定任意企业即可获得企业管理员权限
+    pub fn promotes_on_bind(&self) -> bool {
+        self.promote_on_bind && self.require_onchain_authorization
+    }
+}
+
+impl Default for EnterpriseBinding {
+    fn default() -> Self {
+        Self {
+            promote_on_bind: false,
+            revert_on_unbind: false,
+            require_signature: false,
+            require_onchain_authorization: false,
+            info_cache_ttl_secs: 60,
+        }
+    }
+}
+
+/// 企业登记配置
+#[derive(Clone, Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct EnterpriseCfg {
+    /// 重复登记同一钱包地址时返回 409 `ENTERPRISE_EXISTS`；关闭时返回已有企业 (幂等重试)
+    pub reject_duplicate: bool,
+}
+
+/// 功能开关配置，判定规则见 api-server `utils::feature_flags`
+#[derive(Clone, Debug, Deserialize, Default)]
+#[serde(default)]
+pub struct Features {
+    /// 允许管理员通过 `X-Feature-Flags` 请求头覆盖开关
+    pub allow_admin_override: bool,
+    /// 开关默认值，key 为开关名称，如 "new_fee_model"
+    pub flags: HashMap<String, bool>,
+}
+
+/// 认购配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Purchase {
+    /// 禁止投资人认购其绑定企业发行的票据 (防止自融)，测试环境可关闭
+    pub prevent_self_funding: bool,
+    /// 只允许认购审核通过 (Verified) 的企业发行的票据，测试环境可关闭
+    pub require_verified_issuer: bool,
+    /// 票据认购锁 TTL (毫秒)，认购与预约共用，需覆盖一次认购 (含数据库事务) 的耗时
+    pub lock_ttl_ms: u64,
+}
+
+impl Default for Purchase {
+    fn default() -> Self {
+        Self { prevent_self_funding: true, require_verified_issuer: true, lock_ttl_ms: 30_000 }
+    }
+}
+
+/// 接口限流配置 (固定窗口计数，计数保存在 Redis)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct RateLimit {
+    pub enabled: bool,
+    /// 计数窗口 (秒)
+    pub window_secs: u64,
+    /// 每个 IP 在窗口内可请求登录挑战的次数
+    pub challenge_per_ip: u32,
+    /// 每个钱包地址在窗口内可请求登录挑战的次数
+    pub challenge_per_address: u32,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self { enabled: true, window_secs: 60, challenge_per_ip: 20, challenge_per_address: 5 }
+    }
+}
+
+/// 区块链网络配置，签名私钥仍从环境变量 SIGNER_PRIVATE_KEY 读取。
+///
+/// 每一项都可以被环境变量 (含 .env) 覆盖，环境变量非空时优先于配置文件；
+/// 已配置 RPC 与票据合约地址时，RPC 不可用、链 ID 不一致或地址非法都会拒绝启动
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Chain {
+    /// 期望的链 ID，启动时与 RPC 返回的 eth_chainId 比对，不一致时拒绝启动；0 表示不校验。环境变量 CHAIN_ID 优先
+    pub chain_id: u64,
+    /// RPC 地址；环境变量 PHAROS_RPC_URL 优先
+    pub rpc_url: String,
+    /// 票据合约地址；环境变量 INVOICE_CONTRACT_ADDRESS 优先
+    pub invoice_contract: String,
+    /// 稳定币合约地址，键为币种符号 (如 USDT)；环境变量 STABLE_TOKEN_<SYMBOL> 优先
+    pub stable_tokens: HashMap<String, String>,
+}
+
+/// 合约调用重试配置 (仅重试网络/限流等临时错误，合约 revert 不重试)
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ContractRetry {
+    /// 最大尝试次数 (含首次)，1 表示不重试
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间 (毫秒)，之后按 2 的指数增长
+    pub base_delay_ms: u64,
+    /// 单次等待上限 (毫秒)
+    pub max_delay_ms: u64,
+}
+
+impl Default for ContractRetry {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 200, max_delay_ms: 5_000 }
+    }
+}
+
+/// 链上交易回执轮询配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TransactionPoller {
+    /// 交易所在区块之上需要的确认数 (含所在区块)，达到后标记为 Confirmed
+    pub confirmations: u64,
+    /// 轮询间隔 (秒)
+    pub interval_secs: u64,
+    /// 每轮最多检查的待确认交易数
+    pub batch_size: i64,
+}
+
+impl Default for TransactionPoller {
+    fn default() -> Self {
+        Self { confirmations: 2, interval_secs: 15, batch_size: 100 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TokenMint {
+    /// 扫描待铸造记录的间隔 (秒)
+    pub interval_secs: u64,
+    /// 每轮最多提交的铸造数
+    pub batch_size: i64,
+    /// 交易未发出时的最大尝试次数，用尽后标记为失败等待对账
+    pub max_attempts: u32,
+    /// 首次重试等待 (秒)，之后指数增长
+    pub base_backoff_secs: u64,
+}
+
+impl Default for TokenMint {
+    fn default() -> Self {
+        Self { interval_secs: 5, batch_size: 50, max_attempts: 5, base_backoff_secs: 30 }
+    }
+}
+
+/// ERC20 Transfer 事件索引配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct TransferIndexer {
+    /// 是否启动索引任务 (需要区块链连接)
+    pub enabled: bool,
+    /// 首次运行时开始扫描的区块
+    pub start_block: u64,
+    /// 每轮回退重新处理的区块数，用于纠正该深度内的链重组
+    pub confirmation_depth: u64,
+    /// 单次 eth_getLogs 查询的最大区块跨度
+    pub max_block_range: u64,
+    /// 追上链头后的轮询间隔 (秒)
+    pub interval_secs: u64,
+}
+
+impl Default for TransferIndexer {
+    fn default() -> Self {
+        Self { enabled: false, start_block: 0, confirmation_depth: 12, max_block_range: 2_000, interval_secs: 15 }
+    }
+}
+
+/// 分页配置
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Pagination {
+    /// 未指定时的默认分页大小
+    pub default_page_size: u64,
+    /// 全局最大分页大小
+    pub max_page_size: u64,
+    /// 绝对上限，任何接口 (包括覆盖配置) 都不能超过
+    pub hard_ceiling: u64,
+    /// 按接口覆盖的最大分页大小，key 为接口名称，如 "token.markets"
+    pub overrides: HashMap<String, u64>,
+}
+
+impl Default for Pagination {
+    fn default() -> Self {
+        Self {
+            default_page_size: 10,
+            max_page_size: 100,
+            hard_ceiling: 1000,
+            overrides: HashMap::new(),
+        }
+    }
+}
+
 /// Kafka 配置文件
 #[derive(Debug, Deserialize)]
 pub struct Kafka {
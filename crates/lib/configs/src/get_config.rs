@@ -27,6 +27,18 @@ impl Configs {
             Ok(s) => s,
             Err(e) => panic!("读取配置文件失败，错误信息：{}", e),
         };
-        toml::from_str(&cfg_contents).expect("解析配置文件错误")
+        let mut cfg: Configs = toml::from_str(&cfg_contents).expect("解析配置文件错误");
+        cfg.env = opt.env;
+        // 密钥优先从环境变量读取，避免写入配置文件
+        if let Some(secret) = std::env::var("JWT_SECRET").ok().filter(|s| !s.is_empty()) {
+            cfg.jwt.secret = secret;
+        }
+        if let Some(secret) = std::env::var("SESSION_SECRET").ok().filter(|s| !s.is_empty()) {
+            cfg.session.secret = secret;
+        }
+        if let Some(key) = std::env::var("WEBHOOK_SIGNING_KEY").ok().filter(|s| !s.is_empty()) {
+            cfg.webhook.signing_key = key;
+        }
+        cfg
     }
 }
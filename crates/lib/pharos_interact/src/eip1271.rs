@@ -0,0 +1,100 @@
+//! EIP-1271 合约钱包签名校验 (Gnosis Safe 等)
+//!
+//! 合约钱包没有私钥，ECDSA 恢复出的地址不是钱包地址；需要调用钱包合约的
+//! `isValidSignature(bytes32,bytes)`，返回 `0x1626ba7e` 即签名有效。
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use dotenv::dotenv;
+use ethers::prelude::*;
+use ethers::providers::{Http, Provider};
+use ethers::types::{Address, Bytes, H256};
+use std::env;
+
+abigen!(
+    ERC1271Wallet,
+    r#"[
+        function isValidSignature(bytes32 hash, bytes signature) external view returns (bytes4)
+    ]"#
+);
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`
+pub const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// 合约钱包签名校验
+#[async_trait::async_trait]
+pub trait SignatureValidator: Send + Sync {
+    /// `wallet` 合约是否认可对 `hash` 的签名。合约回滚 (未实现 EIP-1271 / 签名无效) 视为 false
+    async fn is_valid_signature(&self, wallet: Address, hash: H256, signature: Bytes) -> Result<bool>;
+}
+
+/// 通过只读 provider 调用钱包合约
+pub struct Eip1271Verifier<M: Middleware> {
+    client: Arc<M>,
+}
+
+impl<M: Middleware + 'static> Eip1271Verifier<M> {
+    pub fn new(client: Arc<M>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + Send + Sync + 'static> SignatureValidator for Eip1271Verifier<M> {
+    async fn is_valid_signature(&self, wallet: Address, hash: H256, signature: Bytes) -> Result<bool> {
+        let contract = ERC1271Wallet::new(wallet, self.client.clone());
+        match contract.is_valid_signature(hash.0, signature).call().await {
+            Ok(magic) => Ok(magic == EIP1271_MAGIC_VALUE),
+            Err(ContractError::Revert(data)) => {
+                tracing::warn!("isValidSignature reverted for {:?}: {}", wallet, data);
+                Ok(false)
+            }
+            Err(e) => Err(anyhow::anyhow!("isValidSignature call to {:?} failed: {}", wallet, e)),
+        }
+    }
+}
+
+/// 使用 PHAROS_RPC_URL 创建只读校验器 (不需要签名私钥)
+pub fn initialize_signature_verifier_from_env() -> Result<Eip1271Verifier<Provider<Http>>> {
+    dotenv().ok();
+    let rpc_url = env::var("PHAROS_RPC_URL").context("Failed to read PHAROS_RPC_URL from environment")?;
+    initialize_signature_verifier(&rpc_url)
+}
+
+/// 使用指定 RPC 地址创建只读校验器
+pub fn initialize_signature_verifier(rpc_url: &str) -> Result<Eip1271Verifier<Provider<Http>>> {
+    let provider = Provider::<Http>::try_from(rpc_url).context("Failed to create HTTP provider from RPC URL")?;
+    Ok(Eip1271Verifier::new(Arc::new(provider)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn abi_encoded_bytes4(value: [u8; 4]) -> Bytes {
+        let mut word = [0u8; 32];
+        word[..4].copy_from_slice(&value);
+        Bytes::from(word.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_magic_value_is_accepted() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(abi_encoded_bytes4(EIP1271_MAGIC_VALUE)).unwrap();
+        let verifier = Eip1271Verifier::new(Arc::new(provider));
+
+        let valid = verifier.is_valid_signature(Address::repeat_byte(0x11), H256::repeat_byte(0x22), Bytes::from(vec![1u8; 65])).await.unwrap();
+        assert!(valid);
+    }
+
+    #[tokio::test]
+    async fn test_other_return_value_is_rejected() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<Bytes, _>(abi_encoded_bytes4([0xff, 0xff, 0xff, 0xff])).unwrap();
+        let verifier = Eip1271Verifier::new(Arc::new(provider));
+
+        let valid = verifier.is_valid_signature(Address::repeat_byte(0x11), H256::repeat_byte(0x22), Bytes::from(vec![1u8; 65])).await.unwrap();
+        assert!(!valid);
+    }
+}
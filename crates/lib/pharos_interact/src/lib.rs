@@ -5,16 +5,31 @@ use ethers::prelude::*;
 use ethers::providers::{Http, Provider};
 use ethers::signers::{LocalWallet, Signer};
 use ethers::types::{Address, U256};
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::env;
-use std::sync::Arc; // Import anyhow Result, Context, and anyhow!
+use std::sync::{Arc, Mutex}; // Import anyhow Result, Context, and anyhow!
+use std::time::{Duration, Instant};
 
-use log::error;
+use tracing::error;
 use salvo_oapi::ToSchema;
 use common::domain::dto::invoice_dto::InvoiceDataDto;
 use common::domain::dto::query_invoice_dto::QueryParamsDto;
 use common::utils::get_time::get_current_timestamp_nanos;
 
+pub mod eip1271;
+#[cfg(any(test, feature = "test-support"))]
+pub mod mock;
+pub mod nonce;
+pub mod retry;
+pub mod revert;
+pub mod transfer_indexer;
+pub use eip1271::{initialize_signature_verifier, initialize_signature_verifier_from_env, Eip1271Verifier, SignatureValidator, EIP1271_MAGIC_VALUE};
+pub use nonce::NonceManager;
+pub use retry::{is_transient, retry, RetryConfig, TxPossiblySubmitted};
+pub use revert::{decode_revert, decode_revert_data, decode_revert_reason, extract_tx_hash, RevertError};
+pub use transfer_indexer::{RpcTransferSource, TransferEvent, TransferIndexer, TransferIndexerConfig, TransferStore, TransferSyncHandle, TransferSyncStatus};
+
 // Regenerate bindings using the updated ABI
 abigen!(
     InvoiceContractABI,   // Name of the generated module
@@ -22,10 +37,30 @@ abigen!(
     event_derives(serde::Deserialize, serde::Serialize)
 );
 
+// 代币批次合约只需要读取余额与精度
+abigen!(
+    ERC20Token,
+    r#"[
+        function balanceOf(address owner) external view returns (uint256)
+        function decimals() external view returns (uint8)
+    ]"#
+);
+
+// 企业钱包合约的所有者查询：Gnosis Safe 的 isOwner 与 OpenZeppelin Ownable 的 owner
+abigen!(
+    EnterpriseWallet,
+    r#"[
+        function isOwner(address owner) external view returns (bool)
+        function owner() external view returns (address)
+    ]"#
+);
+
 impl TryFrom<InvoiceDataDto> for InvoiceData {
     type Error = anyhow::Error; // Change associated error type to anyhow::Error
 
     fn try_from(val: InvoiceDataDto) -> Result<Self, Self::Error> {
+        // 金额为整数最小单位，零金额票据不允许上链
+        anyhow::ensure!(val.amount > 0, "Invoice {} amount must be positive", val.invoice_number);
         Ok(InvoiceData {
             invoice_number: val.invoice_number,
             payee: val.payee.parse::<Address>().context("Invalid payee address format")?,
@@ -57,6 +92,56 @@ impl From<InvoiceData> for InvoiceDataDto {
     }
 }
 
+/// 合约处于暂停状态时写操作返回的错误 (包装在 anyhow::Error 中，可通过 downcast_ref 识别)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContractPaused;
+
+impl std::fmt::Display for ContractPaused {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "contract paused")
+    }
+}
+
+impl std::error::Error for ContractPaused {}
+
+/// 判断错误是否由合约暂停引起
+pub fn is_contract_paused(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<ContractPaused>().is_some()
+}
+
+/// 预估 gas 时合约回滚 (交易提交后必然失败)，`0` 为解码后的 revert 原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContractReverted(pub RevertError);
+
+impl std::fmt::Display for ContractReverted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Contract call would revert: {}", self.0)
+    }
+}
+
+impl std::error::Error for ContractReverted {}
+
+/// 错误为 [`ContractReverted`] 时返回解码后的 revert 原因
+pub fn revert_error(err: &anyhow::Error) -> Option<&RevertError> {
+    err.downcast_ref::<ContractReverted>().map(|e| &e.0)
+}
+
+/// 错误为 [`ContractReverted`] 时返回可读的 revert 原因
+pub fn revert_reason(err: &anyhow::Error) -> Option<String> {
+    revert_error(err).map(|e| e.to_string())
+}
+
+/// 交易费用预估
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasEstimate {
+    /// 预估 gas 用量
+    pub gas_units: U256,
+    /// 最新区块的 base fee 加上节点建议的优先费 (不支持 EIP-1559 的链使用 gas price)，单位 wei
+    pub fee_per_gas: U256,
+    /// gas_units * fee_per_gas，单位 wei
+    pub estimated_fee: U256,
+}
+
 // --- Contract Interaction Traits ---
 // These traits define the capabilities of contract interaction
 
@@ -65,6 +150,25 @@ impl From<InvoiceData> for InvoiceDataDto {
 pub trait ContractQuerier {
     /// Query invoices based on filter parameters
     async fn query_invoices(&self, params: QueryParamsDto) -> Result<Vec<InvoiceDataDto>>;
+
+    /// 按票据编号读取合约中存储的票据，未上链时返回 `None`
+    async fn get_invoice(&self, invoice_number: String) -> Result<Option<InvoiceData>>;
+
+    /// Read the contract's paused state (OpenZeppelin `Pausable`)
+    async fn is_paused(&self) -> Result<bool>;
+
+    /// 以认购用户 `buyer` 为 msg.sender 预估 `purchaseShares(batch_id, amount)` 的 gas 与费用，合约回滚时返回 [`ContractReverted`]
+    async fn estimate_gas_for_purchase(&self, buyer: Address, batch_id: String, amount_str: String) -> Result<GasEstimate>;
+
+    /// 读取 ERC20 代币合约 `token` 中 `owner` 的余额 (`balanceOf`，最小单位)
+    async fn token_balance_of(&self, token: Address, owner: Address) -> Result<U256>;
+
+    /// 读取 ERC20 代币合约 `token` 的精度 (`decimals`)
+    async fn token_decimals(&self, token: Address) -> Result<u8>;
+
+    /// `account` 是否为企业钱包 `enterprise` 的签名人/所有者：EOA 企业钱包要求地址相同，
+    /// 合约钱包依次尝试 Safe `isOwner(account)` 和 Ownable `owner()`，两者都不支持时视为 false
+    async fn is_enterprise_signer(&self, enterprise: Address, account: Address) -> Result<bool>;
 }
 
 /// Trait for contract write operations that modify blockchain state
@@ -87,8 +191,34 @@ pub trait ContractWriter: ContractQuerier {
     /// Confirm a token batch issue
     async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>>;
 
-    /// Purchase shares from a token batch
-    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> Result<Option<TransactionReceipt>>;
+    /// 代投资人认购批次份额 (`purchaseSharesFor`)，代币铸造给 `recipient`。交易回滚时返回错误
+    async fn purchase_shares(&self, batch_id: String, recipient: String, amount_str: String) -> Result<Option<TransactionReceipt>>;
+
+    /// 企业还款后按持有人分配兑付资金，`amounts` 为稳定币最小单位，与 `holders` 一一对应。交易回滚时返回错误
+    async fn distribute_repayment(&self, batch_id: String, holders: Vec<String>, amounts: Vec<String>) -> Result<Option<TransactionReceipt>>;
+
+    /// 作废链上票据 (管理员撤销)，交易回滚时返回错误
+    async fn invalidate_invoice(&self, invoice_number: String) -> Result<Option<TransactionReceipt>>;
+}
+
+/// 已广播的交易 (尚未等待回执)
+#[derive(Debug, Clone, Copy)]
+pub struct BroadcastTx<'a> {
+    /// ContractWriter 的方法名，如 "purchase_shares"
+    pub operation: &'a str,
+    /// 业务标识 (批次 ID / 票据号)
+    pub reference: &'a str,
+    pub tx_hash: TxHash,
+    pub from: Option<Address>,
+    pub to: Option<Address>,
+    /// 交易代其操作的用户地址 (兑付持有人等)，平台自身的操作为空
+    pub accounts: &'a [Address],
+}
+
+/// 交易广播成功后、等待回执之前调用，用于登记待确认交易；回调内部处理自己的错误，不影响交易本身
+#[async_trait::async_trait]
+pub trait BroadcastObserver: Send + Sync {
+    async fn broadcast(&self, tx: BroadcastTx<'_>);
 }
 
 // --- Contract Interaction Logic ---
@@ -96,8 +226,20 @@ pub trait ContractWriter: ContractQuerier {
 pub struct InvoiceContract<M: Middleware> {
     contract: InvoiceContractABI<M>,
     client: Arc<M>, // Keep client if needed for direct calls, otherwise remove
+    retry: RetryConfig,
+    /// 客户端带签名账户时按账户在进程内分配 nonce
+    nonces: Option<NonceManager>,
+    observer: Option<Arc<dyn BroadcastObserver>>,
+    /// 配置中的稳定币合约地址，键为大写币种符号
+    stable_tokens: HashMap<String, Address>,
+    /// 写操作前的暂停检查在 `paused_ttl` 内复用上次读到的暂停状态
+    paused_ttl: Duration,
+    paused_cache: Mutex<Option<(bool, Instant)>>,
 }
 
+/// 暂停状态默认缓存时长
+pub const DEFAULT_PAUSED_CACHE_TTL: Duration = Duration::from_secs(5);
+
 // Implement ContractQuerier for InvoiceContract
 #[async_trait::async_trait]
 impl<M: Middleware + Send + Sync + 'static> ContractQuerier for InvoiceContract<M> {
@@ -121,24 +263,163 @@ impl<M: Middleware + Send + Sync + 'static> ContractQuerier for InvoiceContract<
             check_valid: params_dto.is_valid.unwrap_or(false), // Assuming check_valid corresponds to is_valid query?
         };
 
-        log::info!("Query_Invoices parameters: {:?}", params.clone());
+        tracing::info!("Query_Invoices parameters: {:?}", params.clone());
         // Call the contract
-        let result: QueryResult = self.contract.query_invoices(params).call().await.map_err(|e| {
-            error!("Error calling queryInvoices: {}", e);
-            anyhow!("Contract query failed: {}", e)
-        })?;
+        let result: QueryResult = retry::retry(&self.retry, "queryInvoices", || {
+            let params = params.clone();
+            async move {
+                self.contract.query_invoices(params).call().await.map_err(|e| {
+                    error!("Error calling queryInvoices: {}", e);
+                    anyhow!("Contract query failed: {}", e)
+                })
+            }
+        })
+        .await?;
 
         // Convert internal Vec<InvoiceData> to Vec<InvoiceDataDto>
         let result_dto: Vec<InvoiceDataDto> = result.invoices.into_iter().map(InvoiceDataDto::from).collect();
 
         Ok(result_dto)
     }
+
+    async fn get_invoice(&self, invoice_number: String) -> Result<Option<InvoiceData>> {
+        // 合约没有单张票据的读取接口，按编号过滤 queryInvoices 后取编号完全一致的一条
+        let params = QueryParams {
+            batch_id: "".to_string(),
+            payee: Address::zero(),
+            invoice_number: invoice_number.clone(),
+            payer: Address::zero(),
+            check_valid: false,
+        };
+        let result: QueryResult = retry::retry(&self.retry, "queryInvoices", || {
+            let params = params.clone();
+            async move {
+                self.contract.query_invoices(params).call().await.map_err(|e| {
+                    error!("Error calling queryInvoices for invoice '{}': {}", invoice_number, e);
+                    anyhow!("Contract query failed: {}", e)
+                })
+            }
+        })
+        .await?;
+        Ok(result.invoices.into_iter().find(|invoice| invoice.invoice_number == invoice_number))
+    }
+
+    async fn is_paused(&self) -> Result<bool> {
+        let paused = retry::retry(&self.retry, "paused", || async move {
+            self.contract.paused().call().await.map_err(|e| {
+                error!("Error calling paused: {}", e);
+                anyhow!("Contract query failed: {}", e)
+            })
+        })
+        .await?;
+        *self.paused_cache.lock().unwrap() = Some((paused, Instant::now()));
+        Ok(paused)
+    }
+
+    async fn estimate_gas_for_purchase(&self, buyer: Address, batch_id: String, amount_str: String) -> Result<GasEstimate> {
+        let amount = U256::from_dec_str(&amount_str).context("Invalid amount format")?;
+
+        let gas_units = retry::retry(&self.retry, "estimateGas(purchaseShares)", || {
+            let batch_id = batch_id.clone();
+            async move {
+                // 余额、授权等检查依赖 msg.sender，必须按用户自己的地址预估
+                match self.contract.purchase_shares(batch_id.clone(), amount).from(buyer).estimate_gas().await {
+                    Ok(gas) => Ok(gas),
+                    Err(e) => {
+                        let message = e.to_string();
+                        if matches!(e, ContractError::Revert(_)) || message.contains("execution reverted") {
+                            let reason = decode_revert(&message).unwrap_or(RevertError::Unknown(None));
+                            tracing::warn!("purchaseShares for batch '{}' would revert: {}", batch_id, reason);
+                            return Err(anyhow::Error::new(ContractReverted(reason)));
+                        }
+                        error!("Error estimating gas for purchaseShares batch '{}': {}", batch_id, e);
+                        Err(anyhow!("Failed to estimate gas for purchaseShares: {}", e))
+                    }
+                }
+            }
+        })
+        .await?;
+
+        let fee_per_gas = retry::retry(&self.retry, "currentFeePerGas", || async move {
+            let block = self.client.get_block(BlockNumber::Latest).await.map_err(|e| anyhow!("Failed to fetch latest block: {}", e))?;
+            match block.and_then(|b| b.base_fee_per_gas) {
+                Some(base_fee) => {
+                    let priority_fee: U256 = self.client.provider().request("eth_maxPriorityFeePerGas", ()).await
+                        .map_err(|e| anyhow!("Failed to fetch max priority fee: {}", e))?;
+                    Ok(base_fee.saturating_add(priority_fee))
+                }
+                None => self.client.get_gas_price().await.map_err(|e| anyhow!("Failed to fetch gas price: {}", e)),
+            }
+        })
+        .await?;
+
+        Ok(GasEstimate { gas_units, fee_per_gas, estimated_fee: gas_units.saturating_mul(fee_per_gas) })
+    }
+
+    async fn token_balance_of(&self, token: Address, owner: Address) -> Result<U256> {
+        let erc20 = ERC20Token::new(token, self.client.clone());
+        retry::retry(&self.retry, "balanceOf", || {
+            let erc20 = erc20.clone();
+            async move {
+                erc20.balance_of(owner).call().await.map_err(|e| {
+                    error!("Error calling balanceOf({:?}) on token {:?}: {}", owner, token, e);
+                    anyhow!("Contract query failed: {}", e)
+                })
+            }
+        })
+        .await
+    }
+
+    async fn token_decimals(&self, token: Address) -> Result<u8> {
+        let erc20 = ERC20Token::new(token, self.client.clone());
+        retry::retry(&self.retry, "decimals", || {
+            let erc20 = erc20.clone();
+            async move {
+                erc20.decimals().call().await.map_err(|e| {
+                    error!("Error calling decimals() on token {:?}: {}", token, e);
+                    anyhow!("Contract query failed: {}", e)
+                })
+            }
+        })
+        .await
+    }
+
+    async fn is_enterprise_signer(&self, enterprise: Address, account: Address) -> Result<bool> {
+        if enterprise == account {
+            return Ok(true);
+        }
+        let code = retry::retry(&self.retry, "getCode", || async move {
+            self.client.get_code(enterprise, None).await.map_err(|e| anyhow!("Failed to fetch code of {:?}: {}", enterprise, e))
+        })
+        .await?;
+        // 地址不同的 EOA 企业钱包不可能授权其他账户
+        if code.is_empty() {
+            return Ok(false);
+        }
+
+        let wallet = EnterpriseWallet::new(enterprise, self.client.clone());
+        match wallet.is_owner(account).call().await {
+            Ok(is_owner) => return Ok(is_owner),
+            Err(ContractError::Revert(_)) => {}
+            Err(e) => return Err(anyhow!("isOwner call to {:?} failed: {}", enterprise, e)),
+        }
+        match wallet.owner().call().await {
+            Ok(owner) => Ok(owner == account),
+            Err(ContractError::Revert(data)) => {
+                tracing::warn!("Enterprise wallet {:?} supports neither isOwner nor owner: {}", enterprise, data);
+                Ok(false)
+            }
+            Err(e) => Err(anyhow!("owner call to {:?} failed: {}", enterprise, e)),
+        }
+    }
 }
 
 // Implement ContractWriter for InvoiceContract
 #[async_trait::async_trait]
 impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M> {
     async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> Result<Option<TransactionReceipt>> {
+        self.ensure_not_paused().await?;
+        let reference = invoices.iter().map(|i| i.invoice_number.as_str()).collect::<Vec<_>>().join(",");
         let invoice_data_vec: Result<Vec<InvoiceData>, _> = invoices.into_iter().map(InvoiceData::try_from).collect();
 
         let invoice_data_vec = invoice_data_vec.context("Failed to parse one or more invoice data DTOs")?;
@@ -148,16 +429,19 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
         let data_for_send = invoice_data_vec; // Original vec will be moved here
 
         // 1. Estimate required gas
-        let gas_estimate = self.contract.batch_create_invoices(data_for_estimation)
-            .estimate_gas()
-            .await
-            .map_err(|e| {
-                error!("Error estimating gas for batchCreateInvoices: {}", e);
-                anyhow!("Failed to estimate gas (potential revert): {}", e)
-            })?;
+        let gas_estimate = retry::retry(&self.retry, "estimateGas(batchCreateInvoices)", || {
+            let data = data_for_estimation.clone();
+            async move {
+                self.contract.batch_create_invoices(data).estimate_gas().await.map_err(|e| {
+                    error!("Error estimating gas for batchCreateInvoices: {}", e);
+                    anyhow!("Failed to estimate gas (potential revert): {}", e)
+                })
+            }
+        })
+        .await?;
 
         // 2. Log estimate for debugging
-        log::warn!("Estimated gas for batchCreateInvoices: {}", gas_estimate);
+        tracing::warn!("Estimated gas for batchCreateInvoices: {}", gas_estimate);
 
         // 3. Set gas limit with a buffer
         let gas_limit = gas_estimate * 1; // Add a 25% buffer to the estimate
@@ -171,14 +455,12 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
             .batch_create_invoices(data_for_send) // Move original data here
             .gas(gas_limit); // Set the calculated gas limit
 
-        // 5. Send the transaction using the prepared call object
-        let pending_tx_result = call.send().await; // Call send().await separately
-
-        // 6. Handle potential errors during the send phase
-        let pending_tx = pending_tx_result.map_err(|e| {
+        // 5. Send the transaction using the prepared call object (nonce 固定，临时错误重发同一笔交易)
+        let tx_hash = self.send_with_retry("batch_create_invoices", &reference, &[], call.tx).await.map_err(|e| {
             error!("Error sending batchCreateInvoices transaction: {}", e);
-            anyhow!("Failed to send transaction: {}", e)
+            e.context("Failed to send transaction")
         })?;
+        let pending_tx = PendingTransaction::new(tx_hash, self.client.provider());
 
     
         // 5. Wait for the transaction receipt
@@ -191,7 +473,7 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
                 // Transaction confirmed successfully
                 // Check receipt status
                 if receipt.status == Some(1.into()) {
-                    log::info!(
+                    tracing::info!(
                         "batchCreateInvoices transaction successful! Hash: {:?}, Block: {:?}",
                         receipt.transaction_hash,
                         receipt.block_number.unwrap_or_default()
@@ -233,6 +515,7 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
         max_term_str: String,
         interest_rate_str: String,
     ) -> Result<Option<TransactionReceipt>> {
+        self.ensure_not_paused().await?;
         // Parse inputs
         let stable_token = stable_token_address.parse::<Address>().context("Invalid stable token address")?;
         let min_term = U256::from_dec_str(&min_term_str).context("Invalid min term format")?;
@@ -247,41 +530,103 @@ impl<M: Middleware + Send + Sync + 'static> ContractWriter for InvoiceContract<M
             max_term,
             interest_rate,
         );
-        let pending_tx = tx.send().await.map_err(|e| {
+        let tx_hash = self.send_with_retry("create_token_batch", &batch_id, &[], tx.tx).await.map_err(|e| {
             error!("Error sending createTokenBatch transaction for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to send createTokenBatch transaction: {}", e)
+            e.context("Failed to send createTokenBatch transaction")
         })?;
-        pending_tx.await.map_err(|e| {
+        PendingTransaction::new(tx_hash, self.client.provider()).await.map_err(|e| {
             error!("Error waiting for createTokenBatch transaction receipt for batch '{}': {}", batch_id, e);
             anyhow!("Failed to get createTokenBatch transaction receipt: {}", e)
         })
     }
 
     async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>> {
+        self.ensure_not_paused().await?;
         let tx = self.contract.confirm_token_batch_issue(batch_id.clone());
-        let pending_tx = tx.send().await.map_err(|e| {
+        let tx_hash = self.send_with_retry("confirm_token_batch_issue", &batch_id, &[], tx.tx).await.map_err(|e| {
             error!("Error sending confirmTokenBatchIssue transaction for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to send confirmTokenBatchIssue transaction: {}", e)
+            e.context("Failed to send confirmTokenBatchIssue transaction")
         })?;
-        pending_tx.await.map_err(|e| {
+        PendingTransaction::new(tx_hash, self.client.provider()).await.map_err(|e| {
             error!("Error waiting for confirmTokenBatchIssue transaction receipt for batch '{}': {}", batch_id, e);
             anyhow!("Failed to get confirmTokenBatchIssue transaction receipt: {}", e)
         })
     }
 
-    async fn purchase_shares(&self, batch_id: String, amount_str: String) -> Result<Option<TransactionReceipt>> {
+    async fn purchase_shares(&self, batch_id: String, recipient: String, amount_str: String) -> Result<Option<TransactionReceipt>> {
+        self.ensure_not_paused().await?;
         // Parse amount
         let amount = U256::from_dec_str(&amount_str).context("Invalid amount format")?;
+        let recipient = recipient.parse::<Address>().with_context(|| format!("Invalid recipient address: {}", recipient))?;
 
-        let tx = self.contract.purchase_shares(batch_id.clone(), amount);
-        let pending_tx = tx.send().await.map_err(|e| {
-            error!("Error sending purchaseShares transaction for batch '{}' amount '{}': {}", batch_id, amount_str, e);
-            anyhow!("Failed to send purchaseShares transaction: {}", e)
+        let tx = self.contract.purchase_shares_for(batch_id.clone(), recipient, amount);
+        let tx_hash = self.send_with_retry("purchase_shares", &batch_id, &[recipient], tx.tx).await.map_err(|e| {
+            error!("Error sending purchaseSharesFor transaction for batch '{}' recipient {:?} amount '{}': {}", batch_id, recipient, amount_str, e);
+            e.context("Failed to send purchaseSharesFor transaction")
         })?;
-        pending_tx.await.map_err(|e| {
-            error!("Error waiting for purchaseShares transaction receipt for batch '{}': {}", batch_id, e);
-            anyhow!("Failed to get purchaseShares transaction receipt: {}", e)
-        })
+        let receipt = PendingTransaction::new(tx_hash, self.client.provider()).await.map_err(|e| {
+            error!("Error waiting for purchaseSharesFor transaction receipt for batch '{}': {}", batch_id, e);
+            // 交易已广播，带上哈希供调用方对账 (不能重新提交)
+            anyhow!("Failed to get purchaseSharesFor transaction receipt, transaction_hash: {:?}: {}", tx_hash, e)
+        })?;
+        // 回滚的交易没有铸造代币，不能标记为已铸造
+        if let Some(receipt) = &receipt {
+            if receipt.status != Some(1.into()) {
+                return Err(anyhow!("purchaseSharesFor reverted (status 0), transaction_hash: {:?}", receipt.transaction_hash));
+            }
+        }
+        Ok(receipt)
+    }
+
+    async fn distribute_repayment(&self, batch_id: String, holders: Vec<String>, amounts: Vec<String>) -> Result<Option<TransactionReceipt>> {
+        if holders.len() != amounts.len() {
+            return Err(anyhow!("distributeRepayment holders ({}) and amounts ({}) length mismatch", holders.len(), amounts.len()));
+        }
+        self.ensure_not_paused().await?;
+        let holders = holders
+            .iter()
+            .map(|h| h.parse::<Address>().with_context(|| format!("Invalid holder address: {}", h)))
+            .collect::<Result<Vec<_>>>()?;
+        let amounts = amounts
+            .iter()
+            .map(|a| U256::from_dec_str(a).with_context(|| format!("Invalid payout amount: {}", a)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let tx = self.contract.distribute_repayment(batch_id.clone(), holders.clone(), amounts);
+        let tx_hash = self.send_with_retry("distribute_repayment", &batch_id, &holders, tx.tx).await.map_err(|e| {
+            error!("Error sending distributeRepayment transaction for batch '{}': {}", batch_id, e);
+            e.context("Failed to send distributeRepayment transaction")
+        })?;
+        let receipt = PendingTransaction::new(tx_hash, self.client.provider()).await.map_err(|e| {
+            error!("Error waiting for distributeRepayment transaction receipt for batch '{}': {}", batch_id, e);
+            anyhow!("Failed to get distributeRepayment transaction receipt for tx {:?}: {}", tx_hash, e)
+        })?;
+        // 兑付以回执成功为准，回滚的交易不能标记为已兑付
+        if let Some(receipt) = &receipt {
+            if receipt.status != Some(1.into()) {
+                return Err(anyhow!("distributeRepayment reverted (status 0), transaction_hash: {:?}", receipt.transaction_hash));
+            }
+        }
+        Ok(receipt)
+    }
+
+    async fn invalidate_invoice(&self, invoice_number: String) -> Result<Option<TransactionReceipt>> {
+        self.ensure_not_paused().await?;
+        let tx = self.contract.invalidate_invoice(invoice_number.clone());
+        let tx_hash = self.send_with_retry("invalidate_invoice", &invoice_number, &[], tx.tx).await.map_err(|e| {
+            error!("Error sending invalidateInvoice transaction for invoice '{}': {}", invoice_number, e);
+            e.context("Failed to send invalidateInvoice transaction")
+        })?;
+        let receipt = PendingTransaction::new(tx_hash, self.client.provider()).await.map_err(|e| {
+            error!("Error waiting for invalidateInvoice transaction receipt for invoice '{}': {}", invoice_number, e);
+            anyhow!("Failed to get invalidateInvoice transaction receipt for tx {:?}: {}", tx_hash, e)
+        })?;
+        if let Some(receipt) = &receipt {
+            if receipt.status != Some(1.into()) {
+                return Err(anyhow!("invalidateInvoice reverted (status 0), transaction_hash: {:?}", receipt.transaction_hash));
+            }
+        }
+        Ok(receipt)
     }
 }
 
@@ -289,14 +634,265 @@ impl<M: Middleware + Send + Sync + 'static> InvoiceContract<M> {
     /// Creates a new instance of the InvoiceContract wrapper.
     pub fn new(address: Address, client: Arc<M>) -> Self {
         let contract = InvoiceContractABI::new(address, client.clone());
-        Self { contract, client }
+        let nonces = client.default_sender().map(NonceManager::new);
+        Self {
+            contract,
+            client,
+            retry: RetryConfig::default(),
+            nonces,
+            observer: None,
+            stable_tokens: HashMap::new(),
+            paused_ttl: DEFAULT_PAUSED_CACHE_TTL,
+            paused_cache: Mutex::new(None),
+        }
+    }
+
+    /// 设置暂停状态的缓存时长，为 0 时每次写操作前都查询合约
+    pub fn with_paused_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.paused_ttl = ttl;
+        self
+    }
+
+    /// 设置稳定币合约地址 (键为币种符号)
+    pub fn with_stable_tokens(mut self, stable_tokens: HashMap<String, Address>) -> Self {
+        self.stable_tokens = stable_tokens.into_iter().map(|(symbol, address)| (symbol.to_uppercase(), address)).collect();
+        self
+    }
+
+    /// 按币种符号 (不区分大小写) 查找配置的稳定币合约地址
+    pub fn stable_token(&self, symbol: &str) -> Option<Address> {
+        self.stable_tokens.get(&symbol.to_uppercase()).copied()
+    }
+
+    /// 交易广播后通知 `observer` (如登记待确认交易)
+    pub fn with_broadcast_observer(mut self, observer: Arc<dyn BroadcastObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    /// 替换合约调用的重试策略
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Address of the deployed invoice contract
+    pub fn address(&self) -> Address {
+        self.contract.address()
+    }
+
+    /// 合约使用的 provider / 签名中间件，可用于查询回执等非合约调用
+    pub fn client(&self) -> Arc<M> {
+        self.client.clone()
+    }
+
+    /// 写操作前检查暂停状态，暂停时直接返回 [`ContractPaused`]，避免提交必然回滚的交易。
+    /// 缓存未过期时不再查询合约
+    async fn ensure_not_paused(&self) -> Result<()> {
+        let cached = *self.paused_cache.lock().unwrap();
+        let paused = match cached.filter(|(_, at)| at.elapsed() < self.paused_ttl) {
+            Some((paused, _)) => paused,
+            None => self.is_paused().await?,
+        };
+        if paused {
+            tracing::warn!("Contract is paused, rejecting write operation");
+            return Err(anyhow::Error::new(ContractPaused));
+        }
+        Ok(())
+    }
+
+    /// 填充 nonce / gas 后广播交易，返回交易哈希。
+    ///
+    /// nonce 由 [`NonceManager`] 分配 (没有签名账户时由 `fill_transaction` 查询)，之后的重试广播的是同一笔交易；
+    /// 节点报告 `already known` 时交易已在交易池中，按本地签名算出交易哈希照常返回；
+    /// 重试时节点报告 nonce 已被使用，说明之前的某次广播已经到达节点，返回 [`TxPossiblySubmitted`] 而不是再提交一笔新交易。
+    /// 交易未发出时回收 nonce，无法确定或与节点不一致时下次重新同步。广播成功后先通知 [`BroadcastObserver`] 再返回。
+    async fn send_with_retry(&self, operation: &str, reference: &str, accounts: &[Address], mut tx: TypedTransaction) -> Result<TxHash> {
+        let nonce = match &self.nonces {
+            Some(nonces) => Some(nonces.reserve(self.client.as_ref()).await?),
+            None => None,
+        };
+        if let Some(nonce) = nonce {
+            tx.set_nonce(nonce);
+        }
+
+        let filled = retry::retry(&self.retry, operation, || {
+            let mut tx = tx.clone();
+            async move {
+                self.client.fill_transaction(&mut tx, None).await.map_err(|e| anyhow!("Failed to fill {} transaction: {}", operation, e))?;
+                Ok(tx)
+            }
+        })
+        .await;
+        let tx = match filled {
+            Ok(tx) => tx,
+            Err(e) => {
+                // 交易没有发出，nonce 可回收
+                if let (Some(nonces), Some(nonce)) = (&self.nonces, nonce) {
+                    nonces.release(nonce).await;
+                }
+                return Err(e);
+            }
+        };
+
+        let mut attempt = 0u32;
+        let sent = retry::retry(&self.retry, operation, || {
+            attempt += 1;
+            let is_retry = attempt > 1;
+            let tx = tx.clone();
+            async move {
+                match self.client.send_transaction(tx.clone(), None).await {
+                    Ok(pending) => Ok(pending.tx_hash()),
+                    Err(e) if retry::is_already_known(&e.to_string()) => match self.signed_tx_hash(&tx).await {
+                        Ok(tx_hash) => {
+                            tracing::warn!("{} broadcast reported already known, using local tx hash {:?}", operation, tx_hash);
+                            Ok(tx_hash)
+                        }
+                        Err(sign_err) => {
+                            tracing::warn!("{} broadcast reported already known but tx hash is unavailable: {}", operation, sign_err);
+                            Err(anyhow::Error::new(TxPossiblySubmitted(operation.to_string())))
+                        }
+                    },
+                    Err(e) if is_retry && retry::is_nonce_consumed(&e.to_string()) => {
+                        tracing::warn!("{} rebroadcast rejected ({}), an earlier attempt was likely accepted", operation, e);
+                        Err(anyhow::Error::new(TxPossiblySubmitted(operation.to_string())))
+                    }
+                    Err(e) => Err(anyhow!("{}", e)),
+                }
+            }
+        })
+        .await;
+
+        if let (Err(e), Some(nonces), Some(nonce)) = (&sent, &self.nonces, nonce) {
+            let message = format!("{:#}", e);
+            if e.downcast_ref::<TxPossiblySubmitted>().is_some() {
+                // nonce 已被之前的广播使用
+            } else if retry::is_nonce_consumed(&message) || is_transient(e) {
+                // 本地计数与节点不一致，或无法确定交易是否到达节点
+                tracing::warn!("{} failed with nonce {}, resyncing nonce from node: {}", operation, nonce, message);
+                nonces.resync().await;
+            } else {
+                nonces.release(nonce).await;
+            }
+        }
+        if let (Ok(tx_hash), Some(observer)) = (&sent, &self.observer) {
+            observer
+                .broadcast(BroadcastTx { operation, reference, tx_hash: *tx_hash, from: tx.from().copied(), to: tx.to_addr().copied(), accounts })
+                .await;
+        }
+        sent
     }
 
-    // No method implementations here - they're all in the trait impls
+    /// 对已填充的交易签名并计算哈希。签名是确定性的 (RFC 6979)，与广播出去的交易哈希一致；
+    /// 没有本地签名账户时返回错误
+    async fn signed_tx_hash(&self, tx: &TypedTransaction) -> Result<TxHash> {
+        let from = tx.from().copied().or_else(|| self.client.default_sender()).ok_or_else(|| anyhow!("Transaction has no sender"))?;
+        let signature = self.client.sign_transaction(tx, from).await.map_err(|e| anyhow!("Failed to sign transaction: {}", e))?;
+        Ok(tx.hash(&signature))
+    }
 }
 
 // --- Initialization ---
 
+/// RPC 返回的链 ID 与配置不一致 (例如测试网配置连到了主网)，此时不应启动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainIdMismatch {
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl std::fmt::Display for ChainIdMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "RPC reports chain ID {} but chain ID {} is configured", self.actual, self.expected)
+    }
+}
+
+impl std::error::Error for ChainIdMismatch {}
+
+/// 链连接参数
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChainSettings {
+    /// 期望的链 ID，0 表示不校验
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub invoice_contract: String,
+    /// 稳定币合约地址，键为币种符号 (如 USDT)
+    pub stable_tokens: HashMap<String, String>,
+}
+
+/// 覆盖稳定币地址的环境变量前缀，如 STABLE_TOKEN_USDT
+const STABLE_TOKEN_ENV_PREFIX: &str = "STABLE_TOKEN_";
+
+impl ChainSettings {
+    /// 以配置文件的值为基础，环境变量 (含 .env) 优先：CHAIN_ID / PHAROS_RPC_URL / INVOICE_CONTRACT_ADDRESS /
+    /// STABLE_TOKEN_<SYMBOL>。空值视为未设置；CHAIN_ID 不是整数时返回错误
+    pub fn with_env_overrides(self) -> Result<Self> {
+        dotenv().ok();
+        self.with_overrides(env::vars())
+    }
+
+    fn with_overrides(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self> {
+        for (key, value) in vars.into_iter().filter(|(_, v)| !v.is_empty()) {
+            match key.as_str() {
+                "CHAIN_ID" => self.chain_id = value.parse().with_context(|| format!("Invalid CHAIN_ID: {}", value))?,
+                "PHAROS_RPC_URL" => self.rpc_url = value,
+                "INVOICE_CONTRACT_ADDRESS" => self.invoice_contract = value,
+                _ => {
+                    if let Some(symbol) = key.strip_prefix(STABLE_TOKEN_ENV_PREFIX).filter(|s| !s.is_empty()) {
+                        self.stable_tokens.insert(symbol.to_uppercase(), value);
+                    }
+                }
+            }
+        }
+        Ok(self)
+    }
+
+    /// RPC 地址与票据合约地址均已配置；未配置时服务以无链上能力的方式启动
+    pub fn is_configured(&self) -> bool {
+        !self.rpc_url.is_empty() && !self.invoice_contract.is_empty()
+    }
+
+    /// 解析稳定币地址，币种符号统一为大写；任一地址非法时返回错误
+    pub fn stable_token_addresses(&self) -> Result<HashMap<String, Address>> {
+        self.stable_tokens
+            .iter()
+            .map(|(symbol, address)| {
+                let parsed = address.parse::<Address>().with_context(|| format!("Invalid stable token address for {}: {}", symbol, address))?;
+                Ok((symbol.to_uppercase(), parsed))
+            })
+            .collect()
+    }
+}
+
+/// 查询 RPC 的 eth_chainId，与 `expected` 不一致时返回 [`ChainIdMismatch`] (包装在 anyhow::Error 中)。
+/// `expected` 为 0 时只返回实际链 ID
+pub async fn ensure_chain_id<M: Middleware>(client: &M, expected: u64) -> Result<u64> {
+    let actual = client.get_chainid().await.map_err(|e| anyhow!("Failed to get chain ID from provider: {}", e))?.as_u64();
+    if expected != 0 && actual != expected {
+        return Err(ChainIdMismatch { expected, actual }.into());
+    }
+    Ok(actual)
+}
+
+/// 按配置连接区块链并创建票据合约实例，签名私钥从环境变量 SIGNER_PRIVATE_KEY 读取。
+/// RPC 不可用、链 ID 不一致 ([`ChainIdMismatch`]) 或任一合约地址非法时返回错误
+pub async fn initialize_contract(settings: &ChainSettings) -> Result<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>> {
+    dotenv().ok();
+    anyhow::ensure!(!settings.rpc_url.is_empty(), "RPC URL is not configured (chain.rpc_url or PHAROS_RPC_URL)");
+    anyhow::ensure!(!settings.invoice_contract.is_empty(), "Invoice contract address is not configured (chain.invoice_contract or INVOICE_CONTRACT_ADDRESS)");
+    let private_key_str = env::var("SIGNER_PRIVATE_KEY").context("Failed to read SIGNER_PRIVATE_KEY from environment")?;
+
+    let provider = Provider::<Http>::try_from(settings.rpc_url.as_str()).context("Failed to create HTTP provider from RPC URL")?;
+    let chain_id = ensure_chain_id(&provider, settings.chain_id).await?;
+    let wallet = private_key_str.parse::<LocalWallet>().context("Failed to parse private key")?.with_chain_id(chain_id);
+    let client = Arc::new(SignerMiddleware::new(provider, wallet));
+    let contract_address = settings.invoice_contract.parse::<Address>().context("Failed to parse contract address")?;
+    let stable_tokens = settings.stable_token_addresses()?;
+
+    Ok(InvoiceContract::new(contract_address, client).with_stable_tokens(stable_tokens))
+}
+
+
 /// Initializes a connection to the blockchain and creates an InvoiceContract instance.
 ///
 /// Reads configuration (RPC URL, contract address, private key) from environment variables.
@@ -310,17 +906,183 @@ impl<M: Middleware + Send + Sync + 'static> InvoiceContract<M> {
 /// Returns an error if environment variables are missing or invalid, or if connection
 /// to the blockchain fails.
 pub async fn initialize_contract_from_env() -> Result<InvoiceContract<SignerMiddleware<Provider<Http>, LocalWallet>>> {
-    dotenv().ok(); // Load .env file
-    let rpc_url = env::var("PHAROS_RPC_URL").context("Failed to read PHAROS_RPC_URL from environment")?;
-    let contract_address_str = env::var("INVOICE_CONTRACT_ADDRESS").context("Failed to read INVOICE_CONTRACT_ADDRESS from environment")?;
-    let private_key_str = env::var("SIGNER_PRIVATE_KEY").context("Failed to read SIGNER_PRIVATE_KEY from environment")?;
+    initialize_contract(&ChainSettings::default().with_env_overrides()?).await
+}
 
-    let provider = Provider::<Http>::try_from(rpc_url).context("Failed to create HTTP provider from RPC URL")?;
-    let chain_id = provider.get_chainid().await.context("Failed to get chain ID from provider")?.as_u64();
-    let wallet = private_key_str.parse::<LocalWallet>().context("Failed to parse private key")?.with_chain_id(chain_id);
-    let client = Arc::new(SignerMiddleware::new(provider, wallet));
-    let contract_address = contract_address_str.parse::<Address>().context("Failed to parse contract address")?;
 
-    Ok(InvoiceContract::new(contract_address, client))
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::{encode, Token};
+    use ethers::providers::{JsonRpcError, MockResponse};
+
+    #[tokio::test]
+    async fn test_startup_rejects_mismatched_chain_id() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<U256, _>(U256::from(1)).unwrap();
+        let err = ensure_chain_id(&provider, 50002).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ChainIdMismatch>(), Some(&ChainIdMismatch { expected: 50002, actual: 1 }));
+        assert!(err.to_string().contains("50002"));
+
+        mock.push::<U256, _>(U256::from(50002)).unwrap();
+        assert_eq!(ensure_chain_id(&provider, 50002).await.unwrap(), 50002);
+
+        // 未配置链 ID 时不校验
+        mock.push::<U256, _>(U256::from(1)).unwrap();
+        assert_eq!(ensure_chain_id(&provider, 0).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_startup_fails_when_rpc_unreachable() {
+        // mock 没有排队的响应，相当于 RPC 请求失败
+        let (provider, _mock) = Provider::mocked();
+        let err = ensure_chain_id(&provider, 50002).await.unwrap_err();
+        assert!(err.downcast_ref::<ChainIdMismatch>().is_none());
+        assert!(err.to_string().contains("Failed to get chain ID"));
+    }
+
+    #[test]
+    fn test_env_overrides_config_values() {
+        let config = ChainSettings {
+            chain_id: 50002,
+            rpc_url: "https://config-rpc".to_string(),
+            invoice_contract: "0x1111111111111111111111111111111111111111".to_string(),
+            stable_tokens: HashMap::from([("usdt".to_string(), "0x2222222222222222222222222222222222222222".to_string())]),
+        };
+        let vars = [
+            ("CHAIN_ID", "5003"),
+            ("PHAROS_RPC_URL", "https://env-rpc"),
+            ("INVOICE_CONTRACT_ADDRESS", ""),
+            ("STABLE_TOKEN_HKDC", "0x3333333333333333333333333333333333333333"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let settings = config.clone().with_overrides(vars).unwrap();
+        assert_eq!(settings.chain_id, 5003);
+        assert_eq!(settings.rpc_url, "https://env-rpc");
+        // 空的环境变量不覆盖配置
+        assert_eq!(settings.invoice_contract, config.invoice_contract);
+        let tokens = settings.stable_token_addresses().unwrap();
+        assert_eq!(tokens["USDT"], Address::repeat_byte(0x22));
+        assert_eq!(tokens["HKDC"], Address::repeat_byte(0x33));
+
+        assert!(config.clone().with_overrides([("CHAIN_ID".to_string(), "mainnet".to_string())]).is_err());
+        let bad = ChainSettings { stable_tokens: HashMap::from([("USDT".to_string(), "0x12".to_string())]), ..config };
+        assert!(bad.stable_token_addresses().is_err());
+    }
 
+    #[tokio::test]
+    async fn test_is_paused_retries_rate_limited_rpc() {
+        let (provider, mock) = Provider::mocked();
+        // MockProvider 按后进先出返回：先压入成功结果，再压入两次限流错误
+        mock.push::<Bytes, _>(Bytes::from([0u8; 32].to_vec())).unwrap();
+        for _ in 0..2 {
+            mock.push_response(MockResponse::Error(JsonRpcError { code: -32005, message: "limit exceeded".to_string(), data: None }));
+        }
+        let contract = InvoiceContract::new(Address::repeat_byte(0x11), Arc::new(provider))
+            .with_retry(RetryConfig { max_attempts: 3, base_delay_ms: 1, max_delay_ms: 2 });
+
+        assert!(!contract.is_paused().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_paused_state_is_cached_for_writes() {
+        let (provider, mock) = Provider::mocked();
+        // 只有一次 paused() 的响应，第二次检查必须命中缓存
+        let mut paused = [0u8; 32];
+        paused[31] = 1;
+        mock.push::<Bytes, _>(Bytes::from(paused.to_vec())).unwrap();
+        let contract = fast_contract(provider);
+
+        for _ in 0..2 {
+            let err = contract.ensure_not_paused().await.unwrap_err();
+            assert!(is_contract_paused(&err));
+        }
+
+        // 缓存过期后重新查询 (mock 已无响应)
+        let contract = contract.with_paused_cache_ttl(Duration::ZERO);
+        assert!(!is_contract_paused(&contract.ensure_not_paused().await.unwrap_err()));
+    }
+
+    fn fast_contract(provider: Provider<MockProvider>) -> InvoiceContract<Provider<MockProvider>> {
+        InvoiceContract::new(Address::repeat_byte(0x11), Arc::new(provider))
+            .with_retry(RetryConfig { max_attempts: 3, base_delay_ms: 1, max_delay_ms: 2 })
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_for_purchase() {
+        let (provider, mock) = Provider::mocked();
+        // 后进先出：eth_estimateGas 先返回，随后是最新区块和优先费
+        mock.push(U256::from(1_000_000_000u64)).unwrap();
+        let block = Block::<TxHash> { number: Some(100.into()), base_fee_per_gas: Some(U256::from(7_000_000_000u64)), ..Default::default() };
+        mock.push(block).unwrap();
+        mock.push(U256::from(120_000u64)).unwrap();
+
+        let buyer = Address::repeat_byte(0x55);
+        let estimate = fast_contract(provider).estimate_gas_for_purchase(buyer, "batch-1".to_string(), "1000".to_string()).await.unwrap();
+        assert_eq!(estimate.gas_units, U256::from(120_000u64));
+        assert_eq!(estimate.fee_per_gas, U256::from(8_000_000_000u64));
+        assert_eq!(estimate.estimated_fee, U256::from(960_000_000_000_000u64));
+    }
+
+    #[tokio::test]
+    async fn test_estimate_gas_for_purchase_revert() {
+        let (provider, mock) = Provider::mocked();
+        let mut data = hex::decode("08c379a0").unwrap();
+        data.extend(encode(&[Token::String("Batch not active".to_string())]));
+        mock.push_response(MockResponse::Error(JsonRpcError {
+            code: 3,
+            message: "execution reverted: Batch not active".to_string(),
+            data: Some(serde_json::Value::String(format!("0x{}", hex::encode(data)))),
+        }));
+
+        let err = fast_contract(provider).estimate_gas_for_purchase(Address::repeat_byte(0x55), "batch-1".to_string(), "1000".to_string()).await.unwrap_err();
+        assert_eq!(revert_reason(&err).as_deref(), Some("Batch not active"));
+    }
+
+    #[tokio::test]
+    async fn test_already_known_broadcast_returns_tx_hash() {
+        let (provider, mock) = Provider::mocked();
+        let wallet: LocalWallet = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318".parse().unwrap();
+        let client = Arc::new(SignerMiddleware::new(provider, wallet.clone()));
+        let contract = InvoiceContract::new(Address::repeat_byte(0x11), client)
+            .with_retry(RetryConfig { max_attempts: 3, base_delay_ms: 1, max_delay_ms: 2 });
+
+        // 后进先出：先查询 pending nonce，再广播被节点以 already known 拒绝
+        mock.push_response(MockResponse::Error(JsonRpcError { code: -32000, message: "already known".to_string(), data: None }));
+        mock.push(U256::from(5)).unwrap();
+
+        let tx: TypedTransaction = TransactionRequest::new().to(Address::repeat_byte(0x11)).value(0).gas(21_000).gas_price(1).into();
+        let tx_hash = contract.send_with_retry("test", "ref", &[], tx.clone()).await.unwrap();
+
+        let mut expected = tx;
+        expected.set_from(wallet.address());
+        expected.set_nonce(5);
+        expected.set_chain_id(wallet.chain_id());
+        let signature = wallet.sign_transaction_sync(&expected).unwrap();
+        assert_eq!(tx_hash, expected.hash(&signature));
+    }
+
+    #[tokio::test]
+    async fn test_is_enterprise_signer() {
+        let enterprise = Address::repeat_byte(0x22);
+        let owner = Address::repeat_byte(0x33);
+        let stranger = Address::repeat_byte(0x44);
+        let abi_bool = |b: bool| Bytes::from(encode(&[Token::Bool(b)]));
+
+        // EOA 企业钱包：只有地址本身有权限，且地址相同时不发起 RPC
+        let (provider, mock) = Provider::mocked();
+        let contract = fast_contract(provider);
+        assert!(contract.is_enterprise_signer(enterprise, enterprise).await.unwrap());
+        mock.push::<Bytes, _>(Bytes::default()).unwrap();
+        assert!(!contract.is_enterprise_signer(enterprise, stranger).await.unwrap());
+
+        // Safe 合约钱包：后进先出，先 eth_getCode 再 isOwner
+        mock.push::<Bytes, _>(abi_bool(true)).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x60, 0x80])).unwrap();
+        assert!(contract.is_enterprise_signer(enterprise, owner).await.unwrap());
+        mock.push::<Bytes, _>(abi_bool(false)).unwrap();
+        mock.push::<Bytes, _>(Bytes::from(vec![0x60, 0x80])).unwrap();
+        assert!(!contract.is_enterprise_signer(enterprise, stranger).await.unwrap());
+    }
+}
@@ -0,0 +1,343 @@
+//! 测试用合约：查询返回预设数据，写入只记录调用并返回回执，不访问任何节点
+//!
+//! 未预设的数据使用明确的默认值：合约未暂停、票据不存在、余额为 0、代币精度为 [`MockContract::DEFAULT_DECIMALS`]、
+//! 无企业签名人、gas 预估为 [`MockContract::DEFAULT_GAS`]。
+//! [`MockContract::unavailable`] 模拟 RPC 不可用，此时所有调用都返回错误。
+//!
+//! 转账索引使用 [`MockTransferSource`] 模拟链上日志、[`MemoryTransferStore`] 在内存中记录历史和余额。
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, TransactionReceipt, H256, U256, U64};
+
+use common::domain::dto::invoice_dto::InvoiceDataDto;
+use common::domain::dto::query_invoice_dto::QueryParamsDto;
+
+use crate::transfer_indexer::{TransferEvent, TransferSource, TransferStore};
+use crate::{ContractQuerier, ContractWriter, GasEstimate, InvoiceData};
+
+/// 一次写入调用：方法名与参数 (按调用顺序记录)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockWrite {
+    pub operation: &'static str,
+    pub args: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct MockContract {
+    invoices: HashMap<String, InvoiceData>,
+    balances: HashMap<(Address, Address), U256>,
+    decimals: HashMap<Address, u8>,
+    signers: Vec<Address>,
+    paused: bool,
+    unavailable: Option<String>,
+    write_error: Option<String>,
+    writes: Mutex<Vec<MockWrite>>,
+}
+
+impl MockContract {
+    /// 默认 gas 预估：21000 gas，1 gwei
+    pub const DEFAULT_GAS: GasEstimate = GasEstimate {
+        gas_units: U256([21_000, 0, 0, 0]),
+        fee_per_gas: U256([1_000_000_000, 0, 0, 0]),
+        estimated_fee: U256([21_000_000_000_000, 0, 0, 0]),
+    };
+
+    /// 默认代币精度
+    pub const DEFAULT_DECIMALS: u8 = 18;
+
+    pub fn with_invoice(mut self, invoice: InvoiceData) -> Self {
+        self.invoices.insert(invoice.invoice_number.clone(), invoice);
+        self
+    }
+
+    pub fn with_balance(mut self, token: Address, owner: Address, balance: U256) -> Self {
+        self.balances.insert((token, owner), balance);
+        self
+    }
+
+    pub fn with_decimals(mut self, token: Address, decimals: u8) -> Self {
+        self.decimals.insert(token, decimals);
+        self
+    }
+
+    /// 企业钱包的签名人列表 (对所有企业钱包生效)
+    pub fn with_signers(mut self, signers: Vec<Address>) -> Self {
+        self.signers = signers;
+        self
+    }
+
+    pub fn with_paused(mut self, paused: bool) -> Self {
+        self.paused = paused;
+        self
+    }
+
+    /// 模拟 RPC 不可用：所有查询和写入都返回 `message`
+    pub fn unavailable(mut self, message: &str) -> Self {
+        self.unavailable = Some(message.to_string());
+        self
+    }
+
+    /// 查询正常，写入返回 `message` (如交易回滚)
+    pub fn failing_writes(mut self, message: &str) -> Self {
+        self.write_error = Some(message.to_string());
+        self
+    }
+
+    /// 已成功提交的写入
+    pub fn writes(&self) -> Vec<MockWrite> {
+        self.writes.lock().unwrap().clone()
+    }
+
+    fn check_available(&self) -> Result<()> {
+        match &self.unavailable {
+            Some(message) => Err(anyhow!(message.clone())),
+            None => Ok(()),
+        }
+    }
+
+    /// 记录写入并返回成功回执，交易哈希按写入顺序递增
+    fn write(&self, operation: &'static str, args: Vec<String>) -> Result<Option<TransactionReceipt>> {
+        self.check_available()?;
+        if let Some(message) = &self.write_error {
+            return Err(anyhow!(message.clone()));
+        }
+        let mut writes = self.writes.lock().unwrap();
+        writes.push(MockWrite { operation, args });
+        Ok(Some(TransactionReceipt {
+            transaction_hash: H256::from_low_u64_be(writes.len() as u64),
+            status: Some(U64::one()),
+            ..Default::default()
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl ContractQuerier for MockContract {
+    /// 忽略筛选条件，返回全部预设票据
+    async fn query_invoices(&self, _params: QueryParamsDto) -> Result<Vec<InvoiceDataDto>> {
+        self.check_available()?;
+        Ok(self.invoices.values().cloned().map(InvoiceDataDto::from).collect())
+    }
+
+    async fn get_invoice(&self, invoice_number: String) -> Result<Option<InvoiceData>> {
+        self.check_available()?;
+        Ok(self.invoices.get(&invoice_number).cloned())
+    }
+
+    async fn is_paused(&self) -> Result<bool> {
+        self.check_available()?;
+        Ok(self.paused)
+    }
+
+    async fn estimate_gas_for_purchase(&self, _buyer: Address, _batch_id: String, _amount_str: String) -> Result<GasEstimate> {
+        self.check_available()?;
+        Ok(Self::DEFAULT_GAS)
+    }
+
+    async fn token_balance_of(&self, token: Address, owner: Address) -> Result<U256> {
+        self.check_available()?;
+        Ok(self.balances.get(&(token, owner)).copied().unwrap_or_default())
+    }
+
+    async fn token_decimals(&self, token: Address) -> Result<u8> {
+        self.check_available()?;
+        Ok(self.decimals.get(&token).copied().unwrap_or(Self::DEFAULT_DECIMALS))
+    }
+
+    async fn is_enterprise_signer(&self, _enterprise: Address, account: Address) -> Result<bool> {
+        self.check_available()?;
+        Ok(self.signers.contains(&account))
+    }
+}
+
+#[async_trait::async_trait]
+impl ContractWriter for MockContract {
+    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> Result<Option<TransactionReceipt>> {
+        self.write("batch_create_invoices", invoices.into_iter().map(|i| i.invoice_number).collect())
+    }
+
+    async fn create_token_batch(
+        &self,
+        batch_id: String,
+        invoice_numbers: Vec<String>,
+        stable_token_address: String,
+        min_term_str: String,
+        max_term_str: String,
+        interest_rate_str: String,
+    ) -> Result<Option<TransactionReceipt>> {
+        let mut args = vec![batch_id];
+        args.extend(invoice_numbers);
+        args.extend([stable_token_address, min_term_str, max_term_str, interest_rate_str]);
+        self.write("create_token_batch", args)
+    }
+
+    async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>> {
+        self.write("confirm_token_batch_issue", vec![batch_id])
+    }
+
+    async fn purchase_shares(&self, batch_id: String, recipient: String, amount_str: String) -> Result<Option<TransactionReceipt>> {
+        self.write("purchase_shares", vec![batch_id, recipient, amount_str])
+    }
+
+    async fn distribute_repayment(&self, batch_id: String, holders: Vec<String>, amounts: Vec<String>) -> Result<Option<TransactionReceipt>> {
+        let mut args = vec![batch_id];
+        args.extend(holders.into_iter().zip(amounts).map(|(holder, amount)| format!("{}={}", holder, amount)));
+        self.write("distribute_repayment", args)
+    }
+
+    async fn invalidate_invoice(&self, invoice_number: String) -> Result<Option<TransactionReceipt>> {
+        self.write("invalidate_invoice", vec![invoice_number])
+    }
+}
+
+/// 模拟链：按区块保存转账日志，替换区块内容即可模拟重组。克隆后共享同一条链
+#[derive(Clone, Default)]
+pub struct MockTransferSource(Arc<Mutex<MockChain>>);
+
+#[derive(Default)]
+struct MockChain {
+    head: u64,
+    blocks: BTreeMap<u64, Vec<TransferEvent>>,
+}
+
+impl MockTransferSource {
+    pub fn set_head(&self, head: u64) {
+        self.0.lock().unwrap().head = head;
+    }
+
+    /// 设置 (或替换) 区块内的转账
+    pub fn set_block(&self, block: u64, transfers: Vec<TransferEvent>) {
+        self.0.lock().unwrap().blocks.insert(block, transfers);
+    }
+}
+
+#[async_trait::async_trait]
+impl TransferSource for MockTransferSource {
+    async fn latest_block(&self) -> Result<u64> {
+        Ok(self.0.lock().unwrap().head)
+    }
+
+    async fn transfer_logs(&self, tokens: &[Address], from_block: u64, to_block: u64) -> Result<Vec<TransferEvent>> {
+        let chain = self.0.lock().unwrap();
+        Ok(chain
+            .blocks
+            .range(from_block..=to_block)
+            .flat_map(|(_, transfers)| transfers.iter().filter(|t| tokens.contains(&t.token)).cloned())
+            .collect())
+    }
+}
+
+/// 内存转账存储，克隆后共享同一份历史、余额与进度
+#[derive(Clone, Default)]
+pub struct MemoryTransferStore {
+    tokens: Vec<Address>,
+    state: Arc<Mutex<MemoryTransferState>>,
+}
+
+#[derive(Default)]
+struct MemoryTransferState {
+    last_block: Option<u64>,
+    history: Vec<TransferEvent>,
+    balances: HashMap<(Address, Address), U256>,
+}
+
+impl MemoryTransferState {
+    /// 转出方减少、转入方增加，`reverse` 时反向调整。零地址 (铸造 / 销毁) 不记余额
+    fn adjust(&mut self, t: &TransferEvent, reverse: bool) {
+        let (debit, credit) = if reverse { (t.to, t.from) } else { (t.from, t.to) };
+        if !debit.is_zero() {
+            let balance = self.balances.entry((t.token, debit)).or_default();
+            *balance -= t.value;
+        }
+        if !credit.is_zero() {
+            let balance = self.balances.entry((t.token, credit)).or_default();
+            *balance += t.value;
+        }
+    }
+}
+
+impl MemoryTransferStore {
+    pub fn new(tokens: Vec<Address>) -> Self {
+        Self { tokens, state: Default::default() }
+    }
+
+    pub fn balance(&self, token: Address, holder: Address) -> U256 {
+        self.state.lock().unwrap().balances.get(&(token, holder)).copied().unwrap_or_default()
+    }
+
+    /// 已记录的转账
+    pub fn history(&self) -> Vec<TransferEvent> {
+        self.state.lock().unwrap().history.clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl TransferStore for MemoryTransferStore {
+    async fn tracked_tokens(&self) -> Result<Vec<Address>> {
+        Ok(self.tokens.clone())
+    }
+
+    async fn last_processed_block(&self) -> Result<Option<u64>> {
+        Ok(self.state.lock().unwrap().last_block)
+    }
+
+    async fn rollback_from(&self, from_block: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let (undo, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut state.history).into_iter().partition(|t| t.block_number >= from_block);
+        for t in &undo {
+            state.adjust(t, true);
+        }
+        state.history = keep;
+        Ok(())
+    }
+
+    async fn apply(&self, transfers: &[TransferEvent], processed_to: u64) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        for t in transfers {
+            if state.history.iter().any(|h| h.tx_hash == t.tx_hash && h.log_index == t.log_index) {
+                continue;
+            }
+            state.adjust(t, false);
+            state.history.push(t.clone());
+        }
+        state.last_block = Some(processed_to);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_defaults_are_explicit() {
+        let contract = MockContract::default();
+        assert!(!contract.is_paused().await.unwrap());
+        assert_eq!(contract.get_invoice("INV-1".to_string()).await.unwrap(), None);
+        assert_eq!(contract.token_balance_of(Address::zero(), Address::zero()).await.unwrap(), U256::zero());
+        assert_eq!(contract.token_decimals(Address::zero()).await.unwrap(), MockContract::DEFAULT_DECIMALS);
+        assert!(!contract.is_enterprise_signer(Address::zero(), Address::repeat_byte(1)).await.unwrap());
+        let gas = contract.estimate_gas_for_purchase(Address::zero(), "1".to_string(), "1".to_string()).await.unwrap();
+        assert_eq!(gas.estimated_fee, gas.gas_units * gas.fee_per_gas);
+    }
+
+    #[tokio::test]
+    async fn test_writes_are_recorded_in_order() {
+        let contract = MockContract::default();
+        let first = contract.invalidate_invoice("INV-1".to_string()).await.unwrap().unwrap();
+        let second = contract.purchase_shares("7".to_string(), "0xinvestor".to_string(), "100".to_string()).await.unwrap().unwrap();
+        assert_ne!(first.transaction_hash, second.transaction_hash);
+        assert_eq!(contract.writes(), vec![
+            MockWrite { operation: "invalidate_invoice", args: vec!["INV-1".to_string()] },
+            MockWrite { operation: "purchase_shares", args: vec!["7".to_string(), "0xinvestor".to_string(), "100".to_string()] },
+        ]);
+
+        let unavailable = MockContract::default().unavailable("rpc down");
+        assert!(unavailable.is_paused().await.is_err());
+        assert!(unavailable.invalidate_invoice("INV-1".to_string()).await.is_err());
+        assert!(unavailable.writes().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! 签名账户的 nonce 分配
+//!
+//! `SignerMiddleware` 每次发送都向节点查询 nonce，并发写入时多笔交易会拿到相同的 nonce
+//! (`nonce too low` / `replacement transaction underpriced`)。这里在进程内按顺序分配 nonce，
+//! 首次分配或检测到空洞后从 `eth_getTransactionCount(pending)` 重新同步。
+
+use anyhow::{anyhow, Result};
+use ethers::providers::Middleware;
+use ethers::types::{Address, BlockNumber, U256};
+use tracing::warn;
+use tokio::sync::Mutex;
+
+pub struct NonceManager {
+    address: Address,
+    /// 下一个可分配的 nonce，None 表示需要从节点同步
+    next: Mutex<Option<U256>>,
+}
+
+impl NonceManager {
+    pub fn new(address: Address) -> Self {
+        Self { address, next: Mutex::new(None) }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    /// 分配一个 nonce。持锁查询节点，保证并发调用拿到连续且不重复的值
+    pub async fn reserve<M: Middleware>(&self, client: &M) -> Result<U256> {
+        let mut next = self.next.lock().await;
+        let nonce = match *next {
+            Some(nonce) => nonce,
+            None => client
+                .get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(|e| anyhow!("Failed to fetch pending nonce of {:?}: {}", self.address, e))?,
+        };
+        *next = Some(nonce + 1);
+        Ok(nonce)
+    }
+
+    /// 分配的 nonce 未被使用 (交易没有到达节点)。是最后分配的值则直接回收，否则会留下空洞，改为下次重新同步
+    pub async fn release(&self, nonce: U256) {
+        let mut next = self.next.lock().await;
+        if *next == Some(nonce + 1) {
+            *next = Some(nonce);
+        } else {
+            warn!("Nonce {} of {:?} released out of order, resyncing from node", nonce, self.address);
+            *next = None;
+        }
+    }
+
+    /// 节点报告 nonce 冲突等情况下丢弃本地计数，下次分配时重新同步
+    pub async fn resync(&self) {
+        *self.next.lock().await = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use ethers::providers::Provider;
+
+    #[tokio::test]
+    async fn test_concurrent_reservations_are_gap_free() {
+        let (provider, mock) = Provider::mocked();
+        // 只应查询一次节点
+        mock.push(U256::from(5)).unwrap();
+        let provider = Arc::new(provider);
+        let manager = Arc::new(NonceManager::new(Address::repeat_byte(0x01)));
+
+        let handles: Vec<_> = (0..10)
+            .map(|_| {
+                let manager = manager.clone();
+                let provider = provider.clone();
+                tokio::spawn(async move { manager.reserve(provider.as_ref()).await.unwrap() })
+            })
+            .collect();
+        let mut nonces = Vec::new();
+        for handle in handles {
+            nonces.push(handle.await.unwrap().as_u64());
+        }
+        nonces.sort();
+        assert_eq!(nonces, (5..15).collect::<Vec<u64>>());
+    }
+
+    #[tokio::test]
+    async fn test_release_and_resync() {
+        let (provider, mock) = Provider::mocked();
+        mock.push(U256::from(9)).unwrap();
+        mock.push(U256::from(3)).unwrap();
+        let manager = NonceManager::new(Address::repeat_byte(0x02));
+
+        assert_eq!(manager.reserve(&provider).await.unwrap(), U256::from(3));
+        let second = manager.reserve(&provider).await.unwrap();
+        assert_eq!(second, U256::from(4));
+        // 最后分配的 nonce 可直接回收
+        manager.release(second).await;
+        assert_eq!(manager.reserve(&provider).await.unwrap(), U256::from(4));
+
+        // 回收较早的 nonce 会留下空洞，下次分配从节点重新同步
+        manager.release(U256::from(3)).await;
+        assert_eq!(manager.reserve(&provider).await.unwrap(), U256::from(9));
+    }
+}
@@ -0,0 +1,192 @@
+//! 合约调用重试：网络超时、RPC 限流等临时错误按指数退避 (带随机抖动) 重试，合约 revert 不重试
+//!
+//! 写操作只重试发送前的只读步骤和广播本身；广播前固定 nonce，重发的是同一笔交易，
+//! 已上链的交易再次广播会被节点以 `nonce too low` / `already known` 拒绝，不会重复执行。
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use tracing::warn;
+
+use crate::{is_contract_paused, ContractReverted};
+use crate::revert::decode_revert_reason;
+
+/// 重试策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryConfig {
+    /// 最大尝试次数 (含首次)，1 表示不重试
+    pub max_attempts: u32,
+    /// 首次重试前的等待时间 (毫秒)
+    pub base_delay_ms: u64,
+    /// 单次等待上限 (毫秒)
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self { max_attempts: 3, base_delay_ms: 200, max_delay_ms: 5_000 }
+    }
+}
+
+impl RetryConfig {
+    /// 不重试
+    pub fn disabled() -> Self {
+        Self { max_attempts: 1, ..Self::default() }
+    }
+
+    /// 第 `attempt` 次失败后的等待时间：上限为 `base * 2^(attempt-1)` (不超过 max)，在 [上限/2, 上限] 内随机
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let cap = self.base_delay_ms.saturating_mul(1u64 << attempt.saturating_sub(1).min(32)).min(self.max_delay_ms);
+        Duration::from_millis(rand::random_range(cap / 2..=cap))
+    }
+}
+
+/// 广播的交易已被节点接受过 (nonce 已使用)，说明之前某次尝试可能已经成功
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxPossiblySubmitted(pub String);
+
+impl std::fmt::Display for TxPossiblySubmitted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} may already have been submitted by an earlier attempt, not resubmitting", self.0)
+    }
+}
+
+impl std::error::Error for TxPossiblySubmitted {}
+
+const TRANSIENT_MARKERS: [&str; 12] = [
+    "timeout",
+    "timed out",
+    "429",
+    "too many requests",
+    "rate limit",
+    "limit exceeded",
+    "connection",
+    "error sending request",
+    "502",
+    "503",
+    "504",
+    "temporarily unavailable",
+];
+
+/// 是否为可重试的临时错误。合约 revert、暂停等确定性错误一律不重试
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    if is_contract_paused(err) || err.downcast_ref::<TxPossiblySubmitted>().is_some() || err.downcast_ref::<ContractReverted>().is_some() {
+        return false;
+    }
+    let message = format!("{:#}", err);
+    if decode_revert_reason(&message).is_some() {
+        return false;
+    }
+    let message = message.to_lowercase();
+    TRANSIENT_MARKERS.iter().any(|marker| message.contains(marker))
+}
+
+/// 节点的交易池中已有这笔交易 (同一签名交易重复广播)
+pub fn is_already_known(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("already known") || message.contains("known transaction")
+}
+
+/// 节点拒绝交易是因为相同 nonce 的交易已存在
+pub fn is_nonce_consumed(message: &str) -> bool {
+    let message = message.to_lowercase();
+    message.contains("nonce too low")
+        || message.contains("already known")
+        || message.contains("known transaction")
+        || message.contains("replacement transaction underpriced")
+}
+
+/// 按 `config` 重试 `f`，只重试 [`is_transient`] 的错误
+pub async fn retry<T, F, Fut>(config: &RetryConfig, operation: &str, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < config.max_attempts && is_transient(&e) => {
+                let delay = config.backoff(attempt);
+                warn!("{} failed (attempt {}/{}), retrying in {:?}: {}", operation, attempt, config.max_attempts, delay, e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn fast() -> RetryConfig {
+        RetryConfig { max_attempts: 3, base_delay_ms: 1, max_delay_ms: 2 }
+    }
+
+    #[tokio::test]
+    async fn test_fails_twice_then_succeeds() {
+        let attempts = AtomicU32::new(0);
+        let result = retry(&fast(), "queryInvoices", || async {
+            if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(anyhow!("Contract query failed: (code: 429, message: Too Many Requests)"))
+            } else {
+                Ok(42)
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = retry(&fast(), "paused", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("error sending request for url: operation timed out"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_revert_is_not_retried() {
+        let attempts = AtomicU32::new(0);
+        let result: Result<()> = retry(&fast(), "purchaseShares", || async {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow!("Failed to estimate gas (potential revert): execution reverted: Batch not active"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_transient_classification() {
+        assert!(is_transient(&anyhow!("(code: -32005, message: limit exceeded, data: None)")));
+        assert!(is_transient(&anyhow!("HTTP error 503 Service Unavailable")));
+        assert!(!is_transient(&anyhow!("Invalid payee address format")));
+        assert!(!is_transient(&anyhow::Error::new(crate::ContractPaused)));
+        assert!(!is_transient(&anyhow::Error::new(TxPossiblySubmitted("createTokenBatch".to_string()))));
+        assert!(is_nonce_consumed("(code: -32000, message: nonce too low, data: None)"));
+        assert!(is_nonce_consumed("already known"));
+        assert!(is_already_known("(code: -32000, message: already known, data: None)"));
+        assert!(!is_already_known("nonce too low"));
+    }
+
+    #[test]
+    fn test_backoff_is_bounded() {
+        let config = RetryConfig { max_attempts: 5, base_delay_ms: 100, max_delay_ms: 1_000 };
+        for attempt in 1..=10 {
+            let cap = (100u64 << (attempt - 1)).min(1_000);
+            let delay = config.backoff(attempt).as_millis() as u64;
+            assert!(delay >= cap / 2 && delay <= cap, "attempt {} delay {}", attempt, delay);
+        }
+    }
+}
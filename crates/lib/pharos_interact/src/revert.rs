@@ -0,0 +1,215 @@
+//! 从合约调用错误信息中提取 revert 原因和交易哈希
+//!
+//! 写操作的错误在返回前已被转换为字符串 (`anyhow!`)，这里按 RPC 常见格式解析：
+//! `execution reverted: <reason>`、ABI 编码的 `Error(string)` / `Panic(uint256)` / 自定义错误数据，
+//! 以及确认后状态为 0 的交易回执。
+
+use std::fmt;
+use std::sync::LazyLock;
+
+use ethers::abi::{Abi, AbiError, Token};
+
+/// `Error(string)` 选择器
+const ERROR_STRING_SELECTOR: &str = "08c379a0";
+/// `Panic(uint256)` 选择器
+const PANIC_SELECTOR: &str = "4e487b71";
+
+/// 合约依赖的 OpenZeppelin 5.x 自定义错误，票据合约 ABI 未声明时也能识别
+const STANDARD_ERRORS: &[&str] = &[
+    "error EnforcedPause()",
+    "error ExpectedPause()",
+    "error OwnableUnauthorizedAccount(address account)",
+    "error AccessControlUnauthorizedAccount(address account, bytes32 neededRole)",
+    "error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed)",
+    "error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed)",
+    "error ReentrancyGuardReentrantCall()",
+];
+
+/// 可识别的自定义错误：票据合约 ABI 中声明的错误 + [`STANDARD_ERRORS`]
+static KNOWN_ERRORS: LazyLock<Vec<AbiError>> = LazyLock::new(|| {
+    let mut errors: Vec<AbiError> = match serde_json::from_str::<Abi>(include_str!("../invoice_abi.json")) {
+        Ok(abi) => abi.errors().cloned().collect(),
+        Err(e) => {
+            tracing::error!("Failed to parse custom errors from invoice ABI: {}", e);
+            Vec::new()
+        }
+    };
+    let standard = ethers::abi::parse_abi(STANDARD_ERRORS).expect("standard error signatures are valid");
+    errors.extend(standard.errors().cloned());
+    errors
+});
+
+/// 解码后的 revert 原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevertError {
+    /// `require(cond, "reason")` / `revert("reason")`
+    Message(String),
+    /// `Panic(uint256)`，如算术溢出 (0x11)、除零 (0x12)，值为十六进制错误码
+    Panic(String),
+    /// ABI 中声明的自定义错误，参数按声明顺序格式化
+    Custom { name: String, args: Vec<String> },
+    /// 无法识别的 revert 数据 (0x 开头的十六进制)，没有数据时为 None
+    Unknown(Option<String>),
+}
+
+impl fmt::Display for RevertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RevertError::Message(reason) => f.write_str(reason),
+            RevertError::Panic(code) => write!(f, "panic code {}", code),
+            RevertError::Custom { name, args } => write!(f, "{}({})", name, args.join(", ")),
+            RevertError::Unknown(Some(data)) => write!(f, "unrecognized revert data {}", data),
+            RevertError::Unknown(None) => f.write_str("reverted without reason"),
+        }
+    }
+}
+
+/// 解码 revert 数据 (选择器 + ABI 编码参数)
+pub fn decode_revert_data(data: &[u8]) -> RevertError {
+    let unknown = || RevertError::Unknown((!data.is_empty()).then(|| format!("0x{}", hex::encode(data))));
+    let Some((selector, params)) = data.split_first_chunk::<4>() else {
+        return unknown();
+    };
+    match hex::encode(selector).as_str() {
+        ERROR_STRING_SELECTOR => match ethers::abi::decode(&[ethers::abi::ParamType::String], params).ok().and_then(|t| t.into_iter().next()) {
+            Some(Token::String(reason)) => RevertError::Message(reason),
+            _ => unknown(),
+        },
+        PANIC_SELECTOR => match ethers::abi::decode(&[ethers::abi::ParamType::Uint(256)], params).ok().and_then(|t| t.into_iter().next()) {
+            Some(Token::Uint(code)) => RevertError::Panic(format!("{:#x}", code)),
+            _ => unknown(),
+        },
+        _ => KNOWN_ERRORS
+            .iter()
+            .find(|error| error.signature().as_bytes()[..4] == selector[..])
+            .and_then(|error| error.decode(params).ok().map(|tokens| (error, tokens)))
+            .map(|(error, tokens)| RevertError::Custom { name: error.name.clone(), args: tokens.iter().map(format_token).collect() })
+            .unwrap_or_else(unknown),
+    }
+}
+
+/// 从错误信息中解析 revert，错误与合约回滚无关时返回 None
+pub fn decode_revert(message: &str) -> Option<RevertError> {
+    if let Some(data) = find_revert_data(message) {
+        return Some(decode_revert_data(&data));
+    }
+    if let Some(pos) = message.find("execution reverted: ") {
+        let rest = &message[pos + "execution reverted: ".len()..];
+        let end = rest.find(|c| matches!(c, ',' | ')' | '"' | '\n')).unwrap_or(rest.len());
+        let reason = rest[..end].trim();
+        if !reason.is_empty() {
+            return Some(RevertError::Message(reason.to_string()));
+        }
+    }
+    if message.contains("execution reverted") || message.contains("reverted (status 0)") {
+        return Some(RevertError::Unknown(None));
+    }
+    None
+}
+
+/// 解析 revert 原因，无法识别时返回 None
+pub fn decode_revert_reason(message: &str) -> Option<String> {
+    decode_revert(message).map(|revert| revert.to_string())
+}
+
+/// 提取错误信息 (通常是回执的 Debug 输出) 中的交易哈希
+pub fn extract_tx_hash(message: &str) -> Option<String> {
+    let pos = message.find("transaction_hash: 0x")?;
+    let hex_start = pos + "transaction_hash: 0x".len();
+    let hash: String = message[hex_start..].chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+    (hash.len() == 64).then(|| format!("0x{}", hash))
+}
+
+// revert 数据在错误信息中的常见位置：JSON-RPC 错误的 data 字段、ethers `ContractError::Revert` 的输出
+const REVERT_DATA_MARKERS: &[&str] = &["String(\"0x", "\"data\":\"0x", "reverted with data: 0x"];
+
+// 返回 revert 数据，至少包含 4 字节选择器
+fn find_revert_data(message: &str) -> Option<Vec<u8>> {
+    REVERT_DATA_MARKERS.iter().find_map(|marker| {
+        let pos = message.find(marker)?;
+        let hex: String = message[pos + marker.len()..].chars().take_while(|c| c.is_ascii_hexdigit()).collect();
+        let data = hex::decode(hex.get(..hex.len() / 2 * 2)?).ok()?;
+        (data.len() >= 4).then_some(data)
+    })
+}
+
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{:#x}", address),
+        Token::Bytes(bytes) | Token::FixedBytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Uint(value) | Token::Int(value) => value.to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_abi_error_string() {
+        // Error("Invoice exists")
+        let data = "0x08c379a0\
+            0000000000000000000000000000000000000000000000000000000000000020\
+            000000000000000000000000000000000000000000000000000000000000000e\
+            496e766f69636520657869737473000000000000000000000000000000000000";
+        let message = format!("Failed to send transaction: (code: 3, message: execution reverted, data: Some(String(\"{}\")))", data);
+        assert_eq!(decode_revert_reason(&message).as_deref(), Some("Invoice exists"));
+    }
+
+    #[test]
+    fn test_decode_plain_and_status_zero() {
+        assert_eq!(
+            decode_revert_reason("(code: 3, message: execution reverted: Batch not found, data: None)").as_deref(),
+            Some("Batch not found")
+        );
+        let receipt = format!("Transaction reverted (status 0). Receipt: TransactionReceipt {{ transaction_hash: 0x{} }}", "ab".repeat(32));
+        assert_eq!(decode_revert_reason(&receipt).as_deref(), Some("reverted without reason"));
+        assert_eq!(extract_tx_hash(&receipt), Some(format!("0x{}", "ab".repeat(32))));
+        assert_eq!(decode_revert_reason("Failed to get transaction receipt: timeout"), None);
+    }
+
+    #[test]
+    fn test_decode_error_string_payload() {
+        let mut data = hex::decode(ERROR_STRING_SELECTOR).unwrap();
+        data.extend(ethers::abi::encode(&[Token::String("Batch not active".to_string())]));
+        assert_eq!(decode_revert_data(&data), RevertError::Message("Batch not active".to_string()));
+
+        let message = format!("(code: 3, message: execution reverted, data: Some(String(\"0x{}\")))", hex::encode(&data));
+        assert_eq!(decode_revert(&message), Some(RevertError::Message("Batch not active".to_string())));
+    }
+
+    #[test]
+    fn test_decode_custom_error_selector() {
+        // ERC20InsufficientBalance(address,uint256,uint256) = 0xe450d38c
+        let sender = ethers::types::Address::repeat_byte(0x11);
+        let mut data = hex::decode("e450d38c").unwrap();
+        data.extend(ethers::abi::encode(&[Token::Address(sender), Token::Uint(5.into()), Token::Uint(20.into())]));
+        let expected = RevertError::Custom {
+            name: "ERC20InsufficientBalance".to_string(),
+            args: vec![format!("{:#x}", sender), "5".to_string(), "20".to_string()],
+        };
+        assert_eq!(decode_revert_data(&data), expected);
+
+        let message = format!("Contract call reverted with data: 0x{}", hex::encode(&data));
+        assert_eq!(decode_revert(&message), Some(expected));
+        assert_eq!(
+            decode_revert_reason(&message).as_deref(),
+            Some("ERC20InsufficientBalance(0x1111111111111111111111111111111111111111, 5, 20)")
+        );
+
+        // EnforcedPause() 没有参数
+        assert_eq!(decode_revert_data(&hex::decode("d93c0665").unwrap()), RevertError::Custom { name: "EnforcedPause".to_string(), args: vec![] });
+    }
+
+    #[test]
+    fn test_unknown_selector_keeps_raw_data() {
+        let message = "(code: 3, message: execution reverted, data: Some(String(\"0xdeadbeef01\")))";
+        assert_eq!(decode_revert(message), Some(RevertError::Unknown(Some("0xdeadbeef01".to_string()))));
+        assert_eq!(decode_revert_data(&[]), RevertError::Unknown(None));
+        // Panic(0x11) 算术溢出
+        let mut data = hex::decode(PANIC_SELECTOR).unwrap();
+        data.extend(ethers::abi::encode(&[Token::Uint(0x11.into())]));
+        assert_eq!(decode_revert_data(&data).to_string(), "panic code 0x11");
+    }
+}
@@ -0,0 +1,375 @@
+//! ERC20 `Transfer` 事件索引：按区块区间轮询已跟踪代币合约的 `Transfer` 日志，写入转账历史并同步余额
+//!
+//! 最后处理的区块持久化在 [`TransferStore`] 中，重启后从该区块继续。每一轮都先回退
+//! `confirmation_depth` 个区块：撤销这段区块内已记录的转账后重新拉取日志处理，
+//! 因此该深度内的链重组 (日志消失或变化) 会在下一轮被纠正。
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ethers::providers::Middleware;
+use ethers::types::{Address, Filter, Log, H256, U256};
+use ethers::utils::keccak256;
+use tracing::{error, info};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+/// `keccak256("Transfer(address,address,uint256)")`
+pub fn transfer_topic() -> H256 {
+    H256::from(keccak256("Transfer(address,address,uint256)"))
+}
+
+/// 一条已解析的 ERC20 转账日志
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferEvent {
+    pub token: Address,
+    /// 铸造时为零地址
+    pub from: Address,
+    /// 销毁时为零地址
+    pub to: Address,
+    pub value: U256,
+    pub block_number: u64,
+    pub tx_hash: H256,
+    pub log_index: u64,
+}
+
+impl TransferEvent {
+    /// 解析标准 ERC20 `Transfer` 日志 (from/to 为 indexed topic，value 在 data 中)。
+    /// 其他事件、ERC721 `Transfer` (tokenId 也是 indexed) 以及未打包的日志返回 None
+    pub fn from_log(log: &Log) -> Option<Self> {
+        if log.topics.len() != 3 || log.topics[0] != transfer_topic() || log.data.len() != 32 {
+            return None;
+        }
+        Some(Self {
+            token: log.address,
+            from: Address::from(log.topics[1]),
+            to: Address::from(log.topics[2]),
+            value: U256::from_big_endian(&log.data),
+            block_number: log.block_number?.as_u64(),
+            tx_hash: log.transaction_hash?,
+            log_index: log.log_index?.as_u64(),
+        })
+    }
+}
+
+/// 转账日志来源
+#[async_trait::async_trait]
+pub trait TransferSource: Send + Sync {
+    /// 当前链头区块
+    async fn latest_block(&self) -> Result<u64>;
+
+    /// `tokens` 在 `[from_block, to_block]` 内的转账，按 (区块, 日志序号) 升序
+    async fn transfer_logs(&self, tokens: &[Address], from_block: u64, to_block: u64) -> Result<Vec<TransferEvent>>;
+}
+
+/// 转账历史、余额与同步进度的存储
+#[async_trait::async_trait]
+pub trait TransferStore: Send + Sync {
+    /// 需要索引的代币合约
+    async fn tracked_tokens(&self) -> Result<Vec<Address>>;
+
+    /// 最后处理完成的区块，从未同步时为 None
+    async fn last_processed_block(&self) -> Result<Option<u64>>;
+
+    /// 撤销 `from_block` 及之后区块已记录的转账：余额反向调整并删除历史
+    async fn rollback_from(&self, from_block: u64) -> Result<()>;
+
+    /// 记录转账并调整余额，然后把最后处理区块推进到 `processed_to`。
+    /// 已记录过的转账 (同一交易的同一日志序号) 忽略，重复调用不会重复计入余额
+    async fn apply(&self, transfers: &[TransferEvent], processed_to: u64) -> Result<()>;
+}
+
+/// 通过 `eth_getLogs` 拉取转账日志
+pub struct RpcTransferSource<M: Middleware> {
+    client: Arc<M>,
+}
+
+impl<M: Middleware + 'static> RpcTransferSource<M> {
+    pub fn new(client: Arc<M>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: Middleware + Send + Sync + 'static> TransferSource for RpcTransferSource<M> {
+    async fn latest_block(&self) -> Result<u64> {
+        let block = self.client.get_block_number().await.map_err(|e| anyhow!("eth_blockNumber failed: {}", e))?;
+        Ok(block.as_u64())
+    }
+
+    async fn transfer_logs(&self, tokens: &[Address], from_block: u64, to_block: u64) -> Result<Vec<TransferEvent>> {
+        let filter = Filter::new()
+            .address(tokens.to_vec())
+            .topic0(transfer_topic())
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = self.client.get_logs(&filter).await.map_err(|e| anyhow!("eth_getLogs failed: {}", e))?;
+        let mut transfers: Vec<TransferEvent> = logs.iter().filter_map(TransferEvent::from_log).collect();
+        transfers.sort_by_key(|t| (t.block_number, t.log_index));
+        Ok(transfers)
+    }
+}
+
+/// 索引任务配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferIndexerConfig {
+    /// 首次运行时开始扫描的区块
+    pub start_block: u64,
+    /// 每轮回退重新处理的区块数
+    pub confirmation_depth: u64,
+    /// 单次拉取的最大区块跨度 (至少覆盖回退的区块)
+    pub max_block_range: u64,
+    /// 追上链头后的轮询间隔 (秒)
+    pub interval_secs: u64,
+}
+
+/// 索引同步状态，供管理接口查看
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TransferSyncStatus {
+    pub last_processed_block: Option<u64>,
+    pub chain_head: Option<u64>,
+    pub tracked_tokens: usize,
+    pub last_synced_at: Option<DateTime<Utc>>,
+    /// 最近一轮失败的原因，成功后清空
+    pub last_error: Option<String>,
+}
+
+impl TransferSyncStatus {
+    /// 落后链头的区块数
+    pub fn lag(&self) -> Option<u64> {
+        Some(self.chain_head?.saturating_sub(self.last_processed_block?))
+    }
+}
+
+/// 进程内共享的同步状态
+#[derive(Debug, Clone, Default)]
+pub struct TransferSyncHandle(Arc<RwLock<TransferSyncStatus>>);
+
+impl TransferSyncHandle {
+    pub fn snapshot(&self) -> TransferSyncStatus {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    fn update(&self, f: impl FnOnce(&mut TransferSyncStatus)) {
+        f(&mut self.0.write().unwrap_or_else(|e| e.into_inner()));
+    }
+}
+
+/// 一轮同步的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncRound {
+    /// 本轮处理到的区块
+    pub processed_to: u64,
+    pub chain_head: u64,
+    /// 本轮拉取到的转账数 (含回退重新处理的部分)
+    pub transfers: usize,
+}
+
+impl SyncRound {
+    pub fn caught_up(&self) -> bool {
+        self.processed_to >= self.chain_head
+    }
+}
+
+pub struct TransferIndexer<S, T> {
+    source: S,
+    store: T,
+    config: TransferIndexerConfig,
+    status: TransferSyncHandle,
+}
+
+impl<S: TransferSource + 'static, T: TransferStore + 'static> TransferIndexer<S, T> {
+    pub fn new(source: S, store: T, config: TransferIndexerConfig) -> Self {
+        Self { source, store, config, status: TransferSyncHandle::default() }
+    }
+
+    pub fn status(&self) -> TransferSyncHandle {
+        self.status.clone()
+    }
+
+    /// 同步一轮。没有跟踪的代币或没有需要处理的区块时返回 None
+    pub async fn sync_once(&self) -> Result<Option<SyncRound>> {
+        let tokens = self.store.tracked_tokens().await?;
+        let head = self.source.latest_block().await?;
+        let last = self.store.last_processed_block().await?;
+        self.status.update(|s| {
+            s.chain_head = Some(head);
+            s.last_processed_block = last;
+            s.tracked_tokens = tokens.len();
+        });
+
+        let Some((from, to)) = next_range(&self.config, last, head) else { return Ok(None) };
+        if tokens.is_empty() {
+            return Ok(None);
+        }
+
+        self.store.rollback_from(from).await?;
+        let transfers = self.source.transfer_logs(&tokens, from, to).await?;
+        self.store.apply(&transfers, to).await?;
+
+        self.status.update(|s| {
+            s.last_processed_block = Some(to);
+            s.last_synced_at = Some(Utc::now());
+            s.last_error = None;
+        });
+        Ok(Some(SyncRound { processed_to: to, chain_head: head, transfers: transfers.len() }))
+    }
+
+    /// 启动后台同步任务：落后链头时连续同步，追上后按间隔轮询。`shutdown` 变为 true 后在两轮之间退出，
+    /// 进度已按轮持久化，不需要额外收尾
+    pub fn spawn(self: &Arc<Self>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        let indexer = self.clone();
+        tokio::spawn(async move {
+            let idle = Duration::from_secs(indexer.config.interval_secs.max(1));
+            while !*shutdown.borrow() {
+                match indexer.sync_once().await {
+                    Ok(Some(round)) if !round.caught_up() => {
+                        info!("Indexed ERC20 transfers up to block {} of {}", round.processed_to, round.chain_head);
+                        continue;
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("ERC20 transfer indexing failed: {}", e);
+                        indexer.status.update(|s| s.last_error = Some(e.to_string()));
+                    }
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(idle) => {}
+                    _ = shutdown.changed() => break,
+                }
+            }
+            info!("ERC20 transfer indexer stopped");
+        })
+    }
+}
+
+/// 本轮需要 (重新) 处理的区块区间。首次从 `start_block` 开始，之后回退 `confirmation_depth` 个区块；
+/// 区间至少覆盖到上一轮的最后区块，避免跨度小于回退深度时进度倒退
+fn next_range(config: &TransferIndexerConfig, last: Option<u64>, head: u64) -> Option<(u64, u64)> {
+    let from = match last {
+        Some(last) => (last + 1).saturating_sub(config.confirmation_depth).max(config.start_block),
+        None => config.start_block,
+    };
+    if from > head {
+        return None;
+    }
+    let span = config.max_block_range.max(config.confirmation_depth + 1);
+    Some((from, head.min(from.saturating_add(span - 1))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{MemoryTransferStore, MockTransferSource};
+
+    const TOKEN: Address = Address::repeat_byte(0x70);
+    const ALICE: Address = Address::repeat_byte(0xa1);
+    const BOB: Address = Address::repeat_byte(0xb0);
+
+    fn transfer(block: u64, log_index: u64, from: Address, to: Address, value: u64) -> TransferEvent {
+        TransferEvent {
+            token: TOKEN,
+            from,
+            to,
+            value: U256::from(value),
+            block_number: block,
+            tx_hash: H256::from_low_u64_be(block * 100 + log_index),
+            log_index,
+        }
+    }
+
+    fn new_indexer(source: &MockTransferSource, store: &MemoryTransferStore, max_block_range: u64) -> TransferIndexer<MockTransferSource, MemoryTransferStore> {
+        let config = TransferIndexerConfig { start_block: 1, confirmation_depth: 2, max_block_range, interval_secs: 1 };
+        TransferIndexer::new(source.clone(), store.clone(), config)
+    }
+
+    async fn sync_to_head(indexer: &TransferIndexer<MockTransferSource, MemoryTransferStore>) {
+        while let Some(round) = indexer.sync_once().await.unwrap() {
+            if round.caught_up() {
+                break;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transfer_sequence_produces_ending_balances() {
+        let source = MockTransferSource::default();
+        source.set_head(10);
+        source.set_block(2, vec![transfer(2, 0, Address::zero(), ALICE, 1_000)]);
+        source.set_block(5, vec![transfer(5, 0, ALICE, BOB, 300), transfer(5, 1, BOB, ALICE, 50)]);
+        source.set_block(9, vec![transfer(9, 3, BOB, Address::zero(), 100)]);
+        // 未跟踪的代币不计入
+        source.set_block(7, vec![TransferEvent { token: Address::repeat_byte(0x99), ..transfer(7, 0, ALICE, BOB, 1) }]);
+        let store = MemoryTransferStore::new(vec![TOKEN]);
+        // 小跨度多轮同步，每轮都会回退重新处理，不应重复计入
+        let indexer = new_indexer(&source, &store, 3);
+        sync_to_head(&indexer).await;
+
+        assert_eq!(store.balance(TOKEN, ALICE), U256::from(750));
+        assert_eq!(store.balance(TOKEN, BOB), U256::from(150));
+        assert_eq!(store.history().len(), 4);
+        let status = indexer.status().snapshot();
+        assert_eq!((status.last_processed_block, status.lag()), (Some(10), Some(0)));
+
+        // 模拟重启：新的索引器从持久化的区块继续
+        source.set_head(12);
+        source.set_block(12, vec![transfer(12, 0, ALICE, BOB, 250)]);
+        sync_to_head(&new_indexer(&source, &store, 3)).await;
+        assert_eq!(store.balance(TOKEN, ALICE), U256::from(500));
+        assert_eq!(store.balance(TOKEN, BOB), U256::from(400));
+    }
+
+    #[tokio::test]
+    async fn test_reorg_within_confirmation_depth_is_corrected() {
+        let source = MockTransferSource::default();
+        source.set_head(6);
+        source.set_block(1, vec![transfer(1, 0, Address::zero(), ALICE, 100)]);
+        source.set_block(6, vec![transfer(6, 0, ALICE, BOB, 40)]);
+        let store = MemoryTransferStore::new(vec![TOKEN]);
+        let indexer = new_indexer(&source, &store, 100);
+        sync_to_head(&indexer).await;
+        assert_eq!(store.balance(TOKEN, BOB), U256::from(40));
+
+        // 区块 6 被重组替换，转账金额和日志位置都变了
+        source.set_head(7);
+        source.set_block(6, vec![transfer(6, 2, ALICE, BOB, 10)]);
+        sync_to_head(&indexer).await;
+        assert_eq!(store.balance(TOKEN, ALICE), U256::from(90));
+        assert_eq!(store.balance(TOKEN, BOB), U256::from(10));
+        assert_eq!(store.history().len(), 2);
+    }
+
+    #[test]
+    fn test_next_range() {
+        let config = TransferIndexerConfig { start_block: 5, confirmation_depth: 3, max_block_range: 2, interval_secs: 1 };
+        assert_eq!(next_range(&config, None, 4), None);
+        assert_eq!(next_range(&config, None, 100), Some((5, 8)));
+        // 回退 3 个区块，跨度至少覆盖到上一轮最后区块
+        assert_eq!(next_range(&config, Some(20), 100), Some((18, 21)));
+        assert_eq!(next_range(&config, Some(20), 20), Some((18, 20)));
+        assert_eq!(next_range(&config, Some(6), 100), Some((5, 8)));
+    }
+
+    #[test]
+    fn test_parse_transfer_log() {
+        let mut data = [0u8; 32];
+        U256::from(42).to_big_endian(&mut data);
+        let log = Log {
+            address: TOKEN,
+            topics: vec![transfer_topic(), H256::from(ALICE), H256::from(BOB)],
+            data: data.to_vec().into(),
+            block_number: Some(7.into()),
+            transaction_hash: Some(H256::repeat_byte(0x01)),
+            log_index: Some(U256::from(3)),
+            ..Default::default()
+        };
+        let event = TransferEvent::from_log(&log).unwrap();
+        assert_eq!((event.from, event.to, event.value, event.block_number, event.log_index), (ALICE, BOB, U256::from(42), 7, 3));
+
+        // ERC721 Transfer 的 tokenId 是第 4 个 topic
+        let nft = Log { topics: vec![transfer_topic(), H256::from(ALICE), H256::from(BOB), H256::zero()], data: Default::default(), ..log };
+        assert_eq!(TransferEvent::from_log(&nft), None);
+    }
+}
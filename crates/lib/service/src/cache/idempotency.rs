@@ -0,0 +1,239 @@
+//! 请求幂等：同一用户携带相同 `Idempotency-Key` 重试时直接返回首次请求的结果，不再重复执行
+//!
+//! 首次请求先写入占位值占用键，执行成功后替换为响应 JSON；执行失败则删除键，允许客户端重试。
+//! 键对应的记录同时保存请求体的指纹，相同键携带不同请求体时拒绝 (`IdempotencyKeyReused`)，不返回另一请求的结果。
+
+use std::future::Future;
+
+use tracing::warn;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+
+use crate::cache::InvoiceRedisService;
+use crate::error::ServiceError;
+
+/// 幂等键保留时间
+pub const IDEMPOTENCY_TTL_SECS: u64 = 24 * 3600;
+/// 幂等键最大长度
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 128;
+
+/// 幂等键保存的记录：请求体指纹，以及完成后的响应 JSON (处理中为 None)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct IdempotencyRecord {
+    pub fingerprint: String,
+    pub response: Option<String>,
+}
+
+impl IdempotencyRecord {
+    pub(crate) fn encode(&self) -> Result<String, ServiceError> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    pub(crate) fn into_state(self) -> IdempotencyState {
+        match self.response {
+            None => IdempotencyState::InProgress { fingerprint: self.fingerprint },
+            Some(response) => IdempotencyState::Completed { fingerprint: self.fingerprint, response },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdempotencyState {
+    /// 首次出现，已占用
+    Started,
+    /// 相同键的请求仍在处理
+    InProgress { fingerprint: String },
+    /// 已完成，保存的响应 JSON
+    Completed { fingerprint: String, response: String },
+}
+
+pub trait IdempotencyStore: Send + Sync {
+    fn begin(&self, key: &str, fingerprint: &str, ttl_secs: u64) -> Result<IdempotencyState, ServiceError>;
+    fn complete(&self, key: &str, fingerprint: &str, response: &str, ttl_secs: u64) -> Result<(), ServiceError>;
+    fn release(&self, key: &str) -> Result<(), ServiceError>;
+}
+
+impl IdempotencyStore for InvoiceRedisService {
+    fn begin(&self, key: &str, fingerprint: &str, ttl_secs: u64) -> Result<IdempotencyState, ServiceError> {
+        let placeholder = IdempotencyRecord { fingerprint: fingerprint.to_string(), response: None }.encode()?;
+        Ok(match self.claim_idempotency_key(key, &placeholder, ttl_secs)? {
+            None => IdempotencyState::Started,
+            Some(value) => serde_json::from_str::<IdempotencyRecord>(&value)?.into_state(),
+        })
+    }
+
+    fn complete(&self, key: &str, fingerprint: &str, response: &str, ttl_secs: u64) -> Result<(), ServiceError> {
+        let record = IdempotencyRecord { fingerprint: fingerprint.to_string(), response: Some(response.to_string()) };
+        self.store_idempotent_response(key, &record.encode()?, ttl_secs)
+    }
+
+    fn release(&self, key: &str) -> Result<(), ServiceError> {
+        self.release_idempotency_key(key)
+    }
+}
+
+/// 认购请求的幂等键，按用户隔离
+pub fn purchase_idempotency_key(user_address: &str, key: &str) -> String {
+    format!("idempotency:purchase:{}:{}", user_address.to_lowercase(), key)
+}
+
+/// 校验客户端传入的幂等键：非空、不超过 128 个可见 ASCII 字符
+pub fn is_valid_idempotency_key(key: &str) -> bool {
+    !key.is_empty() && key.len() <= MAX_IDEMPOTENCY_KEY_LEN && key.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// 请求体的指纹 (序列化后 JSON 的 SHA-256)
+pub fn request_fingerprint<B: Serialize>(body: &B) -> Result<String, ServiceError> {
+    Ok(hex::encode(Sha256::digest(serde_json::to_vec(body)?)))
+}
+
+/// 以幂等方式执行 `f`。`key` 为空时直接执行；`fingerprint` 为本次请求体的指纹
+pub async fn run_idempotent<S, T, F, Fut>(store: &S, key: Option<&str>, fingerprint: &str, ttl_secs: u64, f: F) -> Result<T, ServiceError>
+where
+    S: IdempotencyStore + ?Sized,
+    T: Serialize + DeserializeOwned,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ServiceError>>,
+{
+    let Some(key) = key else {
+        return f().await;
+    };
+    match store.begin(key, fingerprint, ttl_secs)? {
+        IdempotencyState::Started => {}
+        IdempotencyState::Completed { fingerprint: stored, response } if stored == fingerprint => return Ok(serde_json::from_str(&response)?),
+        IdempotencyState::InProgress { fingerprint: stored } if stored == fingerprint => {
+            return Err(ServiceError::IdempotencyKeyInProgress(key.to_string()));
+        }
+        // 相同键被用于不同的请求体
+        _ => return Err(ServiceError::IdempotencyKeyReused(key.to_string())),
+    }
+
+    match f().await {
+        Ok(value) => {
+            // 保存失败时占位值会保留到过期，重试请求得到 409 而不会重复执行
+            match serde_json::to_string(&value) {
+                Ok(response) => {
+                    if let Err(e) = store.complete(key, fingerprint, &response, ttl_secs) {
+                        warn!("Failed to store idempotent response for {}: {}", key, e);
+                    }
+                }
+                Err(e) => warn!("Failed to serialize idempotent response for {}: {}", key, e),
+            }
+            Ok(value)
+        }
+        Err(e) => {
+            if let Err(release_err) = store.release(key) {
+                warn!("Failed to release idempotency key {}: {}", key, release_err);
+            }
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::test_support::MemoryIdempotencyStore;
+
+    const BODY: &str = "body-fingerprint";
+
+    #[derive(Debug, Clone, PartialEq, Serialize, serde::Deserialize)]
+    struct Receipt {
+        holding_id: String,
+        amount: String,
+    }
+
+    /// 模拟一次链上写入，记录执行次数
+    async fn purchase(executions: &AtomicUsize) -> Result<Receipt, ServiceError> {
+        let n = executions.fetch_add(1, Ordering::SeqCst) + 1;
+        Ok(Receipt { holding_id: format!("H-{}", n), amount: "100.00".to_string() })
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_request_returns_cached_response() {
+        let store = MemoryIdempotencyStore::default();
+        let executions = AtomicUsize::new(0);
+        let key = purchase_idempotency_key("0xAbC", "retry-1");
+
+        let first = run_idempotent(&store, Some(&key), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        let second = run_idempotent(&store, Some(&key), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+
+        // 不带幂等键的请求照常执行
+        run_idempotent(&store, None, BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_per_user() {
+        let store = MemoryIdempotencyStore::default();
+        let executions = AtomicUsize::new(0);
+        let alice = purchase_idempotency_key("0xaaa", "same-key");
+        let bob = purchase_idempotency_key("0xbbb", "same-key");
+        assert_eq!(alice, purchase_idempotency_key("0xAAA", "same-key"));
+
+        let a = run_idempotent(&store, Some(&alice), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        let b = run_idempotent(&store, Some(&bob), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        assert_ne!(a, b);
+        assert_eq!(executions.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_can_be_retried() {
+        let store = MemoryIdempotencyStore::default();
+        let executions = AtomicUsize::new(0);
+        let key = purchase_idempotency_key("0xabc", "retry-2");
+
+        let failed: Result<Receipt, ServiceError> = run_idempotent(&store, Some(&key), BODY, IDEMPOTENCY_TTL_SECS, || async {
+            Err(ServiceError::InvoiceNotAvailable("INV-1".to_string()))
+        })
+        .await;
+        assert!(failed.is_err());
+        run_idempotent(&store, Some(&key), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_duplicate_is_rejected() {
+        let store = MemoryIdempotencyStore::default();
+        let executions = AtomicUsize::new(0);
+        let key = purchase_idempotency_key("0xabc", "retry-3");
+        assert_eq!(store.begin(&key, BODY, IDEMPOTENCY_TTL_SECS).unwrap(), IdempotencyState::Started);
+
+        let result = run_idempotent(&store, Some(&key), BODY, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await;
+        assert!(matches!(result, Err(ServiceError::IdempotencyKeyInProgress(_))));
+        assert_eq!(executions.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_reused_key_with_different_body_is_rejected() {
+        let store = MemoryIdempotencyStore::default();
+        let executions = AtomicUsize::new(0);
+        let key = purchase_idempotency_key("0xabc", "retry-4");
+        let first_body = request_fingerprint(&serde_json::json!({ "invoice_id": "INV-1", "units": 1 })).unwrap();
+        let other_body = request_fingerprint(&serde_json::json!({ "invoice_id": "INV-1", "units": 5 })).unwrap();
+        assert_ne!(first_body, other_body);
+
+        run_idempotent(&store, Some(&key), &first_body, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await.unwrap();
+        let reused = run_idempotent(&store, Some(&key), &other_body, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await;
+        assert!(matches!(reused, Err(ServiceError::IdempotencyKeyReused(_))));
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+
+        // 处理中的键同样按请求体区分
+        let pending = purchase_idempotency_key("0xabc", "retry-5");
+        store.begin(&pending, &first_body, IDEMPOTENCY_TTL_SECS).unwrap();
+        let reused = run_idempotent(&store, Some(&pending), &other_body, IDEMPOTENCY_TTL_SECS, || purchase(&executions)).await;
+        assert!(matches!(reused, Err(ServiceError::IdempotencyKeyReused(_))));
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_key_validation() {
+        assert!(is_valid_idempotency_key("0b6e4c1a-8f1e-4a3b-9d7c-2f1e0a9b8c7d"));
+        assert!(!is_valid_idempotency_key(""));
+        assert!(!is_valid_idempotency_key("has space"));
+        assert!(!is_valid_idempotency_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LEN + 1)));
+    }
+}
@@ -0,0 +1,134 @@
+//! 票据状态变更的 Redis pub/sub 通道：`InvoiceService` 提交状态变更后发布，
+//! `GET /invoice/{id}/events` 的 SSE 连接按票据订阅，多实例部署时任一实例的变更都能推送到所有订阅者
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::{Stream, StreamExt};
+use tracing::{error, warn};
+use redis::{AsyncCommands, Client};
+
+use common::domain::dto::invoice_status_event_dto::InvoiceStatusEventDto;
+
+use crate::error::ServiceError;
+use crate::invoice::status_events::{InvoiceStatusChange, InvoiceStatusNotifier};
+
+/// 单张票据的事件频道
+pub fn invoice_events_channel(invoice_id: &str) -> String {
+    format!("invoice:events:{}", invoice_id)
+}
+
+/// 推送给订阅者的事件内容
+pub fn status_event(change: &InvoiceStatusChange) -> InvoiceStatusEventDto {
+    InvoiceStatusEventDto {
+        invoice_id: change.invoice_id.to_hex(),
+        invoice_number: change.invoice_number.clone(),
+        from: change.from,
+        to: change.to,
+        occurred_at: change.occurred_at.timestamp_millis(),
+    }
+}
+
+#[derive(Clone)]
+pub struct InvoiceEventBus {
+    client: Client,
+}
+
+impl InvoiceEventBus {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// 发布事件，返回收到事件的订阅者数量 (没有订阅者时为 0)
+    pub async fn publish(&self, event: &InvoiceStatusEventDto) -> Result<u32, ServiceError> {
+        let payload = serde_json::to_string(event).map_err(|e| ServiceError::SerializationError(e.to_string()))?;
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let receivers: u32 = conn.publish(invoice_events_channel(&event.invoice_id), payload).await?;
+        Ok(receivers)
+    }
+
+    /// 订阅票据的状态变更事件。每个订阅独占一条 Redis 连接，返回的流被丢弃 (如客户端断开) 时连接随之关闭
+    pub async fn subscribe(&self, invoice_id: &str) -> Result<impl Stream<Item = InvoiceStatusEventDto> + Send + 'static, ServiceError> {
+        let mut pubsub = self.client.get_async_pubsub().await?;
+        pubsub.subscribe(invoice_events_channel(invoice_id)).await?;
+        Ok(pubsub.into_on_message().filter_map(|msg| async move {
+            let payload: String = match msg.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!("Dropping unreadable invoice event on {}: {}", msg.get_channel_name(), e);
+                    return None;
+                }
+            };
+            match serde_json::from_str(&payload) {
+                Ok(event) => Some(event),
+                Err(e) => {
+                    warn!("Dropping malformed invoice event {}: {}", payload, e);
+                    None
+                }
+            }
+        }))
+    }
+}
+
+#[async_trait]
+impl InvoiceStatusNotifier for InvoiceEventBus {
+    async fn invoice_status_changed(self: Arc<Self>, change: InvoiceStatusChange) {
+        if let Err(e) = self.publish(&status_event(&change)).await {
+            error!("Failed to publish status event for invoice {} ({:?} -> {:?}): {}", change.invoice_id, change.from, change.to, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use common::domain::dto::invoice_dto::CreateInvoiceDto;
+    use common::domain::entity::{Invoice, invoice_status::InvoiceStatus};
+    use mongodb::bson::oid::ObjectId;
+    use crate::test_support::redis_test_client;
+
+    fn status_change(from: InvoiceStatus, to: InvoiceStatus) -> InvoiceStatusChange {
+        let invoice = Invoice::new(&CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 1000,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 1_800_000_000_000,
+            currency: "USDC".to_string(),
+        });
+        InvoiceStatusChange::new(ObjectId::new(), &invoice, from, to)
+    }
+
+    #[test]
+    fn test_status_event_payload() {
+        let change = status_change(InvoiceStatus::OnSale, InvoiceStatus::Financed);
+        let event = status_event(&change);
+        assert_eq!(invoice_events_channel(&event.invoice_id), format!("invoice:events:{}", change.invoice_id.to_hex()));
+        assert_eq!(event.occurred_at, change.occurred_at.timestamp_millis());
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["from"], "ON_SALE");
+        assert_eq!(json["to"], "FINANCED");
+    }
+
+    /// 需要 Redis：REDIS_TEST_URL 未设置时跳过
+    #[tokio::test]
+    async fn test_published_change_reaches_subscriber() {
+        let Some(client) = redis_test_client() else { return };
+        let bus = Arc::new(InvoiceEventBus::new(client));
+        let change = status_change(InvoiceStatus::OnSale, InvoiceStatus::Financed);
+        let other = status_change(InvoiceStatus::Verified, InvoiceStatus::Packaged);
+        let mut events = Box::pin(bus.subscribe(&change.invoice_id.to_hex()).await.unwrap());
+
+        // 其他票据的事件不会推送给该订阅者
+        bus.clone().invoice_status_changed(other).await;
+        bus.clone().invoice_status_changed(change.clone()).await;
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.next()).await
+            .expect("no event received")
+            .expect("stream closed");
+        assert_eq!(event, status_event(&change));
+    }
+}
@@ -15,6 +15,11 @@ impl InvoiceRedisService {
         Self { client }
     }
 
+    /// 底层 Redis 客户端，供共享同一连接配置的锁使用
+    pub fn redis_client(&self) -> Client {
+        self.client.clone()
+    }
+
     // 获取Redis连接
     fn get_connection(&self) -> Result<Connection> {
         let conn = self.client.get_connection()?;
@@ -89,6 +94,27 @@ impl InvoiceRedisService {
         Ok(())
     }
 
+    // 归还份数 (取消/过期的预约)，不超过总份数
+    pub fn release_invoice_shares(&self, invoice_id: &str, shares_to_add: u64) -> Result<(), ServiceError> {
+        let key = format!("invoice:{}", invoice_id);
+        let mut conn = self.get_connection()?;
+
+        let current_json: Option<String> = conn.get(&key)?;
+        let Some(current_json) = current_json else {
+            // 票据已下架，无需归还
+            return Ok(());
+        };
+        let mut invoice: InvoiceRedisDto = serde_json::from_str(&current_json)
+            .map_err(|e| ServiceError::SerializationError(format!("Failed to deserialize invoice from Redis: {}", e)))?;
+
+        invoice.available_shares = (invoice.available_shares + shares_to_add).min(invoice.total_shares);
+
+        let updated_json = serde_json::to_string(&invoice)
+            .map_err(|e| ServiceError::SerializationError(format!("Failed to serialize updated invoice: {}", e)))?;
+        let _: () = conn.set(&key, updated_json)?;
+        Ok(())
+    }
+
     // 添加新票据到Redis
     pub fn add_invoice(&self, invoice: InvoiceRedisDto) -> Result<(), ServiceError> {
         let mut conn = self.get_connection()?;
@@ -123,4 +149,35 @@ impl InvoiceRedisService {
         let _: () = conn.set(&key, json_data)?; 
         Ok(())
     }
+
+    // 占用幂等键：键不存在时写入占位值并返回 None；已存在时返回已保存的值
+    pub fn claim_idempotency_key(&self, key: &str, placeholder: &str, ttl_secs: u64) -> Result<Option<String>, ServiceError> {
+        let mut conn = self.get_connection()?;
+        // SET NX 与 GET 之间键可能恰好过期，重试一次
+        for _ in 0..2 {
+            let claimed: Option<String> = redis::cmd("SET").arg(key).arg(placeholder).arg("NX").arg("EX").arg(ttl_secs).query(&mut conn)?;
+            if claimed.is_some() {
+                return Ok(None);
+            }
+            let existing: Option<String> = conn.get(key)?;
+            if existing.is_some() {
+                return Ok(existing);
+            }
+        }
+        Ok(Some(placeholder.to_string()))
+    }
+
+    // 保存幂等键对应的响应
+    pub fn store_idempotent_response(&self, key: &str, response: &str, ttl_secs: u64) -> Result<(), ServiceError> {
+        let mut conn = self.get_connection()?;
+        let _: () = conn.set_ex(key, response, ttl_secs)?;
+        Ok(())
+    }
+
+    // 释放幂等键 (请求失败时允许客户端重试)
+    pub fn release_idempotency_key(&self, key: &str) -> Result<(), ServiceError> {
+        let mut conn = self.get_connection()?;
+        let _: () = conn.del(key)?;
+        Ok(())
+    }
 }
@@ -1,9 +1,19 @@
+pub mod idempotency;
+pub mod invoice_event_bus;
 pub mod invoice_redis_service;
+pub mod purchase_lock;
+pub mod reservation_service;
+pub mod settlement_lock;
+pub mod token_holder_cache;
 
+pub use invoice_event_bus::InvoiceEventBus;
 pub use invoice_redis_service::InvoiceRedisService;
+pub use reservation_service::ReservationService;
+pub use settlement_lock::RedisSettlementLock;
+pub use token_holder_cache::TokenHolderCache;
 
 use anyhow::{Result, Context};
-use log::info;
+use tracing::info;
 use redis::{Client, RedisError};
 use configs::cfgs::Redis;
 
@@ -0,0 +1,105 @@
+//! 票据认购锁：同一票据同时只允许一笔认购执行 "检查可售份数 -> 写入 -> 扣减份数"，避免超额认购
+//!
+//! 与结算共用 [`SettlementLock`] 的实现 (Redis 中为 [`RedisSettlementLock`](crate::cache::RedisSettlementLock))，
+//! 键为 `lock:purchase:{invoice_id}`，TTL 由 `[purchase] lock_ttl_ms` 配置。
+
+use std::future::Future;
+
+use tracing::warn;
+
+use crate::error::ServiceError;
+use crate::invoice::settlement_executor::SettlementLock;
+
+fn purchase_lock_key(invoice_id: &str) -> String {
+    format!("lock:purchase:{}", invoice_id)
+}
+
+/// 持有票据认购锁执行 `f`，无论成功或失败都会释放锁。锁被占用时立即返回 `PurchaseInProgress`
+pub async fn with_purchase_lock<L, T, F, Fut>(lock: &L, invoice_id: &str, ttl_ms: u64, f: F) -> Result<T, ServiceError>
+where
+    L: SettlementLock + ?Sized,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, ServiceError>>,
+{
+    let key = purchase_lock_key(invoice_id);
+    let token = uuid::Uuid::new_v4().to_string();
+    if !lock.try_acquire(&key, &token, ttl_ms).await? {
+        return Err(ServiceError::PurchaseInProgress(invoice_id.to_string()));
+    }
+
+    let result = f().await;
+    if let Err(e) = lock.release(&key, &token).await {
+        // 释放失败时锁会在 TTL 后自动过期
+        warn!("Failed to release purchase lock for invoice {}: {}", invoice_id, e);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::time::Duration;
+    use crate::test_support::MemoryLock;
+
+    const PURCHASE_LOCK_TTL_MS: u64 = 30_000;
+
+    /// 模拟认购：读可售份数 -> 链上/数据库写入 (耗时) -> 扣减份数
+    async fn buy_one_share(shares: &AtomicU64) -> Result<(), ServiceError> {
+        if shares.load(Ordering::SeqCst) == 0 {
+            return Err(ServiceError::InvoiceNotAvailable("INV-1".to_string()));
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        shares.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_purchases_of_single_share() {
+        let lock = Arc::new(MemoryLock::default());
+        let shares = Arc::new(AtomicU64::new(1));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let (lock, shares) = (lock.clone(), shares.clone());
+                tokio::spawn(async move {
+                    with_purchase_lock(lock.as_ref(), "INV-1", PURCHASE_LOCK_TTL_MS, || buy_one_share(&shares)).await
+                })
+            })
+            .collect();
+
+        let mut succeeded = 0;
+        for task in tasks {
+            match task.await.unwrap() {
+                Ok(()) => succeeded += 1,
+                Err(ServiceError::PurchaseInProgress(_)) | Err(ServiceError::InvoiceNotAvailable(_)) => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert_eq!(succeeded, 1);
+        assert_eq!(shares.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_lock_released_on_error() {
+        let lock = MemoryLock::default();
+        let failed: Result<(), ServiceError> = with_purchase_lock(&lock, "INV-1", PURCHASE_LOCK_TTL_MS, || async {
+            Err(ServiceError::InternalError("contract call reverted".to_string()))
+        })
+        .await;
+        assert!(failed.is_err());
+        assert!(lock.is_empty());
+
+        let retried = with_purchase_lock(&lock, "INV-1", PURCHASE_LOCK_TTL_MS, || async { Ok(()) }).await;
+        assert!(retried.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_contention_returns_purchase_in_progress() {
+        let lock = MemoryLock::default();
+        assert!(lock.try_acquire(&purchase_lock_key("INV-1"), "other", PURCHASE_LOCK_TTL_MS).await.unwrap());
+        let result = with_purchase_lock(&lock, "INV-1", PURCHASE_LOCK_TTL_MS, || async { Ok(()) }).await;
+        assert!(matches!(result, Err(ServiceError::PurchaseInProgress(_))));
+    }
+}
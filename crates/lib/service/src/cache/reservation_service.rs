@@ -0,0 +1,336 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{debug, error, info, warn};
+use redis::{Client, Commands};
+use rust_decimal::Decimal;
+use rust_decimal::prelude::ToPrimitive;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use common::domain::dto::reservation_dto::ReservationDto;
+use common::utils::money::Money;
+use configs::cfgs::Purchase;
+use crate::cache::{InvoiceRedisService, RedisSettlementLock};
+use crate::cache::purchase_lock::with_purchase_lock;
+use crate::error::ServiceError;
+
+/// 全部预约的过期时间索引 (有序集合，score 为过期时间毫秒，member 为 `{user}:{预约 ID}`)
+const EXPIRY_KEY: &str = "reservations:expiry";
+
+/// 票据预约服务
+///
+/// 每个用户的预约保存在 Hash `reservations:{user}` 中 (field 为预约 ID)，过期时间同时写入 [`EXPIRY_KEY`]，
+/// 由后台任务 [`ReservationService::spawn_sweeper`] 定期清理。预约时即从票据可用份数中扣除，取消或过期后归还，
+/// 认购时使用的预约直接转为认购份数。所有份数变更都持有票据认购锁 (与认购共用)，不会和并发认购互相覆盖。
+pub struct ReservationService {
+    client: Client,
+    redis_service: Arc<InvoiceRedisService>,
+    ttl_secs: i64,
+    purchase_lock: RedisSettlementLock,
+    purchase_lock_ttl_ms: u64,
+}
+
+impl ReservationService {
+    pub fn new(client: Client, redis_service: Arc<InvoiceRedisService>, ttl_secs: i64) -> Self {
+        let purchase_lock = RedisSettlementLock::new(client.clone());
+        Self { client, redis_service, ttl_secs, purchase_lock, purchase_lock_ttl_ms: Purchase::default().lock_ttl_ms }
+    }
+
+    /// 票据认购锁 TTL，与认购使用相同的配置
+    pub fn with_purchase_lock_ttl(mut self, ttl_ms: u64) -> Self {
+        self.purchase_lock_ttl_ms = ttl_ms;
+        self
+    }
+
+    fn user_key(user_address: &str) -> String {
+        format!("reservations:{}", user_address.to_lowercase())
+    }
+
+    fn expiry_member(reservation: &ReservationDto) -> String {
+        format!("{}:{}", reservation.user_id.to_lowercase(), reservation.reservation_id)
+    }
+
+    /// 预约票据份额
+    pub async fn reserve(&self, user_address: &str, invoice_id: &str, amount: Money) -> Result<ReservationDto, ServiceError> {
+        with_purchase_lock(&self.purchase_lock, invoice_id, self.purchase_lock_ttl_ms, || async {
+            self.reserve_locked(user_address, invoice_id, amount)
+        })
+        .await
+    }
+
+    fn reserve_locked(&self, user_address: &str, invoice_id: &str, amount: Money) -> Result<ReservationDto, ServiceError> {
+        let invoice = self.redis_service.get_invoice(invoice_id)?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_string()))?;
+        if !invoice.is_available_for_purchase() {
+            return Err(ServiceError::InvoiceNotAvailable(invoice_id.to_string()));
+        }
+
+        let share_price = invoice.share_price;
+        if !amount.is_positive() || !share_price.is_positive() {
+            return Err(ServiceError::InvalidPurchaseAmount("Reservation amount and share price must be positive".to_string()));
+        }
+        let shares = amount.ratio(share_price)?.round().to_u64().unwrap_or(0);
+        if shares == 0 {
+            return Err(ServiceError::InvalidPurchaseAmount("Reservation amount too small to reserve any shares".to_string()));
+        }
+
+        // 先扣减份数，失败 (份数不足) 时不写入预约
+        self.redis_service.update_invoice_shares(invoice_id, shares)?;
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let reservation = ReservationDto {
+            reservation_id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_address.to_string(),
+            invoice_id: invoice_id.to_string(),
+            invoice_number: invoice.invoice_number,
+            shares,
+            reserved_amount: share_price.checked_mul(Decimal::from(shares))?.to_string(),
+            created_at: now_ms,
+            expires_at: now_ms + self.ttl_secs * 1000,
+        };
+        if let Err(e) = self.store(&reservation) {
+            // 预约未写入，归还刚扣减的份数
+            self.redis_service.release_invoice_shares(invoice_id, shares)?;
+            return Err(e);
+        }
+
+        info!("User {} reserved {} shares of invoice {}", user_address, shares, invoice_id);
+        Ok(reservation)
+    }
+
+    /// 列出用户未过期的预约 (按过期时间排序)，读到的过期预约顺带归还份数
+    pub async fn list_active(&self, user_address: &str) -> Result<Vec<ReservationDto>, ServiceError> {
+        let key = Self::user_key(user_address);
+        let entries: Vec<(String, String)> = self.client.get_connection()?.hgetall(&key)?;
+
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut active = Vec::new();
+        for (id, json) in entries {
+            let Some(reservation) = self.parse_or_drop(&key, &id, &json)? else { continue };
+            if !reservation.is_expired(now_ms) {
+                active.push(reservation);
+                continue;
+            }
+            // 票据正在认购时留给清理任务处理
+            match self.release(&reservation).await {
+                Ok(_) | Err(ServiceError::PurchaseInProgress(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        active.sort_by_key(|r| r.expires_at);
+        Ok(active)
+    }
+
+    /// 提前取消预约并归还份数，预约不存在或已过期时返回 false。票据正在认购时返回 `PurchaseInProgress`
+    pub async fn cancel(&self, user_address: &str, reservation_id: &str) -> Result<bool, ServiceError> {
+        let json: Option<String> = self.client.get_connection()?.hget(Self::user_key(user_address), reservation_id)?;
+        let Some(json) = json else { return Ok(false) };
+        let reservation: ReservationDto = serde_json::from_str(&json)?;
+        if reservation.is_expired(chrono::Utc::now().timestamp_millis()) {
+            return Ok(false);
+        }
+        self.release(&reservation).await
+    }
+
+    /// 查找用户在 `invoice_id` 上未过期的预约，用于认购时抵扣份数
+    pub fn find_active(&self, user_address: &str, reservation_id: &str, invoice_id: &str) -> Result<ReservationDto, ServiceError> {
+        let json: Option<String> = self.client.get_connection()?.hget(Self::user_key(user_address), reservation_id)?;
+        let reservation = json.map(|json| serde_json::from_str::<ReservationDto>(&json)).transpose()?
+            .filter(|r| r.invoice_id == invoice_id && !r.is_expired(chrono::Utc::now().timestamp_millis()));
+        reservation.ok_or_else(|| ServiceError::ReservationNotFound(reservation_id.to_string()))
+    }
+
+    /// 认购使用预约：移除预约但不归还份数 (份数转为认购)。调用方需持有票据认购锁，预约已被移除时返回 `ReservationNotFound`
+    pub fn consume(&self, reservation: &ReservationDto) -> Result<(), ServiceError> {
+        if !self.remove(reservation)? {
+            return Err(ServiceError::ReservationNotFound(reservation.reservation_id.clone()));
+        }
+        Ok(())
+    }
+
+    /// 认购失败时恢复已移除的预约。调用方需持有票据认购锁
+    pub fn restore(&self, reservation: &ReservationDto) -> Result<(), ServiceError> {
+        self.store(reservation)
+    }
+
+    /// 清理最多 `limit` 条已过期的预约并归还份数，返回清理的数量。票据正在认购的预约留到下一轮
+    pub async fn sweep_expired(&self, limit: isize) -> Result<usize, ServiceError> {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let members: Vec<String> = self.client.get_connection()?.zrangebyscore_limit(EXPIRY_KEY, "-inf", now_ms, 0, limit)?;
+
+        let mut released = 0;
+        for member in members {
+            let Some((user, id)) = member.split_once(':') else {
+                let _: () = self.client.get_connection()?.zrem(EXPIRY_KEY, &member)?;
+                continue;
+            };
+            let key = Self::user_key(user);
+            let json: Option<String> = self.client.get_connection()?.hget(&key, id)?;
+            let Some(reservation) = json.map(|json| self.parse_or_drop(&key, id, &json)).transpose()?.flatten() else {
+                // 预约已取消或已被认购使用
+                let _: () = self.client.get_connection()?.zrem(EXPIRY_KEY, &member)?;
+                continue;
+            };
+            match self.release(&reservation).await {
+                Ok(true) => released += 1,
+                Ok(false) => {}
+                Err(ServiceError::PurchaseInProgress(_)) => debug!("Invoice {} is being purchased, expired reservation {} deferred", reservation.invoice_id, id),
+                Err(e) => warn!("Failed to release expired reservation {}: {}", id, e),
+            }
+        }
+        Ok(released)
+    }
+
+    /// 启动过期预约清理任务，`shutdown` 变为 true 后退出
+    pub fn spawn_sweeper(self: &Arc<Self>, interval: Duration, batch_size: isize, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval.max(Duration::from_secs(1)));
+            while !*shutdown.borrow() {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                match service.sweep_expired(batch_size.max(1)).await {
+                    Ok(0) => {}
+                    Ok(released) => info!("Released {} expired reservations", released),
+                    Err(e) => error!("Reservation sweep failed: {}", e),
+                }
+            }
+            info!("Reservation sweeper stopped");
+        })
+    }
+
+    // 持有票据认购锁移除预约并归还份数，预约已被移除时返回 false
+    async fn release(&self, reservation: &ReservationDto) -> Result<bool, ServiceError> {
+        with_purchase_lock(&self.purchase_lock, &reservation.invoice_id, self.purchase_lock_ttl_ms, || async {
+            if !self.remove(reservation)? {
+                return Ok(false);
+            }
+            self.redis_service.release_invoice_shares(&reservation.invoice_id, reservation.shares)?;
+            Ok(true)
+        })
+        .await
+    }
+
+    // 预约与过期索引一起写入
+    fn store(&self, reservation: &ReservationDto) -> Result<(), ServiceError> {
+        let json = serde_json::to_string(reservation)?;
+        let mut conn = self.client.get_connection()?;
+        let _: () = redis::pipe().atomic()
+            .hset(Self::user_key(&reservation.user_id), &reservation.reservation_id, json).ignore()
+            .zadd(EXPIRY_KEY, Self::expiry_member(reservation), reservation.expires_at).ignore()
+            .query(&mut conn)?;
+        Ok(())
+    }
+
+    // 同时移除预约与过期索引，HDEL 成功的一方负责后续的份数处理
+    fn remove(&self, reservation: &ReservationDto) -> Result<bool, ServiceError> {
+        let mut conn = self.client.get_connection()?;
+        let (removed, _): (i32, i32) = redis::pipe().atomic()
+            .hdel(Self::user_key(&reservation.user_id), &reservation.reservation_id)
+            .zrem(EXPIRY_KEY, Self::expiry_member(reservation))
+            .query(&mut conn)?;
+        Ok(removed > 0)
+    }
+
+    // 无法解析的预约直接删除
+    fn parse_or_drop(&self, key: &str, id: &str, json: &str) -> Result<Option<ReservationDto>, ServiceError> {
+        match serde_json::from_str(json) {
+            Ok(reservation) => Ok(Some(reservation)),
+            Err(e) => {
+                warn!("Dropping malformed reservation {}: {}", id, e);
+                let _: () = self.client.get_connection()?.hdel(key, id)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use common::domain::dto::invoice_redis_dto::InvoiceRedisDto;
+    use common::domain::entity::invoice_status::InvoiceStatus;
+    use crate::test_support::redis_test_client;
+
+    fn cached_invoice(available_shares: u64) -> InvoiceRedisDto {
+        let invoice_id = uuid::Uuid::new_v4().to_string();
+        InvoiceRedisDto {
+            invoice_number: format!("INV-{}", invoice_id),
+            invoice_id,
+            title: "Test".to_string(),
+            description: None,
+            annual_rate: Decimal::from(5),
+            total_shares: 100,
+            available_shares,
+            share_price: Money::from(10u64),
+            issue_date: NaiveDate::from_ymd_opt(2026, 1, 1).unwrap(),
+            maturity_date: NaiveDate::from_ymd_opt(2026, 12, 31).unwrap(),
+            status: InvoiceStatus::Packaged,
+        }
+    }
+
+    fn available(redis_service: &InvoiceRedisService, invoice_id: &str) -> u64 {
+        redis_service.get_invoice(invoice_id).unwrap().unwrap().available_shares
+    }
+
+    /// 需要 Redis：REDIS_TEST_URL 未设置时跳过
+    #[tokio::test]
+    async fn test_expired_reservation_is_swept_once() {
+        let Some(client) = redis_test_client() else { return };
+        let redis_service = Arc::new(InvoiceRedisService::new(client.clone()));
+        let invoice = cached_invoice(100);
+        redis_service.set_invoice(&invoice).unwrap();
+        // TTL 为 0：创建即过期
+        let service = ReservationService::new(client, redis_service.clone(), 0);
+        let user = format!("0x{}", uuid::Uuid::new_v4().simple());
+
+        let reservation = service.reserve(&user, &invoice.invoice_id, Money::from(30)).await.unwrap();
+        let reserved = available(&redis_service, &invoice.invoice_id);
+        service.sweep_expired(1_000).await.unwrap();
+        let swept = available(&redis_service, &invoice.invoice_id);
+        // 再次清理和取消都不会重复归还
+        service.sweep_expired(1_000).await.unwrap();
+        let cancelled = service.cancel(&user, &reservation.reservation_id).await.unwrap();
+        let after = available(&redis_service, &invoice.invoice_id);
+        redis_service.delete_invoice(&invoice.invoice_id).unwrap();
+
+        assert_eq!((reserved, swept, after), (97, 100, 100));
+        assert!(!cancelled);
+        assert!(service.list_active(&user).await.unwrap().is_empty());
+    }
+
+    /// 需要 Redis：REDIS_TEST_URL 未设置时跳过
+    #[tokio::test]
+    async fn test_consumed_reservation_is_not_released() {
+        let Some(client) = redis_test_client() else { return };
+        let redis_service = Arc::new(InvoiceRedisService::new(client.clone()));
+        let invoice = cached_invoice(100);
+        redis_service.set_invoice(&invoice).unwrap();
+        let service = ReservationService::new(client, redis_service.clone(), 900);
+        let user = format!("0x{}", uuid::Uuid::new_v4().simple());
+
+        let reservation = service.reserve(&user, &invoice.invoice_id, Money::from(50)).await.unwrap();
+        let found = service.find_active(&user, &reservation.reservation_id, &invoice.invoice_id).unwrap();
+        let other_invoice = service.find_active(&user, &reservation.reservation_id, "other");
+        service.consume(&found).unwrap();
+        let consumed_again = service.consume(&found);
+        let cancelled = service.cancel(&user, &reservation.reservation_id).await.unwrap();
+        let after = available(&redis_service, &invoice.invoice_id);
+        // 认购失败时恢复的预约仍可取消
+        service.restore(&found).unwrap();
+        let cancelled_after_restore = service.cancel(&user, &reservation.reservation_id).await.unwrap();
+        let restored = available(&redis_service, &invoice.invoice_id);
+        redis_service.delete_invoice(&invoice.invoice_id).unwrap();
+
+        assert!(matches!(other_invoice, Err(ServiceError::ReservationNotFound(_))));
+        assert!(matches!(consumed_again, Err(ServiceError::ReservationNotFound(_))));
+        assert!(!cancelled);
+        assert_eq!(after, 95);
+        assert!(cancelled_after_restore);
+        assert_eq!(restored, 100);
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use redis::Client;
+
+use crate::error::ServiceError;
+use crate::invoice::settlement_executor::SettlementLock;
+
+// 只有持有者 (值等于 token) 才能删除锁
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+else
+    return 0
+end
+"#;
+
+// 只有持有者才能续期
+const EXTEND_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+else
+    return 0
+end
+"#;
+
+/// 基于 Redis `SET NX PX` 的结算锁
+#[derive(Clone)]
+pub struct RedisSettlementLock {
+    client: Client,
+}
+
+impl RedisSettlementLock {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl SettlementLock for RedisSettlementLock {
+    async fn try_acquire(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let reply: Option<String> = redis::cmd("SET")
+            .arg(key)
+            .arg(token)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut conn)
+            .await?;
+        Ok(reply.is_some())
+    }
+
+    async fn release(&self, key: &str, token: &str) -> Result<(), ServiceError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let _: i32 = redis::Script::new(RELEASE_SCRIPT).key(key).arg(token).invoke_async(&mut conn).await?;
+        Ok(())
+    }
+
+    async fn extend(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError> {
+        let mut conn = self.client.get_multiplexed_async_connection().await?;
+        let extended: i32 = redis::Script::new(EXTEND_SCRIPT).key(key).arg(token).arg(ttl_ms).invoke_async(&mut conn).await?;
+        Ok(extended == 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use crate::test_support::redis_test_client;
+
+    /// 需要 Redis：REDIS_TEST_URL 未设置时跳过
+    #[tokio::test]
+    async fn test_only_holder_can_extend() {
+        let Some(client) = redis_test_client() else { return };
+        let lock = RedisSettlementLock::new(client);
+        let key = format!("lock:settle:test-{}", uuid::Uuid::new_v4());
+
+        assert!(lock.try_acquire(&key, "holder", 200).await.unwrap());
+        assert!(!lock.extend(&key, "other", 10_000).await.unwrap());
+        assert!(lock.extend(&key, "holder", 10_000).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        // 续期后原 TTL 到期也不会被他人抢占
+        assert!(!lock.try_acquire(&key, "other", 200).await.unwrap());
+
+        lock.release(&key, "holder").await.unwrap();
+        assert!(!lock.extend(&key, "holder", 10_000).await.unwrap());
+    }
+}
@@ -0,0 +1,59 @@
+use tracing::warn;
+use redis::{AsyncCommands, Client};
+
+use common::domain::dto::token_holder_dto::TokenHolderDistributionDto;
+
+/// 代币持有人分布缓存：写入新的转账记录时必须调用 `invalidate`
+pub struct TokenHolderCache {
+    client: Client,
+    ttl_secs: u64,
+}
+
+impl TokenHolderCache {
+    pub fn new(client: Client, ttl_secs: u64) -> Self {
+        Self { client, ttl_secs }
+    }
+
+    fn key(batch_id: &str) -> String {
+        format!("token:holders:{}", batch_id)
+    }
+
+    // 缓存读写失败只记录日志，回退到实时计算
+    pub async fn get(&self, batch_id: &str) -> Option<TokenHolderDistributionDto> {
+        let mut conn = match self.client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Redis unavailable when reading holders of {}: {}", batch_id, e);
+                return None;
+            }
+        };
+        let cached: Option<String> = conn.get(Self::key(batch_id)).await.ok()?;
+        cached.and_then(|json| serde_json::from_str(&json).ok())
+    }
+
+    pub async fn set(&self, distribution: &TokenHolderDistributionDto) {
+        if self.ttl_secs == 0 {
+            return;
+        }
+        let Ok(json) = serde_json::to_string(distribution) else { return };
+        match self.client.get_multiplexed_async_connection().await {
+            Ok(mut conn) => {
+                if let Err(e) = conn.set_ex::<_, _, ()>(Self::key(&distribution.batch_id), json, self.ttl_secs).await {
+                    warn!("Failed to cache holders of {}: {}", distribution.batch_id, e);
+                }
+            }
+            Err(e) => warn!("Redis unavailable when caching holders of {}: {}", distribution.batch_id, e),
+        }
+    }
+
+    pub async fn invalidate(&self, batch_id: &str) {
+        match self.client.get_multiplexed_async_connection().await {
+            Ok(mut conn) => {
+                if let Err(e) = conn.del::<_, ()>(Self::key(batch_id)).await {
+                    warn!("Failed to invalidate holders cache of {}: {}", batch_id, e);
+                }
+            }
+            Err(e) => warn!("Redis unavailable when invalidating holders of {}: {}", batch_id, e),
+        }
+    }
+}
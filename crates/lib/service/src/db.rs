@@ -1,8 +1,16 @@
-use mongodb::{Client, Database, options::ClientOptions};
-use log::{info, error};
+use std::future::Future;
+use mongodb::{Client, ClientSession, Database, options::ClientOptions};
+use mongodb::error::UNKNOWN_TRANSACTION_COMMIT_RESULT;
+use tracing::{info, error, warn};
 use mongodb::options::Credential;
 use configs::cfgs::Database as DbConfig;
-use common::domain::entity::{Repayment, User};
+use common::domain::entity::{AuditLog, Enterprise, Invoice, OnchainTransaction, Repayment, RepaymentPayout, TokenMint, Transaction, User, UserInvoiceHolding};
+use crate::error::ServiceError;
+use crate::repository::enterprise_repository::{ENTERPRISE_WALLET_INDEX, LEGACY_ENTERPRISE_WALLET_INDEX};
+use crate::repository::invoice_repository::{INVOICE_PAYEE_STATUS_INDEX, INVOICE_STATUS_INDEX};
+
+/// 事务体遇到 TransientTransactionError 或提交结果未知时的最多尝试次数
+pub const MAX_TRANSACTION_ATTEMPTS: usize = 3;
 
 // MongoDB client initialization
 pub async fn init_mongodb(db_config: &DbConfig) -> Result<Database, mongodb::error::Error> {
@@ -62,5 +70,279 @@ pub async fn create_indexes(db: &Database) -> Result<(), mongodb::error::Error>
     db.collection::<Repayment>("repayments")
         .create_index(index)
         .await?;
+
+    create_invoice_indexes(db).await?;
+    create_transaction_indexes(db).await?;
+    create_onchain_transaction_indexes(db).await?;
+    Ok(())
+}
+
+/// 票据搜索使用的复合索引 (名称需与 InvoiceFilter::index_hint 一致)，重复创建无副作用
+pub async fn create_invoice_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::options::IndexOptions;
+    use mongodb::IndexModel;
+
+    let invoices = db.collection::<Invoice>("invoices");
+    let index = IndexModel::builder()
+        .keys(doc! { "payee": 1, "status": 1, "created_at": -1 })
+        .options(IndexOptions::builder().name(INVOICE_PAYEE_STATUS_INDEX.to_string()).build())
+        .build();
+    invoices.create_index(index).await?;
+    let index = IndexModel::builder()
+        .keys(doc! { "status": 1, "created_at": -1 })
+        .options(IndexOptions::builder().name(INVOICE_STATUS_INDEX.to_string()).build())
+        .build();
+    invoices.create_index(index).await?;
+    // 企业履约统计按付款方筛选
+    let index = IndexModel::builder().keys(doc! { "payer": 1, "status": 1 }).build();
+    invoices.create_index(index).await?;
+    Ok(())
+}
+
+/// 资金流水：按票据与类型查找 (企业履约统计的 $lookup、兑付记录)
+pub async fn create_transaction_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::IndexModel;
+
+    let transactions = db.collection::<Transaction>("transactions");
+    let index = IndexModel::builder().keys(doc! { "invoice_id": 1, "transaction_type": 1, "transaction_date": 1 }).build();
+    transactions.create_index(index).await?;
+    Ok(())
+}
+
+/// 链上交易：tx_hash 唯一，回执轮询按 (status, updated_at) 取待确认交易
+pub async fn create_onchain_transaction_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::options::IndexOptions;
+    use mongodb::IndexModel;
+
+    let transactions = db.collection::<OnchainTransaction>("onchain_transactions");
+    let index = IndexModel::builder()
+        .keys(doc! { "tx_hash": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    transactions.create_index(index).await?;
+    let index = IndexModel::builder().keys(doc! { "status": 1, "updated_at": 1 }).build();
+    transactions.create_index(index).await?;
+    // 投资人按 accounts 查询自己的交易
+    let index = IndexModel::builder().keys(doc! { "accounts": 1, "_id": -1 }).build();
+    transactions.create_index(index).await?;
+    Ok(())
+}
+
+/// 认购代币铸造：每个持仓只铸造一次，后台任务按到期时间扫描
+pub async fn create_token_mint_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::options::IndexOptions;
+    use mongodb::IndexModel;
+
+    let mints = db.collection::<TokenMint>("token_mints");
+    let index = IndexModel::builder()
+        .keys(doc! { "holding_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    mints.create_index(index).await?;
+    let index = IndexModel::builder().keys(doc! { "status": 1, "next_attempt_at": 1 }).build();
+    mints.create_index(index).await?;
+    Ok(())
+}
+
+/// 审计日志：按目标 (如票据变更记录) 查询并按时间排序
+pub async fn create_audit_log_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::IndexModel;
+
+    let audits = db.collection::<AuditLog>("audit_logs");
+    let index = IndexModel::builder().keys(doc! { "target_type": 1, "target_id": 1, "created_at": 1 }).build();
+    audits.create_index(index).await?;
+    Ok(())
+}
+
+/// 还款兑付明细：同一票据同一持有人只有一条
+pub async fn create_repayment_payout_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::options::IndexOptions;
+    use mongodb::IndexModel;
+
+    let payouts = db.collection::<RepaymentPayout>("repayment_payouts");
+    let index = IndexModel::builder()
+        .keys(doc! { "invoice_id": 1, "user_id": 1 })
+        .options(IndexOptions::builder().unique(true).build())
+        .build();
+    payouts.create_index(index).await?;
     Ok(())
-} 
\ No newline at end of file
+}
+
+/// 投资人持仓：认购记录按用户分页并按认购时间倒序
+pub async fn create_holding_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::IndexModel;
+
+    let holdings = db.collection::<UserInvoiceHolding>("user_invoice_holdings");
+    let index = IndexModel::builder().keys(doc! { "user_id": 1, "purchase_date": -1, "holding_id": -1 }).build();
+    holdings.create_index(index).await?;
+    Ok(())
+}
+
+/// 企业：钱包地址唯一且不区分大小写 (历史数据可能以校验和形式保存)。
+/// 已存在仅大小写不同的重复地址时创建失败，需先人工合并
+pub async fn create_enterprise_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::error::ErrorKind;
+    use mongodb::options::{Collation, CollationStrength, IndexOptions};
+    use mongodb::IndexModel;
+
+    let enterprises = db.collection::<Enterprise>("enterprises");
+    let collation = Collation::builder().locale("en").strength(CollationStrength::Secondary).build();
+    let index = IndexModel::builder()
+        .keys(doc! { "wallet_address": 1 })
+        .options(IndexOptions::builder().name(ENTERPRISE_WALLET_INDEX.to_string()).unique(true).collation(collation).build())
+        .build();
+    enterprises.create_index(index).await?;
+    // 旧索引区分大小写，已被上面的索引取代 (IndexNotFound 表示已删除)
+    match enterprises.drop_index(LEGACY_ENTERPRISE_WALLET_INDEX).await {
+        Err(e) if !matches!(*e.kind, ErrorKind::Command(ref c) if c.code == 27) => return Err(e),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// 用户：一个钱包只能关联到一个账户 (只索引已有关联钱包的用户，空数组不参与唯一约束)
+pub async fn create_user_indexes(db: &Database) -> Result<(), mongodb::error::Error> {
+    use mongodb::bson::doc;
+    use mongodb::options::IndexOptions;
+    use mongodb::IndexModel;
+
+    let users = db.collection::<User>("users");
+    let index = IndexModel::builder()
+        .keys(doc! { "linked_wallets": 1 })
+        .options(
+            IndexOptions::builder()
+                .unique(true)
+                .partial_filter_expression(doc! { "linked_wallets": { "$type": "string" } })
+                .build(),
+        )
+        .build();
+    users.create_index(index).await?;
+    Ok(())
+}
+
+/// 条款确认上线前已上架的存量票据没有 `accepted_terms`，标记 `terms_exempt` 使其仍可认购。
+/// 上架前必须接受条款，新票据不会进入上架后的状态而缺少条款，因此每次启动重复执行无副作用。返回本次标记的票据数
+pub async fn exempt_legacy_listed_invoices(db: &Database) -> Result<u64, mongodb::error::Error> {
+    use mongodb::bson::{self, doc, Bson, DateTime};
+    use common::domain::entity::invoice_status::InvoiceStatus;
+
+    let listed: Vec<Bson> = [InvoiceStatus::Packaged, InvoiceStatus::OnSale, InvoiceStatus::Financed, InvoiceStatus::Overdue, InvoiceStatus::Repaid, InvoiceStatus::Defaulted]
+        .iter()
+        .map(bson::to_bson)
+        .collect::<Result<_, _>>()
+        .map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+    let filter = doc! {
+        "status": { "$in": listed },
+        "accepted_terms": Bson::Null,
+        "terms_exempt": { "$ne": true },
+    };
+    let update = doc! { "$set": { "terms_exempt": true, "updated_at": DateTime::now() } };
+    let result = db.collection::<Invoice>("invoices").update_many(filter, update).await?;
+    Ok(result.modified_count)
+}
+
+/// 是否为唯一索引冲突 (E11000)
+pub fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+    use mongodb::error::{ErrorKind, WriteFailure};
+
+    match *err.kind {
+        ErrorKind::Write(WriteFailure::WriteError(ref e)) => e.code == 11000,
+        ErrorKind::Command(ref e) => e.code == 11000,
+        _ => false,
+    }
+}
+
+/// 结束事务：事务体成功则提交 (提交结果未知时重试提交)，失败则回滚并返回原错误
+pub async fn finish_transaction<T>(mut session: ClientSession, result: Result<T, ServiceError>) -> Result<T, ServiceError> {
+    let value = match result {
+        Ok(value) => value,
+        Err(e) => {
+            if let Err(abort_err) = session.abort_transaction().await {
+                error!("Failed to abort transaction: {}", abort_err);
+            }
+            return Err(e);
+        }
+    };
+    let mut attempt = 1;
+    loop {
+        match session.commit_transaction().await {
+            Ok(()) => return Ok(value),
+            Err(e) if e.contains_label(UNKNOWN_TRANSACTION_COMMIT_RESULT) && attempt < MAX_TRANSACTION_ATTEMPTS => {
+                warn!("Transaction commit result unknown (attempt {}), retrying commit: {}", attempt, e);
+                attempt += 1;
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// 执行整个事务 (开启会话、读写、提交)，`TransientTransaction` 错误时重新执行，最多 `max_attempts` 次
+pub async fn retry_transient_transaction<T, F, Fut>(max_attempts: usize, mut run: F) -> Result<T, ServiceError>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: Future<Output = Result<T, ServiceError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match run(attempt).await {
+            Err(ServiceError::TransientTransaction(msg)) if attempt < max_attempts => {
+                warn!("Transient transaction error (attempt {}/{}), retrying: {}", attempt, max_attempts, msg);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_transient_transaction_is_retried() {
+        let calls = AtomicUsize::new(0);
+        let result = retry_transient_transaction(MAX_TRANSACTION_ATTEMPTS, |attempt| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if attempt < 2 {
+                    Err(ServiceError::TransientTransaction("write conflict".to_string()))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), 2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_business_error_and_exhausted_retries_are_returned() {
+        let calls = AtomicUsize::new(0);
+        let result: Result<(), _> = retry_transient_transaction(MAX_TRANSACTION_ATTEMPTS, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(ServiceError::InsufficientFunds("0xabc".to_string(), "10".to_string(), "1".to_string())) }
+        })
+        .await;
+        assert!(matches!(result, Err(ServiceError::InsufficientFunds(..))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        let calls = AtomicUsize::new(0);
+        let result: Result<(), _> = retry_transient_transaction(MAX_TRANSACTION_ATTEMPTS, |_| {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(ServiceError::TransientTransaction("write conflict".to_string())) }
+        })
+        .await;
+        assert!(matches!(result, Err(ServiceError::TransientTransaction(_))));
+        assert_eq!(calls.load(Ordering::SeqCst), MAX_TRANSACTION_ATTEMPTS);
+    }
+}
@@ -3,6 +3,11 @@ use redis::RedisError;
 use mongodb::error::Error as MongoError;
 use anyhow::Error as AnyhowError;
 use serde_json;
+use common::domain::entity::invoice_status::InvoiceStatus;
+use common::domain::entity::UserRole;
+use common::pagination::InvalidCursor;
+use common::utils::money::MoneyError;
+use mongodb::error::TRANSIENT_TRANSACTION_ERROR;
 
 #[derive(Error, Debug, Clone)]
 pub enum ServiceError {
@@ -15,6 +20,10 @@ pub enum ServiceError {
     #[error("MongoDB transaction error: {0}")]
     MongoDbTransactionError(String),
 
+    /// 带 TransientTransactionError 标签的错误，整个事务可以重试
+    #[error("Transient transaction error: {0}")]
+    TransientTransaction(String),
+
     #[error("Serialization error: {0}")]
     SerializationError(String),
 
@@ -65,6 +74,100 @@ pub enum ServiceError {
 
     #[error("Interest already accrued for holding {0} on date {1}")]
     InterestAlreadyAccrued(String, String),
+
+    #[error("Funding terms not accepted for invoice: {0}")]
+    TermsNotAccepted(String),
+
+    #[error("Funding terms cannot be accepted: {0}")]
+    TermsNotAcceptable(String),
+
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+
+    #[error("Settlement before maturity: {0}")]
+    SettlementBeforeMaturity(String),
+
+    #[error("Settlement already in progress for invoice: {0}")]
+    SettlementInProgress(String),
+
+    #[error("Self-funding not allowed: {0}")]
+    SelfFundingNotAllowed(String),
+
+    #[error("Issuing enterprise not verified: {0}")]
+    EnterpriseNotVerified(String),
+
+    #[error("Invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("Blockchain RPC error: {0}")]
+    ChainRpcError(String),
+
+    #[error("Another purchase of invoice {0} is in progress")]
+    PurchaseInProgress(String),
+
+    #[error("Reservation not found or expired: {0}")]
+    ReservationNotFound(String),
+
+    #[error("Request with idempotency key {0} is still in progress")]
+    IdempotencyKeyInProgress(String),
+
+    #[error("Idempotency key {0} was already used for a different request")]
+    IdempotencyKeyReused(String),
+
+    #[error("Invalid invoice status transition: {from:?} -> {to:?}")]
+    InvalidStatusTransition { from: InvoiceStatus, to: InvoiceStatus },
+
+    #[error("Invoice already settled: {0}")]
+    InvoiceAlreadySettled(String),
+
+    #[error("Invalid repayment amount: {0}")]
+    InvalidRepaymentAmount(String),
+
+    #[error("Repayment cannot be settled: {0}")]
+    RepaymentNotAllowed(String),
+
+    #[error("Invoice cannot be refunded: {0}")]
+    RefundNotAllowed(String),
+
+    #[error("Invoice not financed: {0}")]
+    InvoiceNotFinanced(String),
+
+    #[error("Invoice cannot be deleted: {0}")]
+    InvoiceNotDeletable(String),
+
+    #[error("Account has active financed positions: {0}")]
+    ActivePositions(String),
+
+    #[error("Account deleted: {0}")]
+    AccountDeleted(String),
+
+    #[error("Role change not allowed: {from:?} -> {to:?}")]
+    RoleTransitionNotAllowed { from: UserRole, to: UserRole },
+
+    #[error("Cannot demote the last platform admin: {0}")]
+    LastAdmin(String),
+
+    #[error("Insufficient capacity: requested {requested} shares, {remaining} remaining")]
+    InsufficientCapacity { requested: u64, remaining: u64 },
+
+    /// Webhook 回调地址无效或指向本机、内网等不允许的地址
+    #[error("Invalid webhook URL: {0}")]
+    InvalidWebhookUrl(String),
+
+    /// 乐观锁冲突：记录已被并发修改
+    #[error("Stale write: expected version {expected}, current version {actual}")]
+    StaleWrite { expected: i64, actual: i64 },
+}
+
+impl ServiceError {
+    /// 按状态机校验票据状态变更
+    pub fn check_transition(from: InvoiceStatus, to: InvoiceStatus) -> Result<(), ServiceError> {
+        if from.can_transition_to(to) {
+            Ok(())
+        } else {
+            Err(ServiceError::InvalidStatusTransition { from, to })
+        }
+    }
 }
 
 impl From<RedisError> for ServiceError {
@@ -75,7 +178,11 @@ impl From<RedisError> for ServiceError {
 
 impl From<MongoError> for ServiceError {
     fn from(err: MongoError) -> Self {
-        ServiceError::MongoDbError(err.to_string())
+        if err.contains_label(TRANSIENT_TRANSACTION_ERROR) {
+            ServiceError::TransientTransaction(err.to_string())
+        } else {
+            ServiceError::MongoDbError(err.to_string())
+        }
     }
 }
 
@@ -85,6 +192,18 @@ impl From<AnyhowError> for ServiceError {
     }
 }
 
+impl From<InvalidCursor> for ServiceError {
+    fn from(err: InvalidCursor) -> Self {
+        ServiceError::InvalidCursor(err.0)
+    }
+}
+
+impl From<MoneyError> for ServiceError {
+    fn from(err: MoneyError) -> Self {
+        ServiceError::DecimalConversionError(err.to_string())
+    }
+}
+
 // Implement From<serde_json::Error>
 impl From<serde_json::Error> for ServiceError {
     fn from(err: serde_json::Error) -> Self {
@@ -0,0 +1,160 @@
+//! 批量创建票据
+//!
+//! 每条票据单独校验、单独写入，单条失败不影响其余条目；结果按请求顺序逐条返回。
+
+use std::future::Future;
+
+use futures::StreamExt;
+
+use common::domain::dto::batch_invoice_create_dto::{BatchCreateInvoicesDto, BatchCreateItemDto, BatchCreateStatus};
+use common::domain::dto::invoice_dto::{CreateInvoiceDto, InvoiceDataDto};
+use common::domain::entity::Invoice;
+
+/// 未通过校验的条目，`code` 为对外的错误码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchItemError {
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// 已写入数据库的票据，`registration` 为链上登记结果 (未上链时为 None)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedInvoice {
+    pub id: String,
+    pub invoice_number: String,
+    pub registration: Option<Result<(), String>>,
+}
+
+/// 依次处理 `items`，校验通过的条目交给 `create`，最多 `concurrency` 条并发
+pub async fn run_batch<F, Fut>(items: Vec<Result<CreateInvoiceDto, BatchItemError>>, concurrency: usize, create: F) -> BatchCreateInvoicesDto
+where
+    F: Fn(CreateInvoiceDto) -> Fut,
+    Fut: Future<Output = Result<CreatedInvoice, String>>,
+{
+    let create = &create;
+    let mut results: Vec<BatchCreateItemDto> = futures::stream::iter(items.into_iter().enumerate())
+        .map(|(index, item)| async move {
+            match item {
+                Ok(data) => item_result(index, create(data).await),
+                Err(e) => failed(index, e.code, e.message),
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .collect()
+        .await;
+    results.sort_by_key(|r| r.index);
+
+    let failed = results.iter().filter(|r| r.status == BatchCreateStatus::Failed).count() as u32;
+    BatchCreateInvoicesDto { total: results.len() as u32, created: results.len() as u32 - failed, failed, results }
+}
+
+fn item_result(index: usize, outcome: Result<CreatedInvoice, String>) -> BatchCreateItemDto {
+    let created = match outcome {
+        Ok(created) => created,
+        Err(message) => return failed(index, None, message),
+    };
+    let (status, error) = match created.registration {
+        None => (BatchCreateStatus::Created, None),
+        Some(Ok(())) => (BatchCreateStatus::Registered, None),
+        Some(Err(e)) => (BatchCreateStatus::Created, Some(format!("On-chain registration failed: {}", e))),
+    };
+    BatchCreateItemDto { index, status, id: Some(created.id), invoice_number: Some(created.invoice_number), error_code: None, error }
+}
+
+fn failed(index: usize, error_code: Option<String>, message: String) -> BatchCreateItemDto {
+    BatchCreateItemDto { index, status: BatchCreateStatus::Failed, id: None, invoice_number: None, error_code, error: Some(message) }
+}
+
+/// 链上登记使用的票据数据
+pub fn invoice_data(invoice: &Invoice) -> InvoiceDataDto {
+    InvoiceDataDto {
+        payee: invoice.payee.clone(),
+        payer: invoice.payer.clone(),
+        amount: invoice.amount,
+        invoice_ipfs_hash: invoice.invoice_ipfs_hash.clone().unwrap_or_default(),
+        contract_ipfs_hash: invoice.contract_ipfs_hash.clone().unwrap_or_default(),
+        due_date: invoice.due_date,
+        currency: invoice.currency.clone(),
+        invoice_number: invoice.invoice_number.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    fn dto(amount: u64) -> CreateInvoiceDto {
+        CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }
+    }
+
+    fn invalid(code: &str) -> Result<CreateInvoiceDto, BatchItemError> {
+        Err(BatchItemError { code: Some(code.to_string()), message: "rejected".to_string() })
+    }
+
+    #[tokio::test]
+    async fn test_mixed_batch_reports_each_item() {
+        let items = vec![Ok(dto(100)), invalid("INVOICE_AMOUNT_NOT_POSITIVE"), Ok(dto(200)), Ok(dto(300)), Ok(dto(400))];
+        let summary = run_batch(items, 2, |data| async move {
+            match data.amount {
+                // 数据库写入失败
+                300 => Err("insert failed".to_string()),
+                // 上链失败，票据仍已创建
+                400 => Ok(CreatedInvoice { id: "id-400".to_string(), invoice_number: "INV-400".to_string(), registration: Some(Err("reverted".to_string())) }),
+                amount => Ok(CreatedInvoice { id: format!("id-{}", amount), invoice_number: format!("INV-{}", amount), registration: Some(Ok(())) }),
+            }
+        })
+        .await;
+
+        assert_eq!((summary.total, summary.created, summary.failed), (5, 3, 2));
+        let statuses: Vec<_> = summary.results.iter().map(|r| (r.index, r.status)).collect();
+        assert_eq!(statuses, vec![
+            (0, BatchCreateStatus::Registered),
+            (1, BatchCreateStatus::Failed),
+            (2, BatchCreateStatus::Registered),
+            (3, BatchCreateStatus::Failed),
+            (4, BatchCreateStatus::Created),
+        ]);
+        assert_eq!(summary.results[0].id.as_deref(), Some("id-100"));
+        assert_eq!(summary.results[1].error_code.as_deref(), Some("INVOICE_AMOUNT_NOT_POSITIVE"));
+        assert!(summary.results[3].id.is_none());
+        assert_eq!(summary.results[4].id.as_deref(), Some("id-400"));
+        assert!(summary.results[4].error.as_deref().unwrap().contains("reverted"));
+
+        let json = serde_json::to_value(&summary.results[1]).unwrap();
+        assert_eq!(json, serde_json::json!({ "index": 1, "status": "failed", "error_code": "INVOICE_AMOUNT_NOT_POSITIVE", "error": "rejected" }));
+        let json = serde_json::to_value(&summary.results[0]).unwrap();
+        assert_eq!(json, serde_json::json!({ "index": 0, "status": "registered", "id": "id-100", "invoice_number": "INV-100" }));
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_is_bounded() {
+        let in_flight = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let items = (1..=10).map(|i| Ok(dto(i))).collect();
+        let summary = run_batch(items, 3, |data| {
+            let (in_flight, peak) = (&in_flight, &peak);
+            async move {
+                let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                Ok(CreatedInvoice { id: data.amount.to_string(), invoice_number: String::new(), registration: None })
+            }
+        })
+        .await;
+
+        assert_eq!(summary.created, 10);
+        assert!(summary.results.iter().all(|r| r.status == BatchCreateStatus::Created));
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+}
@@ -0,0 +1,170 @@
+//! 管理员批量变更票据状态
+//!
+//! 每张票据单独按状态机校验、单独提交事务 (状态与审计记录一起写入)，单张失败不回滚其余票据；结果按请求顺序逐条返回。
+
+use std::future::Future;
+use std::str::FromStr;
+
+use mongodb::bson::oid::ObjectId;
+
+use common::domain::dto::batch_invoice_transition_dto::{BatchTransitionDto, BatchTransitionItemDto, BatchTransitionStatus};
+use common::domain::entity::invoice_status::InvoiceStatus;
+
+use crate::error::ServiceError;
+
+/// 审计记录中的操作名
+pub const BULK_TRANSITION_ACTION: &str = "bulk_transition";
+
+/// 允许批量变更到的目标状态。Packaged、OnSale 需要链上打包和上架，Financed、Repaid、Cancelled 伴随认购、兑付、退款等资金操作，
+/// 都只能走各自的接口
+pub fn is_bulk_transition_target(to: InvoiceStatus) -> bool {
+    use InvoiceStatus::*;
+    matches!(to, Verified | Overdue | Defaulted)
+}
+
+/// 单张票据变更失败的原因，`from` 为失败时读到的状态 (票据不存在时为空)
+#[derive(Debug, Clone)]
+pub struct TransitionError {
+    pub from: Option<InvoiceStatus>,
+    pub error: ServiceError,
+}
+
+impl From<ServiceError> for TransitionError {
+    fn from(error: ServiceError) -> Self {
+        let from = match &error {
+            ServiceError::InvalidStatusTransition { from, .. } => Some(*from),
+            _ => None,
+        };
+        Self { from, error }
+    }
+}
+
+/// 按请求顺序依次处理 `invoice_ids`，`transition` 成功时返回变更前的状态
+pub async fn run_transitions<F, Fut>(invoice_ids: Vec<String>, to: InvoiceStatus, transition: F) -> BatchTransitionDto
+where
+    F: Fn(ObjectId) -> Fut,
+    Fut: Future<Output = Result<InvoiceStatus, TransitionError>>,
+{
+    let mut results = Vec::with_capacity(invoice_ids.len());
+    for invoice_id in invoice_ids {
+        let item = match ObjectId::from_str(invoice_id.trim()) {
+            Ok(id) => item_result(invoice_id, transition(id).await),
+            Err(_) => failed(invoice_id, None, Some("INVALID_ID"), "Invalid invoice id".to_string()),
+        };
+        results.push(item);
+    }
+
+    let failed = results.iter().filter(|r| r.status == BatchTransitionStatus::Failed).count() as u32;
+    BatchTransitionDto { to, total: results.len() as u32, transitioned: results.len() as u32 - failed, failed, results }
+}
+
+fn item_result(invoice_id: String, outcome: Result<InvoiceStatus, TransitionError>) -> BatchTransitionItemDto {
+    match outcome {
+        Ok(from) => BatchTransitionItemDto { invoice_id, status: BatchTransitionStatus::Transitioned, from: Some(from), error_code: None, error: None },
+        Err(e) => failed(invoice_id, e.from, failure_code(&e.error), e.error.to_string()),
+    }
+}
+
+fn failed(invoice_id: String, from: Option<InvoiceStatus>, error_code: Option<&str>, message: String) -> BatchTransitionItemDto {
+    BatchTransitionItemDto { invoice_id, status: BatchTransitionStatus::Failed, from, error_code: error_code.map(str::to_string), error: Some(message) }
+}
+
+/// 失败原因对应的错误码 (与单条接口一致)，数据库等内部错误不返回错误码
+fn failure_code(error: &ServiceError) -> Option<&'static str> {
+    match error {
+        ServiceError::InvoiceNotFound(_) => Some("INVOICE_NOT_FOUND"),
+        ServiceError::InvalidStatusTransition { .. } => Some("INVALID_STATUS_TRANSITION"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::domain::dto::invoice_dto::CreateInvoiceDto;
+    use crate::invoice::InvoiceService;
+    use crate::repository::InvoiceRepository;
+    use crate::test_support::{TestDb, unused_redis_client};
+
+    #[test]
+    fn test_bulk_targets_exclude_chain_and_money_movements() {
+        let allowed: Vec<_> = InvoiceStatus::ALL.into_iter().filter(|s| is_bulk_transition_target(*s)).collect();
+        use InvoiceStatus::*;
+        assert_eq!(allowed, vec![Verified, Overdue, Defaulted]);
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_mixed_batch_with_illegal_transitions() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let service = InvoiceService::new(db.clone(), unused_redis_client());
+        let create = || CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        };
+        let mut ids = Vec::new();
+        for _ in 0..5 {
+            ids.push(repo.create(&create()).await.unwrap().id.unwrap());
+        }
+        let [pending, second_pending, repaid, verified, deleted] = ids[..] else { unreachable!() };
+        repo.transition_status(repaid, InvoiceStatus::Pending, InvoiceStatus::Repaid).await.unwrap();
+        repo.transition_status(verified, InvoiceStatus::Pending, InvoiceStatus::Verified).await.unwrap();
+        repo.soft_delete(deleted, "0xadmin").await.unwrap();
+
+        let request = vec![
+            pending.to_hex(),
+            repaid.to_hex(),
+            "not-an-id".to_string(),
+            ObjectId::new().to_hex(),
+            second_pending.to_hex(),
+            verified.to_hex(),
+            // 同一请求中重复的 ID：第一次已变更为 Verified，再次变更为非法
+            pending.to_hex(),
+            // 已软删除的票据视为不存在
+            deleted.to_hex(),
+        ];
+        let summary = service.transition_invoices(request, InvoiceStatus::Verified, "0xadmin").await;
+        let mut stored = Vec::new();
+        for id in [pending, second_pending, repaid, deleted] {
+            stored.push(repo.find_by_id(id).await.unwrap().unwrap().status);
+        }
+        let audits = repo.find_audit_trail(pending).await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((summary.total, summary.transitioned, summary.failed), (8, 2, 6));
+        let statuses: Vec<_> = summary.results.iter().map(|r| (r.status, r.error_code.as_deref())).collect();
+        assert_eq!(statuses, vec![
+            (BatchTransitionStatus::Transitioned, None),
+            (BatchTransitionStatus::Failed, Some("INVALID_STATUS_TRANSITION")),
+            (BatchTransitionStatus::Failed, Some("INVALID_ID")),
+            (BatchTransitionStatus::Failed, Some("INVOICE_NOT_FOUND")),
+            (BatchTransitionStatus::Transitioned, None),
+            (BatchTransitionStatus::Failed, Some("INVALID_STATUS_TRANSITION")),
+            (BatchTransitionStatus::Failed, Some("INVALID_STATUS_TRANSITION")),
+            (BatchTransitionStatus::Failed, Some("INVOICE_NOT_FOUND")),
+        ]);
+        // 失败的条目不影响已完成的变更
+        use InvoiceStatus::*;
+        assert_eq!(stored, vec![Verified, Verified, Repaid, Pending]);
+        assert_eq!(audits.iter().filter(|a| a.action == BULK_TRANSITION_ACTION).count(), 1);
+
+        assert_eq!(summary.results[0].from, Some(Pending));
+        assert_eq!(summary.results[1].from, Some(Repaid));
+        assert_eq!(summary.results[3].from, None);
+        let json = serde_json::to_value(&summary.results[1]).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "invoice_id": repaid.to_hex(),
+            "status": "failed",
+            "from": "REPAID",
+            "error_code": "INVALID_STATUS_TRANSITION",
+            "error": "Invalid invoice status transition: Repaid -> Verified",
+        }));
+    }
+}
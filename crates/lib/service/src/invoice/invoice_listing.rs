@@ -0,0 +1,62 @@
+//! 票据分页列表：以 `_id` 作为游标，按 `_id` 倒序 (最新在前)，并发插入的新票据不会打乱后续页
+
+use common::domain::entity::invoice_status::InvoiceStatus;
+use mongodb::bson::{self, Document, doc};
+
+use crate::error::ServiceError;
+use crate::repository::invoice_repository::exclude_deleted;
+
+/// 列表筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct InvoiceListFilter {
+    pub status: Option<InvoiceStatus>,
+    /// 收款方钱包地址 (不区分大小写)
+    pub payee: Option<String>,
+    /// 是否包含已软删除的票据 (仅管理员可用)
+    pub include_deleted: bool,
+}
+
+impl InvoiceListFilter {
+    /// 生成查询条件 (不含游标条件，游标由 `Pagination::apply_cursor` 追加)
+    pub fn to_document(&self) -> Result<Document, ServiceError> {
+        let mut filter = doc! {};
+        if let Some(status) = &self.status {
+            let status = bson::to_bson(status).map_err(|e| ServiceError::SerializationError(e.to_string()))?;
+            filter.insert("status", status);
+        }
+        if let Some(payee) = &self.payee {
+            filter.insert(
+                "payee",
+                bson::Regex { pattern: format!("^{}$", regex::escape(payee)), options: "i".to_string() },
+            );
+        }
+        exclude_deleted(&mut filter, self.include_deleted);
+        Ok(filter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deleted_invoices_only_listed_on_request() {
+        let filter = InvoiceListFilter { status: Some(InvoiceStatus::OnSale), ..Default::default() };
+        assert_eq!(filter.to_document().unwrap(), doc! { "status": "ON_SALE", "deleted_at": bson::Bson::Null });
+
+        let admin = InvoiceListFilter { include_deleted: true, ..filter };
+        assert_eq!(admin.to_document().unwrap(), doc! { "status": "ON_SALE" });
+    }
+
+    #[test]
+    fn test_payee_matches_case_insensitively() {
+        let filter = InvoiceListFilter { payee: Some("0xAb.C".to_string()), ..Default::default() };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! {
+                "payee": bson::Regex { pattern: r"^0xAb\.C$".to_string(), options: "i".to_string() },
+                "deleted_at": bson::Bson::Null,
+            }
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use std::str::FromStr;
 use mongodb::{Database, bson::{doc, Decimal128, oid::ObjectId}};
 use anyhow::{Result, anyhow};
-use chrono::{Utc, NaiveDate, Duration, Datelike, TimeZone};
+use chrono::{Utc, NaiveDate, Duration, TimeZone};
 use crate::{
    
     repository::{
@@ -10,7 +10,14 @@ use crate::{
         DailyInterestAccrualRepository,
         TransactionRepository,
         InvoiceRepository,
+        AuditLogRepository,
     },
+    invoice::settlement_guard::{SettlementOptions, BATCH_SETTLEMENT_STATUSES, ensure_settlement_allowed, is_batch_settlement_eligible},
+    invoice::settlement_executor::{SettlementExecutor, SettlementSubmitter},
+    invoice::batch_create::{BatchItemError, CreatedInvoice, invoice_data, run_batch},
+    invoice::bulk_transition::{BULK_TRANSITION_ACTION, TransitionError, run_transitions},
+    invoice::invoice_listing::InvoiceListFilter,
+    invoice::status_events::{InvoiceStatusChange, InvoiceStatusNotifier},
 };
 use common::domain::{
     entity::{
@@ -20,6 +27,8 @@ use common::domain::{
         HoldingStatus,
         TransactionType,
         Invoice,
+        AuditLog,
+        audit_log::SYSTEM_ACTOR,
         invoice_status::InvoiceStatus,
     },
     dto::{
@@ -27,24 +36,37 @@ use common::domain::{
         purchase_invoice_dto::PurchaseInvoiceDto,
         holding_dto:: HoldingDto,
         interest_detail_dto::InterestDetailDto,
+        batch_settlement_dto::{BatchSettlementDto, BatchSettlementItemDto, BatchSettlementStatus},
+        batch_invoice_create_dto::BatchCreateInvoicesDto,
+        batch_invoice_transition_dto::BatchTransitionDto,
+        invoice_dto::CreateInvoiceDto,
     },
 };
 use redis::Client as RedisClient;
 use common::domain::dto::invoice_redis_dto::InvoiceRedisDto;
-use crate::cache::InvoiceRedisService;
-use futures::TryStreamExt;
-use log::{error, info, warn};
+use common::pagination::{Page, Pagination};
+use common::domain::entity::invoice::InvoiceDto;
+use crate::cache::{InvoiceRedisService, RedisSettlementLock};
+use crate::invoice::reconciliation::reconcile_with_contract;
+use crate::service::interest_calculator::{daily_day_count, year_basis};
+use common::domain::dto::invoice_reconciliation_dto::InvoiceReconciliationDto;
+use pharos_interact::{ContractQuerier, ContractWriter};
+use async_trait::async_trait;
+use futures::{StreamExt, TryStreamExt};
+use tracing::{error, info, warn};
 use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument};
 use mongodb::{ClientSession, Collection};
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use mongodb::bson::DateTime;
 use crate::error::ServiceError;
 use mongodb::error::Error as MongoError;
 use futures::FutureExt;
 use rust_decimal::Decimal;
-use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
-use rust_decimal_macros::dec;
+use rust_decimal::prelude::ToPrimitive;
+use common::utils::money::Money;
+use configs::cfgs::DayCountConvention;
 
 pub struct InvoiceService {
     db: Database,
@@ -54,8 +76,18 @@ pub struct InvoiceService {
     interest_accrual_repo: DailyInterestAccrualRepository,
     transaction_repo: TransactionRepository,
     invoice_repository: InvoiceRepository,
+    audit_repo: AuditLogRepository,
+    settlement_executor: SettlementExecutor<RedisSettlementLock, InvoiceRepository>,
+    status_notifiers: Vec<Arc<dyn InvoiceStatusNotifier>>,
+    /// 每日计息的日计数规则，与应计利息接口一致
+    day_count: DayCountConvention,
 }
 
+/// 结算锁 TTL，需覆盖一张票据全部持仓的兑付耗时
+const SETTLEMENT_LOCK_TTL_MS: u64 = 10 * 60 * 1000;
+/// 其他实例持锁时的最长等待时间，超时后本次跳过该票据
+const SETTLEMENT_LOCK_WAIT_MS: u64 = 5_000;
+
 impl InvoiceService {
     pub fn new(db: Database, redis_client: RedisClient) -> Self {
         Self {
@@ -64,18 +96,154 @@ impl InvoiceService {
             interest_accrual_repo: DailyInterestAccrualRepository::new(&db),
             transaction_repo: TransactionRepository::new(&db),
             invoice_repository: InvoiceRepository::new(&db),
+            audit_repo: AuditLogRepository::new(&db),
+            settlement_executor: SettlementExecutor::new(
+                RedisSettlementLock::new(redis_client.clone()),
+                InvoiceRepository::new(&db),
+                SETTLEMENT_LOCK_TTL_MS,
+                SETTLEMENT_LOCK_WAIT_MS,
+            ),
             invoice_redis_service: InvoiceRedisService::new(redis_client),
+            status_notifiers: Vec::new(),
+            day_count: DayCountConvention::default(),
             db,
         }
     }
+
+    /// 每日计息使用的日计数规则 (默认 ACT/365)，应与 `[interest] day_count` 一致
+    pub fn with_day_count(mut self, day_count: DayCountConvention) -> Self {
+        self.day_count = day_count;
+        self
+    }
+
+    /// 状态变更提交后通知 `notifier` (例如推送企业 webhook、发布 SSE 事件)，可多次调用注册多个
+    pub fn with_status_notifier(mut self, notifier: Arc<dyn InvoiceStatusNotifier>) -> Self {
+        self.status_notifiers.push(notifier);
+        self
+    }
+
+    async fn notify_status_change(&self, change: InvoiceStatusChange) {
+        for notifier in &self.status_notifiers {
+            notifier.clone().invoice_status_changed(change.clone()).await;
+        }
+    }
     
+    /// 游标分页查询票据列表，`pagination.limit` 需已按分页配置截断；`total` 为不含游标条件的总数
+    pub async fn list_invoices(&self, filter: InvoiceListFilter, pagination: Pagination) -> Result<Page<InvoiceDto>, ServiceError> {
+        let filter = filter.to_document()?;
+        let mut page_filter = filter.clone();
+        pagination.apply_cursor(&mut page_filter)?;
+        let total = self.invoice_repository.count(filter).await?;
+        let rows = self.invoice_repository.find_page(page_filter, &pagination).await?;
+        Ok(Page::from_rows(rows, &pagination, total, |invoice| invoice.id, |invoice| InvoiceDto::from(&invoice)))
+    }
+
+    /// 按 ID 查询票据，不存在或已软删除 (且 `include_deleted` 为 false) 时返回 `InvoiceNotFound`
+    pub async fn get_by_id(&self, invoice_id: ObjectId, include_deleted: bool) -> Result<Invoice, ServiceError> {
+        self.invoice_repository.find_by_id(invoice_id).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to find invoice: {}", e)))?
+            .filter(|invoice| include_deleted || !invoice.is_deleted())
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))
+    }
+
+    /// 软删除票据并移出可售缓存，之后不能再认购或兑付
+    ///
+    /// 只有出票企业 (payee) 或管理员可以删除 (否则 `Forbidden`)，且只能删除尚未认购的 Pending 票据 (否则 `InvoiceNotDeletable`)；
+    /// 不存在或已删除时返回 `InvoiceNotFound`
+    pub async fn soft_delete(&self, invoice_id: ObjectId, actor: &str, is_admin: bool) -> Result<(), ServiceError> {
+        let invoice = self.invoice_repository.find_by_id(invoice_id).await?
+            .filter(|invoice| !invoice.is_deleted())
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))?;
+        check_deletable(&invoice, actor, is_admin)?;
+        // 读取后被认购或变更状态时条件更新不生效
+        self.invoice_repository.soft_delete(invoice_id, actor).await?
+            .ok_or_else(|| ServiceError::InvoiceNotDeletable(invoice.invoice_number.clone()))?;
+        if let Err(e) = self.invoice_redis_service.delete_invoice(&invoice_id.to_hex()) {
+            warn!("Invoice {} deleted but could not be removed from the listing cache: {}", invoice_id, e);
+        }
+        info!("Invoice {} soft-deleted by {}", invoice_id, actor);
+        Ok(())
+    }
+
+    /// 批量创建票据，`items` 为逐条校验的结果，单条失败不影响其余条目。
+    /// `writer` 不为空时创建后逐条提交链上登记，写库与上链最多 `concurrency` 条并发
+    pub async fn create_invoices_batch<W: ContractWriter + Sync + ?Sized>(
+        &self,
+        items: Vec<Result<CreateInvoiceDto, BatchItemError>>,
+        writer: Option<&W>,
+        concurrency: usize,
+        actor: &str,
+    ) -> BatchCreateInvoicesDto {
+        let summary = run_batch(items, concurrency, |data| async move {
+            let invoice = self.invoice_repository.create_from_blockchain(&data, actor).await.map_err(|e| e.to_string())?;
+            let registration = match writer {
+                Some(writer) => Some(writer.batch_create_invoices(vec![invoice_data(&invoice)]).await.map(|_| ()).map_err(|e| {
+                    warn!("On-chain registration of invoice {} failed: {:#}", invoice.invoice_number, e);
+                    format!("{:#}", e)
+                })),
+                None => None,
+            };
+            Ok(CreatedInvoice {
+                id: invoice.id.map(|id| id.to_hex()).unwrap_or_default(),
+                invoice_number: invoice.invoice_number,
+                registration,
+            })
+        })
+        .await;
+        info!("Batch invoice creation by {}: {} of {} created", actor, summary.created, summary.total);
+        summary
+    }
+
+    /// 管理员批量变更票据状态，每张票据单独提交 (状态与审计记录同一事务)，失败的票据不影响其余票据
+    pub async fn transition_invoices(&self, invoice_ids: Vec<String>, to: InvoiceStatus, actor: &str) -> BatchTransitionDto {
+        let summary = run_transitions(invoice_ids, to, |id| self.transition_invoice(id, to, actor)).await;
+        info!("Bulk transition to {:?} by {}: {} of {} transitioned", to, actor, summary.transitioned, summary.total);
+        summary
+    }
+
+    // 按状态机变更单张票据，返回变更前的状态；已软删除的票据视为不存在
+    async fn transition_invoice(&self, id: ObjectId, to: InvoiceStatus, actor: &str) -> Result<InvoiceStatus, TransitionError> {
+        let invoice = self.invoice_repository.find_by_id(id).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to find invoice: {}", e)))?
+            .filter(|invoice| !invoice.is_deleted())
+            .ok_or_else(|| ServiceError::InvoiceNotFound(id.to_hex()))?;
+        let from = invoice.status;
+        ServiceError::check_transition(from, to)?;
+
+        let result = self.invoice_repository.transition_status_audited(id, from, to, actor, BULK_TRANSITION_ACTION).await
+            .map_err(|error| TransitionError { from: Some(from), error })?;
+        if result.modified_count == 0 {
+            // 读取后状态已被并发修改
+            return Err(ServiceError::InvalidStatusTransition { from, to }.into());
+        }
+        self.notify_status_change(InvoiceStatusChange::new(id, &invoice, from, to)).await;
+        Ok(from)
+    }
+
+    /// 核对票据数据库记录与合约存储的金额、所有人和状态，返回差异
+    pub async fn reconcile<Q: ContractQuerier + Sync + ?Sized>(&self, invoice_id: ObjectId, querier: &Q) -> Result<InvoiceReconciliationDto, ServiceError> {
+        let invoice = self.invoice_repository.find_by_id(invoice_id).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to find invoice: {}", e)))?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))?;
+        let result = reconcile_with_contract(&invoice, querier).await?;
+        if !result.consistent {
+            warn!("Invoice {} differs from on-chain state: {:?}", invoice_id, result.diffs);
+        }
+        Ok(result)
+    }
+
+    /// 票据的变更记录 (按时间先后)
+    pub async fn invoice_history(&self, invoice_id: ObjectId) -> Result<Vec<AuditLog>, ServiceError> {
+        Ok(self.invoice_repository.find_audit_trail(invoice_id).await?)
+    }
+
     // 获取所有可购买的票据
     pub async fn get_available_invoices(&self) -> Result<Vec<InvoiceRedisDto>, ServiceError> {
         self.invoice_redis_service.get_available_invoices()
     }
     
-    // 上链功能(将票据状态从Pending更新为Verified)
-    pub async fn verify_invoice(&self, invoice_id: &str) -> Result<Invoice, ServiceError> {
+    // 上链功能(将票据状态从Pending更新为Verified)，`actor` 为操作人地址
+    pub async fn verify_invoice(&self, invoice_id: &str, actor: &str) -> Result<Invoice, ServiceError> {
         // 将invoice_id从字符串转换为ObjectId
         let obj_id = ObjectId::from_str(invoice_id)
             .map_err(|_| ServiceError::InternalError(format!("Invalid invoice id: {}", invoice_id)))?;
@@ -85,17 +253,15 @@ impl InvoiceService {
             .map_err(|e| ServiceError::MongoDbError(format!("Failed to find invoice: {}", e)))?
             .ok_or_else(|| ServiceError::NotFound(format!("Invoice not found: {}", invoice_id)))?;
             
-        // 检查票据状态是否为Pending
-        if invoice.status != InvoiceStatus::Pending {
-            return Err(ServiceError::InternalError(format!(
-                "Invoice cannot be verified: status is {:?}, expected Pending", 
-                invoice.status
-            )));
+        // 状态机校验：只有 Pending 票据可以上链
+        ServiceError::check_transition(invoice.status, InvoiceStatus::Verified)?;
+
+        // 条件更新，避免并发请求重复变更；审计记录在同一事务内写入
+        let result = self.invoice_repository.transition_status_audited(obj_id, invoice.status, InvoiceStatus::Verified, actor, "verify").await?;
+        if result.modified_count == 0 {
+            return Err(ServiceError::InvalidStatusTransition { from: invoice.status, to: InvoiceStatus::Verified });
         }
-        
-        // 更新票据状态为Verified
-        self.invoice_repository.update_status(obj_id, InvoiceStatus::Verified).await
-            .map_err(|e| ServiceError::MongoDbError(format!("Failed to update invoice status: {}", e)))?;
+        self.notify_status_change(InvoiceStatusChange::new(obj_id, &invoice, invoice.status, InvoiceStatus::Verified)).await;
             
         // 获取并返回更新后的票据
         self.invoice_repository.find_by_id(obj_id).await
@@ -103,8 +269,49 @@ impl InvoiceService {
             .ok_or_else(|| ServiceError::NotFound(format!("Updated invoice not found: {}", invoice_id)))
     }
     
+    // 企业接受平台报价的融资条款 (Verified 状态下的草稿票据才能接受，接受后才允许上架/融资)
+    pub async fn accept_terms(&self, invoice_id: &str, user_address: &str, apr: &str, platform_fee_rate: &str, quoted_apr: &str, quoted_fee_rate: &str) -> Result<Invoice, ServiceError> {
+        let obj_id = ObjectId::from_str(invoice_id)
+            .map_err(|_| ServiceError::InternalError(format!("Invalid invoice id: {}", invoice_id)))?;
+
+        let invoice = self.invoice_repository.find_by_id(obj_id).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("Invoice not found: {}", invoice_id)))?;
+
+        if !invoice.payee.eq_ignore_ascii_case(user_address) {
+            return Err(ServiceError::Forbidden("Only the payee enterprise can accept funding terms".to_string()));
+        }
+        if invoice.accepted_terms.is_some() {
+            return Err(ServiceError::TermsNotAcceptable("Terms already accepted".to_string()));
+        }
+        if invoice.status != InvoiceStatus::Verified {
+            return Err(ServiceError::TermsNotAcceptable(format!("Invoice status is {:?}, expected Verified", invoice.status)));
+        }
+        // 客户端确认的报价必须和平台当前报价一致，避免接受过期报价
+        if apr != quoted_apr || platform_fee_rate != quoted_fee_rate {
+            return Err(ServiceError::TermsNotAcceptable(format!(
+                "Quoted terms have changed: apr={}, platform_fee_rate={}", quoted_apr, quoted_fee_rate
+            )));
+        }
+
+        let terms = common::domain::entity::AcceptedTerms {
+            apr: quoted_apr.to_string(),
+            platform_fee_rate: quoted_fee_rate.to_string(),
+            accepted_by: user_address.to_lowercase(),
+            accepted_at: DateTime::now(),
+        };
+        let result = self.invoice_repository.accept_terms_audited(obj_id, &terms).await?;
+        if result.modified_count == 0 {
+            // 并发请求已先一步接受或状态已变化
+            return Err(ServiceError::TermsNotAcceptable("Invoice state changed concurrently".to_string()));
+        }
+
+        info!("Funding terms accepted for invoice {} by {}", invoice_id, user_address);
+        self.invoice_repository.find_by_id(obj_id).await?
+            .ok_or_else(|| ServiceError::NotFound(format!("Invoice not found: {}", invoice_id)))
+    }
+
     // 批量发行票据到市场(将票据状态从Verified更新为OnSale)
-    pub async fn issue_invoices(&self, invoice_ids: &[String]) -> Result<usize, ServiceError> {
+    pub async fn issue_invoices(&self, invoice_ids: &[String], actor: &str) -> Result<usize, ServiceError> {
         if invoice_ids.is_empty() {
             return Err(ServiceError::InvoiceNotIssue("No invoices selected for issuance".to_string()));
         }
@@ -126,14 +333,19 @@ impl InvoiceService {
                 }
             };
             
-            // 检查票据状态是否为Verified
-            if invoice.status != InvoiceStatus::Verified {
+            // 检查票据状态是否允许打包发行
+            if !invoice.status.can_transition_to(InvoiceStatus::Packaged) {
                 warn!(
                     "Invoice {} cannot be issued: status is {:?}, expected Verified", 
                     invoice_id, invoice.status
                 );
                 continue;
             }
+            // 企业必须先接受融资条款才能上架
+            if invoice.accepted_terms.is_none() {
+                warn!("Invoice {} cannot be issued: funding terms not accepted", invoice_id);
+                continue;
+            }
             valid_invoices.push(invoice);
         }
         
@@ -206,9 +418,9 @@ impl InvoiceService {
             for invoice in &valid_invoices {
                 if let Some(id) = invoice.id {
                     if let Some(batch_obj_id) = saved_batch.id {
-                        // 将票据关联到批次
-                        self.invoice_repository.add_to_batch(id, batch_obj_id).await
-                            .map_err(|e| ServiceError::MongoDbError(format!("Failed to add invoice to batch: {}", e)))?;
+                        // 将票据关联到批次，并在同一事务内写入审计记录
+                        self.invoice_repository.add_to_batch_session(id, batch_obj_id, &mut session).await?;
+                        self.invoice_repository.append_audit_session(&issue_audit(id, invoice.status, actor, batch_obj_id), &mut session).await?;
                         
                         success_count += 1;
                     } else {
@@ -241,6 +453,11 @@ impl InvoiceService {
                 
                 batch_id = id;
                 info!("Successfully issued {} invoices into batch {:?}", success_count, batch_id);
+                for invoice in &valid_invoices {
+                    if let Some(id) = invoice.id {
+                        self.notify_status_change(InvoiceStatusChange::new(id, invoice, invoice.status, InvoiceStatus::Packaged)).await;
+                    }
+                }
                 Ok(success_count)
             },
             Err(e) => {
@@ -257,7 +474,7 @@ impl InvoiceService {
     pub async fn purchase_invoice(&self, user_id: &str, purchase_req: PurchaseInvoiceDto) -> Result<String> {
         // 验证票据是否可购买
         let invoice_id = &purchase_req.invoice_id;
-        let purchase_amount_f64 = purchase_req.purchase_amount;
+        let purchase_amount = purchase_req.purchase_amount;
         
         let invoice = self.invoice_redis_service.get_invoice(invoice_id)?
             .ok_or_else(|| anyhow!("票据不存在"))?;
@@ -266,24 +483,17 @@ impl InvoiceService {
             return Err(anyhow!("票据当前不可购买"));
         }
         
-        // Calculate shares and validate amount/shares (using rust_decimal)
-        let purchase_amount_dec = Decimal::from_f64(purchase_amount_f64)
-            .ok_or_else(|| anyhow!("无效的购买金额格式"))?;
-        let share_price_dec = Decimal::from_f64(invoice.share_price)
-             .ok_or_else(|| anyhow!("无效的 Redis 份额价格格式"))?;
-        let zero_dec = dec!(0.0);
-        let one_dec = dec!(1.0);
-
-        if purchase_amount_dec <= zero_dec || share_price_dec <= zero_dec {
+        // 按整数份折算实际认购金额 (Money 十进制运算，无浮点误差)
+        let share_price = invoice.share_price;
+        if !purchase_amount.is_positive() || !share_price.is_positive() {
              return Err(anyhow!("购买金额和份额价格必须为正"));
         }
 
-        let calculated_shares_dec = (purchase_amount_dec / share_price_dec).round();
+        let calculated_shares_dec = purchase_amount.ratio(share_price)?.round();
         let calculated_shares = calculated_shares_dec.to_u64()
              .ok_or_else(|| anyhow!("计算出的份额无效"))?;
 
-        let actual_purchase_amount_dec = calculated_shares_dec * share_price_dec;
-        let actual_purchase_decimal128 = Decimal128::from_str(&actual_purchase_amount_dec.to_string())
+        let actual_purchase_decimal128 = share_price.checked_mul(calculated_shares_dec)?.to_decimal128()
             .map_err(|e| anyhow!("无法转换最终购买金额: {}", e))?;
 
         if calculated_shares == 0 {
@@ -441,17 +651,11 @@ impl InvoiceService {
             }
 
             // --- Calculate Interest (outside transaction) --- 
-            let is_leap_year = accrual_naive_date.year() % 4 == 0 && (accrual_naive_date.year() % 100 != 0 || accrual_naive_date.year() % 400 == 0);
-            let days_in_year = if is_leap_year { 366.0 } else { 365.0 };
-            let annual_rate = invoice.annual_rate; 
-            let daily_rate = annual_rate / days_in_year / 100.0; 
-            let principal = holding.current_balance; 
-            let principal_f64 = principal.to_string().parse::<f64>().unwrap_or(0.0); // Handle potential parse error better?
-            let daily_interest_f64 = principal_f64 * daily_rate;
-            let daily_interest_decimal = match Decimal128::from_str(&format!("{:.8}", daily_interest_f64)) {
+            let days = daily_day_count(self.day_count, accrual_naive_date);
+            let daily_interest_decimal = match daily_interest(&holding.current_balance, invoice.annual_rate, days, year_basis(self.day_count)) {
                 Ok(d) => d,
                 Err(e) => {
-                    error!("Failed to parse daily interest '{}' for holding {}: {}. Skipping.", daily_interest_f64, holding_id_str, e);
+                    error!("Failed to calculate daily interest for holding {}: {}. Skipping.", holding_id_str, e);
                     continue;
                 }
             };
@@ -532,7 +736,9 @@ impl InvoiceService {
     }
 
     // Renamed from process_maturity_payments
-    pub async fn process_maturity_payments_for_date(&self, payment_date: NaiveDate) -> Result<u32, ServiceError> {
+    /// 处理到期兑付，按票据返回兑付结果。未到期 (减去提前兑付窗口) 的票据记为 `NotMatured` 并跳过，不影响同批其他票据；
+    /// 管理员通过 `options.early_override_by` 强制提前兑付时，每笔兑付都会写入审计日志。
+    pub async fn process_maturity_payments_for_date(&self, payment_date: NaiveDate, options: &SettlementOptions) -> Result<BatchSettlementDto, ServiceError> {
         info!("Processing maturity payments for date: {}", payment_date);
 
         // Find holdings maturing on this date
@@ -541,101 +747,532 @@ impl InvoiceService {
 
         if maturing_holdings.is_empty() {
             info!("No maturing holdings found for date: {}", payment_date);
-            return Ok(0);
+            return Ok(BatchSettlementDto::from_results(0, Vec::new()));
         }
 
         info!("Found {} holdings maturing on {}", maturing_holdings.len(), payment_date);
-        let mut success_count = 0;
 
+        // 按票据分组，每张票据通过 SettlementExecutor 加锁并检查结算状态后再兑付，重复触发或多实例并发时不会重复打款
+        let mut holdings_by_invoice: HashMap<ObjectId, Vec<UserInvoiceHolding>> = HashMap::new();
         for holding in maturing_holdings {
-             let holding_id_str = holding.holding_id.clone();
-
-             // --- Calculate Payment Amount (outside transaction) --- 
-             let principal = holding.purchase_amount.clone();
-             let accrued_interest = holding.total_accrued_interest.clone();
-             // TODO: Implement robust Decimal128 addition if necessary
-             let principal_f64 = principal.to_string().parse::<f64>().unwrap_or(0.0); // Handle parse error?
-             let interest_f64 = accrued_interest.to_string().parse::<f64>().unwrap_or(0.0); // Handle parse error?
-             let total_payment_f64 = principal_f64 + interest_f64;
-             let total_payment_decimal = match Decimal128::from_str(&format!("{:.8}", total_payment_f64)) {
-                 Ok(d) => d,
-                 Err(e) => {
-                     error!("Failed to parse total payment '{}' for holding {}: {}. Skipping.", total_payment_f64, holding_id_str, e);
-                     continue;
-                 }
-             };
+            holdings_by_invoice.entry(holding.invoice_id).or_default().push(holding);
+        }
 
-            // --- Start Transaction per Holding --- 
-            let mut session = match self.db.client().start_session().await {
-                 Ok(s) => s,
-                 Err(e) => {
-                     error!("Failed to start session for maturity payment on holding {}: {}. Skipping.", holding_id_str, e);
-                     continue;
-                 }
+        let now_ms = Utc::now().timestamp_millis();
+        let mut results = Vec::with_capacity(holdings_by_invoice.len());
+        for (invoice_id, holdings) in holdings_by_invoice {
+            if let Some(item) = self.settle_maturing_invoice(invoice_id, holdings, options, now_ms).await {
+                results.push(item);
+            }
+        }
+
+        let summary = BatchSettlementDto::from_results(results.len() as u32, results);
+        info!(
+            "Processed maturity payments for {}: {} settled, {} not matured, {} failed",
+            payment_date, summary.settled, summary.not_matured, summary.failed
+        );
+        Ok(summary)
+    }
+
+    /// 单张票据的到期兑付。票据已删除时返回 `None`，其余情况 (包括未到期、查询失败) 都返回该票据的处理结果
+    async fn settle_maturing_invoice(&self, invoice_oid: ObjectId, holdings: Vec<UserInvoiceHolding>, options: &SettlementOptions, now_ms: i64) -> Option<BatchSettlementItemDto> {
+        let invoice_id = invoice_oid.to_hex();
+        let invoice = match self.invoice_repository.find_by_id(invoice_oid).await {
+            Ok(Some(invoice)) => invoice,
+            Ok(None) => {
+                let mut item = BatchSettlementItemDto::new(invoice_id.clone(), String::new());
+                item.error = Some(ServiceError::InvoiceNotFound(invoice_id).to_string());
+                return Some(item);
+            }
+            Err(e) => {
+                let mut item = BatchSettlementItemDto::new(invoice_id, String::new());
+                item.error = Some(e.to_string());
+                return Some(item);
+            }
+        };
+        if invoice.is_deleted() {
+            warn!("Invoice {} has been deleted, skipping maturity payment", invoice.invoice_number);
+            return None;
+        }
+
+        let mut early = false;
+        if let Err(e) = ensure_settlement_allowed(&invoice.invoice_number, invoice.due_date, now_ms, options.early_window_secs) {
+            match &options.early_override_by {
+                Some(admin) => {
+                    warn!("Admin {} overriding maturity check: {}", admin, e);
+                    early = true;
+                }
+                None => {
+                    info!("Skipping maturity payment: {}", e);
+                    let mut item = BatchSettlementItemDto::new(invoice_id, invoice.invoice_number.clone());
+                    item.status = BatchSettlementStatus::NotMatured;
+                    item.error = Some(e.to_string());
+                    return Some(item);
+                }
+            }
+        }
+
+        let payout = MaturityPayout {
+            service: self,
+            holdings,
+            early_invoice: early.then_some(&invoice),
+            early_override_by: options.early_override_by.as_deref(),
+            now_ms,
+            paid: AtomicU32::new(0),
+        };
+        let actor = options.early_override_by.as_deref().unwrap_or(SYSTEM_ACTOR);
+        Some(self.execute_payout(invoice_oid, &invoice, actor, payout).await)
+    }
+
+    /// 批量兑付所有已到期、已募满且未结算的票据。
+    /// 每张票据仍经过 SettlementExecutor 加锁和结算状态检查，最多 `concurrency` 张并发处理，
+    /// 结束后将汇总写入审计日志。
+    pub async fn settle_matured_invoices(&self, actor: &str, early_window_secs: i64, concurrency: usize) -> Result<BatchSettlementDto, ServiceError> {
+        let now_ms = Utc::now().timestamp_millis();
+        let candidates = self.invoice_repository.find_unsettled_by_statuses(&BATCH_SETTLEMENT_STATUSES).await?;
+
+        let mut eligible = Vec::new();
+        for invoice in candidates {
+            let Some(invoice_id) = invoice.id else { continue };
+            let available_shares = match self.invoice_redis_service.get_invoice(&invoice_id.to_hex()) {
+                Ok(cached) => cached.map(|c| c.available_shares),
+                Err(e) => {
+                    warn!("Failed to read funding status of invoice {}: {}", invoice_id, e);
+                    None
+                }
             };
-            let transaction_result = session.start_transaction()
-                .and_run(
-                    (self, &holding, total_payment_decimal.clone()), // Clone payment decimal
-                    |session, (service, h, payment_amount)| {
-                        async move { 
-                            // a. Create MaturityPayment transaction record
-                            let transaction = Transaction::new_maturity_payment(
-                                h.user_id.clone(),
-                                h.invoice_id,
-                                h.holding_id.clone(),
-                                payment_amount.clone(),
-                            );
-                            service.transaction_repo.create_session(transaction, session).await?;
+            if is_batch_settlement_eligible(&invoice, available_shares, now_ms, early_window_secs) {
+                eligible.push((invoice_id, invoice));
+            }
+        }
+        info!("Batch settlement by {}: {} eligible invoices", actor, eligible.len());
 
-                            // b. Update holding status to Matured
-                            service.user_holding_repo.update_holding_status_session(
-                                &h.holding_id,
-                                HoldingStatus::Matured, 
-                                session,
-                            ).await?;
-                            
-                            // c. Simulate crediting user balance
-                            let user_addr = &h.user_id;
-                            let update_successful = service.user_repo.update_balance_session(user_addr, payment_amount.clone(), session).await?;
-                            if !update_successful {
-                                error!("Failed to credit balance for user {} during maturity payment.", user_addr);
-                                // Note: Returning error here rolls back the whole transaction
-                                return Err(ServiceError::BalanceUpdateFailed(user_addr.to_string()));
-                            }
-                            info!("Credited maturity payment {} to user {} within transaction", payment_amount, user_addr);
+        let results: Vec<BatchSettlementItemDto> = futures::stream::iter(eligible.iter())
+            .map(|(invoice_id, invoice)| self.settle_one_matured_invoice(*invoice_id, invoice, actor, now_ms))
+            .buffer_unordered(concurrency.max(1))
+            .collect()
+            .await;
 
-                            Ok(())
+        let summary = BatchSettlementDto::from_results(eligible.len() as u32, results);
+
+        let failed_ids: Vec<&str> = summary.results.iter()
+            .filter(|r| r.status == BatchSettlementStatus::Failed)
+            .map(|r| r.invoice_id.as_str())
+            .collect();
+        let audit = AuditLog::new(actor, "settlement.batch", "invoice", "batch", Some(doc! {
+            "eligible": summary.eligible,
+            "settled": summary.settled,
+            "already_settled": summary.already_settled,
+            "in_progress": summary.in_progress,
+            "failed": summary.failed,
+            "failed_invoice_ids": failed_ids,
+            "executed_at": DateTime::from_millis(now_ms),
+        }));
+        if let Err(e) = self.audit_repo.create(&audit).await {
+            error!("Failed to write batch settlement audit log: {}", e);
+        }
+
+        Ok(summary)
+    }
+
+    async fn settle_one_matured_invoice(&self, invoice_oid: ObjectId, invoice: &Invoice, actor: &str, now_ms: i64) -> BatchSettlementItemDto {
+        let holdings = match self.user_holding_repo.find_active_by_invoice(invoice_oid).await {
+            Ok(holdings) => holdings,
+            Err(e) => {
+                let mut item = BatchSettlementItemDto::new(invoice_oid.to_hex(), invoice.invoice_number.clone());
+                item.error = Some(e.to_string());
+                return item;
+            }
+        };
+        let payout = MaturityPayout {
+            service: self,
+            holdings,
+            early_invoice: None,
+            early_override_by: None,
+            now_ms,
+            paid: AtomicU32::new(0),
+        };
+        self.execute_payout(invoice_oid, invoice, actor, payout).await
+    }
+
+    /// 通过 SettlementExecutor 执行一张票据的兑付，并把结果转换为逐张票据的兑付结果
+    async fn execute_payout(&self, invoice_oid: ObjectId, invoice: &Invoice, actor: &str, payout: MaturityPayout<'_>) -> BatchSettlementItemDto {
+        let invoice_id = invoice_oid.to_hex();
+        let mut item = BatchSettlementItemDto::new(invoice_id.clone(), invoice.invoice_number.clone());
+        match self.settlement_executor.settle(&invoice_id, actor, &payout).await {
+            Ok(outcome) => {
+                item.status = if outcome.already_settled { BatchSettlementStatus::AlreadySettled } else { BatchSettlementStatus::Settled };
+                item.settlement_tx_hash = Some(outcome.tx_hash);
+                if !outcome.already_settled {
+                    self.notify_status_change(InvoiceStatusChange::new(invoice_oid, invoice, invoice.status, InvoiceStatus::Repaid)).await;
+                }
+            }
+            Err(ServiceError::SettlementInProgress(_)) => item.status = BatchSettlementStatus::InProgress,
+            Err(e) => {
+                error!("Settlement of invoice {} failed: {}", invoice_id, e);
+                item.error = Some(e.to_string());
+            }
+        }
+        item.paid_holdings = payout.paid.load(Ordering::SeqCst);
+        item
+    }
+
+    /// 单笔持仓到期兑付：写交易记录、更新持仓状态、入账用户余额 (同一事务)
+    async fn pay_out_holding(&self, holding: &UserInvoiceHolding, early_audit: Option<AuditLog>) -> Result<(), ServiceError> {
+        let holding_id_str = holding.holding_id.clone();
+
+        // --- Calculate Payment Amount (outside transaction) ---
+        let principal = Money::from_decimal128(&holding.purchase_amount)?;
+        let accrued_interest = Money::from_decimal128(&holding.total_accrued_interest)?;
+        let total_payment_decimal = principal.checked_add(accrued_interest)?.to_decimal128().map_err(|e| {
+            ServiceError::DecimalConversionError(format!("Failed to convert total payment for holding {}: {}", holding_id_str, e))
+        })?;
+
+        // --- Start Transaction per Holding ---
+        let mut session = self.db.client().start_session().await?;
+        let transaction_result = session.start_transaction()
+            .and_run(
+                (self, holding, total_payment_decimal, &early_audit),
+                |session, (service, h, payment_amount, audit)| {
+                    async move {
+                        // a. Create MaturityPayment transaction record
+                        let transaction = Transaction::new_maturity_payment(
+                            h.user_id.clone(),
+                            h.invoice_id,
+                            h.holding_id.clone(),
+                            payment_amount.clone(),
+                        );
+                        service.transaction_repo.create_session(transaction, session).await?;
+
+                        // b. Update holding status to Matured
+                        service.user_holding_repo.update_holding_status_session(
+                            &h.holding_id,
+                            HoldingStatus::Matured,
+                            session,
+                        ).await?;
+
+                        // c. Simulate crediting user balance
+                        let user_addr = &h.user_id;
+                        let update_successful = service.user_repo.update_balance_session(user_addr, payment_amount.clone(), session).await?;
+                        if !update_successful {
+                            error!("Failed to credit balance for user {} during maturity payment.", user_addr);
+                            // Note: Returning error here rolls back the whole transaction
+                            return Err(ServiceError::BalanceUpdateFailed(user_addr.to_string()));
                         }
-                        .map(|res| res.map_err(|service_err: ServiceError| {
-                            MongoError::custom(Box::new(service_err))
-                        }))
-                       .boxed()
-                    }
-                ).await;
+                        info!("Credited maturity payment {} to user {} within transaction", payment_amount, user_addr);
 
-             match transaction_result {
-                Ok(_) => {
-                    success_count += 1;
-                    info!("Successfully processed maturity for holding {}", holding_id_str);
-                    // TODO: Trigger actual off-chain payout here AFTER successful commit?
-                    // Or should payout be triggered by listening to events/DB changes?
+                        // d. 提前兑付的审计记录与兑付在同一事务中写入
+                        if let Some(entry) = audit {
+                            service.audit_repo.create_session(entry, session).await?;
+                        }
+
+                        Ok(())
+                    }
+                    .map(|res| res.map_err(|service_err: ServiceError| {
+                        MongoError::custom(Box::new(service_err))
+                    }))
+                   .boxed()
                 }
-                Err(e) => {
-                    error!("Failed transaction for maturity payment on holding {}: {:?}", holding_id_str, e);
-                     if let MongoError { kind: ref error_kind, .. } = e {
-                        if let mongodb::error::ErrorKind::Custom(inner_error) = &**error_kind {
-                            if let Some(service_error) = inner_error.downcast_ref::<ServiceError>() {
-                                error!("(ServiceError details: {:?})", service_error);
-                            }
+            ).await;
+
+        match transaction_result {
+            Ok(_) => {
+                info!("Successfully processed maturity for holding {}", holding_id_str);
+                // TODO: Trigger actual off-chain payout here AFTER successful commit?
+                // Or should payout be triggered by listening to events/DB changes?
+                Ok(())
+            }
+            Err(e) => {
+                error!("Failed transaction for maturity payment on holding {}: {:?}", holding_id_str, e);
+                if let MongoError { kind: ref error_kind, .. } = e {
+                    if let mongodb::error::ErrorKind::Custom(inner_error) = &**error_kind {
+                        if let Some(service_error) = inner_error.downcast_ref::<ServiceError>() {
+                            error!("(ServiceError details: {:?})", service_error);
                         }
                     }
-                    // Continue with the next holding
                 }
+                Err(e.into())
             }
-            // --- End Transaction per Holding --- 
         }
+    }
+}
 
-        info!("Successfully processed {} maturity payments for date: {}", success_count, payment_date);
-        Ok(success_count)
+/// 单张票据的到期兑付，作为结算提交步骤交给 [`SettlementExecutor`]
+///
+/// 合约目前没有还款接口，兑付在链下完成，返回的结算哈希为链下兑付批次号。
+/// 部分持仓失败时返回错误，票据不会被标记为已结算，重试时只处理仍为 Active 的持仓。
+struct MaturityPayout<'a> {
+    service: &'a InvoiceService,
+    holdings: Vec<UserInvoiceHolding>,
+    early_invoice: Option<&'a Invoice>,
+    early_override_by: Option<&'a str>,
+    now_ms: i64,
+    paid: AtomicU32,
+}
+
+#[async_trait]
+impl SettlementSubmitter for MaturityPayout<'_> {
+    async fn submit(&self, invoice_id: &str) -> Result<String, ServiceError> {
+        let mut failed = 0;
+        for holding in &self.holdings {
+            let early_audit = match (self.early_invoice, self.early_override_by) {
+                (Some(invoice), Some(admin)) => Some(AuditLog::new(
+                    admin,
+                    "settlement.early_override",
+                    "invoice",
+                    invoice_id,
+                    Some(doc! {
+                        "invoice_number": &invoice.invoice_number,
+                        "holding_id": &holding.holding_id,
+                        "due_date": invoice.due_date,
+                        "settled_at": DateTime::from_millis(self.now_ms),
+                    }),
+                )),
+                _ => None,
+            };
+            match self.service.pay_out_holding(holding, early_audit).await {
+                Ok(()) => {
+                    self.paid.fetch_add(1, Ordering::SeqCst);
+                }
+                Err(_) => failed += 1,
+            }
+        }
+        if failed > 0 {
+            return Err(ServiceError::InternalError(format!(
+                "{} of {} maturity payments failed for invoice {}",
+                failed,
+                self.holdings.len(),
+                invoice_id
+            )));
+        }
+        Ok(format!("offchain-{}", uuid::Uuid::new_v4().simple()))
+    }
+}
+
+/// 发行 (打包进批次) 的审计记录
+/// 出票企业或管理员才能删除，已上链或已有认购的票据不能删除 (投资人资金与链上数据依赖它)
+fn check_deletable(invoice: &Invoice, actor: &str, is_admin: bool) -> Result<(), ServiceError> {
+    if !is_admin && !invoice.payee.eq_ignore_ascii_case(actor) {
+        return Err(ServiceError::Forbidden(format!("{} cannot delete invoice {}", actor, invoice.invoice_number)));
+    }
+    if invoice.status != InvoiceStatus::Pending || invoice.funded_shares > 0 {
+        return Err(ServiceError::InvoiceNotDeletable(invoice.invoice_number.clone()));
+    }
+    Ok(())
+}
+
+fn issue_audit(invoice_id: ObjectId, previous: InvoiceStatus, actor: &str, batch_id: ObjectId) -> AuditLog {
+    AuditLog::invoice(invoice_id, actor, "issue", Some(previous), InvoiceStatus::Packaged).with_reason(format!("batch {}", batch_id.to_hex()))
+}
+
+/// 当日利息 = 本金 × 年化利率 (百分数) × 计息天数 / 年基准天数，保留 `Money::SCALE` 位小数
+fn daily_interest(principal: &Decimal128, annual_rate_percent: Decimal, days: i64, basis: i64) -> Result<Decimal128, ServiceError> {
+    let interest = Money::from_decimal128(principal)?
+        .checked_mul(annual_rate_percent * Decimal::from(days))?
+        .checked_div(Decimal::from(100 * basis))?;
+    Ok(interest.to_decimal128()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestDb, unused_redis_client};
+    use common::domain::entity::InvoiceAuditDto;
+
+    #[test]
+    fn issue_audit_records_batch_and_lowercased_actor() {
+        let invoice_id = ObjectId::new();
+        let batch_id = ObjectId::new();
+        let audit = issue_audit(invoice_id, InvoiceStatus::Verified, "0xABCdef", batch_id);
+
+        assert_eq!((audit.target_type.as_str(), audit.target_id.clone()), ("invoice", invoice_id.to_hex()));
+        assert_eq!(audit.actor, "0xabcdef");
+        assert_eq!(audit.action, "issue");
+        let dto = InvoiceAuditDto::from(&audit);
+        assert_eq!(dto.previous_status, Some(InvoiceStatus::Verified));
+        assert_eq!(dto.next_status, Some(InvoiceStatus::Packaged));
+        assert_eq!(dto.reason, Some(format!("batch {}", batch_id.to_hex())));
+    }
+
+    #[test]
+    fn daily_interest_is_exact_decimal() {
+        let interest = |principal: &str, rate: &str, convention: DayCountConvention, date: NaiveDate| {
+            let days = daily_day_count(convention, date);
+            let stored = daily_interest(&Decimal128::from_str(principal).unwrap(), Decimal::from_str(rate).unwrap(), days, year_basis(convention)).unwrap();
+            Money::from_decimal128(&stored).unwrap().to_string()
+        };
+        let leap_day = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        // ACT/365 固定分母，闰年也按 365 天计
+        assert_eq!(interest("36500", "7.3", DayCountConvention::Act365, leap_day), "7.30000000");
+        // 30/360 下 2 月 29 日计 2 天，结果四舍五入到 8 位小数
+        assert_eq!(interest("1000.1", "10", DayCountConvention::Thirty360, leap_day), "0.55561111");
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn get_by_id_returns_present_invoice_and_not_found_for_missing() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let service = InvoiceService::new(db.clone(), unused_redis_client());
+        let invoice = InvoiceRepository::new(&db).create(&CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }).await.unwrap();
+        let invoice_id = invoice.id.unwrap();
+
+        let present = service.get_by_id(invoice_id, false).await;
+        let missing = service.get_by_id(ObjectId::new(), false).await;
+        test_db.cleanup().await;
+
+        let found = present.unwrap();
+        assert_eq!(found.id, Some(invoice_id));
+        assert_eq!(found.invoice_number, invoice.invoice_number);
+        assert!(matches!(missing, Err(ServiceError::InvoiceNotFound(_))));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn soft_deleted_invoice_is_hidden_unless_requested() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let service = InvoiceService::new(db.clone(), unused_redis_client());
+        let payee = format!("0xsoftdelete{}", ObjectId::new().to_hex());
+        let invoice = repo.create(&CreateInvoiceDto {
+            payee: payee.clone(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }).await.unwrap();
+        let invoice_id = invoice.id.unwrap();
+
+        let deleted = repo.soft_delete(invoice_id, "0xadmin").await;
+        let deleted_again = repo.soft_delete(invoice_id, "0xadmin").await;
+        let hidden = service.get_by_id(invoice_id, false).await;
+        let visible = service.get_by_id(invoice_id, true).await;
+        let listing = |include_deleted| InvoiceListFilter { payee: Some(payee.clone()), include_deleted, ..Default::default() };
+        let pagination = || Pagination::new(None, 10);
+        let user_page = service.list_invoices(listing(false), pagination()).await;
+        let admin_page = service.list_invoices(listing(true), pagination()).await;
+        let history = repo.find_audit_trail(invoice_id).await;
+        test_db.cleanup().await;
+
+        assert!(deleted.unwrap().unwrap().deleted_at.is_some());
+        assert!(deleted_again.unwrap().is_none());
+        assert!(matches!(hidden, Err(ServiceError::InvoiceNotFound(_))));
+        assert!(visible.unwrap().deleted_at.is_some());
+        let (user_page, admin_page) = (user_page.unwrap(), admin_page.unwrap());
+        assert!(user_page.rows.is_empty());
+        assert_eq!(user_page.total, 0);
+        assert_eq!((admin_page.rows.len(), admin_page.total), (1, 1));
+        assert_eq!(history.unwrap().iter().filter(|a| a.action == "delete").count(), 1);
+    }
+
+    #[test]
+    fn test_only_payee_or_admin_deletes_unfunded_pending_invoice() {
+        let mut invoice = Invoice::new(&CreateInvoiceDto {
+            payee: "0xPayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        assert!(check_deletable(&invoice, "0xpayee", false).is_ok());
+        assert!(check_deletable(&invoice, "0xadmin", true).is_ok());
+        assert!(matches!(check_deletable(&invoice, "0xinvestor", false), Err(ServiceError::Forbidden(_))));
+
+        invoice.funded_shares = 1;
+        assert!(matches!(check_deletable(&invoice, "0xpayee", false), Err(ServiceError::InvoiceNotDeletable(_))));
+        invoice.funded_shares = 0;
+        for status in [InvoiceStatus::Verified, InvoiceStatus::OnSale, InvoiceStatus::Financed, InvoiceStatus::Repaid] {
+            invoice.status = status;
+            assert!(matches!(check_deletable(&invoice, "0xadmin", true), Err(ServiceError::InvoiceNotDeletable(_))));
+        }
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_soft_delete_rejects_other_users_and_funded_invoices() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let service = InvoiceService::new(db.clone(), unused_redis_client());
+        let create = || CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        };
+        let pending = repo.create(&create()).await.unwrap().id.unwrap();
+        let funded = repo.create(&create()).await.unwrap().id.unwrap();
+        db.collection::<Invoice>("invoices").update_one(doc! { "_id": funded }, doc! { "$set": { "funded_shares": 2_i64 } }).await.unwrap();
+
+        let by_investor = service.soft_delete(pending, "0xinvestor", false).await;
+        let funded_by_admin = service.soft_delete(funded, "0xadmin", true).await;
+        let by_payee = service.soft_delete(pending, "0xPAYEE", false).await;
+        let again = service.soft_delete(pending, "0xpayee", false).await;
+        let remaining = service.get_by_id(funded, false).await;
+        test_db.cleanup().await;
+
+        assert!(matches!(by_investor, Err(ServiceError::Forbidden(_))));
+        assert!(matches!(funded_by_admin, Err(ServiceError::InvoiceNotDeletable(_))));
+        assert!(by_payee.is_ok());
+        assert!(matches!(again, Err(ServiceError::InvoiceNotFound(_))));
+        assert!(remaining.is_ok());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_immature_invoices_are_reported_per_invoice() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let service = InvoiceService::new(db.clone(), unused_redis_client());
+        let today = Utc::now().date_naive();
+        let maturity = DateTime::from_millis(Utc::now().timestamp_millis());
+        let due_in_a_week = (Utc::now() + Duration::days(7)).timestamp();
+
+        let mut invoice_ids = Vec::new();
+        for _ in 0..2 {
+            let invoice_id = repo.create(&CreateInvoiceDto {
+                payee: "0xpayee".to_string(),
+                payer: "0xpayer".to_string(),
+                amount: 100,
+                invoice_ipfs_hash: String::new(),
+                contract_ipfs_hash: String::new(),
+                due_date: due_in_a_week,
+                currency: "USDC".to_string(),
+            }).await.unwrap().id.unwrap();
+            db.collection::<Invoice>("invoices")
+                .update_one(doc! { "_id": invoice_id }, doc! { "$set": { "maturity_date": maturity } })
+                .await.unwrap();
+            let holding = UserInvoiceHolding::new("0xinvestor".to_string(), invoice_id, Decimal128::from_str("100").unwrap());
+            db.collection::<UserInvoiceHolding>("user_invoice_holdings").insert_one(&holding).await.unwrap();
+            invoice_ids.push(invoice_id.to_hex());
+        }
+
+        let result = service.process_maturity_payments_for_date(today, &SettlementOptions::default()).await;
+        test_db.cleanup().await;
+
+        let summary = result.unwrap();
+        assert_eq!((summary.eligible, summary.not_matured, summary.settled), (2, 2, 0));
+        let mut reported: Vec<String> = summary.results.iter().map(|r| r.invoice_id.clone()).collect();
+        reported.sort();
+        invoice_ids.sort();
+        assert_eq!(reported, invoice_ids);
+        assert!(summary.results.iter().all(|r| r.status == BatchSettlementStatus::NotMatured && r.paid_holdings == 0));
     }
 }
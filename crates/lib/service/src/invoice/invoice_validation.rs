@@ -0,0 +1,218 @@
+//! 创建与修改票据时的金额、币种与到期日校验，在写库和提交合约之前执行
+
+use serde_json::Value;
+use thiserror::Error;
+
+use common::domain::dto::invoice_dto::CreateInvoiceDto;
+
+use crate::service::interest_calculator::due_date_to_millis;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvoiceValidationError {
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+
+    #[error("Invoice amount must be greater than zero")]
+    AmountNotPositive,
+
+    #[error("Invoice amount must be an integer in minor currency units")]
+    AmountNotInteger,
+
+    #[error("Invoice amount exceeds the maximum of {max} minor units")]
+    AmountExceedsCap { max: u64 },
+
+    #[error("Currency {0} is not supported")]
+    UnsupportedCurrency(String),
+
+    #[error("Invoice due date must be in the future")]
+    DueDateNotInFuture,
+}
+
+/// 票据创建限制，来自 `[invoice]` 配置
+#[derive(Debug, Clone)]
+pub struct InvoiceLimits {
+    /// 金额上限 (最小货币单位)
+    pub max_amount: u64,
+    pub supported_currencies: Vec<String>,
+}
+
+/// 解析并校验创建票据的请求体。通过后金额为整数最小单位，币种统一为大写
+pub fn parse_create_invoice(mut body: Value, limits: &InvoiceLimits, now_ms: i64) -> Result<CreateInvoiceDto, InvoiceValidationError> {
+    let Some(fields) = body.as_object_mut() else {
+        return Err(InvoiceValidationError::InvalidBody("expected a JSON object".to_string()));
+    };
+    let amount = check_amount(parse_minor_units(fields.get("amount").unwrap_or(&Value::Null))?, limits)?;
+    fields.insert("amount".to_string(), Value::from(amount));
+
+    let mut dto: CreateInvoiceDto = serde_json::from_value(body).map_err(|e| InvoiceValidationError::InvalidBody(e.to_string()))?;
+    dto.currency = check_currency(&dto.currency, limits)?;
+    if due_date_to_millis(dto.due_date) <= now_ms {
+        return Err(InvoiceValidationError::DueDateNotInFuture);
+    }
+    Ok(dto)
+}
+
+/// 修改票据时提交的金额、币种与到期日，未提交的字段为 None
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InvoiceEdit {
+    pub amount: Option<u64>,
+    pub currency: Option<String>,
+    pub due_date: Option<i64>,
+}
+
+/// 按创建票据的规则校验修改的字段，通过后币种统一为大写
+pub fn validate_invoice_edit(edit: InvoiceEdit, limits: &InvoiceLimits, now_ms: i64) -> Result<InvoiceEdit, InvoiceValidationError> {
+    let amount = edit.amount.map(|amount| check_amount(amount as u128, limits)).transpose()?;
+    let currency = edit.currency.as_deref().map(|currency| check_currency(currency, limits)).transpose()?;
+    if edit.due_date.is_some_and(|due| due_date_to_millis(due) <= now_ms) {
+        return Err(InvoiceValidationError::DueDateNotInFuture);
+    }
+    Ok(InvoiceEdit { amount, currency, due_date: edit.due_date })
+}
+
+/// 金额只接受 JSON 整数或纯数字字符串 (避免浮点误差)，负数视为非正数，小数与科学计数法一律拒绝。
+/// 超出 u128 的数字串按 `u128::MAX` 处理，由上限校验拒绝
+pub fn parse_minor_units(raw: &Value) -> Result<u128, InvoiceValidationError> {
+    match raw {
+        Value::Number(n) => {
+            if let Some(v) = n.as_u64() {
+                Ok(v as u128)
+            } else if n.as_i64().is_some() || n.as_f64().is_some_and(|f| f <= 0.0) {
+                Err(InvoiceValidationError::AmountNotPositive)
+            } else {
+                Err(InvoiceValidationError::AmountNotInteger)
+            }
+        }
+        Value::String(s) => {
+            let s = s.trim();
+            let (negative, digits) = match s.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, s),
+            };
+            if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(InvoiceValidationError::AmountNotInteger);
+            }
+            if negative {
+                return Err(InvoiceValidationError::AmountNotPositive);
+            }
+            Ok(digits.parse::<u128>().unwrap_or(u128::MAX))
+        }
+        _ => Err(InvoiceValidationError::AmountNotInteger),
+    }
+}
+
+fn check_amount(amount: u128, limits: &InvoiceLimits) -> Result<u64, InvoiceValidationError> {
+    if amount == 0 {
+        return Err(InvoiceValidationError::AmountNotPositive);
+    }
+    if amount > limits.max_amount as u128 {
+        return Err(InvoiceValidationError::AmountExceedsCap { max: limits.max_amount });
+    }
+    Ok(amount as u64)
+}
+
+fn check_currency(currency: &str, limits: &InvoiceLimits) -> Result<String, InvoiceValidationError> {
+    let currency = currency.trim().to_uppercase();
+    if limits.supported_currencies.iter().any(|c| c.eq_ignore_ascii_case(&currency)) {
+        Ok(currency)
+    } else {
+        Err(InvoiceValidationError::UnsupportedCurrency(currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const NOW_MS: i64 = 1_750_000_000_000;
+    const MAX: u64 = 1_000_000;
+
+    fn limits() -> InvoiceLimits {
+        InvoiceLimits { max_amount: MAX, supported_currencies: vec!["CNY".to_string(), "USDC".to_string()] }
+    }
+
+    fn body(amount: Value, currency: &str, due_date: i64) -> Value {
+        json!({
+            "payee": "0xpayee",
+            "payer": "0xpayer",
+            "amount": amount,
+            "invoice_ipfs_hash": "Qm",
+            "contract_ipfs_hash": "0x",
+            "due_date": due_date,
+            "currency": currency,
+        })
+    }
+
+    fn parse(amount: Value, currency: &str, due_date: i64) -> Result<CreateInvoiceDto, InvoiceValidationError> {
+        parse_create_invoice(body(amount, currency, due_date), &limits(), NOW_MS)
+    }
+
+    #[test]
+    fn test_amount_must_be_positive() {
+        assert_eq!(parse(json!(0), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(parse(json!("0"), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(parse(json!(-1), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(parse(json!("-5"), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(parse(json!(-0.5), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(parse(json!(1), "CNY", NOW_MS + 1).unwrap().amount, 1);
+    }
+
+    #[test]
+    fn test_amount_must_be_integer_minor_units() {
+        for amount in [json!(1.5), json!(100.0), json!("1.5"), json!("1e3"), json!(""), json!(null), json!(true)] {
+            assert_eq!(parse(amount.clone(), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountNotInteger, "{}", amount);
+        }
+        assert_eq!(parse(json!(" 250 "), "CNY", NOW_MS + 1).unwrap().amount, 250);
+    }
+
+    #[test]
+    fn test_amount_cap() {
+        assert_eq!(parse(json!(MAX), "CNY", NOW_MS + 1).unwrap().amount, MAX);
+        assert_eq!(parse(json!(MAX + 1), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountExceedsCap { max: MAX });
+        let huge = "9".repeat(60);
+        assert_eq!(parse(json!(huge), "CNY", NOW_MS + 1).unwrap_err(), InvoiceValidationError::AmountExceedsCap { max: MAX });
+    }
+
+    #[test]
+    fn test_currency_whitelist() {
+        assert_eq!(parse(json!(1), " usdc ", NOW_MS + 1).unwrap().currency, "USDC");
+        assert_eq!(parse(json!(1), "EUR", NOW_MS + 1).unwrap_err(), InvoiceValidationError::UnsupportedCurrency("EUR".to_string()));
+        assert_eq!(parse(json!(1), "", NOW_MS + 1).unwrap_err(), InvoiceValidationError::UnsupportedCurrency(String::new()));
+    }
+
+    #[test]
+    fn test_due_date_strictly_in_future() {
+        assert_eq!(parse(json!(1), "CNY", NOW_MS).unwrap_err(), InvoiceValidationError::DueDateNotInFuture);
+        assert_eq!(parse(json!(1), "CNY", NOW_MS - 1).unwrap_err(), InvoiceValidationError::DueDateNotInFuture);
+        assert!(parse(json!(1), "CNY", NOW_MS + 1).is_ok());
+        // 秒级到期日同样适用
+        assert_eq!(parse(json!(1), "CNY", NOW_MS / 1000).unwrap_err(), InvoiceValidationError::DueDateNotInFuture);
+        assert!(parse(json!(1), "CNY", NOW_MS / 1000 + 1).is_ok());
+    }
+
+    #[test]
+    fn test_edit_uses_create_rules() {
+        let edit = |amount: Option<u64>, currency: Option<&str>, due_date: Option<i64>| {
+            validate_invoice_edit(InvoiceEdit { amount, currency: currency.map(str::to_string), due_date }, &limits(), NOW_MS)
+        };
+        assert_eq!(edit(None, None, None).unwrap(), InvoiceEdit::default());
+        assert_eq!(edit(Some(0), None, None).unwrap_err(), InvoiceValidationError::AmountNotPositive);
+        assert_eq!(edit(Some(MAX + 1), None, None).unwrap_err(), InvoiceValidationError::AmountExceedsCap { max: MAX });
+        assert_eq!(edit(None, Some("EUR"), None).unwrap_err(), InvoiceValidationError::UnsupportedCurrency("EUR".to_string()));
+        // 秒级到期日按毫秒比较
+        assert_eq!(edit(None, None, Some(NOW_MS / 1000)).unwrap_err(), InvoiceValidationError::DueDateNotInFuture);
+        assert_eq!(
+            edit(Some(MAX), Some(" usdc "), Some(NOW_MS / 1000 + 1)).unwrap(),
+            InvoiceEdit { amount: Some(MAX), currency: Some("USDC".to_string()), due_date: Some(NOW_MS / 1000 + 1) }
+        );
+    }
+
+    #[test]
+    fn test_invalid_body() {
+        assert!(matches!(parse_create_invoice(json!([1]), &limits(), NOW_MS), Err(InvoiceValidationError::InvalidBody(_))));
+        let mut missing_payee = body(json!(1), "CNY", NOW_MS + 1);
+        missing_payee.as_object_mut().unwrap().remove("payee");
+        assert!(matches!(parse_create_invoice(missing_payee, &limits(), NOW_MS), Err(InvoiceValidationError::InvalidBody(_))));
+    }
+}
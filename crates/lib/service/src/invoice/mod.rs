@@ -1,5 +1,20 @@
+pub mod batch_create;
+pub mod bulk_transition;
+pub mod invoice_listing;
 pub mod invoice_service;
+pub mod invoice_validation;
+pub mod reconciliation;
 pub mod scheduled_tasks;
+pub mod settlement_guard;
+pub mod settlement_executor;
+pub mod status_events;
 
+pub use batch_create::BatchItemError;
+pub use bulk_transition::is_bulk_transition_target;
+pub use invoice_listing::InvoiceListFilter;
 pub use invoice_service::InvoiceService;
+pub use invoice_validation::{InvoiceLimits, InvoiceValidationError};
 pub use scheduled_tasks::setup_scheduled_tasks;
+pub use settlement_guard::SettlementOptions;
+pub use settlement_executor::{SettlementExecutor, SettlementOutcome};
+pub use status_events::{InvoiceStatusChange, InvoiceStatusNotifier};
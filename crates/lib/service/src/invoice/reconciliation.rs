@@ -0,0 +1,146 @@
+//! 票据数据库记录与合约存储的核对 (人工干预后排查数据不一致)
+//!
+//! 比较金额、所有人 (合约中的 payee) 与状态。合约只记录是否已清算 (`is_cleared`)，
+//! 因此状态按三类比较：未上链 (`Pending`)、已上链未清算、已清算 (`Repaid`)。
+
+use common::domain::dto::invoice_reconciliation_dto::{InvoiceReconciliationDto, ReconciliationDiffDto};
+use common::domain::entity::Invoice;
+use common::domain::entity::invoice_status::InvoiceStatus;
+use pharos_interact::{ContractQuerier, InvoiceData};
+
+use crate::error::ServiceError;
+
+const ONCHAIN_MISSING: &str = "not registered";
+const ONCHAIN_ACTIVE: &str = "registered";
+const ONCHAIN_CLEARED: &str = "cleared";
+
+/// 读取合约中的票据并与数据库记录比较
+pub async fn reconcile_with_contract<Q: ContractQuerier + Sync + ?Sized>(invoice: &Invoice, querier: &Q) -> Result<InvoiceReconciliationDto, ServiceError> {
+    let onchain = querier
+        .get_invoice(invoice.invoice_number.clone())
+        .await
+        .map_err(|e| ServiceError::ChainRpcError(format!("Failed to read invoice {} from contract: {:#}", invoice.invoice_number, e)))?;
+    Ok(reconcile(invoice, onchain.as_ref()))
+}
+
+pub fn reconcile(invoice: &Invoice, onchain: Option<&InvoiceData>) -> InvoiceReconciliationDto {
+    let mut diffs = Vec::new();
+    let expected_status = expected_onchain_status(invoice.status);
+    let actual_status = onchain.map_or(ONCHAIN_MISSING, |data| if data.is_cleared { ONCHAIN_CLEARED } else { ONCHAIN_ACTIVE });
+    if expected_status != actual_status {
+        diffs.push(diff("status", format!("{:?}", invoice.status), actual_status.to_string()));
+    }
+
+    if let Some(data) = onchain {
+        let onchain_amount = data.amount.to_string();
+        if invoice.amount.to_string() != onchain_amount {
+            diffs.push(diff("amount", invoice.amount.to_string(), onchain_amount));
+        }
+        let onchain_owner = format!("{:?}", data.payee);
+        if !invoice.payee.eq_ignore_ascii_case(&onchain_owner) {
+            diffs.push(diff("owner", invoice.payee.clone(), onchain_owner));
+        }
+    }
+
+    InvoiceReconciliationDto {
+        invoice_id: invoice.id.map(|id| id.to_hex()).unwrap_or_default(),
+        invoice_number: invoice.invoice_number.clone(),
+        onchain_found: onchain.is_some(),
+        consistent: diffs.is_empty(),
+        diffs,
+    }
+}
+
+fn expected_onchain_status(status: InvoiceStatus) -> &'static str {
+    match status {
+        InvoiceStatus::Pending => ONCHAIN_MISSING,
+        InvoiceStatus::Repaid => ONCHAIN_CLEARED,
+        _ => ONCHAIN_ACTIVE,
+    }
+}
+
+fn diff(field: &str, db_value: String, onchain_value: String) -> ReconciliationDiffDto {
+    ReconciliationDiffDto { field: field.to_string(), db_value, onchain_value }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::domain::dto::invoice_dto::CreateInvoiceDto;
+    use ethers::types::U256;
+    use mongodb::bson::oid::ObjectId;
+    use pharos_interact::mock::MockContract;
+
+    const PAYEE: &str = "0x1111111111111111111111111111111111111111";
+    const OTHER: &str = "0x2222222222222222222222222222222222222222";
+
+    fn invoice(status: InvoiceStatus) -> Invoice {
+        let mut invoice = Invoice::new(&CreateInvoiceDto {
+            payee: PAYEE.to_string(),
+            payer: OTHER.to_string(),
+            amount: 5_000,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 1_800_000_000_000,
+            currency: "USDC".to_string(),
+        });
+        invoice.id = Some(ObjectId::new());
+        invoice.status = status;
+        invoice
+    }
+
+    fn onchain(invoice: &Invoice, payee: &str, amount: u64, is_cleared: bool) -> InvoiceData {
+        InvoiceData {
+            invoice_number: invoice.invoice_number.clone(),
+            payee: payee.parse().unwrap(),
+            payer: OTHER.parse().unwrap(),
+            amount: U256::from(amount),
+            is_cleared,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_matching_invoice_has_no_diffs() {
+        let invoice = invoice(InvoiceStatus::Financed);
+        let contract = MockContract::default().with_invoice(onchain(&invoice, PAYEE, 5_000, false));
+        let result = reconcile_with_contract(&invoice, &contract).await.unwrap();
+        assert!(result.onchain_found);
+        assert!(result.consistent);
+        assert!(result.diffs.is_empty());
+        assert_eq!(result.invoice_number, invoice.invoice_number);
+
+        // 未上链的票据在合约中查不到同样视为一致
+        let pending = self::invoice(InvoiceStatus::Pending);
+        let result = reconcile_with_contract(&pending, &MockContract::default()).await.unwrap();
+        assert!(!result.onchain_found);
+        assert!(result.consistent);
+    }
+
+    #[tokio::test]
+    async fn test_mismatched_invoice_reports_each_field() {
+        let invoice = invoice(InvoiceStatus::Repaid);
+        let contract = MockContract::default().with_invoice(onchain(&invoice, OTHER, 4_000, false));
+        let result = reconcile_with_contract(&invoice, &contract).await.unwrap();
+        assert!(!result.consistent);
+        assert_eq!(
+            result.diffs,
+            vec![
+                diff("status", "Repaid".to_string(), "registered".to_string()),
+                diff("amount", "5000".to_string(), "4000".to_string()),
+                diff("owner", PAYEE.to_string(), OTHER.to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_onchain_invoice() {
+        let invoice = invoice(InvoiceStatus::Verified);
+        let result = reconcile_with_contract(&invoice, &MockContract::default()).await.unwrap();
+        assert!(!result.onchain_found);
+        assert_eq!(result.diffs, vec![diff("status", "Verified".to_string(), "not registered".to_string())]);
+
+        let err = reconcile_with_contract(&invoice, &MockContract::default().unavailable("rpc down")).await.unwrap_err();
+        assert!(matches!(err, ServiceError::ChainRpcError(_)));
+    }
+}
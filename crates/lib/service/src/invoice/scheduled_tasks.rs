@@ -1,20 +1,36 @@
 use chrono::{Utc, Duration, Local, NaiveTime};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::{self, sleep};
-use crate::invoice::InvoiceService;
-use log::{info, error};
+use crate::invoice::{InvoiceService, SettlementOptions};
+use tracing::{info, error};
 use std::sync::Arc;
 
-// 设置定时任务
-pub fn setup_scheduled_tasks(invoice_service: Arc<InvoiceService>) {
-    // 启动每日计息任务
-    tokio::spawn(daily_interest_calculation_task(invoice_service.clone()));
-    
-    // 启动到期兑付任务
-    tokio::spawn(maturity_payment_task(invoice_service));
+// 设置定时任务，返回 (任务名, 句柄) 供调用方登记到优雅停机；`shutdown` 变为 true 后任务在等待下一次运行时退出，
+// 正在执行的一轮会先完成
+pub fn setup_scheduled_tasks(
+    invoice_service: Arc<InvoiceService>,
+    settlement_options: SettlementOptions,
+    shutdown: watch::Receiver<bool>,
+) -> Vec<(&'static str, JoinHandle<()>)> {
+    vec![
+        // 启动每日计息任务
+        ("daily_interest", tokio::spawn(daily_interest_calculation_task(invoice_service.clone(), shutdown.clone()))),
+        // 启动到期兑付任务
+        ("maturity_payment", tokio::spawn(maturity_payment_task(invoice_service, settlement_options, shutdown))),
+    ]
+}
+
+// 等待到下一次运行时间，期间收到停机信号时返回 false
+async fn wait_until(duration: std::time::Duration, shutdown: &mut watch::Receiver<bool>) -> bool {
+    tokio::select! {
+        _ = sleep(duration) => !*shutdown.borrow(),
+        _ = shutdown.wait_for(|stop| *stop) => false,
+    }
 }
 
 // 每日计息任务
-async fn daily_interest_calculation_task(invoice_service: Arc<InvoiceService>) {
+async fn daily_interest_calculation_task(invoice_service: Arc<InvoiceService>, mut shutdown: watch::Receiver<bool>) {
     // 设置每日运行时间（例如UTC 0:10，给前一天的交易留出时间完成）
     let target_time = NaiveTime::from_hms_opt(0, 10, 0).unwrap();
     
@@ -32,7 +48,9 @@ async fn daily_interest_calculation_task(invoice_service: Arc<InvoiceService>) {
         // 计算需要等待的时间
         let wait_duration = next_run.and_local_timezone(Local).unwrap() - now;
         info!("下一次计息任务将在 {} 运行", next_run);
-        sleep(wait_duration.to_std().unwrap()).await;
+        if !wait_until(wait_duration.to_std().unwrap(), &mut shutdown).await {
+            break;
+        }
         
         // 获取昨天的日期（计算的是前一天的利息）
         let yesterday = Utc::now().date_naive() - Duration::days(1);
@@ -55,10 +73,11 @@ async fn daily_interest_calculation_task(invoice_service: Arc<InvoiceService>) {
         // 避免在同一秒内多次执行
         sleep(time::Duration::from_secs(1)).await;
     }
+    info!("计息任务已停止");
 }
 
 // 到期兑付任务
-async fn maturity_payment_task(invoice_service: Arc<InvoiceService>) {
+async fn maturity_payment_task(invoice_service: Arc<InvoiceService>, settlement_options: SettlementOptions, mut shutdown: watch::Receiver<bool>) {
     // 设置每日运行时间（例如UTC 1:00，在计息任务之后）
     let target_time = NaiveTime::from_hms_opt(1, 0, 0).unwrap();
     
@@ -76,7 +95,9 @@ async fn maturity_payment_task(invoice_service: Arc<InvoiceService>) {
         // 计算需要等待的时间
         let wait_duration = next_run.and_local_timezone(Local).unwrap() - now;
         info!("下一次到期兑付任务将在 {} 运行", next_run);
-        sleep(wait_duration.to_std().unwrap()).await;
+        if !wait_until(wait_duration.to_std().unwrap(), &mut shutdown).await {
+            break;
+        }
         
         // 获取当天日期（检查今天到期的票据）
         let today = Utc::now().date_naive();
@@ -84,9 +105,12 @@ async fn maturity_payment_task(invoice_service: Arc<InvoiceService>) {
         info!("开始处理 {} 到期的票据", today);
         
         // 执行到期兑付任务
-        match invoice_service.process_maturity_payments_for_date(today).await {
-            Ok(success_count) => {
-                info!("到期兑付任务完成: 成功处理 {} 条持仓记录", success_count);
+        match invoice_service.process_maturity_payments_for_date(today, &settlement_options).await {
+            Ok(summary) => {
+                info!(
+                    "到期兑付任务完成: {} 张票据兑付成功，{} 张未到期跳过，{} 张失败",
+                    summary.settled, summary.not_matured, summary.failed
+                );
             },
             Err(e) => {
                 error!("执行到期兑付任务失败: {:?}", e);
@@ -96,4 +120,5 @@ async fn maturity_payment_task(invoice_service: Arc<InvoiceService>) {
         // 避免在同一秒内多次执行
         sleep(time::Duration::from_secs(1)).await;
     }
+    info!("到期兑付任务已停止");
 }
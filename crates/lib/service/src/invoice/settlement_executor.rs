@@ -0,0 +1,268 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use tracing::{error, info, warn};
+
+use common::domain::entity::RepaymentPayout;
+
+use crate::error::ServiceError;
+
+/// 等待锁时的轮询间隔
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 跨实例互斥锁
+#[async_trait]
+pub trait SettlementLock: Send + Sync {
+    /// 尝试加锁，成功返回 true。`token` 用于释放时确认仍由自己持有
+    async fn try_acquire(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError>;
+
+    /// 仅当锁仍由 `token` 持有时释放
+    async fn release(&self, key: &str, token: &str) -> Result<(), ServiceError>;
+
+    /// 仅当锁仍由 `token` 持有时将 TTL 重置为 `ttl_ms`，锁已过期或被他人持有时返回 false
+    async fn extend(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError>;
+}
+
+/// 结算状态存储，以数据库中的状态为准
+#[async_trait]
+pub trait SettlementStore: Send + Sync {
+    /// 票据已结算时返回记录的交易哈希
+    async fn settled_tx_hash(&self, invoice_id: &str) -> Result<Option<String>, ServiceError>;
+
+    /// 记录结算交易哈希并将票据标记为已清算，`actor` 写入审计记录，`payouts` 与状态在同一事务内写入
+    async fn mark_settled(&self, invoice_id: &str, tx_hash: &str, actor: &str, payouts: &[RepaymentPayout]) -> Result<(), ServiceError>;
+}
+
+/// 实际执行结算 (提交交易) 并返回交易哈希
+#[async_trait]
+pub trait SettlementSubmitter: Send + Sync {
+    async fn submit(&self, invoice_id: &str) -> Result<String, ServiceError>;
+
+    /// 提交成功后随结算状态一起持久化的兑付明细，默认没有
+    fn payouts(&self, _tx_hash: &str) -> Vec<RepaymentPayout> {
+        Vec::new()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SettlementOutcome {
+    pub tx_hash: String,
+    /// true 表示票据此前已结算，本次未重新提交
+    pub already_settled: bool,
+}
+
+/// 幂等结算：加票据级分布式锁 -> 重新检查数据库状态 -> 仅在未结算时提交 -> 持久化交易哈希 -> 释放锁
+///
+/// 持锁期间每隔 TTL 的三分之一续期，提交耗时超过 TTL 时锁不会过期；进程崩溃后锁最多 TTL 后释放。
+pub struct SettlementExecutor<L, S> {
+    lock: L,
+    store: S,
+    lock_ttl_ms: u64,
+    wait_ms: u64,
+}
+
+impl<L: SettlementLock, S: SettlementStore> SettlementExecutor<L, S> {
+    pub fn new(lock: L, store: S, lock_ttl_ms: u64, wait_ms: u64) -> Self {
+        Self { lock, store, lock_ttl_ms, wait_ms }
+    }
+
+    pub fn with_lock_timing(mut self, lock_ttl_ms: u64, wait_ms: u64) -> Self {
+        self.lock_ttl_ms = lock_ttl_ms;
+        self.wait_ms = wait_ms;
+        self
+    }
+
+    fn lock_key(invoice_id: &str) -> String {
+        format!("lock:settle:{}", invoice_id)
+    }
+
+    pub async fn settle(&self, invoice_id: &str, actor: &str, submitter: &dyn SettlementSubmitter) -> Result<SettlementOutcome, ServiceError> {
+        if let Some(tx_hash) = self.store.settled_tx_hash(invoice_id).await? {
+            return Ok(SettlementOutcome { tx_hash, already_settled: true });
+        }
+
+        let key = Self::lock_key(invoice_id);
+        let token = uuid::Uuid::new_v4().to_string();
+        let deadline = Instant::now() + Duration::from_millis(self.wait_ms);
+        while !self.lock.try_acquire(&key, &token, self.lock_ttl_ms).await? {
+            if Instant::now() >= deadline {
+                return Err(ServiceError::SettlementInProgress(invoice_id.to_string()));
+            }
+            tokio::time::sleep(LOCK_RETRY_INTERVAL).await;
+        }
+
+        let work = self.settle_locked(invoice_id, actor, submitter);
+        tokio::pin!(work);
+        let result = tokio::select! {
+            result = &mut work => result,
+            // 续期失败说明锁已丢失，已开始的提交不能中断，只能等待完成
+            _ = self.keep_alive(&key, &token, invoice_id) => work.await,
+        };
+        if let Err(e) = self.lock.release(&key, &token).await {
+            // 释放失败时锁会在 TTL 后自动过期
+            warn!("Failed to release settlement lock for invoice {}: {}", invoice_id, e);
+        }
+        result
+    }
+
+    /// 定期续期，仅在锁丢失时返回
+    async fn keep_alive(&self, key: &str, token: &str, invoice_id: &str) {
+        let period = Duration::from_millis((self.lock_ttl_ms / 3).max(1));
+        loop {
+            tokio::time::sleep(period).await;
+            match self.lock.extend(key, token, self.lock_ttl_ms).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    error!("Settlement lock for invoice {} was lost before settling finished", invoice_id);
+                    return;
+                }
+                Err(e) => warn!("Failed to renew settlement lock for invoice {}: {}", invoice_id, e),
+            }
+        }
+    }
+
+    async fn settle_locked(&self, invoice_id: &str, actor: &str, submitter: &dyn SettlementSubmitter) -> Result<SettlementOutcome, ServiceError> {
+        // 持锁后必须重新检查，等锁期间可能已被其他实例结算
+        if let Some(tx_hash) = self.store.settled_tx_hash(invoice_id).await? {
+            info!("Invoice {} already settled in tx {}", invoice_id, tx_hash);
+            return Ok(SettlementOutcome { tx_hash, already_settled: true });
+        }
+
+        let tx_hash = submitter.submit(invoice_id).await?;
+        self.store.mark_settled(invoice_id, &tx_hash, actor, &submitter.payouts(&tx_hash)).await?;
+        info!("Invoice {} settled in tx {}", invoice_id, tx_hash);
+        Ok(SettlementOutcome { tx_hash, already_settled: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use crate::test_support::{MemoryLock, MemorySettlementStore};
+
+    #[derive(Default)]
+    struct CountingSubmitter(AtomicUsize);
+
+    #[async_trait]
+    impl SettlementSubmitter for CountingSubmitter {
+        async fn submit(&self, invoice_id: &str) -> Result<String, ServiceError> {
+            let n = self.0.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Ok(format!("0x{}-{}", invoice_id, n))
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_settle_submits_once() {
+        let lock = MemoryLock::default();
+        let store = MemorySettlementStore::default();
+        let submitter = Arc::new(CountingSubmitter::default());
+
+        // 模拟多个实例共享同一个 Redis 和数据库
+        let mut handles = Vec::new();
+        for _ in 0..8 {
+            let executor = SettlementExecutor::new(lock.clone(), store.clone(), 30_000, 5_000);
+            let submitter = submitter.clone();
+            handles.push(tokio::spawn(async move { executor.settle("inv-1", "system", submitter.as_ref()).await }));
+        }
+
+        let mut outcomes = Vec::new();
+        for handle in handles {
+            outcomes.push(handle.await.unwrap().unwrap());
+        }
+
+        assert_eq!(submitter.0.load(Ordering::SeqCst), 1);
+        assert!(outcomes.iter().all(|o| o.tx_hash == "0xinv-1-0"));
+        assert_eq!(outcomes.iter().filter(|o| !o.already_settled).count(), 1);
+
+        // 重试直接返回已记录的交易哈希
+        let executor = SettlementExecutor::new(lock.clone(), store.clone(), 30_000, 0);
+        let retry = executor.settle("inv-1", "system", submitter.as_ref()).await.unwrap();
+        assert_eq!(retry, SettlementOutcome { tx_hash: "0xinv-1-0".to_string(), already_settled: true });
+        assert!(lock.is_empty());
+    }
+
+    struct PayoutSubmitter(RepaymentPayout);
+
+    #[async_trait]
+    impl SettlementSubmitter for PayoutSubmitter {
+        async fn submit(&self, _invoice_id: &str) -> Result<String, ServiceError> {
+            Ok("0xpaid".to_string())
+        }
+
+        fn payouts(&self, tx_hash: &str) -> Vec<RepaymentPayout> {
+            vec![RepaymentPayout { settlement_tx_hash: tx_hash.to_string(), ..self.0.clone() }]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_payouts_are_stored_with_settlement() {
+        use std::str::FromStr;
+        use mongodb::bson::{oid::ObjectId, DateTime, Decimal128};
+        let payout = RepaymentPayout {
+            id: None,
+            invoice_id: ObjectId::new(),
+            batch_id: ObjectId::new(),
+            user_id: ObjectId::new(),
+            wallet_address: "0xholder".to_string(),
+            token_balance: Decimal128::from_str("100").unwrap(),
+            amount: Decimal128::from_str("105").unwrap(),
+            settlement_tx_hash: String::new(),
+            created_at: DateTime::now(),
+        };
+        let store = MemorySettlementStore::default();
+        let executor = SettlementExecutor::new(MemoryLock::default(), store.clone(), 30_000, 0);
+        let submitter = PayoutSubmitter(payout);
+
+        executor.settle("inv-3", "system", &submitter).await.unwrap();
+        // 已结算后重试不会重复写入明细
+        executor.settle("inv-3", "system", &submitter).await.unwrap();
+
+        let stored = store.payouts();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].settlement_tx_hash, "0xpaid");
+    }
+
+    #[derive(Clone, Default)]
+    struct RenewCountingLock {
+        inner: MemoryLock,
+        extends: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl SettlementLock for RenewCountingLock {
+        async fn try_acquire(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError> {
+            self.inner.try_acquire(key, token, ttl_ms).await
+        }
+
+        async fn release(&self, key: &str, token: &str) -> Result<(), ServiceError> {
+            self.inner.release(key, token).await
+        }
+
+        async fn extend(&self, key: &str, token: &str, ttl_ms: u64) -> Result<bool, ServiceError> {
+            self.extends.fetch_add(1, Ordering::SeqCst);
+            self.inner.extend(key, token, ttl_ms).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_renewed_while_submitting() {
+        let lock = RenewCountingLock::default();
+        // 提交耗时 50ms，远超 9ms 的 TTL
+        let executor = SettlementExecutor::new(lock.clone(), MemorySettlementStore::default(), 9, 0);
+        executor.settle("inv-4", "system", &CountingSubmitter::default()).await.unwrap();
+        assert!(lock.extends.load(Ordering::SeqCst) >= 3);
+        assert!(lock.inner.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_settle_times_out_while_locked() {
+        let lock = MemoryLock::default();
+        lock.try_acquire("lock:settle:inv-2", "other", 30_000).await.unwrap();
+        let executor = SettlementExecutor::new(lock, MemorySettlementStore::default(), 30_000, 0);
+        let result = executor.settle("inv-2", "system", &CountingSubmitter::default()).await;
+        assert!(matches!(result, Err(ServiceError::SettlementInProgress(_))));
+    }
+}
@@ -0,0 +1,113 @@
+use common::domain::entity::{Invoice, invoice_status::InvoiceStatus};
+
+use crate::error::ServiceError;
+use crate::service::interest_calculator::due_date_to_millis;
+
+/// 兑付选项
+#[derive(Debug, Clone, Default)]
+pub struct SettlementOptions {
+    /// 允许提前兑付的窗口 (秒)，0 表示必须到期后才能兑付
+    pub early_window_secs: i64,
+    /// 管理员强制提前兑付时填写操作人地址，会写入审计日志
+    pub early_override_by: Option<String>,
+}
+
+/// 检查当前时间是否允许兑付：`now >= due_date - early_window`
+pub fn ensure_settlement_allowed(invoice_number: &str, due_date: i64, now_ms: i64, early_window_secs: i64) -> Result<(), ServiceError> {
+    let due_ms = due_date_to_millis(due_date);
+    let earliest_ms = due_ms - early_window_secs.max(0) * 1000;
+    if now_ms < earliest_ms {
+        return Err(ServiceError::SettlementBeforeMaturity(format!(
+            "invoice {} cannot be settled before {} (due {})",
+            invoice_number,
+            chrono::DateTime::from_timestamp_millis(earliest_ms).map(|d| d.to_rfc3339()).unwrap_or_default(),
+            chrono::DateTime::from_timestamp_millis(due_ms).map(|d| d.to_rfc3339()).unwrap_or_default(),
+        )));
+    }
+    Ok(())
+}
+
+/// 批量兑付的候选票据状态 (已上架/在售/已融资/逾期)
+pub const BATCH_SETTLEMENT_STATUSES: [InvoiceStatus; 4] = [InvoiceStatus::Packaged, InvoiceStatus::OnSale, InvoiceStatus::Financed, InvoiceStatus::Overdue];
+
+/// 判断票据是否可纳入批量兑付：未结算、已到期 (不支持提前兑付覆盖)、且已募满。
+/// `available_shares` 为 Redis 中剩余可售份数，缓存缺失时无法确认募集情况，按不可兑付处理。
+pub fn is_batch_settlement_eligible(invoice: &Invoice, available_shares: Option<u64>, now_ms: i64, early_window_secs: i64) -> bool {
+    invoice.settlement_tx_hash.is_none()
+        && BATCH_SETTLEMENT_STATUSES.contains(&invoice.status)
+        && ensure_settlement_allowed(&invoice.invoice_number, invoice.due_date, now_ms, early_window_secs).is_ok()
+        && available_shares == Some(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DUE_SECS: i64 = 1704067200; // 2024-01-01T00:00:00Z
+
+    #[test]
+    fn test_settlement_boundary_at_due_date() {
+        let due_ms = DUE_SECS * 1000;
+        assert!(matches!(
+            ensure_settlement_allowed("INV-1", DUE_SECS, due_ms - 1, 0),
+            Err(ServiceError::SettlementBeforeMaturity(_))
+        ));
+        assert!(ensure_settlement_allowed("INV-1", DUE_SECS, due_ms, 0).is_ok());
+        assert!(ensure_settlement_allowed("INV-1", DUE_SECS, due_ms + 1, 0).is_ok());
+        // 毫秒存储的到期日同样适用
+        assert!(ensure_settlement_allowed("INV-1", due_ms, due_ms - 1, 0).is_err());
+    }
+
+    #[test]
+    fn test_settlement_boundary_with_early_window() {
+        let due_ms = DUE_SECS * 1000;
+        let window = 24 * 3600;
+        assert!(ensure_settlement_allowed("INV-1", DUE_SECS, due_ms - window * 1000, window).is_ok());
+        assert!(ensure_settlement_allowed("INV-1", DUE_SECS, due_ms - window * 1000 - 1, window).is_err());
+        // 负数窗口按 0 处理
+        assert!(ensure_settlement_allowed("INV-1", DUE_SECS, due_ms - 1, -10).is_err());
+    }
+
+    #[test]
+    fn test_batch_settlement_eligibility() {
+        let due_ms = DUE_SECS * 1000;
+        let mut invoice = Invoice {
+            id: None,
+            invoice_number: "INV-1".to_string(),
+            payee: "0xa".to_string(),
+            payer: "0xb".to_string(),
+            amount: 1000,
+            currency: "USDC".to_string(),
+            due_date: DUE_SECS,
+            invoice_ipfs_hash: None,
+            contract_ipfs_hash: None,
+            status: InvoiceStatus::OnSale,
+            blockchain_timestamp: None,
+            token_batch: None,
+            is_cleared: None,
+            is_valid: None,
+            document_count: 0,
+            document_total_bytes: 0,
+            accepted_terms: None,
+            terms_exempt: false,
+            settlement_tx_hash: None,
+            funded_shares: 0,
+            version: 0,
+            deleted_at: None,
+            created_at: mongodb::bson::DateTime::now(),
+            updated_at: mongodb::bson::DateTime::now(),
+        };
+
+        assert!(is_batch_settlement_eligible(&invoice, Some(0), due_ms, 0));
+        // 未到期、未募满、募集情况未知
+        assert!(!is_batch_settlement_eligible(&invoice, Some(0), due_ms - 1, 0));
+        assert!(!is_batch_settlement_eligible(&invoice, Some(3), due_ms, 0));
+        assert!(!is_batch_settlement_eligible(&invoice, None, due_ms, 0));
+
+        invoice.status = InvoiceStatus::Repaid;
+        assert!(!is_batch_settlement_eligible(&invoice, Some(0), due_ms, 0));
+        invoice.status = InvoiceStatus::Overdue;
+        invoice.settlement_tx_hash = Some("offchain-1".to_string());
+        assert!(!is_batch_settlement_eligible(&invoice, Some(0), due_ms, 0));
+    }
+}
@@ -0,0 +1,37 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use mongodb::bson::{DateTime, oid::ObjectId};
+
+use common::domain::entity::{Invoice, invoice_status::InvoiceStatus};
+
+/// 一次已提交的票据状态变更
+#[derive(Debug, Clone)]
+pub struct InvoiceStatusChange {
+    pub invoice_id: ObjectId,
+    pub invoice_number: String,
+    /// 收款企业的钱包地址
+    pub payee: String,
+    pub from: InvoiceStatus,
+    pub to: InvoiceStatus,
+    pub occurred_at: DateTime,
+}
+
+impl InvoiceStatusChange {
+    pub fn new(invoice_id: ObjectId, invoice: &Invoice, from: InvoiceStatus, to: InvoiceStatus) -> Self {
+        Self {
+            invoice_id,
+            invoice_number: invoice.invoice_number.clone(),
+            payee: invoice.payee.clone(),
+            from,
+            to,
+            occurred_at: DateTime::now(),
+        }
+    }
+}
+
+/// `InvoiceService` 在状态变更提交后调用，通知失败只记录日志，不影响状态变更本身
+#[async_trait]
+pub trait InvoiceStatusNotifier: Send + Sync {
+    async fn invoice_status_changed(self: Arc<Self>, change: InvoiceStatusChange);
+}
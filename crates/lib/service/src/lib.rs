@@ -5,9 +5,12 @@ pub mod invoice;
 pub mod service;
 pub mod error;
 pub mod cache;
+pub mod metrics;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 use ::redis::{Client, RedisError};
-use log::info;
+use tracing::info;
 use configs::cfgs::Redis;
 // Re-export key items for easier access from other crates
 pub use db::{create_indexes, init_mongodb};
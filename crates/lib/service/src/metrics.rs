@@ -0,0 +1,38 @@
+//! 进程内 Prometheus 指标
+//!
+//! 所有指标注册到同一个 [`REGISTRY`]，由 api-server 的 `/metrics` 统一导出。
+
+use once_cell::sync::Lazy;
+use prometheus::{IntCounterVec, Opts, Registry};
+
+pub static REGISTRY: Lazy<Registry> = Lazy::new(Registry::new);
+
+/// 合约写操作次数，按操作类型与结果 (success / reverted / paused / error) 分组
+pub static CONTRACT_WRITES: Lazy<IntCounterVec> = Lazy::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new("contract_writes_total", "Contract write operations by outcome"),
+        &["operation", "outcome"],
+    )
+    .expect("valid contract_writes_total metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("contract_writes_total registered once");
+    counter
+});
+
+pub fn record_contract_write(operation: &str, outcome: &str) {
+    CONTRACT_WRITES.with_label_values(&[operation, outcome]).inc();
+}
+
+/// ERC20 转账索引跳过的转账数，按原因 (如 amount_overflow) 分组；非零即需要人工核对余额
+pub static TRANSFERS_SKIPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    let counter = IntCounterVec::new(
+        Opts::new("erc20_transfers_skipped_total", "ERC20 transfers the indexer could not record"),
+        &["reason"],
+    )
+    .expect("valid erc20_transfers_skipped_total metric");
+    REGISTRY.register(Box::new(counter.clone())).expect("erc20_transfers_skipped_total registered once");
+    counter
+});
+
+pub fn record_skipped_transfer(reason: &str) {
+    TRANSFERS_SKIPPED.with_label_values(&[reason]).inc();
+}
@@ -0,0 +1,39 @@
+use futures::stream::TryStreamExt;
+use mongodb::{ClientSession, Collection, Database, bson::doc};
+
+use common::domain::entity::AuditLog;
+
+pub struct AuditLogRepository {
+    collection: Collection<AuditLog>,
+}
+
+impl AuditLogRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<AuditLog>("audit_logs"),
+        }
+    }
+
+    // Append an audit entry
+    pub async fn create(&self, entry: &AuditLog) -> Result<AuditLog, mongodb::error::Error> {
+        let result = self.collection.insert_one(entry).await?;
+        let mut created = entry.clone();
+        created.id = result.inserted_id.as_object_id();
+        Ok(created)
+    }
+
+    // Append an audit entry within a transaction
+    pub async fn create_session(&self, entry: &AuditLog, session: &mut ClientSession) -> Result<AuditLog, mongodb::error::Error> {
+        let result = self.collection.insert_one(entry).session(session).await?;
+        let mut created = entry.clone();
+        created.id = result.inserted_id.as_object_id();
+        Ok(created)
+    }
+
+    // Find entries for a target, oldest first
+    pub async fn find_by_target(&self, target_type: &str, target_id: &str) -> Result<Vec<AuditLog>, mongodb::error::Error> {
+        let filter = doc! { "target_type": target_type, "target_id": target_id };
+        let cursor = self.collection.find(filter).sort(doc! { "created_at": 1, "_id": 1 }).await?;
+        cursor.try_collect().await
+    }
+}
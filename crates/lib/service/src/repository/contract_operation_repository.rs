@@ -0,0 +1,61 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    Collection, Database,
+    bson::{self, DateTime, doc},
+};
+
+use common::domain::entity::{ContractOperation, ContractOperationStatus};
+
+pub struct ContractOperationRepository {
+    collection: Collection<ContractOperation>,
+}
+
+impl ContractOperationRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<ContractOperation>("contract_operations"),
+        }
+    }
+
+    /// 记录一次提交结果：同一 (operation, reference) 合并为一条记录，attempts 加一
+    pub async fn record_attempt(
+        &self,
+        operation: &str,
+        reference: &str,
+        status: ContractOperationStatus,
+        tx_hash: Option<String>,
+        revert_reason: Option<String>,
+        error: Option<String>,
+    ) -> Result<(), mongodb::error::Error> {
+        let now = DateTime::now();
+        let filter = doc! { "operation": operation, "reference": reference };
+        let update = doc! {
+            "$set": {
+                "status": status_bson(status)?,
+                "tx_hash": tx_hash,
+                "revert_reason": revert_reason,
+                "error": error,
+                "updated_at": now,
+            },
+            "$setOnInsert": { "created_at": now },
+            "$inc": { "attempts": 1_i64 },
+        };
+        self.collection.update_one(filter, update).upsert(true).await?;
+        Ok(())
+    }
+
+    // Find failed operations, most recent first
+    pub async fn find_failures(&self, operation: Option<&str>, skip: u64, limit: i64) -> Result<(Vec<ContractOperation>, u64), mongodb::error::Error> {
+        let mut filter = doc! { "status": status_bson(ContractOperationStatus::Failed)? };
+        if let Some(operation) = operation {
+            filter.insert("operation", operation);
+        }
+        let total = self.collection.count_documents(filter.clone()).await?;
+        let cursor = self.collection.find(filter).sort(doc! { "updated_at": -1 }).skip(skip).limit(limit).await?;
+        Ok((cursor.try_collect().await?, total))
+    }
+}
+
+fn status_bson(status: ContractOperationStatus) -> Result<bson::Bson, mongodb::error::Error> {
+    bson::to_bson(&status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))
+}
@@ -1,19 +1,26 @@
 use futures::TryStreamExt;
 use mongodb::{
-    bson::{self, doc, oid::ObjectId, DateTime},
-    options::{FindOptions, UpdateOptions}, // Import necessary options
+    bson::{self, doc, oid::ObjectId, DateTime, Document},
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument, UpdateOptions}, // Import necessary options
     results::{DeleteResult, UpdateResult},  // Import result types
-    Collection, Database,
+    ClientSession, Collection, Database,
 };
 
 use serde::Serialize;
 use common::domain::entity::{Enterprise, EnterpriseStatus};
+use common::utils::wallet_utils::normalize_address;
+use common::pagination::OffsetPagination;
 // Needed for generic update
 
 pub struct EnterpriseRepository {
     collection: Collection<Enterprise>,
 }
 
+/// 企业钱包地址唯一索引 (不区分大小写)
+pub const ENTERPRISE_WALLET_INDEX: &str = "wallet_address_1_unique_ci";
+/// 早期区分大小写的钱包地址唯一索引，建索引时删除
+pub const LEGACY_ENTERPRISE_WALLET_INDEX: &str = "wallet_address_1_unique";
+
 // --- Struct for partial updates ---
 #[derive(Serialize, Default, Debug)]
 pub struct UpdateEnterpriseData {
@@ -29,6 +36,29 @@ pub struct UpdateEnterpriseData {
     // updated_at will be set automatically
 }
 
+/// 管理员企业列表的筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct EnterpriseFilter {
+    /// 审核状态
+    pub status: Option<EnterpriseStatus>,
+    /// 名称包含的子串，不区分大小写
+    pub name: Option<String>,
+}
+
+impl EnterpriseFilter {
+    pub fn to_document(&self) -> Result<Document, mongodb::error::Error> {
+        let mut filter = doc! {};
+        if let Some(status) = &self.status {
+            filter.insert("status", bson::to_bson(status)?);
+        }
+        if let Some(name) = self.name.as_deref().map(str::trim).filter(|n| !n.is_empty()) {
+            // 转义后按字面子串匹配
+            filter.insert("name", doc! { "$regex": regex::escape(name), "$options": "i" });
+        }
+        Ok(filter)
+    }
+}
+
 impl EnterpriseRepository {
     pub fn new(db: &Database) -> Self {
         Self {
@@ -53,6 +83,19 @@ impl EnterpriseRepository {
         Ok(results)
     }
 
+    /// 按条件分页查询企业 (最新创建在前)，同时返回符合条件的总数
+    pub async fn list(&self, filter: &EnterpriseFilter, pagination: OffsetPagination) -> Result<(Vec<Enterprise>, u64), mongodb::error::Error> {
+        let filter = filter.to_document()?;
+        let total = self.collection.count_documents(filter.clone()).await?;
+        let cursor = self.collection
+            .find(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(pagination.skip())
+            .limit(pagination.page_size as i64)
+            .await?;
+        Ok((cursor.try_collect().await?, total))
+    }
+
     // Find enterprise by wallet address
     pub async fn find_by_wallet(&self, wallet_address: &str) -> Result<Option<Enterprise>, mongodb::error::Error> {
         let filter = doc! { "wallet_address": wallet_address.to_lowercase() };
@@ -61,16 +104,31 @@ impl EnterpriseRepository {
 
     // Find enterprise by wallet address (case-insensitive)
     pub async fn find_by_wallet_address(&self, wallet_address: &str) -> Result<Option<Enterprise>, mongodb::error::Error> {
-        // Create a case-insensitive regex query
+        // 格式或 EIP-55 校验和不合法的地址不可能对应任何企业
+        let Ok(wallet_address) = normalize_address(wallet_address) else {
+            return Ok(None);
+        };
+        // Create a case-insensitive regex query (历史数据可能以校验和形式存储)
         let filter = doc! { 
             "wallet_address": bson::Regex { 
-                pattern: format!("^{}$", regex::escape(wallet_address)), 
+                pattern: format!("^{}$", regex::escape(&wallet_address)), 
                 options: "i".to_string() // "i" for case-insensitive
             }
         };
         self.collection.find_one(filter).await
     }
 
+    // Find enterprise by wallet address within a transaction session (case-insensitive)
+    pub async fn find_by_wallet_address_session(&self, wallet_address: &str, session: &mut ClientSession) -> Result<Option<Enterprise>, mongodb::error::Error> {
+        let filter = doc! {
+            "wallet_address": bson::Regex {
+                pattern: format!("^{}$", regex::escape(wallet_address)),
+                options: "i".to_string()
+            }
+        };
+        self.collection.find_one(filter).session(session).await
+    }
+
     // Create new enterprise
     pub async fn create(&self, name: &str, wallet_address: &str) -> Result<Enterprise, mongodb::error::Error> {
         let enterprise = Enterprise::new(name.to_string(), wallet_address.to_lowercase());
@@ -83,10 +141,32 @@ impl EnterpriseRepository {
         Ok(created_enterprise)
     }
 
+    /// 按钱包地址幂等创建企业，返回 (企业, 是否新建)。
+    /// 该地址已有企业 (含以校验和形式保存的历史数据) 时不做修改并返回已有企业；
+    /// 并发创建由唯一索引保证只有一条写入成功
+    pub async fn create_or_get(&self, name: &str, wallet_address: &str) -> Result<(Enterprise, bool), mongodb::error::Error> {
+        if let Some(existing) = self.find_by_wallet_address(wallet_address).await? {
+            return Ok((existing, false));
+        }
+
+        let id = ObjectId::new();
+        let mut enterprise = Enterprise::new(name.to_string(), wallet_address.to_lowercase());
+        enterprise.id = Some(id);
+        let filter = doc! { "wallet_address": &enterprise.wallet_address };
+        let update = doc! { "$setOnInsert": bson::to_document(&enterprise)? };
+        let options = FindOneAndUpdateOptions::builder().upsert(true).return_document(ReturnDocument::After).build();
+        let stored = self.collection.find_one_and_update(filter, update).with_options(options).await?
+            .ok_or_else(|| mongodb::error::Error::custom(format!("Upsert of enterprise {} returned no document", wallet_address)))?;
+        let created = stored.id == Some(id);
+        Ok((stored, created))
+    }
+
     // Generic Update enterprise data
     pub async fn update(&self, id: ObjectId, data: UpdateEnterpriseData) -> Result<UpdateResult, mongodb::error::Error> {
         let filter = doc! { "_id": id };
         
+        // 钱包地址统一以小写保存
+        let data = UpdateEnterpriseData { wallet_address: data.wallet_address.map(|w| w.to_lowercase()), ..data };
         // Convert the update data struct to BSON, skipping None fields automatically due to serde attributes
         let mut update_doc = bson::to_document(&data)?;
         
@@ -113,6 +193,20 @@ impl EnterpriseRepository {
         self.collection.update_one(filter, update).await
     }
 
+    /// 设置企业审核状态并返回更新后的企业；驳回时记录原因，其他状态清除原因
+    pub async fn set_verification_status(&self, id: ObjectId, status: EnterpriseStatus, reason: Option<&str>) -> Result<Option<Enterprise>, mongodb::error::Error> {
+        let filter = doc! { "_id": id };
+        let update = match reason.filter(|_| status == EnterpriseStatus::Rejected) {
+            Some(reason) => doc! { "$set": { "status": bson::to_bson(&status)?, "rejection_reason": reason, "updated_at": DateTime::now() } },
+            None => doc! {
+                "$set": { "status": bson::to_bson(&status)?, "updated_at": DateTime::now() },
+                "$unset": { "rejection_reason": "" },
+            },
+        };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.collection.find_one_and_update(filter, update).with_options(options).await
+    }
+
     // Update enterprise KYC details IPFS hash
     pub async fn update_kyc_hash(&self, id: ObjectId, hash: &str) -> Result<UpdateResult, mongodb::error::Error> {
         let now = DateTime::now();
@@ -126,4 +220,101 @@ impl EnterpriseRepository {
         
         self.collection.update_one(filter, update).await
     }
-} 
\ No newline at end of file
+} 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_enterprise_indexes;
+    use crate::test_support::TestDb;
+    use mongodb::IndexModel;
+
+    #[test]
+    fn test_filter_document() {
+        let filter = EnterpriseFilter { status: Some(EnterpriseStatus::Verified), name: Some(" Acme (HK) ".to_string()) };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! { "status": "Verified", "name": { "$regex": r"Acme \(HK\)", "$options": "i" } }
+        );
+        assert_eq!(EnterpriseFilter { status: None, name: Some("  ".to_string()) }.to_document().unwrap(), doc! {});
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_list_filters_by_name_and_status() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = EnterpriseRepository::new(&db);
+
+        let mut ids = Vec::new();
+        for name in ["Alpha Trading", "alpha logistics", "Beta Foods"] {
+            let wallet = format!("0x{:0>40}", ObjectId::new().to_hex());
+            ids.push(repo.create(name, &wallet).await.unwrap().id.unwrap());
+        }
+        repo.set_verification_status(ids[1], EnterpriseStatus::Verified, None).await.unwrap();
+
+        let by_name = EnterpriseFilter { status: None, name: Some("ALPHA".to_string()) };
+        let (rows, total) = repo.list(&by_name, OffsetPagination::new(1, 10)).await.unwrap();
+        assert_eq!(total, 2);
+        assert!(rows.iter().all(|e| e.name.to_lowercase().contains("alpha")));
+
+        let (first_page, total) = repo.list(&by_name, OffsetPagination::new(1, 1)).await.unwrap();
+        assert_eq!((first_page.len(), total), (1, 2));
+
+        let verified = EnterpriseFilter { status: Some(EnterpriseStatus::Verified), name: None };
+        let (rows, total) = repo.list(&verified, OffsetPagination::new(1, 10)).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(rows[0].id, Some(ids[1]));
+        test_db.cleanup().await;
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_duplicate_creation_returns_existing_enterprise() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = EnterpriseRepository::new(&db);
+        create_enterprise_indexes(&db).await.unwrap();
+
+        let wallet = format!("0x{:0>40}", ObjectId::new().to_hex());
+        let (first, created) = repo.create_or_get("Acme", &wallet).await.unwrap();
+        assert!(created);
+        // 重试 (地址大小写不同) 返回同一企业，不覆盖名称
+        let (second, created_again) = repo.create_or_get("Acme Retry", &wallet.to_uppercase().replacen("0X", "0x", 1)).await.unwrap();
+        let count = db.collection::<Enterprise>("enterprises").count_documents(doc! { "wallet_address": &wallet }).await.unwrap();
+        // 绕过 create_or_get 直接插入重复地址会被唯一索引拒绝
+        let direct = repo.create("Acme Copy", &wallet).await;
+        // 以校验和形式保存的历史数据同样占用该地址
+        let legacy = db.collection::<Enterprise>("enterprises")
+            .insert_one(Enterprise::new("Acme Legacy".to_string(), wallet.to_uppercase().replacen("0X", "0x", 1)))
+            .await;
+        test_db.cleanup().await;
+
+        assert!(!created_again);
+        assert_eq!(second.id, first.id);
+        assert_eq!(second.name, "Acme");
+        assert_eq!(count, 1);
+        assert!(direct.is_err());
+        assert!(legacy.is_err());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_wallet_address_unique_index_is_created() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = EnterpriseRepository::new(&db);
+        create_enterprise_indexes(&db).await.unwrap();
+        // 再次调用 (重启) 不报错
+        create_enterprise_indexes(&db).await.unwrap();
+
+        let indexes: Vec<IndexModel> = db.collection::<Enterprise>("enterprises").list_indexes().await.unwrap().try_collect().await.unwrap();
+        test_db.cleanup().await;
+        let index = indexes.iter()
+            .find(|index| index.options.as_ref().and_then(|o| o.name.as_deref()) == Some(ENTERPRISE_WALLET_INDEX))
+            .expect("wallet_address index missing");
+        assert_eq!(index.keys, doc! { "wallet_address": 1 });
+        assert_eq!(index.options.as_ref().unwrap().unique, Some(true));
+        assert!(index.options.as_ref().unwrap().collation.is_some());
+    }
+}
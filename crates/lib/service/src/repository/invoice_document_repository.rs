@@ -0,0 +1,37 @@
+use mongodb::{
+    Collection, Database,
+    bson::{doc, oid::ObjectId},
+    results::DeleteResult,
+};
+
+use common::domain::entity::InvoiceDocument;
+
+pub struct InvoiceDocumentRepository {
+    collection: Collection<InvoiceDocument>,
+}
+
+impl InvoiceDocumentRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<InvoiceDocument>("invoice_documents"),
+        }
+    }
+
+    // Create a document record
+    pub async fn create(&self, document: &InvoiceDocument) -> Result<InvoiceDocument, mongodb::error::Error> {
+        let result = self.collection.insert_one(document).await?;
+        let mut created = document.clone();
+        created.id = result.inserted_id.as_object_id();
+        Ok(created)
+    }
+
+    // Find a document belonging to the given invoice
+    pub async fn find_by_id_and_invoice(&self, id: ObjectId, invoice_id: ObjectId) -> Result<Option<InvoiceDocument>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "_id": id, "invoice_id": invoice_id }).await
+    }
+
+    // Delete document record by ID
+    pub async fn delete(&self, id: ObjectId) -> Result<DeleteResult, mongodb::error::Error> {
+        self.collection.delete_one(doc! { "_id": id }).await
+    }
+}
@@ -1,23 +1,130 @@
+use crate::db::finish_transaction;
 use crate::error::ServiceError;
+use crate::invoice::settlement_executor::SettlementStore;
+use crate::repository::AuditLogRepository;
+use async_trait::async_trait;
 use futures::stream::TryStreamExt;
 use mongodb::{
     ClientSession, Collection, Database,
-    bson::{self, DateTime, Decimal128, doc, oid::ObjectId},
-    options::{FindOneOptions, FindOptions, UpdateOptions},
+    bson::{self, DateTime, Decimal128, Document, doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, FindOneOptions, FindOptions, Hint, ReturnDocument, UpdateOptions},
     results::{DeleteResult, UpdateResult},
 };
 use serde::Serialize;
 
-use common::domain::entity::Invoice;
+use common::domain::entity::{AcceptedTerms, AuditLog, Enterprise, Invoice, RepaymentPayout};
 
 use chrono;
 use common::domain::dto::invoice_dto::{CreateInvoiceDto};
+use common::domain::entity::audit_log::INVOICE_TARGET;
 use common::domain::entity::invoice_status::InvoiceStatus;
+use common::pagination::Pagination;
 
 
 
 pub struct InvoiceRepository {
     collection: Collection<Invoice>,
+    enterprise_collection: Collection<Enterprise>,
+    audit_repo: AuditLogRepository,
+    payout_collection: Collection<RepaymentPayout>,
+}
+
+/// 票据搜索条件，所有条件均为可选，全部为空时返回全部票据 (分页)
+#[derive(Debug, Clone, Default)]
+pub struct InvoiceFilter {
+    /// 出票企业 (按企业钱包地址匹配票据 payee)
+    pub enterprise_id: Option<ObjectId>,
+    pub status: Option<InvoiceStatus>,
+    /// 金额下限 (含)
+    pub min_amount: Option<u64>,
+    /// 金额上限 (含)
+    pub max_amount: Option<u64>,
+    /// 创建时间区间 [start, end)
+    pub created_between: Option<(DateTime, DateTime)>,
+    /// 是否包含已软删除的票据 (仅管理员可用)
+    pub include_deleted: bool,
+}
+
+/// 按 payee 搜索时使用的复合索引
+pub const INVOICE_PAYEE_STATUS_INDEX: &str = "payee_1_status_1_created_at_-1";
+/// 仅按状态搜索时使用的复合索引
+pub const INVOICE_STATUS_INDEX: &str = "status_1_created_at_-1";
+
+impl InvoiceFilter {
+    /// 生成查询条件，`payees` 为企业钱包地址 (已解析 enterprise_id 时传入)
+    pub fn to_document(&self, payees: Option<&[String]>) -> Result<Document, mongodb::error::Error> {
+        let mut filter = doc! {};
+        if let Some(payees) = payees {
+            filter.insert("payee", doc! { "$in": payees.to_vec() });
+        }
+        if let Some(status) = &self.status {
+            let status = bson::to_bson(status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+            filter.insert("status", status);
+        }
+        let mut amount = doc! {};
+        if let Some(min) = self.min_amount {
+            amount.insert("$gte", min as i64);
+        }
+        if let Some(max) = self.max_amount {
+            amount.insert("$lte", max as i64);
+        }
+        if !amount.is_empty() {
+            filter.insert("amount", amount);
+        }
+        if let Some((start, end)) = self.created_between {
+            filter.insert("created_at", doc! { "$gte": start, "$lt": end });
+        }
+        exclude_deleted(&mut filter, self.include_deleted);
+        Ok(filter)
+    }
+
+    /// 选择与查询条件前缀匹配的复合索引，没有合适索引时交给查询优化器
+    pub fn index_hint(&self, has_payee: bool) -> Option<Hint> {
+        if has_payee {
+            Some(Hint::Name(INVOICE_PAYEE_STATUS_INDEX.to_string()))
+        } else if self.status.is_some() {
+            Some(Hint::Name(INVOICE_STATUS_INDEX.to_string()))
+        } else {
+            None
+        }
+    }
+}
+
+/// 默认查询排除已软删除的票据；`deleted_at` 缺失的旧文档视为未删除
+pub fn exclude_deleted(filter: &mut Document, include_deleted: bool) {
+    if !include_deleted {
+        filter.insert("deleted_at", bson::Bson::Null);
+    }
+}
+
+/// 条件状态变更，已软删除的票据不会被匹配
+fn transition_update(id: ObjectId, from: InvoiceStatus, to: InvoiceStatus) -> Result<(Document, Document), mongodb::error::Error> {
+    let from = bson::to_bson(&from).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+    let to = bson::to_bson(&to).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+    Ok((doc! { "_id": id, "status": from, "deleted_at": bson::Bson::Null }, doc! { "$set": { "status": to, "updated_at": DateTime::now() }, "$inc": { "version": 1_i64 } }))
+}
+
+/// 乐观锁更新：仅当版本号仍为 `expected_version` 时写入并加 1。
+/// 旧文档没有 version 字段，期望版本为 0 时同时匹配缺失字段
+fn versioned_update(id: ObjectId, expected_version: i64, data: &UpdateInvoiceData) -> Result<(Document, Document), mongodb::error::Error> {
+    let mut set = bson::to_document(data).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize update data: {}", e)))?;
+    set.insert("updated_at", DateTime::now());
+    let version = if expected_version == 0 {
+        bson::Bson::Document(doc! { "$in": [0_i64, bson::Bson::Null] })
+    } else {
+        bson::Bson::Int64(expected_version)
+    };
+    Ok((doc! { "_id": id, "version": version }, doc! { "$set": set, "$inc": { "version": 1_i64 } }))
+}
+
+/// 票据 payee 可能以校验和或小写形式保存，两种都匹配
+fn payee_variants(wallet_address: &str) -> Vec<String> {
+    let lower = wallet_address.to_lowercase();
+    if lower == wallet_address {
+        vec![lower]
+    } else {
+        vec![wallet_address.to_string(), lower]
+    }
 }
 
 /// Fields that can be updated for an Invoice.
@@ -30,11 +137,11 @@ pub struct UpdateInvoiceData {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub currency: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub due_date: Option<DateTime>,
+    pub due_date: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub status: Option<InvoiceStatus>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub ipfs_hash: Option<String>,
+    pub invoice_ipfs_hash: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub batch_id: Option<ObjectId>,
     // Blockchain related fields are generally not updated directly via this struct
@@ -53,28 +160,73 @@ impl InvoiceRepository {
     pub fn new(db: &Database) -> Self {
         Self {
             collection: db.collection::<Invoice>("invoices"),
+            enterprise_collection: db.collection::<Enterprise>("enterprises"),
+            audit_repo: AuditLogRepository::new(db),
+            payout_collection: db.collection::<RepaymentPayout>("repayment_payouts"),
         }
     }
 
+    /// 按条件搜索票据，按创建时间倒序，返回 (当前页, 总数)
+    pub async fn query(&self, filter: &InvoiceFilter, skip: u64, limit: i64) -> Result<(Vec<Invoice>, u64), mongodb::error::Error> {
+        let payees = match filter.enterprise_id {
+            Some(enterprise_id) => match self.enterprise_collection.find_one(doc! { "_id": enterprise_id }).await? {
+                Some(enterprise) => Some(payee_variants(&enterprise.wallet_address)),
+                None => return Ok((Vec::new(), 0)),
+            },
+            None => None,
+        };
+        let query = filter.to_document(payees.as_deref())?;
+        let hint = filter.index_hint(payees.is_some());
+
+        let mut count = self.collection.count_documents(query.clone());
+        let mut find = self.collection.find(query).sort(doc! { "created_at": -1 }).skip(skip).limit(limit);
+        if let Some(hint) = hint {
+            count = count.hint(hint.clone());
+            find = find.hint(hint);
+        }
+        let total = count.await?;
+        Ok((find.await?.try_collect().await?, total))
+    }
+
     // Find invoice by ID
     pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<Invoice>, mongodb::error::Error> {
         let filter = doc! { "_id": id };
         self.collection.find_one(filter).await
     }
 
+    // Find invoices by a set of IDs
+    pub async fn find_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<Invoice>, mongodb::error::Error> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let cursor = self.collection.find(doc! { "_id": { "$in": ids.to_vec() } }).await?;
+        cursor.try_collect().await
+    }
+
     // Find all invoices (consider adding pagination/filtering later)
     pub async fn find_all(&self) -> Result<Vec<Invoice>, mongodb::error::Error> {
-        let filter = doc! {};
+        let filter = doc! { "deleted_at": bson::Bson::Null };
         let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
         let mut cursor = self.collection.find(filter).with_options(find_options).await?;
         let mut results = Vec::new();
         while let Some(result) = cursor.try_next().await? {
             results.push(result);
         }
-        log::warn!("Finding all items:{:?}", results.clone());
+        tracing::warn!("Finding all items:{:?}", results.clone());
         Ok(results)
     }
 
+    /// 符合条件的票据数
+    pub async fn count(&self, filter: bson::Document) -> Result<u64, mongodb::error::Error> {
+        self.collection.count_documents(filter).await
+    }
+
+    // Find a page of invoices ordered by _id descending (newest first)
+    pub async fn find_page(&self, filter: bson::Document, pagination: &Pagination) -> Result<Vec<Invoice>, mongodb::error::Error> {
+        let cursor = self.collection.find(filter).with_options(pagination.find_options()).await?;
+        cursor.try_collect().await
+    }
+
     // Find invoice by invoice_number
     pub async fn find_by_invoice_number(&self, invoice_number: &str) -> Result<Option<Invoice>, mongodb::error::Error> {
         let filter = doc! { "invoice_number": invoice_number };
@@ -89,37 +241,48 @@ impl InvoiceRepository {
             .find_one(filter)
             .session(session)
             .await
-            .map_err(|e| ServiceError::MongoDbError(e.to_string()))
+            .map_err(ServiceError::from)
     }
 
-    // Find invoices by user_address
+    // Find invoices by user_address (地址不区分大小写)
     pub async fn find_by_user(&self, user_address: &str) -> Result<Vec<Invoice>, mongodb::error::Error> {
-        let filter = doc! { "payee": user_address };
-        let find_options = FindOptions::builder().sort(doc! { "created_at": -1 }).build();
+        let payee = bson::Regex { pattern: format!("^{}$", regex::escape(user_address)), options: "i".to_string() };
+        let filter = doc! { "payee": payee, "deleted_at": bson::Bson::Null };
 
-        let cursor = self.collection.find(filter).await?;
+        let cursor = self.collection.find(filter).sort(doc! { "created_at": -1 }).await?;
         let invoices = cursor.try_collect().await?;
 
         Ok(invoices)
     }
 
     // Create new invoice from data provided by frontend/API (not directly from blockchain event)
-    pub async fn create_from_blockchain(&self, data: &CreateInvoiceDto) -> Result<Invoice, ServiceError> {
+    // 新建记录与票据在同一事务内写入，`actor` 为创建人 (从链上同步时为 "system")
+    pub async fn create_from_blockchain(&self, data: &CreateInvoiceDto, actor: &str) -> Result<Invoice, ServiceError> {
         // Create a new invoice instance
         let mut invoice = Invoice::new(data);
 
         // Default status for new invoices created via API
         invoice.status = InvoiceStatus::Pending;
 
-        // Insert the invoice and get its ID
-        let result = self
-            .collection
-            .insert_one(&invoice)
-            .await
-            .map_err(|e| ServiceError::MongoDbError(format!("Failed to insert invoice: {}", e)))?;
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            // Insert the invoice and get its ID
+            let result = self
+                .collection
+                .insert_one(&invoice)
+                .session(&mut session)
+                .await
+                .map_err(|e| ServiceError::MongoDbError(format!("Failed to insert invoice: {}", e)))?;
+            let id = result.inserted_id.as_object_id()
+                .ok_or_else(|| ServiceError::MongoDbError("Inserted invoice has no ObjectId".to_string()))?;
+            self.append_audit_session(&AuditLog::invoice(id, actor, "create", None, invoice.status), &mut session).await?;
+            Ok(id)
+        }
+        .await;
+        let id = finish_transaction(session, result).await?;
 
         let mut created_invoice = invoice;
-        created_invoice.id = result.inserted_id.as_object_id();
+        created_invoice.id = Some(id);
 
         Ok(created_invoice)
     }
@@ -133,25 +296,53 @@ impl InvoiceRepository {
         Ok(created_invoice)
     }
 
-    // Generic Update invoice data
-    pub async fn update(&self, id: ObjectId, data: UpdateInvoiceData) -> Result<UpdateResult, mongodb::error::Error> {
-        let filter = doc! { "_id": id };
-
-        // Convert the UpdateInvoiceData struct to a BSON document
-        // This automatically skips None values due to `skip_serializing_if`
-        let mut update_doc = bson::to_document(&data).map_err(|e| {
-            // Provide more context for serialization errors
-            mongodb::error::Error::custom(format!("Failed to serialize update data: {}", e))
-        })?;
-
-        // Always update the 'updated_at' timestamp
-        update_doc.insert("updated_at", DateTime::now());
-
-        // Create the $set update operation
-        let update = doc! { "$set": update_doc };
+    /// 按版本号更新票据，并在同一事务内记录修改人与修改的字段，返回更新后的票据。
+    /// 票据不存在时返回 `InvoiceNotFound`，版本号已变化 (被并发修改) 时返回 `StaleWrite`
+    pub async fn update(&self, id: ObjectId, expected_version: i64, data: UpdateInvoiceData, actor: &str) -> Result<Invoice, ServiceError> {
+        let (filter, update) = versioned_update(id, expected_version, &data)?;
+        let changed_fields: Vec<String> = update.get_document("$set")
+            .map(|set| set.keys().filter(|key| *key != "updated_at").cloned().collect())
+            .unwrap_or_default();
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            let updated = self.collection.find_one_and_update(filter, update).with_options(options).session(&mut session).await?;
+            if let Some(invoice) = &updated {
+                let entry = AuditLog::invoice(id, actor, "update", Some(invoice.status), invoice.status).with_detail("changed_fields", changed_fields);
+                self.append_audit_session(&entry, &mut session).await?;
+            }
+            Ok(updated)
+        }
+        .await;
+        match finish_transaction(session, result).await? {
+            Some(invoice) => Ok(invoice),
+            None => match self.find_by_id(id).await? {
+                Some(current) => Err(ServiceError::StaleWrite { expected: expected_version, actual: current.version }),
+                None => Err(ServiceError::InvoiceNotFound(id.to_hex())),
+            },
+        }
+    }
 
-        // Perform the update operation
-        self.collection.update_one(filter, update).await
+    // 软删除：写入 deleted_at 并在同一事务内记录审计。只删除尚未认购的 Pending 票据，已删除或状态已变化时返回 None
+    pub async fn soft_delete(&self, id: ObjectId, actor: &str) -> Result<Option<Invoice>, ServiceError> {
+        let pending = bson::to_bson(&InvoiceStatus::Pending).map_err(|e| ServiceError::SerializationError(e.to_string()))?;
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            let now = DateTime::now();
+            // funded_shares 缺失的旧文档视为 0
+            let filter = doc! { "_id": id, "deleted_at": bson::Bson::Null, "status": pending, "funded_shares": { "$in": [0_i64, bson::Bson::Null] } };
+            let update = doc! { "$set": { "deleted_at": now, "updated_at": now }, "$inc": { "version": 1_i64 } };
+            let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+            let deleted = self.collection.find_one_and_update(filter, update).with_options(options).session(&mut session).await?;
+            if let Some(invoice) = &deleted {
+                let entry = AuditLog::invoice(id, actor, "delete", Some(invoice.status), invoice.status);
+                self.append_audit_session(&entry, &mut session).await?;
+            }
+            Ok(deleted)
+        }
+        .await;
+        finish_transaction(session, result).await
     }
 
     // Delete invoice by ID
@@ -188,11 +379,76 @@ impl InvoiceRepository {
         let filter = doc! { "_id": id };
         // Ensure status is serialized correctly to BSON
         let status_bson = bson::to_bson(&status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
-        let update = doc! { "$set": { "status": status_bson, "updated_at": now } };
+        let update = doc! { "$set": { "status": status_bson, "updated_at": now }, "$inc": { "version": 1_i64 } };
+
+        self.collection.update_one(filter, update).await
+    }
 
+    // 条件更新状态：仅当当前状态仍为 `from` 时写入 `to`，并发变更时 modified_count 为 0
+    pub async fn transition_status(&self, id: ObjectId, from: InvoiceStatus, to: InvoiceStatus) -> Result<UpdateResult, mongodb::error::Error> {
+        let (filter, update) = transition_update(id, from, to)?;
         self.collection.update_one(filter, update).await
     }
 
+    /// 事务内累加已认购份数，累加后超过 `total_shares` 时不更新并返回 None，否则返回累加后的份数
+    pub async fn add_funded_shares_session(&self, id: ObjectId, shares: u64, total_shares: u64, session: &mut ClientSession) -> Result<Option<u64>, ServiceError> {
+        let Some(max_before) = total_shares.checked_sub(shares) else {
+            return Ok(None);
+        };
+        // 旧文档没有 funded_shares 字段，$not + $gt 同时匹配缺失字段
+        let filter = doc! { "_id": id, "funded_shares": { "$not": { "$gt": max_before as i64 } } };
+        let update = doc! { "$inc": { "funded_shares": shares as i64, "version": 1_i64 }, "$set": { "updated_at": DateTime::now() } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        let updated = self.collection.find_one_and_update(filter, update).with_options(options).session(session).await?;
+        Ok(updated.map(|invoice| invoice.funded_shares))
+    }
+
+    // 事务内条件更新状态
+    pub async fn transition_status_session(&self, id: ObjectId, from: InvoiceStatus, to: InvoiceStatus, session: &mut ClientSession) -> Result<UpdateResult, ServiceError> {
+        let (filter, update) = transition_update(id, from, to)?;
+        Ok(self.collection.update_one(filter, update).session(session).await?)
+    }
+
+    async fn start_transaction(&self) -> Result<ClientSession, ServiceError> {
+        let mut session = self.collection.client().start_session().await?;
+        session.start_transaction().await?;
+        Ok(session)
+    }
+
+    // 条件更新状态并在同一事务内写入审计记录，未发生变更时不写审计
+    pub async fn transition_status_audited(&self, id: ObjectId, from: InvoiceStatus, to: InvoiceStatus, actor: &str, action: &str) -> Result<UpdateResult, ServiceError> {
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            let result = self.transition_status_session(id, from, to, &mut session).await?;
+            if result.modified_count > 0 {
+                self.append_audit_session(&AuditLog::invoice(id, actor, action, Some(from), to), &mut session).await?;
+            }
+            Ok(result)
+        }
+        .await;
+        finish_transaction(session, result).await
+    }
+
+    // 作废票据并在同一事务内写入带原因的审计记录，状态已被并发修改时不写审计
+    // 追加审计记录，调用方负责事务
+    pub async fn append_audit_session(&self, entry: &AuditLog, session: &mut ClientSession) -> Result<(), ServiceError> {
+        self.audit_repo.create_session(entry, session).await?;
+        Ok(())
+    }
+
+    // 票据的审计记录，按时间先后排列
+    pub async fn find_audit_trail(&self, invoice_id: ObjectId) -> Result<Vec<AuditLog>, mongodb::error::Error> {
+        self.audit_repo.find_by_target(INVOICE_TARGET, &invoice_id.to_hex()).await
+    }
+
+    // Find invoices in the given statuses that have no settlement hash yet
+    pub async fn find_unsettled_by_statuses(&self, statuses: &[InvoiceStatus]) -> Result<Vec<Invoice>, mongodb::error::Error> {
+        let statuses = bson::to_bson(statuses).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+        let filter = doc! { "status": { "$in": statuses }, "settlement_tx_hash": bson::Bson::Null, "deleted_at": bson::Bson::Null };
+        let cursor = self.collection.find(filter).sort(doc! { "due_date": 1 }).await?;
+        cursor.try_collect().await
+    }
+
     // Add invoice to batch
     pub async fn add_to_batch(&self, id: ObjectId, batch_id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
         let now = DateTime::now();
@@ -210,6 +466,13 @@ impl InvoiceRepository {
         self.collection.update_one(filter, update).await
     }
 
+    // 事务内将票据加入批次并标记为 Packaged
+    pub async fn add_to_batch_session(&self, id: ObjectId, batch_id: ObjectId, session: &mut ClientSession) -> Result<UpdateResult, ServiceError> {
+        let status_bson = bson::to_bson(&InvoiceStatus::Packaged).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+        let update = doc! { "$set": { "batch_id": batch_id, "status": status_bson, "updated_at": DateTime::now() } };
+        Ok(self.collection.update_one(doc! { "_id": id }, update).session(session).await?)
+    }
+
     // 查找属于特定批次的所有发票
     pub async fn find_by_batch_id(&self, batch_id: ObjectId) -> Result<Vec<Invoice>, mongodb::error::Error> {
         let filter = doc! { "batch_id": batch_id };
@@ -217,4 +480,285 @@ impl InvoiceRepository {
         let cursor = self.collection.find(filter).with_options(find_options).await?;
         cursor.try_collect().await
     }
+
+    // 占用附件配额：仅当数量和累计大小都未超限时才原子地递增
+    pub async fn reserve_document_quota(&self, id: ObjectId, size: u64, max_count: u32, max_bytes: u64) -> Result<bool, mongodb::error::Error> {
+        if size > max_bytes {
+            return Ok(false);
+        }
+        let filter = doc! {
+            "_id": id,
+            "$and": [
+                { "$or": [ { "document_count": { "$exists": false } }, { "document_count": { "$lt": max_count as i64 } } ] },
+                { "$or": [ { "document_total_bytes": { "$exists": false } }, { "document_total_bytes": { "$lte": (max_bytes - size) as i64 } } ] },
+            ]
+        };
+        let update = doc! {
+            "$inc": { "document_count": 1_i64, "document_total_bytes": size as i64 },
+            "$set": { "updated_at": DateTime::now() }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count == 1)
+    }
+
+    // 释放附件配额 (删除附件或上传失败时调用)
+    pub async fn release_document_quota(&self, id: ObjectId, size: u64) -> Result<UpdateResult, mongodb::error::Error> {
+        let filter = doc! { "_id": id, "document_count": { "$gt": 0 } };
+        let update = doc! {
+            "$inc": { "document_count": -1_i64, "document_total_bytes": -(size as i64) },
+            "$set": { "updated_at": DateTime::now() }
+        };
+        self.collection.update_one(filter, update).await
+    }
+
+    // 记录已接受的融资条款，只允许已上链且尚未接受过条款的票据
+    pub async fn accept_terms(&self, id: ObjectId, terms: &AcceptedTerms) -> Result<UpdateResult, mongodb::error::Error> {
+        let verified = bson::to_bson(&InvoiceStatus::Verified).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+        let terms_bson = bson::to_bson(terms).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize terms: {}", e)))?;
+        let filter = doc! { "_id": id, "status": verified, "accepted_terms": bson::Bson::Null };
+        let update = doc! { "$set": { "accepted_terms": terms_bson, "updated_at": DateTime::now() }, "$inc": { "version": 1_i64 } };
+        self.collection.update_one(filter, update).await
+    }
+
+    // 接受融资条款并写入审计记录 (状态不变)
+    pub async fn accept_terms_audited(&self, id: ObjectId, terms: &AcceptedTerms) -> Result<UpdateResult, ServiceError> {
+        let verified = bson::to_bson(&InvoiceStatus::Verified).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+        let terms_bson = bson::to_bson(terms).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize terms: {}", e)))?;
+        let filter = doc! { "_id": id, "status": verified, "accepted_terms": bson::Bson::Null };
+        let update = doc! { "$set": { "accepted_terms": terms_bson, "updated_at": DateTime::now() }, "$inc": { "version": 1_i64 } };
+
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            let result = self.collection.update_one(filter, update).session(&mut session).await?;
+            if result.modified_count > 0 {
+                let entry = AuditLog::invoice(id, &terms.accepted_by, "accept_terms", Some(InvoiceStatus::Verified), InvoiceStatus::Verified)
+                    .with_reason(format!("apr={}, platform_fee_rate={}", terms.apr, terms.platform_fee_rate));
+                self.append_audit_session(&entry, &mut session).await?;
+            }
+            Ok(result)
+        }
+        .await;
+        finish_transaction(session, result).await
+    }
+
+    // 记录结算交易哈希，已有哈希时不覆盖
+    pub async fn set_settlement_tx_hash(&self, id: ObjectId, tx_hash: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let repaid = bson::to_bson(&InvoiceStatus::Repaid).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+        let filter = doc! { "_id": id, "settlement_tx_hash": bson::Bson::Null };
+        let update = doc! { "$set": { "status": repaid, "settlement_tx_hash": tx_hash, "updated_at": DateTime::now() }, "$inc": { "version": 1_i64 } };
+        self.collection.update_one(filter, update).await
+    }
+}
+
+#[async_trait]
+impl SettlementStore for InvoiceRepository {
+    async fn settled_tx_hash(&self, invoice_id: &str) -> Result<Option<String>, ServiceError> {
+        let id = ObjectId::parse_str(invoice_id).map_err(|_| ServiceError::InvoiceNotFound(invoice_id.to_string()))?;
+        let invoice = self.find_by_id(id).await?.ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_string()))?;
+        Ok(invoice.settlement_tx_hash)
+    }
+
+    async fn mark_settled(&self, invoice_id: &str, tx_hash: &str, actor: &str, payouts: &[RepaymentPayout]) -> Result<(), ServiceError> {
+        let id = ObjectId::parse_str(invoice_id).map_err(|_| ServiceError::InvoiceNotFound(invoice_id.to_string()))?;
+        let repaid = bson::to_bson(&InvoiceStatus::Repaid).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))?;
+
+        let mut session = self.start_transaction().await?;
+        let result = async {
+            let invoice = self.collection.find_one(doc! { "_id": id }).session(&mut session).await?
+                .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_string()))?;
+            let filter = doc! { "_id": id, "settlement_tx_hash": bson::Bson::Null };
+            let update = doc! { "$set": { "status": repaid, "settlement_tx_hash": tx_hash, "updated_at": DateTime::now() }, "$inc": { "version": 1_i64 } };
+            let result = self.collection.update_one(filter, update).session(&mut session).await?;
+            if result.matched_count == 0 {
+                return Err(ServiceError::InternalError(format!("Invoice {} already has a settlement tx hash", invoice_id)));
+            }
+            if !payouts.is_empty() {
+                self.payout_collection.insert_many(payouts).session(&mut session).await?;
+            }
+            let entry = AuditLog::invoice(id, actor, "settle", Some(invoice.status), InvoiceStatus::Repaid).with_reason(format!("tx {}", tx_hash));
+            self.append_audit_session(&entry, &mut session).await
+        }
+        .await;
+        finish_transaction(session, result).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestDb;
+
+    fn filter_doc(filter: &InvoiceFilter) -> Document {
+        filter.to_document(None).unwrap()
+    }
+
+    #[test]
+    fn test_versioned_update_matches_expected_version() {
+        let id = ObjectId::new();
+        let data = UpdateInvoiceData { amount: Some(2_000), ..Default::default() };
+        let (filter, update) = versioned_update(id, 3, &data).unwrap();
+        assert_eq!(filter, doc! { "_id": id, "version": 3_i64 });
+        assert_eq!(update.get_document("$inc").unwrap(), &doc! { "version": 1_i64 });
+        let set = update.get_document("$set").unwrap();
+        assert_eq!(set.get_i64("amount").unwrap(), 2_000);
+        assert!(set.contains_key("updated_at"));
+    }
+
+    #[test]
+    fn test_versioned_update_rejects_stale_version() {
+        // 另一个请求已把版本号从 0 更新到 1，带旧版本 0 的写入不会匹配
+        let id = ObjectId::new();
+        let (filter, _) = versioned_update(id, 0, &UpdateInvoiceData::default()).unwrap();
+        let version = filter.get_document("version").unwrap().get_array("$in").unwrap();
+        assert!(version.contains(&bson::Bson::Int64(0)) && version.contains(&bson::Bson::Null));
+        assert!(!version.contains(&bson::Bson::Int64(1)));
+
+        let err = ServiceError::StaleWrite { expected: 0, actual: 1 };
+        assert_eq!(err.to_string(), "Stale write: expected version 0, current version 1");
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_update_with_stale_version_conflicts() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let invoice = repo.create(&CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        }).await.unwrap();
+        let id = invoice.id.unwrap();
+
+        // 两个请求读到同一版本，先提交的生效，后提交的因版本号已变化被拒绝
+        let first = repo.update(id, invoice.version, UpdateInvoiceData { amount: Some(200), ..Default::default() }, "0xPayee").await;
+        let second = repo.update(id, invoice.version, UpdateInvoiceData { amount: Some(300), ..Default::default() }, "0xpayee").await;
+        let missing = repo.update(ObjectId::new(), 0, UpdateInvoiceData::default(), "0xpayee").await;
+        let current = repo.find_by_id(id).await.unwrap().unwrap();
+        let trail = repo.find_audit_trail(id).await.unwrap();
+        test_db.cleanup().await;
+
+        let first = first.unwrap();
+        assert_eq!(first.version, invoice.version + 1);
+        assert!(matches!(second, Err(ServiceError::StaleWrite { expected, actual }) if expected == invoice.version && actual == first.version));
+        assert!(matches!(missing, Err(ServiceError::InvoiceNotFound(_))));
+        assert_eq!(current.amount, 200);
+        assert_eq!(current.version, first.version);
+        // 被拒绝的写入不留下记录
+        let updates: Vec<_> = trail.iter().filter(|entry| entry.action == "update").collect();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].actor, "0xpayee");
+        assert_eq!(updates[0].details.as_ref().unwrap().get_array("changed_fields").unwrap(), &vec![bson::Bson::from("amount")]);
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_create_update_and_delete_are_audited() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = InvoiceRepository::new(&db);
+        let data = CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        };
+        let invoice = repo.create_from_blockchain(&data, "0xpayee").await.unwrap();
+        let id = invoice.id.unwrap();
+        let edit = UpdateInvoiceData { currency: Some("USDT".to_string()), due_date: Some(1), ..Default::default() };
+        repo.update(id, invoice.version, edit, "0xadmin").await.unwrap();
+        repo.soft_delete(id, "0xadmin").await.unwrap().unwrap();
+        let trail = repo.find_audit_trail(id).await.unwrap();
+        test_db.cleanup().await;
+
+        let entries: Vec<_> = trail.iter().map(|entry| (entry.action.as_str(), entry.actor.as_str())).collect();
+        assert_eq!(entries, vec![("create", "0xpayee"), ("update", "0xadmin"), ("delete", "0xadmin")]);
+        assert!(trail.iter().all(|entry| entry.target_type == INVOICE_TARGET && entry.target_id == id.to_hex()));
+        let created = common::domain::entity::InvoiceAuditDto::from(&trail[0]);
+        assert_eq!((created.previous_status, created.next_status), (None, Some(InvoiceStatus::Pending)));
+        let updated = common::domain::entity::InvoiceAuditDto::from(&trail[1]);
+        assert_eq!(updated.changed_fields, Some(vec!["currency".to_string(), "due_date".to_string()]));
+        assert_eq!((updated.previous_status, updated.next_status), (Some(InvoiceStatus::Pending), Some(InvoiceStatus::Pending)));
+    }
+
+    #[test]
+    fn test_empty_filter_matches_all() {
+        let filter = InvoiceFilter::default();
+        assert_eq!(filter_doc(&filter), doc! { "deleted_at": bson::Bson::Null });
+        assert!(filter.index_hint(false).is_none());
+    }
+
+    #[test]
+    fn test_include_deleted_filter() {
+        let filter = InvoiceFilter { status: Some(InvoiceStatus::OnSale), include_deleted: true, ..Default::default() };
+        assert_eq!(filter_doc(&filter), doc! { "status": "ON_SALE" });
+
+        let mut query = doc! { "payee": "0xabc" };
+        exclude_deleted(&mut query, false);
+        assert_eq!(query, doc! { "payee": "0xabc", "deleted_at": bson::Bson::Null });
+    }
+
+    #[test]
+    fn test_enterprise_filter() {
+        let filter = InvoiceFilter { enterprise_id: Some(ObjectId::new()), ..Default::default() };
+        let payees = payee_variants("0xAbC");
+        assert_eq!(filter.to_document(Some(&payees)).unwrap(), doc! { "payee": { "$in": ["0xAbC", "0xabc"] }, "deleted_at": bson::Bson::Null });
+        assert_eq!(payee_variants("0xabc"), vec!["0xabc".to_string()]);
+        assert!(matches!(filter.index_hint(true), Some(Hint::Name(name)) if name == INVOICE_PAYEE_STATUS_INDEX));
+    }
+
+    #[test]
+    fn test_status_filter() {
+        let filter = InvoiceFilter { status: Some(InvoiceStatus::OnSale), ..Default::default() };
+        assert_eq!(filter_doc(&filter), doc! { "status": "ON_SALE", "deleted_at": bson::Bson::Null });
+        assert!(matches!(filter.index_hint(false), Some(Hint::Name(name)) if name == INVOICE_STATUS_INDEX));
+    }
+
+    #[test]
+    fn test_amount_filter() {
+        let min_only = InvoiceFilter { min_amount: Some(100), ..Default::default() };
+        assert_eq!(filter_doc(&min_only), doc! { "amount": { "$gte": 100_i64 }, "deleted_at": bson::Bson::Null });
+        let max_only = InvoiceFilter { max_amount: Some(500), ..Default::default() };
+        assert_eq!(filter_doc(&max_only), doc! { "amount": { "$lte": 500_i64 }, "deleted_at": bson::Bson::Null });
+    }
+
+    #[test]
+    fn test_created_between_is_half_open() {
+        let start = DateTime::from_millis(1_700_000_000_000);
+        let end = DateTime::from_millis(1_700_086_400_000);
+        let filter = InvoiceFilter { created_between: Some((start, end)), ..Default::default() };
+        assert_eq!(filter_doc(&filter), doc! { "created_at": { "$gte": start, "$lt": end }, "deleted_at": bson::Bson::Null });
+    }
+
+    #[test]
+    fn test_combined_filter() {
+        let start = DateTime::from_millis(1_700_000_000_000);
+        let end = DateTime::from_millis(1_700_086_400_000);
+        let filter = InvoiceFilter {
+            enterprise_id: Some(ObjectId::new()),
+            status: Some(InvoiceStatus::Verified),
+            min_amount: Some(100),
+            max_amount: Some(500),
+            created_between: Some((start, end)),
+            include_deleted: false,
+        };
+        let payees = vec!["0xabc".to_string()];
+        assert_eq!(
+            filter.to_document(Some(&payees)).unwrap(),
+            doc! {
+                "payee": { "$in": ["0xabc"] },
+                "status": "VERIFIED",
+                "amount": { "$gte": 100_i64, "$lte": 500_i64 },
+                "created_at": { "$gte": start, "$lt": end },
+                "deleted_at": bson::Bson::Null,
+            }
+        );
+    }
 }
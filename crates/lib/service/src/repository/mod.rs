@@ -6,6 +6,15 @@ pub mod daily_interest_accrual_repository;
 pub mod transaction_repository;
 pub mod token_repository;
 pub mod invoice_batch_repository;
+pub mod invoice_document_repository;
+pub mod webhook_delivery_repository;
+pub mod webhook_subscription_repository;
+pub mod audit_log_repository;
+pub mod contract_operation_repository;
+pub mod onchain_transaction_repository;
+pub mod repayment_payout_repository;
+pub mod token_transfer_repository;
+pub mod token_mint_repository;
 
 pub use user_invoice_holding_repository::UserInvoiceHoldingRepository;
 pub use daily_interest_accrual_repository::DailyInterestAccrualRepository;
@@ -15,4 +24,13 @@ pub use user_repository::UserRepository;
 pub use enterprise_repository::EnterpriseRepository;
 pub use invoice_repository::InvoiceRepository;
 pub use token_repository::TokenRepository;
-pub use invoice_batch_repository::InvoiceBatchRepository; 
\ No newline at end of file
+pub use invoice_batch_repository::InvoiceBatchRepository;
+pub use invoice_document_repository::InvoiceDocumentRepository;
+pub use webhook_delivery_repository::WebhookDeliveryRepository;
+pub use webhook_subscription_repository::WebhookSubscriptionRepository;
+pub use audit_log_repository::AuditLogRepository;
+pub use contract_operation_repository::ContractOperationRepository;
+pub use onchain_transaction_repository::OnchainTransactionRepository;
+pub use repayment_payout_repository::RepaymentPayoutRepository;
+pub use token_transfer_repository::TokenTransferRepository;
+pub use token_mint_repository::TokenMintRepository;
@@ -0,0 +1,99 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    Collection, Database,
+    bson::{self, DateTime, Document, doc},
+};
+
+use common::domain::entity::{OnchainTransaction, OnchainTxStatus};
+use common::pagination::Pagination;
+
+pub struct OnchainTransactionRepository {
+    collection: Collection<OnchainTransaction>,
+}
+
+impl OnchainTransactionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<OnchainTransaction>("onchain_transactions"),
+        }
+    }
+
+    /// 登记已广播的交易，状态为 Pending；同一哈希重复登记不会覆盖已有状态。
+    /// 没有回执 (等待回执失败) 时发送方/接收方地址未知
+    pub async fn track_pending(
+        &self,
+        tx_hash: &str,
+        operation: &str,
+        reference: &str,
+        from_address: Option<&str>,
+        to_address: Option<&str>,
+        accounts: &[String],
+    ) -> Result<(), mongodb::error::Error> {
+        let now = DateTime::now();
+        let accounts: Vec<String> = accounts.iter().map(|a| a.to_lowercase()).collect();
+        let update = doc! {
+            "$setOnInsert": {
+                "tx_hash": tx_hash,
+                "operation": operation,
+                "reference": reference,
+                "from_address": from_address.map(str::to_lowercase),
+                "to_address": to_address.map(str::to_lowercase),
+                "accounts": accounts,
+                "status": status_bson(OnchainTxStatus::Pending)?,
+                "block_number": bson::Bson::Null,
+                "confirmations": 0_i64,
+                "created_at": now,
+                "updated_at": now,
+            },
+        };
+        self.collection.update_one(doc! { "tx_hash": tx_hash }, update).upsert(true).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_hash(&self, tx_hash: &str) -> Result<Option<OnchainTransaction>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "tx_hash": tx_hash }).await
+    }
+
+    /// 符合条件的交易数
+    pub async fn count(&self, filter: Document) -> Result<u64, mongodb::error::Error> {
+        self.collection.count_documents(filter).await
+    }
+
+    /// 按 `_id` 倒序 (最新在前) 分页查询
+    pub async fn find_page(&self, filter: Document, pagination: &Pagination) -> Result<Vec<OnchainTransaction>, mongodb::error::Error> {
+        let cursor = self.collection.find(filter).with_options(pagination.find_options()).await?;
+        cursor.try_collect().await
+    }
+
+    /// 待确认交易，最久未检查的优先
+    pub async fn find_pending(&self, limit: i64) -> Result<Vec<OnchainTransaction>, mongodb::error::Error> {
+        let filter = doc! { "status": status_bson(OnchainTxStatus::Pending)? };
+        let cursor = self.collection.find(filter).sort(doc! { "updated_at": 1 }).limit(limit).await?;
+        cursor.try_collect().await
+    }
+
+    /// 写入轮询结果。只更新仍为 Pending 的记录，已确认 / 已回滚的不会被改回
+    pub async fn update_progress(
+        &self,
+        tx_hash: &str,
+        status: OnchainTxStatus,
+        block_number: Option<u64>,
+        confirmations: u64,
+    ) -> Result<(), mongodb::error::Error> {
+        let filter = doc! { "tx_hash": tx_hash, "status": status_bson(OnchainTxStatus::Pending)? };
+        let update = doc! {
+            "$set": {
+                "status": status_bson(status)?,
+                "block_number": block_number.map(|n| n as i64),
+                "confirmations": confirmations as i64,
+                "updated_at": DateTime::now(),
+            },
+        };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+}
+
+fn status_bson(status: OnchainTxStatus) -> Result<bson::Bson, mongodb::error::Error> {
+    bson::to_bson(&status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))
+}
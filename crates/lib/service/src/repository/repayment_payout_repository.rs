@@ -0,0 +1,31 @@
+use futures::stream::TryStreamExt;
+use mongodb::{Collection, Database, bson::{doc, oid::ObjectId}};
+
+use common::domain::entity::RepaymentPayout;
+
+pub struct RepaymentPayoutRepository {
+    collection: Collection<RepaymentPayout>,
+}
+
+impl RepaymentPayoutRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<RepaymentPayout>("repayment_payouts"),
+        }
+    }
+
+    // 记录一次兑付的全部持有人金额
+    pub async fn insert_many(&self, payouts: &[RepaymentPayout]) -> Result<(), mongodb::error::Error> {
+        if payouts.is_empty() {
+            return Ok(());
+        }
+        self.collection.insert_many(payouts).await?;
+        Ok(())
+    }
+
+    // 票据的兑付明细，金额从大到小
+    pub async fn find_by_invoice(&self, invoice_id: ObjectId) -> Result<Vec<RepaymentPayout>, mongodb::error::Error> {
+        let cursor = self.collection.find(doc! { "invoice_id": invoice_id }).sort(doc! { "amount": -1, "user_id": 1 }).await?;
+        cursor.try_collect().await
+    }
+}
@@ -0,0 +1,98 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    ClientSession, Collection, Database,
+    bson::{self, DateTime, doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    results::UpdateResult,
+};
+
+use common::domain::entity::{TokenMint, TokenMintStatus};
+
+pub struct TokenMintRepository {
+    collection: Collection<TokenMint>,
+}
+
+impl TokenMintRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<TokenMint>("token_mints"),
+        }
+    }
+
+    /// 在认购事务内写入待铸造记录
+    pub async fn create_session(&self, mint: &TokenMint, session: &mut ClientSession) -> Result<(), mongodb::error::Error> {
+        self.collection.insert_one(mint).session(session).await?;
+        Ok(())
+    }
+
+    pub async fn find_by_holding(&self, holding_id: &str) -> Result<Option<TokenMint>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "holding_id": holding_id }).await
+    }
+
+    /// 到期待提交的铸造，最早到期的优先
+    pub async fn find_due(&self, max_attempts: u32, limit: i64) -> Result<Vec<TokenMint>, mongodb::error::Error> {
+        let filter = doc! {
+            "status": status_bson(TokenMintStatus::Pending)?,
+            "next_attempt_at": { "$lte": DateTime::now() },
+            "attempts": { "$lt": max_attempts as i64 },
+        };
+        let cursor = self.collection.find(filter).sort(doc! { "next_attempt_at": 1 }).limit(limit).await?;
+        cursor.try_collect().await
+    }
+
+    /// 抢占一条到期的铸造：attempts 加一，并把 next_attempt_at 推迟 lease_ms 作为租约，
+    /// 多个实例不会同时提交同一笔铸造，进程崩溃后租约过期也能被重新抢占
+    pub async fn claim_due(&self, id: ObjectId, max_attempts: u32, lease_ms: i64) -> Result<Option<TokenMint>, mongodb::error::Error> {
+        let now = DateTime::now();
+        let filter = doc! {
+            "_id": id,
+            "status": status_bson(TokenMintStatus::Pending)?,
+            "next_attempt_at": { "$lte": now },
+            "attempts": { "$lt": max_attempts as i64 },
+        };
+        let update = doc! {
+            "$inc": { "attempts": 1 },
+            "$set": {
+                "next_attempt_at": DateTime::from_millis(now.timestamp_millis() + lease_ms),
+                "updated_at": now,
+            }
+        };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.collection.find_one_and_update(filter, update).with_options(options).await
+    }
+
+    pub async fn mark_minted(&self, id: ObjectId, tx_hash: Option<&str>) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "status": status_bson(TokenMintStatus::Minted)?,
+            "tx_hash": tx_hash,
+            "last_error": bson::Bson::Null,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+
+    /// 记录失败并安排下一次提交
+    pub async fn schedule_retry(&self, id: ObjectId, next_attempt_at: DateTime, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "next_attempt_at": next_attempt_at,
+            "last_error": error,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+
+    /// 放弃自动重试，`tx_hash` 为可能已广播的交易
+    pub async fn mark_failed(&self, id: ObjectId, tx_hash: Option<&str>, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "status": status_bson(TokenMintStatus::Failed)?,
+            "tx_hash": tx_hash,
+            "last_error": error,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+}
+
+fn status_bson(status: TokenMintStatus) -> Result<bson::Bson, mongodb::error::Error> {
+    bson::to_bson(&status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))
+}
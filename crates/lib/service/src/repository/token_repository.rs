@@ -5,12 +5,13 @@ use mongodb::{
     Collection, Database,
     options::FindOptions,
 };
+use std::str::FromStr;
 use std::sync::Arc;
-use log::{debug, error, info};
+use tracing::{debug, error, info};
 
 use common::domain::entity::{
     TokenBatch, TokenBatchStatus, TokenMarket, TokenHolding, TokenHoldingStatus,
-    TokenTransaction, TokenTransactionType, TokenTransactionStatus,
+    TokenTransaction, TokenTransactionType, TokenTransactionStatus, OnchainTokenBalance,
 };
 
 use crate::error::ServiceError;
@@ -21,6 +22,7 @@ pub struct TokenRepository {
     token_market_collection: Collection<Document>,
     token_holding_collection: Collection<Document>,
     token_transaction_collection: Collection<Document>,
+    onchain_balance_collection: Collection<Document>,
 }
 
 impl TokenRepository {
@@ -30,6 +32,7 @@ impl TokenRepository {
             token_market_collection: db.collection("token_markets"),
             token_holding_collection: db.collection("token_holdings"),
             token_transaction_collection: db.collection("token_transactions"),
+            onchain_balance_collection: db.collection("onchain_token_balances"),
             db,
         }
     }
@@ -84,6 +87,31 @@ impl TokenRepository {
         }
     }
 
+    /// 票据对应的代币批次
+    pub async fn find_token_batch_by_invoice(&self, invoice_id: ObjectId) -> Result<Option<TokenBatch>> {
+        match self.token_batch_collection.find_one(doc! { "invoice_id": invoice_id }).await? {
+            Some(doc) => Ok(Some(from_document(doc)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 按代币合约地址查找批次 (地址不区分大小写)
+    pub async fn find_token_batch_by_contract_address(&self, contract_address: &str) -> Result<Option<TokenBatch>> {
+        let filter = doc! { "contract_address": { "$regex": format!("^{}$", regex::escape(contract_address)), "$options": "i" } };
+        match self.token_batch_collection.find_one(filter).await? {
+            Some(doc) => Ok(Some(from_document(doc)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// 已部署合约的代币批次地址，供转账索引跟踪
+    pub async fn tracked_contract_addresses(&self) -> Result<Vec<String>> {
+        let values = self.token_batch_collection
+            .distinct("contract_address", doc! { "contract_address": { "$type": "string" } })
+            .await?;
+        Ok(values.into_iter().filter_map(|v| v.as_str().map(str::to_lowercase)).collect())
+    }
+
     pub async fn list_token_batches(
         &self, 
         status: Option<TokenBatchStatus>,
@@ -355,4 +383,86 @@ impl TokenRepository {
         
         Ok(token_transactions)
     }
-} 
\ No newline at end of file
+
+    /// 按用户汇总某批次已完成转账的净持仓 (Purchase 计入，Sale/Redemption 扣减)，只返回持仓为正的用户
+    pub async fn aggregate_holder_balances(&self, batch_id: ObjectId) -> Result<Vec<(ObjectId, Decimal128)>> {
+        let pipeline = vec![
+            doc! { "$match": completed_holding_transactions(batch_id) },
+            doc! { "$group": { "_id": "$user_id", "balance": net_token_amount() } },
+            doc! { "$match": { "balance": { "$gt": 0 } } },
+            doc! { "$sort": { "balance": -1, "_id": 1 } },
+        ];
+
+        let mut cursor = self.token_transaction_collection.aggregate(pipeline).await?;
+        let mut balances = Vec::new();
+        while let Some(document) = cursor.try_next().await? {
+            let (Ok(user_id), Ok(balance)) = (document.get_object_id("_id"), document.get_decimal128("balance")) else {
+                error!("Skipping malformed holder balance document: {:?}", document);
+                continue;
+            };
+            balances.push((user_id, *balance));
+        }
+        Ok(balances)
+    }
+
+    /// 单个用户在批次上的持仓净额，只聚合该用户的交易；没有已完成交易时为 0
+    pub async fn holder_balance(&self, batch_id: ObjectId, user_id: ObjectId) -> Result<Decimal128> {
+        let mut filter = completed_holding_transactions(batch_id);
+        filter.insert("user_id", user_id);
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": { "_id": null, "balance": net_token_amount() } },
+        ];
+
+        let mut cursor = self.token_transaction_collection.aggregate(pipeline).await?;
+        match cursor.try_next().await? {
+            Some(document) => Ok(*document.get_decimal128("balance")?),
+            None => Ok(Decimal128::from_str("0")?),
+        }
+    }
+
+    // On-chain balance operations (由转账索引维护)
+
+    /// 按转账调整链上余额，`delta` 可为负；记录不存在时创建
+    pub async fn adjust_onchain_balance_session(
+        &self,
+        token_address: &str,
+        wallet_address: &str,
+        delta: Decimal128,
+        session: &mut mongodb::ClientSession,
+    ) -> Result<()> {
+        let filter = doc! { "token_address": token_address, "wallet_address": wallet_address };
+        let update = doc! {
+            "$inc": { "balance": delta },
+            "$set": { "updated_at": DateTime::now() },
+        };
+        self.onchain_balance_collection.update_one(filter, update).upsert(true).session(session).await?;
+        Ok(())
+    }
+
+    pub async fn get_onchain_balance(&self, token_address: &str, wallet_address: &str) -> Result<Option<OnchainTokenBalance>> {
+        let filter = doc! { "token_address": token_address, "wallet_address": wallet_address };
+        match self.onchain_balance_collection.find_one(filter).await? {
+            Some(doc) => Ok(Some(from_document(doc)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 计入持仓的已完成交易 (认购增加，转售、赎回减少)
+fn completed_holding_transactions(batch_id: ObjectId) -> Document {
+    doc! {
+        "batch_id": batch_id,
+        "status": "Completed",
+        "transaction_type": { "$in": ["Purchase", "Sale", "Redemption"] },
+    }
+}
+
+/// `$group` 中按交易类型累加的持仓净额
+fn net_token_amount() -> Document {
+    doc! { "$sum": { "$cond": [
+        { "$eq": ["$transaction_type", "Purchase"] },
+        { "$toDecimal": "$token_amount" },
+        { "$multiply": [{ "$toDecimal": "$token_amount" }, -1] },
+    ]}}
+}
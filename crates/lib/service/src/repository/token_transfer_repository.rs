@@ -0,0 +1,66 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    ClientSession, Collection, Database,
+    bson::{DateTime, Document, doc},
+};
+
+use common::domain::entity::TokenTransfer;
+
+/// 链上转账历史与索引进度
+pub struct TokenTransferRepository {
+    collection: Collection<TokenTransfer>,
+    state: Collection<Document>,
+}
+
+impl TokenTransferRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<TokenTransfer>("token_transfers"),
+            state: db.collection::<Document>("indexer_state"),
+        }
+    }
+
+    /// 在事务内记录转账，同一交易的同一日志已存在时不写入并返回 false
+    pub async fn insert_if_absent_session(&self, transfer: &TokenTransfer, session: &mut ClientSession) -> Result<bool, mongodb::error::Error> {
+        let update = doc! {
+            "$setOnInsert": {
+                "token_address": &transfer.token_address,
+                "from_address": &transfer.from_address,
+                "to_address": &transfer.to_address,
+                "value": transfer.value,
+                "block_number": transfer.block_number,
+                "tx_hash": &transfer.tx_hash,
+                "log_index": transfer.log_index,
+                "created_at": transfer.created_at,
+            },
+        };
+        let filter = doc! { "tx_hash": &transfer.tx_hash, "log_index": transfer.log_index };
+        let result = self.collection.update_one(filter, update).upsert(true).session(session).await?;
+        Ok(result.upserted_id.is_some())
+    }
+
+    /// `block_number` 不小于 `from_block` 的转账，区块倒序
+    pub async fn find_from_block(&self, from_block: i64) -> Result<Vec<TokenTransfer>, mongodb::error::Error> {
+        let filter = doc! { "block_number": { "$gte": from_block } };
+        let cursor = self.collection.find(filter).sort(doc! { "block_number": -1, "log_index": -1 }).await?;
+        cursor.try_collect().await
+    }
+
+    /// 在事务内删除一条转账，返回是否由本次调用删除 (用于保证回滚只撤销一次余额)
+    pub async fn delete_session(&self, tx_hash: &str, log_index: i64, session: &mut ClientSession) -> Result<bool, mongodb::error::Error> {
+        let result = self.collection.delete_one(doc! { "tx_hash": tx_hash, "log_index": log_index }).session(session).await?;
+        Ok(result.deleted_count == 1)
+    }
+
+    /// 某个索引任务最后处理完成的区块
+    pub async fn last_processed_block(&self, indexer: &str) -> Result<Option<u64>, mongodb::error::Error> {
+        let state = self.state.find_one(doc! { "_id": indexer }).await?;
+        Ok(state.and_then(|s| s.get_i64("last_processed_block").ok()).map(|b| b as u64))
+    }
+
+    pub async fn set_last_processed_block(&self, indexer: &str, block: u64) -> Result<(), mongodb::error::Error> {
+        let update = doc! { "$set": { "last_processed_block": block as i64, "updated_at": DateTime::now() } };
+        self.state.update_one(doc! { "_id": indexer }, update).upsert(true).await?;
+        Ok(())
+    }
+}
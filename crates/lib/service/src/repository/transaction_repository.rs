@@ -28,12 +28,21 @@ impl TransactionRepository {
     pub async fn create_session(&self, transaction: Transaction, session: &mut ClientSession) -> Result<Transaction, ServiceError> {
         let mut transaction = transaction;
         let result: InsertOneResult = self.collection.insert_one(&transaction).session(session).await
-             .map_err(|e| ServiceError::MongoDbError(e.to_string()))?;
+             .map_err(ServiceError::from)?;
         transaction.id = Some(result.inserted_id.as_object_id().unwrap()); // Assign the MongoDB generated _id
         // We return the input struct updated with the ID, as insert_one_with_session doesn't return the doc.
         Ok(transaction)
     }
 
+    // 记录认购的链上交易哈希 (`metadata.tx_hash`)，由代币铸造完成后写入
+    pub async fn set_purchase_tx_hash(&self, holding_id: &str, tx_hash: &str) -> Result<()> {
+        let transaction_type = mongodb::bson::to_bson(&TransactionType::Purchase)?;
+        let filter = doc! { "holding_id": holding_id, "transaction_type": transaction_type };
+        let update = doc! { "$set": { "metadata.tx_hash": tx_hash, "updated_at": DateTime::now() } };
+        self.collection.update_many(filter, update).await?;
+        Ok(())
+    }
+
     // 根据ID查找交易记录
     pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<Transaction>> {
         let filter = doc! { "_id": id };
@@ -61,6 +70,16 @@ impl TransactionRepository {
         Ok(transactions)
     }
 
+    // 根据票据ID查找交易记录 (按交易时间升序)
+    pub async fn find_by_invoice_id(&self, invoice_id: ObjectId) -> Result<Vec<Transaction>> {
+        let filter = doc! { "invoice_id": invoice_id };
+
+        let cursor = self.collection.find(filter).sort(doc! { "transaction_date": 1 }).await?;
+        let transactions = cursor.try_collect().await?;
+
+        Ok(transactions)
+    }
+
     // 根据用户ID和交易类型查找交易记录
     pub async fn find_by_user_id_and_type(&self, user_id: &str, transaction_type: &str) -> Result<Vec<Transaction>> {
         let filter = doc! {
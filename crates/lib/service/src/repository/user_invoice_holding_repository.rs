@@ -3,7 +3,10 @@ use anyhow::{Result, anyhow};
 use common::domain::entity::{UserInvoiceHolding, HoldingStatus};
 use futures::stream::{StreamExt, TryStreamExt};
 use chrono::{Utc, NaiveDate, TimeZone};
+use common::domain::dto::purchase_history_dto::PurchaseSummaryDto;
 use crate::error::ServiceError;
+use common::pagination::OffsetPagination;
+use crate::service::purchase_history::summary_from_totals;
 
 pub struct UserInvoiceHoldingRepository {
     collection: Collection<UserInvoiceHolding>,
@@ -29,7 +32,7 @@ impl UserInvoiceHoldingRepository {
         let mut holding = holding;
         // Use .insert_one().session()
         let result = self.collection.insert_one(&holding).session(session).await
-            .map_err(|e| ServiceError::MongoDbError(e.to_string()))?;
+            .map_err(ServiceError::from)?;
         holding.id = Some(result.inserted_id.as_object_id().unwrap()); // Assign the MongoDB generated _id
         Ok(holding) // Return the original holding struct, now with the db id
     }
@@ -43,6 +46,45 @@ impl UserInvoiceHoldingRepository {
         Ok(holdings)
     }
     
+    /// 投资人认购记录分页，按认购时间倒序
+    pub async fn list_by_user(&self, user_id: &str, pagination: OffsetPagination) -> Result<(Vec<UserInvoiceHolding>, u64), ServiceError> {
+        let filter = doc! { "user_id": user_id };
+        let total = self.collection.count_documents(filter.clone()).await?;
+        let cursor = self.collection
+            .find(filter)
+            .sort(doc! { "purchase_date": -1, "holding_id": -1 })
+            .skip(pagination.skip())
+            .limit(pagination.page_size as i64)
+            .await?;
+        Ok((cursor.try_collect().await?, total))
+    }
+
+    /// 汇总投资人全部认购：累计认购金额与持有中的仓位数
+    pub async fn summarize_by_user(&self, user_id: &str) -> Result<PurchaseSummaryDto, ServiceError> {
+        let pipeline = vec![
+            doc! { "$match": { "user_id": user_id } },
+            doc! { "$group": {
+                "_id": null,
+                "total_invested": { "$sum": "$purchase_amount" },
+                "active_positions": { "$sum": { "$cond": [{ "$eq": ["$holding_status", "Active"] }, 1, 0] } },
+            } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let Some(row) = cursor.try_next().await? else {
+            return Ok(summary_from_totals(None, 0));
+        };
+        let total_invested = row.get_decimal128("total_invested").ok().copied();
+        let active_positions = row.get_i32("active_positions").map(i64::from).or_else(|_| row.get_i64("active_positions")).unwrap_or(0);
+        Ok(summary_from_totals(total_invested, active_positions.max(0) as u64))
+    }
+
+    // 事务内查询多个钱包的全部持仓
+    pub async fn find_by_user_ids_session(&self, user_ids: &[String], session: &mut ClientSession) -> Result<Vec<UserInvoiceHolding>, ServiceError> {
+        let filter = doc! { "user_id": { "$in": user_ids } };
+        let mut cursor = self.collection.find(filter).session(&mut *session).await?;
+        Ok(cursor.stream(session).try_collect().await?)
+    }
+
     // 根据用户ID和持仓ID查询具体持仓
     pub async fn find_by_user_id_and_holding_id(&self, user_id: &str, holding_id: &str) -> Result<Option<UserInvoiceHolding>> {
         let filter = doc! {
@@ -60,6 +102,14 @@ impl UserInvoiceHoldingRepository {
         Ok(holdings)
     }
     
+    // 查询某张票据下所有活跃的持仓
+    pub async fn find_active_by_invoice(&self, invoice_id: ObjectId) -> Result<Vec<UserInvoiceHolding>> {
+        let filter = doc! { "invoice_id": invoice_id, "holding_status": "Active" };
+        let cursor = self.collection.find(filter).await?;
+        let holdings = cursor.try_collect().await?;
+        Ok(holdings)
+    }
+
     // 更新累计利息和最后计息日期
     pub async fn update_accrued_interest(
         &self,
@@ -80,6 +130,14 @@ impl UserInvoiceHoldingRepository {
         Ok(())
     }
     
+    // 记录认购的链上交易哈希 (`metadata.tx_hash`)，由代币铸造完成后写入
+    pub async fn set_purchase_tx_hash(&self, holding_id: &str, tx_hash: &str) -> Result<()> {
+        let filter = doc! { "holding_id": holding_id };
+        let update = doc! { "$set": { "metadata.tx_hash": tx_hash, "updated_at": DateTime::now() } };
+        self.collection.update_one(filter, update).await?;
+        Ok(())
+    }
+
     // 更新持仓状态（例如到期）
     pub async fn update_holding_status(
         &self,
@@ -140,6 +198,15 @@ impl UserInvoiceHoldingRepository {
         Ok(())
     }
     
+    // 事务内将活跃持仓标记为已退款，持仓已不是活跃状态时返回 false
+    pub async fn refund_active_session(&self, holding_id: &str, session: &mut ClientSession) -> Result<bool, ServiceError> {
+        let status = |status: HoldingStatus| bson::to_bson(&status).map_err(|e| ServiceError::SerializationError(e.to_string()));
+        let filter = doc! { "holding_id": holding_id, "holding_status": status(HoldingStatus::Active)? };
+        let update = doc! { "$set": { "holding_status": status(HoldingStatus::Refunded)?, "updated_at": DateTime::now() } };
+        let result = self.collection.update_one(filter, update).session(session).await?;
+        Ok(result.modified_count > 0)
+    }
+
     // 查询到期日为指定日期的活跃持仓
     pub async fn find_maturing_holdings(&self, maturity_date: NaiveDate) -> Result<Vec<UserInvoiceHolding>> {
         // 需要与Invoice集合做关联查询，这里使用聚合管道
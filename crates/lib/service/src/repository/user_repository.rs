@@ -1,16 +1,48 @@
-use mongodb::{bson, bson::{doc, oid::ObjectId, DateTime, Decimal128}, Collection, Database, ClientSession};
+use mongodb::{bson, bson::{doc, oid::ObjectId, DateTime, Decimal128, Document}, Collection, Database, ClientSession};
 
-use log::{info, error};
+use tracing::{info, error};
+use common::domain::dto::admin_user_dto::UserRoleCountsDto;
 use common::domain::entity::{User, UserRole};
-use mongodb::options::UpdateOptions;
+use mongodb::options::{FindOneAndUpdateOptions, ReturnDocument, UpdateOptions};
 use futures::stream::TryStreamExt; // For cursor iteration
 use regex;
+use crate::db::is_duplicate_key;
 use crate::error::ServiceError;
+use common::pagination::OffsetPagination;
 
 pub struct UserRepository {
     collection: Collection<User>,
 }
 
+/// 管理员用户列表的筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct UserFilter {
+    pub role: Option<UserRole>,
+    /// true 只返回已绑定企业的用户，false 只返回未绑定的用户
+    pub bound: Option<bool>,
+    /// 创建时间区间 [start, end)
+    pub created_between: Option<(DateTime, DateTime)>,
+}
+
+impl UserFilter {
+    pub fn to_document(&self) -> Result<Document, mongodb::error::Error> {
+        let mut filter = doc! {};
+        if let Some(role) = &self.role {
+            filter.insert("role", bson::to_bson(role)?);
+        }
+        match self.bound {
+            Some(true) => { filter.insert("enterprise_id", doc! { "$type": "objectId" }); }
+            // null 同时匹配字段缺失的历史数据
+            Some(false) => { filter.insert("enterprise_id", bson::Bson::Null); }
+            None => {}
+        }
+        if let Some((start, end)) = self.created_between {
+            filter.insert("created_at", doc! { "$gte": start, "$lt": end });
+        }
+        Ok(filter)
+    }
+}
+
 impl UserRepository {
     pub fn new(db: &Database) -> Self {
         Self {
@@ -29,6 +61,20 @@ impl UserRepository {
         self.collection.find_one(filter).await
     }
 
+    // Find user by primary or linked wallet address
+    pub async fn find_by_any_wallet(&self, wallet_address: &str) -> Result<Option<User>, mongodb::error::Error> {
+        let filter = doc! {
+            "$or": [
+                { "wallet_address": bson::Regex {
+                    pattern: format!("^{}$", regex::escape(wallet_address)),
+                    options: "i".to_string()
+                } },
+                { "linked_wallets": wallet_address.to_lowercase() },
+            ]
+        };
+        self.collection.find_one(filter).await
+    }
+
     // Find user by wallet address within a transaction session (case-insensitive)
     pub async fn find_by_wallet_address_session(&self, wallet_address: &str, session: &mut ClientSession) -> Result<Option<User>, ServiceError> {
         let filter = doc! { 
@@ -39,7 +85,7 @@ impl UserRepository {
         };
         // Use find_one() with session argument
         self.collection.find_one(filter).session(session).await
-            .map_err(|e| ServiceError::MongoDbError(e.to_string()))
+            .map_err(ServiceError::from)
     }
 
     // Atomically update user balance within a transaction session
@@ -60,11 +106,49 @@ impl UserRepository {
 
         // Use update_one() with session argument
         let result = self.collection.update_one(filter, update).session(session).await
-            .map_err(|e| ServiceError::MongoDbError(e.to_string()))?;
+            .map_err(ServiceError::from)?;
 
         Ok(result.modified_count > 0)
     }
 
+    // 按 _id 批量查询用户
+    pub async fn find_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<User>, mongodb::error::Error> {
+        let cursor = self.collection.find(doc! { "_id": { "$in": ids } }).await?;
+        cursor.try_collect().await
+    }
+
+    /// 按条件分页查询用户 (最新创建在前)，同时返回符合条件的总数
+    pub async fn list(&self, filter: &UserFilter, pagination: OffsetPagination) -> Result<(Vec<User>, u64), mongodb::error::Error> {
+        let filter = filter.to_document()?;
+        let total = self.collection.count_documents(filter.clone()).await?;
+        let cursor = self.collection
+            .find(filter)
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .skip(pagination.skip())
+            .limit(pagination.page_size as i64)
+            .await?;
+        Ok((cursor.try_collect().await?, total))
+    }
+
+    /// 按角色统计用户数，忽略筛选条件中的角色 (其余条件照常生效)
+    pub async fn count_by_role(&self, filter: &UserFilter) -> Result<UserRoleCountsDto, mongodb::error::Error> {
+        let filter = UserFilter { role: None, ..filter.clone() }.to_document()?;
+        let pipeline = vec![
+            doc! { "$match": filter },
+            doc! { "$group": { "_id": "$role", "count": { "$sum": 1 } } },
+        ];
+        let mut cursor = self.collection.aggregate(pipeline).await?;
+        let mut counts = UserRoleCountsDto::default();
+        while let Some(row) = cursor.try_next().await? {
+            let Some(role) = row.get("_id").and_then(|role| bson::from_bson::<UserRole>(role.clone()).ok()) else {
+                continue;
+            };
+            let count = row.get_i32("count").map(i64::from).or_else(|_| row.get_i64("count")).unwrap_or(0);
+            counts.add(&role, count.max(0) as u64);
+        }
+        Ok(counts)
+    }
+
     // Create a new user
     pub async fn create_user(&self, user: User) -> Result<ObjectId, mongodb::error::Error> {
         let result = self.collection.insert_one(user).await?;
@@ -79,9 +163,10 @@ impl UserRepository {
         Ok(())
     }
 
-    // Find or create user, and update login time
-    pub async fn process_login(&self, wallet_address: &str) -> Result<User, mongodb::error::Error> {
-        match self.find_by_wallet_address(wallet_address).await? {
+    // Find or create user, and update login time. 关联钱包登录时返回其所属账户，已注销的账户返回 AccountDeleted
+    pub async fn process_login(&self, wallet_address: &str) -> Result<User, ServiceError> {
+        match self.find_by_any_wallet(wallet_address).await? {
+            Some(user) if user.deleted_at.is_some() => Err(ServiceError::AccountDeleted(wallet_address.to_string())),
             Some(mut user) => {
                 // User found, update login time
                 self.update_login_timestamp(user.id.unwrap()).await?;
@@ -101,6 +186,21 @@ impl UserRepository {
         }
     }
 
+    // Link an additional wallet to a user. 已关联过该钱包 (含并发关联到其他账户，由唯一索引拦截) 时返回 false
+    pub async fn add_linked_wallet(&self, user_id: ObjectId, wallet_address: &str) -> Result<bool, mongodb::error::Error> {
+        let wallet = wallet_address.to_lowercase();
+        let filter = doc! { "_id": user_id, "linked_wallets": { "$ne": &wallet } };
+        let update = doc! {
+            "$push": { "linked_wallets": &wallet },
+            "$set": { "updated_at": DateTime::now() }
+        };
+        match self.collection.update_one(filter, update).await {
+            Ok(result) => Ok(result.modified_count > 0),
+            Err(e) if is_duplicate_key(&e) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     // Bind a user to an enterprise
     pub async fn bind_enterprise(&self, user_wallet_address: &str, enterprise_id: ObjectId) -> Result<bool, mongodb::error::Error> {
         let filter = doc! { 
@@ -123,4 +223,200 @@ impl UserRepository {
         // Return true if a document was modified, false otherwise
         Ok(result.modified_count > 0)
     }
-} 
\ No newline at end of file
+
+    // Unbind a user from their enterprise (移除 enterprise_id 字段，与从未绑定的用户一致)
+    pub async fn unbind_enterprise(&self, user_wallet_address: &str) -> Result<bool, mongodb::error::Error> {
+        let filter = doc! {
+            "wallet_address": bson::Regex {
+                pattern: format!("^{}$", regex::escape(user_wallet_address)),
+                options: "i".to_string()
+            },
+            "enterprise_id": { "$ne": bson::Bson::Null }
+        };
+        let update = doc! {
+            "$set": { "updated_at": DateTime::now() },
+            "$unset": { "enterprise_id": "" }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    // Update a user's role, only if it is still `expected` (avoids clobbering concurrent changes)
+    pub async fn update_role(&self, user_wallet_address: &str, expected: &UserRole, role: &UserRole) -> Result<bool, mongodb::error::Error> {
+        let filter = doc! {
+            "wallet_address": bson::Regex {
+                pattern: format!("^{}$", regex::escape(user_wallet_address)),
+                options: "i".to_string()
+            },
+            "role": bson::to_bson(expected)?
+        };
+        let update = doc! {
+            "$set": {
+                "role": bson::to_bson(role)?,
+                "updated_at": DateTime::now()
+            }
+        };
+        let result = self.collection.update_one(filter, update).await?;
+        Ok(result.modified_count > 0)
+    }
+
+    // 按 _id 查询用户
+    pub async fn find_by_id(&self, user_id: ObjectId) -> Result<Option<User>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "_id": user_id }).await
+    }
+
+    /// 按 _id 修改角色，仅当当前角色为 `expected` 且账户未注销时生效，返回修改后的用户
+    pub async fn set_role(&self, user_id: ObjectId, expected: &UserRole, role: &UserRole) -> Result<Option<User>, mongodb::error::Error> {
+        let filter = doc! { "_id": user_id, "role": bson::to_bson(expected)?, "deleted_at": bson::Bson::Null };
+        let update = doc! { "$set": { "role": bson::to_bson(role)?, "updated_at": DateTime::now() } };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.collection.find_one_and_update(filter, update).with_options(options).await
+    }
+
+    /// 未注销的平台管理员数量
+    pub async fn count_active_admins(&self) -> Result<u64, mongodb::error::Error> {
+        let filter = doc! { "role": bson::to_bson(&UserRole::PlatformAdmin)?, "deleted_at": bson::Bson::Null };
+        self.collection.count_documents(filter).await
+    }
+
+    // Anonymize a user on account deletion within a transaction session: 清除昵称、关联钱包与企业绑定，
+    // 保留主钱包地址 (持仓、交易与链上记录均以其关联)。已注销时返回 false
+    pub async fn anonymize_session(&self, user_id: ObjectId, session: &mut ClientSession) -> Result<bool, ServiceError> {
+        let now = DateTime::now();
+        let filter = doc! { "_id": user_id, "deleted_at": bson::Bson::Null };
+        let update = doc! {
+            "$set": {
+                "name": "",
+                "linked_wallets": [],
+                "deleted_at": now,
+                "updated_at": now
+            },
+            "$unset": { "enterprise_id": "" }
+        };
+        let result = self.collection.update_one(filter, update).session(session).await?;
+        Ok(result.modified_count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::create_user_indexes;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn test_role_filter() {
+        let filter = UserFilter { role: Some(UserRole::EnterpriseAdmin), ..Default::default() };
+        assert_eq!(filter.to_document().unwrap(), doc! { "role": "EnterpriseAdmin" });
+        assert_eq!(UserFilter::default().to_document().unwrap(), doc! {});
+    }
+
+    #[test]
+    fn test_bound_filter() {
+        let bound = UserFilter { bound: Some(true), ..Default::default() };
+        assert_eq!(bound.to_document().unwrap(), doc! { "enterprise_id": { "$type": "objectId" } });
+        let unbound = UserFilter { bound: Some(false), ..Default::default() };
+        assert_eq!(unbound.to_document().unwrap(), doc! { "enterprise_id": bson::Bson::Null });
+    }
+
+    #[test]
+    fn test_combined_filter() {
+        let start = DateTime::from_millis(1_700_000_000_000);
+        let end = DateTime::from_millis(1_700_086_400_000);
+        let filter = UserFilter { role: Some(UserRole::Investor), bound: Some(false), created_between: Some((start, end)) };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! { "role": "Investor", "enterprise_id": bson::Bson::Null, "created_at": { "$gte": start, "$lt": end } }
+        );
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_list_filters_by_role_and_binding() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = UserRepository::new(&db);
+
+        // 连续的历史创建时间，按时间窗口筛选
+        let base = i64::from_str_radix(&ObjectId::new().to_hex()[14..], 16).unwrap();
+        let mut ids = Vec::new();
+        for (i, (role, bound)) in [(UserRole::Investor, false), (UserRole::Investor, false), (UserRole::EnterpriseAdmin, true), (UserRole::PlatformAdmin, false)].into_iter().enumerate() {
+            let mut user = User::new(format!("0x{:0>40}", ObjectId::new().to_hex()), String::new(), role);
+            user.created_at = DateTime::from_millis(base + i as i64);
+            if bound {
+                user.enterprise_id = Some(ObjectId::new());
+            }
+            ids.push(repo.create_user(user).await.unwrap());
+        }
+        let window = Some((DateTime::from_millis(base), DateTime::from_millis(base + 10)));
+
+        let investors = UserFilter { role: Some(UserRole::Investor), created_between: window, ..Default::default() };
+        let (rows, total) = repo.list(&investors, OffsetPagination::new(1, 10)).await.unwrap();
+        let (first_page, _) = repo.list(&investors, OffsetPagination::new(1, 1)).await.unwrap();
+        let bound = UserFilter { bound: Some(true), created_between: window, ..Default::default() };
+        let (bound_rows, bound_total) = repo.list(&bound, OffsetPagination::new(1, 10)).await.unwrap();
+        let unbound = UserFilter { bound: Some(false), created_between: window, ..Default::default() };
+        let (_, unbound_total) = repo.list(&unbound, OffsetPagination::new(1, 10)).await.unwrap();
+        let counts = repo.count_by_role(&investors).await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(total, 2);
+        assert!(rows.iter().all(|u| u.role == UserRole::Investor));
+        // 最新创建在前
+        assert_eq!(first_page[0].id, Some(ids[1]));
+        assert_eq!(bound_total, 1);
+        assert_eq!(bound_rows[0].id, Some(ids[2]));
+        assert_eq!(unbound_total, 3);
+        // 角色统计忽略角色筛选
+        assert_eq!(counts, UserRoleCountsDto { investor: 2, enterprise_admin: 1, platform_admin: 1 });
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_unbind_enterprise_removes_field() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = UserRepository::new(&db);
+        let wallet = format!("0x{:0>40}", ObjectId::new().to_hex());
+        let user_id = repo.create_user(User::new(wallet.clone(), String::new(), UserRole::EnterpriseAdmin)).await.unwrap();
+        repo.bind_enterprise(&wallet, ObjectId::new()).await.unwrap();
+
+        let unbound = repo.unbind_enterprise(&wallet).await.unwrap();
+        let unbound_again = repo.unbind_enterprise(&wallet).await.unwrap();
+        let raw = db.collection::<Document>("users").find_one(doc! { "_id": user_id }).await.unwrap().unwrap();
+        let user = repo.find_by_wallet_address(&wallet).await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert!(unbound);
+        assert!(!unbound_again);
+        assert!(!raw.contains_key("enterprise_id"));
+        assert_eq!(user.enterprise_id, None);
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_wallet_links_to_only_one_user() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        create_user_indexes(&db).await.unwrap();
+        let repo = UserRepository::new(&db);
+
+        let new_user = || User::new(format!("0x{:0>40}", ObjectId::new().to_hex()), String::new(), UserRole::Investor);
+        // 未关联钱包的用户 (空数组) 不互相冲突
+        let first = repo.create_user(new_user()).await.unwrap();
+        let second = repo.create_user(new_user()).await.unwrap();
+        let wallet = format!("0x{:0>40}", ObjectId::new().to_hex());
+
+        let linked = repo.add_linked_wallet(first, &wallet).await.unwrap();
+        let linked_again = repo.add_linked_wallet(first, &wallet.to_uppercase().replacen("0X", "0x", 1)).await.unwrap();
+        // 绕过接口的查重并发关联到另一个账户时被唯一索引拦截
+        let stolen = repo.add_linked_wallet(second, &wallet).await.unwrap();
+        let owner = repo.find_by_any_wallet(&wallet).await.unwrap();
+        test_db.cleanup().await;
+
+        assert!(linked);
+        assert!(!linked_again);
+        assert!(!stolen);
+        assert_eq!(owner.and_then(|u| u.id), Some(first));
+    }
+}
@@ -0,0 +1,95 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    Collection, Database,
+    bson::{self, DateTime, doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+    results::UpdateResult,
+};
+
+use common::domain::entity::{WebhookDelivery, WebhookDeliveryStatus};
+
+pub struct WebhookDeliveryRepository {
+    collection: Collection<WebhookDelivery>,
+}
+
+impl WebhookDeliveryRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<WebhookDelivery>("webhook_deliveries"),
+        }
+    }
+
+    // Create a delivery record
+    pub async fn create(&self, delivery: &WebhookDelivery) -> Result<WebhookDelivery, mongodb::error::Error> {
+        let result = self.collection.insert_one(delivery).await?;
+        let mut created = delivery.clone();
+        created.id = result.inserted_id.as_object_id();
+        Ok(created)
+    }
+
+    pub async fn find_by_id(&self, id: ObjectId) -> Result<Option<WebhookDelivery>, mongodb::error::Error> {
+        self.collection.find_one(doc! { "_id": id }).await
+    }
+
+    // Find all deliveries still pending
+    pub async fn find_pending(&self) -> Result<Vec<WebhookDelivery>, mongodb::error::Error> {
+        let filter = doc! { "status": status_bson(WebhookDeliveryStatus::Pending)? };
+        let cursor = self.collection.find(filter).sort(doc! { "next_attempt_at": 1 }).await?;
+        cursor.try_collect().await
+    }
+
+    /// 抢占一条到期的投递：attempts 加一，并把 next_attempt_at 推迟 lease_ms 作为租约，
+    /// 这样多个实例/任务不会同时投递同一条记录，进程崩溃后租约过期也能被重新抢占。
+    pub async fn claim_due(&self, id: ObjectId, lease_ms: i64) -> Result<Option<WebhookDelivery>, mongodb::error::Error> {
+        let now = DateTime::now();
+        let filter = doc! {
+            "_id": id,
+            "status": status_bson(WebhookDeliveryStatus::Pending)?,
+            "next_attempt_at": { "$lte": now },
+            "$expr": { "$lt": ["$attempts", "$max_attempts"] },
+        };
+        let update = doc! {
+            "$inc": { "attempts": 1 },
+            "$set": {
+                "next_attempt_at": DateTime::from_millis(now.timestamp_millis() + lease_ms),
+                "updated_at": now,
+            }
+        };
+        let options = FindOneAndUpdateOptions::builder().return_document(ReturnDocument::After).build();
+        self.collection.find_one_and_update(filter, update).with_options(options).await
+    }
+
+    // Mark delivery as succeeded
+    pub async fn mark_succeeded(&self, id: ObjectId) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "status": status_bson(WebhookDeliveryStatus::Succeeded)?,
+            "last_error": bson::Bson::Null,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+
+    // Record a failed attempt and schedule the next one
+    pub async fn schedule_retry(&self, id: ObjectId, next_attempt_at: DateTime, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "next_attempt_at": next_attempt_at,
+            "last_error": error,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+
+    // Give up on a delivery
+    pub async fn mark_failed(&self, id: ObjectId, error: &str) -> Result<UpdateResult, mongodb::error::Error> {
+        let update = doc! { "$set": {
+            "status": status_bson(WebhookDeliveryStatus::Failed)?,
+            "last_error": error,
+            "updated_at": DateTime::now(),
+        } };
+        self.collection.update_one(doc! { "_id": id }, update).await
+    }
+}
+
+fn status_bson(status: WebhookDeliveryStatus) -> Result<bson::Bson, mongodb::error::Error> {
+    bson::to_bson(&status).map_err(|e| mongodb::error::Error::custom(format!("Failed to serialize status: {}", e)))
+}
@@ -0,0 +1,46 @@
+use futures::stream::TryStreamExt;
+use mongodb::{
+    Collection, Database,
+    bson::{DateTime, doc, oid::ObjectId},
+    options::{FindOneAndUpdateOptions, ReturnDocument},
+};
+
+use common::domain::entity::WebhookSubscription;
+
+pub struct WebhookSubscriptionRepository {
+    collection: Collection<WebhookSubscription>,
+}
+
+impl WebhookSubscriptionRepository {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            collection: db.collection::<WebhookSubscription>("webhook_subscriptions"),
+        }
+    }
+
+    /// 登记回调地址，同一企业重复登记同一地址时更换密钥盐 (旧密钥随之失效)
+    pub async fn upsert(&self, enterprise_id: ObjectId, url: &str, secret_salt: &str) -> Result<Option<WebhookSubscription>, mongodb::error::Error> {
+        let now = DateTime::now();
+        let update = doc! {
+            "$set": { "secret_salt": secret_salt, "updated_at": now },
+            "$setOnInsert": { "created_at": now },
+        };
+        let options = FindOneAndUpdateOptions::builder().upsert(true).return_document(ReturnDocument::After).build();
+        self.collection
+            .find_one_and_update(doc! { "enterprise_id": enterprise_id, "url": url }, update)
+            .with_options(options)
+            .await
+    }
+
+    // Find all callbacks registered by an enterprise
+    pub async fn find_by_enterprise(&self, enterprise_id: ObjectId) -> Result<Vec<WebhookSubscription>, mongodb::error::Error> {
+        let cursor = self.collection.find(doc! { "enterprise_id": enterprise_id }).sort(doc! { "created_at": 1 }).await?;
+        cursor.try_collect().await
+    }
+
+    /// 删除企业自己的回调地址，不存在或属于其他企业时返回 false
+    pub async fn delete(&self, enterprise_id: ObjectId, id: ObjectId) -> Result<bool, mongodb::error::Error> {
+        let result = self.collection.delete_one(doc! { "_id": id, "enterprise_id": enterprise_id }).await?;
+        Ok(result.deleted_count > 0)
+    }
+}
@@ -0,0 +1,380 @@
+//! 用户数据导出、账户注销与角色变更
+//!
+//! 注销只匿名化用户文档 (昵称、关联钱包、企业绑定)，主钱包地址、持仓与交易记录保留，
+//! 它们与链上记录对应，不可删除。存在持有中的仓位时拒绝注销；持仓检查与匿名化在同一事务内完成。
+//! 注销后的账户不能再登录或刷新令牌。
+//! 角色变更只允许 [`UserRole::can_change_to`] 中列出的转换，且不能降级最后一个平台管理员。
+
+use chrono::Utc;
+use tracing::info;
+use mongodb::{Client, Database};
+use mongodb::bson::oid::ObjectId;
+
+use common::domain::dto::user_export_dto::{
+    ExportedEnterpriseDto, ExportedPurchaseDto, ExportedTransactionDto, ExportedUserDto, UserDataExportDto,
+};
+use common::domain::entity::{Enterprise, HoldingStatus, Transaction, User, UserInvoiceHolding, UserRole};
+use crate::db::finish_transaction;
+use crate::error::ServiceError;
+use crate::repository::{EnterpriseRepository, TransactionRepository, UserInvoiceHoldingRepository, UserRepository};
+
+pub struct UserAccountService {
+    client: Client,
+    user_repo: UserRepository,
+    enterprise_repo: EnterpriseRepository,
+    holding_repo: UserInvoiceHoldingRepository,
+    transaction_repo: TransactionRepository,
+}
+
+/// 注销结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountDeletion {
+    pub user_id: ObjectId,
+    /// 注销前绑定的企业
+    pub unbound_enterprise: Option<mongodb::bson::oid::ObjectId>,
+    /// 注销前关联的钱包 (不含主钱包)
+    pub removed_wallets: Vec<String>,
+}
+
+/// 角色变更结果
+#[derive(Debug, Clone)]
+pub struct RoleChange {
+    pub previous: UserRole,
+    pub user: User,
+}
+
+impl UserAccountService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            client: db.client().clone(),
+            user_repo: UserRepository::new(db),
+            enterprise_repo: EnterpriseRepository::new(db),
+            holding_repo: UserInvoiceHoldingRepository::new(db),
+            transaction_repo: TransactionRepository::new(db),
+        }
+    }
+
+    /// 导出用户的全部数据，用户不存在时返回 `UserNotFound`
+    pub async fn export(&self, wallet_address: &str) -> Result<UserDataExportDto, ServiceError> {
+        let user = self.find_user(wallet_address).await?;
+        let enterprise = match user.enterprise_id {
+            Some(id) => self.enterprise_repo.find_by_id(id).await?,
+            None => None,
+        };
+        let (holdings, transactions) = self.load_positions(&user).await?;
+        Ok(build_export(&user, enterprise.as_ref(), &holdings, &transactions, Utc::now().timestamp_millis()))
+    }
+
+    /// 匿名化账户并解除企业绑定；存在持有中的仓位时返回 `ActivePositions`
+    pub async fn delete_account(&self, wallet_address: &str) -> Result<AccountDeletion, ServiceError> {
+        let user = self.find_user(wallet_address).await?;
+        let user_id = user.id.ok_or_else(|| ServiceError::InternalError(format!("User {} has no id", wallet_address)))?;
+
+        let wallets = user_wallets(&user);
+
+        // 认购事务同样写用户文档 (扣减余额)，与注销并发时产生写冲突，只有一方能提交
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let result = async {
+            let holdings = self.holding_repo.find_by_user_ids_session(&wallets, &mut session).await?;
+            check_no_active_positions(&holdings)?;
+            if !self.user_repo.anonymize_session(user_id, &mut session).await? {
+                // 并发注销，已被其他请求处理
+                return Err(ServiceError::UserNotFound(wallet_address.to_string()));
+            }
+            Ok(())
+        }
+        .await;
+        finish_transaction(session, result).await?;
+        info!("Anonymized account {} ({})", user_id, wallet_address);
+        Ok(AccountDeletion { user_id, unbound_enterprise: user.enterprise_id, removed_wallets: user.linked_wallets })
+    }
+
+    /// 修改用户角色 (管理员操作)。用户不存在或已注销时返回 `UserNotFound`，
+    /// 转换不在允许范围内时返回 `RoleTransitionNotAllowed`，降级最后一个平台管理员时返回 `LastAdmin`
+    pub async fn change_role(&self, user_id: ObjectId, target: UserRole) -> Result<RoleChange, ServiceError> {
+        let user = match self.user_repo.find_by_id(user_id).await? {
+            Some(user) if user.deleted_at.is_none() => user,
+            _ => return Err(ServiceError::UserNotFound(user_id.to_hex())),
+        };
+        let active_admins = match user.role {
+            UserRole::PlatformAdmin => self.user_repo.count_active_admins().await?,
+            _ => 0,
+        };
+        check_role_change(&user.role, &target, active_admins)?;
+
+        // 以读取到的角色为条件更新，并发修改时返回 UserNotFound 由调用方重试
+        let updated = self.user_repo.set_role(user_id, &user.role, &target).await?
+            .ok_or_else(|| ServiceError::UserNotFound(user_id.to_hex()))?;
+        info!("Changed role of user {} ({}) from {:?} to {:?}", user_id, user.wallet_address, user.role, target);
+        Ok(RoleChange { previous: user.role, user: updated })
+    }
+
+    // 已注销的账户视为不存在
+    async fn find_user(&self, wallet_address: &str) -> Result<User, ServiceError> {
+        match self.user_repo.find_by_any_wallet(wallet_address).await? {
+            Some(user) if user.deleted_at.is_none() => Ok(user),
+            _ => Err(ServiceError::UserNotFound(wallet_address.to_string())),
+        }
+    }
+
+    // 持仓与交易按认购时使用的钱包记录，主钱包和关联钱包都要查询
+    async fn load_positions(&self, user: &User) -> Result<(Vec<UserInvoiceHolding>, Vec<Transaction>), ServiceError> {
+        let mut holdings = Vec::new();
+        let mut transactions = Vec::new();
+        for wallet in user_wallets(user) {
+            holdings.extend(self.holding_repo.find_by_user_id(&wallet).await?);
+            transactions.extend(self.transaction_repo.find_by_user_id(&wallet).await?);
+        }
+        Ok((holdings, transactions))
+    }
+}
+
+fn user_wallets(user: &User) -> Vec<String> {
+    let mut wallets = vec![user.wallet_address.to_lowercase()];
+    for wallet in &user.linked_wallets {
+        let wallet = wallet.to_lowercase();
+        if !wallets.contains(&wallet) {
+            wallets.push(wallet);
+        }
+    }
+    wallets
+}
+
+/// 有持有中的仓位时不允许注销，错误信息列出这些持仓
+pub fn check_no_active_positions(holdings: &[UserInvoiceHolding]) -> Result<(), ServiceError> {
+    let active: Vec<&str> = holdings.iter()
+        .filter(|h| h.holding_status == HoldingStatus::Active)
+        .map(|h| h.holding_id.as_str())
+        .collect();
+    if active.is_empty() {
+        Ok(())
+    } else {
+        Err(ServiceError::ActivePositions(format!("{} active holding(s): {}", active.len(), active.join(", "))))
+    }
+}
+
+/// 校验角色变更：`active_admins` 为当前未注销的平台管理员数 (用户本身是平台管理员时才需要)
+pub fn check_role_change(from: &UserRole, to: &UserRole, active_admins: u64) -> Result<(), ServiceError> {
+    if !from.can_change_to(to) {
+        return Err(ServiceError::RoleTransitionNotAllowed { from: from.clone(), to: to.clone() });
+    }
+    if *from == UserRole::PlatformAdmin && active_admins <= 1 {
+        return Err(ServiceError::LastAdmin(format!("{} active platform admin(s)", active_admins)));
+    }
+    Ok(())
+}
+
+/// 组装导出数据，认购与交易按时间倒序
+pub fn build_export(
+    user: &User,
+    enterprise: Option<&Enterprise>,
+    holdings: &[UserInvoiceHolding],
+    transactions: &[Transaction],
+    generated_at: i64,
+) -> UserDataExportDto {
+    let mut purchases: Vec<ExportedPurchaseDto> = holdings.iter().map(|h| ExportedPurchaseDto {
+        holding_id: h.holding_id.clone(),
+        invoice_id: h.invoice_id.to_hex(),
+        wallet_address: h.user_id.clone(),
+        purchase_amount: h.purchase_amount.to_string(),
+        current_balance: h.current_balance.to_string(),
+        total_accrued_interest: h.total_accrued_interest.to_string(),
+        purchased_at: h.purchase_date.timestamp_millis(),
+        holding_status: h.holding_status.clone(),
+    }).collect();
+    purchases.sort_by(|a, b| b.purchased_at.cmp(&a.purchased_at));
+
+    let mut transactions: Vec<ExportedTransactionDto> = transactions.iter().map(|tx| ExportedTransactionDto {
+        id: tx.id.map(|id| id.to_hex()).unwrap_or_default(),
+        invoice_id: tx.invoice_id.to_hex(),
+        holding_id: tx.holding_id.clone(),
+        wallet_address: tx.user_id.clone(),
+        transaction_type: format!("{:?}", tx.transaction_type),
+        amount: tx.amount.to_string(),
+        status: tx.status.clone(),
+        transaction_date: tx.transaction_date.timestamp_millis(),
+    }).collect();
+    transactions.sort_by(|a, b| b.transaction_date.cmp(&a.transaction_date));
+
+    UserDataExportDto {
+        generated_at,
+        user: ExportedUserDto {
+            id: user.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: user.name.clone(),
+            wallet_address: user.wallet_address.clone(),
+            linked_wallets: user.linked_wallets.clone(),
+            role: format!("{:?}", user.role),
+            balance: user.balance.to_string(),
+            created_at: user.created_at.timestamp_millis(),
+            updated_at: user.updated_at.timestamp_millis(),
+            last_login_at: user.login_timestamp.timestamp_millis(),
+        },
+        enterprise: enterprise.map(|e| ExportedEnterpriseDto {
+            id: e.id.map(|id| id.to_hex()).unwrap_or_default(),
+            name: e.name.clone(),
+            wallet_address: e.wallet_address.clone(),
+        }),
+        purchases,
+        transactions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use mongodb::bson::{DateTime, Decimal128, oid::ObjectId};
+    use common::domain::entity::{TransactionType, UserRole};
+    use crate::test_support::TestDb;
+
+    const WALLET: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const LINKED: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+
+    fn holding(wallet: &str, status: HoldingStatus, purchased_ms: i64) -> UserInvoiceHolding {
+        let mut holding = UserInvoiceHolding::new(wallet.to_string(), ObjectId::new(), Decimal128::from_str("100").unwrap());
+        holding.holding_status = status;
+        holding.purchase_date = DateTime::from_millis(purchased_ms);
+        holding
+    }
+
+    #[test]
+    fn test_export_bundle_shape() {
+        let enterprise_id = ObjectId::new();
+        let mut user = User::new(WALLET.to_string(), "alice".to_string(), UserRole::Investor);
+        user.id = Some(ObjectId::new());
+        user.enterprise_id = Some(enterprise_id);
+        user.linked_wallets = vec![LINKED.to_string()];
+        let mut enterprise = Enterprise::new("Acme".to_string(), "0xcccccccccccccccccccccccccccccccccccccccc".to_string());
+        enterprise.id = Some(enterprise_id);
+
+        let older = holding(WALLET, HoldingStatus::Matured, 1_000);
+        let newer = holding(LINKED, HoldingStatus::Active, 2_000);
+        let tx = Transaction::new_purchase(LINKED.to_string(), newer.invoice_id, newer.holding_id.clone(), Decimal128::from_str("100").unwrap());
+
+        let export = build_export(&user, Some(&enterprise), &[older.clone(), newer.clone()], &[tx], 42);
+        let value = serde_json::to_value(&export).unwrap();
+
+        let mut keys: Vec<&str> = value.as_object().unwrap().keys().map(|k| k.as_str()).collect();
+        keys.sort();
+        assert_eq!(keys, ["enterprise", "generated_at", "purchases", "transactions", "user"]);
+        assert_eq!(value["generated_at"], 42);
+        assert_eq!(value["user"]["wallet_address"], WALLET);
+        assert_eq!(value["user"]["linked_wallets"][0], LINKED);
+        assert_eq!(value["user"]["role"], "Investor");
+        assert_eq!(value["enterprise"]["id"], enterprise_id.to_hex());
+        assert_eq!(value["enterprise"]["name"], "Acme");
+        // 最新认购在前，金额为字符串
+        assert_eq!(value["purchases"][0]["holding_id"], newer.holding_id.as_str());
+        assert_eq!(value["purchases"][0]["wallet_address"], LINKED);
+        assert_eq!(value["purchases"][1]["holding_status"], "Matured");
+        assert!(value["purchases"][0]["purchase_amount"].is_string());
+        assert_eq!(value["transactions"][0]["transaction_type"], format!("{:?}", TransactionType::Purchase));
+        assert_eq!(value["transactions"][0]["holding_id"], newer.holding_id.as_str());
+    }
+
+    #[test]
+    fn test_export_without_enterprise() {
+        let user = User::new(WALLET.to_string(), String::new(), UserRole::Investor);
+        let value = serde_json::to_value(build_export(&user, None, &[], &[], 0)).unwrap();
+        assert!(value["enterprise"].is_null());
+        assert_eq!(value["purchases"], serde_json::json!([]));
+        assert_eq!(value["transactions"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_active_position_blocks_deletion() {
+        let active = holding(WALLET, HoldingStatus::Active, 1_000);
+        let err = check_no_active_positions(&[holding(WALLET, HoldingStatus::Matured, 0), active.clone()]).unwrap_err();
+        match err {
+            ServiceError::ActivePositions(reason) => assert!(reason.contains(&active.holding_id), "{}", reason),
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert!(check_no_active_positions(&[holding(WALLET, HoldingStatus::Matured, 0), holding(WALLET, HoldingStatus::Sold, 0)]).is_ok());
+        assert!(check_no_active_positions(&[]).is_ok());
+    }
+
+    #[test]
+    fn test_user_wallets_include_linked_without_duplicates() {
+        let mut user = User::new(WALLET.to_string(), String::new(), UserRole::Investor);
+        user.linked_wallets = vec![LINKED.to_uppercase().replace("0X", "0x"), WALLET.to_string()];
+        assert_eq!(user_wallets(&user), vec![WALLET.to_string(), LINKED.to_string()]);
+    }
+
+    #[test]
+    fn test_role_promotion_allowed() {
+        assert!(check_role_change(&UserRole::EnterpriseAdmin, &UserRole::PlatformAdmin, 0).is_ok());
+        assert!(check_role_change(&UserRole::Investor, &UserRole::EnterpriseAdmin, 0).is_ok());
+        // 还有其他平台管理员时可以降级
+        assert!(check_role_change(&UserRole::PlatformAdmin, &UserRole::Investor, 2).is_ok());
+    }
+
+    #[test]
+    fn test_role_transition_outside_allowlist_is_rejected() {
+        for (from, to) in [
+            (UserRole::Investor, UserRole::PlatformAdmin),
+            (UserRole::Investor, UserRole::Investor),
+            (UserRole::PlatformAdmin, UserRole::PlatformAdmin),
+        ] {
+            assert!(matches!(check_role_change(&from, &to, 2), Err(ServiceError::RoleTransitionNotAllowed { .. })), "{:?} -> {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn test_last_admin_cannot_be_demoted() {
+        assert!(matches!(check_role_change(&UserRole::PlatformAdmin, &UserRole::EnterpriseAdmin, 1), Err(ServiceError::LastAdmin(_))));
+        assert!(matches!(check_role_change(&UserRole::PlatformAdmin, &UserRole::Investor, 0), Err(ServiceError::LastAdmin(_))));
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_deleted_account_cannot_log_in_again() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = UserRepository::new(&db);
+        let holdings = UserInvoiceHoldingRepository::new(&db);
+        let service = UserAccountService::new(&db);
+        let mut user = User::new(WALLET.to_string(), "alice".to_string(), UserRole::Investor);
+        user.linked_wallets = vec![LINKED.to_string()];
+        repo.create_user(user).await.unwrap();
+        let active = holdings.create(holding(LINKED, HoldingStatus::Active, 1_000)).await.unwrap();
+
+        let blocked = service.delete_account(WALLET).await;
+        db.collection::<UserInvoiceHolding>("user_invoice_holdings")
+            .update_one(mongodb::bson::doc! { "_id": active.id }, mongodb::bson::doc! { "$set": { "holding_status": "Matured" } })
+            .await
+            .unwrap();
+        let deleted = service.delete_account(WALLET).await;
+        let again = service.delete_account(WALLET).await;
+        let login = repo.process_login(WALLET).await;
+        test_db.cleanup().await;
+
+        assert!(matches!(blocked, Err(ServiceError::ActivePositions(_))));
+        assert_eq!(deleted.unwrap().removed_wallets, vec![LINKED.to_string()]);
+        assert!(matches!(again, Err(ServiceError::UserNotFound(_))));
+        assert!(matches!(login, Err(ServiceError::AccountDeleted(_))));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_change_role_promotes_and_protects_last_admin() {
+        // 独立数据库，平台管理员数量不受其他测试数据影响
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let repo = UserRepository::new(&db);
+        let service = UserAccountService::new(&db);
+        let creditor = repo.create_user(User::new(format!("0x{:0>40}", ObjectId::new().to_hex()), String::new(), UserRole::EnterpriseAdmin)).await.unwrap();
+
+        let promoted = service.change_role(creditor, UserRole::PlatformAdmin).await;
+        // 此时它是唯一的平台管理员，不能降级
+        let demoted = service.change_role(creditor, UserRole::Investor).await;
+        let missing = service.change_role(ObjectId::new(), UserRole::Investor).await;
+        test_db.cleanup().await;
+
+        let promoted = promoted.unwrap();
+        assert_eq!(promoted.previous, UserRole::EnterpriseAdmin);
+        assert_eq!(promoted.user.role, UserRole::PlatformAdmin);
+        assert!(matches!(demoted, Err(ServiceError::LastAdmin(_))));
+        assert!(matches!(missing, Err(ServiceError::UserNotFound(_))));
+    }
+}
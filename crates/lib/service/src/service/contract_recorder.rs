@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::{Address, TransactionReceipt, U256};
+use tracing::{error, warn};
+use mongodb::Database;
+
+use common::domain::dto::invoice_dto::InvoiceDataDto;
+use common::domain::dto::query_invoice_dto::QueryParamsDto;
+use common::domain::entity::ContractOperationStatus;
+use pharos_interact::{ContractQuerier, ContractWriter, GasEstimate, InvoiceData, decode_revert_reason, extract_tx_hash, is_contract_paused};
+
+use crate::metrics::record_contract_write;
+use crate::repository::ContractOperationRepository;
+
+/// 为 ContractWriter 的每次提交写入操作记录 (`contract_operations`，供 `/admin/onchain/failures` 排查) 和写入指标
+///
+/// 待确认交易在广播时由 [`PendingTransactionTracker`](crate::service::PendingTransactionTracker) 登记，不在这里重复登记。
+/// 记录失败只打印日志，不影响链上调用的结果。
+pub struct RecordingContractWriter<W: ?Sized> {
+    inner: Arc<W>,
+    repo: ContractOperationRepository,
+}
+
+/// 服务与接口共用的链上写入入口 (已包装为 [`RecordingContractWriter`])
+pub type SharedContractWriter = Arc<dyn ContractWriter + Send + Sync>;
+
+impl<W: ContractWriter + Send + Sync + ?Sized> RecordingContractWriter<W> {
+    pub fn new(inner: Arc<W>, db: &Database) -> Self {
+        Self { inner, repo: ContractOperationRepository::new(db) }
+    }
+
+    async fn record(&self, operation: &str, reference: &str, result: &Result<Option<TransactionReceipt>>) {
+        let recorded = match result {
+            Ok(receipt) => {
+                record_contract_write(operation, "success");
+                let tx_hash = receipt.as_ref().map(|r| format!("{:?}", r.transaction_hash));
+                self.repo.record_attempt(operation, reference, ContractOperationStatus::Succeeded, tx_hash, None, None).await
+            }
+            Err(e) => {
+                let message = format!("{:#}", e);
+                let revert_reason = if is_contract_paused(e) { Some("contract paused".to_string()) } else { decode_revert_reason(&message) };
+                let outcome = if is_contract_paused(e) { "paused" } else if revert_reason.is_some() { "reverted" } else { "error" };
+                record_contract_write(operation, outcome);
+                warn!("Contract operation {} ({}) failed: {}", operation, reference, message);
+                let tx_hash = extract_tx_hash(&message);
+                self.repo
+                    .record_attempt(operation, reference, ContractOperationStatus::Failed, tx_hash, revert_reason, Some(message))
+                    .await
+            }
+        };
+        if let Err(e) = recorded {
+            error!("Failed to record contract operation {} ({}): {}", operation, reference, e);
+        }
+    }
+}
+
+#[async_trait]
+impl<W: ContractWriter + Send + Sync + ?Sized> ContractQuerier for RecordingContractWriter<W> {
+    async fn query_invoices(&self, params: QueryParamsDto) -> Result<Vec<InvoiceDataDto>> {
+        self.inner.query_invoices(params).await
+    }
+
+    async fn get_invoice(&self, invoice_number: String) -> Result<Option<InvoiceData>> {
+        self.inner.get_invoice(invoice_number).await
+    }
+
+    async fn is_paused(&self) -> Result<bool> {
+        self.inner.is_paused().await
+    }
+
+    async fn estimate_gas_for_purchase(&self, buyer: Address, batch_id: String, amount_str: String) -> Result<GasEstimate> {
+        self.inner.estimate_gas_for_purchase(buyer, batch_id, amount_str).await
+    }
+
+    async fn token_balance_of(&self, token: Address, owner: Address) -> Result<U256> {
+        self.inner.token_balance_of(token, owner).await
+    }
+
+    async fn token_decimals(&self, token: Address) -> Result<u8> {
+        self.inner.token_decimals(token).await
+    }
+
+    async fn is_enterprise_signer(&self, enterprise: Address, account: Address) -> Result<bool> {
+        self.inner.is_enterprise_signer(enterprise, account).await
+    }
+}
+
+#[async_trait]
+impl<W: ContractWriter + Send + Sync + ?Sized> ContractWriter for RecordingContractWriter<W> {
+    async fn batch_create_invoices(&self, invoices: Vec<InvoiceDataDto>) -> Result<Option<TransactionReceipt>> {
+        let reference = invoices.iter().map(|i| i.invoice_number.as_str()).collect::<Vec<_>>().join(",");
+        let result = self.inner.batch_create_invoices(invoices).await;
+        self.record("batch_create_invoices", &reference, &result).await;
+        result
+    }
+
+    async fn create_token_batch(
+        &self,
+        batch_id: String,
+        invoice_numbers: Vec<String>,
+        stable_token_address: String,
+        min_term_str: String,
+        max_term_str: String,
+        interest_rate_str: String,
+    ) -> Result<Option<TransactionReceipt>> {
+        let reference = batch_id.clone();
+        let result = self
+            .inner
+            .create_token_batch(batch_id, invoice_numbers, stable_token_address, min_term_str, max_term_str, interest_rate_str)
+            .await;
+        self.record("create_token_batch", &reference, &result).await;
+        result
+    }
+
+    async fn confirm_token_batch_issue(&self, batch_id: String) -> Result<Option<TransactionReceipt>> {
+        let reference = batch_id.clone();
+        let result = self.inner.confirm_token_batch_issue(batch_id).await;
+        self.record("confirm_token_batch_issue", &reference, &result).await;
+        result
+    }
+
+    async fn purchase_shares(&self, batch_id: String, recipient: String, amount_str: String) -> Result<Option<TransactionReceipt>> {
+        let reference = batch_id.clone();
+        let result = self.inner.purchase_shares(batch_id, recipient, amount_str).await;
+        self.record("purchase_shares", &reference, &result).await;
+        result
+    }
+
+    async fn distribute_repayment(&self, batch_id: String, holders: Vec<String>, amounts: Vec<String>) -> Result<Option<TransactionReceipt>> {
+        let reference = batch_id.clone();
+        let result = self.inner.distribute_repayment(batch_id, holders, amounts).await;
+        self.record("distribute_repayment", &reference, &result).await;
+        result
+    }
+
+    async fn invalidate_invoice(&self, invoice_number: String) -> Result<Option<TransactionReceipt>> {
+        let reference = invoice_number.clone();
+        let result = self.inner.invalidate_invoice(invoice_number).await;
+        self.record("invalidate_invoice", &reference, &result).await;
+        result
+    }
+}
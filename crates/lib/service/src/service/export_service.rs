@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::io::{Seek, Write};
+
+use tracing::info;
+use mongodb::{Database, bson::oid::ObjectId};
+use serde::Serialize;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use common::domain::entity::{AuditLog, Enterprise, Transaction, TransactionType};
+use common::domain::entity::audit_log::SYSTEM_ACTOR;
+use crate::error::ServiceError;
+use crate::repository::{AuditLogRepository, EnterpriseRepository, InvoiceRepository, TransactionRepository};
+
+/// 企业数据导出 (审计用)
+///
+/// 导出该企业作为收款方的票据、票据上的认购与兑付记录以及相关审计日志，不包含其他企业的数据。
+/// `redact_investors` 为 true 时投资人地址与审计日志的操作人替换为导出内编号 (investor-1, investor-2 ...)，
+/// 交易元数据和审计详情不导出。
+///
+/// 归档逐条写入 `writer` (接口中为内存中的 `Cursor<Vec<u8>>`)，认购、兑付和审计记录按票据分批读取，查询结果不会整体载入内存。
+pub struct EnterpriseExportService {
+    enterprise_repo: EnterpriseRepository,
+    invoice_repo: InvoiceRepository,
+    transaction_repo: TransactionRepository,
+    audit_repo: AuditLogRepository,
+}
+
+#[derive(Serialize)]
+struct ExportManifest<'a> {
+    enterprise_id: String,
+    generated_at: String,
+    redacted_investors: bool,
+    invoices: usize,
+    purchases: usize,
+    settlements: usize,
+    audit_entries: usize,
+    files: &'a [&'a str],
+}
+
+const EXPORT_FILES: &[&str] = &["enterprise.json", "invoices.json", "purchases.json", "settlements.json", "audit_logs.json"];
+
+impl EnterpriseExportService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            enterprise_repo: EnterpriseRepository::new(db),
+            invoice_repo: InvoiceRepository::new(db),
+            transaction_repo: TransactionRepository::new(db),
+            audit_repo: AuditLogRepository::new(db),
+        }
+    }
+
+    pub async fn find_enterprise(&self, id: ObjectId) -> Result<Option<Enterprise>, ServiceError> {
+        Ok(self.enterprise_repo.find_by_id(id).await?)
+    }
+
+    /// 将 ZIP 归档写入 `writer` 并返回，清单 (manifest.json) 最后写入
+    pub async fn export_zip<W: Write + Seek>(&self, enterprise: &Enterprise, redact_investors: bool, writer: W) -> Result<W, ServiceError> {
+        let enterprise_id = enterprise.id.map(|id| id.to_hex()).unwrap_or_default();
+        let invoices = self.invoice_repo.find_by_user(&enterprise.wallet_address).await?;
+        let mut pseudonyms = redact_investors.then(|| InvestorPseudonyms::new(&enterprise.wallet_address));
+
+        let mut zip = ZipWriter::new(writer);
+        write_json(&mut zip, "enterprise.json", enterprise)?;
+        write_json(&mut zip, "invoices.json", &invoices)?;
+
+        let mut purchases = JsonArray::start(&mut zip, "purchases.json")?;
+        for invoice_id in invoices.iter().filter_map(|invoice| invoice.id) {
+            for tx in self.invoice_transactions(invoice_id, TransactionType::Purchase).await? {
+                purchases.push(&mut zip, &redact_transaction(tx, pseudonyms.as_mut()))?;
+            }
+        }
+        let purchases = purchases.finish(&mut zip)?;
+
+        let mut settlements = JsonArray::start(&mut zip, "settlements.json")?;
+        for invoice_id in invoices.iter().filter_map(|invoice| invoice.id) {
+            for tx in self.invoice_transactions(invoice_id, TransactionType::MaturityPayment).await? {
+                settlements.push(&mut zip, &redact_transaction(tx, pseudonyms.as_mut()))?;
+            }
+        }
+        let settlements = settlements.finish(&mut zip)?;
+
+        let mut audit_logs = JsonArray::start(&mut zip, "audit_logs.json")?;
+        for entry in self.audit_repo.find_by_target("enterprise", &enterprise_id).await? {
+            audit_logs.push(&mut zip, &redact_audit(entry, pseudonyms.as_mut()))?;
+        }
+        for invoice_id in invoices.iter().filter_map(|invoice| invoice.id) {
+            for entry in self.audit_repo.find_by_target("invoice", &invoice_id.to_hex()).await? {
+                audit_logs.push(&mut zip, &redact_audit(entry, pseudonyms.as_mut()))?;
+            }
+        }
+        let audit_entries = audit_logs.finish(&mut zip)?;
+
+        let manifest = ExportManifest {
+            enterprise_id: enterprise_id.clone(),
+            generated_at: chrono::Utc::now().to_rfc3339(),
+            redacted_investors: redact_investors,
+            invoices: invoices.len(),
+            purchases,
+            settlements,
+            audit_entries,
+            files: EXPORT_FILES,
+        };
+        write_json(&mut zip, "manifest.json", &manifest)?;
+        info!(
+            "Exported enterprise {}: {} invoices, {} purchases, {} settlements, {} audit entries",
+            enterprise_id, manifest.invoices, manifest.purchases, manifest.settlements, manifest.audit_entries
+        );
+        zip.finish().map_err(|e| ServiceError::InternalError(format!("Failed to finish export archive: {}", e)))
+    }
+
+    // 每次只读取一张票据的交易
+    async fn invoice_transactions(&self, invoice_id: ObjectId, transaction_type: TransactionType) -> Result<Vec<Transaction>, ServiceError> {
+        let transactions = self.transaction_repo.find_by_invoice_id(invoice_id).await?;
+        Ok(transactions.into_iter().filter(|tx| tx.transaction_type == transaction_type).collect())
+    }
+}
+
+fn file_options() -> SimpleFileOptions {
+    SimpleFileOptions::default().compression_method(CompressionMethod::Deflated).large_file(true)
+}
+
+fn archive_error(name: &str, e: impl std::fmt::Display) -> ServiceError {
+    ServiceError::InternalError(format!("Failed to write {} to export archive: {}", name, e))
+}
+
+fn write_json<W: Write + Seek, T: Serialize + ?Sized>(zip: &mut ZipWriter<W>, name: &str, value: &T) -> Result<(), ServiceError> {
+    zip.start_file(name, file_options()).map_err(|e| archive_error(name, e))?;
+    serde_json::to_writer_pretty(&mut *zip, value)?;
+    Ok(())
+}
+
+/// 逐条写入归档内的 JSON 数组文件
+struct JsonArray {
+    name: &'static str,
+    count: usize,
+}
+
+impl JsonArray {
+    fn start<W: Write + Seek>(zip: &mut ZipWriter<W>, name: &'static str) -> Result<Self, ServiceError> {
+        zip.start_file(name, file_options()).map_err(|e| archive_error(name, e))?;
+        zip.write_all(b"[").map_err(|e| archive_error(name, e))?;
+        Ok(Self { name, count: 0 })
+    }
+
+    fn push<W: Write + Seek, T: Serialize>(&mut self, zip: &mut ZipWriter<W>, value: &T) -> Result<(), ServiceError> {
+        let separator: &[u8] = if self.count == 0 { b"\n" } else { b",\n" };
+        zip.write_all(separator).map_err(|e| archive_error(self.name, e))?;
+        serde_json::to_writer_pretty(&mut *zip, value)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// 结束数组并返回写入的条数
+    fn finish<W: Write + Seek>(self, zip: &mut ZipWriter<W>) -> Result<usize, ServiceError> {
+        zip.write_all(b"\n]").map_err(|e| archive_error(self.name, e))?;
+        Ok(self.count)
+    }
+}
+
+fn redact_transaction(mut tx: Transaction, pseudonyms: Option<&mut InvestorPseudonyms>) -> Transaction {
+    if let Some(pseudonyms) = pseudonyms {
+        tx.user_id = pseudonyms.pseudonym(&tx.user_id);
+        tx.metadata = None;
+    }
+    tx
+}
+
+// 审计详情中可能包含投资人地址、原因等，脱敏导出时整体去除
+fn redact_audit(mut entry: AuditLog, pseudonyms: Option<&mut InvestorPseudonyms>) -> AuditLog {
+    if let Some(pseudonyms) = pseudonyms {
+        entry.actor = pseudonyms.pseudonym(&entry.actor);
+        entry.details = None;
+    }
+    entry
+}
+
+// 同一投资人在一次导出中使用同一个编号，便于对账但无法还原地址。系统任务和企业自身的钱包不替换
+struct InvestorPseudonyms {
+    enterprise_wallet: String,
+    assigned: HashMap<String, String>,
+}
+
+impl InvestorPseudonyms {
+    fn new(enterprise_wallet: &str) -> Self {
+        Self { enterprise_wallet: enterprise_wallet.to_lowercase(), assigned: HashMap::new() }
+    }
+
+    fn pseudonym(&mut self, address: &str) -> String {
+        let address = address.to_lowercase();
+        if address == SYSTEM_ACTOR || address == self.enterprise_wallet {
+            return address;
+        }
+        let next = self.assigned.len() + 1;
+        self.assigned
+            .entry(address)
+            .or_insert_with(|| format!("investor-{}", next))
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+    use mongodb::bson::doc;
+    use common::domain::entity::Invoice;
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn test_investor_pseudonyms_are_stable_within_export() {
+        let mut pseudonyms = InvestorPseudonyms::new("0xEnterprise");
+        assert_eq!(pseudonyms.pseudonym("0xAbC"), "investor-1");
+        assert_eq!(pseudonyms.pseudonym("0xdef"), "investor-2");
+        assert_eq!(pseudonyms.pseudonym("0xabc"), "investor-1");
+        assert_eq!(pseudonyms.pseudonym("system"), "system");
+        assert_eq!(pseudonyms.pseudonym("0xenterprise"), "0xenterprise");
+    }
+
+    #[test]
+    fn test_redacted_audit_entries_hide_actor_and_details() {
+        let entry = AuditLog::new("0xInvestor", "purchase", "invoice", "inv-1", Some(doc! { "wallet": "0xinvestor" }));
+        let mut pseudonyms = InvestorPseudonyms::new("0xenterprise");
+        let redacted = redact_audit(entry.clone(), Some(&mut pseudonyms));
+        assert_eq!(redacted.actor, "investor-1");
+        assert!(redacted.details.is_none());
+
+        let full = redact_audit(entry, None);
+        assert_eq!(full.actor, "0xInvestor");
+        assert!(full.details.is_some());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_export_matches_payee_case_insensitively() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let mut enterprise = Enterprise::new("ACME".to_string(), "0xABCDEF".to_string());
+        enterprise.id = Some(ObjectId::new());
+        let invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: "0xabcdef".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        test_db.collection::<Invoice>("invoices").insert_one(&invoice).await.unwrap();
+
+        let service = EnterpriseExportService::new(&test_db);
+        let archive = service.export_zip(&enterprise, true, Cursor::new(Vec::new())).await;
+        test_db.cleanup().await;
+
+        let mut archive = zip::ZipArchive::new(archive.unwrap()).unwrap();
+        let mut manifest = String::new();
+        archive.by_name("manifest.json").unwrap().read_to_string(&mut manifest).unwrap();
+        let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["invoices"], 1);
+        assert_eq!(manifest["purchases"], 0);
+        let mut purchases = String::new();
+        archive.by_name("purchases.json").unwrap().read_to_string(&mut purchases).unwrap();
+        assert_eq!(serde_json::from_str::<Vec<serde_json::Value>>(&purchases).unwrap(), Vec::<serde_json::Value>::new());
+    }
+}
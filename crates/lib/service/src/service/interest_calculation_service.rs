@@ -4,8 +4,10 @@ use anyhow::{Result, Context, anyhow};
 use chrono::{NaiveDate, TimeZone, Utc};
 use chrono::Datelike;
 use std::sync::Arc;
-use log::{info, error, warn};
+use tracing::{info, error, warn};
 use crate::error::ServiceError;
+use rust_decimal::Decimal;
+use common::utils::money::Money;
 
 use crate::repository::{
     DailyInterestAccrualRepository,
@@ -50,7 +52,7 @@ impl InterestCalculationService {
         
         // 当前年份是否是闰年
         let is_leap_year = date.year() % 4 == 0 && (date.year() % 100 != 0 || date.year() % 400 == 0);
-        let days_in_year = if is_leap_year { 366.0 } else { 365.0 };
+        let days_in_year = if is_leap_year { 366 } else { 365 };
         
         // 将日期转换为MongoDB的DateTime
         let start_of_day_naive = date.and_hms_opt(0, 0, 0).unwrap();
@@ -79,15 +81,15 @@ impl InterestCalculationService {
             };
             
             // 计算日利率：年利率 / 当年天数
-            let annual_rate = 0.0;
-            let daily_rate = annual_rate / days_in_year / 100.0; // 年利率是百分比，需要除以100
+            let annual_rate = Decimal::ZERO;
+            let daily_rate = annual_rate / Decimal::from(days_in_year) / Decimal::ONE_HUNDRED; // 年利率是百分比，需要除以100
             
             // 计算当日利息：本金 * 日利率
-            let principal = holding.current_balance.to_string().parse::<f64>().unwrap_or(0.0);
-            let daily_interest = principal * daily_rate;
+            let principal = Money::from_decimal128(&holding.current_balance)?;
+            let daily_interest = principal.checked_mul(daily_rate)?;
             
             // 转换为Decimal128
-            let daily_interest_decimal = Decimal128::from_str(&daily_interest.to_string())
+            let daily_interest_decimal = daily_interest.to_decimal128()
                 .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to parse daily interest '{}': {}", daily_interest, e)))?;
             
             // 创建日利息记录
@@ -148,21 +150,12 @@ impl InterestCalculationService {
             let holding_id = holding.holding_id.clone();
             
             // 计算到期兑付金额（本金 + 累计利息）
-            let principal = holding.purchase_amount.clone();
-            let accrued_interest = holding.total_accrued_interest.clone();
+            let principal = Money::from_decimal128(&holding.purchase_amount)?;
+            let accrued_interest = Money::from_decimal128(&holding.total_accrued_interest)?;
             
             // 计算总兑付金额
-            let maturity_amount = {
-                let p_str = principal.to_string();
-                let i_str = accrued_interest.to_string();
-                
-                let p = p_str.parse::<f64>().unwrap_or(0.0);
-                let i = i_str.parse::<f64>().unwrap_or(0.0);
-                
-                let total = p + i;
-                Decimal128::from_str(&total.to_string())
-                    .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to parse maturity amount '{}': {}", total, e)))?
-            };
+            let maturity_amount = principal.checked_add(accrued_interest)?.to_decimal128()
+                .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to convert maturity amount for holding {}: {}", holding_id, e)))?;
             
             // 创建到期兑付的交易记录
             let transaction = Transaction::new_maturity_payment(
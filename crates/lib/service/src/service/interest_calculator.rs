@@ -0,0 +1,143 @@
+use chrono::{Datelike, NaiveDate};
+use configs::cfgs::{Compounding, DayCountConvention};
+use rust_decimal::Decimal;
+
+/// 利息计算器，日计数规则由配置决定，应计利息接口、每日计息任务与兑付预估共用
+#[derive(Debug, Clone, Copy)]
+pub struct InterestCalculator {
+    /// 年化利率 (小数形式，如 0.08)
+    annual_rate: Decimal,
+}
+
+impl InterestCalculator {
+    pub fn new(annual_rate: Decimal) -> Self {
+        Self { annual_rate }
+    }
+
+    /// 按配置的日计数规则与计息方式计算 `[from, to)` 的应计利息；
+    /// 复利按整年滚动，不足一年的部分按单利计入
+    pub fn accrue(&self, principal: Decimal, from: NaiveDate, to: NaiveDate, convention: DayCountConvention, compounding: Compounding) -> Decimal {
+        let days = day_count(convention, from, to);
+        if days <= 0 {
+            return Decimal::ZERO;
+        }
+        let basis = year_basis(convention);
+        match compounding {
+            Compounding::Simple => principal * self.annual_rate * Decimal::from(days) / Decimal::from(basis),
+            Compounding::Compound => {
+                let mut growth = Decimal::ONE;
+                for _ in 0..days / basis {
+                    growth *= Decimal::ONE + self.annual_rate;
+                }
+                let stub = self.annual_rate * Decimal::from(days % basis) / Decimal::from(basis);
+                principal * (growth * (Decimal::ONE + stub) - Decimal::ONE)
+            }
+        }
+    }
+}
+
+/// 按日计数规则计算 `[from, to)` 的计息天数
+pub fn day_count(convention: DayCountConvention, from: NaiveDate, to: NaiveDate) -> i64 {
+    if to <= from {
+        return 0;
+    }
+    match convention {
+        DayCountConvention::Act365 => (to - from).num_days(),
+        DayCountConvention::Thirty360 => {
+            // 起息日为 31 日按 30 日计；起息日为 30/31 日时结息日的 31 日也按 30 日计
+            let d1 = from.day().min(30) as i64;
+            let d2 = if to.day() == 31 && d1 == 30 { 30 } else { to.day() as i64 };
+            360 * (to.year() - from.year()) as i64 + 30 * (to.month() as i64 - from.month() as i64) + (d2 - d1)
+        }
+    }
+}
+
+/// 每日计息任务记入 `date` 当天的计息天数 (30/360 下月末 31 日为 0 天)
+pub fn daily_day_count(convention: DayCountConvention, date: NaiveDate) -> i64 {
+    date.succ_opt().map_or(0, |next| day_count(convention, date, next))
+}
+
+pub fn year_basis(convention: DayCountConvention) -> i64 {
+    match convention {
+        DayCountConvention::Act365 => 365,
+        DayCountConvention::Thirty360 => 360,
+    }
+}
+
+/// 票据到期日 `due_date` 历史数据中既有秒也有毫秒，统一转换为毫秒
+pub fn due_date_to_millis(due_date: i64) -> i64 {
+    if due_date.abs() < 100_000_000_000 { due_date * 1000 } else { due_date }
+}
+
+pub fn due_date_to_naive(due_date: i64) -> Option<NaiveDate> {
+    chrono::DateTime::from_timestamp_millis(due_date_to_millis(due_date)).map(|dt| dt.date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_daily_day_count_matches_period_count() {
+        assert_eq!(daily_day_count(DayCountConvention::Act365, date(2024, 2, 29)), 1);
+        assert_eq!(daily_day_count(DayCountConvention::Thirty360, date(2024, 1, 30)), 0);
+        assert_eq!(daily_day_count(DayCountConvention::Thirty360, date(2024, 2, 29)), 2);
+        // 按日累加与整段计息天数一致
+        for convention in [DayCountConvention::Act365, DayCountConvention::Thirty360] {
+            let (from, to) = (date(2024, 1, 15), date(2024, 4, 15));
+            let daily: i64 = from.iter_days().take_while(|d| *d < to).map(|d| daily_day_count(convention, d)).sum();
+            assert_eq!(daily, day_count(convention, from, to));
+        }
+    }
+
+    #[test]
+    fn test_accrue_simple_act365_partial_period() {
+        let calc = InterestCalculator::new(dec!(0.08));
+        // 2024-01-15 ~ 2024-04-15 实际 91 天
+        assert_eq!(day_count(DayCountConvention::Act365, date(2024, 1, 15), date(2024, 4, 15)), 91);
+        let interest = calc.accrue(dec!(1000000), date(2024, 1, 15), date(2024, 4, 15), DayCountConvention::Act365, Compounding::Simple);
+        assert_eq!(interest.round_dp(6), dec!(19945.205479));
+        // ACT/365 固定分母，闰年整年 366 天略多于一年利息
+        let leap = InterestCalculator::new(dec!(0.0732)).accrue(dec!(1000), date(2024, 1, 1), date(2025, 1, 1), DayCountConvention::Act365, Compounding::Simple);
+        assert_eq!(leap.round_dp(6), dec!(73.400548));
+    }
+
+    #[test]
+    fn test_accrue_simple_thirty360_partial_period() {
+        let calc = InterestCalculator::new(dec!(0.08));
+        let interest = calc.accrue(dec!(1000000), date(2024, 1, 15), date(2024, 4, 15), DayCountConvention::Thirty360, Compounding::Simple);
+        assert_eq!(interest, dec!(20000));
+        // 月末 31 日按 30 日计
+        assert_eq!(day_count(DayCountConvention::Thirty360, date(2024, 1, 31), date(2024, 3, 31)), 60);
+        assert_eq!(day_count(DayCountConvention::Thirty360, date(2024, 1, 30), date(2024, 2, 29)), 29);
+        assert_eq!(day_count(DayCountConvention::Thirty360, date(2024, 2, 29), date(2024, 3, 31)), 32);
+        let month_end = calc.accrue(dec!(1000000), date(2024, 1, 31), date(2024, 3, 31), DayCountConvention::Thirty360, Compounding::Simple);
+        assert_eq!(month_end.round_dp(2), dec!(13333.33));
+    }
+
+    #[test]
+    fn test_accrue_compound_with_stub_period() {
+        let calc = InterestCalculator::new(dec!(0.1));
+        // 547 天 = 1 整年 + 182/365
+        let act = calc.accrue(dec!(1000), date(2023, 1, 1), date(2024, 7, 1), DayCountConvention::Act365, Compounding::Compound);
+        assert_eq!(act.round_dp(6), dec!(154.849315));
+        // 810 天 (30/360) = 2 整年 + 0.25 年
+        let thirty = calc.accrue(dec!(1000), date(2023, 1, 15), date(2025, 4, 15), DayCountConvention::Thirty360, Compounding::Compound);
+        assert_eq!(thirty, dec!(240.25));
+        // 不足一年时与单利一致
+        let short = calc.accrue(dec!(1000), date(2024, 1, 15), date(2024, 4, 15), DayCountConvention::Thirty360, Compounding::Compound);
+        assert_eq!(short, dec!(25));
+        assert_eq!(calc.accrue(dec!(1000), date(2024, 4, 15), date(2024, 1, 15), DayCountConvention::Act365, Compounding::Compound), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_due_date_seconds_or_millis() {
+        assert_eq!(due_date_to_naive(1704067200), Some(date(2024, 1, 1)));
+        assert_eq!(due_date_to_naive(1704067200000), Some(date(2024, 1, 1)));
+    }
+}
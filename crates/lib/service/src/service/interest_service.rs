@@ -0,0 +1,404 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use mongodb::Database;
+use mongodb::bson::{DateTime, oid::ObjectId};
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use common::domain::dto::accrued_interest_dto::AccruedInterestDto;
+use common::domain::dto::payment_schedule_dto::{PaymentScheduleDto, ScheduledPaymentDto};
+use common::domain::dto::portfolio_interest_dto::{PortfolioInterestDto, PositionInterestDto};
+use common::domain::entity::{AuditLog, HoldingStatus, Invoice, TransactionType};
+use common::domain::entity::invoice_status::InvoiceStatus;
+use common::utils::money::Money;
+use configs::cfgs::{Compounding, DayCountConvention, Interest, PaymentFrequency};
+use crate::error::ServiceError;
+use crate::repository::{InvoiceRepository, TransactionRepository, UserInvoiceHoldingRepository};
+use crate::service::interest_calculator::{InterestCalculator, day_count, due_date_to_naive};
+use crate::service::payment_schedule::{ScheduleTerms, ScheduledPayment, build_schedule};
+
+/// 融资完成时写入的审计动作
+const FINANCE_ACTION: &str = "finance";
+
+/// 票据应计利息与还款计划：以融资日为起息日、票据金额为本金、已接受条款中的年化利率计息，到期或还款后不再计息。
+/// 日计数规则与每日计息任务相同，均取 `[interest] day_count`
+pub struct InterestService {
+    invoice_repo: InvoiceRepository,
+    holding_repo: UserInvoiceHoldingRepository,
+    transaction_repo: TransactionRepository,
+    day_count: DayCountConvention,
+    compounding: Compounding,
+    payment_frequency: PaymentFrequency,
+    /// 还款计划金额精度，与稳定币精度一致
+    scale: u32,
+}
+
+/// 票据的计息条款
+#[derive(Debug, Clone, Copy)]
+struct FinancingTerms {
+    principal: Decimal,
+    annual_rate: Decimal,
+    financed_on: NaiveDate,
+    /// 转入已兑付的日期，之后不再计息
+    repaid_on: Option<NaiveDate>,
+}
+
+/// 整张票据的应计利息，尚未融资的票据为 0 且没有计息截止日
+#[derive(Debug, Clone, Copy)]
+struct InvoiceAccrual {
+    principal: Decimal,
+    interest: Decimal,
+    accrued_to: Option<NaiveDate>,
+}
+
+/// 一次计息的输入，与存储无关，便于单独测试
+#[derive(Debug, Clone, Copy)]
+struct AccrualPeriod {
+    principal: Decimal,
+    annual_rate: Decimal,
+    financed_on: NaiveDate,
+    accrued_to: NaiveDate,
+}
+
+impl InterestService {
+    pub fn new(db: &Database, config: &Interest, scale: u32) -> Self {
+        Self {
+            invoice_repo: InvoiceRepository::new(db),
+            holding_repo: UserInvoiceHoldingRepository::new(db),
+            transaction_repo: TransactionRepository::new(db),
+            day_count: config.day_count,
+            compounding: config.compounding,
+            payment_frequency: config.payment_frequency,
+            scale,
+        }
+    }
+
+    pub async fn find_invoice(&self, invoice_id: ObjectId) -> Result<Invoice, ServiceError> {
+        self.invoice_repo
+            .find_by_id(invoice_id)
+            .await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))
+    }
+
+    /// 截至 `as_of` 的应计利息
+    pub async fn accrued_interest(&self, invoice: &Invoice, as_of: DateTime) -> Result<Decimal, ServiceError> {
+        let period = self.accrual_period(invoice, as_of).await?;
+        Ok(self.accrue(&period))
+    }
+
+    /// 截至 `as_of` 的应计利息及计息参数
+    pub async fn accrued_interest_detail(&self, invoice: &Invoice, as_of: DateTime) -> Result<AccruedInterestDto, ServiceError> {
+        let period = self.accrual_period(invoice, as_of).await?;
+        Ok(AccruedInterestDto {
+            invoice_id: invoice.id.map(|id| id.to_hex()).unwrap_or_default(),
+            invoice_number: invoice.invoice_number.clone(),
+            principal: period.principal.to_string(),
+            annual_rate: period.annual_rate.to_string(),
+            day_count: day_count_label(self.day_count).to_string(),
+            compounding: compounding_label(self.compounding).to_string(),
+            financed_on: period.financed_on,
+            accrued_to: period.accrued_to,
+            accrual_days: day_count(self.day_count, period.financed_on, period.accrued_to),
+            accrued_interest: self.accrue(&period).to_string(),
+        })
+    }
+
+    /// 投资人全部活跃持仓截至 `as_of` 的应计利息。
+    /// 每个持仓按认购金额占票据金额的比例分得整张票据的应计利息，尚未融资的票据不计息
+    pub async fn portfolio_interest(&self, user_address: &str, as_of: DateTime) -> Result<PortfolioInterestDto, ServiceError> {
+        let holdings: Vec<_> = self.holding_repo.find_by_user_id(user_address).await?
+            .into_iter()
+            .filter(|holding| holding.holding_status == HoldingStatus::Active)
+            .collect();
+        let mut invoice_ids: Vec<ObjectId> = holdings.iter().map(|holding| holding.invoice_id).collect();
+        invoice_ids.sort();
+        invoice_ids.dedup();
+
+        // 同一票据的多个持仓共用一次计息
+        let mut invoices = HashMap::new();
+        for invoice in self.invoice_repo.find_by_ids(&invoice_ids).await? {
+            let Some(invoice_id) = invoice.id else { continue };
+            let accrual = match self.accrual_period(&invoice, as_of).await {
+                Ok(period) => InvoiceAccrual { principal: period.principal, interest: self.accrue(&period), accrued_to: Some(period.accrued_to) },
+                Err(ServiceError::InvoiceNotFinanced(_) | ServiceError::TermsNotAccepted(_)) => {
+                    InvoiceAccrual { principal: Decimal::from(invoice.amount), interest: Decimal::ZERO, accrued_to: None }
+                }
+                Err(e) => return Err(e),
+            };
+            invoices.insert(invoice_id, (invoice.invoice_number, accrual));
+        }
+
+        let (mut total_purchase, mut total_interest) = (Decimal::ZERO, Decimal::ZERO);
+        let mut positions = Vec::with_capacity(holdings.len());
+        for holding in &holdings {
+            let Some((invoice_number, accrual)) = invoices.get(&holding.invoice_id) else {
+                tracing::warn!("Holding {} references missing invoice {}", holding.holding_id, holding.invoice_id);
+                continue;
+            };
+            let purchase_amount = Money::from_decimal128(&holding.purchase_amount)?.amount();
+            let (ownership, interest) = weighted_interest(accrual, purchase_amount, self.scale);
+            total_purchase += purchase_amount;
+            total_interest += interest;
+            positions.push(PositionInterestDto {
+                holding_id: holding.holding_id.clone(),
+                invoice_id: holding.invoice_id.to_hex(),
+                invoice_number: invoice_number.clone(),
+                purchase_amount: purchase_amount.normalize().to_string(),
+                ownership: ownership.normalize().to_string(),
+                invoice_accrued_interest: accrual.interest.to_string(),
+                accrued_interest: interest.normalize().to_string(),
+                accrued_to: accrual.accrued_to,
+            });
+        }
+
+        Ok(PortfolioInterestDto {
+            as_of: utc_date(as_of),
+            total_purchase_amount: total_purchase.normalize().to_string(),
+            total_accrued_interest: total_interest.normalize().to_string(),
+            positions,
+        })
+    }
+
+    /// 自融资日至到期日的预计还款计划
+    pub async fn payment_schedule(&self, invoice: &Invoice) -> Result<Vec<ScheduledPayment>, ServiceError> {
+        let terms = self.financing_terms(invoice).await?;
+        self.schedule(invoice, &terms)
+    }
+
+    /// 还款计划及汇总
+    pub async fn payment_schedule_detail(&self, invoice: &Invoice) -> Result<PaymentScheduleDto, ServiceError> {
+        let terms = self.financing_terms(invoice).await?;
+        let payments = self.schedule(invoice, &terms)?;
+        Ok(PaymentScheduleDto {
+            invoice_id: invoice.id.map(|id| id.to_hex()).unwrap_or_default(),
+            invoice_number: invoice.invoice_number.clone(),
+            frequency: frequency_label(self.payment_frequency).to_string(),
+            financed_on: terms.financed_on,
+            maturity_date: maturity_date(invoice)?,
+            total_principal: payments.iter().map(|p| p.principal).sum::<Decimal>().to_string(),
+            total_interest: payments.iter().map(|p| p.interest).sum::<Decimal>().to_string(),
+            payments: payments.iter().map(ScheduledPaymentDto::from).collect(),
+        })
+    }
+
+    fn schedule(&self, invoice: &Invoice, terms: &FinancingTerms) -> Result<Vec<ScheduledPayment>, ServiceError> {
+        Ok(build_schedule(&ScheduleTerms {
+            principal: terms.principal,
+            annual_rate: terms.annual_rate,
+            start: terms.financed_on,
+            maturity: maturity_date(invoice)?,
+            frequency: self.payment_frequency,
+            day_count: self.day_count,
+            compounding: self.compounding,
+            scale: self.scale,
+        }))
+    }
+
+    fn accrue(&self, period: &AccrualPeriod) -> Decimal {
+        InterestCalculator::new(period.annual_rate).accrue(
+            period.principal,
+            period.financed_on,
+            period.accrued_to,
+            self.day_count,
+            self.compounding,
+        )
+    }
+
+    async fn accrual_period(&self, invoice: &Invoice, as_of: DateTime) -> Result<AccrualPeriod, ServiceError> {
+        let terms = self.financing_terms(invoice).await?;
+        Ok(AccrualPeriod {
+            principal: terms.principal,
+            annual_rate: terms.annual_rate,
+            financed_on: terms.financed_on,
+            accrued_to: accrual_end(utc_date(as_of), due_date_to_naive(invoice.due_date), terms.repaid_on),
+        })
+    }
+
+    /// 本金取票据金额，利率取已接受条款中的年化利率，起息日取 "finance" 审计记录时间，
+    /// 还款日取首条转入已兑付的审计记录时间
+    async fn financing_terms(&self, invoice: &Invoice) -> Result<FinancingTerms, ServiceError> {
+        let invoice_id = invoice.id.ok_or_else(|| ServiceError::InvoiceNotFound(invoice.invoice_number.clone()))?;
+        let terms = invoice
+            .accepted_terms
+            .as_ref()
+            .ok_or_else(|| ServiceError::TermsNotAccepted(invoice.invoice_number.clone()))?;
+        let annual_rate = Decimal::from_str(&terms.apr)
+            .map_err(|e| ServiceError::DecimalConversionError(format!("'{}': {}", terms.apr, e)))?;
+
+        let audits = self.invoice_repo.find_audit_trail(invoice_id).await?;
+        let financed_at = match audits.iter().find(|audit| audit.action == FINANCE_ACTION) {
+            Some(audit) => Some(audit.created_at),
+            // 审计记录上线前融资的票据没有 "finance" 记录，以售罄 (最后一笔认购) 时间作为起息日
+            None if is_financed(invoice.status) => self.last_purchase_at(invoice_id).await?,
+            None => None,
+        };
+        let financed_at = financed_at.ok_or_else(|| ServiceError::InvoiceNotFinanced(invoice.invoice_number.clone()))?;
+
+        Ok(FinancingTerms {
+            principal: Decimal::from(invoice.amount),
+            annual_rate,
+            financed_on: utc_date(financed_at),
+            repaid_on: repaid_on(&audits),
+        })
+    }
+
+    async fn last_purchase_at(&self, invoice_id: ObjectId) -> Result<Option<DateTime>, ServiceError> {
+        Ok(self.transaction_repo.find_by_invoice_id(invoice_id).await?
+            .into_iter()
+            .filter(|tx| tx.transaction_type == TransactionType::Purchase)
+            .map(|tx| tx.transaction_date)
+            .max())
+    }
+}
+
+impl From<&ScheduledPayment> for ScheduledPaymentDto {
+    fn from(payment: &ScheduledPayment) -> Self {
+        Self {
+            installment: payment.installment,
+            due_date: payment.due_date,
+            principal: payment.principal.to_string(),
+            interest: payment.interest.to_string(),
+            remaining_balance: payment.remaining_balance.to_string(),
+        }
+    }
+}
+
+fn maturity_date(invoice: &Invoice) -> Result<NaiveDate, ServiceError> {
+    due_date_to_naive(invoice.due_date).ok_or_else(|| ServiceError::InternalError(format!("Invalid due date: {}", invoice.due_date)))
+}
+
+/// 融资完成后的状态 (含已兑付、逾期、违约)
+fn is_financed(status: InvoiceStatus) -> bool {
+    matches!(status, InvoiceStatus::Financed | InvoiceStatus::Repaid | InvoiceStatus::Overdue | InvoiceStatus::Defaulted)
+}
+
+fn utc_date(at: DateTime) -> NaiveDate {
+    chrono::DateTime::from_timestamp_millis(at.timestamp_millis()).unwrap_or_default().date_naive()
+}
+
+/// 首条转入已兑付的审计记录日期；审计上线前兑付的票据没有记录，计息截止到到期日
+fn repaid_on(audits: &[AuditLog]) -> Option<NaiveDate> {
+    audits.iter().find(|audit| audit.next_status() == Some(InvoiceStatus::Repaid)).map(|audit| utc_date(audit.created_at))
+}
+
+/// 计息截止日：查询日、到期日与还款日取最早者
+fn accrual_end(as_of: NaiveDate, maturity: Option<NaiveDate>, repaid_on: Option<NaiveDate>) -> NaiveDate {
+    [maturity, repaid_on].into_iter().flatten().fold(as_of, NaiveDate::min)
+}
+
+/// 持仓占票据本金的比例 (不超过 1) 及分得的利息，利息按 `scale` 位小数向下取整，合计不会超过整张票据的利息
+fn weighted_interest(accrual: &InvoiceAccrual, purchase_amount: Decimal, scale: u32) -> (Decimal, Decimal) {
+    if accrual.principal <= Decimal::ZERO || purchase_amount <= Decimal::ZERO {
+        return (Decimal::ZERO, Decimal::ZERO);
+    }
+    let purchase_amount = purchase_amount.min(accrual.principal);
+    let ownership = purchase_amount / accrual.principal;
+    // 先乘后除，减少比例截断带来的误差
+    let interest = (accrual.interest * purchase_amount / accrual.principal).round_dp_with_strategy(scale, RoundingStrategy::ToZero);
+    (ownership, interest)
+}
+
+fn day_count_label(convention: DayCountConvention) -> &'static str {
+    match convention {
+        DayCountConvention::Act365 => "ACT/365",
+        DayCountConvention::Thirty360 => "30/360",
+    }
+}
+
+fn frequency_label(frequency: PaymentFrequency) -> &'static str {
+    match frequency {
+        PaymentFrequency::Monthly => "monthly",
+        PaymentFrequency::Quarterly => "quarterly",
+        PaymentFrequency::Bullet => "bullet",
+    }
+}
+
+fn compounding_label(compounding: Compounding) -> &'static str {
+    match compounding {
+        Compounding::Simple => "simple",
+        Compounding::Compound => "compound",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_accrual_stops_at_maturity() {
+        assert_eq!(accrual_end(date(2024, 3, 1), Some(date(2024, 6, 30)), None), date(2024, 3, 1));
+        assert_eq!(accrual_end(date(2024, 9, 1), Some(date(2024, 6, 30)), None), date(2024, 6, 30));
+        assert_eq!(accrual_end(date(2024, 9, 1), None, None), date(2024, 9, 1));
+    }
+
+    fn audit_at(action: &str, next: InvoiceStatus, at: NaiveDate) -> AuditLog {
+        let mut audit = AuditLog::invoice(ObjectId::new(), "0xadmin", action, Some(InvoiceStatus::Financed), next);
+        audit.created_at = DateTime::from_millis(at.and_hms_opt(12, 0, 0).unwrap().and_utc().timestamp_millis());
+        audit
+    }
+
+    #[test]
+    fn test_accrual_stops_at_repayment() {
+        let audits = vec![
+            audit_at("finance", InvoiceStatus::Financed, date(2024, 1, 15)),
+            audit_at("settle", InvoiceStatus::Repaid, date(2024, 4, 15)),
+        ];
+        let repaid = repaid_on(&audits);
+        assert_eq!(repaid, Some(date(2024, 4, 15)));
+        assert_eq!(accrual_end(date(2024, 9, 1), Some(date(2024, 6, 30)), repaid), date(2024, 4, 15));
+        assert_eq!(accrual_end(date(2024, 3, 1), Some(date(2024, 6, 30)), repaid), date(2024, 3, 1));
+        assert_eq!(repaid_on(&audits[..1]), None);
+    }
+
+    #[test]
+    fn test_only_financed_statuses_fall_back_to_purchases() {
+        assert!(is_financed(InvoiceStatus::Financed));
+        assert!(is_financed(InvoiceStatus::Repaid));
+        assert!(!is_financed(InvoiceStatus::OnSale));
+        assert!(!is_financed(InvoiceStatus::Verified));
+    }
+
+    fn accrual(principal: i64, interest: &str) -> InvoiceAccrual {
+        InvoiceAccrual { principal: Decimal::from(principal), interest: Decimal::from_str(interest).unwrap(), accrued_to: Some(date(2024, 6, 30)) }
+    }
+
+    #[test]
+    fn test_portfolio_weights_interest_by_ownership() {
+        let dec = |s: &str| Decimal::from_str(s).unwrap();
+        let a = accrual(1_000, "12.34");
+        let b = accrual(3_000, "45");
+        let c = accrual(7, "1");
+
+        // 票据 A 持有 25%
+        assert_eq!(weighted_interest(&a, dec("250"), 2), (dec("0.25"), dec("3.08")));
+        // 票据 B 分两次认购 1/3 与 1/6
+        let (own_b1, b1) = weighted_interest(&b, dec("1000"), 2);
+        let (own_b2, b2) = weighted_interest(&b, dec("500"), 2);
+        assert_eq!((b1, b2), (dec("15"), dec("7.5")));
+        assert_eq!((own_b1 + own_b2).round_dp(6), dec("0.5"));
+        // 票据 C 持有 1/7，先乘后除再截断
+        let (own_c, c1) = weighted_interest(&c, dec("1"), 6);
+        assert_eq!(own_c.round_dp(6), dec("0.142857"));
+        assert_eq!(c1, dec("0.142857"));
+
+        let total = dec("3.08") + b1 + b2 + c1;
+        assert_eq!(total, dec("25.722857"));
+    }
+
+    #[test]
+    fn test_weighting_edge_cases() {
+        let dec = |s: &str| Decimal::from_str(s).unwrap();
+        // 全部份额分得全部利息，认购金额超过本金时按 100% 计
+        assert_eq!(weighted_interest(&accrual(1_000, "12.34"), dec("1000"), 2), (dec("1"), dec("12.34")));
+        assert_eq!(weighted_interest(&accrual(1_000, "12.34"), dec("1500"), 2).0, dec("1"));
+        // 未融资票据不计息
+        let unfinanced = InvoiceAccrual { principal: Decimal::from(1_000), interest: Decimal::ZERO, accrued_to: None };
+        assert_eq!(weighted_interest(&unfinanced, dec("500"), 2), (dec("0.5"), Decimal::ZERO));
+        assert_eq!(weighted_interest(&accrual(0, "1"), dec("500"), 2), (Decimal::ZERO, Decimal::ZERO));
+    }
+}
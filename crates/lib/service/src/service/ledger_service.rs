@@ -0,0 +1,146 @@
+use std::str::FromStr;
+
+use mongodb::Database;
+use mongodb::bson::oid::ObjectId;
+use rust_decimal::Decimal;
+
+use common::domain::dto::funding_ledger_dto::{FundingEventDto, FundingLedgerDto};
+use common::domain::entity::{Invoice, Transaction, TransactionType, UserRole};
+use crate::error::ServiceError;
+use crate::repository::{EnterpriseRepository, InvoiceRepository, TransactionRepository, UserRepository};
+
+/// 票据融资台账：由购买交易记录逐笔累加得出
+pub struct InvoiceLedgerService {
+    invoice_repo: InvoiceRepository,
+    transaction_repo: TransactionRepository,
+    user_repo: UserRepository,
+    enterprise_repo: EnterpriseRepository,
+}
+
+impl InvoiceLedgerService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            invoice_repo: InvoiceRepository::new(db),
+            transaction_repo: TransactionRepository::new(db),
+            user_repo: UserRepository::new(db),
+            enterprise_repo: EnterpriseRepository::new(db),
+        }
+    }
+
+    pub async fn find_invoice(&self, invoice_id: ObjectId) -> Result<Invoice, ServiceError> {
+        self.invoice_repo.find_by_id(invoice_id).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))
+    }
+
+    /// 平台管理员、出票企业钱包本身或绑定到出票企业的企业管理员可以查看
+    pub async fn can_view(&self, is_admin: bool, viewer_address: &str, invoice: &Invoice) -> Result<bool, ServiceError> {
+        if is_admin || invoice.payee.eq_ignore_ascii_case(viewer_address) {
+            return Ok(true);
+        }
+        let Some(user) = self.user_repo.find_by_wallet_address(viewer_address).await? else {
+            return Ok(false);
+        };
+        let Some(enterprise_id) = user.enterprise_id.filter(|_| user.role == UserRole::EnterpriseAdmin) else {
+            return Ok(false);
+        };
+        Ok(self.enterprise_repo.find_by_id(enterprise_id).await?
+            .is_some_and(|e| e.wallet_address.eq_ignore_ascii_case(&invoice.payee)))
+    }
+
+    pub async fn ledger(&self, invoice: &Invoice) -> Result<FundingLedgerDto, ServiceError> {
+        let invoice_id = invoice.id.ok_or_else(|| ServiceError::InternalError("Invoice without id".to_string()))?;
+        let purchases = self.transaction_repo.find_by_invoice_id(invoice_id).await?
+            .into_iter()
+            .filter(|tx| matches!(tx.transaction_type, TransactionType::Purchase) && tx.status == "completed")
+            .collect();
+        build_ledger(invoice, purchases)
+    }
+}
+
+/// 按 (认购时间, 记录 ID) 排序后逐笔累加，保证同一时刻的多笔认购顺序稳定
+fn build_ledger(invoice: &Invoice, mut purchases: Vec<Transaction>) -> Result<FundingLedgerDto, ServiceError> {
+    purchases.sort_by_key(|tx| (tx.transaction_date, tx.id));
+
+    let mut running_total = Decimal::ZERO;
+    let mut events = Vec::with_capacity(purchases.len());
+    for tx in purchases {
+        let amount = Decimal::from_str(&tx.amount.to_string())
+            .map_err(|e| ServiceError::DecimalConversionError(format!("Invalid amount {} in transaction {:?}: {}", tx.amount, tx.id, e)))?;
+        running_total += amount;
+        events.push(FundingEventDto {
+            transaction_id: tx.id.map(|id| id.to_hex()).unwrap_or_default(),
+            holding_id: tx.holding_id,
+            investor: tx.user_id,
+            amount: amount.normalize().to_string(),
+            timestamp: tx.transaction_date.timestamp_millis(),
+            running_total: running_total.normalize().to_string(),
+            tx_hash: tx.metadata.as_ref().and_then(|m| m.get_str("tx_hash").ok()).map(str::to_string),
+        });
+    }
+
+    let target = Decimal::from(invoice.amount);
+    Ok(FundingLedgerDto {
+        invoice_id: invoice.id.map(|id| id.to_hex()).unwrap_or_default(),
+        invoice_number: invoice.invoice_number.clone(),
+        currency: invoice.currency.clone(),
+        target_amount: target.to_string(),
+        funded_total: running_total.normalize().to_string(),
+        remaining: (target - running_total).max(Decimal::ZERO).normalize().to_string(),
+        events,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::{DateTime, Decimal128, doc};
+    use common::domain::entity::invoice_status::InvoiceStatus;
+
+    fn purchase(user: &str, amount: &str, at_ms: i64, tx_hash: Option<&str>) -> Transaction {
+        let mut tx = Transaction::new_purchase(user.to_string(), ObjectId::new(), format!("h-{}", user), Decimal128::from_str(amount).unwrap());
+        tx.id = Some(ObjectId::new());
+        tx.transaction_date = DateTime::from_millis(at_ms);
+        tx.metadata = tx_hash.map(|h| doc! { "tx_hash": h });
+        tx
+    }
+
+    #[test]
+    fn test_ledger_running_total_in_time_order() {
+        let invoice = Invoice {
+            id: Some(ObjectId::new()),
+            invoice_number: "INV-1".to_string(),
+            payee: "0xa".to_string(),
+            payer: "0xb".to_string(),
+            amount: 1000,
+            currency: "USDC".to_string(),
+            due_date: 0,
+            invoice_ipfs_hash: None,
+            contract_ipfs_hash: None,
+            status: InvoiceStatus::OnSale,
+            blockchain_timestamp: None,
+            token_batch: None,
+            is_cleared: None,
+            is_valid: None,
+            document_count: 0,
+            document_total_bytes: 0,
+            accepted_terms: None,
+            terms_exempt: false,
+            settlement_tx_hash: None,
+            funded_shares: 0,
+            version: 0,
+            deleted_at: None,
+            created_at: DateTime::from_millis(0),
+            updated_at: DateTime::from_millis(0),
+        };
+        let ledger = build_ledger(&invoice, vec![
+            purchase("bob", "250.50", 2_000, None),
+            purchase("alice", "300", 1_000, Some("0xabc")),
+        ]).unwrap();
+
+        let totals: Vec<_> = ledger.events.iter().map(|e| (e.investor.as_str(), e.running_total.as_str())).collect();
+        assert_eq!(totals, vec![("alice", "300"), ("bob", "550.5")]);
+        assert_eq!(ledger.events[0].tx_hash.as_deref(), Some("0xabc"));
+        assert_eq!(ledger.funded_total, "550.5");
+        assert_eq!(ledger.remaining, "449.5");
+    }
+}
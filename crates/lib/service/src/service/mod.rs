@@ -1,7 +1,36 @@
 pub mod interest_calculation_service;
+pub mod interest_calculator;
+pub mod interest_service;
+pub mod payment_schedule;
 pub mod purchase_service;
+pub mod purchase_history;
+pub mod repayment_split;
 pub mod token_service;
+pub mod stats_service;
+pub mod webhook_service;
+pub mod timeline_service;
+pub mod ledger_service;
+pub mod export_service;
+pub mod contract_recorder;
+pub mod transaction_service;
+pub mod transaction_listing;
+pub mod transfer_store;
+pub mod account_service;
+pub mod token_mint_service;
 
 pub use interest_calculation_service::InterestCalculationService;
+pub use interest_calculator::InterestCalculator;
+pub use interest_service::InterestService;
 pub use purchase_service::PurchaseService;
 pub use token_service::TokenService;
+pub use stats_service::StatsService;
+pub use webhook_service::WebhookService;
+pub use timeline_service::InvoiceTimelineService;
+pub use ledger_service::InvoiceLedgerService;
+pub use export_service::EnterpriseExportService;
+pub use contract_recorder::{RecordingContractWriter, SharedContractWriter};
+pub use transaction_service::{PendingTransactionTracker, TransactionPoller, TransactionPollerConfig, TransactionService};
+pub use transaction_listing::TransactionFilter;
+pub use transfer_store::MongoTransferStore;
+pub use account_service::{AccountDeletion, RoleChange, UserAccountService};
+pub use token_mint_service::{TokenMintConfig, TokenMintService};
@@ -0,0 +1,150 @@
+use chrono::{Months, NaiveDate};
+use configs::cfgs::{Compounding, DayCountConvention, PaymentFrequency};
+use rust_decimal::Decimal;
+
+use crate::service::interest_calculator::InterestCalculator;
+
+/// 还款计划中的一期
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledPayment {
+    /// 期数，从 1 开始
+    pub installment: u32,
+    pub due_date: NaiveDate,
+    pub principal: Decimal,
+    pub interest: Decimal,
+    /// 本期还款后剩余本金
+    pub remaining_balance: Decimal,
+}
+
+/// 生成还款计划所需的票据条款
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleTerms {
+    pub principal: Decimal,
+    pub annual_rate: Decimal,
+    /// 起息日 (融资日)
+    pub start: NaiveDate,
+    pub maturity: NaiveDate,
+    pub frequency: PaymentFrequency,
+    pub day_count: DayCountConvention,
+    pub compounding: Compounding,
+    /// 金额精度 (小数位)
+    pub scale: u32,
+}
+
+/// 生成还款计划
+///
+/// 按期等额还本，每期利息按期初剩余本金计算；各期金额按 `scale` 位小数四舍五入，
+/// 最后一期承担全部舍入差额：本金为剩余本金，利息为利息总额 (未舍入各期之和再舍入) 减去之前各期，
+/// 因此各期本金之和等于 `principal`，利息之和等于利息总额。
+pub fn build_schedule(terms: &ScheduleTerms) -> Vec<ScheduledPayment> {
+    let dates = due_dates(terms.start, terms.maturity, terms.frequency);
+    let count = dates.len();
+    let calc = InterestCalculator::new(terms.annual_rate);
+    let principal_step = (terms.principal / Decimal::from(count)).round_dp(terms.scale);
+
+    let mut schedule = Vec::with_capacity(count);
+    let mut balance = terms.principal;
+    let mut period_start = terms.start;
+    let mut exact_interest = Decimal::ZERO;
+    let mut scheduled_interest = Decimal::ZERO;
+    for (index, due_date) in dates.into_iter().enumerate() {
+        let period_interest = calc.accrue(balance, period_start, due_date, terms.day_count, terms.compounding);
+        exact_interest += period_interest;
+        let (principal, interest) = if index + 1 == count {
+            (balance, exact_interest.round_dp(terms.scale) - scheduled_interest)
+        } else {
+            (principal_step.min(balance), period_interest.round_dp(terms.scale))
+        };
+        balance -= principal;
+        scheduled_interest += interest;
+        schedule.push(ScheduledPayment { installment: index as u32 + 1, due_date, principal, interest, remaining_balance: balance });
+        period_start = due_date;
+    }
+    schedule
+}
+
+/// 各期还款日：从起息日按月/季度推算 (月末按当月最后一天)，最后一期固定为到期日，不足一期的尾期并入到期日
+fn due_dates(start: NaiveDate, maturity: NaiveDate, frequency: PaymentFrequency) -> Vec<NaiveDate> {
+    let step = match frequency {
+        PaymentFrequency::Monthly => 1,
+        PaymentFrequency::Quarterly => 3,
+        PaymentFrequency::Bullet => return vec![maturity],
+    };
+    let mut dates = Vec::new();
+    for period in 1.. {
+        match start.checked_add_months(Months::new(step * period)) {
+            Some(date) if date < maturity => dates.push(date),
+            _ => break,
+        }
+    }
+    dates.push(maturity);
+    dates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn terms(principal: Decimal, annual_rate: Decimal, start: NaiveDate, maturity: NaiveDate, frequency: PaymentFrequency, day_count: DayCountConvention, scale: u32) -> ScheduleTerms {
+        ScheduleTerms { principal, annual_rate, start, maturity, frequency, day_count, compounding: Compounding::Simple, scale }
+    }
+
+    #[test]
+    fn test_bullet_schedule_single_installment() {
+        // 182 天，1000000 * 0.08 * 182 / 365 = 39890.4109...
+        let schedule = build_schedule(&terms(dec!(1000000), dec!(0.08), date(2024, 1, 15), date(2024, 7, 15), PaymentFrequency::Bullet, DayCountConvention::Act365, 2));
+        assert_eq!(
+            schedule,
+            vec![ScheduledPayment {
+                installment: 1,
+                due_date: date(2024, 7, 15),
+                principal: dec!(1000000),
+                interest: dec!(39890.41),
+                remaining_balance: Decimal::ZERO,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_monthly_amortizing_schedule() {
+        // 30/360 下每期月利率 1%，按期初剩余本金计息
+        let schedule = build_schedule(&terms(dec!(1000), dec!(0.12), date(2024, 1, 15), date(2024, 4, 15), PaymentFrequency::Monthly, DayCountConvention::Thirty360, 2));
+        let rows: Vec<_> = schedule.iter().map(|p| (p.due_date, p.principal, p.interest, p.remaining_balance)).collect();
+        assert_eq!(
+            rows,
+            vec![
+                (date(2024, 2, 15), dec!(333.33), dec!(10.00), dec!(666.67)),
+                (date(2024, 3, 15), dec!(333.33), dec!(6.67), dec!(333.34)),
+                (date(2024, 4, 15), dec!(333.34), dec!(3.33), dec!(0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_final_installment_absorbs_rounding() {
+        // 各期利息 8.49 / 5.30 / 2.84 (未舍入)，总额 16.63 → 17，末期为 17 - 8 - 5 = 4
+        let schedule = build_schedule(&terms(dec!(1000), dec!(0.1), date(2024, 1, 1), date(2024, 4, 1), PaymentFrequency::Monthly, DayCountConvention::Act365, 0));
+        let interest: Vec<_> = schedule.iter().map(|p| p.interest).collect();
+        assert_eq!(interest, vec![dec!(8), dec!(5), dec!(4)]);
+        assert_eq!(schedule.iter().map(|p| p.principal).sum::<Decimal>(), dec!(1000));
+        assert_eq!(schedule.last().unwrap().remaining_balance, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_due_dates_clamp_month_end_and_keep_stub() {
+        assert_eq!(
+            due_dates(date(2024, 1, 31), date(2024, 4, 30), PaymentFrequency::Monthly),
+            vec![date(2024, 2, 29), date(2024, 3, 31), date(2024, 4, 30)]
+        );
+        assert_eq!(
+            due_dates(date(2024, 1, 15), date(2024, 8, 31), PaymentFrequency::Quarterly),
+            vec![date(2024, 4, 15), date(2024, 7, 15), date(2024, 8, 31)]
+        );
+        assert_eq!(due_dates(date(2024, 5, 1), date(2024, 4, 30), PaymentFrequency::Monthly), vec![date(2024, 4, 30)]);
+    }
+}
@@ -0,0 +1,143 @@
+//! 投资人认购记录：每笔认购对应一条持仓 (`user_invoice_holdings`)
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use common::domain::dto::purchase_history_dto::{PurchaseHistoryDto, PurchaseHistoryItemDto, PurchaseSummaryDto};
+use common::domain::entity::{Invoice, UserInvoiceHolding};
+use common::pagination::{OffsetPagination, Page};
+use mongodb::bson::Decimal128;
+use mongodb::bson::oid::ObjectId;
+use rust_decimal::Decimal;
+
+/// 由数据库聚合结果构造汇总，没有认购时累计金额为 0
+pub fn summary_from_totals(total_invested: Option<Decimal128>, active_positions: u64) -> PurchaseSummaryDto {
+    let total_invested = total_invested
+        .and_then(|total| Decimal::from_str(&total.to_string()).ok())
+        .unwrap_or_default();
+    PurchaseSummaryDto { total_invested: total_invested.normalize().to_string(), active_positions }
+}
+
+/// 需要查询票据信息的 invoice_id (当前页)
+pub fn page_invoice_ids(rows: &[UserInvoiceHolding]) -> Vec<ObjectId> {
+    let mut ids: Vec<ObjectId> = rows.iter().map(|h| h.invoice_id).collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// `rows` 为数据库已按认购时间倒序分好的当前页，`total` 为投资人全部认购数
+pub fn build_history(
+    rows: Vec<UserInvoiceHolding>,
+    total: u64,
+    summary: PurchaseSummaryDto,
+    invoices: &HashMap<ObjectId, Invoice>,
+    pagination: OffsetPagination,
+) -> PurchaseHistoryDto {
+    let rows = rows
+        .into_iter()
+        .map(|h| {
+            let invoice = invoices.get(&h.invoice_id);
+            PurchaseHistoryItemDto {
+                invoice_id: h.invoice_id.to_hex(),
+                invoice_number: invoice.map(|i| i.invoice_number.clone()),
+                amount: h.purchase_amount.to_string(),
+                purchased_at: h.purchase_date.timestamp_millis(),
+                tx_hash: h.metadata.as_ref().and_then(|m| m.get_str("tx_hash").ok()).map(str::to_string),
+                holding_status: h.holding_status.clone(),
+                invoice_status: invoice.map(|i| i.status),
+                holding_id: h.holding_id,
+            }
+        })
+        .collect();
+    PurchaseHistoryDto { page: Page::offset(rows, total, pagination.skip()), summary }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::domain::entity::HoldingStatus;
+    use mongodb::bson::DateTime;
+    use crate::repository::UserInvoiceHoldingRepository;
+    use crate::test_support::TestDb;
+
+    fn holding(user_id: &str, invoice_id: ObjectId, amount: &str, purchased_at_ms: i64, status: HoldingStatus) -> UserInvoiceHolding {
+        let mut holding = UserInvoiceHolding::new(user_id.to_string(), invoice_id, Decimal128::from_str(amount).unwrap());
+        holding.purchase_date = DateTime::from_millis(purchased_at_ms);
+        holding.holding_status = status;
+        holding
+    }
+
+    fn holdings(user_id: &str, invoice_a: ObjectId, invoice_b: ObjectId) -> Vec<UserInvoiceHolding> {
+        vec![
+            holding(user_id, invoice_a, "100.50", 1_000, HoldingStatus::Active),
+            holding(user_id, invoice_b, "200", 3_000, HoldingStatus::Matured),
+            holding(user_id, invoice_a, "50.25", 2_000, HoldingStatus::Active),
+            holding(user_id, invoice_b, "10", 4_000, HoldingStatus::Active),
+            holding(user_id, invoice_a, "0.25", 5_000, HoldingStatus::Sold),
+        ]
+    }
+
+    #[test]
+    fn test_summary_from_totals() {
+        let summary = summary_from_totals(Some(Decimal128::from_str("361.00").unwrap()), 3);
+        assert_eq!(summary, PurchaseSummaryDto { total_invested: "361".to_string(), active_positions: 3 });
+        assert_eq!(summary_from_totals(None, 0), PurchaseSummaryDto { total_invested: "0".to_string(), active_positions: 0 });
+    }
+
+    #[test]
+    fn test_build_history_uses_database_total() {
+        let invoice = ObjectId::new();
+        let rows = vec![holding("0xinvestor", invoice, "10", 4_000, HoldingStatus::Active)];
+        let summary = summary_from_totals(None, 0);
+
+        let middle = build_history(rows.clone(), 5, summary.clone(), &HashMap::new(), OffsetPagination::new(2, 1));
+        assert_eq!(middle.page.total, 5);
+        assert!(middle.page.has_more);
+        assert_eq!(middle.page.rows[0].purchased_at, 4_000);
+        assert!(middle.page.rows[0].invoice_number.is_none());
+
+        let last = build_history(rows, 5, summary, &HashMap::new(), OffsetPagination::new(5, 1));
+        assert!(!last.page.has_more);
+    }
+
+    #[test]
+    fn test_page_invoice_ids_deduplicated() {
+        let (invoice_a, invoice_b) = (ObjectId::new(), ObjectId::new());
+        let all = holdings("0xinvestor", invoice_a, invoice_b);
+        let mut expected = vec![invoice_a, invoice_b];
+        expected.sort();
+        assert_eq!(page_invoice_ids(&all), expected);
+        assert_eq!(page_invoice_ids(&all[1..2]), vec![invoice_b]);
+        assert!(page_invoice_ids(&[]).is_empty());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_history_paged_in_database_newest_first() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let repo = UserInvoiceHoldingRepository::new(&test_db);
+        let user_id = format!("0x{:0>40}", ObjectId::new().to_hex());
+        let (invoice_a, invoice_b) = (ObjectId::new(), ObjectId::new());
+        for holding in holdings(&user_id, invoice_a, invoice_b) {
+            repo.create(holding).await.unwrap();
+        }
+        // 其他投资人的认购不计入
+        repo.create(holding("0xother", invoice_a, "999", 6_000, HoldingStatus::Active)).await.unwrap();
+
+        let (first, total) = repo.list_by_user(&user_id, OffsetPagination::new(1, 2)).await.unwrap();
+        let (last, _) = repo.list_by_user(&user_id, OffsetPagination::new(3, 2)).await.unwrap();
+        let (beyond, _) = repo.list_by_user(&user_id, OffsetPagination::new(10, 2)).await.unwrap();
+        let summary = repo.summarize_by_user(&user_id).await.unwrap();
+        let empty = repo.summarize_by_user("0xnobody").await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(total, 5);
+        assert_eq!(first.iter().map(|h| h.purchase_date.timestamp_millis()).collect::<Vec<_>>(), vec![5_000, 4_000]);
+        assert_eq!(last.iter().map(|h| h.purchase_date.timestamp_millis()).collect::<Vec<_>>(), vec![1_000]);
+        assert!(beyond.is_empty());
+        // 汇总覆盖全部认购，不受分页影响
+        assert_eq!(summary, PurchaseSummaryDto { total_invested: "361".to_string(), active_positions: 3 });
+        assert_eq!(empty, PurchaseSummaryDto { total_invested: "0".to_string(), active_positions: 0 });
+    }
+}
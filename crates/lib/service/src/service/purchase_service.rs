@@ -1,19 +1,46 @@
 use std::str::FromStr;
 use anyhow::{Result, Context, anyhow};
-use mongodb::{Database, bson::{self, Decimal128, doc}, Client};
+use mongodb::{ClientSession, Database, bson::{self, Decimal128, doc, oid::ObjectId}, Client};
 use std::sync::Arc;
-use log::{info, error, warn};
+use tracing::{info, error, warn};
 use common::domain::dto::invoice_redis_dto::InvoiceRedisDto;
 use common::domain::dto::purchase_invoice_dto::PurchaseInvoiceDto;
-use common::domain::entity::{UserInvoiceHolding, Transaction, TransactionType, User};
-use crate::repository::{UserRepository, InvoiceRepository, UserInvoiceHoldingRepository, TransactionRepository};
-use crate::cache::InvoiceRedisService;
+use common::domain::dto::settlement_projection_dto::SettlementProjectionDto;
+use common::domain::dto::repayment_settlement_dto::RepaymentSettlementDto;
+use common::domain::dto::invoice_cancellation_dto::InvoiceCancellationDto;
+use common::domain::entity::{Enterprise, EnterpriseStatus, Invoice, AuditLog, RepaymentPayout, RepaymentPayoutDto, UserInvoiceHolding, Transaction, TransactionType, TokenMint, User};
+use common::domain::dto::purchase_history_dto::PurchaseHistoryDto;
+use common::utils::money::Money;
+use std::collections::HashMap;
+use common::domain::entity::invoice_status::InvoiceStatus;
+use crate::repository::{UserRepository, InvoiceRepository, UserInvoiceHoldingRepository, TransactionRepository, EnterpriseRepository, TokenRepository, TokenMintRepository};
+use crate::cache::{InvoiceRedisService, RedisSettlementLock, ReservationService};
+use crate::invoice::settlement_executor::{SettlementExecutor, SettlementSubmitter};
+use crate::invoice::status_events::{InvoiceStatusChange, InvoiceStatusNotifier};
+use crate::service::repayment_split::{check_repayment_covers, parse_repayment_amount, split_pro_rata, to_base_units};
+use common::pagination::OffsetPagination;
+use crate::service::purchase_history::{build_history, page_invoice_ids};
+use crate::cache::purchase_lock::with_purchase_lock;
+use crate::cache::idempotency::{IDEMPOTENCY_TTL_SECS, purchase_idempotency_key, request_fingerprint, run_idempotent};
+use crate::db::{MAX_TRANSACTION_ATTEMPTS, finish_transaction, retry_transient_transaction};
 use crate::error::ServiceError;
-use futures::future::FutureExt;
-use mongodb::error::Error as MongoError;
-use rust_decimal::Decimal;
-use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use crate::service::interest_calculator::{InterestCalculator, due_date_to_naive};
+use rust_decimal::{Decimal, RoundingStrategy};
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal_macros::dec;
+use async_trait::async_trait;
+use configs::cfgs::{Compounding, DayCountConvention, Purchase, Settlement};
+use pharos_interact::ContractWriter;
+
+/// 已完成校验、待写入数据库的认购
+struct PurchasePlan<'a> {
+    invoice_number: &'a str,
+    /// 按整数份数折算后的实际扣款金额
+    amount: Decimal128,
+    shares: u64,
+    /// 票据总份数，累计认购份数达到总份数时票据标记为已融资
+    total_shares: u64,
+}
 
 pub struct PurchaseService {
     client: Arc<Client>,
@@ -22,23 +49,107 @@ pub struct PurchaseService {
     invoice_repo: InvoiceRepository,
     holding_repo: UserInvoiceHoldingRepository,
     transaction_repo: TransactionRepository,
+    enterprise_repo: EnterpriseRepository,
+    token_repo: TokenRepository,
+    settlement_executor: SettlementExecutor<RedisSettlementLock, InvoiceRepository>,
+    /// 是否禁止投资人认购其绑定企业发行的票据
+    prevent_self_funding: bool,
+    require_verified_issuer: bool,
+    mint_repo: TokenMintRepository,
+    /// 认购时抵扣的预约，未配置时不接受 `reservation_id`
+    reservations: Option<Arc<ReservationService>>,
+    /// 企业还款、作废退款的票据锁 TTL
+    repayment_lock_ttl_ms: u64,
+    /// 票据认购锁 (与预约共用)
+    purchase_lock: RedisSettlementLock,
+    purchase_lock_ttl_ms: u64,
+    /// 募满 (Financed)、兑付 (Repaid)、作废 (Cancelled) 提交后通知
+    status_notifiers: Vec<Arc<dyn InvoiceStatusNotifier>>,
 }
 
 impl PurchaseService {
-    pub fn new(client: Arc<Client>, redis_service: Arc<InvoiceRedisService>) -> Self {
-        let db = Arc::new(client.database("rwa-db"));
+    pub fn new(db: Arc<Database>, redis_service: Arc<InvoiceRedisService>) -> Self {
+        let client = Arc::new(db.client().clone());
+        let settlement = Settlement::default();
         Self {
             user_repo: UserRepository::new(&db),
             invoice_repo: InvoiceRepository::new(&db),
             holding_repo: UserInvoiceHoldingRepository::new(&db),
             transaction_repo: TransactionRepository::new(&db),
+            enterprise_repo: EnterpriseRepository::new(&db),
+            token_repo: TokenRepository::new(db.clone()),
+            settlement_executor: SettlementExecutor::new(
+                RedisSettlementLock::new(redis_service.redis_client()),
+                InvoiceRepository::new(&db),
+                settlement.lock_ttl_ms,
+                settlement.lock_wait_ms,
+            ),
+            prevent_self_funding: true,
+            require_verified_issuer: true,
+            mint_repo: TokenMintRepository::new(&db),
+            reservations: None,
+            repayment_lock_ttl_ms: settlement.lock_ttl_ms,
+            purchase_lock: RedisSettlementLock::new(redis_service.redis_client()),
+            purchase_lock_ttl_ms: Purchase::default().lock_ttl_ms,
+            status_notifiers: Vec::new(),
             client,
             redis_service,
         }
     }
+
+    /// 开启/关闭自融检查 (默认开启，测试环境可关闭)
+    pub fn with_self_funding_check(mut self, enabled: bool) -> Self {
+        self.prevent_self_funding = enabled;
+        self
+    }
+
+    /// 开启/关闭出票企业审核检查 (默认开启，测试环境可关闭)
+    pub fn with_issuer_verification_check(mut self, enabled: bool) -> Self {
+        self.require_verified_issuer = enabled;
+        self
+    }
+
+    /// 企业还款、作废退款的票据锁 TTL 与等待时间 (默认取 [`Settlement::default`])
+    pub fn with_repayment_lock(mut self, settlement: &Settlement) -> Self {
+        self.settlement_executor = self.settlement_executor.with_lock_timing(settlement.lock_ttl_ms, settlement.lock_wait_ms);
+        self.repayment_lock_ttl_ms = settlement.lock_ttl_ms;
+        self
+    }
+
+    /// 票据认购锁 TTL (默认取 [`Purchase::default`])
+    pub fn with_purchase_lock_ttl(mut self, ttl_ms: u64) -> Self {
+        self.purchase_lock_ttl_ms = ttl_ms;
+        self
+    }
+
+    /// 状态变更提交后通知 `notifier`，与 `InvoiceService` 共用同一组通知 (企业 webhook、SSE 事件)
+    pub fn with_status_notifier(mut self, notifier: Arc<dyn InvoiceStatusNotifier>) -> Self {
+        self.status_notifiers.push(notifier);
+        self
+    }
+
+    async fn notify_status_change(&self, change: InvoiceStatusChange) {
+        for notifier in &self.status_notifiers {
+            notifier.clone().invoice_status_changed(change.clone()).await;
+        }
+    }
+
+    /// 认购时可使用的预约 (与预约接口共用)
+    pub fn with_reservations(mut self, reservations: Arc<ReservationService>) -> Self {
+        self.reservations = Some(reservations);
+        self
+    }
+
     
-    /// 用户购买票据 (使用事务)
+    /// 用户购买票据 (使用事务)。持有票据认购锁完成可售检查、扣款和份数扣减，并发认购时返回 `PurchaseInProgress`
     pub async fn purchase_invoice(&self, user_address: &str, purchase_data: &PurchaseInvoiceDto) -> Result<UserInvoiceHolding, ServiceError> {
+        with_purchase_lock(&self.purchase_lock, &purchase_data.invoice_id, self.purchase_lock_ttl_ms, || {
+            self.purchase_invoice_locked(user_address, purchase_data)
+        })
+        .await
+    }
+
+    async fn purchase_invoice_locked(&self, user_address: &str, purchase_data: &PurchaseInvoiceDto) -> Result<UserInvoiceHolding, ServiceError> {
         info!("Processing invoice purchase for user: {}, invoice: {}, amount: {}", 
               user_address, purchase_data.invoice_id, purchase_data.purchase_amount);
         
@@ -46,158 +157,492 @@ impl PurchaseService {
         let invoice_redis = self.redis_service.get_invoice(&purchase_data.invoice_id)?
             .ok_or_else(|| ServiceError::InvoiceNotFound(purchase_data.invoice_id.clone()))?;
             
-        // 2. 验证票据是否可购买
-        if !invoice_redis.is_available_for_purchase() {
+        // 2. 验证票据是否可购买，使用预约时预约的份数已从可用份数中扣除，一并计入
+        let reservation = match &purchase_data.reservation_id {
+            Some(reservation_id) => Some(self.reservations.as_ref()
+                .ok_or_else(|| ServiceError::ReservationNotFound(reservation_id.clone()))?
+                .find_active(user_address, reservation_id, &purchase_data.invoice_id)?),
+            None => None,
+        };
+        let reserved_shares = reservation.as_ref().map_or(0, |r| r.shares);
+        let available_shares = invoice_redis.available_shares + reserved_shares;
+        if invoice_redis.status != InvoiceStatus::Packaged || available_shares == 0 {
             return Err(ServiceError::InvoiceNotAvailable(purchase_data.invoice_id.clone()));
         }
 
-        // 3. 验证和计算购买信息 (Using rust_decimal for precision)
-        let purchase_amount_dec = Decimal::from_f64(purchase_data.purchase_amount)
-            .ok_or_else(|| ServiceError::DecimalConversionError("Invalid purchase amount format".to_string()))?;
-        let share_price_dec = Decimal::from_f64(invoice_redis.share_price)
-             .ok_or_else(|| ServiceError::DecimalConversionError("Invalid share price format in Redis".to_string()))?;
-        let zero_dec = dec!(0.0);
-        let one_dec = dec!(1.0);
-        
-        if purchase_amount_dec <= zero_dec || share_price_dec <= zero_dec {
+        // 3. 验证和计算购买信息 (Money 十进制运算，避免浮点误差)
+        let purchase_amount = purchase_data.purchase_amount;
+        let share_price = invoice_redis.share_price;
+        if !share_price.is_positive() || (purchase_data.units.is_none() && !purchase_amount.is_positive()) {
              return Err(ServiceError::InvalidPurchaseAmount("Purchase amount and share price must be positive".to_string()));
         }
 
-        // Calculate shares. Round to nearest integer. Handle potential division by zero already checked.
-        // Ensure we round correctly to avoid small amounts buying 0 shares or large amounts losing a share.
-        let calculated_shares_dec = (purchase_amount_dec / share_price_dec).round();
-        let calculated_shares = calculated_shares_dec.to_u64()
-            .ok_or_else(|| ServiceError::InvalidPurchaseAmount("Calculated shares resulted in an invalid number".to_string()))?;
-        
-        // Recalculate the actual purchase amount based on whole shares to ensure consistency
-        let actual_purchase_amount_dec = calculated_shares_dec * share_price_dec;
-        let actual_purchase_decimal128 = Decimal128::from_str(&actual_purchase_amount_dec.to_string())
-            .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to convert final purchase amount: {}", e)))?;
-
+        // 指定份数时按份数认购，否则按金额折算为整数份 (四舍五入)
+        let calculated_shares = match purchase_data.units {
+            Some(units) => units,
+            None => purchase_amount.ratio(share_price)?.round().to_u64()
+                .ok_or_else(|| ServiceError::InvalidPurchaseAmount("Calculated shares resulted in an invalid number".to_string()))?,
+        };
         if calculated_shares == 0 {
              return Err(ServiceError::InvalidPurchaseAmount("Purchase amount too small to buy any shares".to_string()));
         }
+        check_capacity(calculated_shares, available_shares)?;
 
-        if calculated_shares > invoice_redis.available_shares {
-            return Err(ServiceError::InvalidPurchaseShares(calculated_shares, invoice_redis.available_shares));
-        }
+        // Recalculate the actual purchase amount based on whole shares to ensure consistency
+        let actual_purchase_decimal128 = share_price.checked_mul(Decimal::from(calculated_shares))?.to_decimal128()
+            .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to convert final purchase amount: {}", e)))?;
 
-        // 5. Start MongoDB Transaction (using actual_purchase_decimal128)
-        let mut session = self.client.start_session().await
-            .map_err(|e| ServiceError::MongoDbError(e.to_string()))?;
+        // 4. 出票企业须已通过审核 (自融检查在认购事务内进行)
+        if self.require_verified_issuer {
+            self.ensure_issuer_verified(&invoice_redis.invoice_number).await?;
+        }
 
+        // 5. 同一事务内扣款、写持仓和交易记录并更新票据状态，任一步失败整体回滚
         info!("Starting transaction for purchase by user {}: calculated shares: {}, actual amount: {}", 
               user_address, calculated_shares, actual_purchase_decimal128);
+        let plan = PurchasePlan {
+            invoice_number: &invoice_redis.invoice_number,
+            amount: actual_purchase_decimal128,
+            shares: calculated_shares,
+            total_shares: invoice_redis.total_shares,
+        };
+        // 使用的预约在事务开始前移除、事务失败时恢复 (均持有认购锁)；两者之间进程退出时份数保持扣减，不会超卖
+        if let (Some(reservations), Some(reservation)) = (&self.reservations, &reservation) {
+            reservations.consume(reservation)?;
+        }
+        // 代币铸造记录在同一事务内写入，由 TokenMintService 在后台上链，这里不等待链上回执
+        let result = retry_transient_transaction(MAX_TRANSACTION_ATTEMPTS, |_| {
+            self.run_purchase_transaction(user_address, &plan)
+        })
+        .await
+        .inspect_err(|e| error!("Transaction failed for user {}: {}", user_address, e));
+        if let (Err(_), Some(reservations), Some(reservation)) = (&result, &self.reservations, &reservation) {
+            reservations.restore(reservation).unwrap_or_else(|e| error!(
+                "Failed to restore reservation {} after failed purchase: {}. {} shares of invoice {} stay reserved.",
+                reservation.reservation_id, e, reservation.shares, purchase_data.invoice_id
+            ));
+        }
+        let (holding, financed) = result?;
+        info!("Transaction committed successfully for user {}", user_address);
 
-        let transaction_result = session.start_transaction()
-            .and_run(
-                (self, user_address, &purchase_data, &invoice_redis, &actual_purchase_decimal128, calculated_shares),
-                |session, (service, user_addr, p_data, i_redis, actual_p_decimal, shares_to_purchase)| {
-                    async move {
-                        // --- Transaction Operations ---
-                        // a. 检查用户并扣除余额 (using actual_p_decimal)
-                        let user = service.user_repo.find_by_wallet_address_session(user_addr, session).await?
-                             .ok_or_else(|| ServiceError::UserNotFound(user_addr.to_string()))?;
-
-                        // Check balance
-                        let balance_f64 = user.balance.to_string().parse::<f64>()
-                            .map_err(|_| ServiceError::InternalError(format!("Failed to parse user balance for comparison: {}", user.balance)))?;
-                        let purchase_f64 = actual_p_decimal.to_string().parse::<f64>()
-                            .map_err(|_| ServiceError::InternalError(format!("Failed to parse purchase amount for comparison: {}", actual_p_decimal)))?;
-                        
-                        if balance_f64 < purchase_f64 { 
-                            error!("Insufficient funds for user {}. Required: {}, Available: {}", user_addr, actual_p_decimal, user.balance);
-                            return Err(ServiceError::InsufficientFunds(user_addr.to_string(), actual_p_decimal.to_string(), user.balance.to_string()));
-                        }
-                        
-                        // Deduct balance
-                        let negative_purchase_amount = Decimal128::from_str(&format!("-{}", actual_p_decimal.to_string()))
-                                                        .map_err(|_| ServiceError::InternalError("Failed to negate purchase amount".to_string()))?;
-                        let update_successful = service.user_repo.update_balance_session(user_addr, negative_purchase_amount, session).await?;
-                        if !update_successful {
-                            error!("Failed to update balance for user {} during purchase, update returned false.", user_addr);
-                            return Err(ServiceError::BalanceUpdateFailed(user_addr.to_string()));
-                        }
-                        info!("Deducted {} from user {} balance", actual_p_decimal, user_addr);
-
-                        // b. 查找数据库中的票据记录
-                        let invoice_mongo = service.invoice_repo.find_by_number_session(&i_redis.invoice_number, session).await?
-                            .ok_or_else(|| ServiceError::InvoiceNotFound(i_redis.invoice_number.clone()))?;
-
-                        // c. 创建持仓记录 (using actual_p_decimal)
-                        let holding = UserInvoiceHolding::new(
-                            user_addr.to_string(),
-                            invoice_mongo.id.unwrap(), 
-                            actual_p_decimal.clone(),
-                        );
-                        let created_holding = service.holding_repo.create_session(holding, session).await?;
-                        info!("Created holding record within transaction for user {}", user_addr);
-
-                        // d. 创建购买交易记录 (using actual_p_decimal)
-                        let transaction_record = Transaction::new_purchase(
-                            user_addr.to_string(),
-                            invoice_mongo.id.unwrap(),
-                            created_holding.holding_id.clone(),
-                            actual_p_decimal.clone(),
-                        );
-                        service.transaction_repo.create_session(transaction_record, session).await?;
-                        info!("Created transaction record within transaction for user {}", user_addr);
-
-                        // Clone u64 just in case (though it's Copy)
-                        Ok((created_holding, shares_to_purchase.clone()))
-                    }
-                    .map(|res| res.map_err(|service_err: ServiceError| { 
-                         MongoError::custom(Box::new(service_err))
-                    }))
-                   .boxed() 
-                },
-            )
-            .await;
-
-        // Destructure result outside the transaction closure
-        let (created_holding, purchased_shares) = match transaction_result {
-            Ok(result_tuple) => {
-                info!("Transaction committed successfully for user {}", user_address);
-                Ok(result_tuple) 
-            }
-            Err(e) => {
-                error!("Transaction failed for user {}: {:?}", user_address, e);
-                 // Check the error kind
-                 match &*e.kind { // Dereference the Box<ErrorKind>
-                     mongodb::error::ErrorKind::Custom(inner_error) => { 
-                        // Try downcasting the inner Box<dyn Error + Send + Sync>
-                        if let Some(service_error) = inner_error.downcast_ref::<ServiceError>() {
-                             Err(service_error.clone())
-                        } else {
-                            // Custom error wasn't our ServiceError
-                            Err(ServiceError::MongoDbTransactionError(format!("Unknown custom transaction error: {}", e)))
-                        }
-                     },
-                     _ if e.contains_label("TransientTransactionError") => { 
-                         Err(ServiceError::MongoDbTransactionError(format!("Transient transaction error (retry possible): {}", e)))
-                     },
-                     _ if e.contains_label("UnknownTransactionCommitResult") => { 
-                         Err(ServiceError::MongoDbTransactionError(format!("Unknown commit result (needs check): {}", e)))
-                     },
-                     _ => { // Fallback for other MongoDB errors
-                         Err(ServiceError::MongoDbError(e.to_string()))
-                     }
-                 }
-            }
-        }?; 
-
-        // 6. Update Redis (using purchased_shares which is u64)
-        match self.redis_service.update_invoice_shares(&purchase_data.invoice_id, purchased_shares) {
-            Ok(_) => info!("Successfully updated Redis shares ({}) for invoice {}", purchased_shares, purchase_data.invoice_id),
+        // 6. Update Redis：预约的份数已扣除，只扣减超出部分；认购少于预约时归还差额
+        let shares_update = if calculated_shares >= reserved_shares {
+            self.redis_service.update_invoice_shares(&purchase_data.invoice_id, calculated_shares - reserved_shares)
+        } else {
+            self.redis_service.release_invoice_shares(&purchase_data.invoice_id, reserved_shares - calculated_shares)
+        };
+        match shares_update {
+            Ok(_) => info!("Successfully updated Redis shares ({}, {} reserved) for invoice {}", calculated_shares, reserved_shares, purchase_data.invoice_id),
             Err(e) => {
-                error!("Failed to update Redis shares ({}) for invoice {} after successful DB transaction: {}. Data may be inconsistent.", purchased_shares, purchase_data.invoice_id, e);
+                error!("Failed to update Redis shares ({}, {} reserved) for invoice {} after successful DB transaction: {}. Data may be inconsistent.", calculated_shares, reserved_shares, purchase_data.invoice_id, e);
             }
         }
 
+        if let Some(change) = financed {
+            self.notify_status_change(change).await;
+        }
         info!("Successfully completed invoice purchase process for user {}", user_address);
-        Ok(created_holding) 
+        Ok(holding)
     }
     
+    /// 执行一次认购事务，任一步返回错误都回滚整个事务。
+    /// 本次认购使票据募满时一并返回状态变更，提交后再通知
+    async fn run_purchase_transaction(
+        &self,
+        user_address: &str,
+        plan: &PurchasePlan<'_>,
+    ) -> Result<(UserInvoiceHolding, Option<InvoiceStatusChange>), ServiceError> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let result = self.apply_purchase(user_address, plan, &mut session).await;
+        finish_transaction(session, result).await
+    }
+
+    /// 事务内的认购读写，调用方负责提交或回滚
+    async fn apply_purchase(
+        &self,
+        user_addr: &str,
+        plan: &PurchasePlan<'_>,
+        session: &mut ClientSession,
+    ) -> Result<(UserInvoiceHolding, Option<InvoiceStatusChange>), ServiceError> {
+        // a. 检查用户并扣除余额
+        let user = self.user_repo.find_by_wallet_address_session(user_addr, session).await?
+             .filter(|user| user.deleted_at.is_none())
+             .ok_or_else(|| ServiceError::UserNotFound(user_addr.to_string()))?;
+
+        // Check balance
+        let balance = Money::from_decimal128(&user.balance)
+            .map_err(|_| ServiceError::InternalError(format!("Failed to parse user balance for comparison: {}", user.balance)))?;
+        let purchase = Money::from_decimal128(&plan.amount)
+            .map_err(|_| ServiceError::InternalError(format!("Failed to parse purchase amount for comparison: {}", plan.amount)))?;
+        
+        if balance < purchase { 
+            error!("Insufficient funds for user {}. Required: {}, Available: {}", user_addr, plan.amount, user.balance);
+            return Err(ServiceError::InsufficientFunds(user_addr.to_string(), plan.amount.to_string(), user.balance.to_string()));
+        }
+        
+        // Deduct balance
+        let negative_purchase_amount = Decimal128::from_str(&format!("-{}", plan.amount))
+                                        .map_err(|_| ServiceError::InternalError("Failed to negate purchase amount".to_string()))?;
+        let update_successful = self.user_repo.update_balance_session(user_addr, negative_purchase_amount, session).await?;
+        if !update_successful {
+            error!("Failed to update balance for user {} during purchase, update returned false.", user_addr);
+            return Err(ServiceError::BalanceUpdateFailed(user_addr.to_string()));
+        }
+        info!("Deducted {} from user {} balance", plan.amount, user_addr);
+
+        // b. 查找数据库中的票据记录
+        let invoice_mongo = self.invoice_repo.find_by_number_session(plan.invoice_number, session).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(plan.invoice_number.to_string()))?;
+
+        // 已软删除的票据不允许认购
+        if invoice_mongo.is_deleted() {
+            return Err(ServiceError::InvoiceNotAvailable(plan.invoice_number.to_string()));
+        }
+        // 未接受融资条款的票据不允许融资 (迁移标记的存量票据除外)
+        if invoice_mongo.accepted_terms.is_none() && !invoice_mongo.terms_exempt {
+            return Err(ServiceError::TermsNotAccepted(plan.invoice_number.to_string()));
+        }
+        // 只有已发行 (可变更为已融资) 的票据可以认购
+        ServiceError::check_transition(invoice_mongo.status, InvoiceStatus::Financed)?;
+        // 防止自融：企业成员 (含关联钱包) 不能认购本企业发行的票据。与扣款读取同一快照，绑定关系的并发变更会使事务冲突重试
+        if self.prevent_self_funding {
+            // 投资人未绑定企业时无需再查出票企业
+            let issuer_enterprise = match user.enterprise_id {
+                Some(_) => self.enterprise_repo.find_by_wallet_address_session(&invoice_mongo.payee, session).await?.and_then(|e| e.id),
+                None => None,
+            };
+            check_self_funding(&user, &invoice_mongo.payee, issuer_enterprise, plan.invoice_number)?;
+        }
+
+        // 累加认购份数，超过剩余可认购份数时回滚 (缓存中的可售份数可能已过期)
+        let funded_shares = self.invoice_repo
+            .add_funded_shares_session(invoice_mongo.id.unwrap(), plan.shares, plan.total_shares, session)
+            .await?
+            .ok_or_else(|| ServiceError::InsufficientCapacity {
+                requested: plan.shares,
+                remaining: plan.total_shares.saturating_sub(invoice_mongo.funded_shares),
+            })?;
+
+        // c. 创建持仓记录
+        let holding = UserInvoiceHolding::new(
+            user_addr.to_string(),
+            invoice_mongo.id.unwrap(), 
+            plan.amount,
+        );
+        let created_holding = self.holding_repo.create_session(holding, session).await?;
+        info!("Created holding record within transaction for user {}", user_addr);
+
+        // d. 创建购买交易记录
+        let transaction_record = Transaction::new_purchase(
+            user_addr.to_string(),
+            invoice_mongo.id.unwrap(),
+            created_holding.holding_id.clone(),
+            plan.amount,
+        );
+        self.transaction_repo.create_session(transaction_record, session).await?;
+        info!("Created transaction record within transaction for user {}", user_addr);
+
+        // e. 累计认购募满后在同一事务内标记为已融资，状态已被并发修改时回滚
+        let mut financed = None;
+        if funded_shares == plan.total_shares {
+            let result = self.invoice_repo
+                .transition_status_session(invoice_mongo.id.unwrap(), invoice_mongo.status, InvoiceStatus::Financed, session)
+                .await?;
+            if result.modified_count == 0 {
+                return Err(ServiceError::InvalidStatusTransition { from: invoice_mongo.status, to: InvoiceStatus::Financed });
+            }
+            let audit = AuditLog::invoice(invoice_mongo.id.unwrap(), user_addr, "finance", Some(invoice_mongo.status), InvoiceStatus::Financed)
+                .with_reason(format!("fully subscribed by holding {}", created_holding.holding_id));
+            self.invoice_repo.append_audit_session(&audit, session).await?;
+            info!("Invoice {} fully subscribed, marked as financed", plan.invoice_number);
+            financed = Some(InvoiceStatusChange::new(invoice_mongo.id.unwrap(), &invoice_mongo, invoice_mongo.status, InvoiceStatus::Financed));
+        }
+
+        // f. 已发行到链上的票据按认购份数占比铸造代币给认购人 (事务提交后由后台任务上链)
+        if let Some(batch_id) = invoice_mongo.token_batch.clone().filter(|b| !b.is_empty()) {
+            let tokens = minted_token_amount(invoice_mongo.amount, plan.total_shares, funded_shares - plan.shares, plan.shares);
+            if tokens > 0 {
+                let mint = TokenMint::new(created_holding.holding_id.clone(), invoice_mongo.id.unwrap(), batch_id, user.wallet_address.to_lowercase(), tokens.to_string());
+                self.mint_repo.create_session(&mint, session).await?;
+            }
+        }
+
+        Ok((created_holding, financed))
+    }
+
+    /// 企业还款兑付：按代币持仓比例拆分 `amount`，通过合约一次分配给全部持有人，交易成功后在同一事务内将票据标记为已兑付并记录每个持有人的金额
+    ///
+    /// `amount` 少于应付总额 (有效持仓的本金加已计提利息) 时返回 `InvalidRepaymentAmount`。
+    /// 已兑付的票据返回 `InvoiceAlreadySettled`；并发请求由结算锁保证只提交一次分配交易。
+    /// `payout_decimals` 为稳定币精度，每份金额按该精度取整，舍入余额分配给被舍去部分最大的持有人。
+    pub async fn settle_invoice<W: ContractWriter + Send + Sync + ?Sized>(
+        &self,
+        invoice_id: ObjectId,
+        amount: &str,
+        actor: &str,
+        writer: &W,
+        payout_decimals: u32,
+    ) -> Result<RepaymentSettlementDto, ServiceError> {
+        // 已软删除的票据视为不存在，不允许兑付
+        let invoice = self.invoice_repo.find_by_id(invoice_id).await?
+            .filter(|invoice| !invoice.is_deleted())
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))?;
+        if invoice.status == InvoiceStatus::Repaid || invoice.settlement_tx_hash.is_some() {
+            return Err(ServiceError::InvoiceAlreadySettled(invoice.invoice_number));
+        }
+        ServiceError::check_transition(invoice.status, InvoiceStatus::Repaid)?;
+        let total = parse_repayment_amount(amount, payout_decimals)?;
+        let holdings = self.holding_repo.find_active_by_invoice(invoice_id).await?;
+        check_repayment_covers(total, repayment_owed(&holdings, payout_decimals)?, &invoice.invoice_number)?;
+
+        let chain_batch_id = invoice.token_batch.clone().filter(|b| !b.is_empty())
+            .ok_or_else(|| ServiceError::RepaymentNotAllowed(format!("invoice {} has not been issued on chain", invoice.invoice_number)))?;
+        let batch_oid = self.token_repo.find_token_batch_by_invoice(invoice_id).await?
+            .and_then(|batch| batch.id)
+            .ok_or_else(|| ServiceError::RepaymentNotAllowed(format!("invoice {} has no token batch", invoice.invoice_number)))?;
+        let balances = self.token_repo.aggregate_holder_balances(batch_oid).await?;
+        if balances.is_empty() {
+            return Err(ServiceError::RepaymentNotAllowed(format!("invoice {} has no token holders", invoice.invoice_number)));
+        }
+        let payouts = self.build_payouts(invoice_id, batch_oid, &balances, total, payout_decimals).await?;
+
+        let distribution = RepaymentDistribution {
+            writer,
+            batch_id: &chain_batch_id,
+            holders: payouts.iter().map(|p| p.wallet_address.clone()).collect(),
+            amounts: payouts.iter()
+                .map(|p| parse_decimal(&p.amount.to_string()).and_then(|a| to_base_units(a, payout_decimals)))
+                .collect::<Result<_, _>>()?,
+            payouts: &payouts,
+        };
+        let outcome = self.settlement_executor.settle(&invoice_id.to_hex(), actor, &distribution).await?;
+        if outcome.already_settled {
+            return Err(ServiceError::InvoiceAlreadySettled(invoice.invoice_number));
+        }
+
+        self.notify_status_change(InvoiceStatusChange::new(invoice_id, &invoice, invoice.status, InvoiceStatus::Repaid)).await;
+        let payouts = distribution.payouts(&outcome.tx_hash);
+        info!("Invoice {} repaid by {}: {} distributed to {} holders in tx {}", invoice.invoice_number, actor, total, payouts.len(), outcome.tx_hash);
+
+        Ok(RepaymentSettlementDto {
+            invoice_id: invoice_id.to_hex(),
+            invoice_number: invoice.invoice_number,
+            total_amount: total.to_string(),
+            settlement_tx_hash: outcome.tx_hash,
+            payouts: payouts.iter().map(RepaymentPayoutDto::from).collect(),
+        })
+    }
+
+    /// 按持仓拆分金额并查出持有人钱包，交易哈希在分配成功后填入
+    async fn build_payouts(
+        &self,
+        invoice_id: ObjectId,
+        batch_id: ObjectId,
+        balances: &[(ObjectId, Decimal128)],
+        total: Decimal,
+        payout_decimals: u32,
+    ) -> Result<Vec<RepaymentPayout>, ServiceError> {
+        let token_balances = balances.iter()
+            .map(|(_, balance)| parse_decimal(&balance.to_string()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let amounts = split_pro_rata(total, &token_balances, payout_decimals);
+
+        let user_ids: Vec<ObjectId> = balances.iter().map(|(user_id, _)| *user_id).collect();
+        let wallets: HashMap<ObjectId, String> = self.user_repo.find_by_ids(&user_ids).await?
+            .into_iter()
+            .filter_map(|user| user.id.map(|id| (id, user.wallet_address.to_lowercase())))
+            .collect();
+
+        let now = bson::DateTime::now();
+        balances.iter().zip(amounts).map(|((user_id, balance), amount)| {
+            let wallet_address = wallets.get(user_id).cloned()
+                .ok_or_else(|| ServiceError::UserNotFound(user_id.to_hex()))?;
+            let amount = Decimal128::from_str(&amount.to_string())
+                .map_err(|e| ServiceError::DecimalConversionError(format!("Failed to convert payout '{}': {}", amount, e)))?;
+            Ok(RepaymentPayout {
+                id: None,
+                invoice_id,
+                batch_id,
+                user_id: *user_id,
+                wallet_address,
+                token_balance: *balance,
+                amount,
+                settlement_tx_hash: String::new(),
+                created_at: now,
+            })
+        }).collect()
+    }
+
+    /// 管理员作废票据：按代币持仓比例退回投资人的认购资金，链上作废后将票据标记为已作废、认购金额退回用户余额，作废原因写入审计记录
+    ///
+    /// 已兑付的票据返回 `InvoiceAlreadySettled`。作废期间持有票据认购锁，同一票据的认购或重复作废返回 `PurchaseInProgress`。
+    pub async fn cancel_invoice<W: ContractWriter + Send + Sync + ?Sized>(
+        &self,
+        invoice_id: ObjectId,
+        reason: &str,
+        actor: &str,
+        writer: &W,
+        payout_decimals: u32,
+    ) -> Result<InvoiceCancellationDto, ServiceError> {
+        with_purchase_lock(&self.purchase_lock, &invoice_id.to_hex(), self.repayment_lock_ttl_ms, || {
+            self.cancel_invoice_locked(invoice_id, reason, actor, writer, payout_decimals)
+        })
+        .await
+    }
+
+    async fn cancel_invoice_locked<W: ContractWriter + Send + Sync + ?Sized>(
+        &self,
+        invoice_id: ObjectId,
+        reason: &str,
+        actor: &str,
+        writer: &W,
+        payout_decimals: u32,
+    ) -> Result<InvoiceCancellationDto, ServiceError> {
+        let invoice = self.invoice_repo.find_by_id(invoice_id).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))?;
+        check_cancellable(&invoice)?;
+
+        let holdings = self.holding_repo.find_active_by_invoice(invoice_id).await?;
+        let total = refund_total(&holdings, payout_decimals)?;
+        let (refunds, refund_tx_hash) = if total > Decimal::ZERO {
+            let (refunds, tx_hash) = self.refund_holders(&invoice, total, writer, payout_decimals).await?;
+            (refunds, Some(tx_hash))
+        } else {
+            (Vec::new(), None)
+        };
+
+        if invoice.status != InvoiceStatus::Pending {
+            if let Err(e) = writer.invalidate_invoice(invoice.invoice_number.clone()).await {
+                error!("Invoice {} cancelled by {} but on-chain invalidation failed: {:#}. Needs reconciliation.", invoice.invoice_number, actor, e);
+            }
+        }
+
+        let audit_reason = match &refund_tx_hash {
+            Some(tx_hash) => format!("{} (refund {} in tx {})", reason, total, tx_hash),
+            None => reason.to_string(),
+        };
+        // 退款已到账，以下步骤失败时只能根据日志补录
+        let cancelled = self.apply_cancellation(invoice_id, invoice.status, &holdings, actor, &audit_reason).await
+            .inspect_err(|e| error!("Invoice {} cancellation was not recorded: {}; refund tx {:?} needs reconciliation", invoice.invoice_number, e, refund_tx_hash))?;
+        if !cancelled {
+            error!("Invoice {} changed status during cancellation; refund tx {:?} needs reconciliation", invoice.invoice_number, refund_tx_hash);
+            return Err(ServiceError::InvalidStatusTransition { from: invoice.status, to: InvoiceStatus::Cancelled });
+        }
+        if let Err(e) = self.redis_service.delete_invoice(&invoice_id.to_hex()) {
+            warn!("Failed to remove cancelled invoice {} from cache: {}", invoice.invoice_number, e);
+        }
+        self.notify_status_change(InvoiceStatusChange::new(invoice_id, &invoice, invoice.status, InvoiceStatus::Cancelled)).await;
+        info!("Invoice {} cancelled by {}: refunded {} to {} holders", invoice.invoice_number, actor, total, refunds.len());
+
+        Ok(InvoiceCancellationDto {
+            invoice_id: invoice_id.to_hex(),
+            invoice_number: invoice.invoice_number,
+            previous_status: invoice.status,
+            refund_total: total.to_string(),
+            refund_tx_hash,
+            refunds: refunds.iter().map(RepaymentPayoutDto::from).collect(),
+        })
+    }
+
+    /// 作废事务：票据按原状态条件转为已作废，成功后才将活跃持仓标记为已退款、把认购金额退回用户余额并写入审计记录。
+    /// 票据已被其他请求改变状态时返回 false 且不做任何修改，重复作废不会重复退款
+    async fn apply_cancellation(
+        &self,
+        invoice_id: ObjectId,
+        from: InvoiceStatus,
+        holdings: &[UserInvoiceHolding],
+        actor: &str,
+        reason: &str,
+    ) -> Result<bool, ServiceError> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let result = async {
+            let transition = self.invoice_repo.transition_status_session(invoice_id, from, InvoiceStatus::Cancelled, &mut session).await?;
+            if transition.modified_count == 0 {
+                return Ok(false);
+            }
+            for holding in holdings {
+                if !self.holding_repo.refund_active_session(&holding.holding_id, &mut session).await? {
+                    continue;
+                }
+                if !self.user_repo.update_balance_session(&holding.user_id, holding.purchase_amount, &mut session).await? {
+                    return Err(ServiceError::BalanceUpdateFailed(holding.user_id.clone()));
+                }
+            }
+            let entry = AuditLog::invoice(invoice_id, actor, "cancel", Some(from), InvoiceStatus::Cancelled).with_reason(reason);
+            self.invoice_repo.append_audit_session(&entry, &mut session).await?;
+            Ok(true)
+        }
+        .await;
+        finish_transaction(session, result).await
+    }
+
+    /// 按代币持仓比例退款，返回每个持有人的退款及分配交易哈希
+    async fn refund_holders<W: ContractWriter + Send + Sync + ?Sized>(
+        &self,
+        invoice: &Invoice,
+        total: Decimal,
+        writer: &W,
+        payout_decimals: u32,
+    ) -> Result<(Vec<RepaymentPayout>, String), ServiceError> {
+        let invoice_id = invoice.id.ok_or_else(|| ServiceError::InvoiceNotFound(invoice.invoice_number.clone()))?;
+        let chain_batch_id = invoice.token_batch.clone().filter(|b| !b.is_empty())
+            .ok_or_else(|| ServiceError::RefundNotAllowed(format!("invoice {} has not been issued on chain", invoice.invoice_number)))?;
+        let batch_oid = self.token_repo.find_token_batch_by_invoice(invoice_id).await?
+            .and_then(|batch| batch.id)
+            .ok_or_else(|| ServiceError::RefundNotAllowed(format!("invoice {} has no token batch", invoice.invoice_number)))?;
+        let balances = self.token_repo.aggregate_holder_balances(batch_oid).await?;
+        if balances.is_empty() {
+            return Err(ServiceError::RefundNotAllowed(format!("invoice {} has no token holders", invoice.invoice_number)));
+        }
+        let mut refunds = self.build_payouts(invoice_id, batch_oid, &balances, total, payout_decimals).await?;
+
+        let holders = refunds.iter().map(|r| r.wallet_address.clone()).collect();
+        let amounts = refunds.iter()
+            .map(|r| parse_decimal(&r.amount.to_string()).and_then(|a| to_base_units(a, payout_decimals)))
+            .collect::<Result<_, _>>()?;
+        let receipt = writer.distribute_repayment(chain_batch_id, holders, amounts)
+            .await
+            .map_err(|e| ServiceError::ChainRpcError(format!("Refund distribution for invoice {} failed: {:#}", invoice.invoice_number, e)))?
+            .ok_or_else(|| ServiceError::ChainRpcError(format!("Refund distribution for invoice {} returned no receipt", invoice.invoice_number)))?;
+        let tx_hash = format!("{:?}", receipt.transaction_hash);
+        for refund in &mut refunds {
+            refund.settlement_tx_hash = tx_hash.clone();
+        }
+        Ok((refunds, tx_hash))
+    }
+
+    /// 幂等认购：同一用户携带相同 `idempotency_key` 重试时返回首次认购的持仓，不会重复扣款；
+    /// 相同键携带不同的认购请求时返回 `IdempotencyKeyReused`
+    pub async fn purchase_invoice_idempotent(&self, user_address: &str, purchase_data: &PurchaseInvoiceDto, idempotency_key: Option<&str>) -> Result<UserInvoiceHolding, ServiceError> {
+        let key = idempotency_key.map(|k| purchase_idempotency_key(user_address, k));
+        let fingerprint = request_fingerprint(purchase_data)?;
+        run_idempotent(self.redis_service.as_ref(), key.as_deref(), &fingerprint, IDEMPOTENCY_TTL_SECS, || {
+            self.purchase_invoice(user_address, purchase_data)
+        })
+        .await
+    }
+
+    async fn ensure_issuer_verified(&self, invoice_number: &str) -> Result<(), ServiceError> {
+        let invoice = self.invoice_repo.find_by_invoice_number(invoice_number).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_number.to_string()))?;
+        let issuer = self.enterprise_repo.find_by_wallet_address(&invoice.payee).await?;
+        check_issuer_verified(issuer.as_ref(), invoice_number)
+    }
+
+    /// 投资人认购记录 (按认购时间倒序分页) 及全部认购的汇总
+    pub async fn list_by_investor(&self, user_address: &str, pagination: OffsetPagination) -> Result<PurchaseHistoryDto, ServiceError> {
+        let (rows, total) = self.holding_repo.list_by_user(user_address, pagination).await?;
+        let summary = self.holding_repo.summarize_by_user(user_address).await?;
+        let invoice_ids = page_invoice_ids(&rows);
+        let invoices: HashMap<ObjectId, Invoice> = self.invoice_repo.find_by_ids(&invoice_ids).await?
+            .into_iter()
+            .filter_map(|invoice| invoice.id.map(|id| (id, invoice)))
+            .collect();
+        Ok(build_history(rows, total, summary, &invoices, pagination))
+    }
+
     /// 获取用户的所有票据持仓
     pub async fn get_user_holdings(&self, user_address: &str) -> Result<Vec<UserInvoiceHolding>> {
         info!("Fetching holdings for user: {}", user_address);
@@ -218,4 +663,567 @@ impl PurchaseService {
         info!("Found {} available invoices", invoices.len());
         Ok(invoices)
     }
+
+    /// 预估持仓到期兑付金额
+    ///
+    /// 利率优先使用企业已接受的融资条款，否则退回到缓存中的票据年化利率；
+    /// 服务费率优先使用已接受条款，否则使用 `default_fee_rate`。
+    /// 剩余利息按每日计息任务的日计数规则 `day_count` 以单利估算。
+    pub async fn settlement_projection(
+        &self,
+        user_address: &str,
+        holding_id: &str,
+        grace_days: i64,
+        default_fee_rate: &str,
+        day_count: DayCountConvention,
+    ) -> Result<SettlementProjectionDto, ServiceError> {
+        let holding = self.holding_repo.find_by_user_id_and_holding_id(user_address, holding_id).await?
+            .ok_or_else(|| ServiceError::HoldingNotFound(holding_id.to_string()))?;
+        let invoice = self.invoice_repo.find_by_id(holding.invoice_id).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(holding.invoice_id.to_hex()))?;
+
+        let (annual_rate, fee_rate) = match &invoice.accepted_terms {
+            Some(terms) => (parse_decimal(&terms.apr)?, parse_decimal(&terms.platform_fee_rate)?),
+            None => {
+                let cached = self.redis_service.get_invoice(&holding.invoice_id.to_hex())?
+                    .ok_or_else(|| ServiceError::InvoiceNotFound(holding.invoice_id.to_hex()))?;
+                let rate = cached.annual_rate / Decimal::ONE_HUNDRED;
+                (rate, parse_decimal(default_fee_rate)?)
+            }
+        };
+
+        let maturity_date = due_date_to_naive(invoice.due_date)
+            .ok_or_else(|| ServiceError::InternalError(format!("Invalid due date: {}", invoice.due_date)))?;
+        let projected_settlement_date = maturity_date + chrono::Duration::days(grace_days.max(0));
+
+        let principal = parse_decimal(&holding.current_balance.to_string())?;
+        let accrued_interest = parse_decimal(&holding.total_accrued_interest.to_string())?;
+
+        // 最后计息日之前的利息已计入 `total_accrued_interest`，剩余利息从最后计息日算到到期日，到期后不再计息
+        let last_accrual = chrono::DateTime::from_timestamp_millis(holding.last_accrual_date.timestamp_millis())
+            .map(|dt| dt.date_naive())
+            .unwrap_or_else(|| chrono::Utc::now().date_naive());
+        let remaining_interest = InterestCalculator::new(annual_rate).accrue(principal, last_accrual, maturity_date, day_count, Compounding::Simple);
+
+        let projected_interest = (accrued_interest + remaining_interest).round_dp(8);
+        let projected_fees = (projected_interest * fee_rate).round_dp(8);
+        let projected_payout = principal + projected_interest - projected_fees;
+
+        Ok(SettlementProjectionDto {
+            holding_id: holding.holding_id,
+            invoice_id: holding.invoice_id.to_hex(),
+            is_estimate: true,
+            maturity_date,
+            projected_settlement_date,
+            principal: principal.to_string(),
+            accrued_interest: accrued_interest.to_string(),
+            projected_interest: projected_interest.to_string(),
+            projected_fees: projected_fees.to_string(),
+            projected_payout: projected_payout.to_string(),
+            annual_rate: annual_rate.to_string(),
+            fee_rate: fee_rate.to_string(),
+        })
+    }
+}
+
+/// 还款分配交易，作为结算提交步骤交给 [`SettlementExecutor`]，返回分配交易哈希
+struct RepaymentDistribution<'a, W: ?Sized> {
+    writer: &'a W,
+    batch_id: &'a str,
+    holders: Vec<String>,
+    amounts: Vec<String>,
+    /// 交易哈希在分配成功后填入，随票据状态一起写入
+    payouts: &'a [RepaymentPayout],
+}
+
+#[async_trait]
+impl<W: ContractWriter + Send + Sync + ?Sized> SettlementSubmitter for RepaymentDistribution<'_, W> {
+    async fn submit(&self, invoice_id: &str) -> Result<String, ServiceError> {
+        let receipt = self.writer
+            .distribute_repayment(self.batch_id.to_string(), self.holders.clone(), self.amounts.clone())
+            .await
+            .map_err(|e| ServiceError::ChainRpcError(format!("Repayment distribution for invoice {} failed: {:#}", invoice_id, e)))?
+            .ok_or_else(|| ServiceError::ChainRpcError(format!("Repayment distribution for invoice {} returned no receipt", invoice_id)))?;
+        Ok(format!("{:?}", receipt.transaction_hash))
+    }
+
+    fn payouts(&self, tx_hash: &str) -> Vec<RepaymentPayout> {
+        self.payouts.iter()
+            .map(|payout| RepaymentPayout { settlement_tx_hash: tx_hash.to_string(), ..payout.clone() })
+            .collect()
+    }
+}
+
+/// 剩余可认购份数不足时返回 `InsufficientCapacity`
+fn check_capacity(requested: u64, available: u64) -> Result<(), ServiceError> {
+    if requested > available {
+        return Err(ServiceError::InsufficientCapacity { requested, remaining: available });
+    }
+    Ok(())
+}
+
+/// 本次认购应铸造的代币数量 (最小单位)：按累计认购占比折算面值后取差值，
+/// 各次认购的取整误差不会累积，全部认购完成时铸造总量恰好等于面值
+fn minted_token_amount(face_value: u64, total_shares: u64, funded_before: u64, shares: u64) -> u128 {
+    if total_shares == 0 {
+        return 0;
+    }
+    let funded_value = |funded: u64| face_value as u128 * funded.min(total_shares) as u128 / total_shares as u128;
+    funded_value(funded_before + shares) - funded_value(funded_before)
+}
+
+/// 已兑付的票据不能作废，其余按状态机判断 (终态不可作废)
+fn check_cancellable(invoice: &Invoice) -> Result<(), ServiceError> {
+    if invoice.status == InvoiceStatus::Repaid || invoice.settlement_tx_hash.is_some() {
+        return Err(ServiceError::InvoiceAlreadySettled(invoice.invoice_number.clone()));
+    }
+    ServiceError::check_transition(invoice.status, InvoiceStatus::Cancelled)
+}
+
+/// 作废时应退回的总额：各活跃持仓的认购金额之和，按稳定币精度向下取整
+fn refund_total(holdings: &[UserInvoiceHolding], scale: u32) -> Result<Decimal, ServiceError> {
+    let total = holdings.iter()
+        .map(|h| parse_decimal(&h.purchase_amount.to_string()))
+        .sum::<Result<Decimal, _>>()?;
+    Ok(total.round_dp_with_strategy(scale, RoundingStrategy::ToZero).normalize())
+}
+
+/// 兑付时应付给投资人的总额：有效持仓的本金加已计提利息，按 `scale` 向上取整
+fn repayment_owed(holdings: &[UserInvoiceHolding], scale: u32) -> Result<Decimal, ServiceError> {
+    let owed = holdings.iter()
+        .map(|h| Ok(parse_decimal(&h.purchase_amount.to_string())? + parse_decimal(&h.total_accrued_interest.to_string())?))
+        .sum::<Result<Decimal, ServiceError>>()?;
+    Ok(owed.round_dp_with_strategy(scale, RoundingStrategy::AwayFromZero).normalize())
+}
+
+fn parse_decimal(value: &str) -> Result<Decimal, ServiceError> {
+    Decimal::from_str(value).map_err(|e| ServiceError::DecimalConversionError(format!("'{}': {}", value, e)))
+}
+
+/// 出票企业 (钱包为票据 payee 的企业) 必须存在且已通过审核
+fn check_issuer_verified(issuer: Option<&Enterprise>, invoice_number: &str) -> Result<(), ServiceError> {
+    match issuer {
+        Some(enterprise) if enterprise.status == EnterpriseStatus::Verified => Ok(()),
+        Some(enterprise) => Err(ServiceError::EnterpriseNotVerified(format!(
+            "invoice {} issued by enterprise {} with status {:?}",
+            invoice_number, enterprise.name, enterprise.status
+        ))),
+        None => Err(ServiceError::EnterpriseNotVerified(format!("invoice {} has no registered issuing enterprise", invoice_number))),
+    }
+}
+
+/// 投资人钱包即出票企业钱包，或投资人绑定的企业就是出票企业时视为自融
+fn check_self_funding(investor: &User, payee: &str, issuer_enterprise: Option<ObjectId>, invoice_number: &str) -> Result<(), ServiceError> {
+    // 收款钱包为投资人的主钱包或关联钱包 (不区分大小写)
+    let same_wallet = investor.owns_wallet(payee);
+    let same_enterprise = matches!((investor.enterprise_id, issuer_enterprise), (Some(a), Some(b)) if a == b);
+    if same_wallet || same_enterprise {
+        warn!("Rejected self-funding purchase of invoice {} by {}", invoice_number, investor.wallet_address);
+        return Err(ServiceError::SelfFundingNotAllowed(format!(
+            "{} cannot fund invoice {} issued by its own enterprise",
+            investor.wallet_address, invoice_number
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{TestDb, unused_redis_client};
+
+    fn investor(wallet: &str, enterprise_id: Option<ObjectId>, linked_wallets: &[&str]) -> User {
+        let mut user = User::new(wallet.to_string(), "investor".to_string(), common::domain::entity::UserRole::Investor);
+        user.enterprise_id = enterprise_id;
+        user.linked_wallets = linked_wallets.iter().map(|w| w.to_string()).collect();
+        user
+    }
+
+    #[test]
+    fn test_self_funding_rejected_for_bound_enterprise_member() {
+        let enterprise = ObjectId::new();
+        assert!(matches!(
+            check_self_funding(&investor("0xinvestor", Some(enterprise), &[]), "0xpayee", Some(enterprise), "INV-1"),
+            Err(ServiceError::SelfFundingNotAllowed(_))
+        ));
+        // 出票企业钱包本身认购，大小写不同
+        assert!(check_self_funding(&investor("0xPAYEE", None, &[]), "0xpayee", None, "INV-1").is_err());
+        // 出票企业钱包是投资人的关联钱包
+        assert!(check_self_funding(&investor("0xinvestor", None, &["0xpayee"]), "0xPayee", None, "INV-1").is_err());
+    }
+
+    #[test]
+    fn test_self_funding_allowed_for_unbound_or_other_enterprise() {
+        let enterprise = ObjectId::new();
+        assert!(check_self_funding(&investor("0xinvestor", None, &["0xother"]), "0xpayee", Some(enterprise), "INV-1").is_ok());
+        assert!(check_self_funding(&investor("0xinvestor", Some(ObjectId::new()), &[]), "0xpayee", Some(enterprise), "INV-1").is_ok());
+        // 出票企业未登记
+        assert!(check_self_funding(&investor("0xinvestor", Some(enterprise), &[]), "0xpayee", None, "INV-1").is_ok());
+    }
+
+    #[test]
+    fn test_partial_purchases_sum_to_full_funding() {
+        // 面值 1000，7 份，分三次认购 2 + 3 + 2 份
+        let (face_value, total_shares) = (1_000, 7);
+        let mut funded = 0;
+        let mut minted = Vec::new();
+        for shares in [2, 3, 2] {
+            check_capacity(shares, total_shares - funded).unwrap();
+            minted.push(minted_token_amount(face_value, total_shares, funded, shares));
+            funded += shares;
+        }
+        assert_eq!(funded, total_shares);
+        assert_eq!(minted, vec![285, 429, 286]);
+        assert_eq!(minted.iter().sum::<u128>(), face_value as u128);
+    }
+
+    #[test]
+    fn test_purchase_over_remaining_capacity_rejected() {
+        assert!(check_capacity(3, 3).is_ok());
+        assert!(matches!(
+            check_capacity(4, 3),
+            Err(ServiceError::InsufficientCapacity { requested: 4, remaining: 3 })
+        ));
+        assert_eq!(minted_token_amount(1_000, 0, 0, 1), 0);
+    }
+
+    fn cancel_candidate(status: InvoiceStatus) -> Invoice {
+        let mut invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 1_000,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        invoice.status = status;
+        invoice
+    }
+
+    #[test]
+    fn test_repaid_invoice_cannot_be_cancelled() {
+        assert!(matches!(check_cancellable(&cancel_candidate(InvoiceStatus::Repaid)), Err(ServiceError::InvoiceAlreadySettled(_))));
+        // 已提交兑付交易但状态尚未更新
+        let mut settling = cancel_candidate(InvoiceStatus::Financed);
+        settling.settlement_tx_hash = Some("0xabc".to_string());
+        assert!(matches!(check_cancellable(&settling), Err(ServiceError::InvoiceAlreadySettled(_))));
+
+        assert!(check_cancellable(&cancel_candidate(InvoiceStatus::Financed)).is_ok());
+        assert!(check_cancellable(&cancel_candidate(InvoiceStatus::Overdue)).is_ok());
+        assert!(matches!(
+            check_cancellable(&cancel_candidate(InvoiceStatus::Cancelled)),
+            Err(ServiceError::InvalidStatusTransition { .. })
+        ));
+    }
+
+    #[test]
+    fn test_refund_mirrors_token_holdings() {
+        let invoice_id = ObjectId::new();
+        let holdings: Vec<UserInvoiceHolding> = ["600.004", "250", "150.5"].iter()
+            .map(|amount| UserInvoiceHolding::new("0xinvestor".to_string(), invoice_id, Decimal128::from_str(amount).unwrap()))
+            .collect();
+        // 退款总额按稳定币精度向下取整
+        let total = refund_total(&holdings, 2).unwrap();
+        assert_eq!(total, dec!(1000.5));
+        assert_eq!(refund_total(&[], 2).unwrap(), Decimal::ZERO);
+
+        // 按代币持仓拆分，退款之和等于总额
+        let refunds = split_pro_rata(total, &[dec!(600), dec!(250), dec!(150)], 2);
+        assert_eq!(refunds, vec![dec!(600.3), dec!(250.13), dec!(150.07)]);
+        assert_eq!(refunds.iter().sum::<Decimal>(), total);
+        assert_eq!(to_base_units(refunds[1], 6).unwrap(), "250130000");
+    }
+
+    #[test]
+    fn test_repayment_owed_includes_accrued_interest() {
+        let invoice_id = ObjectId::new();
+        let mut first = UserInvoiceHolding::new("0xa".to_string(), invoice_id, Decimal128::from_str("600").unwrap());
+        first.total_accrued_interest = Decimal128::from_str("12.3456").unwrap();
+        let second = UserInvoiceHolding::new("0xb".to_string(), invoice_id, Decimal128::from_str("400").unwrap());
+
+        // 应付总额按稳定币精度向上取整，避免少付
+        let owed = repayment_owed(&[first, second], 2).unwrap();
+        assert_eq!(owed, dec!(1012.35));
+        assert!(check_repayment_covers(dec!(1012.34), owed, "INV-1").is_err());
+        assert!(check_repayment_covers(dec!(1012.35), owed, "INV-1").is_ok());
+    }
+
+    #[test]
+    fn test_unverified_issuer_blocks_purchase_until_verified() {
+        let mut issuer = Enterprise::new("ACME".to_string(), "0xpayee".to_string());
+        assert!(matches!(check_issuer_verified(Some(&issuer), "INV-1"), Err(ServiceError::EnterpriseNotVerified(_))));
+        assert!(matches!(check_issuer_verified(None, "INV-1"), Err(ServiceError::EnterpriseNotVerified(_))));
+
+        issuer.status = EnterpriseStatus::Verified;
+        assert!(check_issuer_verified(Some(&issuer), "INV-1").is_ok());
+
+        for status in [EnterpriseStatus::Rejected, EnterpriseStatus::Suspended] {
+            issuer.status = status;
+            assert!(check_issuer_verified(Some(&issuer), "INV-1").is_err());
+        }
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_verification_status_flips_issuer_gate() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let redis = Arc::new(InvoiceRedisService::new(unused_redis_client()));
+        let service = PurchaseService::new(Arc::new(db.clone()), redis);
+
+        let payee = format!("0x{:0>40}", ObjectId::new().to_hex());
+        let enterprise = EnterpriseRepository::new(&db).create("kyc-test", &payee).await.unwrap();
+        let enterprise_id = enterprise.id.unwrap();
+        let invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: payee.clone(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        db.collection::<Invoice>("invoices").insert_one(&invoice).await.unwrap();
+
+        let pending = service.ensure_issuer_verified(&invoice.invoice_number).await;
+        let repo = EnterpriseRepository::new(&db);
+        let verified = repo.set_verification_status(enterprise_id, EnterpriseStatus::Verified, None).await.unwrap().unwrap();
+        let after_verify = service.ensure_issuer_verified(&invoice.invoice_number).await;
+        let rejected = repo.set_verification_status(enterprise_id, EnterpriseStatus::Rejected, Some("documents expired")).await.unwrap().unwrap();
+        let after_reject = service.ensure_issuer_verified(&invoice.invoice_number).await;
+        test_db.cleanup().await;
+
+        assert!(matches!(pending, Err(ServiceError::EnterpriseNotVerified(_))));
+        assert_eq!(verified.status, EnterpriseStatus::Verified);
+        assert!(after_verify.is_ok());
+        assert_eq!(rejected.rejection_reason.as_deref(), Some("documents expired"));
+        assert!(matches!(after_reject, Err(ServiceError::EnterpriseNotVerified(_))));
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_failure_after_invoice_update_commits_nothing() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let redis = Arc::new(InvoiceRedisService::new(unused_redis_client()));
+        let service = PurchaseService::new(Arc::new(db.clone()), redis);
+
+        let investor = format!("0xtest{}", ObjectId::new().to_hex());
+        let mut user = User::new(investor.clone(), "rollback-test".to_string(), common::domain::entity::UserRole::Investor);
+        user.balance = Decimal128::from_str("100").unwrap();
+        db.collection::<User>("users").insert_one(&user).await.unwrap();
+
+        let mut invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        invoice.status = InvoiceStatus::OnSale;
+        invoice.token_batch = Some("7".to_string());
+        invoice.accepted_terms = Some(common::domain::entity::AcceptedTerms {
+            apr: "0.08".to_string(),
+            platform_fee_rate: "0.01".to_string(),
+            accepted_by: "0xpayee".to_string(),
+            accepted_at: bson::DateTime::now(),
+        });
+        let invoice_id = db.collection::<Invoice>("invoices").insert_one(&invoice).await.unwrap().inserted_id.as_object_id().unwrap();
+
+        // 认购全部剩余份数，票据状态更新后注入失败
+        let plan = PurchasePlan {
+            invoice_number: &invoice.invoice_number,
+            amount: Decimal128::from_str("40").unwrap(),
+            shares: 4,
+            total_shares: 4,
+        };
+        // 与 run_purchase_transaction 相同的事务，全部写入完成后注入失败
+        let mut session = service.client.start_session().await.unwrap();
+        session.start_transaction().await.unwrap();
+        let applied = service.apply_purchase(&investor, &plan, &mut session).await;
+        assert!(applied.is_ok());
+        let result = finish_transaction(session, applied.and(Err::<(), _>(ServiceError::InternalError("injected failure".to_string())))).await;
+        assert!(matches!(result, Err(ServiceError::InternalError(_))));
+
+        let stored = db.collection::<Invoice>("invoices").find_one(doc! { "_id": invoice_id }).await.unwrap().unwrap();
+        assert_eq!(stored.status, InvoiceStatus::OnSale);
+        let stored_user = db.collection::<User>("users").find_one(doc! { "wallet_address": &investor }).await.unwrap().unwrap();
+        assert_eq!(stored_user.balance.to_string(), user.balance.to_string());
+        let holdings = db.collection::<UserInvoiceHolding>("user_invoice_holdings").count_documents(doc! { "invoice_id": invoice_id }).await.unwrap();
+        assert_eq!(holdings, 0);
+        let audits = db.collection::<AuditLog>("audit_logs").count_documents(doc! { "target_id": invoice_id.to_hex() }).await.unwrap();
+        assert_eq!(audits, 0);
+        let mints = db.collection::<TokenMint>("token_mints").count_documents(doc! { "invoice_id": invoice_id }).await.unwrap();
+        assert_eq!(mints, 0);
+        test_db.cleanup().await;
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_legacy_listed_invoice_without_terms_is_purchasable() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let redis = Arc::new(InvoiceRedisService::new(unused_redis_client()));
+        let service = PurchaseService::new(Arc::new(db.clone()), redis);
+
+        let investor = format!("0xtest{}", ObjectId::new().to_hex());
+        let mut user = User::new(investor.clone(), "legacy-test".to_string(), common::domain::entity::UserRole::Investor);
+        user.balance = Decimal128::from_str("100").unwrap();
+        db.collection::<User>("users").insert_one(&user).await.unwrap();
+
+        let new_invoice = |status: InvoiceStatus| {
+            let mut invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+                payee: "0xpayee".to_string(),
+                payer: "0xpayer".to_string(),
+                amount: 100,
+                invoice_ipfs_hash: String::new(),
+                contract_ipfs_hash: String::new(),
+                due_date: 0,
+                currency: "USDC".to_string(),
+            });
+            invoice.status = status;
+            invoice.token_batch = Some("7".to_string());
+            invoice
+        };
+        // 条款确认上线前已上架的票据，以及尚未上架、也未接受条款的票据
+        let legacy = new_invoice(InvoiceStatus::OnSale);
+        let draft = new_invoice(InvoiceStatus::Verified);
+        db.collection::<Invoice>("invoices").insert_many([&legacy, &draft]).await.unwrap();
+
+        let exempted = crate::db::exempt_legacy_listed_invoices(&db).await.unwrap();
+        let exempted_again = crate::db::exempt_legacy_listed_invoices(&db).await.unwrap();
+
+        let plan = |invoice_number| PurchasePlan {
+            invoice_number,
+            amount: Decimal128::from_str("10").unwrap(),
+            shares: 1,
+            total_shares: 4,
+        };
+        let mut session = service.client.start_session().await.unwrap();
+        session.start_transaction().await.unwrap();
+        let legacy_purchase = service.apply_purchase(&investor, &plan(&legacy.invoice_number), &mut session).await;
+        session.abort_transaction().await.unwrap();
+        session.start_transaction().await.unwrap();
+        let draft_purchase = service.apply_purchase(&investor, &plan(&draft.invoice_number), &mut session).await;
+        session.abort_transaction().await.unwrap();
+        let stored_draft = db.collection::<Invoice>("invoices").find_one(doc! { "invoice_number": &draft.invoice_number }).await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((exempted, exempted_again), (1, 0));
+        assert!(legacy_purchase.is_ok());
+        assert!(matches!(draft_purchase, Err(ServiceError::TermsNotAccepted(_))));
+        assert!(!stored_draft.terms_exempt);
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_cancellation_refunds_balance_once() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let redis = Arc::new(InvoiceRedisService::new(unused_redis_client()));
+        let service = PurchaseService::new(Arc::new(db.clone()), redis);
+
+        let investor = format!("0xtest{}", ObjectId::new().to_hex());
+        let user = User::new(investor.clone(), "cancel-test".to_string(), common::domain::entity::UserRole::Investor);
+        db.collection::<User>("users").insert_one(&user).await.unwrap();
+        let mut invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 100,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        invoice.status = InvoiceStatus::Financed;
+        let invoice_id = db.collection::<Invoice>("invoices").insert_one(&invoice).await.unwrap().inserted_id.as_object_id().unwrap();
+        let holding = UserInvoiceHolding::new(investor.clone(), invoice_id, Decimal128::from_str("40").unwrap());
+        db.collection::<UserInvoiceHolding>("user_invoice_holdings").insert_one(&holding).await.unwrap();
+        let holdings = vec![holding];
+
+        let first = service.apply_cancellation(invoice_id, InvoiceStatus::Financed, &holdings, "0xadmin", "fraud").await.unwrap();
+        // 重复作废 (如并发请求) 不会再次退款
+        let second = service.apply_cancellation(invoice_id, InvoiceStatus::Financed, &holdings, "0xadmin", "fraud").await.unwrap();
+
+        let stored = db.collection::<Invoice>("invoices").find_one(doc! { "_id": invoice_id }).await.unwrap().unwrap();
+        let stored_user = db.collection::<User>("users").find_one(doc! { "wallet_address": &investor }).await.unwrap().unwrap();
+        let stored_holding = db.collection::<UserInvoiceHolding>("user_invoice_holdings")
+            .find_one(doc! { "holding_id": &holdings[0].holding_id }).await.unwrap().unwrap();
+        let audits = db.collection::<AuditLog>("audit_logs").count_documents(doc! { "target_id": invoice_id.to_hex(), "action": "cancel" }).await.unwrap();
+        test_db.cleanup().await;
+
+        assert!(first);
+        assert!(!second);
+        assert_eq!(stored.status, InvoiceStatus::Cancelled);
+        assert_eq!(parse_decimal(&stored_user.balance.to_string()).unwrap(), dec!(40));
+        assert_eq!(stored_holding.holding_status, common::domain::entity::HoldingStatus::Refunded);
+        assert_eq!(audits, 1);
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_purchase_queues_mint_to_investor_and_worker_mints_it() {
+        use common::domain::entity::TokenMintStatus;
+        use pharos_interact::mock::{MockContract, MockWrite};
+        use crate::service::{TokenMintConfig, TokenMintService};
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let redis = Arc::new(InvoiceRedisService::new(unused_redis_client()));
+        let service = PurchaseService::new(Arc::new(db.clone()), redis);
+
+        let investor = format!("0x{:0>40}", ObjectId::new().to_hex());
+        let mut user = User::new(investor.to_uppercase().replace("0X", "0x"), "mint-test".to_string(), common::domain::entity::UserRole::Investor);
+        user.balance = Decimal128::from_str("1000").unwrap();
+        db.collection::<User>("users").insert_one(&user).await.unwrap();
+
+        let mut invoice = Invoice::new(&common::domain::dto::invoice_dto::CreateInvoiceDto {
+            payee: "0xpayee".to_string(),
+            payer: "0xpayer".to_string(),
+            amount: 1_000,
+            invoice_ipfs_hash: String::new(),
+            contract_ipfs_hash: String::new(),
+            due_date: 0,
+            currency: "USDC".to_string(),
+        });
+        invoice.status = InvoiceStatus::OnSale;
+        invoice.token_batch = Some("7".to_string());
+        invoice.accepted_terms = Some(common::domain::entity::AcceptedTerms {
+            apr: "0.08".to_string(),
+            platform_fee_rate: "0.01".to_string(),
+            accepted_by: "0xpayee".to_string(),
+            accepted_at: bson::DateTime::now(),
+        });
+        db.collection::<Invoice>("invoices").insert_one(&invoice).await.unwrap();
+
+        // 7 份中认购 2 份
+        let plan = PurchasePlan {
+            invoice_number: &invoice.invoice_number,
+            amount: Decimal128::from_str("285").unwrap(),
+            shares: 2,
+            total_shares: 7,
+        };
+        let (holding, financed) = service.run_purchase_transaction(&user.wallet_address, &plan).await.unwrap();
+        // 7 份中认购 2 份，未募满
+        assert!(financed.is_none());
+        let queued = TokenMintRepository::new(&db).find_by_holding(&holding.holding_id).await.unwrap();
+
+        let contract = Arc::new(MockContract::default());
+        let worker = TokenMintService::new(&db, contract.clone(), TokenMintConfig { interval_secs: 1, batch_size: 10, max_attempts: 3, base_backoff_secs: 1 });
+        let minted = worker.process_due().await.unwrap();
+        let stored = TokenMintRepository::new(&db).find_by_holding(&holding.holding_id).await.unwrap();
+        test_db.cleanup().await;
+
+        let queued = queued.expect("mint not queued with the purchase");
+        assert_eq!((queued.status, queued.recipient.as_str(), queued.amount.as_str()), (TokenMintStatus::Pending, investor.as_str(), "285"));
+        assert_eq!(minted, 1);
+        assert_eq!(contract.writes(), vec![MockWrite {
+            operation: "purchase_shares",
+            args: vec!["7".to_string(), investor.clone(), "285".to_string()],
+        }]);
+        assert_eq!(stored.unwrap().status, TokenMintStatus::Minted);
+    }
 }
@@ -0,0 +1,122 @@
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::error::ServiceError;
+
+/// 解析还款金额：必须为正数，小数位不能超过稳定币精度 `scale`
+pub fn parse_repayment_amount(value: &str, scale: u32) -> Result<Decimal, ServiceError> {
+    let amount = Decimal::from_str(value.trim()).map_err(|e| ServiceError::InvalidRepaymentAmount(format!("'{}': {}", value, e)))?.normalize();
+    if amount <= Decimal::ZERO {
+        return Err(ServiceError::InvalidRepaymentAmount(format!("'{}' must be positive", value)));
+    }
+    if amount.scale() > scale {
+        return Err(ServiceError::InvalidRepaymentAmount(format!("'{}' has more than {} decimal places", value, scale)));
+    }
+    Ok(amount)
+}
+
+/// 按持仓比例拆分还款金额，与 `balances` 一一对应
+///
+/// 每份先按 `scale` 位小数向下取整，舍入余额以最小单位 (10^-scale) 逐份补给被舍去部分最大的持有人，
+/// 相同时按传入顺序，拆分结果之和等于 `total`。
+pub fn split_pro_rata(total: Decimal, balances: &[Decimal], scale: u32) -> Vec<Decimal> {
+    let supply: Decimal = balances.iter().sum();
+    if supply <= Decimal::ZERO {
+        return vec![Decimal::ZERO; balances.len()];
+    }
+
+    let mut shares = Vec::with_capacity(balances.len());
+    let mut remainders = Vec::with_capacity(balances.len());
+    for (index, balance) in balances.iter().enumerate() {
+        let exact = total * balance / supply;
+        let floored = exact.round_dp_with_strategy(scale, RoundingStrategy::ToZero);
+        shares.push(floored);
+        remainders.push((exact - floored, index));
+    }
+
+    // 每份舍去不足一个最小单位，余额单位数小于持有人数
+    let unit = Decimal::new(1, scale);
+    let allocated: Decimal = shares.iter().sum();
+    let leftover_units = ((total - allocated) / unit).round().to_usize().unwrap_or(0);
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, index) in remainders.into_iter().take(leftover_units) {
+        shares[index] += unit;
+    }
+    shares
+}
+
+/// 换算为链上最小单位的整数字符串，超出 `scale` 的小数部分舍去
+///
+/// 按尾数移位计算，不经过 `Decimal` 乘法 (精度 18 以上时 10^scale 会超出范围)；结果超出 i128 时返回错误。
+pub fn to_base_units(amount: Decimal, scale: u32) -> Result<String, ServiceError> {
+    let amount = amount.round_dp_with_strategy(scale, RoundingStrategy::ToZero);
+    10i128.checked_pow(scale - amount.scale())
+        .and_then(|factor| amount.mantissa().checked_mul(factor))
+        .map(|units| units.to_string())
+        .ok_or_else(|| ServiceError::InvalidRepaymentAmount(format!("{} does not fit in base units with {} decimals", amount, scale)))
+}
+
+/// 兑付金额不能少于应付总额 (投资人本金加已计提利息)，多付部分按持仓比例一并分配
+pub fn check_repayment_covers(total: Decimal, owed: Decimal, invoice_number: &str) -> Result<(), ServiceError> {
+    if total < owed {
+        return Err(ServiceError::InvalidRepaymentAmount(format!(
+            "{} is less than the {} owed to holders of invoice {}",
+            total, owed, invoice_number
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_equal_holders_remainder_goes_to_first() {
+        let shares = split_pro_rata(dec!(100), &[dec!(1), dec!(1), dec!(1)], 2);
+        assert_eq!(shares, vec![dec!(33.34), dec!(33.33), dec!(33.33)]);
+        assert_eq!(shares.iter().sum::<Decimal>(), dec!(100));
+    }
+
+    #[test]
+    fn test_remainder_goes_to_largest_fraction() {
+        // 精确值 2.857 / 4.286 / 2.857，取整后余 2 个单位补给小数部分较大的两位
+        let shares = split_pro_rata(dec!(10), &[dec!(2), dec!(3), dec!(2)], 0);
+        assert_eq!(shares, vec![dec!(3), dec!(4), dec!(3)]);
+
+        let shares = split_pro_rata(dec!(1000.01), &[dec!(600), dec!(250), dec!(150)], 2);
+        assert_eq!(shares, vec![dec!(600.01), dec!(250), dec!(150)]);
+        assert_eq!(shares.iter().sum::<Decimal>(), dec!(1000.01));
+    }
+
+    #[test]
+    fn test_amount_parsing_and_base_units() {
+        assert_eq!(parse_repayment_amount("100.50", 2).unwrap(), dec!(100.5));
+        assert!(matches!(parse_repayment_amount("1.001", 2), Err(ServiceError::InvalidRepaymentAmount(_))));
+        assert!(matches!(parse_repayment_amount("0", 6), Err(ServiceError::InvalidRepaymentAmount(_))));
+        assert!(parse_repayment_amount("abc", 6).is_err());
+
+        assert_eq!(to_base_units(dec!(33.34), 6).unwrap(), "33340000");
+        assert_eq!(to_base_units(dec!(100), 2).unwrap(), "10000");
+        assert_eq!(to_base_units(dec!(1.239), 2).unwrap(), "123");
+    }
+
+    #[test]
+    fn test_base_units_for_high_precision_tokens() {
+        // 10^20 超出 u64，旧实现会溢出
+        assert_eq!(to_base_units(dec!(1250.5), 18).unwrap(), "1250500000000000000000");
+        assert_eq!(to_base_units(dec!(0.000000000000000001), 18).unwrap(), "1");
+        assert_eq!(to_base_units(dec!(3), 30).unwrap(), "3000000000000000000000000000000");
+        assert!(matches!(to_base_units(Decimal::MAX, 30), Err(ServiceError::InvalidRepaymentAmount(_))));
+    }
+
+    #[test]
+    fn test_repayment_must_cover_amount_owed() {
+        assert!(check_repayment_covers(dec!(1050), dec!(1050), "INV-1").is_ok());
+        assert!(check_repayment_covers(dec!(1100), dec!(1050), "INV-1").is_ok());
+        assert!(matches!(check_repayment_covers(dec!(1049.99), dec!(1050), "INV-1"), Err(ServiceError::InvalidRepaymentAmount(_))));
+    }
+}
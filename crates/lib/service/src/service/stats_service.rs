@@ -0,0 +1,479 @@
+use std::sync::Arc;
+
+use futures::stream::TryStreamExt;
+use tracing::{info, warn};
+use mongodb::{
+    Database,
+    bson::{Bson, Document, doc},
+};
+use redis::{AsyncCommands, Client};
+
+use common::domain::dto::admin_stats_dto::{AdminStatsDto, StatusCountDto};
+use common::domain::dto::enterprise_performance_dto::EnterprisePerformanceDto;
+use common::domain::dto::platform_stats_dto::{MonthlyVolumeDto, PlatformStatsDto};
+use common::domain::entity::enterprise::Enterprise;
+use crate::error::ServiceError;
+
+const PLATFORM_STATS_CACHE_KEY: &str = "stats:platform";
+const ADMIN_STATS_CACHE_KEY: &str = "stats:admin";
+
+/// 平台统计服务，聚合结果缓存在 Redis 中
+pub struct StatsService {
+    db: Arc<Database>,
+    redis_client: Client,
+    cache_ttl_secs: u64,
+    admin_cache_ttl_secs: u64,
+}
+
+impl StatsService {
+    pub fn new(db: Arc<Database>, redis_client: Client, cache_ttl_secs: u64) -> Self {
+        Self { db, redis_client, cache_ttl_secs, admin_cache_ttl_secs: cache_ttl_secs }
+    }
+
+    /// 管理后台统计的缓存时间，默认与公开统计相同
+    pub fn with_admin_cache_ttl(mut self, admin_cache_ttl_secs: u64) -> Self {
+        self.admin_cache_ttl_secs = admin_cache_ttl_secs;
+        self
+    }
+
+    /// 获取平台公开统计数据 (优先读缓存)
+    pub async fn platform_stats(&self) -> Result<PlatformStatsDto, ServiceError> {
+        if let Some(cached) = self.get_cached::<PlatformStatsDto>(PLATFORM_STATS_CACHE_KEY).await {
+            return Ok(cached);
+        }
+
+        let stats = self.compute_platform_stats().await?;
+        self.set_cached(PLATFORM_STATS_CACHE_KEY, &stats, self.cache_ttl_secs).await;
+        Ok(stats)
+    }
+
+    /// 管理后台平台统计 (优先读缓存)
+    pub async fn admin_stats(&self) -> Result<AdminStatsDto, ServiceError> {
+        if let Some(cached) = self.get_cached::<AdminStatsDto>(ADMIN_STATS_CACHE_KEY).await {
+            return Ok(cached);
+        }
+
+        let stats = self.compute_admin_stats().await?;
+        self.set_cached(ADMIN_STATS_CACHE_KEY, &stats, self.admin_cache_ttl_secs).await;
+        Ok(stats)
+    }
+
+    async fn compute_platform_stats(&self) -> Result<PlatformStatsDto, ServiceError> {
+        info!("Computing platform statistics");
+
+        // 1. 累计融资金额
+        let total_volume_financed = self.total_volume_financed().await?;
+
+        // 2. 平均年化利率
+        let apr = self
+            .aggregate_one(
+                "token_batches",
+                vec![doc! { "$group": { "_id": Bson::Null, "avg": { "$avg": "$interest_rate_apy" } } }],
+            )
+            .await?;
+        let average_apr = apr.as_ref().map(|d| bson_number_to_string(d.get("avg"))).unwrap_or_else(|| "0".to_string());
+
+        // 3. 活跃票据数量
+        let active_invoices = self
+            .db
+            .collection::<Document>("invoices")
+            .count_documents(doc! { "status": { "$in": ["ON_SALE", "PACKAGED"] } })
+            .await?;
+
+        // 4. 投资人数 (按持仓去重)
+        let investors = self
+            .aggregate_one(
+                "user_invoice_holdings",
+                vec![doc! { "$group": { "_id": "$user_id" } }, doc! { "$count": "count" }],
+            )
+            .await?;
+        let total_investors = investors.as_ref().and_then(|d| bson_to_u64(d.get("count"))).unwrap_or(0);
+
+        // 5. 按月融资金额
+        let mut cursor = self
+            .db
+            .collection::<Document>("transactions")
+            .aggregate(vec![
+                doc! { "$match": { "transaction_type": "Purchase" } },
+                doc! { "$group": {
+                    "_id": { "$dateToString": { "format": "%Y-%m", "date": "$transaction_date" } },
+                    "volume": { "$sum": "$amount" }
+                } },
+                doc! { "$sort": { "_id": 1 } },
+            ])
+            .await?;
+        let mut monthly_volume = Vec::new();
+        while let Some(row) = cursor.try_next().await? {
+            monthly_volume.push(MonthlyVolumeDto {
+                month: row.get_str("_id").unwrap_or_default().to_string(),
+                volume: bson_number_to_string(row.get("volume")),
+            });
+        }
+
+        Ok(PlatformStatsDto {
+            total_volume_financed,
+            average_apr,
+            active_invoices,
+            total_investors,
+            monthly_volume,
+        })
+    }
+
+    async fn compute_admin_stats(&self) -> Result<AdminStatsDto, ServiceError> {
+        info!("Computing admin statistics");
+
+        let total_users = self.db.collection::<Document>("users").count_documents(doc! {}).await?;
+        let total_enterprises = self.db.collection::<Document>("enterprises").count_documents(doc! {}).await?;
+
+        let mut cursor = self
+            .db
+            .collection::<Document>("invoices")
+            .aggregate(vec![
+                doc! { "$group": { "_id": "$status", "count": { "$sum": 1 } } },
+                doc! { "$sort": { "_id": 1 } },
+            ])
+            .await?;
+        let mut invoices_by_status = Vec::new();
+        while let Some(row) = cursor.try_next().await? {
+            invoices_by_status.push(StatusCountDto {
+                status: row.get_str("_id").unwrap_or_default().to_string(),
+                count: bson_to_u64(row.get("count")).unwrap_or(0),
+            });
+        }
+
+        let total_volume_financed = self.total_volume_financed().await?;
+
+        // 未兑付本金：有效持仓的当前本金
+        let outstanding = self
+            .aggregate_one(
+                "user_invoice_holdings",
+                vec![
+                    doc! { "$match": { "holding_status": "Active" } },
+                    doc! { "$group": { "_id": Bson::Null, "total": { "$sum": "$current_balance" } } },
+                ],
+            )
+            .await?;
+        let outstanding_principal = outstanding.as_ref().map(|d| bson_number_to_string(d.get("total"))).unwrap_or_else(|| "0".to_string());
+
+        let minted = self
+            .aggregate_one(
+                "token_batches",
+                vec![doc! { "$group": { "_id": Bson::Null, "total": { "$sum": "$total_token_supply" } } }],
+            )
+            .await?;
+        let total_tokens_minted = minted.as_ref().map(|d| bson_number_to_string(d.get("total"))).unwrap_or_else(|| "0".to_string());
+
+        Ok(AdminStatsDto {
+            total_users,
+            total_enterprises,
+            invoices_by_status,
+            total_volume_financed,
+            outstanding_principal,
+            total_tokens_minted,
+        })
+    }
+
+    // 认购交易金额之和
+    async fn total_volume_financed(&self) -> Result<String, ServiceError> {
+        let volume = self
+            .aggregate_one(
+                "transactions",
+                vec![
+                    doc! { "$match": { "transaction_type": "Purchase" } },
+                    doc! { "$group": { "_id": Bson::Null, "total": { "$sum": "$amount" } } },
+                ],
+            )
+            .await?;
+        Ok(volume.as_ref().map(|d| bson_number_to_string(d.get("total"))).unwrap_or_else(|| "0".to_string()))
+    }
+
+    /// 企业历史履约表现：按付款方聚合票据，以首笔到期兑付交易时间判断是否按时兑付。
+    /// 只统计获得过融资且未作废的票据 (作废或从未融资的票据没有兑付义务)；
+    /// 没有兑付交易但状态为 REPAID 的历史票据以 `updated_at` 作为兑付时间。
+    pub async fn enterprise_performance(&self, enterprise: &Enterprise, grace_days: i64, now_ms: i64) -> Result<EnterprisePerformanceDto, ServiceError> {
+        let grace_ms = grace_days.max(0) * 24 * 3600 * 1000;
+        let wallet = &enterprise.wallet_address;
+        let mut cursor = self
+            .db
+            .collection::<Document>("invoices")
+            .aggregate(vec![
+                doc! { "$match": {
+                    "payer": { "$in": [wallet, wallet.to_lowercase()] },
+                    "status": { "$ne": "CANCELLED" },
+                    // 旧文档没有 funded_shares，按状态判断是否已融资
+                    "$or": [
+                        { "status": { "$in": ["FINANCED", "REPAID", "OVERDUE", "DEFAULTED"] } },
+                        { "funded_shares": { "$gt": 0 } },
+                    ],
+                } },
+                // localField/foreignField 形式使用 transactions 的 (invoice_id, transaction_type) 索引
+                doc! { "$lookup": {
+                    "from": "transactions",
+                    "localField": "_id",
+                    "foreignField": "invoice_id",
+                    "pipeline": [
+                        { "$match": { "transaction_type": "MaturityPayment" } },
+                        { "$group": { "_id": Bson::Null, "first": { "$min": "$transaction_date" } } },
+                    ],
+                    "as": "settlement",
+                } },
+                doc! { "$addFields": {
+                    // due_date 历史数据中既有秒也有毫秒
+                    "due_ms": { "$cond": [
+                        { "$lt": [{ "$abs": "$due_date" }, 100_000_000_000_i64] },
+                        { "$multiply": ["$due_date", 1000] },
+                        "$due_date",
+                    ] },
+                    "settled_at": { "$ifNull": [
+                        { "$arrayElemAt": ["$settlement.first", 0] },
+                        { "$cond": [{ "$eq": ["$status", "REPAID"] }, "$updated_at", Bson::Null] },
+                    ] },
+                } },
+                doc! { "$addFields": { "outcome": { "$switch": {
+                    "branches": [
+                        { "case": { "$eq": ["$status", "DEFAULTED"] }, "then": "defaulted" },
+                        { "case": { "$eq": [{ "$type": "$settled_at" }, "date"] }, "then": { "$cond": [
+                            { "$lte": [{ "$toLong": "$settled_at" }, { "$add": ["$due_ms", grace_ms] }] },
+                            "on_time",
+                            "late",
+                        ] } },
+                        { "case": { "$or": [
+                            { "$eq": ["$status", "OVERDUE"] },
+                            { "$lt": [{ "$add": ["$due_ms", grace_ms] }, now_ms] },
+                        ] }, "then": "overdue" },
+                    ],
+                    "default": "outstanding",
+                } } } },
+                doc! { "$group": { "_id": "$outcome", "count": { "$sum": 1 } } },
+            ])
+            .await?;
+
+        let mut counts = OutcomeCounts::default();
+        while let Some(row) = cursor.try_next().await? {
+            let count = bson_to_u64(row.get("count")).unwrap_or(0);
+            match row.get_str("_id").unwrap_or_default() {
+                "on_time" => counts.on_time = count,
+                "late" => counts.late = count,
+                "overdue" => counts.overdue = count,
+                "defaulted" => counts.defaulted = count,
+                _ => counts.outstanding += count,
+            }
+        }
+
+        Ok(counts.into_dto(
+            enterprise.id.map(|id| id.to_hex()).unwrap_or_default(),
+            wallet.clone(),
+            grace_days,
+        ))
+    }
+
+    // 执行聚合并返回第一条结果
+    async fn aggregate_one(&self, collection: &str, pipeline: Vec<Document>) -> Result<Option<Document>, ServiceError> {
+        let mut cursor = self.db.collection::<Document>(collection).aggregate(pipeline).await?;
+        Ok(cursor.try_next().await?)
+    }
+
+    // 缓存读写失败只记录日志，不影响主流程
+    async fn get_cached<T: serde::de::DeserializeOwned>(&self, key: &str) -> Option<T> {
+        let mut conn = match self.redis_client.get_multiplexed_async_connection().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                warn!("Redis unavailable when reading {}: {}", key, e);
+                return None;
+            }
+        };
+        let cached: Option<String> = conn.get(key).await.ok()?;
+        cached.and_then(|json| serde_json::from_str(&json).ok())
+    }
+
+    async fn set_cached<T: serde::Serialize>(&self, key: &str, value: &T, ttl_secs: u64) {
+        if ttl_secs == 0 {
+            return;
+        }
+        let json = match serde_json::to_string(value) {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+        match self.redis_client.get_multiplexed_async_connection().await {
+            Ok(mut conn) => {
+                if let Err(e) = conn.set_ex::<_, _, ()>(key, json, ttl_secs).await {
+                    warn!("Failed to cache {}: {}", key, e);
+                }
+            }
+            Err(e) => warn!("Redis unavailable when writing {}: {}", key, e),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct OutcomeCounts {
+    on_time: u64,
+    late: u64,
+    overdue: u64,
+    defaulted: u64,
+    outstanding: u64,
+}
+
+impl OutcomeCounts {
+    fn into_dto(self, enterprise_id: String, wallet_address: String, grace_days: i64) -> EnterprisePerformanceDto {
+        let matured = self.on_time + self.late + self.overdue + self.defaulted;
+        EnterprisePerformanceDto {
+            enterprise_id,
+            wallet_address,
+            total_invoices: matured + self.outstanding,
+            matured_invoices: matured,
+            settled_on_time: self.on_time,
+            settled_late: self.late,
+            overdue: self.overdue,
+            defaulted: self.defaulted,
+            outstanding: self.outstanding,
+            on_time_rate: rate(self.on_time, matured),
+            late_rate: rate(self.late, matured),
+            default_rate: rate(self.defaulted, matured),
+            delinquency_rate: rate(self.late + self.overdue, matured),
+            grace_days,
+        }
+    }
+}
+
+// 分母为 0 时返回 "0.0000"
+fn rate(numerator: u64, denominator: u64) -> String {
+    if denominator == 0 {
+        return "0.0000".to_string();
+    }
+    format!("{:.4}", numerator as f64 / denominator as f64)
+}
+
+// 聚合结果可能是 Decimal128 / Double / Int，统一转成字符串
+fn bson_number_to_string(value: Option<&Bson>) -> String {
+    match value {
+        Some(Bson::Decimal128(d)) => d.to_string(),
+        Some(Bson::Double(f)) => f.to_string(),
+        Some(Bson::Int32(i)) => i.to_string(),
+        Some(Bson::Int64(i)) => i.to_string(),
+        _ => "0".to_string(),
+    }
+}
+
+fn bson_to_u64(value: Option<&Bson>) -> Option<u64> {
+    match value {
+        Some(Bson::Int32(i)) => Some(*i as u64),
+        Some(Bson::Int64(i)) => Some(*i as u64),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_rates_use_matured_invoices_as_denominator() {
+        let counts = OutcomeCounts { on_time: 6, late: 2, overdue: 1, defaulted: 1, outstanding: 5 };
+        let dto = counts.into_dto("e".into(), "0xabc".into(), 3);
+        assert_eq!(dto.total_invoices, 15);
+        assert_eq!(dto.matured_invoices, 10);
+        assert_eq!(dto.on_time_rate, "0.6000");
+        assert_eq!(dto.late_rate, "0.2000");
+        assert_eq!(dto.default_rate, "0.1000");
+        assert_eq!(dto.delinquency_rate, "0.3000");
+
+        let empty = OutcomeCounts { outstanding: 2, ..Default::default() }.into_dto("e".into(), "0xabc".into(), 3);
+        assert_eq!(empty.on_time_rate, "0.0000");
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_admin_stats_aggregates_seeded_records() {
+        use std::str::FromStr;
+        use mongodb::bson::Decimal128;
+        use crate::test_support::{TestDb, unused_redis_client};
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let dec = |v: &str| Decimal128::from_str(v).unwrap();
+
+        db.collection::<Document>("users").insert_many(vec![doc! { "wallet_address": "0xa" }, doc! { "wallet_address": "0xb" }]).await.unwrap();
+        db.collection::<Document>("enterprises").insert_one(doc! { "name": "ACME" }).await.unwrap();
+        db.collection::<Document>("invoices")
+            .insert_many(vec![doc! { "status": "ON_SALE" }, doc! { "status": "FINANCED" }, doc! { "status": "FINANCED" }])
+            .await
+            .unwrap();
+        db.collection::<Document>("transactions")
+            .insert_many(vec![
+                doc! { "transaction_type": "Purchase", "amount": dec("100.5") },
+                doc! { "transaction_type": "Purchase", "amount": dec("200") },
+                doc! { "transaction_type": "MaturityPayment", "amount": dec("999") },
+            ])
+            .await
+            .unwrap();
+        db.collection::<Document>("user_invoice_holdings")
+            .insert_many(vec![
+                doc! { "holding_status": "Active", "current_balance": dec("150") },
+                doc! { "holding_status": "Matured", "current_balance": dec("80") },
+            ])
+            .await
+            .unwrap();
+        db.collection::<Document>("token_batches").insert_one(doc! { "total_token_supply": dec("1000") }).await.unwrap();
+
+        let service = StatsService::new(Arc::new(db.clone()), unused_redis_client(), 0);
+        let stats = service.compute_admin_stats().await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(stats.total_users, 2);
+        assert_eq!(stats.total_enterprises, 1);
+        let by_status: Vec<_> = stats.invoices_by_status.iter().map(|s| (s.status.as_str(), s.count)).collect();
+        assert_eq!(by_status, vec![("FINANCED", 2), ("ON_SALE", 1)]);
+        assert_eq!(stats.total_volume_financed, "300.5");
+        assert_eq!(stats.outstanding_principal, "150");
+        assert_eq!(stats.total_tokens_minted, "1000");
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_enterprise_performance_counts_financed_invoices_owed_by_payer() {
+        use mongodb::bson::{DateTime, oid::ObjectId};
+        use crate::test_support::{TestDb, unused_redis_client};
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = test_db.database();
+        let now_ms = 1_750_000_000_000_i64;
+        let day_ms = 24 * 3600 * 1000;
+        let payer = "0xPayer";
+        let invoice = |payer: &str, status: &str, funded_shares: i64, due_ms: i64| {
+            doc! { "_id": ObjectId::new(), "payer": payer, "payee": "0xissuer", "status": status, "funded_shares": funded_shares, "due_date": due_ms, "updated_at": DateTime::from_millis(due_ms) }
+        };
+        let on_time = invoice(payer, "REPAID", 10, now_ms - 30 * day_ms);
+        let late = invoice("0xpayer", "REPAID", 10, now_ms - 30 * day_ms);
+        let rows = vec![
+            on_time.clone(),
+            late.clone(),
+            invoice(payer, "DEFAULTED", 10, now_ms - 60 * day_ms),
+            // 部分认购后到期未兑付
+            invoice(payer, "ON_SALE", 3, now_ms - 10 * day_ms),
+            invoice(payer, "FINANCED", 10, now_ms + 30 * day_ms),
+            // 作废、从未融资、以及本企业作为收款方的票据不计入
+            invoice(payer, "CANCELLED", 10, now_ms - 30 * day_ms),
+            invoice(payer, "ON_SALE", 0, now_ms - 30 * day_ms),
+            invoice(payer, "VERIFIED", 0, now_ms - 30 * day_ms),
+            doc! { "payer": "0xother", "payee": payer, "status": "DEFAULTED", "funded_shares": 10_i64, "due_date": now_ms - 30 * day_ms },
+        ];
+        db.collection::<Document>("invoices").insert_many(rows).await.unwrap();
+        let payment = |invoice: &Document, at_ms: i64| {
+            doc! { "invoice_id": invoice.get_object_id("_id").unwrap(), "transaction_type": "MaturityPayment", "transaction_date": DateTime::from_millis(at_ms) }
+        };
+        db.collection::<Document>("transactions")
+            .insert_many(vec![payment(&on_time, now_ms - 30 * day_ms), payment(&late, now_ms - 20 * day_ms)])
+            .await
+            .unwrap();
+
+        let enterprise = Enterprise::new("ACME".to_string(), payer.to_string());
+        let service = StatsService::new(Arc::new(db.clone()), unused_redis_client(), 0);
+        let dto = service.enterprise_performance(&enterprise, 3, now_ms).await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((dto.settled_on_time, dto.settled_late, dto.overdue, dto.defaulted, dto.outstanding), (1, 1, 1, 1, 1));
+        assert_eq!(dto.total_invoices, 5);
+        assert_eq!(dto.default_rate, "0.2500");
+    }
+}
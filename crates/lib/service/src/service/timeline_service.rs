@@ -0,0 +1,171 @@
+use mongodb::Database;
+use mongodb::bson::oid::ObjectId;
+use serde_json::json;
+
+use common::domain::dto::timeline_dto::TimelineEntryDto;
+use common::domain::entity::{Invoice, TransactionType};
+use crate::error::ServiceError;
+use crate::repository::{AuditLogRepository, InvoiceRepository, TransactionRepository};
+use crate::service::interest_calculator::due_date_to_millis;
+
+/// 时间线查看者的权限范围
+#[derive(Debug, Clone, PartialEq)]
+pub enum TimelineViewer {
+    /// 平台管理员：全部记录
+    Admin,
+    /// 票据收款方/付款方：全部记录
+    Party(String),
+    /// 其他用户 (投资人)：不含审计记录，其他投资人的地址被隐藏
+    Public(String),
+}
+
+impl TimelineViewer {
+    /// 根据是否管理员及与票据的关系确定查看范围
+    pub fn resolve(is_admin: bool, viewer_address: &str, invoice: &Invoice) -> Self {
+        if is_admin {
+            TimelineViewer::Admin
+        } else if invoice.payee.eq_ignore_ascii_case(viewer_address) || invoice.payer.eq_ignore_ascii_case(viewer_address) {
+            TimelineViewer::Party(viewer_address.to_string())
+        } else {
+            TimelineViewer::Public(viewer_address.to_string())
+        }
+    }
+
+    fn can_see_audit(&self) -> bool {
+        !matches!(self, TimelineViewer::Public(_))
+    }
+
+    fn can_see_actor(&self, actor: &str) -> bool {
+        match self {
+            TimelineViewer::Public(address) => address.eq_ignore_ascii_case(actor),
+            _ => true,
+        }
+    }
+}
+
+/// 票据活动时间线：合并票据生命周期、链上信息、购买/兑付记录和审计日志
+///
+/// 目前没有独立的链上事件索引，链上记录取自票据中同步的区块链字段。
+pub struct InvoiceTimelineService {
+    invoice_repo: InvoiceRepository,
+    transaction_repo: TransactionRepository,
+    audit_repo: AuditLogRepository,
+}
+
+impl InvoiceTimelineService {
+    pub fn new(db: &Database) -> Self {
+        Self {
+            invoice_repo: InvoiceRepository::new(db),
+            transaction_repo: TransactionRepository::new(db),
+            audit_repo: AuditLogRepository::new(db),
+        }
+    }
+
+    pub async fn find_invoice(&self, invoice_id: ObjectId) -> Result<Invoice, ServiceError> {
+        self.invoice_repo.find_by_id(invoice_id).await?
+            .ok_or_else(|| ServiceError::InvoiceNotFound(invoice_id.to_hex()))
+    }
+
+    /// 返回按时间升序排列的最近 `limit` 条记录
+    pub async fn timeline(&self, invoice: &Invoice, viewer: &TimelineViewer, limit: usize) -> Result<Vec<TimelineEntryDto>, ServiceError> {
+        let invoice_id = invoice.id.ok_or_else(|| ServiceError::InternalError("Invoice without id".to_string()))?;
+        let mut entries = lifecycle_entries(invoice);
+
+        // 购买与兑付记录 (每日计息记录量太大，不放入时间线)
+        for tx in self.transaction_repo.find_by_invoice_id(invoice_id).await? {
+            let (source, event) = match tx.transaction_type {
+                TransactionType::Purchase => ("purchase", "purchase"),
+                TransactionType::MaturityPayment => ("settlement", "maturity_payment"),
+                TransactionType::Withdrawal => ("settlement", "withdrawal"),
+                TransactionType::InterestAccrual => continue,
+            };
+            entries.push(TimelineEntryDto {
+                timestamp: tx.transaction_date.timestamp_millis(),
+                source: source.to_string(),
+                event: event.to_string(),
+                actor: Some(tx.user_id.clone()).filter(|a| viewer.can_see_actor(a)),
+                amount: Some(tx.amount.to_string()),
+                details: Some(json!({ "status": tx.status })),
+            });
+        }
+
+        if viewer.can_see_audit() {
+            for log in self.audit_repo.find_by_target("invoice", &invoice_id.to_hex()).await? {
+                entries.push(TimelineEntryDto {
+                    timestamp: log.created_at.timestamp_millis(),
+                    source: "audit".to_string(),
+                    event: log.action,
+                    actor: Some(log.actor),
+                    amount: None,
+                    details: log.details.map(|d| serde_json::to_value(d).unwrap_or_default()),
+                });
+            }
+        }
+
+        entries.sort_by_key(|e| e.timestamp);
+        let skip = entries.len().saturating_sub(limit);
+        Ok(entries.split_off(skip))
+    }
+}
+
+// 从票据本身推导出的生命周期事件
+fn lifecycle_entries(invoice: &Invoice) -> Vec<TimelineEntryDto> {
+    let mut entries = vec![TimelineEntryDto {
+        timestamp: invoice.created_at.timestamp_millis(),
+        source: "lifecycle".to_string(),
+        event: "invoice.created".to_string(),
+        actor: Some(invoice.payee.clone()),
+        amount: Some(invoice.amount.to_string()),
+        details: Some(json!({ "invoice_number": invoice.invoice_number, "currency": invoice.currency })),
+    }];
+
+    // blockchain_timestamp 为链上事件的秒级时间戳
+    if let Some(ts) = invoice.blockchain_timestamp.as_deref().and_then(|t| t.parse::<i64>().ok()) {
+        entries.push(TimelineEntryDto {
+            timestamp: due_date_to_millis(ts),
+            source: "onchain".to_string(),
+            event: "invoice.recorded_onchain".to_string(),
+            actor: None,
+            amount: None,
+            details: Some(json!({ "token_batch": invoice.token_batch, "is_valid": invoice.is_valid })),
+        });
+    }
+
+    if let Some(terms) = &invoice.accepted_terms {
+        entries.push(TimelineEntryDto {
+            timestamp: terms.accepted_at.timestamp_millis(),
+            source: "lifecycle".to_string(),
+            event: "terms.accepted".to_string(),
+            actor: Some(terms.accepted_by.clone()),
+            amount: None,
+            details: Some(json!({ "apr": terms.apr, "platform_fee_rate": terms.platform_fee_rate })),
+        });
+    }
+
+    if invoice.is_cleared == Some(true) {
+        entries.push(TimelineEntryDto {
+            timestamp: invoice.updated_at.timestamp_millis(),
+            source: "onchain".to_string(),
+            event: "invoice.cleared".to_string(),
+            actor: None,
+            amount: None,
+            details: None,
+        });
+    }
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_viewer_only_sees_own_address() {
+        let viewer = TimelineViewer::Public("0xAbC".to_string());
+        assert!(viewer.can_see_actor("0xabc"));
+        assert!(!viewer.can_see_actor("0xdef"));
+        assert!(!viewer.can_see_audit());
+        assert!(TimelineViewer::Admin.can_see_audit());
+    }
+}
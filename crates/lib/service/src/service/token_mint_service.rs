@@ -0,0 +1,220 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tracing::{error, info, warn};
+use mongodb::{Database, bson::DateTime};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use common::domain::entity::TokenMint;
+use pharos_interact::{TxPossiblySubmitted, extract_tx_hash};
+use crate::error::ServiceError;
+use crate::repository::{TokenMintRepository, TransactionRepository, UserInvoiceHoldingRepository};
+use crate::service::contract_recorder::SharedContractWriter;
+use crate::service::webhook_service::backoff_delay;
+
+/// 单次提交的租约时间，需覆盖等待回执的耗时；超过后视为进程已崩溃，可被重新抢占
+const MINT_LEASE_MS: i64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone)]
+pub struct TokenMintConfig {
+    pub interval_secs: u64,
+    /// 每轮最多提交的铸造数
+    pub batch_size: i64,
+    pub max_attempts: u32,
+    pub base_backoff_secs: u64,
+}
+
+/// 认购代币铸造
+///
+/// 认购事务内写入 `token_mints`，本服务在后台按认购人提交 `purchaseSharesFor`，认购接口不等待链上回执。
+/// 交易未发出的失败按指数退避重试；交易可能已广播 (等待回执失败等) 时不再重试，标记为 Failed 等待对账，避免重复铸造。
+pub struct TokenMintService {
+    repo: TokenMintRepository,
+    transactions: TransactionRepository,
+    holdings: UserInvoiceHoldingRepository,
+    writer: SharedContractWriter,
+    config: TokenMintConfig,
+}
+
+impl TokenMintService {
+    pub fn new(db: &Database, writer: SharedContractWriter, config: TokenMintConfig) -> Self {
+        Self {
+            repo: TokenMintRepository::new(db),
+            transactions: TransactionRepository::new(db),
+            holdings: UserInvoiceHoldingRepository::new(db),
+            writer,
+            config,
+        }
+    }
+
+    /// 把铸造交易哈希写入认购的交易记录和持仓 (`metadata.tx_hash`)，供资金流水和认购记录展示。
+    /// 铸造已完成，写入失败只记录日志
+    async fn record_purchase_tx_hash(&self, holding_id: &str, tx_hash: &str) {
+        if let Err(e) = self.transactions.set_purchase_tx_hash(holding_id, tx_hash).await {
+            error!("Failed to record mint tx {} on purchase transaction of holding {}: {}", tx_hash, holding_id, e);
+        }
+        if let Err(e) = self.holdings.set_purchase_tx_hash(holding_id, tx_hash).await {
+            error!("Failed to record mint tx {} on holding {}: {}", tx_hash, holding_id, e);
+        }
+    }
+
+    /// 提交一批到期的铸造，返回本轮铸造成功的数量
+    pub async fn process_due(&self) -> Result<usize, ServiceError> {
+        let due = self.repo.find_due(self.config.max_attempts, self.config.batch_size).await?;
+        let mut minted = 0;
+        for mint in due {
+            let Some(id) = mint.id else { continue };
+            // 已被其他实例抢占
+            let Some(mint) = self.repo.claim_due(id, self.config.max_attempts, MINT_LEASE_MS).await? else { continue };
+            if self.mint(&mint).await? {
+                minted += 1;
+            }
+        }
+        Ok(minted)
+    }
+
+    async fn mint(&self, mint: &TokenMint) -> Result<bool, ServiceError> {
+        let id = mint.id.expect("claimed mint has an id");
+        let result = self.writer.purchase_shares(mint.batch_id.clone(), mint.recipient.clone(), mint.amount.clone()).await;
+        match result {
+            Ok(receipt) => {
+                let tx_hash = receipt.map(|r| format!("{:?}", r.transaction_hash));
+                self.repo.mark_minted(id, tx_hash.as_deref()).await?;
+                if let Some(tx_hash) = &tx_hash {
+                    self.record_purchase_tx_hash(&mint.holding_id, tx_hash).await;
+                }
+                info!("Minted {} tokens of batch {} to {} for holding {}", mint.amount, mint.batch_id, mint.recipient, mint.holding_id);
+                Ok(true)
+            }
+            Err(e) => {
+                let message = format!("{:#}", e);
+                let tx_hash = extract_tx_hash(&message);
+                if tx_hash.is_some() || e.downcast_ref::<TxPossiblySubmitted>().is_some() {
+                    error!(
+                        "Minting {} tokens of batch {} to {} (holding {}) may have been submitted in {:?}: {}. Needs reconciliation.",
+                        mint.amount, mint.batch_id, mint.recipient, mint.holding_id, tx_hash, message
+                    );
+                    self.repo.mark_failed(id, tx_hash.as_deref(), &message).await?;
+                } else if mint.attempts >= self.config.max_attempts {
+                    error!(
+                        "Minting {} tokens of batch {} to {} (holding {}) failed after {} attempts: {}. Needs reconciliation.",
+                        mint.amount, mint.batch_id, mint.recipient, mint.holding_id, mint.attempts, message
+                    );
+                    self.repo.mark_failed(id, None, &message).await?;
+                } else {
+                    let backoff = backoff_delay(self.config.base_backoff_secs, mint.attempts);
+                    warn!("Minting for holding {} attempt {} failed: {}, retrying in {:?}", mint.holding_id, mint.attempts, message, backoff);
+                    let next = DateTime::from_millis(DateTime::now().timestamp_millis() + backoff.as_millis() as i64);
+                    self.repo.schedule_retry(id, next, &message).await?;
+                }
+                Ok(false)
+            }
+        }
+    }
+
+    /// 启动后台铸造任务，`shutdown` 变为 true 后退出 (进行中的提交完成后)
+    pub fn spawn_worker(self: &Arc<Self>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(service.config.interval_secs.max(1)));
+            while !*shutdown.borrow() {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                if let Err(e) = service.process_due().await {
+                    error!("Token minting failed: {}", e);
+                }
+            }
+            info!("Token mint worker stopped");
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mongodb::bson::oid::ObjectId;
+    use pharos_interact::mock::{MockContract, MockWrite};
+    use common::domain::entity::{TokenMintStatus, Transaction, UserInvoiceHolding};
+    use mongodb::bson::Decimal128;
+    use std::str::FromStr;
+    use crate::test_support::TestDb;
+
+    fn config(max_attempts: u32) -> TokenMintConfig {
+        TokenMintConfig { interval_secs: 1, batch_size: 10, max_attempts, base_backoff_secs: 0 }
+    }
+
+    async fn insert(test_db: &TestDb, mint: &TokenMint) {
+        test_db.collection::<TokenMint>("token_mints").insert_one(mint).await.unwrap();
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_mints_to_recipient_once() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let contract = Arc::new(MockContract::default());
+        let service = TokenMintService::new(&test_db, contract.clone(), config(3));
+        let invoice_id = ObjectId::new();
+        insert(&test_db, &TokenMint::new("h-1".to_string(), invoice_id, "7".to_string(), "0xinvestor".to_string(), "285".to_string())).await;
+        let amount = Decimal128::from_str("285").unwrap();
+        let mut holding = UserInvoiceHolding::new("0xinvestor".to_string(), invoice_id, amount);
+        holding.holding_id = "h-1".to_string();
+        service.holdings.create(holding).await.unwrap();
+        service.transactions.create(Transaction::new_purchase("0xinvestor".to_string(), invoice_id, "h-1".to_string(), amount)).await.unwrap();
+
+        let first = service.process_due().await.unwrap();
+        let second = service.process_due().await.unwrap();
+        let stored = service.repo.find_by_holding("h-1").await.unwrap().unwrap();
+        let purchase = service.transactions.find_by_holding_id("h-1").await.unwrap();
+        let holding = service.holdings.find_by_user_id_and_holding_id("0xinvestor", "h-1").await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((first, second), (1, 0));
+        assert_eq!(contract.writes(), vec![MockWrite {
+            operation: "purchase_shares",
+            args: vec!["7".to_string(), "0xinvestor".to_string(), "285".to_string()],
+        }]);
+        assert_eq!(stored.status, TokenMintStatus::Minted);
+        // 资金流水与认购记录读取的链上交易哈希
+        let tx_hash = stored.tx_hash.expect("minted with a receipt");
+        let recorded = |metadata: Option<&mongodb::bson::Document>| metadata.and_then(|m| m.get_str("tx_hash").ok()).map(str::to_string);
+        assert_eq!(recorded(purchase[0].metadata.as_ref()), Some(tx_hash.clone()));
+        assert_eq!(recorded(holding.metadata.as_ref()), Some(tx_hash));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_failed_mint_is_retried_then_given_up() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let service = TokenMintService::new(&test_db, Arc::new(MockContract::default().unavailable("rpc down")), config(2));
+        insert(&test_db, &TokenMint::new("h-1".to_string(), ObjectId::new(), "7".to_string(), "0xinvestor".to_string(), "285".to_string())).await;
+
+        service.process_due().await.unwrap();
+        let retrying = service.repo.find_by_holding("h-1").await.unwrap().unwrap();
+        service.process_due().await.unwrap();
+        let failed = service.repo.find_by_holding("h-1").await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((retrying.status, retrying.attempts), (TokenMintStatus::Pending, 1));
+        assert_eq!(retrying.last_error.as_deref(), Some("rpc down"));
+        assert_eq!((failed.status, failed.attempts), (TokenMintStatus::Failed, 2));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_possibly_submitted_mint_is_not_retried() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let receipt_error = format!("Failed to get purchaseSharesFor transaction receipt, transaction_hash: {:?}: timeout", ethers::types::H256::repeat_byte(1));
+        let service = TokenMintService::new(&test_db, Arc::new(MockContract::default().failing_writes(&receipt_error)), config(5));
+        insert(&test_db, &TokenMint::new("h-1".to_string(), ObjectId::new(), "7".to_string(), "0xinvestor".to_string(), "285".to_string())).await;
+
+        service.process_due().await.unwrap();
+        let stored = service.repo.find_by_holding("h-1").await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!((stored.status, stored.attempts), (TokenMintStatus::Failed, 1));
+        assert_eq!(stored.tx_hash, Some(format!("{:?}", ethers::types::H256::repeat_byte(1))));
+    }
+}
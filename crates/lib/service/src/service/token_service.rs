@@ -1,7 +1,8 @@
 use std::sync::Arc;
 use anyhow::Result;
 use chrono::Utc;
-use log::{debug, error, info};
+use ethers::types::U256;
+use tracing::{debug, error, info};
 use mongodb::bson::{oid::ObjectId, DateTime, Decimal128, doc};
 use rust_decimal::Decimal;
 use std::str::FromStr;
@@ -15,6 +16,9 @@ use common::domain::entity::{
     invoice_batch::InvoiceBatchStatus
 };
 use common::domain::entity::token::CreateTokenBatchFromInvoiceBatchRequest;
+use common::domain::dto::token_holder_dto::{TokenHolderDistributionDto, TokenHolderDto};
+use common::pagination::OffsetPagination;
+use crate::cache::TokenHolderCache;
 use crate::error::ServiceError;
 use crate::repository::{
     TokenRepository, InvoiceRepository, EnterpriseRepository, UserRepository, InvoiceBatchRepository
@@ -27,6 +31,7 @@ pub struct TokenService {
     enterprise_repository: Arc<EnterpriseRepository>,
     user_repository: Arc<UserRepository>,
     database: Arc<Database>,
+    holder_cache: TokenHolderCache,
 }
 
 impl TokenService {
@@ -36,6 +41,7 @@ impl TokenService {
         enterprise_repository: Arc<EnterpriseRepository>,
         user_repository: Arc<UserRepository>,
         database: Arc<Database>,
+        holder_cache: TokenHolderCache,
     ) -> Self {
         Self {
             token_repository,
@@ -43,6 +49,7 @@ impl TokenService {
             enterprise_repository,
             user_repository,
             database,
+            holder_cache,
         }
     }
 
@@ -348,6 +355,8 @@ impl TokenService {
 
         self.token_repository.create_token_transaction(token_transaction).await
             .map_err(|e| ServiceError::MongoDbError(format!("Failed to create token transaction: {}", e)))?;
+        // 新的转账记录会改变持有人分布
+        self.holder_cache.invalidate(&batch_id.to_hex()).await;
 
         // Update token batch available amount
         let mut updated_batch = token_batch.clone();
@@ -400,6 +409,55 @@ impl TokenService {
         Ok(holding_id.to_hex())
     }
 
+    /// Get the holder distribution of a token batch, sorted by balance descending.
+    /// Returns one page of holders together with the total holder count.
+    pub async fn get_token_holders(
+        &self,
+        batch_id: &str,
+        pagination: OffsetPagination,
+    ) -> Result<(Vec<TokenHolderDto>, u64), ServiceError> {
+        let distribution = self.token_holder_distribution(batch_id).await?;
+        let total = distribution.holders.len() as u64;
+        let rows = distribution.holders.into_iter()
+            .skip(usize::try_from(pagination.skip()).unwrap_or(usize::MAX))
+            .take(usize::try_from(pagination.page_size).unwrap_or(usize::MAX))
+            .collect();
+        Ok((rows, total))
+    }
+
+    async fn token_holder_distribution(&self, batch_id: &str) -> Result<TokenHolderDistributionDto, ServiceError> {
+        let batch_oid = ObjectId::parse_str(batch_id)
+            .map_err(|_| ServiceError::InternalError(format!("Invalid token ID: {}", batch_id)))?;
+        if let Some(cached) = self.holder_cache.get(batch_id).await {
+            return Ok(cached);
+        }
+
+        let token_batch = self.token_repository.get_token_batch_by_id(batch_oid).await
+            .map_err(|e| ServiceError::NotFound(format!("Token batch not found: {}", e)))?;
+        let total_supply = Decimal::from_str(&token_batch.total_token_supply.to_string())
+            .map_err(|e| ServiceError::InternalError(format!("Failed to parse total supply: {}", e)))?;
+
+        let balances = self.token_repository.aggregate_holder_balances(batch_oid).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to aggregate token holders: {}", e)))?;
+        let balances = balances.into_iter()
+            .filter_map(|(user_id, balance)| {
+                let parsed = Decimal::from_str(&balance.to_string()).ok();
+                if parsed.is_none() {
+                    error!("Skipping unparsable balance {} of holder {}", balance, user_id);
+                }
+                parsed.map(|b| (user_id.to_hex(), b))
+            })
+            .collect();
+
+        let distribution = TokenHolderDistributionDto {
+            batch_id: batch_id.to_string(),
+            total_supply: total_supply.normalize().to_string(),
+            holders: build_holder_distribution(balances, total_supply),
+        };
+        self.holder_cache.set(&distribution).await;
+        Ok(distribution)
+    }
+
     /// Get token holdings for a user
     pub async fn get_user_token_holdings(
         &self,
@@ -434,6 +492,23 @@ impl TokenService {
         Ok(responses)
     }
 
+    /// 钱包在某代币合约上的数据库记账余额 (已完成转账净额)。合约地址没有对应批次时返回 NotFound，
+    /// 钱包未注册或没有持仓时余额为 0
+    pub async fn recorded_balance(&self, token_address: &str, wallet_address: &str) -> Result<Decimal, ServiceError> {
+        let batch = self.token_repository.find_token_batch_by_contract_address(token_address).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to find token batch: {}", e)))?
+            .ok_or_else(|| ServiceError::NotFound(format!("No token batch for contract {}", token_address)))?;
+        let batch_id = batch.id.ok_or_else(|| ServiceError::InternalError("Token batch is missing ID".to_string()))?;
+
+        let Some(user_id) = self.user_repository.find_by_wallet_address(wallet_address).await?.and_then(|u| u.id) else {
+            return Ok(Decimal::ZERO);
+        };
+
+        let balance = self.token_repository.holder_balance(batch_id, user_id).await
+            .map_err(|e| ServiceError::MongoDbError(format!("Failed to aggregate holder balance: {}", e)))?;
+        Ok(Decimal::from_str(&balance.to_string()).unwrap_or(Decimal::ZERO))
+    }
+
     /// 从发票批次创建token批次
     pub async fn create_token_batch_from_invoice_batch(
         &self, 
@@ -613,4 +688,82 @@ impl TokenService {
             }
         }
     }
-} 
\ No newline at end of file
+}
+
+/// 按持仓降序排列持有人并计算占发行量的百分比；发行量为 0 时百分比记为 0
+fn build_holder_distribution(mut balances: Vec<(String, Decimal)>, total_supply: Decimal) -> Vec<TokenHolderDto> {
+    balances.retain(|(_, balance)| *balance > Decimal::ZERO);
+    balances.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    balances.into_iter()
+        .map(|(user_id, balance)| {
+            let percentage = if total_supply.is_zero() {
+                Decimal::ZERO
+            } else {
+                balance * Decimal::ONE_HUNDRED / total_supply
+            };
+            TokenHolderDto {
+                user_id,
+                balance: balance.normalize().to_string(),
+                percentage_of_supply: format!("{:.4}", percentage.round_dp(4)),
+            }
+        })
+        .collect()
+}
+
+/// 链上 `balanceOf` (最小单位) 按代币精度 `decimals()` 换算为代币数量，与数据库记账余额单位一致。
+/// 超出 Decimal 表示范围 (或精度超过 28 位) 时返回 None
+pub fn scale_onchain_balance(raw: U256, decimals: u8) -> Option<Decimal> {
+    let mut amount = Decimal::from_str(&raw.to_string()).ok()?;
+    amount.set_scale(decimals as u32).ok()?;
+    Some(amount.normalize())
+}
+
+/// 数据库记账余额与链上 `balanceOf` 是否不一致 (漏处理链上事件的信号)。
+/// 链上余额换算后超出 Decimal 表示范围时一定不一致
+pub fn balance_drift(recorded: Decimal, raw: U256, decimals: u8) -> bool {
+    scale_onchain_balance(raw, decimals).map_or(true, |onchain| onchain != recorded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_balance_drift() {
+        let tokens = |amount: u64| U256::from(amount) * U256::exp10(18);
+        assert!(!balance_drift(dec!(100), tokens(100), 18));
+        assert!(!balance_drift(dec!(100.00), tokens(100), 18));
+        assert!(!balance_drift(dec!(1.5), U256::from(1_500_000u64), 6));
+        assert!(!balance_drift(dec!(100), U256::from(100u64), 0));
+        assert!(!balance_drift(Decimal::ZERO, U256::zero(), 18));
+        // 未按精度换算的原始值不相等
+        assert!(balance_drift(dec!(100), U256::from(100u64), 18));
+        assert!(balance_drift(dec!(100), tokens(90), 18));
+        assert!(balance_drift(Decimal::ZERO, U256::from(1u64), 18));
+        assert!(balance_drift(dec!(100), U256::MAX, 0));
+        assert_eq!(scale_onchain_balance(U256::from(1_500_000u64), 6), Some(dec!(1.5)));
+    }
+
+    #[test]
+    fn test_holder_distribution_sorted_with_supply_share() {
+        let holders = build_holder_distribution(
+            vec![
+                ("a".to_string(), dec!(100)),
+                ("b".to_string(), dec!(250.50)),
+                ("c".to_string(), dec!(0)),
+                ("d".to_string(), dec!(100)),
+            ],
+            dec!(1000),
+        );
+        let summary: Vec<_> = holders.iter()
+            .map(|h| (h.user_id.as_str(), h.balance.as_str(), h.percentage_of_supply.as_str()))
+            .collect();
+        assert_eq!(summary, vec![
+            ("b", "250.5", "25.0500"),
+            ("a", "100", "10.0000"),
+            ("d", "100", "10.0000"),
+        ]);
+        assert_eq!(build_holder_distribution(vec![("a".to_string(), dec!(1))], Decimal::ZERO)[0].percentage_of_supply, "0.0000");
+    }
+}
@@ -0,0 +1,89 @@
+//! 链上交易分页列表：与票据列表相同，以 `_id` 作为游标按倒序 (最新在前) 返回
+
+use common::domain::entity::{OnchainTransaction, OnchainTxStatus};
+use mongodb::bson::{self, DateTime, Document, doc};
+
+use crate::error::ServiceError;
+
+/// 列表筛选条件
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    /// 发送方、接收方或代其操作的用户地址 (不区分大小写)
+    pub address: Option<String>,
+    pub status: Option<OnchainTxStatus>,
+    /// 登记时间下限 (含)
+    pub since: Option<DateTime>,
+    /// 登记时间上限 (不含)
+    pub until: Option<DateTime>,
+}
+
+impl TransactionFilter {
+    /// 生成查询条件 (不含游标条件，游标由 `Pagination::apply_cursor` 追加)
+    pub fn to_document(&self) -> Result<Document, ServiceError> {
+        let mut filter = doc! {};
+        if let Some(address) = &self.address {
+            let address = address.to_lowercase();
+            filter.insert("$or", vec![doc! { "from_address": &address }, doc! { "to_address": &address }, doc! { "accounts": &address }]);
+        }
+        if let Some(status) = &self.status {
+            let status = bson::to_bson(status).map_err(|e| ServiceError::SerializationError(e.to_string()))?;
+            filter.insert("status", status);
+        }
+        let mut created_at = doc! {};
+        if let Some(since) = self.since {
+            created_at.insert("$gte", since);
+        }
+        if let Some(until) = self.until {
+            created_at.insert("$lt", until);
+        }
+        if !created_at.is_empty() {
+            filter.insert("created_at", created_at);
+        }
+        Ok(filter)
+    }
+}
+
+/// 确定查询的地址范围：管理员可以查询任意地址或全部交易，其他用户只能查询自己的地址
+pub fn resolve_address_scope(is_admin: bool, viewer_address: &str, requested: Option<&str>) -> Result<Option<String>, ServiceError> {
+    match requested.map(str::trim).filter(|a| !a.is_empty()) {
+        Some(address) if is_admin || address.eq_ignore_ascii_case(viewer_address) => Ok(Some(address.to_lowercase())),
+        Some(address) => Err(ServiceError::Forbidden(format!("cannot list transactions of {}", address))),
+        None if is_admin => Ok(None),
+        None => Ok(Some(viewer_address.to_lowercase())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_investor_is_scoped_to_own_address() {
+        assert_eq!(resolve_address_scope(false, "0xABC", None).unwrap(), Some("0xabc".to_string()));
+        assert_eq!(resolve_address_scope(false, "0xabc", Some("0xAbC")).unwrap(), Some("0xabc".to_string()));
+        assert!(matches!(resolve_address_scope(false, "0xabc", Some("0xdef")), Err(ServiceError::Forbidden(_))));
+        // 管理员不限地址，不传时查询全部
+        assert_eq!(resolve_address_scope(true, "0xabc", Some("0xDEF")).unwrap(), Some("0xdef".to_string()));
+        assert_eq!(resolve_address_scope(true, "0xabc", Some(" ")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_filter_document() {
+        let filter = TransactionFilter { address: Some("0xABC".to_string()), status: Some(OnchainTxStatus::Reverted), ..Default::default() };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! {
+                "$or": [{ "from_address": "0xabc" }, { "to_address": "0xabc" }, { "accounts": "0xabc" }],
+                "status": "Reverted",
+            }
+        );
+
+        let since = DateTime::from_millis(1_700_000_000_000);
+        let until = DateTime::from_millis(1_800_000_000_000);
+        let filter = TransactionFilter { status: Some(OnchainTxStatus::Pending), since: Some(since), until: Some(until), ..Default::default() };
+        assert_eq!(
+            filter.to_document().unwrap(),
+            doc! { "status": "Pending", "created_at": { "$gte": since, "$lt": until } }
+        );
+    }
+}
@@ -0,0 +1,321 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use ethers::providers::Middleware;
+use async_trait::async_trait;
+use ethers::types::{H256, TransactionReceipt};
+use tracing::{error, info, warn};
+use mongodb::Database;
+use salvo_oapi::ToSchema;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+
+use common::domain::entity::{OnchainTransaction, OnchainTxStatus};
+use common::pagination::{Page, Pagination};
+use pharos_interact::{BroadcastObserver, BroadcastTx};
+use crate::error::ServiceError;
+use crate::repository::OnchainTransactionRepository;
+use crate::service::transaction_listing::TransactionFilter;
+
+#[derive(Debug, Clone)]
+pub struct TransactionPollerConfig {
+    /// 标记为 Confirmed 需要的确认数 (含所在区块)
+    pub confirmations: u64,
+    pub interval_secs: u64,
+    pub batch_size: i64,
+}
+
+/// 一次回执查询的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReceiptProgress {
+    pub status: OnchainTxStatus,
+    pub block_number: Option<u64>,
+    pub confirmations: u64,
+}
+
+/// 链上交易查询：按哈希查询状态、按条件分页列出 (不需要区块链连接)
+pub struct TransactionService {
+    repo: OnchainTransactionRepository,
+}
+
+impl TransactionService {
+    pub fn new(db: &Database) -> Self {
+        Self { repo: OnchainTransactionRepository::new(db) }
+    }
+
+    pub async fn find_by_hash(&self, tx_hash: &str) -> Result<Option<OnchainTransaction>, ServiceError> {
+        Ok(self.repo.find_by_hash(&normalize_tx_hash(tx_hash)).await?)
+    }
+
+    /// 按筛选条件分页查询链上交易，最新在前；`convert` 把记录转换为返回类型
+    pub async fn list<T: ToSchema + 'static>(
+        &self,
+        filter: &TransactionFilter,
+        pagination: &Pagination,
+        convert: impl FnMut(OnchainTransaction) -> T,
+    ) -> Result<Page<T>, ServiceError> {
+        let filter = filter.to_document()?;
+        let mut page_filter = filter.clone();
+        pagination.apply_cursor(&mut page_filter)?;
+        let total = self.repo.count(filter).await?;
+        let rows = self.repo.find_page(page_filter, pagination).await?;
+        Ok(Page::from_rows(rows, pagination, total, |tx| tx.id, convert))
+    }
+}
+
+/// 链上交易回执轮询
+///
+/// 合约写操作广播后由 [`PendingTransactionTracker`] 登记为 Pending，后台任务定期查询回执：执行失败标记为 Reverted，
+/// 确认数达到配置值后标记为 Confirmed。
+pub struct TransactionPoller<M> {
+    client: Arc<M>,
+    repo: OnchainTransactionRepository,
+    config: TransactionPollerConfig,
+}
+
+impl<M: Middleware + 'static> TransactionPoller<M> {
+    pub fn new(client: Arc<M>, db: &Database, config: TransactionPollerConfig) -> Self {
+        Self { client, repo: OnchainTransactionRepository::new(db), config }
+    }
+
+    /// 检查一批待确认交易，返回本轮结束跟踪 (Confirmed / Reverted) 的数量
+    pub async fn poll_pending(&self) -> Result<usize, ServiceError> {
+        let pending = self.repo.find_pending(self.config.batch_size).await?;
+        let mut settled = 0;
+        for tx in pending {
+            let hash = match tx.tx_hash.parse::<H256>() {
+                Ok(hash) => hash,
+                Err(e) => {
+                    error!("Tracked transaction has invalid hash {}: {}", tx.tx_hash, e);
+                    continue;
+                }
+            };
+            let progress = match check_receipt(self.client.as_ref(), hash, self.config.confirmations).await {
+                Ok(progress) => progress,
+                Err(e) => {
+                    warn!("Failed to check receipt of {} ({} {}): {}", tx.tx_hash, tx.operation, tx.reference, e);
+                    continue;
+                }
+            };
+            // Pending 也写回，刷新 updated_at 让下一轮优先检查其他交易
+            self.repo.update_progress(&tx.tx_hash, progress.status, progress.block_number, progress.confirmations).await?;
+            if progress.status != OnchainTxStatus::Pending {
+                info!("Transaction {} ({} {}) is {:?} at block {:?}", tx.tx_hash, tx.operation, tx.reference, progress.status, progress.block_number);
+                settled += 1;
+            }
+        }
+        Ok(settled)
+    }
+
+    /// 启动后台轮询任务。`shutdown` 变为 true 后再检查一轮 (记录停机前刚广播的交易进度) 然后退出
+    pub fn spawn_poller(self: &Arc<Self>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(service.config.interval_secs.max(1)));
+            while !*shutdown.borrow() {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                if let Err(e) = service.poll_pending().await {
+                    error!("Transaction receipt polling failed: {}", e);
+                }
+            }
+            match service.poll_pending().await {
+                Ok(settled) => info!("Transaction receipt poller stopped after final check ({} settled)", settled),
+                Err(e) => error!("Final transaction receipt check failed: {}", e),
+            }
+        })
+    }
+}
+
+/// 合约交易广播后 (等待回执之前) 登记为 Pending，`GET /transaction/{tx_hash}` 和回执轮询随即可见
+pub struct PendingTransactionTracker {
+    repo: OnchainTransactionRepository,
+}
+
+impl PendingTransactionTracker {
+    pub fn new(db: &Database) -> Self {
+        Self { repo: OnchainTransactionRepository::new(db) }
+    }
+}
+
+#[async_trait]
+impl BroadcastObserver for PendingTransactionTracker {
+    async fn broadcast(&self, tx: BroadcastTx<'_>) {
+        let tx_hash = normalize_tx_hash(&format!("{:?}", tx.tx_hash));
+        let (from, to) = (tx.from.map(|a| format!("{:?}", a)), tx.to.map(|a| format!("{:?}", a)));
+        let accounts: Vec<String> = tx.accounts.iter().map(|a| format!("{:?}", a)).collect();
+        if let Err(e) = self.repo.track_pending(&tx_hash, tx.operation, tx.reference, from.as_deref(), to.as_deref(), &accounts).await {
+            // 交易已广播，登记失败只影响状态查询
+            error!("Failed to track transaction {} of {} ({}): {}", tx_hash, tx.operation, tx.reference, e);
+        }
+    }
+}
+
+/// 查询交易回执并与最新区块比较得出确认进度
+pub async fn check_receipt<M: Middleware>(client: &M, tx_hash: H256, required_confirmations: u64) -> Result<ReceiptProgress, ServiceError> {
+    let receipt = client
+        .get_transaction_receipt(tx_hash)
+        .await
+        .map_err(|e| ServiceError::ChainRpcError(format!("eth_getTransactionReceipt failed: {}", e)))?;
+    let Some(receipt) = receipt else {
+        return Ok(evaluate_receipt(None, 0, required_confirmations));
+    };
+    let latest_block = client
+        .get_block_number()
+        .await
+        .map_err(|e| ServiceError::ChainRpcError(format!("eth_blockNumber failed: {}", e)))?;
+    Ok(evaluate_receipt(Some(&receipt), latest_block.as_u64(), required_confirmations))
+}
+
+/// 回执缺失或未打包视为 Pending；执行失败直接 Reverted；否则确认数达到要求 (至少 1) 时 Confirmed
+pub fn evaluate_receipt(receipt: Option<&TransactionReceipt>, latest_block: u64, required_confirmations: u64) -> ReceiptProgress {
+    let pending = ReceiptProgress { status: OnchainTxStatus::Pending, block_number: None, confirmations: 0 };
+    let Some(receipt) = receipt else { return pending };
+    let Some(block) = receipt.block_number.map(|b| b.as_u64()) else { return pending };
+
+    // 节点落后于回执所在区块时按 0 确认处理
+    let confirmations = latest_block.checked_sub(block).map_or(0, |depth| depth + 1);
+    let status = if receipt.status == Some(0.into()) {
+        OnchainTxStatus::Reverted
+    } else if confirmations >= required_confirmations.max(1) {
+        OnchainTxStatus::Confirmed
+    } else {
+        OnchainTxStatus::Pending
+    };
+    ReceiptProgress { status, block_number: Some(block), confirmations }
+}
+
+/// 统一为 0x 开头的小写形式
+pub fn normalize_tx_hash(tx_hash: &str) -> String {
+    let hash = tx_hash.trim().to_lowercase();
+    if hash.starts_with("0x") { hash } else { format!("0x{}", hash) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::Provider;
+    use ethers::types::U64;
+
+    fn mined(block: u64, status: u64) -> TransactionReceipt {
+        TransactionReceipt { block_number: Some(block.into()), status: Some(status.into()), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_pending_to_confirmed() {
+        let (provider, mock) = Provider::mocked();
+        let hash = H256::repeat_byte(0xab);
+
+        // 尚未打包
+        mock.push::<Option<TransactionReceipt>, _>(None).unwrap();
+        let progress = check_receipt(&provider, hash, 2).await.unwrap();
+        assert_eq!(progress, ReceiptProgress { status: OnchainTxStatus::Pending, block_number: None, confirmations: 0 });
+
+        // MockProvider 后进先出：先压入区块高度，再压入回执
+        mock.push::<U64, _>(10.into()).unwrap();
+        mock.push(mined(10, 1)).unwrap();
+        let progress = check_receipt(&provider, hash, 2).await.unwrap();
+        assert_eq!(progress, ReceiptProgress { status: OnchainTxStatus::Pending, block_number: Some(10), confirmations: 1 });
+
+        mock.push::<U64, _>(11.into()).unwrap();
+        mock.push(mined(10, 1)).unwrap();
+        let progress = check_receipt(&provider, hash, 2).await.unwrap();
+        assert_eq!(progress, ReceiptProgress { status: OnchainTxStatus::Confirmed, block_number: Some(10), confirmations: 2 });
+    }
+
+    #[tokio::test]
+    async fn test_reverted_receipt() {
+        let (provider, mock) = Provider::mocked();
+        mock.push::<U64, _>(20.into()).unwrap();
+        mock.push(mined(20, 0)).unwrap();
+        let progress = check_receipt(&provider, H256::repeat_byte(0xcd), 3).await.unwrap();
+        assert_eq!(progress.status, OnchainTxStatus::Reverted);
+        assert_eq!(progress.block_number, Some(20));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_broadcast_registers_pending_transaction() {
+        use ethers::types::Address;
+        use crate::test_support::TestDb;
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let tracker = PendingTransactionTracker::new(&test_db);
+        let tx = BroadcastTx {
+            operation: "purchase_shares",
+            reference: "7",
+            tx_hash: H256::repeat_byte(0xab),
+            from: Some(Address::repeat_byte(1)),
+            to: Some(Address::repeat_byte(2)),
+            accounts: &[],
+        };
+        tracker.broadcast(tx).await;
+        // 重试后同一笔交易再次广播不覆盖已有记录
+        tracker.broadcast(tx).await;
+        let tracked = OnchainTransactionRepository::new(&test_db).find_by_hash(&format!("{:?}", tx.tx_hash)).await.unwrap();
+        test_db.cleanup().await;
+
+        let tracked = tracked.expect("transaction not tracked");
+        assert_eq!((tracked.operation.as_str(), tracked.reference.as_str()), ("purchase_shares", "7"));
+        assert_eq!(tracked.status, OnchainTxStatus::Pending);
+        assert_eq!(tracked.from_address.as_deref(), Some(format!("{:?}", Address::repeat_byte(1)).as_str()));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_investor_lists_transactions_sent_on_their_behalf() {
+        use ethers::types::Address;
+        use crate::service::transaction_listing::resolve_address_scope;
+        use crate::test_support::TestDb;
+
+        let Some(test_db) = TestDb::connect().await else { return };
+        let tracker = PendingTransactionTracker::new(&test_db);
+        // 交易都由平台钱包发往合约，投资人只出现在 accounts 中
+        let (platform, contract, investor) = (Address::repeat_byte(1), Address::repeat_byte(2), Address::repeat_byte(0xab));
+        let holders = [investor, Address::repeat_byte(3)];
+        let txs = [
+            ("distribute_repayment", H256::repeat_byte(1), &holders[..]),
+            ("create_token_batch", H256::repeat_byte(2), &[][..]),
+            ("distribute_repayment", H256::repeat_byte(3), &holders[1..]),
+        ];
+        for (operation, tx_hash, accounts) in txs {
+            tracker.broadcast(BroadcastTx { operation, reference: "7", tx_hash, from: Some(platform), to: Some(contract), accounts }).await;
+        }
+        let repo = OnchainTransactionRepository::new(&test_db);
+        repo.update_progress(&format!("{:?}", H256::repeat_byte(1)), OnchainTxStatus::Confirmed, Some(10), 1).await.unwrap();
+
+        let service = TransactionService::new(&test_db);
+        let page = |address: Option<String>, status: Option<OnchainTxStatus>| {
+            let service = &service;
+            async move {
+                let filter = TransactionFilter { address, status, ..Default::default() };
+                let page = service.list(&filter, &Pagination::new(None, 10), |tx| tx.tx_hash).await.unwrap();
+                page.rows
+            }
+        };
+        // 地址大小写与存储不一致也能匹配
+        let scope = resolve_address_scope(false, &format!("{:?}", investor).to_uppercase().replace("0X", "0x"), None).unwrap();
+        let own = page(scope.clone(), None).await;
+        let confirmed = page(scope, Some(OnchainTxStatus::Confirmed)).await;
+        let pending = page(Some(format!("{:?}", investor)), Some(OnchainTxStatus::Pending)).await;
+        let all = page(None, None).await;
+        test_db.cleanup().await;
+
+        assert_eq!(own, vec![format!("{:?}", H256::repeat_byte(1))]);
+        assert_eq!(confirmed, own);
+        assert!(pending.is_empty());
+        assert_eq!(all.len(), 3);
+    }
+
+    #[test]
+    fn test_evaluate_receipt_edge_cases() {
+        // 节点落后
+        assert_eq!(evaluate_receipt(Some(&mined(10, 1)), 9, 1).status, OnchainTxStatus::Pending);
+        // 配置为 0 时至少需要打包
+        assert_eq!(evaluate_receipt(Some(&mined(10, 1)), 10, 0).status, OnchainTxStatus::Confirmed);
+        assert_eq!(normalize_tx_hash(" 0xABcd "), "0xabcd");
+        assert_eq!(normalize_tx_hash("abcd"), "0xabcd");
+    }
+}
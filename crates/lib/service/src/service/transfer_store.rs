@@ -0,0 +1,197 @@
+use std::str::FromStr;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use ethers::types::Address;
+use tracing::{error, warn};
+use mongodb::bson::{DateTime, Decimal128};
+use mongodb::{Client, ClientSession, Database};
+
+use common::domain::entity::TokenTransfer;
+use pharos_interact::{TransferEvent, TransferStore};
+
+use crate::metrics;
+use crate::repository::{TokenRepository, TokenTransferRepository};
+
+/// `indexer_state` 中 ERC20 转账索引的进度记录 ID
+pub const TRANSFER_INDEXER_ID: &str = "erc20_transfer";
+
+/// 转账索引的 Mongo 存储：历史写入 `token_transfers`，余额累计在 `TokenRepository` 的链上余额中。
+///
+/// 每笔转账的历史插入 / 删除与余额调整在同一事务内完成 (需要副本集)；历史记录作为认领点，
+/// 保证同一笔转账的余额最多计入 (撤销) 一次，中途失败不会留下只写了一半的转账。
+pub struct MongoTransferStore {
+    client: Client,
+    tokens: Arc<TokenRepository>,
+    transfers: TokenTransferRepository,
+}
+
+impl MongoTransferStore {
+    pub fn new(db: &Database, tokens: Arc<TokenRepository>) -> Self {
+        Self { client: db.client().clone(), tokens, transfers: TokenTransferRepository::new(db) }
+    }
+
+    /// 在一个事务内认领转账 (`reverse` 时删除历史，否则插入) 并调整余额；已被认领过时不调整
+    async fn record(&self, transfer: &TokenTransfer, reverse: bool) -> Result<()> {
+        let mut session = self.client.start_session().await?;
+        session.start_transaction().await?;
+        let claimed = if reverse {
+            self.transfers.delete_session(&transfer.tx_hash, transfer.log_index, &mut session).await?
+        } else {
+            self.transfers.insert_if_absent_session(transfer, &mut session).await?
+        };
+        if claimed {
+            self.adjust_balances(transfer, reverse, &mut session).await?;
+        }
+        session.commit_transaction().await?;
+        Ok(())
+    }
+
+    /// 按转账调整双方余额，`reverse` 时反向调整。零地址 (铸造 / 销毁) 不记余额
+    async fn adjust_balances(&self, transfer: &TokenTransfer, reverse: bool, session: &mut ClientSession) -> Result<()> {
+        let value = transfer.value.to_string();
+        let (outgoing, incoming) = if reverse { (value.clone(), format!("-{}", value)) } else { (format!("-{}", value), value) };
+        if transfer.from_address != ZERO_ADDRESS {
+            self.tokens.adjust_onchain_balance_session(&transfer.token_address, &transfer.from_address, decimal(&outgoing)?, session).await?;
+        }
+        if transfer.to_address != ZERO_ADDRESS {
+            self.tokens.adjust_onchain_balance_session(&transfer.token_address, &transfer.to_address, decimal(&incoming)?, session).await?;
+        }
+        Ok(())
+    }
+}
+
+const ZERO_ADDRESS: &str = "0x0000000000000000000000000000000000000000";
+
+fn decimal(value: &str) -> Result<Decimal128> {
+    Decimal128::from_str(value).with_context(|| format!("Token amount {} does not fit Decimal128", value))
+}
+
+/// 转账事件转为历史记录，地址与哈希统一为小写
+pub fn transfer_record(event: &TransferEvent) -> Result<TokenTransfer> {
+    Ok(TokenTransfer {
+        id: None,
+        token_address: format!("{:?}", event.token),
+        from_address: format!("{:?}", event.from),
+        to_address: format!("{:?}", event.to),
+        value: decimal(&event.value.to_string())?,
+        block_number: event.block_number as i64,
+        tx_hash: format!("{:?}", event.tx_hash),
+        log_index: event.log_index as i64,
+        created_at: DateTime::now(),
+    })
+}
+
+#[async_trait]
+impl TransferStore for MongoTransferStore {
+    async fn tracked_tokens(&self) -> Result<Vec<Address>> {
+        let addresses = self.tokens.tracked_contract_addresses().await?;
+        Ok(addresses
+            .into_iter()
+            .filter_map(|a| match a.parse::<Address>() {
+                Ok(address) => Some(address),
+                Err(e) => {
+                    warn!("Skipping token batch with invalid contract address {}: {}", a, e);
+                    None
+                }
+            })
+            .collect())
+    }
+
+    async fn last_processed_block(&self) -> Result<Option<u64>> {
+        Ok(self.transfers.last_processed_block(TRANSFER_INDEXER_ID).await?)
+    }
+
+    async fn rollback_from(&self, from_block: u64) -> Result<()> {
+        for transfer in self.transfers.find_from_block(from_block as i64).await? {
+            self.record(&transfer, true).await?;
+        }
+        Ok(())
+    }
+
+    async fn apply(&self, transfers: &[TransferEvent], processed_to: u64) -> Result<()> {
+        for event in transfers {
+            // 金额超出 Decimal128 精度时跳过并告警：整轮失败会在每次重试时再次遇到同一条日志，索引将停滞
+            let transfer = match transfer_record(event) {
+                Ok(transfer) => transfer,
+                Err(e) => {
+                    error!(
+                        "Skipping ERC20 transfer {:?}#{} of token {:?} in block {}, balances need manual review: {:#}",
+                        event.tx_hash, event.log_index, event.token, event.block_number, e
+                    );
+                    metrics::record_skipped_transfer("amount_overflow");
+                    continue;
+                }
+            };
+            self.record(&transfer, false).await?;
+        }
+        self.transfers.set_last_processed_block(TRANSFER_INDEXER_ID, processed_to).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{H256, U256};
+    use crate::test_support::TestDb;
+
+    #[test]
+    fn test_transfer_record_normalizes_addresses() {
+        let event = TransferEvent {
+            token: Address::repeat_byte(0xab),
+            from: Address::zero(),
+            to: Address::repeat_byte(0x0c),
+            value: U256::exp10(24),
+            block_number: 42,
+            tx_hash: H256::repeat_byte(0xef),
+            log_index: 3,
+        };
+        let record = transfer_record(&event).unwrap();
+        assert_eq!(record.token_address, format!("0x{}", "ab".repeat(20)));
+        assert_eq!(record.from_address, ZERO_ADDRESS);
+        assert_eq!(record.tx_hash, format!("0x{}", "ef".repeat(32)));
+        assert_eq!(record.value.to_string(), "1000000000000000000000000");
+        assert_eq!((record.block_number, record.log_index), (42, 3));
+    }
+
+    fn event(log_index: u64, to: Address, value: U256) -> TransferEvent {
+        TransferEvent {
+            token: Address::repeat_byte(0xab),
+            from: Address::zero(),
+            to,
+            value,
+            block_number: 7,
+            tx_hash: H256::repeat_byte(0x01),
+            log_index,
+        }
+    }
+
+    /// 需要副本集 MongoDB (事务)：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_overflowing_transfer_is_skipped_without_stalling() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let tokens = Arc::new(TokenRepository::new(Arc::new(test_db.database())));
+        let store = MongoTransferStore::new(&test_db, tokens.clone());
+        let holder = Address::repeat_byte(0x0c);
+        let (token, wallet) = (format!("{:?}", Address::repeat_byte(0xab)), format!("{:?}", holder));
+
+        let transfers = [event(0, holder, U256::MAX), event(1, holder, U256::from(100))];
+        let applied = store.apply(&transfers, 9).await;
+        // 重复处理同一区间不会重复计入
+        let reapplied = store.apply(&transfers, 9).await;
+        let last_block = store.last_processed_block().await;
+        let balance = tokens.get_onchain_balance(&token, &wallet).await;
+        let rolled_back = store.rollback_from(7).await;
+        let after_rollback = tokens.get_onchain_balance(&token, &wallet).await;
+        test_db.cleanup().await;
+
+        applied.unwrap();
+        reapplied.unwrap();
+        assert_eq!(last_block.unwrap(), Some(9));
+        assert_eq!(balance.unwrap().unwrap().balance.to_string(), "100");
+        rolled_back.unwrap();
+        assert_eq!(after_rollback.unwrap().unwrap().balance.to_string(), "0");
+    }
+}
@@ -0,0 +1,635 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use tracing::{error, info, warn};
+use mongodb::{Database, bson::{DateTime, oid::ObjectId}};
+use serde::Serialize;
+use sha2::Sha256;
+use tokio::sync::watch;
+use tokio::task::{JoinHandle, JoinSet};
+
+use common::domain::entity::{WebhookDelivery, WebhookSubscription, invoice_status::InvoiceStatus};
+use crate::error::ServiceError;
+use crate::invoice::{InvoiceStatusChange, InvoiceStatusNotifier};
+use crate::repository::{EnterpriseRepository, WebhookDeliveryRepository, WebhookSubscriptionRepository};
+
+/// 单次投递的租约时间，超过后视为投递进程已崩溃，可被重新抢占
+const DELIVERY_LEASE_MS: i64 = 60_000;
+/// 退避上限
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+pub const SIGNATURE_HEADER: &str = "X-Pharos-Signature";
+pub const EVENT_HEADER: &str = "X-Pharos-Event";
+pub const INVOICE_STATUS_EVENT: &str = "invoice.status_changed";
+
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub max_attempts: u32,
+    pub base_backoff_secs: u64,
+    pub timeout_secs: u64,
+    /// 派生各回调签名密钥的服务端密钥
+    pub signing_key: String,
+    /// 允许回调到本机、内网及链路本地地址 (本地联调)
+    pub allow_private_targets: bool,
+}
+
+/// Webhook 投递服务
+///
+/// 每条投递都先写入 `webhook_deliveries`，再由后台任务投递；
+/// 失败后按指数退避重试，重启时通过 [`WebhookService::resume_pending_deliveries`] 恢复。
+/// 回调地址在登记和每次投递前都会解析校验，投递时固定连接校验过的地址且不跟随重定向。
+/// 停机时由 [`WebhookService::spawn_drain`] 等待进行中的投递结束，等待重试的投递留到下次启动恢复。
+pub struct WebhookService {
+    delivery_repo: WebhookDeliveryRepository,
+    subscription_repo: WebhookSubscriptionRepository,
+    enterprise_repo: EnterpriseRepository,
+    config: WebhookConfig,
+    deliveries: Mutex<JoinSet<()>>,
+    stopped: watch::Sender<bool>,
+}
+
+impl WebhookService {
+    pub fn new(db: Arc<Database>, config: WebhookConfig) -> Self {
+        Self {
+            delivery_repo: WebhookDeliveryRepository::new(&db),
+            subscription_repo: WebhookSubscriptionRepository::new(&db),
+            enterprise_repo: EnterpriseRepository::new(&db),
+            config,
+            deliveries: Mutex::default(),
+            stopped: watch::channel(false).0,
+        }
+    }
+
+    /// 登记回调地址，返回登记记录和签名密钥；密钥不落库，只在此时返回一次
+    pub async fn register(&self, enterprise_id: ObjectId, url: &str) -> Result<(WebhookSubscription, String), ServiceError> {
+        if self.config.signing_key.is_empty() {
+            return Err(ServiceError::ConfigError("webhook.signing_key is not configured".to_string()));
+        }
+        resolve_target(url, self.config.allow_private_targets).await.map_err(ServiceError::InvalidWebhookUrl)?;
+        let salt = uuid::Uuid::new_v4().simple().to_string();
+        let subscription = self.subscription_repo.upsert(enterprise_id, url, &salt).await?
+            .ok_or_else(|| ServiceError::InternalError(format!("Failed to register webhook {}", url)))?;
+        let secret = derive_secret(&self.config.signing_key, &subscription.secret_salt);
+        Ok((subscription, secret))
+    }
+
+    pub async fn list_subscriptions(&self, enterprise_id: ObjectId) -> Result<Vec<WebhookSubscription>, ServiceError> {
+        Ok(self.subscription_repo.find_by_enterprise(enterprise_id).await?)
+    }
+
+    /// 删除企业自己的回调地址，已排队的投递不受影响
+    pub async fn unregister(&self, enterprise_id: ObjectId, id: ObjectId) -> Result<bool, ServiceError> {
+        Ok(self.subscription_repo.delete(enterprise_id, id).await?)
+    }
+
+    /// 持久化一条投递并立即安排投递
+    pub async fn enqueue(
+        self: &Arc<Self>,
+        enterprise_id: Option<ObjectId>,
+        url: String,
+        event: String,
+        payload: String,
+        signature: Option<String>,
+    ) -> Result<WebhookDelivery, ServiceError> {
+        let delivery = WebhookDelivery::new(enterprise_id, url, event, payload, signature, self.config.max_attempts);
+        let created = self.delivery_repo.create(&delivery).await?;
+        if let Some(id) = created.id {
+            self.schedule(id, Duration::ZERO);
+        }
+        Ok(created)
+    }
+
+    /// 向收款企业登记的每个回调地址投递一次状态变更，按各自的密钥签名
+    pub async fn notify_invoice_status(self: &Arc<Self>, change: &InvoiceStatusChange) -> Result<usize, ServiceError> {
+        let Some(enterprise_id) = self.enterprise_repo.find_by_wallet_address(&change.payee).await?.and_then(|e| e.id) else {
+            return Ok(0);
+        };
+        let subscriptions = self.subscription_repo.find_by_enterprise(enterprise_id).await?;
+        if subscriptions.is_empty() {
+            return Ok(0);
+        }
+
+        let payload = serde_json::to_string(&InvoiceStatusPayload::from(change))
+            .map_err(|e| ServiceError::InternalError(format!("Failed to serialize webhook payload: {}", e)))?;
+        for subscription in &subscriptions {
+            let signature = sign_payload(&derive_secret(&self.config.signing_key, &subscription.secret_salt), &payload);
+            self.enqueue(Some(enterprise_id), subscription.url.clone(), INVOICE_STATUS_EVENT.to_string(), payload.clone(), Some(signature))
+                .await?;
+        }
+        Ok(subscriptions.len())
+    }
+
+    /// 启动时扫描未完成的投递并重新安排，已成功的记录不会被再次投递
+    pub async fn resume_pending_deliveries(self: &Arc<Self>) -> Result<usize, ServiceError> {
+        let pending = self.delivery_repo.find_pending().await?;
+        let mut scheduled = 0;
+        for delivery in pending {
+            let Some(id) = delivery.id else { continue };
+            if delivery.attempts >= delivery.max_attempts {
+                // 上次进程在最后一次尝试期间退出，直接标记失败
+                self.delivery_repo
+                    .mark_failed(id, delivery.last_error.as_deref().unwrap_or("max attempts reached"))
+                    .await?;
+                continue;
+            }
+            let delay_ms = (delivery.next_attempt_at.timestamp_millis() - DateTime::now().timestamp_millis()).max(0);
+            self.schedule(id, Duration::from_millis(delay_ms as u64));
+            scheduled += 1;
+        }
+        info!("Rescheduled {} pending webhook deliveries", scheduled);
+        Ok(scheduled)
+    }
+
+    /// 启动停机收尾任务：`shutdown` 变为 true 后不再发起新的投递，并等待进行中的投递结束
+    pub fn spawn_drain(self: &Arc<Self>, mut shutdown: watch::Receiver<bool>) -> JoinHandle<()> {
+        let service = self.clone();
+        tokio::spawn(async move {
+            // 发送端随停机协调一起释放时同样视为停机
+            let _ = shutdown.wait_for(|stop| *stop).await;
+            service.stopped.send_replace(true);
+            loop {
+                let mut deliveries = std::mem::take(&mut *service.deliveries.lock().unwrap_or_else(|e| e.into_inner()));
+                if deliveries.is_empty() {
+                    break;
+                }
+                while deliveries.join_next().await.is_some() {}
+            }
+            info!("Webhook deliveries drained");
+        })
+    }
+
+    fn schedule(self: &Arc<Self>, id: ObjectId, delay: Duration) {
+        let service = self.clone();
+        let mut stopped = self.stopped.subscribe();
+        let mut deliveries = self.deliveries.lock().unwrap_or_else(|e| e.into_inner());
+        while deliveries.try_join_next().is_some() {}
+        deliveries.spawn(async move {
+            // 停机后放弃等待，投递仍为 Pending，下次启动由 resume_pending_deliveries 接管
+            tokio::select! {
+                biased;
+                _ = stopped.wait_for(|stop| *stop) => return,
+                _ = tokio::time::sleep(delay) => {}
+            }
+            service.attempt(id).await;
+        });
+    }
+
+    async fn attempt(self: &Arc<Self>, id: ObjectId) {
+        let delivery = match self.delivery_repo.claim_due(id, DELIVERY_LEASE_MS).await {
+            Ok(Some(delivery)) => delivery,
+            // 已被其他任务投递、已成功或尚未到期
+            Ok(None) => return,
+            Err(e) => {
+                error!("Failed to claim webhook delivery {}: {}", id, e);
+                return;
+            }
+        };
+
+        let result = match resolve_target(&delivery.url, self.config.allow_private_targets).await {
+            Ok(target) => post_delivery(&self.client_for(&target), &delivery).await,
+            Err(err) => {
+                // 地址已解析到内网等不允许的目标，不再重试
+                warn!("Webhook delivery {} blocked: {}", id, err);
+                if let Err(e) = self.delivery_repo.mark_failed(id, &err).await {
+                    error!("Failed to record webhook delivery {} outcome: {}", id, e);
+                }
+                return;
+            }
+        };
+        let outcome = match result {
+            Ok(()) => self.delivery_repo.mark_succeeded(id).await.map(|_| ()),
+            Err(err) if delivery.attempts >= delivery.max_attempts => {
+                warn!("Webhook delivery {} failed permanently after {} attempts: {}", id, delivery.attempts, err);
+                self.delivery_repo.mark_failed(id, &err).await.map(|_| ())
+            }
+            Err(err) => {
+                let backoff = backoff_delay(self.config.base_backoff_secs, delivery.attempts);
+                warn!("Webhook delivery {} attempt {} failed: {}, retrying in {:?}", id, delivery.attempts, err, backoff);
+                let next = DateTime::from_millis(DateTime::now().timestamp_millis() + backoff.as_millis() as i64);
+                let res = self.delivery_repo.schedule_retry(id, next, &err).await.map(|_| ());
+                if res.is_ok() {
+                    self.schedule(id, backoff);
+                }
+                res
+            }
+        };
+        if let Err(e) = outcome {
+            error!("Failed to record webhook delivery {} outcome: {}", id, e);
+        }
+    }
+
+    /// 连接固定到校验过的地址，避免校验后域名被重新解析到内网 (DNS 重绑定)
+    fn client_for(&self, target: &ResolvedTarget) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = &target.domain {
+            builder = builder.resolve_to_addrs(domain, &target.addrs);
+        }
+        builder.build().unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl InvoiceStatusNotifier for WebhookService {
+    async fn invoice_status_changed(self: Arc<Self>, change: InvoiceStatusChange) {
+        if let Err(e) = self.notify_invoice_status(&change).await {
+            error!("Failed to enqueue webhook for invoice {} ({:?} -> {:?}): {}", change.invoice_id, change.from, change.to, e);
+        }
+    }
+}
+
+/// `invoice.status_changed` 事件的请求体
+#[derive(Debug, Serialize)]
+struct InvoiceStatusPayload<'a> {
+    event: &'static str,
+    /// 每次变更唯一，接收方可据此去重 (重试时不变)
+    event_id: String,
+    invoice_id: String,
+    invoice_number: &'a str,
+    from: InvoiceStatus,
+    to: InvoiceStatus,
+    occurred_at: i64,
+}
+
+impl<'a> From<&'a InvoiceStatusChange> for InvoiceStatusPayload<'a> {
+    fn from(change: &'a InvoiceStatusChange) -> Self {
+        Self {
+            event: INVOICE_STATUS_EVENT,
+            event_id: ObjectId::new().to_hex(),
+            invoice_id: change.invoice_id.to_hex(),
+            invoice_number: &change.invoice_number,
+            from: change.from,
+            to: change.to,
+            occurred_at: change.occurred_at.timestamp_millis(),
+        }
+    }
+}
+
+/// 回调签名密钥：HMAC-SHA256(服务端密钥, 登记时生成的盐) 的十六进制
+pub fn derive_secret(signing_key: &str, salt: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(signing_key.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(salt.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// 校验通过的回调目标，`domain` 为 None 表示 URL 中直接写了 IP
+#[derive(Debug)]
+struct ResolvedTarget {
+    domain: Option<String>,
+    addrs: Vec<SocketAddr>,
+}
+
+/// 解析回调地址，任一解析结果不是公网地址时拒绝 (`allow_private` 时跳过地址检查)
+async fn resolve_target(url: &str, allow_private: bool) -> Result<ResolvedTarget, String> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "Invalid webhook URL".to_string())?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("Webhook URL must be an absolute http(s) URL".to_string());
+    }
+    let host = parsed.host_str().ok_or_else(|| "Webhook URL must be an absolute http(s) URL".to_string())?;
+    let port = parsed.port_or_known_default().unwrap_or(80);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let (domain, addrs) = match host.parse::<IpAddr>() {
+        Ok(ip) => (None, vec![SocketAddr::new(ip, port)]),
+        Err(_) => {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await
+                .map_err(|e| format!("Failed to resolve webhook host {}: {}", host, e))?
+                .collect();
+            (Some(host.to_string()), addrs)
+        }
+    };
+    if addrs.is_empty() {
+        return Err(format!("Webhook host {} did not resolve", host));
+    }
+    if !allow_private {
+        if let Some(addr) = addrs.iter().find(|addr| !is_public_ip(addr.ip())) {
+            return Err(format!("Webhook host {} resolves to non-public address {}", host, addr.ip()));
+        }
+    }
+    Ok(ResolvedTarget { domain, addrs })
+}
+
+/// 公网单播地址；本机、私有网段、链路本地 (含 169.254.169.254 元数据地址)、CGNAT、组播等都不是
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            let shared = octets[0] == 100 && (octets[1] & 0xc0) == 64;
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                || shared
+                || octets[0] == 0)
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public_ip(IpAddr::V4(v4)),
+            None => !(v6.is_loopback() || v6.is_unspecified() || v6.is_multicast() || v6.is_unique_local() || v6.is_unicast_link_local()),
+        },
+    }
+}
+
+/// 请求体的 HMAC-SHA256 签名，格式为 `sha256=<hex>`，放在 [`SIGNATURE_HEADER`] 中
+pub fn sign_payload(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 投递一次，非 2xx 响应或网络错误都视为失败
+async fn post_delivery(http: &reqwest::Client, delivery: &WebhookDelivery) -> Result<(), String> {
+    let mut request = http
+        .post(&delivery.url)
+        .header("Content-Type", "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .body(delivery.payload.clone());
+    if let Some(signature) = &delivery.signature {
+        request = request.header(SIGNATURE_HEADER, signature);
+    }
+    match request.send().await {
+        Ok(resp) if resp.status().is_success() => Ok(()),
+        Ok(resp) => Err(format!("receiver responded with {}", resp.status())),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// 第 n 次失败后的退避时间: base * 2^(n-1)，不超过 MAX_BACKOFF_SECS
+pub fn backoff_delay(base_secs: u64, attempts: u32) -> Duration {
+    let exp = attempts.saturating_sub(1).min(16);
+    Duration::from_secs(base_secs.saturating_mul(1u64 << exp).min(MAX_BACKOFF_SECS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use crate::test_support::TestDb;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// 依次以 `statuses` 应答 (用完后一律 200) 的 HTTP 服务，记录收到的原始请求
+    async fn mock_receiver(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            let mut statuses = statuses.into_iter();
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let request = read_request(&mut stream).await;
+                received.lock().unwrap().push(request);
+                let status = statuses.next().unwrap_or(200);
+                let response = format!("HTTP/1.1 {} Mock\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+        (url, requests)
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> String {
+        let mut buf = Vec::new();
+        let mut chunk = [0u8; 4096];
+        loop {
+            let n = stream.read(&mut chunk).await.unwrap_or(0);
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+            let text = String::from_utf8_lossy(&buf).to_string();
+            if let Some(end) = text.find("\r\n\r\n") {
+                let content_length = text[..end]
+                    .lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().parse::<usize>().unwrap_or(0)))
+                    .unwrap_or(0);
+                if buf.len() >= end + 4 + content_length {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&buf).to_string()
+    }
+
+    fn test_config(allow_private_targets: bool) -> WebhookConfig {
+        WebhookConfig {
+            max_attempts: 3,
+            base_backoff_secs: 0,
+            timeout_secs: 5,
+            signing_key: "test-webhook-signing-key".to_string(),
+            allow_private_targets,
+        }
+    }
+
+    async fn wait_until_done(repo: &WebhookDeliveryRepository, id: ObjectId) -> WebhookDelivery {
+        let mut delivery = repo.find_by_id(id).await.unwrap().unwrap();
+        for _ in 0..100 {
+            if delivery.status != common::domain::entity::WebhookDeliveryStatus::Pending {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            delivery = repo.find_by_id(id).await.unwrap().unwrap();
+        }
+        delivery
+    }
+
+    fn status_change() -> InvoiceStatusChange {
+        InvoiceStatusChange {
+            invoice_id: ObjectId::new(),
+            invoice_number: "INV-001".to_string(),
+            payee: "0xpayee".to_string(),
+            from: InvoiceStatus::Pending,
+            to: InvoiceStatus::Verified,
+            occurred_at: DateTime::from_millis(1_700_000_000_000),
+        }
+    }
+
+    #[test]
+    fn test_sign_payload() {
+        // 常见的 HMAC-SHA256 参考向量
+        assert_eq!(
+            sign_payload("key", "The quick brown fox jumps over the lazy dog"),
+            "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_ne!(sign_payload("key", "{}"), sign_payload("other-key", "{}"));
+    }
+
+    #[test]
+    fn test_invoice_status_payload() {
+        let change = status_change();
+        let payload: serde_json::Value = serde_json::to_value(InvoiceStatusPayload::from(&change)).unwrap();
+        assert_eq!(payload["event"], INVOICE_STATUS_EVENT);
+        assert_eq!(payload["invoice_id"], change.invoice_id.to_hex());
+        assert_eq!(payload["from"], "PENDING");
+        assert_eq!(payload["to"], "VERIFIED");
+        assert_eq!(payload["occurred_at"], 1_700_000_000_000i64);
+    }
+
+    #[tokio::test]
+    async fn test_post_delivery_fails_on_5xx_and_sends_signature() {
+        let (url, requests) = mock_receiver(vec![503]).await;
+        let payload = r#"{"event":"invoice.status_changed"}"#.to_string();
+        let signature = sign_payload("secret", &payload);
+        let delivery = WebhookDelivery::new(None, url, INVOICE_STATUS_EVENT.to_string(), payload.clone(), Some(signature.clone()), 3);
+        let http = reqwest::Client::new();
+
+        let err = post_delivery(&http, &delivery).await.unwrap_err();
+        assert!(err.contains("503"), "{}", err);
+        assert!(post_delivery(&http, &delivery).await.is_ok());
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        let request = requests[1].to_ascii_lowercase();
+        assert!(request.contains(&format!("{}: {}", SIGNATURE_HEADER.to_ascii_lowercase(), signature)));
+        assert!(request.contains(&format!("{}: {}", EVENT_HEADER.to_ascii_lowercase(), INVOICE_STATUS_EVENT)));
+        assert!(requests[1].ends_with(&payload));
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_delivery_retries_after_5xx_until_success() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = Arc::new(test_db.database());
+        let service = Arc::new(WebhookService::new(db.clone(), test_config(true)));
+        let (url, requests) = mock_receiver(vec![500, 502]).await;
+
+        let created = service.enqueue(None, url, INVOICE_STATUS_EVENT.to_string(), "{}".to_string(), None).await.unwrap();
+        let id = created.id.unwrap();
+        let delivery = wait_until_done(&WebhookDeliveryRepository::new(&db), id).await;
+        test_db.cleanup().await;
+
+        assert_eq!(delivery.status, common::domain::entity::WebhookDeliveryStatus::Succeeded);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_drain_leaves_waiting_retry_pending() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = Arc::new(test_db.database());
+        let config = WebhookConfig { base_backoff_secs: 3600, ..test_config(true) };
+        let service = Arc::new(WebhookService::new(db.clone(), config));
+        let (shutdown, signal) = watch::channel(false);
+        let drain = service.spawn_drain(signal);
+        let (url, requests) = mock_receiver(vec![500]).await;
+
+        let created = service.enqueue(None, url, INVOICE_STATUS_EVENT.to_string(), "{}".to_string(), None).await.unwrap();
+        let repo = WebhookDeliveryRepository::new(&db);
+        let id = created.id.unwrap();
+        for _ in 0..100 {
+            if repo.find_by_id(id).await.unwrap().unwrap().attempts > 0 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        shutdown.send_replace(true);
+        let drained = tokio::time::timeout(Duration::from_secs(5), drain).await;
+        let delivery = repo.find_by_id(id).await.unwrap().unwrap();
+        test_db.cleanup().await;
+
+        assert!(drained.is_ok(), "drain should not wait for the scheduled retry");
+        assert_eq!(delivery.status, common::domain::entity::WebhookDeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 1);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_delivery_to_private_address_is_blocked() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = Arc::new(test_db.database());
+        let service = Arc::new(WebhookService::new(db.clone(), test_config(false)));
+        let (url, requests) = mock_receiver(vec![]).await;
+
+        let created = service.enqueue(None, url, INVOICE_STATUS_EVENT.to_string(), "{}".to_string(), None).await.unwrap();
+        let delivery = wait_until_done(&WebhookDeliveryRepository::new(&db), created.id.unwrap()).await;
+        test_db.cleanup().await;
+
+        assert_eq!(delivery.status, common::domain::entity::WebhookDeliveryStatus::Failed);
+        assert!(delivery.last_error.unwrap().contains("non-public"));
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    /// 需要 MongoDB：MONGODB_TEST_URI 未设置时跳过
+    #[tokio::test]
+    async fn test_register_list_and_unregister() {
+        let Some(test_db) = TestDb::connect().await else { return };
+        let db = Arc::new(test_db.database());
+        let service = WebhookService::new(db.clone(), test_config(true));
+        let (enterprise, other) = (ObjectId::new(), ObjectId::new());
+
+        let (first, first_secret) = service.register(enterprise, "http://127.0.0.1:9/hook").await.unwrap();
+        // 重复登记同一地址更换密钥
+        let (again, again_secret) = service.register(enterprise, "http://127.0.0.1:9/hook").await.unwrap();
+        let stored: mongodb::bson::Document = db.collection("webhook_subscriptions").find_one(mongodb::bson::doc! { "_id": again.id.unwrap() }).await.unwrap().unwrap();
+        let listed = service.list_subscriptions(enterprise).await.unwrap();
+        let deleted_by_other = service.unregister(other, again.id.unwrap()).await.unwrap();
+        let deleted = service.unregister(enterprise, again.id.unwrap()).await.unwrap();
+        let remaining = service.list_subscriptions(enterprise).await.unwrap();
+        test_db.cleanup().await;
+
+        assert_eq!(first.id, again.id);
+        assert_ne!(first_secret, again_secret);
+        assert_eq!(again_secret, derive_secret("test-webhook-signing-key", &again.secret_salt));
+        // 数据库中不保存密钥
+        assert!(!stored.values().any(|v| v.as_str() == Some(again_secret.as_str())));
+        assert_eq!(listed.len(), 1);
+        assert!(!deleted_by_other);
+        assert!(deleted);
+        assert!(remaining.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_target_rejects_private_addresses() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://localhost:8080/hook",
+            "http://10.0.0.5/hook",
+            "http://192.168.1.1/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hook",
+            "http://[::ffff:127.0.0.1]/hook",
+        ] {
+            let err = resolve_target(url, false).await.unwrap_err();
+            assert!(err.contains("non-public"), "{}: {}", url, err);
+        }
+        assert!(resolve_target("ftp://8.8.8.8/hook", false).await.is_err());
+        assert!(resolve_target("not a url", false).await.is_err());
+        let target = resolve_target("https://8.8.8.8/hook", false).await.unwrap();
+        assert_eq!(target.addrs, vec!["8.8.8.8:443".parse::<SocketAddr>().unwrap()]);
+        // 联调模式允许本机地址
+        assert!(resolve_target("http://127.0.0.1:9/hook", true).await.is_ok());
+    }
+
+    #[test]
+    fn test_is_public_ip() {
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.0.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:10.0.0.1"] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_derive_secret_depends_on_key_and_salt() {
+        let secret = derive_secret("server-key", "salt-a");
+        assert_eq!(secret.len(), 64);
+        assert_eq!(secret, derive_secret("server-key", "salt-a"));
+        assert_ne!(secret, derive_secret("server-key", "salt-b"));
+        assert_ne!(secret, derive_secret("other-key", "salt-a"));
+    }
+
+    #[test]
+    fn test_backoff_delay_grows_and_caps() {
+        assert_eq!(backoff_delay(10, 1), Duration::from_secs(10));
+        assert_eq!(backoff_delay(10, 2), Duration::from_secs(20));
+        assert_eq!(backoff_delay(10, 4), Duration::from_secs(80));
+        assert_eq!(backoff_delay(10, 30), Duration::from_secs(MAX_BACKOFF_SECS));
+    }
+}
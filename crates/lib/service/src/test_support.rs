@@ -0,0 +1,169 @@
+//! 测试辅助：独立的 MongoDB 测试库、Redis 测试连接，以及锁、结算状态、幂等存储的内存实现
+//!
+//! 数据库测试通过 `MONGODB_TEST_URI` 连接 (事务相关的测试需要副本集，如 `mongodb://localhost:27017/?replicaSet=rs0`)，
+//! Redis 测试通过 `REDIS_TEST_URL` 连接；未设置时 [`TestDb::connect`] / [`redis_test_client`] 返回 `None`，测试直接返回。
+
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use mongodb::bson::oid::ObjectId;
+use mongodb::{Client, Database};
+
+use common::domain::entity::RepaymentPayout;
+
+use crate::cache::idempotency::{IdempotencyRecord, IdempotencyState, IdempotencyStore};
+use crate::error::ServiceError;
+use crate::invoice::settlement_executor::{SettlementLock, SettlementStore};
+
+/// MongoDB 测试连接串的环境变量
+pub const MONGODB_TEST_URI: &str = "MONGODB_TEST_URI";
+/// Redis 测试连接串的环境变量
+pub const REDIS_TEST_URL: &str = "REDIS_TEST_URL";
+
+/// 每个测试独占的数据库，名称随机，测试结束时调用 [`TestDb::cleanup`] 删除
+pub struct TestDb {
+    client: Client,
+    db: Database,
+}
+
+impl TestDb {
+    /// 未设置 `MONGODB_TEST_URI` 时返回 `None`
+    pub async fn connect() -> Option<Self> {
+        let uri = std::env::var(MONGODB_TEST_URI).ok()?;
+        let client = Client::with_uri_str(&uri).await.expect("invalid MONGODB_TEST_URI");
+        let db = client.database(&format!("rwa-test-{}", ObjectId::new().to_hex()));
+        Some(Self { client, db })
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    pub fn database(&self) -> Database {
+        self.db.clone()
+    }
+
+    /// 删除测试库。断言失败时测试库会遗留，可按 `rwa-test-` 前缀清理
+    pub async fn cleanup(self) {
+        self.db.drop().await.expect("failed to drop test database");
+    }
+}
+
+impl Deref for TestDb {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+/// 未设置 `REDIS_TEST_URL` 时返回 `None`
+pub fn redis_test_client() -> Option<redis::Client> {
+    let url = std::env::var(REDIS_TEST_URL).ok()?;
+    Some(redis::Client::open(url.as_str()).expect("invalid REDIS_TEST_URL"))
+}
+
+/// 不会被实际访问的 Redis 客户端 (按需建立连接)，用于构造只在部分路径使用 Redis 的服务
+pub fn unused_redis_client() -> redis::Client {
+    redis::Client::open("redis://127.0.0.1/").unwrap()
+}
+
+/// 内存互斥锁，克隆后共享同一份状态，可模拟多个实例共用一个 Redis
+#[derive(Clone, Default)]
+pub struct MemoryLock(Arc<Mutex<HashMap<String, String>>>);
+
+impl MemoryLock {
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().unwrap().is_empty()
+    }
+
+    fn acquire_with(&self, key: &str, token: &str) -> bool {
+        let mut locks = self.0.lock().unwrap();
+        if locks.contains_key(key) {
+            return false;
+        }
+        locks.insert(key.to_string(), token.to_string());
+        true
+    }
+
+    fn release_with(&self, key: &str, token: &str) {
+        let mut locks = self.0.lock().unwrap();
+        if locks.get(key).map(String::as_str) == Some(token) {
+            locks.remove(key);
+        }
+    }
+}
+
+#[async_trait]
+impl SettlementLock for MemoryLock {
+    async fn try_acquire(&self, key: &str, token: &str, _ttl_ms: u64) -> Result<bool, ServiceError> {
+        Ok(self.acquire_with(key, token))
+    }
+
+    async fn release(&self, key: &str, token: &str) -> Result<(), ServiceError> {
+        self.release_with(key, token);
+        Ok(())
+    }
+
+    async fn extend(&self, key: &str, token: &str, _ttl_ms: u64) -> Result<bool, ServiceError> {
+        Ok(self.0.lock().unwrap().get(key).map(String::as_str) == Some(token))
+    }
+}
+
+/// 内存结算状态：票据 ID -> 结算交易哈希，以及随结算写入的兑付明细
+#[derive(Clone, Default)]
+pub struct MemorySettlementStore {
+    settled: Arc<Mutex<HashMap<String, String>>>,
+    payouts: Arc<Mutex<Vec<RepaymentPayout>>>,
+}
+
+impl MemorySettlementStore {
+    pub fn payouts(&self) -> Vec<RepaymentPayout> {
+        self.payouts.lock().unwrap().clone()
+    }
+}
+
+#[async_trait]
+impl SettlementStore for MemorySettlementStore {
+    async fn settled_tx_hash(&self, invoice_id: &str) -> Result<Option<String>, ServiceError> {
+        Ok(self.settled.lock().unwrap().get(invoice_id).cloned())
+    }
+
+    async fn mark_settled(&self, invoice_id: &str, tx_hash: &str, _actor: &str, payouts: &[RepaymentPayout]) -> Result<(), ServiceError> {
+        self.settled.lock().unwrap().insert(invoice_id.to_string(), tx_hash.to_string());
+        self.payouts.lock().unwrap().extend_from_slice(payouts);
+        Ok(())
+    }
+}
+
+/// 内存幂等存储，忽略 TTL
+#[derive(Default)]
+pub struct MemoryIdempotencyStore {
+    entries: Mutex<HashMap<String, IdempotencyRecord>>,
+}
+
+impl IdempotencyStore for MemoryIdempotencyStore {
+    fn begin(&self, key: &str, fingerprint: &str, _ttl_secs: u64) -> Result<IdempotencyState, ServiceError> {
+        let mut entries = self.entries.lock().unwrap();
+        Ok(match entries.get(key) {
+            None => {
+                entries.insert(key.to_string(), IdempotencyRecord { fingerprint: fingerprint.to_string(), response: None });
+                IdempotencyState::Started
+            }
+            Some(record) => record.clone().into_state(),
+        })
+    }
+
+    fn complete(&self, key: &str, fingerprint: &str, response: &str, _ttl_secs: u64) -> Result<(), ServiceError> {
+        let record = IdempotencyRecord { fingerprint: fingerprint.to_string(), response: Some(response.to_string()) };
+        self.entries.lock().unwrap().insert(key.to_string(), record);
+        Ok(())
+    }
+
+    fn release(&self, key: &str) -> Result<(), ServiceError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}